@@ -0,0 +1,219 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{StatusCode, header::AUTHORIZATION},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::api_tokens::{self, TokenScope};
+
+/// 允许访问admin接口的地址：单个IP或CIDR网段
+#[derive(Debug, Clone, PartialEq)]
+pub struct IpRule {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpRule {
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (value, None),
+        };
+        let network: IpAddr = addr.parse().ok()?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix {
+            Some(prefix) => prefix.parse().ok().filter(|len| *len <= max_len)?,
+            None => max_len,
+        };
+        Some(Self {
+            network,
+            prefix_len,
+        })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // Compare IPv4-mapped IPv6 clients against IPv4 rules
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            IpAddr::V4(_) => ip,
+        };
+        match (self.network, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => prefix_matches(
+                u32::from(net) as u128,
+                u32::from(ip) as u128,
+                32,
+                self.prefix_len,
+            ),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_matches(u128::from(net), u128::from(ip), 128, self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(net: u128, ip: u128, bits: u8, prefix_len: u8) -> bool {
+    if prefix_len == 0 {
+        return true;
+    }
+    let shift = bits - prefix_len;
+    (net >> shift) == (ip >> shift)
+}
+
+/// admin接口访问控制配置
+#[derive(Debug, Clone, Default)]
+pub struct AdminConfig {
+    /// Bearer token，未配置时admin接口全部拒绝
+    pub token: Option<String>,
+    /// IP白名单，为空时不限制来源
+    pub allowlist: Vec<IpRule>,
+    /// /api 只读接口是否需要读取权限的token（API_REQUIRE_TOKEN）
+    pub read_token_required: bool,
+}
+
+impl AdminConfig {
+    pub fn from_env() -> Self {
+        let token = std::env::var("ADMIN_API_TOKEN")
+            .ok()
+            .filter(|token| !token.trim().is_empty());
+        let allowlist = std::env::var("ADMIN_IP_ALLOWLIST")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .filter_map(|entry| {
+                let rule = IpRule::parse(entry);
+                if rule.is_none() {
+                    warn!("Ignoring invalid ADMIN_IP_ALLOWLIST entry: {}", entry);
+                }
+                rule
+            })
+            .collect();
+        let read_token_required = std::env::var("API_REQUIRE_TOKEN")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .unwrap_or(false);
+        Self {
+            token,
+            allowlist,
+            read_token_required,
+        }
+    }
+
+    // The master ADMIN_API_TOKEN, or a stored API token with the scope
+    async fn token_allows(&self, provided: &str, scope: TokenScope) -> bool {
+        if self
+            .token
+            .as_deref()
+            .is_some_and(|expected| token_matches(expected, provided))
+        {
+            return true;
+        }
+        api_tokens::authorize(provided, scope).await
+    }
+
+    fn ip_allowed(&self, ip: Option<IpAddr>) -> bool {
+        if self.allowlist.is_empty() {
+            return true;
+        }
+        ip.is_some_and(|ip| self.allowlist.iter().any(|rule| rule.contains(ip)))
+    }
+}
+
+// Compare tokens without short-circuiting on the first differing byte
+pub fn token_matches(expected: &str, provided: &str) -> bool {
+    expected.len() == provided.len()
+        && expected
+            .bytes()
+            .zip(provided.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn bearer_token(req: &Request) -> Option<&str> {
+    req.headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+// Guard for the /admin scope: source IP must be allowlisted and the bearer token
+// must be ADMIN_API_TOKEN or an API token with the admin scope
+pub async fn require_admin(
+    State(config): State<Arc<AdminConfig>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let client_ip = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    if !config.ip_allowed(client_ip) {
+        warn!(
+            "Rejected admin request from {:?}: not allowlisted",
+            client_ip
+        );
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    if config.token.is_none() {
+        return (
+            StatusCode::FORBIDDEN,
+            "Admin API disabled: set ADMIN_API_TOKEN to enable it",
+        )
+            .into_response();
+    };
+    let authorized = match bearer_token(&req) {
+        Some(token) => config.token_allows(token, TokenScope::Admin).await,
+        None => false,
+    };
+    if !authorized {
+        warn!("Rejected admin request from {:?}: bad token", client_ip);
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    next.run(req).await
+}
+
+// Guard for the read-only /api routes, open unless API_REQUIRE_TOKEN is set
+pub async fn require_read(
+    State(config): State<Arc<AdminConfig>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if !config.read_token_required {
+        return next.run(req).await;
+    }
+    let authorized = match bearer_token(&req) {
+        Some(token) => config.token_allows(token, TokenScope::Read).await,
+        None => false,
+    };
+    if !authorized {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_rule() {
+        let rule = IpRule::parse("10.0.0.0/8").unwrap();
+        assert!(rule.contains("10.1.2.3".parse().unwrap()));
+        assert!(rule.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!rule.contains("11.0.0.1".parse().unwrap()));
+
+        let single = IpRule::parse("127.0.0.1").unwrap();
+        assert!(single.contains("127.0.0.1".parse().unwrap()));
+        assert!(!single.contains("127.0.0.2".parse().unwrap()));
+
+        assert!(IpRule::parse("10.0.0.0/33").is_none());
+        assert!(IpRule::parse("not-an-ip").is_none());
+    }
+}
@@ -0,0 +1,153 @@
+// Redis cache for aggregates computed over all mints (mint list, stats,
+// heatmaps). Entries are keyed by the current candle minute, so they are
+// invalidated when candles close, and shared by every web process: one request
+// per minute does the scan and the others reuse its result. While one process
+// computes an entry, the others wait for it instead of scanning too.
+
+use anyhow::Result;
+use redis::AsyncCommands;
+use serde::{Serialize, de::DeserializeOwned};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::redis_helper;
+
+// Set of the entry keys currently stored, so they can be dropped together
+const ENTRIES_KEY: &str = "agg:entries";
+// Entries outlive their minute a little so late readers still find them
+const ENTRY_TTL_SECS: u64 = 120;
+// A computing process that died releases its claim after this long
+const LOCK_TTL_SECS: u64 = 30;
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone)]
+pub struct AggregateCacheConfig {
+    pub enabled: bool,
+    // Longest a request waits for another process computing the same entry
+    pub wait: Duration,
+}
+
+impl AggregateCacheConfig {
+    pub fn from_env() -> Self {
+        Self {
+            enabled: std::env::var("AGGREGATE_CACHE_ENABLED")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
+            wait: Duration::from_millis(
+                std::env::var("AGGREGATE_CACHE_WAIT_MS")
+                    .unwrap_or_else(|_| "5000".to_string())
+                    .parse()
+                    .unwrap_or(5000),
+            ),
+        }
+    }
+}
+
+// Redis key of an aggregate for the candle minute containing `now`
+pub fn entry_key(name: &str, now: i64) -> String {
+    format!("agg:{}:{}", name, now - now.rem_euclid(60))
+}
+
+async fn read_entry<T: DeserializeOwned>(key: &str) -> Result<Option<T>> {
+    let mut con = redis_helper::get_connection().await?;
+    let data: Option<String> = con.get(key).await?;
+    Ok(data.and_then(|d| serde_json::from_str(&d).ok()))
+}
+
+// Claim the computation of an entry; false when another process holds it
+async fn claim(key: &str) -> Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    let claimed: Option<String> = redis::cmd("SET")
+        .arg(format!("{}:lock", key))
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(LOCK_TTL_SECS)
+        .query_async(&mut *con)
+        .await?;
+    Ok(claimed.is_some())
+}
+
+async fn store_entry<T: Serialize>(key: &str, value: &T) -> Result<()> {
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = redis::pipe()
+        .set_ex(key, serde_json::to_string(value)?, ENTRY_TTL_SECS)
+        .sadd(ENTRIES_KEY, key)
+        .expire(ENTRIES_KEY, ENTRY_TTL_SECS as i64)
+        .del(format!("{}:lock", key))
+        .query_async(&mut *con)
+        .await?;
+    Ok(())
+}
+
+// The aggregate of the current candle minute, computed at most once per minute
+// across processes. Redis failures fall back to computing locally.
+pub async fn get_or_compute<T, F>(name: &str, compute: F) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+    F: Future<Output = Result<T>>,
+{
+    let config = AggregateCacheConfig::from_env();
+    if !config.enabled {
+        return compute.await;
+    }
+
+    let key = entry_key(name, chrono::Utc::now().timestamp());
+    let started = Instant::now();
+    loop {
+        match read_entry(&key).await {
+            Ok(Some(value)) => return Ok(value),
+            Ok(None) => {}
+            Err(e) => {
+                warn!("Aggregate cache read failed for {}: {}", key, e);
+                return compute.await;
+            }
+        }
+        match claim(&key).await {
+            Ok(true) => break,
+            Ok(false) if started.elapsed() < config.wait => {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+            Ok(false) => {
+                debug!("Gave up waiting for {}, computing it here", key);
+                break;
+            }
+            Err(e) => {
+                warn!("Aggregate cache claim failed for {}: {}", key, e);
+                break;
+            }
+        }
+    }
+
+    let value = compute.await?;
+    if let Err(e) = store_entry(&key, &value).await {
+        warn!("Aggregate cache write failed for {}: {}", key, e);
+    }
+    Ok(value)
+}
+
+// Drop all cached aggregates, e.g. after candles were deleted
+pub async fn invalidate_all() -> Result<()> {
+    let mut con = redis_helper::get_connection().await?;
+    let mut keys: Vec<String> = con.smembers(ENTRIES_KEY).await?;
+    keys.push(ENTRIES_KEY.to_string());
+    let _: () = con.del(keys).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_key() {
+        assert_eq!(entry_key("mints", 1_700_000_000), "agg:mints:1699999980");
+        assert_eq!(entry_key("mints", 1_699_999_980), "agg:mints:1699999980");
+        assert_eq!(
+            entry_key("heatmap:900", 1_700_000_039),
+            "agg:heatmap:900:1699999980"
+        );
+    }
+}
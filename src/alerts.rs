@@ -0,0 +1,541 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use redis::streams::{StreamMaxlen, StreamRangeReply};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::annotations;
+pub use crate::api::{AlertPoll, FeedAlert};
+use crate::kline::{KLineData, KLineManager};
+use crate::redis_helper;
+use crate::strategy::StrategyAlert;
+use crate::supervisor;
+
+// Redis list holding recent alerts, newest first
+const RECENT_ALERTS_KEY: &str = "alerts:recent";
+
+// Redis hash of all-time alert counts by strategy name
+const ALERT_TOTALS_KEY: &str = "alerts:stats:total";
+
+// Redis ZSET of alert ids scored by timestamp, trimmed to the last 24 hours
+const ALERT_WINDOW_KEY: &str = "alerts:stats:window";
+
+// Redis hash of alert id -> post-alert return (%) for alerts still in the history
+const ALERT_OUTCOMES_KEY: &str = "alerts:outcomes";
+
+// Redis ZSET of measured AlertOutcome records scored by alert timestamp. Unlike
+// ALERT_OUTCOMES_KEY it outlives the alert history, for the leaderboard
+const OUTCOME_LOG_KEY: &str = "alerts:outcomes:log";
+
+// How often the strategy service measures outcomes of new alerts
+const OUTCOME_UPDATE_INTERVAL: Duration = Duration::from_secs(60);
+
+// Redis stream of alerts; entry ids are the cursors of the long-poll API
+const ALERT_FEED_KEY: &str = "stream:alerts";
+
+// How often a long-poll checks the feed for new alerts
+const ALERT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+const DAY_SECS: i64 = 86400;
+
+// How long after an alert its outcome is measured (15 minutes)
+const OUTCOME_HORIZON_SECS: i64 = 900;
+
+/// Alert summary for one strategy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyStats {
+    pub strategy_name: String,
+    pub total_alerts: u64,
+    pub alerts_24h: u64,
+    // Alerts in the history whose 15m outcome could be measured
+    pub evaluated_alerts: u64,
+    pub avg_return_15m: Option<Decimal>,
+    // Share of evaluated alerts whose price did not rise within 15m
+    pub false_positive_rate: Option<Decimal>,
+}
+
+// Measured 15m outcome of one alert, kept in the outcome log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertOutcome {
+    pub strategy_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<String>,
+    pub mint: String,
+    pub timestamp: i64,
+    pub return_pct: Decimal,
+}
+
+// Time range of the leaderboard, counted back from now by alert time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LeaderboardPeriod {
+    #[serde(rename = "24h")]
+    Day,
+    #[default]
+    #[serde(rename = "7d")]
+    Week,
+    #[serde(rename = "30d")]
+    Month,
+    // Everything still in the outcome log
+    #[serde(rename = "all")]
+    All,
+}
+
+impl LeaderboardPeriod {
+    pub fn secs(self) -> Option<i64> {
+        match self {
+            Self::Day => Some(DAY_SECS),
+            Self::Week => Some(7 * DAY_SECS),
+            Self::Month => Some(30 * DAY_SECS),
+            Self::All => None,
+        }
+    }
+}
+
+// Hit rate and forward return of one strategy and parameter set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub strategy_name: String,
+    pub params: Option<String>,
+    pub evaluated_alerts: u64,
+    // Share of alerts whose price rose within the horizon
+    pub hit_rate: Decimal,
+    pub avg_return: Decimal,
+    pub best_return: Decimal,
+    pub worst_return: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub period: LeaderboardPeriod,
+    // Earliest alert time included, None for the whole log
+    pub since: Option<i64>,
+    pub horizon_secs: i64,
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+// Maximum number of alerts kept in the history
+fn alert_history_limit() -> isize {
+    std::env::var("ALERT_HISTORY_LIMIT")
+        .unwrap_or_else(|_| "500".to_string())
+        .parse()
+        .unwrap_or(500)
+}
+
+// Days of measured outcomes kept for the leaderboard
+fn outcome_retention_secs() -> i64 {
+    std::env::var("ALERT_OUTCOME_RETENTION_DAYS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse::<i64>()
+        .unwrap_or(30)
+        .max(1)
+        * DAY_SECS
+}
+
+// Store an alert in the recent alerts history
+pub async fn record_alert(alert: &StrategyAlert) -> Result<()> {
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = con
+        .lpush(RECENT_ALERTS_KEY, serde_json::to_string(alert)?)
+        .await?;
+    let _: () = con
+        .ltrim(RECENT_ALERTS_KEY, 0, alert_history_limit() - 1)
+        .await?;
+
+    let _: () = con.hincr(ALERT_TOTALS_KEY, &alert.strategy_name, 1).await?;
+    let _: () = con
+        .zadd(ALERT_WINDOW_KEY, alert_id(alert), alert.timestamp)
+        .await?;
+    let _: () = con
+        .zrembyscore(ALERT_WINDOW_KEY, "-inf", alert.timestamp - DAY_SECS)
+        .await?;
+
+    let _: Option<String> = con
+        .xadd_maxlen(
+            ALERT_FEED_KEY,
+            StreamMaxlen::Approx(alert_history_limit() as usize),
+            "*",
+            &[("alert", serde_json::to_string(alert)?)],
+        )
+        .await?;
+
+    annotations::annotate_alert(&mut con, alert).await
+}
+
+// Check a cursor has the stream id form "<ms>" or "<ms>-<seq>"
+pub fn is_valid_cursor(id: &str) -> bool {
+    let mut parts = id.splitn(2, '-');
+    parts.next().is_some_and(|ms| ms.parse::<u64>().is_ok())
+        && parts.next().is_none_or(|seq| seq.parse::<u64>().is_ok())
+}
+
+// Read up to `limit` alerts after a cursor, oldest first. Without a cursor nothing is
+// returned and the cursor points at the latest alert, so only new alerts are read next.
+// A cursor older than the retained history resumes from the oldest retained alert.
+pub async fn read_alerts_since(since_id: Option<&str>, limit: usize) -> Result<AlertPoll> {
+    let mut con = redis_helper::get_connection().await?;
+    let Some(since_id) = since_id else {
+        let latest: StreamRangeReply = con.xrevrange_count(ALERT_FEED_KEY, "+", "-", 1).await?;
+        return Ok(AlertPoll {
+            alerts: Vec::new(),
+            next_id: latest
+                .ids
+                .into_iter()
+                .next()
+                .map(|entry| entry.id)
+                .unwrap_or_else(|| "0-0".to_string()),
+        });
+    };
+
+    let reply: StreamRangeReply = con
+        .xrange_count(ALERT_FEED_KEY, format!("({}", since_id), "+", limit.max(1))
+        .await?;
+    let mut next_id = since_id.to_string();
+    let mut alerts = Vec::new();
+    for entry in reply.ids {
+        next_id = entry.id.clone();
+        let data: Option<String> = entry.get("alert");
+        if let Some(alert) = data.and_then(|d| serde_json::from_str(&d).ok()) {
+            alerts.push(FeedAlert {
+                id: entry.id,
+                alert,
+            });
+        }
+    }
+    Ok(AlertPoll { alerts, next_id })
+}
+
+// Wait up to `timeout` for alerts after a cursor, returning as soon as any arrive
+pub async fn poll_alerts(
+    since_id: Option<&str>,
+    limit: usize,
+    timeout: Duration,
+) -> Result<AlertPoll> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut poll = read_alerts_since(since_id, limit).await?;
+    while poll.alerts.is_empty() && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(ALERT_POLL_INTERVAL.min(deadline - tokio::time::Instant::now())).await;
+        poll = read_alerts_since(Some(&poll.next_id), limit).await?;
+    }
+    Ok(poll)
+}
+
+// Identify an alert by time, strategy and mint
+fn alert_id(alert: &StrategyAlert) -> String {
+    format!("{}|{}|{}", alert.timestamp, alert.strategy_name, alert.mint)
+}
+
+// Percentage change from the close at alert time to the close `horizon` seconds later.
+// None if the mint has no candle at or after the horizon yet.
+pub fn post_alert_return(klines: &[KLineData], alert_ts: i64, horizon: i64) -> Option<Decimal> {
+    let close_at = |ts: i64| {
+        klines
+            .iter()
+            .filter(|k| k.timestamp <= ts)
+            .max_by_key(|k| k.timestamp)
+            .and_then(|k| k.close.parse::<Decimal>().ok())
+    };
+    let target = alert_ts + horizon;
+    if !klines
+        .iter()
+        .any(|k| k.timestamp >= KLineManager::get_minute_timestamp(target))
+    {
+        return None;
+    }
+
+    let entry = close_at(alert_ts).filter(|p| *p > Decimal::ZERO)?;
+    let exit = close_at(target)?;
+    Some((exit - entry) / entry * Decimal::from(100))
+}
+
+// Measure the 15m outcome of alerts in the history that are old enough and not yet measured,
+// and drop outcomes of alerts that fell out of the history
+async fn update_outcomes(
+    manager: &KLineManager,
+    alerts: &[StrategyAlert],
+) -> Result<HashMap<String, Decimal>> {
+    let mut outcomes: HashMap<String, Decimal> = {
+        let mut con = redis_helper::get_connection().await?;
+        let stored: HashMap<String, String> = con.hgetall(ALERT_OUTCOMES_KEY).await?;
+        stored
+            .into_iter()
+            .filter_map(|(id, ret)| Some((id, ret.parse().ok()?)))
+            .collect()
+    };
+
+    let ids: HashSet<String> = alerts.iter().map(alert_id).collect();
+    let stale: Vec<String> = outcomes
+        .keys()
+        .filter(|id| !ids.contains(*id))
+        .cloned()
+        .collect();
+
+    let now = chrono::Utc::now().timestamp();
+    let mut klines_by_mint: HashMap<String, Vec<KLineData>> = HashMap::new();
+    let mut measured = Vec::new();
+    let mut logged = Vec::new();
+    for alert in alerts {
+        let id = alert_id(alert);
+        if outcomes.contains_key(&id) || now - alert.timestamp < OUTCOME_HORIZON_SECS {
+            continue;
+        }
+        if !klines_by_mint.contains_key(&alert.mint) {
+            let klines = manager.get_klines_for_mint(&alert.mint, 60, None).await?;
+            klines_by_mint.insert(alert.mint.clone(), klines);
+        }
+        if let Some(ret) = post_alert_return(
+            &klines_by_mint[&alert.mint],
+            alert.timestamp,
+            OUTCOME_HORIZON_SECS,
+        ) {
+            let ret = ret.round_dp(4);
+            measured.push((id.clone(), ret.to_string()));
+            let outcome = AlertOutcome {
+                strategy_name: alert.strategy_name.clone(),
+                params: alert.params.clone(),
+                mint: alert.mint.clone(),
+                timestamp: alert.timestamp,
+                return_pct: ret,
+            };
+            logged.push((alert.timestamp, serde_json::to_string(&outcome)?));
+            outcomes.insert(id, ret);
+        }
+    }
+
+    let mut con = redis_helper::get_connection().await?;
+    if !stale.is_empty() {
+        let _: () = con.hdel(ALERT_OUTCOMES_KEY, &stale).await?;
+        for id in &stale {
+            outcomes.remove(id);
+        }
+    }
+    if !measured.is_empty() {
+        let _: () = con.hset_multiple(ALERT_OUTCOMES_KEY, &measured).await?;
+    }
+    if !logged.is_empty() {
+        let _: () = con.zadd_multiple(OUTCOME_LOG_KEY, &logged).await?;
+        let _: () = con
+            .zrembyscore(OUTCOME_LOG_KEY, "-inf", now - outcome_retention_secs())
+            .await?;
+    }
+    Ok(outcomes)
+}
+
+// Measure outcomes of new alerts every minute, so they reach the outcome log
+// even when they leave the history before anyone asks for stats
+pub fn spawn_outcome_task(manager: Arc<KLineManager>) -> tokio::task::JoinHandle<()> {
+    supervisor::spawn_supervised("alert_outcomes", async move {
+        let mut interval = tokio::time::interval(OUTCOME_UPDATE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let result = match get_recent_alerts(alert_history_limit() as usize).await {
+                Ok(history) => update_outcomes(&manager, &history).await.map(|_| ()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                warn!("Failed to measure alert outcomes: {}", e);
+            }
+        }
+    })
+}
+
+// Group outcomes by strategy and parameter set, keep groups with at least
+// `min_alerts` alerts and rank them by hit rate, then average return
+pub fn rank_outcomes(outcomes: &[AlertOutcome], min_alerts: u64) -> Vec<LeaderboardEntry> {
+    let mut groups: BTreeMap<(&str, Option<&str>), Vec<Decimal>> = BTreeMap::new();
+    for outcome in outcomes {
+        groups
+            .entry((&outcome.strategy_name, outcome.params.as_deref()))
+            .or_default()
+            .push(outcome.return_pct);
+    }
+
+    let mut entries: Vec<LeaderboardEntry> = groups
+        .into_iter()
+        .filter(|(_, rets)| rets.len() as u64 >= min_alerts.max(1))
+        .map(|((name, params), rets)| {
+            let count = Decimal::from(rets.len());
+            let winners = rets.iter().filter(|r| **r > Decimal::ZERO).count();
+            LeaderboardEntry {
+                strategy_name: name.to_string(),
+                params: params.map(str::to_string),
+                evaluated_alerts: rets.len() as u64,
+                hit_rate: (Decimal::from(winners) / count).round_dp(4),
+                avg_return: (rets.iter().sum::<Decimal>() / count).round_dp(2),
+                best_return: rets.iter().copied().max().unwrap_or_default(),
+                worst_return: rets.iter().copied().min().unwrap_or_default(),
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        b.hit_rate
+            .cmp(&a.hit_rate)
+            .then(b.avg_return.cmp(&a.avg_return))
+            .then(b.evaluated_alerts.cmp(&a.evaluated_alerts))
+    });
+    entries
+}
+
+// Rank strategies and parameter sets by the outcomes of their alerts in a period
+pub async fn get_leaderboard(
+    manager: &KLineManager,
+    period: LeaderboardPeriod,
+    min_alerts: u64,
+) -> Result<Leaderboard> {
+    let history = get_recent_alerts(alert_history_limit() as usize).await?;
+    update_outcomes(manager, &history).await?;
+
+    let since = period
+        .secs()
+        .map(|secs| chrono::Utc::now().timestamp() - secs);
+    let data: Vec<String> = {
+        let mut con = redis_helper::get_connection().await?;
+        match since {
+            Some(since) => con.zrangebyscore(OUTCOME_LOG_KEY, since, "+inf").await?,
+            None => con.zrange(OUTCOME_LOG_KEY, 0, -1).await?,
+        }
+    };
+    let outcomes: Vec<AlertOutcome> = data
+        .iter()
+        .filter_map(|d| serde_json::from_str(d).ok())
+        .collect();
+    Ok(Leaderboard {
+        period,
+        since,
+        horizon_secs: OUTCOME_HORIZON_SECS,
+        entries: rank_outcomes(&outcomes, min_alerts),
+    })
+}
+
+// Summarize alerts per strategy: totals, last 24h count and 15m outcomes of alerts in the history
+pub async fn get_strategy_stats(manager: &KLineManager) -> Result<Vec<StrategyStats>> {
+    let (totals, window) = {
+        let mut con = redis_helper::get_connection().await?;
+        let totals: HashMap<String, u64> = con.hgetall(ALERT_TOTALS_KEY).await?;
+        let since = chrono::Utc::now().timestamp() - DAY_SECS;
+        let window: Vec<String> = con.zrangebyscore(ALERT_WINDOW_KEY, since, "+inf").await?;
+        (totals, window)
+    };
+
+    let history = get_recent_alerts(alert_history_limit() as usize).await?;
+    let outcomes = update_outcomes(manager, &history).await?;
+
+    let mut stats: HashMap<String, StrategyStats> = totals
+        .into_iter()
+        .map(|(name, total)| {
+            let entry = StrategyStats {
+                strategy_name: name.clone(),
+                total_alerts: total,
+                alerts_24h: 0,
+                evaluated_alerts: 0,
+                avg_return_15m: None,
+                false_positive_rate: None,
+            };
+            (name, entry)
+        })
+        .collect();
+
+    for id in window {
+        if let Some(entry) = id.split('|').nth(1).and_then(|name| stats.get_mut(name)) {
+            entry.alerts_24h += 1;
+        }
+    }
+
+    let mut returns: HashMap<&str, Vec<Decimal>> = HashMap::new();
+    for alert in &history {
+        if let Some(ret) = outcomes.get(&alert_id(alert)) {
+            returns.entry(&alert.strategy_name).or_default().push(*ret);
+        }
+    }
+    for (name, rets) in returns {
+        let Some(entry) = stats.get_mut(name) else {
+            continue;
+        };
+        let count = Decimal::from(rets.len());
+        let losers = rets.iter().filter(|r| **r <= Decimal::ZERO).count();
+        entry.evaluated_alerts = rets.len() as u64;
+        entry.avg_return_15m = Some((rets.iter().sum::<Decimal>() / count).round_dp(2));
+        entry.false_positive_rate = Some((Decimal::from(losers) / count).round_dp(4));
+    }
+
+    let mut stats: Vec<StrategyStats> = stats.into_values().collect();
+    stats.sort_by_key(|s| std::cmp::Reverse(s.total_alerts));
+    Ok(stats)
+}
+
+// Get the most recent alerts, newest first
+pub async fn get_recent_alerts(limit: usize) -> Result<Vec<StrategyAlert>> {
+    let mut con = redis_helper::get_connection().await?;
+    let data: Vec<String> = con.lrange(RECENT_ALERTS_KEY, 0, limit as isize - 1).await?;
+    Ok(data
+        .iter()
+        .filter_map(|d| serde_json::from_str(d).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::candle as kline;
+
+    #[test]
+    fn test_post_alert_return() {
+        let base = KLineManager::get_minute_timestamp(1_700_000_000);
+        let klines = vec![
+            kline(base, "2"),
+            kline(base + 600, "3"),
+            kline(base + 960, "5"),
+        ];
+        // No candle in the horizon minute: the last close before it is used
+        assert_eq!(
+            post_alert_return(&klines, base + 10, 900),
+            Some(Decimal::from(50))
+        );
+        // Horizon not reached by any candle yet
+        assert_eq!(post_alert_return(&klines, base + 120, 900), None);
+    }
+
+    #[test]
+    fn test_rank_outcomes() {
+        let outcome = |name: &str, params: Option<&str>, ret: i64| AlertOutcome {
+            strategy_name: name.to_string(),
+            params: params.map(str::to_string),
+            mint: "M".to_string(),
+            timestamp: 0,
+            return_pct: Decimal::from(ret),
+        };
+        let outcomes = vec![
+            outcome("连续递增上涨模式", Some("count=4"), 10),
+            outcome("连续递增上涨模式", Some("count=4"), -5),
+            outcome("连续递增上涨模式", Some("count=5"), 3),
+            outcome("连续递增上涨模式", Some("count=5"), 1),
+            outcome("自定义规则", None, 50),
+        ];
+
+        let entries = rank_outcomes(&outcomes, 2);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].params.as_deref(), Some("count=5"));
+        assert_eq!(entries[0].hit_rate, Decimal::ONE);
+        assert_eq!(entries[0].avg_return, Decimal::TWO);
+        assert_eq!(entries[1].hit_rate, Decimal::new(5, 1));
+        assert_eq!(entries[1].best_return, Decimal::from(10));
+        assert_eq!(entries[1].worst_return, Decimal::from(-5));
+
+        assert_eq!(rank_outcomes(&outcomes, 1)[0].strategy_name, "自定义规则");
+        assert_eq!(
+            serde_json::from_str::<LeaderboardPeriod>("\"30d\"").unwrap(),
+            LeaderboardPeriod::Month
+        );
+    }
+
+    #[test]
+    fn test_is_valid_cursor() {
+        assert!(is_valid_cursor("0"));
+        assert!(is_valid_cursor("1700000000000-3"));
+        assert!(!is_valid_cursor("1700000000000-"));
+        assert!(!is_valid_cursor("latest"));
+    }
+}
@@ -0,0 +1,119 @@
+use anyhow::Result;
+use redis::{AsyncCommands, aio::ConnectionManager};
+use std::collections::HashMap;
+
+pub use crate::api::Annotation;
+use crate::kline::KLineManager;
+use crate::redis_helper;
+use crate::strategy::StrategyAlert;
+
+// Redis hash of a mint's annotations, field "{candle_ts}|{strategy}" -> JSON
+pub fn get_annotations_key(mint: &str) -> String {
+    format!("annotations:{}", mint)
+}
+
+// The candle an alert refers to: the last candle it evaluated, or the minute it fired in
+fn alert_candle_timestamp(alert: &StrategyAlert) -> i64 {
+    alert
+        .klines
+        .iter()
+        .map(|k| k.timestamp)
+        .max()
+        .unwrap_or_else(|| KLineManager::get_minute_timestamp(alert.timestamp))
+}
+
+// Record an annotation for an alert; a strategy firing again on the same candle replaces it
+pub async fn annotate_alert(con: &mut ConnectionManager, alert: &StrategyAlert) -> Result<()> {
+    let annotation = Annotation {
+        timestamp: alert_candle_timestamp(alert),
+        alert_timestamp: alert.timestamp,
+        strategy_name: alert.strategy_name.clone(),
+        message: alert.message.clone(),
+        invalidated: false,
+    };
+    let field = format!("{}|{}", annotation.timestamp, annotation.strategy_name);
+    let _: () = con
+        .hset(
+            get_annotations_key(&alert.mint),
+            field,
+            serde_json::to_string(&annotation)?,
+        )
+        .await?;
+    Ok(())
+}
+
+// Mark an annotation as invalidated, keeping it on the chart
+pub async fn invalidate_annotation(mint: &str, annotation: &Annotation) -> Result<()> {
+    let annotation = Annotation {
+        invalidated: true,
+        ..annotation.clone()
+    };
+    let field = format!("{}|{}", annotation.timestamp, annotation.strategy_name);
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = con
+        .hset(
+            get_annotations_key(mint),
+            field,
+            serde_json::to_string(&annotation)?,
+        )
+        .await?;
+    Ok(())
+}
+
+// Get a mint's annotations on candles within [from, to], oldest first
+pub async fn get_annotations(mint: &str, from: i64, to: i64) -> Result<Vec<Annotation>> {
+    let mut con = redis_helper::get_connection().await?;
+    let data: HashMap<String, String> = con.hgetall(get_annotations_key(mint)).await?;
+
+    let mut annotations: Vec<Annotation> = data
+        .values()
+        .filter_map(|d| serde_json::from_str::<Annotation>(d).ok())
+        .filter(|a| a.timestamp >= from && a.timestamp <= to)
+        .collect();
+    annotations.sort_by_key(|a| (a.timestamp, a.alert_timestamp));
+    Ok(annotations)
+}
+
+// Hash fields of annotations on candles within [from, to]
+fn fields_in_range(fields: Vec<String>, from: i64, to: i64) -> Vec<String> {
+    fields
+        .into_iter()
+        .filter(|field| {
+            field
+                .split_once('|')
+                .and_then(|(timestamp, _)| timestamp.parse::<i64>().ok())
+                .is_some_and(|timestamp| timestamp >= from && timestamp <= to)
+        })
+        .collect()
+}
+
+// Delete a mint's annotations on candles within [from, to], returning how many were removed
+pub async fn delete_annotations(mint: &str, from: i64, to: i64) -> Result<usize> {
+    let key = get_annotations_key(mint);
+    let mut con = redis_helper::get_connection().await?;
+    let fields: Vec<String> = con.hkeys(&key).await?;
+    let fields = fields_in_range(fields, from, to);
+    if fields.is_empty() {
+        return Ok(0);
+    }
+    Ok(con.hdel(&key, fields).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields_in_range() {
+        let fields = vec![
+            "1700000000|连续上涨".to_string(),
+            "1700000060|连续上涨".to_string(),
+            "1700000120|放量".to_string(),
+            "invalid".to_string(),
+        ];
+        assert_eq!(
+            fields_in_range(fields, 1_700_000_060, 1_700_000_120),
+            vec!["1700000060|连续上涨", "1700000120|放量"]
+        );
+    }
+}
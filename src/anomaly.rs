@@ -0,0 +1,262 @@
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use tracing::{info, warn};
+
+use crate::localized;
+use crate::notification::NotificationManager;
+use crate::notification_format::{Language, NotificationFormat};
+use crate::remote_config;
+use crate::strategy::StrategyAlert;
+use crate::trades::TradeRecord;
+
+// Statistics of mints without a trade for this long are dropped when the map is full
+const STALE_MINT_SECS: i64 = 3600;
+
+#[derive(Debug, Clone)]
+pub struct AnomalyConfig {
+    // Trades the moving statistics span; larger adapts slower
+    pub span: u64,
+    // Trades seen before a mint is scored
+    pub min_samples: u64,
+    // Score that raises an alert, 0 disables alerts
+    pub alert_score: f64,
+    // Seconds between alerts for the same mint
+    pub alert_cooldown_secs: i64,
+    // Mints kept in memory
+    pub max_mints: usize,
+}
+
+impl AnomalyConfig {
+    pub fn from_env() -> Self {
+        Self {
+            span: std::env::var("ANOMALY_SPAN")
+                .unwrap_or_else(|_| "50".to_string())
+                .parse()
+                .unwrap_or(50),
+            min_samples: std::env::var("ANOMALY_MIN_SAMPLES")
+                .unwrap_or_else(|_| "20".to_string())
+                .parse()
+                .unwrap_or(20),
+            alert_score: remote_config::var("ANOMALY_ALERT_SCORE")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0.0),
+            alert_cooldown_secs: remote_config::var("ANOMALY_ALERT_COOLDOWN_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+            max_mints: std::env::var("ANOMALY_MAX_MINTS")
+                .unwrap_or_else(|_| "10000".to_string())
+                .parse()
+                .unwrap_or(10000),
+        }
+    }
+
+    // Weight of the newest sample in the moving statistics
+    fn alpha(&self) -> f64 {
+        2.0 / (self.span.max(1) as f64 + 1.0)
+    }
+}
+
+// Exponentially weighted mean and variance, updated one sample at a time
+#[derive(Debug, Clone, Default)]
+struct Ewma {
+    count: u64,
+    mean: f64,
+    variance: f64,
+}
+
+impl Ewma {
+    // Z-score of a sample against the statistics so far
+    fn zscore(&self, value: f64) -> Option<f64> {
+        let std_dev = self.variance.sqrt();
+        (std_dev > f64::EPSILON).then(|| (value - self.mean) / std_dev)
+    }
+
+    fn update(&mut self, value: f64, alpha: f64) {
+        if self.count == 0 {
+            self.mean = value;
+        } else {
+            let diff = value - self.mean;
+            let increment = alpha * diff;
+            self.mean += increment;
+            self.variance = (1.0 - alpha) * (self.variance + diff * increment);
+        }
+        self.count += 1;
+    }
+}
+
+// Moving statistics of one mint's trades
+#[derive(Debug, Clone, Default)]
+pub struct MintStats {
+    last_price: Option<f64>,
+    returns: Ewma,
+    volumes: Ewma,
+    last_trade: i64,
+    last_alert: i64,
+}
+
+// Anomaly of a trade: z-scores of its price return and of its size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnomalyScore {
+    pub return_z: f64,
+    pub volume_z: f64,
+}
+
+impl AnomalyScore {
+    // Largest deviation; only unusually large trades count, small ones are normal
+    pub fn score(&self) -> f64 {
+        self.return_z.abs().max(self.volume_z).max(0.0)
+    }
+}
+
+impl MintStats {
+    // Score a trade against the statistics so far, then fold it in. None until
+    // `min_samples` trades were seen.
+    pub fn observe(
+        &mut self,
+        price: f64,
+        sol_amount: f64,
+        timestamp: i64,
+        config: &AnomalyConfig,
+    ) -> Option<AnomalyScore> {
+        let alpha = config.alpha();
+        let warmed_up = self.volumes.count >= config.min_samples;
+
+        // Log return against the previous trade and log trade size
+        let log_return = self
+            .last_price
+            .filter(|last| *last > 0.0 && price > 0.0)
+            .map(|last| (price / last).ln());
+        let log_volume = sol_amount.max(0.0).ln_1p();
+
+        let return_z = log_return.and_then(|r| self.returns.zscore(r));
+        let volume_z = self.volumes.zscore(log_volume);
+
+        if let Some(log_return) = log_return {
+            self.returns.update(log_return, alpha);
+        }
+        self.volumes.update(log_volume, alpha);
+        self.last_price = Some(price);
+        self.last_trade = timestamp;
+
+        if !warmed_up || (return_z.is_none() && volume_z.is_none()) {
+            return None;
+        }
+        Some(AnomalyScore {
+            return_z: return_z.unwrap_or(0.0),
+            volume_z: volume_z.unwrap_or(0.0),
+        })
+    }
+}
+
+static STATS: LazyLock<Mutex<HashMap<String, MintStats>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Score a trade of the monitored feed and alert when the score spikes. The
+// statistics live in memory, so each monitor process scores the trades it sees.
+pub fn score_trade(trade: &TradeRecord) -> Option<f64> {
+    let config = AnomalyConfig::from_env();
+    let price = trade.price.to_f64()?;
+    let sol_amount = trade.sol_amount.to_f64()?;
+
+    let (anomaly, alert) = {
+        let mut stats = STATS.lock().unwrap_or_else(|e| e.into_inner());
+        if stats.len() >= config.max_mints && !stats.contains_key(&trade.mint) {
+            let cutoff = trade.timestamp - STALE_MINT_SECS;
+            stats.retain(|_, s| s.last_trade >= cutoff);
+        }
+        let mint_stats = stats.entry(trade.mint.clone()).or_default();
+        let anomaly = mint_stats.observe(price, sol_amount, trade.timestamp, &config)?;
+        let alert = config.alert_score > 0.0
+            && anomaly.score() >= config.alert_score
+            && trade.timestamp - mint_stats.last_alert >= config.alert_cooldown_secs;
+        if alert {
+            mint_stats.last_alert = trade.timestamp;
+        }
+        (anomaly, alert)
+    };
+
+    if alert {
+        info!(
+            "📈 Anomalous trade on {}: score {:.2} (return z {:.2}, volume z {:.2})",
+            trade.mint,
+            anomaly.score(),
+            anomaly.return_z,
+            anomaly.volume_z
+        );
+        let trade = trade.clone();
+        tokio::spawn(async move { alert_anomaly(&trade, anomaly).await });
+    }
+    Some((anomaly.score() * 100.0).round() / 100.0)
+}
+
+async fn alert_anomaly(trade: &TradeRecord, anomaly: AnomalyScore) {
+    let format = NotificationFormat::current().await;
+    let side = match (format.language, trade.is_buy) {
+        (Language::Zh, true) => "买入",
+        (Language::Zh, false) => "卖出",
+        (Language::En, true) => "buy",
+        (Language::En, false) => "sell",
+    };
+    let alert = StrategyAlert {
+        mint: trade.mint.clone(),
+        strategy_name: "交易异常".to_string(),
+        message: localized!(
+            format.language,
+            "异常分 {:.2}: 价格收益 z={:.2}, 成交量 z={:.2}, 价格 {}, 成交 {} ({})",
+            "Anomaly score {:.2}: return z={:.2}, volume z={:.2}, price {}, traded {} ({})",
+            anomaly.score(),
+            anomaly.return_z,
+            anomaly.volume_z,
+            format.price(trade.price),
+            format.amount(trade.sol_amount),
+            side
+        ),
+        timestamp: chrono::Local::now().timestamp(),
+        severity: None,
+        params: None,
+        klines: Vec::new(),
+    };
+    let sent = match NotificationManager::new() {
+        Ok(manager) => manager.send_notification(&alert).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = sent {
+        warn!("Failed to notify trade anomaly: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anomaly_score() {
+        let config = AnomalyConfig {
+            span: 20,
+            min_samples: 10,
+            alert_score: 0.0,
+            alert_cooldown_secs: 300,
+            max_mints: 10,
+        };
+        let mut stats = MintStats::default();
+        // Steady trades alternating slightly in price and size
+        for i in 0..30 {
+            let wobble = if i % 2 == 0 { 1.0 } else { -1.0 };
+            let score = stats.observe(1.0 + 0.01 * wobble, 1.0 + 0.1 * wobble, i, &config);
+            assert_eq!(score.is_some(), i >= 10);
+            assert!(score.is_none_or(|s| s.score() < 3.0));
+        }
+
+        // A large buy that jumps the price stands out on both measures
+        let spike = stats.observe(1.5, 50.0, 30, &config).unwrap();
+        assert!(spike.return_z > 10.0);
+        assert!(spike.volume_z > 10.0);
+
+        // Small trades never score on size
+        let small = stats.observe(1.5, 0.001, 31, &config).unwrap();
+        assert!(small.volume_z < 0.0);
+    }
+}
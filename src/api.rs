@@ -0,0 +1,170 @@
+// Types exchanged over the web API. They are shared by the server modules,
+// which re-export them where they used to live, and by the `client` feature,
+// so a client-only build gets them without the server dependencies.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+// Program a trade was executed on. Bonding curve trades happen before graduation,
+// AMM trades after, so the two are separate liquidity regimes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeSource {
+    // pump.fun bonding curve
+    Pump,
+    // PumpSwap AMM pool
+    Amm,
+    // Raydium pool (not monitored yet)
+    Raydium,
+}
+
+impl TradeSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TradeSource::Pump => "pump",
+            TradeSource::Amm => "amm",
+            TradeSource::Raydium => "raydium",
+        }
+    }
+}
+
+impl std::str::FromStr for TradeSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "pump" => Ok(TradeSource::Pump),
+            "amm" => Ok(TradeSource::Amm),
+            "raydium" => Ok(TradeSource::Raydium),
+            _ => Err(anyhow::anyhow!("Invalid trade source: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KLineData {
+    pub timestamp: i64,       // Timestamp representing the start time of this K-line
+    pub open: String,         // Opening price (stored as String for Decimal)
+    pub high: String,         // Highest price
+    pub low: String,          // Lowest price
+    pub close: String,        // Closing price
+    pub volume_sol: String,   // Trading volume (SOL)
+    pub volume_token: String, // Trading volume (Token)
+    pub net_flow_sol: String, // Net flow (buy - sell) in SOL
+    pub last_update: u64,     // Last update timestamp (seconds)
+    // Program the candle's trades came from, None for candles stored before source tagging
+    #[serde(default)]
+    pub source: Option<TradeSource>,
+    // Latest pool liquidity in SOL (both sides), only for candles of AMM trades
+    #[serde(default)]
+    pub liquidity_sol: Option<String>,
+    // Volume relative to pool liquidity (volume_sol / liquidity_sol)
+    #[serde(default)]
+    pub turnover: Option<String>,
+    // Highest anomaly score (z-score of price return or trade size) among the candle's trades
+    #[serde(default)]
+    pub anomaly_score: Option<f64>,
+    // Time (seconds) of the trade that set the close, None for candles stored before it was tracked
+    #[serde(default)]
+    pub close_time: Option<i64>,
+}
+
+// Run of missing candles between two stored ones
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CandleGap {
+    // Start of the first missing candle
+    pub from: i64,
+    // Start of the last missing candle
+    pub to: i64,
+    pub candles: i64,
+    // Whether placeholder candles were inserted for it
+    pub filled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyAlert {
+    pub mint: String,
+    pub strategy_name: String,
+    pub message: String,
+    pub timestamp: i64,
+    /// 严重度评分（0-100），见 `severity` 模块；未评分的告警（如系统告警）为None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<u8>,
+    /// 触发时的策略参数组合，如 "count=4 min_gain=0.001 lookback=5"，用于按参数组合统计命中率；
+    /// 没有可调参数的告警为None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<String>,
+    pub klines: Vec<KLineData>,
+}
+
+/// 告警在K线图上的标记
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    /// 触发告警的K线时间戳（分钟开始时间）
+    pub timestamp: i64,
+    pub alert_timestamp: i64,
+    pub strategy_name: String,
+    pub message: String,
+    /// K线被延迟交易修正后告警不再成立
+    #[serde(default)]
+    pub invalidated: bool,
+}
+
+/// 单个mint的K线更新，每笔交易写入K线后发布
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CandleUpdate {
+    pub mint: String,
+    pub kline: KLineData,
+    /// 该交易开启了新的K线，说明上一根K线已收盘
+    pub opened: bool,
+}
+
+/// An alert in the feed with its cursor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedAlert {
+    pub id: String,
+    #[serde(flatten)]
+    pub alert: StrategyAlert,
+}
+
+/// Alerts after a cursor, with the cursor to resume from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertPoll {
+    pub alerts: Vec<FeedAlert>,
+    pub next_id: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ApiResponse<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MintInfo {
+    pub mint: String,
+    pub last_activity: u64,
+    pub kline_count: usize,
+    pub complete: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct KlineQuery {
+    pub limit: Option<usize>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    // Candle interval: 1m (default) or one of KLINE_ROLLUP_INTERVALS
+    pub interval: Option<String>,
+    // none (default), markers or fill
+    pub gaps: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KLinesResponse {
+    pub klines: Vec<KLineData>,
+    pub annotations: Vec<Annotation>,
+    // Missing candles, only listed when requested with `gaps=markers` or `gaps=fill`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gaps: Vec<CandleGap>,
+}
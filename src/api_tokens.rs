@@ -0,0 +1,186 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::redis_helper;
+
+// Redis hash of token id -> ApiToken
+const TOKENS_KEY: &str = "api_tokens";
+// Redis hash of SHA-256 of the secret -> token id; secrets themselves are never stored
+const TOKEN_HASHES_KEY: &str = "api_tokens:hashes";
+// Prefix of generated secrets, so leaked tokens are easy to recognize
+const TOKEN_PREFIX: &str = "pkm_";
+
+/// API token权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenScope {
+    /// 读取 /api 只读接口（开启 API_REQUIRE_TOKEN 时需要）
+    Read,
+    /// 访问admin接口，包含读取权限
+    Admin,
+    /// 推送数据，如Helius webhook
+    Ingest,
+}
+
+impl std::str::FromStr for TokenScope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "read" => Ok(TokenScope::Read),
+            "admin" => Ok(TokenScope::Admin),
+            "ingest" => Ok(TokenScope::Ingest),
+            _ => Err(anyhow::anyhow!("Invalid token scope: {}", s)),
+        }
+    }
+}
+
+/// 可单独撤销的API token，只保存元数据和哈希
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    pub created_at: i64,
+}
+
+impl ApiToken {
+    /// 是否拥有该权限，admin权限包含读取权限
+    pub fn allows(&self, scope: TokenScope) -> bool {
+        self.scopes.contains(&scope)
+            || (scope == TokenScope::Read && self.scopes.contains(&TokenScope::Admin))
+    }
+}
+
+/// 新建的token，secret只在创建时返回一次
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedToken {
+    #[serde(flatten)]
+    pub token: ApiToken,
+    pub secret: String,
+}
+
+fn hash_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+/// 创建token，名称不能为空且至少有一个权限
+pub async fn create_token(name: &str, scopes: &[TokenScope]) -> Result<CreatedToken> {
+    let name = name.trim();
+    if name.is_empty() {
+        anyhow::bail!("Token name must not be empty");
+    }
+    if scopes.is_empty() {
+        anyhow::bail!("Token needs at least one scope");
+    }
+    let mut unique = Vec::new();
+    for scope in scopes {
+        if !unique.contains(scope) {
+            unique.push(*scope);
+        }
+    }
+
+    let secret = format!(
+        "{}{}",
+        TOKEN_PREFIX,
+        bs58::encode(rand::random::<[u8; 24]>()).into_string()
+    );
+    let token = ApiToken {
+        id: format!("{:x}", rand::random::<u64>()),
+        name: name.to_string(),
+        scopes: unique,
+        created_at: chrono::Utc::now().timestamp(),
+    };
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = redis::pipe()
+        .atomic()
+        .hset(TOKENS_KEY, &token.id, serde_json::to_string(&token)?)
+        .hset(TOKEN_HASHES_KEY, hash_secret(&secret), &token.id)
+        .query_async(&mut *con)
+        .await?;
+    Ok(CreatedToken { token, secret })
+}
+
+/// 所有token，最新创建的在前
+pub async fn list_tokens() -> Result<Vec<ApiToken>> {
+    let mut con = redis_helper::get_connection().await?;
+    let data: HashMap<String, String> = con.hgetall(TOKENS_KEY).await?;
+    let mut tokens: Vec<ApiToken> = data
+        .values()
+        .filter_map(|d| serde_json::from_str(d).ok())
+        .collect();
+    tokens.sort_by_key(|token| std::cmp::Reverse(token.created_at));
+    Ok(tokens)
+}
+
+/// 撤销token，返回是否存在
+pub async fn revoke_token(id: &str) -> Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    let removed: usize = con.hdel(TOKENS_KEY, id).await?;
+    // The hash entry is keyed by the secret's hash, find it by its id
+    let hashes: HashMap<String, String> = con.hgetall(TOKEN_HASHES_KEY).await?;
+    let stale: Vec<&String> = hashes
+        .iter()
+        .filter(|(_, token_id)| *token_id == id)
+        .map(|(hash, _)| hash)
+        .collect();
+    if !stale.is_empty() {
+        let _: () = con.hdel(TOKEN_HASHES_KEY, stale).await?;
+    }
+    Ok(removed > 0)
+}
+
+/// 按secret查找token，不存在或已撤销时返回None
+pub async fn authenticate(secret: &str) -> Result<Option<ApiToken>> {
+    if !secret.starts_with(TOKEN_PREFIX) {
+        return Ok(None);
+    }
+    let mut con = redis_helper::get_connection().await?;
+    let id: Option<String> = con.hget(TOKEN_HASHES_KEY, hash_secret(secret)).await?;
+    let Some(id) = id else {
+        return Ok(None);
+    };
+    let data: Option<String> = con.hget(TOKENS_KEY, &id).await?;
+    Ok(data.and_then(|d| serde_json::from_str(&d).ok()))
+}
+
+/// secret是否属于拥有该权限的token，查询失败时视为无权限
+pub async fn authorize(secret: &str, scope: TokenScope) -> bool {
+    match authenticate(secret).await {
+        Ok(token) => token.is_some_and(|token| token.allows(scope)),
+        Err(e) => {
+            warn!("API token lookup failed: {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_scopes() {
+        let token = ApiToken {
+            id: "1".to_string(),
+            name: "dashboard".to_string(),
+            scopes: vec!["admin".parse().unwrap()],
+            created_at: 0,
+        };
+        assert!(token.allows(TokenScope::Admin));
+        assert!(token.allows(TokenScope::Read));
+        assert!(!token.allows(TokenScope::Ingest));
+
+        let bot = ApiToken {
+            scopes: vec![TokenScope::Read],
+            ..token
+        };
+        assert!(!bot.allows(TokenScope::Admin));
+        assert!("owner".parse::<TokenScope>().is_err());
+        assert_eq!(hash_secret("pkm_x").len(), 64);
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::Result;
+use rand::seq::IndexedRandom;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::alerts;
+use crate::kline::{KLineData, KLineManager};
+use crate::strategy::ConsecutiveRisingPattern;
+
+// Result of replaying one parameter combination over stored candles
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestResult {
+    pub consecutive_count: usize,
+    pub min_gain_threshold: Decimal,
+    pub alerts: usize,
+    // Alerts whose forward return could be measured
+    pub evaluated: usize,
+    pub avg_return: Option<Decimal>,
+    // Share of evaluated alerts with a positive forward return
+    pub hit_rate: Option<Decimal>,
+}
+
+// Forward returns (%) of every alert the pattern would have raised on a mint's candles.
+// Each candle is treated as the forming candle once, like a live check at the start of
+// its minute, and the return is measured from the close of the previous candle.
+pub fn replay_pattern(
+    pattern: &ConsecutiveRisingPattern,
+    klines: &[KLineData],
+    horizon_secs: i64,
+) -> (usize, Vec<Decimal>) {
+    let window = pattern.consecutive_count + 1;
+    let mut triggered = 0;
+    let mut returns = Vec::new();
+    for end in window..=klines.len() {
+        let candles = &klines[end - window..end];
+        if pattern.detect(candles).is_none() {
+            continue;
+        }
+        triggered += 1;
+        let alert_ts = candles[candles.len() - 1].timestamp - 1;
+        if let Some(ret) = alerts::post_alert_return(klines, alert_ts, horizon_secs) {
+            returns.push(ret);
+        }
+    }
+    (triggered, returns)
+}
+
+// Replay a pattern over several mints and summarize its alerts
+pub fn backtest_pattern(
+    pattern: &ConsecutiveRisingPattern,
+    klines_by_mint: &[Vec<KLineData>],
+    horizon_secs: i64,
+) -> BacktestResult {
+    let mut alerts = 0;
+    let mut returns = Vec::new();
+    for klines in klines_by_mint {
+        let (mint_alerts, mint_returns) = replay_pattern(pattern, klines, horizon_secs);
+        alerts += mint_alerts;
+        returns.extend(mint_returns);
+    }
+
+    let count = Decimal::from(returns.len());
+    let (avg_return, hit_rate) = if returns.is_empty() {
+        (None, None)
+    } else {
+        let winners = returns.iter().filter(|r| **r > Decimal::ZERO).count();
+        (
+            Some((returns.iter().sum::<Decimal>() / count).round_dp(2)),
+            Some((Decimal::from(winners) / count).round_dp(4)),
+        )
+    };
+    BacktestResult {
+        consecutive_count: pattern.consecutive_count,
+        min_gain_threshold: pattern.min_gain_threshold,
+        alerts,
+        evaluated: returns.len(),
+        avg_return,
+        hit_rate,
+    }
+}
+
+// Load the full candle history of one mint, or of a random sample of active mints,
+// at the candle interval of the rising strategy
+pub async fn load_klines(
+    manager: &KLineManager,
+    mint: Option<&str>,
+    sample_size: usize,
+) -> Result<Vec<Vec<KLineData>>> {
+    let mints: Vec<String> = match mint {
+        Some(mint) => vec![mint.to_string()],
+        None => manager
+            .get_active_mints()
+            .await?
+            .choose_multiple(&mut rand::rng(), sample_size)
+            .map(|(mint, _, _)| mint.clone())
+            .collect(),
+    };
+
+    let interval_secs = ConsecutiveRisingPattern::from_env().interval_secs;
+    let mut klines_by_mint = Vec::new();
+    for mint in mints {
+        let mut klines = manager
+            .get_klines_for_mint(&mint, interval_secs, None)
+            .await?;
+        klines.sort_by_key(|k| k.timestamp);
+        klines_by_mint.push(klines);
+    }
+    Ok(klines_by_mint)
+}
+
+// Backtest the consecutive rising pattern for every combination of the given
+// candle counts and minimum gains (%), keeping the other settings from the environment
+pub fn sweep(
+    klines_by_mint: &[Vec<KLineData>],
+    counts: &[usize],
+    min_gains: &[Decimal],
+    horizon_secs: i64,
+) -> Vec<BacktestResult> {
+    let base = ConsecutiveRisingPattern::from_env();
+    let mut results = Vec::new();
+    for &consecutive_count in counts {
+        for &min_gain_threshold in min_gains {
+            let pattern = ConsecutiveRisingPattern {
+                consecutive_count,
+                min_gain_threshold,
+                ..base.clone()
+            };
+            results.push(backtest_pattern(&pattern, klines_by_mint, horizon_secs));
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::candle;
+
+    fn kline(timestamp: i64, open: &str, close: &str) -> KLineData {
+        KLineData {
+            open: open.to_string(),
+            low: open.to_string(),
+            ..candle(timestamp, close)
+        }
+    }
+
+    #[test]
+    fn test_replay_pattern() {
+        let base = KLineManager::get_minute_timestamp(1_700_000_000);
+        // Three 10% candles, then a flat candle and a later candle at +15m
+        let klines = vec![
+            kline(base, "100", "110"),
+            kline(base + 60, "110", "121"),
+            kline(base + 120, "121", "133.1"),
+            kline(base + 180, "133.1", "133.1"),
+            kline(base + 1020, "133.1", "146.41"),
+        ];
+        let pattern = ConsecutiveRisingPattern {
+            consecutive_count: 3,
+            ..Default::default()
+        };
+        let result = backtest_pattern(&pattern, std::slice::from_ref(&klines), 900);
+        assert_eq!(result.alerts, 1);
+        assert_eq!(result.evaluated, 1);
+        assert_eq!(result.avg_return, Some(Decimal::from(10)));
+
+        let strict = ConsecutiveRisingPattern {
+            consecutive_count: 3,
+            min_gain_threshold: Decimal::from(15),
+            ..Default::default()
+        };
+        assert_eq!(backtest_pattern(&strict, &[klines], 900).alerts, 0);
+    }
+}
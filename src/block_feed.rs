@@ -0,0 +1,281 @@
+use base64::{Engine, engine::general_purpose};
+use serde_json::{Value, json};
+
+use crate::constant::{
+    ANCHOR_EVENT_IX_TAG, BUY_IX_DISCRIMINATOR, CREATE_IX_DISCRIMINATOR, SELL_IX_DISCRIMINATOR,
+};
+
+// How the monitors receive transactions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IngestionMode {
+    // logsSubscribe: one notification per transaction, logs may be truncated
+    Logs,
+    // blockSubscribe: full blocks, events are also read from the program's
+    // self-CPI instructions, which are never truncated
+    Block,
+}
+
+impl IngestionMode {
+    // INGESTION_MODE=logs|block, INGESTION_MODE_<MONITOR> overrides it per monitor
+    pub fn for_monitor(monitor_name: &str) -> Self {
+        let value = std::env::var(format!("INGESTION_MODE_{}", monitor_name.to_uppercase()))
+            .or_else(|_| std::env::var("INGESTION_MODE"))
+            .unwrap_or_default();
+        match value.trim().to_lowercase().as_str() {
+            "block" => IngestionMode::Block,
+            _ => IngestionMode::Logs,
+        }
+    }
+
+    // Subscription request of the mode for one monitored program. Both
+    // subscriptions filter on a single account, so a monitor of several
+    // programs sends one request per program, each with its own id.
+    pub fn subscribe_request(&self, program: &str, id: u64) -> Value {
+        match self {
+            IngestionMode::Logs => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "logsSubscribe",
+                "params": [
+                    { "mentions": [program] },
+                    { "commitment": "confirmed" }
+                ]
+            }),
+            IngestionMode::Block => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "blockSubscribe",
+                "params": [
+                    { "mentionsAccountOrProgram": program },
+                    {
+                        "commitment": "confirmed",
+                        "encoding": "json",
+                        "transactionDetails": "full",
+                        "showRewards": false,
+                        "maxSupportedTransactionVersion": 0
+                    }
+                ]
+            }),
+        }
+    }
+
+    pub fn notification_method(&self) -> &'static str {
+        match self {
+            IngestionMode::Logs => "logsNotification",
+            IngestionMode::Block => "blockNotification",
+        }
+    }
+}
+
+// Account keys of a transaction in the order instructions index them: static
+// keys, then writable and readonly addresses loaded from lookup tables
+fn account_keys(transaction: &Value) -> Vec<String> {
+    let strings = |value: Option<&Value>| -> Vec<String> {
+        value
+            .and_then(Value::as_array)
+            .map(|keys| {
+                keys.iter()
+                    .filter_map(|k| k.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let mut keys = strings(transaction.pointer("/transaction/message/accountKeys"));
+    keys.extend(strings(
+        transaction.pointer("/meta/loadedAddresses/writable"),
+    ));
+    keys.extend(strings(
+        transaction.pointer("/meta/loadedAddresses/readonly"),
+    ));
+    keys
+}
+
+// Log lines equivalent to what the program would have logged for one of its
+// instructions: the invoke line, the Buy/Sell/Create instruction name, and
+// "Program data" for self-CPI events
+pub fn instruction_logs(program: &str, data: &[u8], logs: &mut Vec<String>) {
+    if let Some(event) = data.strip_prefix(&ANCHOR_EVENT_IX_TAG[..]) {
+        logs.push(format!(
+            "Program data: {}",
+            general_purpose::STANDARD.encode(event)
+        ));
+        return;
+    }
+    logs.push(format!("Program {} invoke [1]", program));
+    let name = match data.get(..8) {
+        Some(d) if d == BUY_IX_DISCRIMINATOR => "Buy",
+        Some(d) if d == SELL_IX_DISCRIMINATOR => "Sell",
+        Some(d) if d == CREATE_IX_DISCRIMINATOR => "Create",
+        _ => return,
+    };
+    logs.push(format!("Program log: Instruction: {}", name));
+}
+
+// Rebuild a transaction's program logs from its instructions. None when the
+// program emitted no self-CPI events, e.g. for programs that only log them.
+pub fn reconstruct_logs(transaction: &Value, program: &str) -> Option<Vec<String>> {
+    let keys = account_keys(transaction);
+    let program_of = |instruction: &Value| {
+        let index = instruction.get("programIdIndex")?.as_u64()? as usize;
+        keys.get(index).map(String::as_str)
+    };
+    let data_of = |instruction: &Value| {
+        bs58::decode(instruction.get("data")?.as_str()?)
+            .into_vec()
+            .ok()
+    };
+
+    let inner = transaction
+        .pointer("/meta/innerInstructions")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let outer = transaction
+        .pointer("/transaction/message/instructions")
+        .and_then(Value::as_array)?;
+
+    let mut logs = Vec::new();
+    for (index, instruction) in outer.iter().enumerate() {
+        let nested = inner
+            .iter()
+            .filter(|group| group.get("index").and_then(Value::as_u64) == Some(index as u64))
+            .filter_map(|group| group.get("instructions")?.as_array())
+            .flatten();
+        for instruction in std::iter::once(instruction).chain(nested) {
+            if program_of(instruction) != Some(program) {
+                continue;
+            }
+            if let Some(data) = data_of(instruction) {
+                instruction_logs(program, &data, &mut logs);
+            }
+        }
+    }
+    logs.iter()
+        .any(|log| log.starts_with("Program data: "))
+        .then_some(logs)
+}
+
+// Split a blockNotification into one logsNotification-shaped message per
+// successful transaction, so the log-based parsers handle both modes. Events
+// come from the program's self-CPI instructions when it emits them, since logs
+// can be truncated, and from the transaction logs otherwise.
+pub fn transactions_from_block(notification: &Value, programs: &[&str]) -> Vec<Value> {
+    let Some(value) = notification.pointer("/params/result/value") else {
+        return Vec::new();
+    };
+    let slot = value
+        .get("slot")
+        .and_then(Value::as_u64)
+        .unwrap_or_default();
+    let Some(transactions) = value
+        .pointer("/block/transactions")
+        .and_then(Value::as_array)
+    else {
+        return Vec::new();
+    };
+
+    transactions
+        .iter()
+        .filter_map(|tx| transaction_message(tx, programs, slot))
+        .collect()
+}
+
+// logsNotification-shaped message of one successful transaction in the
+// getTransaction JSON layout, None for failed transactions. The rebuilt logs of
+// several programs are joined, so a transaction reads the same whichever of
+// their subscriptions delivered it.
+pub fn transaction_message(transaction: &Value, programs: &[&str], slot: u64) -> Option<Value> {
+    if !transaction.pointer("/meta/err").is_none_or(Value::is_null) {
+        return None;
+    }
+    let signature = transaction.pointer("/transaction/signatures/0")?.as_str()?;
+    let rebuilt: Vec<String> = programs
+        .iter()
+        .filter_map(|program| reconstruct_logs(transaction, program))
+        .flatten()
+        .collect();
+    let logs = if rebuilt.is_empty() {
+        serde_json::from_value(transaction.pointer("/meta/logMessages")?.clone()).ok()?
+    } else {
+        rebuilt
+    };
+    Some(logs_notification(signature, slot, logs))
+}
+
+// Message in the shape of a logsNotification, as the log-based parsers expect
+pub fn logs_notification(signature: &str, slot: u64, logs: Vec<String>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "logsNotification",
+        "params": {
+            "result": {
+                "context": { "slot": slot },
+                "value": { "signature": signature, "err": null, "logs": logs }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transactions_from_block() {
+        let program = "Prog1111111111111111111111111111111111111111";
+        let buy = bs58::encode(BUY_IX_DISCRIMINATOR).into_string();
+        let mut event = ANCHOR_EVENT_IX_TAG.to_vec();
+        event.extend_from_slice(&[1, 2, 3]);
+        let event = bs58::encode(event).into_string();
+
+        let notification = json!({
+            "method": "blockNotification",
+            "params": { "result": { "value": { "slot": 42, "block": { "transactions": [
+                {
+                    "transaction": {
+                        "signatures": ["sig1"],
+                        "message": {
+                            "accountKeys": ["Payer", program],
+                            "instructions": [{ "programIdIndex": 1, "data": buy }]
+                        }
+                    },
+                    "meta": {
+                        "err": null,
+                        "logMessages": ["Log truncated"],
+                        "loadedAddresses": { "writable": [], "readonly": [] },
+                        "innerInstructions": [
+                            { "index": 0, "instructions": [{ "programIdIndex": 1, "data": event }] }
+                        ]
+                    }
+                },
+                {
+                    "transaction": { "signatures": ["sig2"], "message": { "accountKeys": [], "instructions": [] } },
+                    "meta": { "err": { "InstructionError": [0, "Custom"] }, "logMessages": [] }
+                },
+                {
+                    "transaction": { "signatures": ["sig3"], "message": { "accountKeys": ["Payer"], "instructions": [] } },
+                    "meta": { "err": null, "logMessages": ["Program data: AQID"] }
+                }
+            ]}}}}
+        });
+
+        let messages = transactions_from_block(&notification, &[program]);
+        assert_eq!(messages.len(), 2);
+        let value = &messages[0]["params"]["result"]["value"];
+        assert_eq!(value["signature"], "sig1");
+        assert_eq!(messages[0]["params"]["result"]["context"]["slot"], 42);
+        assert_eq!(
+            value["logs"],
+            json!([
+                format!("Program {} invoke [1]", program),
+                "Program log: Instruction: Buy",
+                "Program data: AQID"
+            ])
+        );
+        // Without self-CPI events the transaction logs are used as they are
+        assert_eq!(
+            messages[1]["params"]["result"]["value"]["logs"],
+            json!(["Program data: AQID"])
+        );
+    }
+}
@@ -0,0 +1,116 @@
+// A Redis set, or the fields of a Redis hash, copied into memory for the trade
+// path and reloaded once it is older than its refresh interval. Readers take the
+// current snapshot under a short read lock. The first reader finding it stale
+// reloads it while the others keep using the previous copy, so trades never
+// queue behind a Redis round trip and no lock is held across an await.
+
+use anyhow::Result;
+use redis::AsyncCommands;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::redis_helper;
+
+// Which part of the Redis key is cached
+#[derive(Debug, Clone, Copy)]
+pub enum SetSource {
+    // Members of a set (SMEMBERS)
+    Members,
+    // Field names of a hash (HKEYS)
+    HashFields,
+}
+
+#[derive(Debug, Default)]
+struct Snapshot {
+    // None before the first load and after an invalidation
+    loaded_at: Option<Instant>,
+    members: Option<Arc<HashSet<String>>>,
+}
+
+pub struct CachedRedisSet {
+    key: &'static str,
+    source: SetSource,
+    refresh: fn() -> Duration,
+    snapshot: RwLock<Snapshot>,
+    refreshing: AtomicBool,
+}
+
+// Clears the refreshing flag even when the reload is cancelled
+struct RefreshGuard<'a>(&'a AtomicBool);
+
+impl Drop for RefreshGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+impl CachedRedisSet {
+    pub const fn new(key: &'static str, source: SetSource, refresh: fn() -> Duration) -> Self {
+        Self {
+            key,
+            source,
+            refresh,
+            snapshot: RwLock::new(Snapshot {
+                loaded_at: None,
+                members: None,
+            }),
+            refreshing: AtomicBool::new(false),
+        }
+    }
+
+    // The cached members, reloaded first when stale. A failed reload keeps the
+    // previous copy; before the first load completes the set reads as empty.
+    pub async fn snapshot(&self) -> Arc<HashSet<String>> {
+        let (fresh, members) = {
+            let snapshot = self.snapshot.read().unwrap_or_else(|e| e.into_inner());
+            let fresh = snapshot
+                .loaded_at
+                .is_some_and(|loaded_at| loaded_at.elapsed() < (self.refresh)());
+            (fresh, snapshot.members.clone())
+        };
+        if fresh || self.refreshing.swap(true, Ordering::AcqRel) {
+            return members.unwrap_or_default();
+        }
+
+        let _guard = RefreshGuard(&self.refreshing);
+        match self.load().await {
+            Ok(loaded) => {
+                let loaded = Arc::new(loaded);
+                *self.snapshot.write().unwrap_or_else(|e| e.into_inner()) = Snapshot {
+                    loaded_at: Some(Instant::now()),
+                    members: Some(loaded.clone()),
+                };
+                loaded
+            }
+            Err(e) => {
+                warn!("Failed to refresh cached {}: {}", self.key, e);
+                members.unwrap_or_default()
+            }
+        }
+    }
+
+    // Whether the cached set contains `member`
+    pub async fn contains(&self, member: &str) -> bool {
+        self.snapshot().await.contains(member)
+    }
+
+    // Reload on the next read, e.g. after the set was changed. The previous copy
+    // is still served while the reload runs.
+    pub fn invalidate(&self) {
+        self.snapshot
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .loaded_at = None;
+    }
+
+    async fn load(&self) -> Result<HashSet<String>> {
+        let mut con = redis_helper::clone_connection().await?;
+        Ok(match self.source {
+            SetSource::Members => con.smembers(self.key).await?,
+            SetSource::HashFields => con.hkeys(self.key).await?,
+        })
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use crate::kline::KLineData;
+
+/// 策略预热配置
+#[derive(Debug, Clone)]
+pub struct WarmupConfig {
+    /// 启动时预加载最近活跃mint的K线，并在内存中缓存，之后只增量读取新K线
+    pub enabled: bool,
+    /// 预加载和缓存的mint数量上限，超出时淘汰最久没有新K线的mint
+    pub max_mints: usize,
+}
+
+impl WarmupConfig {
+    pub fn from_env() -> Self {
+        Self {
+            enabled: std::env::var("STRATEGY_WARMUP")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            max_mints: std::env::var("STRATEGY_WARMUP_MAX_MINTS")
+                .unwrap_or_else(|_| "2000".to_string())
+                .parse()
+                .unwrap_or(2000),
+        }
+    }
+}
+
+// Candles of one mint, oldest first, with the lookback they were loaded for.
+// `synced` is set while pushed updates have kept them current since the last
+// load from Redis
+#[derive(Debug, Clone)]
+struct CachedCandles {
+    lookback: usize,
+    candles: Vec<KLineData>,
+    synced: bool,
+}
+
+/// 策略使用的K线内存缓存，每个mint保留最近的回溯数量的K线
+#[derive(Debug, Clone, Default)]
+pub struct CandleCache {
+    max_mints: usize,
+    mints: HashMap<String, CachedCandles>,
+}
+
+impl CandleCache {
+    pub fn new(max_mints: usize) -> Self {
+        Self {
+            max_mints: max_mints.max(1),
+            mints: HashMap::new(),
+        }
+    }
+
+    /// 缓存的mint数量上限
+    pub fn capacity(&self) -> usize {
+        self.max_mints
+    }
+
+    pub fn len(&self) -> usize {
+        self.mints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mints.is_empty()
+    }
+
+    /// 缓存中最新K线的时间戳。缓存的回溯数量不足时返回None，需要重新完整加载
+    pub fn last_timestamp(&self, mint: &str, lookback: usize) -> Option<i64> {
+        let cached = self.mints.get(mint)?;
+        if cached.lookback < lookback {
+            return None;
+        }
+        cached.candles.last().map(|k| k.timestamp)
+    }
+
+    /// 完整加载的K线替换缓存
+    pub fn insert(&mut self, mint: &str, candles: Vec<KLineData>, lookback: usize) {
+        self.mints.insert(
+            mint.to_string(),
+            CachedCandles {
+                lookback,
+                candles,
+                synced: true,
+            },
+        );
+        self.evict();
+    }
+
+    /// 合并增量读取的K线（从缓存中最新K线开始），返回最近 `lookback` 根K线，
+    /// mint不在缓存中时返回None。最新K线在收盘前可能还在更新，因此同一时间戳的K线以新读取的为准
+    pub fn merge(
+        &mut self,
+        mint: &str,
+        newer: Vec<KLineData>,
+        lookback: usize,
+    ) -> Option<Vec<KLineData>> {
+        let cached = self.mints.get_mut(mint)?;
+        cached.synced = true;
+        if let Some(first) = newer.first() {
+            cached.candles.retain(|k| k.timestamp < first.timestamp);
+            cached.candles.extend(newer);
+        }
+        let excess = cached.candles.len().saturating_sub(cached.lookback);
+        cached.candles.drain(..excess);
+
+        let skip = cached.candles.len().saturating_sub(lookback);
+        Some(cached.candles[skip..].to_vec())
+    }
+
+    /// K线被修正或删除后丢弃缓存，下次检测时重新完整加载
+    pub fn invalidate(&mut self, mint: &str) {
+        self.mints.remove(mint);
+    }
+
+    /// 写入推送的单根K线：替换同一时间戳的K线或按时间插入，mint不在缓存中时忽略
+    pub fn apply(&mut self, mint: &str, candle: KLineData) {
+        let Some(cached) = self.mints.get_mut(mint) else {
+            return;
+        };
+        match cached
+            .candles
+            .binary_search_by_key(&candle.timestamp, |k| k.timestamp)
+        {
+            Ok(i) => cached.candles[i] = candle,
+            // Older than every cached candle, outside the lookback
+            Err(0) if cached.candles.len() >= cached.lookback => {}
+            Err(i) => cached.candles.insert(i, candle),
+        }
+        let excess = cached.candles.len().saturating_sub(cached.lookback);
+        cached.candles.drain(..excess);
+    }
+
+    /// 推送的更新保持最新时直接返回最近 `lookback` 根K线，否则返回None，需要从Redis同步
+    pub fn synced(&self, mint: &str, lookback: usize) -> Option<Vec<KLineData>> {
+        let cached = self.mints.get(mint)?;
+        if !cached.synced || cached.lookback < lookback {
+            return None;
+        }
+        let skip = cached.candles.len().saturating_sub(lookback);
+        Some(cached.candles[skip..].to_vec())
+    }
+
+    /// 推送的更新有丢失时调用，所有mint在下次检测时先从Redis增量同步
+    pub fn mark_unsynced(&mut self) {
+        for cached in self.mints.values_mut() {
+            cached.synced = false;
+        }
+    }
+
+    // Drop the mints whose latest candle is the oldest until within max_mints
+    fn evict(&mut self) {
+        while self.mints.len() > self.max_mints {
+            let Some(stalest) = self
+                .mints
+                .iter()
+                .min_by_key(|(_, cached)| cached.candles.last().map(|k| k.timestamp))
+                .map(|(mint, _)| mint.clone())
+            else {
+                break;
+            };
+            self.mints.remove(&stalest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::candle;
+
+    #[test]
+    fn test_candle_cache_merge() {
+        let mut cache = CandleCache::new(2);
+        cache.insert(
+            "a",
+            vec![candle(0, "1"), candle(60, "2"), candle(120, "3")],
+            3,
+        );
+        assert_eq!(cache.last_timestamp("a", 3), Some(120));
+        assert_eq!(cache.last_timestamp("a", 5), None);
+
+        // The open candle was updated and a new one started
+        let candles = cache
+            .merge("a", vec![candle(120, "4"), candle(180, "5")], 2)
+            .unwrap();
+        let closes: Vec<&str> = candles.iter().map(|k| k.close.as_str()).collect();
+        assert_eq!(closes, ["4", "5"]);
+        assert_eq!(cache.last_timestamp("a", 3), Some(180));
+
+        cache.insert("b", vec![candle(240, "1")], 3);
+        cache.insert("c", vec![candle(300, "1")], 3);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.last_timestamp("a", 3), None);
+
+        cache.invalidate("b");
+        assert!(cache.merge("b", vec![candle(300, "1")], 3).is_none());
+    }
+
+    #[test]
+    fn test_candle_cache_apply() {
+        let mut cache = CandleCache::new(2);
+        cache.insert("a", vec![candle(0, "1"), candle(60, "2")], 3);
+
+        // Updates of the open candle replace it, a new candle is appended
+        cache.apply("a", candle(60, "3"));
+        cache.apply("a", candle(120, "4"));
+        cache.apply("a", candle(180, "5"));
+        let candles = cache.synced("a", 3).unwrap();
+        let closes: Vec<&str> = candles.iter().map(|k| k.close.as_str()).collect();
+        assert_eq!(closes, ["3", "4", "5"]);
+
+        // Too old for the lookback, and unknown mints are ignored
+        cache.apply("a", candle(0, "9"));
+        assert_eq!(cache.synced("a", 3).unwrap()[0].close, "3");
+        cache.apply("b", candle(0, "1"));
+        assert!(cache.synced("b", 1).is_none());
+
+        // Missed updates need a sync from Redis first
+        cache.mark_unsynced();
+        assert!(cache.synced("a", 3).is_none());
+        cache.merge("a", vec![candle(180, "6")], 3);
+        assert_eq!(cache.synced("a", 3).unwrap()[2].close, "6");
+    }
+}
@@ -0,0 +1,180 @@
+use anyhow::Result;
+use futures_util::StreamExt;
+use redis::{AsyncCommands, aio::ConnectionManager};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+pub use crate::api::CandleUpdate;
+use crate::supervisor;
+
+// Redis pub/sub channel carrying candle updates from the monitors to other processes
+pub const CANDLE_UPDATES_CHANNEL: &str = "candle_updates";
+
+// Buffered updates per channel before slow subscribers start lagging
+fn channel_capacity() -> usize {
+    std::env::var("CANDLE_HUB_CAPACITY")
+        .unwrap_or_else(|_| "256".to_string())
+        .parse()
+        .unwrap_or(256)
+}
+
+// In-process fan-out of candle updates: one broadcast channel per subscribed mint
+// plus one for all mints. Consumers subscribe here instead of polling Redis.
+pub struct CandleHub {
+    capacity: usize,
+    mints: Mutex<HashMap<String, broadcast::Sender<CandleUpdate>>>,
+    all: broadcast::Sender<CandleUpdate>,
+}
+
+impl CandleHub {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            mints: Mutex::new(HashMap::new()),
+            all: broadcast::channel(capacity).0,
+        }
+    }
+
+    // Subscribe to the updates of one mint. Channels whose subscribers have all
+    // left are dropped here too, so mints that stop trading don't keep one
+    pub fn subscribe(&self, mint: &str) -> broadcast::Receiver<CandleUpdate> {
+        let mut mints = self.mints.lock().unwrap_or_else(|e| e.into_inner());
+        mints.retain(|_, sender| sender.receiver_count() > 0);
+        mints
+            .entry(mint.to_string())
+            .or_insert_with(|| broadcast::channel(self.capacity).0)
+            .subscribe()
+    }
+
+    // Subscribe to the updates of every mint
+    pub fn subscribe_all(&self) -> broadcast::Receiver<CandleUpdate> {
+        self.all.subscribe()
+    }
+
+    // Fan an update out to its mint's subscribers and the all-mints subscribers
+    pub fn publish(&self, update: CandleUpdate) {
+        {
+            let mut mints = self.mints.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(sender) = mints.get(&update.mint) {
+                // Drop the channel once its last subscriber has gone
+                if sender.send(update.clone()).is_err() {
+                    mints.remove(&update.mint);
+                }
+            }
+        }
+        let _ = self.all.send(update);
+    }
+
+    // Number of mints with an open channel
+    pub fn mint_channels(&self) -> usize {
+        self.mints.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+}
+
+static HUB: LazyLock<CandleHub> = LazyLock::new(|| CandleHub::new(channel_capacity()));
+
+// The process-wide candle hub
+pub fn hub() -> &'static CandleHub {
+    &HUB
+}
+
+// Whether the monitors mirror candle updates to Redis pub/sub for other processes
+fn redis_bridge_enabled() -> bool {
+    std::env::var("CANDLE_HUB_REDIS_BRIDGE")
+        .unwrap_or_else(|_| "true".to_string())
+        .parse()
+        .unwrap_or(true)
+}
+
+// Publish a candle update to the local hub and, when enabled, to Redis pub/sub
+pub async fn publish(con: &mut ConnectionManager, update: CandleUpdate) -> Result<()> {
+    if redis_bridge_enabled() {
+        let _: () = con
+            .publish(CANDLE_UPDATES_CHANNEL, serde_json::to_string(&update)?)
+            .await?;
+    }
+    hub().publish(update);
+    Ok(())
+}
+
+// Feed candle updates published by the monitors into this process's hub.
+// Used by processes that don't write candles themselves, such as the web service.
+pub fn spawn_redis_bridge() -> JoinHandle<()> {
+    supervisor::spawn_supervised("candle_hub_bridge", async {
+        loop {
+            if let Err(e) = run_redis_bridge().await {
+                warn!("Candle hub bridge disconnected: {}", e);
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+        }
+    })
+}
+
+async fn run_redis_bridge() -> Result<()> {
+    let redis_url =
+        std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379/".to_string());
+    let mut pubsub = redis::Client::open(redis_url)?.get_async_pubsub().await?;
+    pubsub.subscribe(CANDLE_UPDATES_CHANNEL).await?;
+    info!(
+        "📡 Candle hub bridged to Redis channel {}",
+        CANDLE_UPDATES_CHANNEL
+    );
+
+    let mut messages = pubsub.on_message();
+    while let Some(message) = messages.next().await {
+        let payload: String = message.get_payload()?;
+        match serde_json::from_str::<CandleUpdate>(&payload) {
+            Ok(update) => hub().publish(update),
+            Err(e) => debug!("Ignoring malformed candle update: {}", e),
+        }
+    }
+    Err(anyhow::anyhow!("Redis pub/sub stream ended"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kline::KLineData;
+    use crate::test_harness;
+
+    fn update(mint: &str) -> CandleUpdate {
+        CandleUpdate {
+            mint: mint.to_string(),
+            kline: KLineData {
+                net_flow_sol: "1".to_string(),
+                last_update: 1_700_000_000,
+                ..test_harness::candle(1_700_000_000, "1")
+            },
+            opened: true,
+        }
+    }
+
+    #[test]
+    fn test_hub_fan_out() {
+        let hub = CandleHub::new(8);
+        let mut mint_a = hub.subscribe("A");
+        let mut all = hub.subscribe_all();
+
+        hub.publish(update("A"));
+        hub.publish(update("B"));
+        assert_eq!(mint_a.try_recv().unwrap().mint, "A");
+        assert!(mint_a.try_recv().is_err());
+        assert_eq!(all.try_recv().unwrap().mint, "A");
+        assert_eq!(all.try_recv().unwrap().mint, "B");
+
+        // The channel of a mint is dropped after its last subscriber leaves
+        drop(mint_a);
+        hub.publish(update("A"));
+        assert_eq!(hub.mint_channels(), 0);
+
+        // Idle channels of mints with no further updates go on the next subscribe
+        let mint_b = hub.subscribe("B");
+        drop(mint_b);
+        let _mint_c = hub.subscribe("C");
+        assert_eq!(hub.mint_channels(), 1);
+    }
+}
@@ -0,0 +1,174 @@
+// Candle pre-aggregation for the monitor processes. Instead of a Redis
+// read-modify-write per trade, trades are folded into in-memory candles per
+// mint and minute, and merged into the stored candles every flush interval.
+// A busy mint then costs one candle write per interval, at the price of
+// readers seeing candles up to one interval late.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::kline::{self, KLineData, KLineManager};
+use crate::{instance_lock, supervisor};
+
+#[derive(Debug, Clone)]
+pub struct PreaggConfig {
+    pub enabled: bool,
+    // How often buffered candles are written to Redis
+    pub flush_interval: Duration,
+}
+
+impl PreaggConfig {
+    pub fn from_env() -> Self {
+        Self {
+            enabled: std::env::var("CANDLE_PREAGG_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            flush_interval: Duration::from_millis(
+                std::env::var("CANDLE_PREAGG_FLUSH_MS")
+                    .unwrap_or_else(|_| "1000".to_string())
+                    .parse::<u64>()
+                    .unwrap_or(1000)
+                    .max(50),
+            ),
+        }
+    }
+}
+
+// Trades of one mint minute that are not in Redis yet
+#[derive(Debug, Clone)]
+pub struct PendingCandle {
+    // Candle built from the buffered trades only, merged into the stored one on flush
+    pub delta: KLineData,
+    pub complete: bool,
+}
+
+// Buffered candles keyed by mint and minute, so each mint flushes oldest first
+#[derive(Debug, Default)]
+pub struct PendingCandles {
+    candles: BTreeMap<(String, i64), PendingCandle>,
+}
+
+impl PendingCandles {
+    // Fold a one-trade candle into the buffered candle of its minute
+    pub fn add(&mut self, mint: &str, delta: KLineData, complete: bool) {
+        match self.candles.get_mut(&(mint.to_string(), delta.timestamp)) {
+            Some(pending) => {
+                kline::merge_kline(&mut pending.delta, &delta);
+                pending.complete |= complete;
+            }
+            None => {
+                self.candles.insert(
+                    (mint.to_string(), delta.timestamp),
+                    PendingCandle { delta, complete },
+                );
+            }
+        }
+    }
+
+    // Put back a candle that failed to flush, ahead of trades buffered since
+    pub fn requeue(&mut self, mint: &str, candle: PendingCandle) {
+        let key = (mint.to_string(), candle.delta.timestamp);
+        match self.candles.remove(&key) {
+            Some(newer) => {
+                let mut merged = candle;
+                kline::merge_kline(&mut merged.delta, &newer.delta);
+                merged.complete |= newer.complete;
+                self.candles.insert(key, merged);
+            }
+            None => {
+                self.candles.insert(key, candle);
+            }
+        }
+    }
+
+    // Take all buffered candles, each mint's oldest first
+    pub fn drain(&mut self) -> Vec<(String, PendingCandle)> {
+        std::mem::take(&mut self.candles)
+            .into_iter()
+            .map(|((mint, _), candle)| (mint, candle))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.candles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candles.is_empty()
+    }
+}
+
+// Periodically write the candles the monitor buffered in memory
+pub fn spawn_flush_task(
+    kline_manager: Arc<KLineManager>,
+    config: &PreaggConfig,
+) -> tokio::task::JoinHandle<()> {
+    info!(
+        "🧮 Candle pre-aggregation enabled: flushing every {}ms",
+        config.flush_interval.as_millis()
+    );
+    let flush_interval = config.flush_interval;
+    supervisor::spawn_supervised("candle_preagg_flush", async move {
+        let mut interval = tokio::time::interval(flush_interval);
+        loop {
+            interval.tick().await;
+            // A standby buffers nothing, and must not write what it may still hold
+            if instance_lock::is_read_only() {
+                continue;
+            }
+            match kline_manager.flush_pending().await {
+                Ok(0) => {}
+                Ok(flushed) => debug!("Flushed {} pre-aggregated candles", flushed),
+                Err(e) => warn!("Failed to flush pre-aggregated candles: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness;
+
+    fn candle(timestamp: i64, price: &str, volume: &str) -> KLineData {
+        KLineData {
+            volume_sol: volume.to_string(),
+            volume_token: volume.to_string(),
+            net_flow_sol: volume.to_string(),
+            ..test_harness::candle(timestamp, price)
+        }
+    }
+
+    #[test]
+    fn test_pending_candles() {
+        let mut pending = PendingCandles::default();
+        pending.add("B", candle(60, "1", "1"), false);
+        pending.add("A", candle(120, "2", "1"), false);
+        pending.add("A", candle(60, "3", "2"), false);
+        pending.add("A", candle(60, "1", "1"), true);
+        assert_eq!(pending.len(), 3);
+
+        let drained = pending.drain();
+        assert!(pending.is_empty());
+        let (mint, first) = &drained[0];
+        assert_eq!((mint.as_str(), first.delta.timestamp), ("A", 60));
+        assert_eq!(first.delta.open, "3");
+        assert_eq!(first.delta.close, "1");
+        assert_eq!(first.delta.high, "3");
+        assert_eq!(first.delta.volume_sol, "3");
+        assert!(first.complete);
+        assert_eq!(drained[1].1.delta.timestamp, 120);
+        assert_eq!(drained[2].0, "B");
+
+        // A failed flush goes back ahead of the trades buffered after it
+        pending.add("A", candle(60, "5", "1"), false);
+        pending.requeue("A", drained[0].1.clone());
+        let requeued = &pending.drain()[0].1;
+        assert_eq!(requeued.delta.open, "3");
+        assert_eq!(requeued.delta.close, "5");
+        assert_eq!(requeued.delta.volume_sol, "4");
+    }
+}
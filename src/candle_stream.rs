@@ -0,0 +1,187 @@
+use anyhow::Result;
+use redis::streams::{
+    StreamAutoClaimOptions, StreamAutoClaimReply, StreamId, StreamMaxlen, StreamReadOptions,
+    StreamReadReply,
+};
+use redis::{AsyncCommands, aio::ConnectionManager, aio::MultiplexedConnection};
+use tracing::info;
+
+use crate::message_bus;
+
+// Redis stream of candle-close events produced by the monitors
+pub const CANDLE_STREAM_KEY: &str = "stream:candle_closed";
+
+/// K线收盘事件：mint在 `closed_before` 之前的K线都已收盘
+#[derive(Debug, Clone)]
+pub struct CandleClosedEvent {
+    pub id: String,
+    pub mint: String,
+    pub closed_before: i64,
+    /// 修正事件：该时间戳的K线在收盘后被延迟到达的交易改变
+    pub corrected: Option<i64>,
+}
+
+// Approximate maximum number of events kept in the stream
+fn stream_maxlen() -> usize {
+    std::env::var("CANDLE_STREAM_MAXLEN")
+        .unwrap_or_else(|_| "100000".to_string())
+        .parse()
+        .unwrap_or(100000)
+}
+
+// Milliseconds an event may stay unacknowledged before another consumer claims it
+pub fn claim_idle_ms() -> u64 {
+    std::env::var("CANDLE_STREAM_CLAIM_IDLE_SECS")
+        .unwrap_or_else(|_| "60".to_string())
+        .parse::<u64>()
+        .unwrap_or(60)
+        .max(1)
+        * 1000
+}
+
+fn event_from_entry(entry: StreamId) -> CandleClosedEvent {
+    CandleClosedEvent {
+        mint: entry.get("mint").unwrap_or_default(),
+        closed_before: entry.get("closed_before").unwrap_or_default(),
+        corrected: entry.get("corrected"),
+        id: entry.id,
+    }
+}
+
+// Publish a candle-close event, called when the first trade of a new minute opens a candle
+pub async fn publish_candle_closed(
+    con: &mut ConnectionManager,
+    mint: &str,
+    closed_before: i64,
+) -> Result<()> {
+    let _: Option<String> = con
+        .xadd_maxlen(
+            CANDLE_STREAM_KEY,
+            StreamMaxlen::Approx(stream_maxlen()),
+            "*",
+            &[
+                ("mint", mint.to_string()),
+                ("closed_before", closed_before.to_string()),
+            ],
+        )
+        .await?;
+    message_bus::publish_candle_closed(mint, closed_before, None);
+    Ok(())
+}
+
+// Re-publish the close of a candle a late trade changed after its close event
+pub async fn publish_candle_corrected(
+    con: &mut ConnectionManager,
+    mint: &str,
+    candle_ts: i64,
+) -> Result<()> {
+    let _: Option<String> = con
+        .xadd_maxlen(
+            CANDLE_STREAM_KEY,
+            StreamMaxlen::Approx(stream_maxlen()),
+            "*",
+            &[
+                ("mint", mint.to_string()),
+                ("closed_before", (candle_ts + 60).to_string()),
+                ("corrected", candle_ts.to_string()),
+            ],
+        )
+        .await?;
+    message_bus::publish_candle_closed(mint, candle_ts + 60, Some(candle_ts));
+    Ok(())
+}
+
+/// 基于consumer group的K线收盘事件消费者。
+/// 使用独立连接，阻塞读取不会占用全局连接池。
+pub struct CandleStreamConsumer {
+    con: MultiplexedConnection,
+    group: String,
+    consumer: String,
+}
+
+impl CandleStreamConsumer {
+    pub async fn new(group: &str, consumer: &str) -> Result<Self> {
+        let redis_url =
+            std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379/".to_string());
+        let mut con = redis::Client::open(redis_url)?
+            .get_multiplexed_async_connection()
+            .await?;
+
+        // Create the group from the start of the stream; it may already exist
+        let created: redis::RedisResult<()> = con
+            .xgroup_create_mkstream(CANDLE_STREAM_KEY, group, "0")
+            .await;
+        match created {
+            Ok(()) => info!(
+                "📥 Created consumer group {} on {}",
+                group, CANDLE_STREAM_KEY
+            ),
+            Err(e) if e.code() == Some("BUSYGROUP") => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        Ok(Self {
+            con,
+            group: group.to_string(),
+            consumer: consumer.to_string(),
+        })
+    }
+
+    /// 读取事件。`start_id` 为 ">" 时读取新事件（最多阻塞 `block_ms`），
+    /// 为其他id时读取本consumer在该id之后尚未ack的事件（用于重启后重放）
+    pub async fn read(
+        &mut self,
+        start_id: &str,
+        count: usize,
+        block_ms: usize,
+    ) -> Result<Vec<CandleClosedEvent>> {
+        let mut options = StreamReadOptions::default()
+            .group(&self.group, &self.consumer)
+            .count(count);
+        if start_id == ">" {
+            options = options.block(block_ms);
+        }
+
+        let reply: Option<StreamReadReply> = self
+            .con
+            .xread_options(&[CANDLE_STREAM_KEY], &[start_id], &options)
+            .await?;
+
+        Ok(reply
+            .into_iter()
+            .flat_map(|r| r.keys)
+            .flat_map(|k| k.ids)
+            .map(event_from_entry)
+            .collect())
+    }
+
+    /// 认领组内任一consumer超过 `min_idle_ms` 未ack的事件，
+    /// consumer宕机后其pending事件由其他worker接手处理
+    pub async fn claim_stale(
+        &mut self,
+        min_idle_ms: u64,
+        count: usize,
+    ) -> Result<Vec<CandleClosedEvent>> {
+        let reply: StreamAutoClaimReply = self
+            .con
+            .xautoclaim_options(
+                CANDLE_STREAM_KEY,
+                &self.group,
+                &self.consumer,
+                min_idle_ms,
+                "0-0",
+                StreamAutoClaimOptions::default().count(count),
+            )
+            .await?;
+        Ok(reply.claimed.into_iter().map(event_from_entry).collect())
+    }
+
+    /// 确认事件已处理
+    pub async fn ack(&mut self, ids: &[String]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let _: usize = self.con.xack(CANDLE_STREAM_KEY, &self.group, ids).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+use anyhow::{Context, Result, bail};
+use futures_util::{Stream, stream};
+use reqwest::header::AUTHORIZATION;
+use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::api::{
+    AlertPoll, ApiResponse, CandleUpdate, FeedAlert, KLinesResponse, KlineQuery, MintInfo,
+};
+
+// Long polls are held open by the server, requests wait this much longer than asked
+const POLL_GRACE: Duration = Duration::from_secs(10);
+
+// Typed async client for the pump-kmonitor web API
+#[derive(Debug, Clone)]
+pub struct KmonitorClient {
+    base_url: String,
+    http: reqwest::Client,
+    admin_token: Option<String>,
+}
+
+// One event read from a server-sent events stream
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseEvent {
+    pub event: String,
+    pub data: String,
+}
+
+// Incremental parser of a server-sent events body, fed chunk by chunk
+#[derive(Debug, Default)]
+pub struct SseParser {
+    // Raw bytes, a chunk may end inside a multi-byte character
+    buffer: Vec<u8>,
+}
+
+impl SseParser {
+    // Append a chunk and return the events it completes. Comments such as
+    // keep-alives are skipped, events default to "message".
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let block: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            let block = String::from_utf8_lossy(&block);
+            let mut event = String::from("message");
+            let mut data = Vec::new();
+            for line in block.lines() {
+                let (field, value) = line.split_once(':').unwrap_or((line, ""));
+                let value = value.strip_prefix(' ').unwrap_or(value);
+                match field {
+                    "event" => event = value.to_string(),
+                    "data" => data.push(value),
+                    _ => {}
+                }
+            }
+            if !data.is_empty() {
+                events.push(SseEvent {
+                    event,
+                    data: data.join("\n"),
+                });
+            }
+        }
+        events
+    }
+}
+
+impl KmonitorClient {
+    // Client of the web server at `base_url`, e.g. "http://localhost:8080"
+    pub fn new(base_url: &str) -> Result<Self> {
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http: reqwest::Client::builder().build()?,
+            admin_token: None,
+        })
+    }
+
+    // Send ADMIN_API_TOKEN as bearer token, needed for the admin-guarded routes
+    pub fn with_admin_token(mut self, token: &str) -> Self {
+        self.admin_token = Some(token.to_string());
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    fn request(&self, path: &str) -> reqwest::RequestBuilder {
+        let request = self.http.get(self.url(path));
+        match &self.admin_token {
+            Some(token) => request.header(AUTHORIZATION, format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+
+    // Send a request and unwrap the ApiResponse envelope
+    async fn send<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T> {
+        let response = request.send().await?.error_for_status()?;
+        let body: ApiResponse<T> = response.json().await?;
+        if !body.success {
+            bail!(
+                "{}",
+                body.message
+                    .unwrap_or_else(|| "Request failed without a message".to_string())
+            );
+        }
+        body.data.context("Response has no data")
+    }
+
+    // Active mints with their candle counts
+    pub async fn get_mints(&self) -> Result<Vec<MintInfo>> {
+        self.send(self.request("/api/mints")).await
+    }
+
+    // Candles of a mint with the alert annotations on them
+    pub async fn get_klines(&self, mint: &str, query: &KlineQuery) -> Result<KLinesResponse> {
+        self.send(
+            self.request(&format!("/api/v2/mint/{}/klines", mint))
+                .query(query),
+        )
+        .await
+    }
+
+    // Alerts after a cursor, waiting up to `timeout` for new ones. Without a
+    // cursor only the cursor to resume from is returned.
+    pub async fn poll_alerts(
+        &self,
+        since_id: Option<&str>,
+        timeout: Duration,
+    ) -> Result<AlertPoll> {
+        let mut query = vec![("timeout", timeout.as_secs().to_string())];
+        if let Some(since_id) = since_id {
+            query.push(("since_id", since_id.to_string()));
+        }
+        self.send(
+            self.request("/api/alerts/poll")
+                .query(&query)
+                .timeout(timeout + POLL_GRACE),
+        )
+        .await
+    }
+
+    // Alerts as they are raised, by chaining long polls from `since_id` (or from now)
+    pub fn stream_alerts(
+        &self,
+        since_id: Option<String>,
+    ) -> impl Stream<Item = Result<FeedAlert>> + '_ {
+        let state = (since_id, VecDeque::<FeedAlert>::new());
+        stream::unfold(Some(state), move |state| async move {
+            let (mut cursor, mut pending) = state?;
+            loop {
+                if let Some(alert) = pending.pop_front() {
+                    return Some((Ok(alert), Some((cursor, pending))));
+                }
+                match self
+                    .poll_alerts(cursor.as_deref(), Duration::from_secs(25))
+                    .await
+                {
+                    Ok(poll) => {
+                        cursor = Some(poll.next_id);
+                        pending.extend(poll.alerts);
+                    }
+                    Err(e) => return Some((Err(e), None)),
+                }
+            }
+        })
+    }
+
+    // Live candle updates of one mint
+    pub async fn stream_mint_klines(
+        &self,
+        mint: &str,
+    ) -> Result<impl Stream<Item = Result<CandleUpdate>> + use<>> {
+        self.stream_candles(&format!("/api/mint/{}/klines/stream", mint))
+            .await
+    }
+
+    // Live candle updates of every mint
+    pub async fn stream_all_klines(
+        &self,
+    ) -> Result<impl Stream<Item = Result<CandleUpdate>> + use<>> {
+        self.stream_candles("/api/klines/stream").await
+    }
+
+    async fn stream_candles(
+        &self,
+        path: &str,
+    ) -> Result<impl Stream<Item = Result<CandleUpdate>> + use<>> {
+        let response = self.request(path).send().await?.error_for_status()?;
+        let state = (response, SseParser::default(), VecDeque::<SseEvent>::new());
+        Ok(stream::unfold(Some(state), |state| async move {
+            let (mut response, mut parser, mut pending) = state?;
+            loop {
+                if let Some(event) = pending.pop_front() {
+                    if event.event != "candle" {
+                        continue;
+                    }
+                    let update = serde_json::from_str(&event.data).map_err(Into::into);
+                    return Some((update, Some((response, parser, pending))));
+                }
+                match response.chunk().await {
+                    Ok(Some(chunk)) => pending.extend(parser.push(&chunk)),
+                    Ok(None) => return None,
+                    Err(e) => return Some((Err(e.into()), None)),
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_parser() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b"event: candle\ndata: {\"a\"").is_empty());
+        let events = parser.push(b":1}\n\n:\n\ndata:x\ndata: y\n\n");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: "candle".to_string(),
+                    data: "{\"a\":1}".to_string(),
+                },
+                SseEvent {
+                    event: "message".to_string(),
+                    data: "x\ny".to_string(),
+                },
+            ]
+        );
+    }
+}
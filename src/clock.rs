@@ -0,0 +1,123 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 时钟抽象，KLineManager和策略引擎通过它获取当前时间。
+/// 实时监控使用系统时钟，交易回放使用回放时钟，测试使用手动时钟
+pub trait Clock: Send + Sync {
+    /// 当前时间（秒级Unix时间戳）
+    fn now(&self) -> i64;
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+/// 系统时钟
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+}
+
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// 手动时钟，只在调用 `set`/`advance` 时前进，用于可复现的测试
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    now: AtomicI64,
+}
+
+impl ManualClock {
+    pub fn new(now: i64) -> Self {
+        Self {
+            now: AtomicI64::new(now),
+        }
+    }
+
+    pub fn set(&self, now: i64) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, secs: i64) {
+        self.now.fetch_add(secs, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> i64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+/// 回放时钟：从录制的起始时间开始，按 `speed` 倍速前进。
+/// K线仍按交易记录中的时间戳分桶，该时钟只决定回放节奏和"当前时间"
+#[derive(Debug, Clone)]
+pub struct ReplayClock {
+    start_ts: i64,
+    started_at: Instant,
+    speed: f64,
+}
+
+impl ReplayClock {
+    pub fn new(start_ts: i64, speed: f64) -> Self {
+        Self {
+            start_ts,
+            started_at: Instant::now(),
+            speed: if speed > 0.0 { speed } else { 1.0 },
+        }
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// 等待回放时间到达录制时间戳 `ts`，用于按倍速投放交易
+    pub async fn sleep_until(&self, ts: i64) {
+        tokio::time::sleep(self.real_delay_until(ts, self.started_at.elapsed())).await;
+    }
+
+    // Replay time after `elapsed` wall time
+    fn replay_time(&self, elapsed: Duration) -> i64 {
+        self.start_ts + (elapsed.as_secs_f64() * self.speed) as i64
+    }
+
+    // Wall time still needed to reach replay time `ts`
+    fn real_delay_until(&self, ts: i64, elapsed: Duration) -> Duration {
+        let target = Duration::from_secs_f64((ts - self.start_ts).max(0) as f64 / self.speed);
+        target.saturating_sub(elapsed)
+    }
+}
+
+impl Clock for ReplayClock {
+    fn now(&self) -> i64 {
+        self.replay_time(self.started_at.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_clock_speed() {
+        let clock = ReplayClock::new(1_000, 100.0);
+        // 3 real seconds at 100x cover 5 minutes of recorded trades
+        assert_eq!(clock.replay_time(Duration::from_secs(3)), 1_300);
+        assert_eq!(
+            clock.real_delay_until(1_600, Duration::from_secs(3)),
+            Duration::from_secs(3)
+        );
+        assert_eq!(
+            clock.real_delay_until(1_100, Duration::from_secs(3)),
+            Duration::ZERO
+        );
+
+        let manual = ManualClock::new(1_000);
+        manual.advance(60);
+        assert_eq!(manual.now(), 1_060);
+    }
+}
@@ -0,0 +1,322 @@
+use anyhow::Result;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::labels::{self, LabelCategory};
+use crate::launch;
+use crate::localized;
+use crate::notification_format::Language;
+use crate::positions;
+use crate::redis_helper;
+use crate::remote_config;
+use crate::strategy::StrategyAlert;
+
+// Funders don't change, so resolved wallets are cached for a week ("" = none found)
+const FUNDER_CACHE_TTL_SECS: u64 = 604800;
+
+// Mints analyzed at once while annotating a strategy pass's alerts
+const ANNOTATE_CONCURRENCY: usize = 8;
+
+/// 对Top钱包做资金来源分析得到的钱包集群
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterAnalysis {
+    pub mint: String,
+    pub analyzed_at: i64,
+    /// 参与分析的钱包数量
+    pub wallets: usize,
+    /// 估计控制这些钱包的实体数量（同一来源资助或互相资助的钱包算一个实体）
+    pub cluster_count: usize,
+    /// 最大集群的钱包数量
+    pub largest_cluster: usize,
+    /// 至少包含两个钱包的集群
+    pub clusters: Vec<Vec<String>>,
+    /// 钱包 -> 资助来源，交易所热钱包资助的钱包记为无来源
+    pub funders: HashMap<String, Option<String>>,
+}
+
+impl ClusterAnalysis {
+    /// 告警中附加的集群描述
+    pub fn summary(&self) -> String {
+        localized!(
+            Language::from_env(),
+            "Top{}钱包约由{}个实体控制，最大集群{}个钱包",
+            "The top {} wallets belong to about {} entities, the largest cluster has {} wallets",
+            self.wallets,
+            self.cluster_count,
+            self.largest_cluster
+        )
+    }
+}
+
+pub struct ClusterConfig {
+    pub enabled: bool,
+    pub ttl_secs: u64,
+    /// 一轮告警的集群分析总时限
+    pub timeout: Duration,
+}
+
+impl ClusterConfig {
+    pub fn from_env() -> Self {
+        Self {
+            enabled: remote_config::var("CLUSTER_ANALYSIS_ENABLED")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
+            ttl_secs: std::env::var("CLUSTER_ANALYSIS_TTL_SECS")
+                .unwrap_or_else(|_| "600".to_string())
+                .parse()
+                .unwrap_or(600),
+            timeout: Duration::from_secs(
+                std::env::var("CLUSTER_ANALYSIS_TIMEOUT_SECS")
+                    .unwrap_or_else(|_| "15".to_string())
+                    .parse()
+                    .unwrap_or(15),
+            ),
+        }
+    }
+}
+
+fn get_clusters_key(mint: &str) -> String {
+    format!("clusters:{}", mint)
+}
+
+fn get_funder_key(wallet: &str) -> String {
+    format!("funder:{}", wallet)
+}
+
+/// 通过缓存获取钱包的资金来源，未缓存时查询RPC
+pub async fn get_funder_cached(wallet: &str) -> Result<Option<String>> {
+    let key = get_funder_key(wallet);
+    if let Some(funder) = redis_helper::get::<_, String>(&key).await? {
+        return Ok(Some(funder).filter(|f| !f.is_empty()));
+    }
+    let funder = launch::find_funder(wallet).await?;
+    redis_helper::setex(
+        &key,
+        funder.clone().unwrap_or_default(),
+        FUNDER_CACHE_TTL_SECS,
+    )
+    .await?;
+    Ok(funder)
+}
+
+fn find(parent: &mut HashMap<String, String>, node: &str) -> String {
+    let next = parent
+        .get(node)
+        .cloned()
+        .unwrap_or_else(|| node.to_string());
+    if next == node {
+        return next;
+    }
+    let root = find(parent, &next);
+    parent.insert(node.to_string(), root.clone());
+    root
+}
+
+// Drop funders labeled as exchanges. Withdrawals from one exchange hot wallet
+// reach unrelated users, so they don't tie wallets to one entity.
+pub fn exclude_exchange_funders(
+    funders: &mut HashMap<String, Option<String>>,
+    exchanges: &HashMap<String, labels::WalletLabel>,
+) {
+    for funder in funders.values_mut() {
+        if funder.as_ref().is_some_and(|f| {
+            exchanges
+                .get(f)
+                .is_some_and(|label| label.category == LabelCategory::Exchange)
+        }) {
+            *funder = None;
+        }
+    }
+}
+
+// Group wallets sharing a funder, or funded by another analyzed wallet.
+// Returns the groups of analyzed wallets, largest first.
+pub fn group_wallets(funders: &HashMap<String, Option<String>>) -> Vec<Vec<String>> {
+    let mut parent: HashMap<String, String> = HashMap::new();
+    for (wallet, funder) in funders {
+        parent
+            .entry(wallet.clone())
+            .or_insert_with(|| wallet.clone());
+        if let Some(funder) = funder {
+            let a = find(&mut parent, wallet);
+            let b = find(&mut parent, funder);
+            if a != b {
+                parent.insert(a, b);
+            }
+        }
+    }
+
+    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+    for wallet in funders.keys() {
+        let root = find(&mut parent, wallet);
+        groups.entry(root).or_default().push(wallet.clone());
+    }
+    let mut groups: Vec<Vec<String>> = groups.into_values().collect();
+    for group in groups.iter_mut() {
+        group.sort();
+    }
+    groups.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    groups
+}
+
+/// 分析mint的Top钱包资金来源并缓存结果
+pub async fn analyze_mint(mint: &str) -> Result<ClusterAnalysis> {
+    let config = ClusterConfig::from_env();
+    let wallets: Vec<String> = positions::get_mint_positions(mint)
+        .await?
+        .wallets
+        .into_iter()
+        .map(|w| w.wallet)
+        .collect();
+
+    let lookups = wallets.into_iter().map(|wallet| async move {
+        let funder = match get_funder_cached(&wallet).await {
+            Ok(funder) => funder,
+            Err(e) => {
+                debug!("Failed to resolve funder for {}: {}", wallet, e);
+                None
+            }
+        };
+        (wallet, funder)
+    });
+    let mut funders: HashMap<String, Option<String>> = futures_util::future::join_all(lookups)
+        .await
+        .into_iter()
+        .collect();
+    let funder_labels = labels::get_labels(funders.values().flatten().map(String::as_str)).await?;
+    exclude_exchange_funders(&mut funders, &funder_labels);
+
+    let groups = group_wallets(&funders);
+    let analysis = ClusterAnalysis {
+        mint: mint.to_string(),
+        analyzed_at: chrono::Utc::now().timestamp(),
+        wallets: funders.len(),
+        cluster_count: groups.len(),
+        largest_cluster: groups.first().map(|g| g.len()).unwrap_or(0),
+        clusters: groups.into_iter().filter(|g| g.len() > 1).collect(),
+        funders,
+    };
+    redis_helper::setex(
+        get_clusters_key(mint),
+        serde_json::to_string(&analysis)?,
+        config.ttl_secs,
+    )
+    .await?;
+    info!(
+        "🕸️ Wallet clusters for {}: {} entities across {} top wallets",
+        mint, analysis.cluster_count, analysis.wallets
+    );
+    Ok(analysis)
+}
+
+/// 获取缓存的集群分析结果
+pub async fn get_cluster_analysis(mint: &str) -> Result<Option<ClusterAnalysis>> {
+    let data: Option<String> = redis_helper::get(get_clusters_key(mint)).await?;
+    Ok(data.and_then(|d| serde_json::from_str(&d).ok()))
+}
+
+/// 为告警的mint做集群分析（已缓存的直接使用），并把结果附加到告警信息中。
+/// 各mint并发分析，整轮不超过 `CLUSTER_ANALYSIS_TIMEOUT_SECS`，超时未完成的mint不附加集群信息
+pub async fn annotate_alerts(alerts: &mut [StrategyAlert]) {
+    let config = ClusterConfig::from_env();
+    if !config.enabled || alerts.is_empty() {
+        return;
+    }
+
+    let mut mints: Vec<String> = alerts.iter().map(|alert| alert.mint.clone()).collect();
+    mints.sort();
+    mints.dedup();
+    let total = mints.len();
+    let mut pending = futures_util::stream::iter(mints)
+        .map(|mint| async move {
+            let analysis = match get_cluster_analysis(&mint).await {
+                Ok(Some(analysis)) => Some(analysis),
+                _ => match analyze_mint(&mint).await {
+                    Ok(analysis) => Some(analysis),
+                    Err(e) => {
+                        warn!("⚠️ 钱包集群分析失败 {}: {}", mint, e);
+                        None
+                    }
+                },
+            };
+            (mint, analysis)
+        })
+        .buffer_unordered(ANNOTATE_CONCURRENCY);
+
+    // 已解析的资金来源各自缓存，超时中断的分析下一轮可以继续使用
+    let deadline = tokio::time::Instant::now() + config.timeout;
+    let mut analyzed: HashMap<String, ClusterAnalysis> = HashMap::new();
+    let mut finished = 0;
+    loop {
+        match tokio::time::timeout_at(deadline, pending.next()).await {
+            Ok(Some((mint, analysis))) => {
+                finished += 1;
+                if let Some(analysis) = analysis {
+                    analyzed.insert(mint, analysis);
+                }
+            }
+            Ok(None) => break,
+            Err(_) => {
+                warn!(
+                    "⚠️ 钱包集群分析超时，{}/{} 个mint未完成",
+                    total - finished,
+                    total
+                );
+                break;
+            }
+        }
+    }
+
+    for alert in alerts.iter_mut() {
+        if let Some(analysis) = analyzed.get(&alert.mint)
+            && analysis.wallets > 0
+        {
+            alert.message = format!("{} | {}", alert.message, analysis.summary());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_wallets() {
+        let funders: HashMap<String, Option<String>> = [
+            ("a", Some("f1")),
+            ("b", Some("f1")),
+            // c was funded by top wallet a, so it joins a's cluster
+            ("c", Some("a")),
+            ("d", Some("f2")),
+            ("e", None),
+        ]
+        .into_iter()
+        .map(|(w, f)| (w.to_string(), f.map(|f| f.to_string())))
+        .collect();
+
+        let groups = group_wallets(&funders);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0], vec!["a", "b", "c"]);
+        assert_eq!(groups[1], vec!["d"]);
+        assert_eq!(groups[2], vec!["e"]);
+
+        // Wallets funded by an exchange hot wallet are not one entity
+        let mut funders: HashMap<String, Option<String>> = [("a", "cex"), ("b", "cex")]
+            .into_iter()
+            .map(|(w, f)| (w.to_string(), Some(f.to_string())))
+            .collect();
+        let exchanges = HashMap::from([(
+            "cex".to_string(),
+            labels::WalletLabel {
+                name: "Exchange 1".to_string(),
+                category: LabelCategory::Exchange,
+            },
+        )]);
+        exclude_exchange_funders(&mut funders, &exchanges);
+        assert_eq!(group_wallets(&funders).len(), 2);
+    }
+}
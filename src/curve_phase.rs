@@ -0,0 +1,235 @@
+// Early-entry detector for bonding-curve tokens. The monitors follow each
+// mint's curve progress from the reserves in its Pump trade events and alert
+// when progress crosses a milestone while buy flow is accelerating.
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use tracing::info;
+
+use crate::alerts;
+use crate::localized;
+use crate::notification::NotificationManager;
+use crate::notification_format::NotificationFormat;
+use crate::remote_config;
+use crate::severity;
+use crate::strategy::StrategyAlert;
+use crate::trades::TradeRecord;
+
+pub const CURVE_PHASE_STRATEGY_NAME: &str = "联合曲线阶段";
+
+// Curves kept in memory before idle ones are dropped
+const MAX_TRACKED_CURVES: usize = 10000;
+
+#[derive(Debug, Clone)]
+pub struct CurvePhaseConfig {
+    pub enabled: bool,
+    // Curve progress milestones in percent, ascending
+    pub milestones: Vec<f64>,
+    // Buy flow of the last window is compared with the window before it
+    pub window_secs: i64,
+    // Last window's buys divided by the previous window's that counts as accelerating
+    pub min_acceleration: Decimal,
+    // SOL bought in the last window required for an alert
+    pub min_buy_sol: Decimal,
+}
+
+impl CurvePhaseConfig {
+    pub fn from_env() -> Self {
+        let mut milestones: Vec<f64> = remote_config::var("CURVE_PHASE_MILESTONES")
+            .unwrap_or_else(|_| "25,50,75".to_string())
+            .split(',')
+            .filter_map(|m| m.trim().parse().ok())
+            .filter(|m| *m > 0.0 && *m < 100.0)
+            .collect();
+        milestones.sort_by(f64::total_cmp);
+        Self {
+            enabled: remote_config::var("CURVE_PHASE_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            milestones,
+            window_secs: remote_config::var("CURVE_PHASE_WINDOW_SECS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            min_acceleration: remote_config::var("CURVE_PHASE_MIN_ACCELERATION")
+                .unwrap_or_else(|_| "1.5".to_string())
+                .parse()
+                .unwrap_or(Decimal::new(15, 1)),
+            min_buy_sol: remote_config::var("CURVE_PHASE_MIN_BUY_SOL")
+                .unwrap_or_else(|_| "1".to_string())
+                .parse()
+                .unwrap_or(Decimal::ONE),
+        }
+    }
+}
+
+// A milestone crossed with accelerating buys
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhaseCross {
+    pub milestone: f64,
+    pub progress_pct: f64,
+    // SOL bought in the last window and the one before it
+    pub recent_buy_sol: Decimal,
+    pub previous_buy_sol: Decimal,
+}
+
+// Progress and recent buys of one curve
+#[derive(Debug, Clone, Default)]
+pub struct CurveState {
+    progress_pct: Option<f64>,
+    // Highest milestone alerted, so sells and re-buys do not alert it again
+    alerted: f64,
+    buys: VecDeque<(i64, Decimal)>,
+    last_seen: i64,
+}
+
+impl CurveState {
+    // Record a trade and return the highest milestone it crossed, if buys are
+    // accelerating. The first trade seen only sets the starting progress, so
+    // a restarted monitor does not alert curves that are already past a milestone.
+    pub fn observe(
+        &mut self,
+        progress_pct: f64,
+        timestamp: i64,
+        buy_sol: Option<Decimal>,
+        config: &CurvePhaseConfig,
+    ) -> Option<PhaseCross> {
+        self.last_seen = timestamp;
+        while self
+            .buys
+            .front()
+            .is_some_and(|(t, _)| *t <= timestamp - 2 * config.window_secs)
+        {
+            self.buys.pop_front();
+        }
+        if let Some(sol) = buy_sol {
+            self.buys.push_back((timestamp, sol));
+        }
+
+        let previous = self.progress_pct.replace(progress_pct)?;
+        let milestone = config
+            .milestones
+            .iter()
+            .copied()
+            .rfind(|m| previous.max(self.alerted) < *m && *m <= progress_pct)?;
+
+        let split = timestamp - config.window_secs;
+        let (recent, earlier): (Vec<_>, Vec<_>) = self.buys.iter().partition(|(t, _)| *t > split);
+        let recent_buy_sol: Decimal = recent.iter().map(|(_, sol)| sol).sum();
+        let previous_buy_sol: Decimal = earlier.iter().map(|(_, sol)| sol).sum();
+        let accelerating = recent_buy_sol >= config.min_buy_sol
+            && recent_buy_sol >= previous_buy_sol * config.min_acceleration;
+        if !accelerating {
+            return None;
+        }
+
+        self.alerted = milestone;
+        Some(PhaseCross {
+            milestone,
+            progress_pct,
+            recent_buy_sol,
+            previous_buy_sol,
+        })
+    }
+}
+
+static CURVES: LazyLock<Mutex<HashMap<String, CurveState>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Check a bonding-curve trade against the milestones and alert on a crossing.
+// `curve_progress` is the share of the curve's tokens sold (0-1).
+pub async fn check_trade(trade: &TradeRecord, curve_progress: f64) -> Result<()> {
+    let config = CurvePhaseConfig::from_env();
+    if !config.enabled || config.milestones.is_empty() {
+        return Ok(());
+    }
+
+    let cross = {
+        let mut curves = CURVES.lock().unwrap_or_else(|e| e.into_inner());
+        if curves.len() > MAX_TRACKED_CURVES && !curves.contains_key(&trade.mint) {
+            let cutoff = trade.timestamp - 2 * config.window_secs;
+            curves.retain(|_, curve| curve.last_seen >= cutoff);
+        }
+        curves.entry(trade.mint.clone()).or_default().observe(
+            curve_progress * 100.0,
+            trade.timestamp,
+            trade.is_buy.then_some(trade.sol_amount),
+            &config,
+        )
+    };
+    let Some(cross) = cross else {
+        return Ok(());
+    };
+
+    info!(
+        "📈 {} bonding curve crossed {}% ({:.1}%), buys {} SOL vs {} SOL",
+        trade.mint,
+        cross.milestone,
+        cross.progress_pct,
+        cross.recent_buy_sol,
+        cross.previous_buy_sol
+    );
+    let format = NotificationFormat::current().await;
+    let mut alert = StrategyAlert {
+        mint: trade.mint.clone(),
+        strategy_name: CURVE_PHASE_STRATEGY_NAME.to_string(),
+        message: localized!(
+            format.language,
+            "联合曲线进度突破 {}% (当前 {:.1}%)，近{}秒买入 {}，此前{}秒 {}",
+            "Bonding curve passed {}% (now {:.1}%), buys in the last {}s: {}, in the {}s before: {}",
+            cross.milestone,
+            cross.progress_pct,
+            config.window_secs,
+            format.amount(cross.recent_buy_sol),
+            config.window_secs,
+            format.amount(cross.previous_buy_sol)
+        ),
+        timestamp: trade.timestamp,
+        severity: None,
+        params: None,
+        klines: Vec::new(),
+    };
+    severity::score_alert(&mut alert).await;
+    alerts::record_alert(&alert).await?;
+    NotificationManager::new()?
+        .send_one_shot_notification(&alert)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curve_phase() {
+        let config = CurvePhaseConfig {
+            enabled: true,
+            milestones: vec![25.0, 50.0, 75.0],
+            window_secs: 60,
+            min_acceleration: Decimal::TWO,
+            min_buy_sol: Decimal::ONE,
+        };
+        let sol = |n: i64| Some(Decimal::from(n));
+        let mut curve = CurveState::default();
+        // The first trade only sets the starting point
+        assert!(curve.observe(30.0, 0, sol(1), &config).is_none());
+        // 1 SOL before the window, 3 SOL in it
+        assert!(curve.observe(45.0, 70, sol(1), &config).is_none());
+        let cross = curve.observe(55.0, 100, sol(2), &config).unwrap();
+        assert_eq!(cross.milestone, 50.0);
+        assert_eq!(cross.recent_buy_sol, Decimal::from(3));
+        assert_eq!(cross.previous_buy_sol, Decimal::ONE);
+
+        // Falling back and crossing 50% again does not alert twice
+        assert!(curve.observe(48.0, 110, None, &config).is_none());
+        assert!(curve.observe(52.0, 115, sol(5), &config).is_none());
+
+        // Crossing without accelerating buys does not alert
+        let mut curve = CurveState::default();
+        curve.observe(20.0, 0, sol(5), &config);
+        assert!(curve.observe(26.0, 61, sol(1), &config).is_none());
+    }
+}
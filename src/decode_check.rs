@@ -0,0 +1,285 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{LazyLock, Mutex};
+use tracing::{error, warn};
+
+use crate::localized;
+use crate::metrics;
+use crate::notification::NotificationManager;
+use crate::notification_format::Language;
+use crate::pump::TradeEvent;
+use crate::pump_amm::AmmTradeEvent;
+use crate::redis_helper;
+use crate::remote_config;
+use crate::strategy::StrategyAlert;
+
+// Redis hash of suspect decode counts, field "suspect:{program}:{reason}"
+const DECODE_STATS_KEY: &str = "stats:decode";
+
+// Total supply of a pump.fun token in raw units (1B tokens, 6 decimals)
+const PUMP_TOKEN_SUPPLY: u64 = 1_000_000_000_000_000;
+
+// Plausibility limits for decoded trade events
+#[derive(Debug, Clone)]
+pub struct DecodeLimits {
+    // Largest allowed distance between the event timestamp and local time
+    pub max_clock_skew_secs: i64,
+    // Largest plausible SOL amount of a single trade, in lamports
+    pub max_sol_lamports: u64,
+}
+
+impl DecodeLimits {
+    pub fn from_env() -> Self {
+        let max_sol: u64 = remote_config::var("DECODE_MAX_SOL_AMOUNT")
+            .unwrap_or_else(|_| "100000".to_string())
+            .parse()
+            .unwrap_or(100_000);
+        Self {
+            max_clock_skew_secs: remote_config::var("DECODE_MAX_CLOCK_SKEW_SECS")
+                .unwrap_or_else(|_| "600".to_string())
+                .parse()
+                .unwrap_or(600),
+            max_sol_lamports: max_sol.saturating_mul(1_000_000_000),
+        }
+    }
+}
+
+fn is_on_curve(address: &str) -> Option<bool> {
+    Pubkey::from_str(address).ok().map(|key| key.is_on_curve())
+}
+
+// Sanity checks for a decoded pump.fun TradeEvent, returning the failed checks.
+// Mints are created from keypairs, so they must be on the ed25519 curve.
+pub fn check_pump_trade(event: &TradeEvent, now: i64, limits: &DecodeLimits) -> Vec<&'static str> {
+    let mut issues = Vec::new();
+    if (event.timestamp - now).abs() > limits.max_clock_skew_secs {
+        issues.push("timestamp_out_of_range");
+    }
+    if event.sol_amount > limits.max_sol_lamports {
+        issues.push("sol_amount_above_cap");
+    }
+    if event.token_amount > PUMP_TOKEN_SUPPLY
+        || event.virtual_token_reserves > 2 * PUMP_TOKEN_SUPPLY
+        || event.real_token_reserves > PUMP_TOKEN_SUPPLY
+    {
+        issues.push("token_amount_above_supply");
+    }
+    if is_on_curve(&event.mint) != Some(true) {
+        issues.push("mint_off_curve");
+    }
+    issues
+}
+
+// Sanity checks for a decoded PumpSwap TradeEvent, returning the failed checks.
+// Pools are PDAs, so they must be off the ed25519 curve.
+pub fn check_amm_trade(
+    event: &AmmTradeEvent,
+    now: i64,
+    limits: &DecodeLimits,
+) -> Vec<&'static str> {
+    let mut issues = Vec::new();
+    if (event.timestamp - now).abs() > limits.max_clock_skew_secs {
+        issues.push("timestamp_out_of_range");
+    }
+    if event.sol_amount > limits.max_sol_lamports {
+        issues.push("sol_amount_above_cap");
+    }
+    if event.pool_base_token_reserves == 0 || event.pool_quote_token_reserves == 0 {
+        issues.push("empty_pool_reserves");
+    }
+    if is_on_curve(&event.pool) != Some(false) {
+        issues.push("pool_on_curve");
+    }
+    issues
+}
+
+// Decodes seen since the last drift evaluation, per program
+#[derive(Debug, Default)]
+struct DriftWindow {
+    decoded: u64,
+    suspect: u64,
+}
+
+static WINDOWS: LazyLock<Mutex<HashMap<&'static str, DriftWindow>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Number of decodes per drift evaluation and the suspect share that raises an alert
+fn drift_config() -> (u64, f64) {
+    let window = std::env::var("DECODE_DRIFT_WINDOW")
+        .unwrap_or_else(|_| "200".to_string())
+        .parse()
+        .unwrap_or(200);
+    let ratio = remote_config::var("DECODE_DRIFT_ALERT_RATIO")
+        .unwrap_or_else(|_| "0.1".to_string())
+        .parse()
+        .unwrap_or(0.1);
+    (window, ratio)
+}
+
+// Add a decode to the program's window; returns the suspect share once the window is full
+fn record_window(program: &'static str, suspect: bool, window_size: u64) -> Option<f64> {
+    let mut windows = WINDOWS.lock().unwrap_or_else(|e| e.into_inner());
+    let window = windows.entry(program).or_default();
+    window.decoded += 1;
+    if suspect {
+        window.suspect += 1;
+    }
+    if window.decoded < window_size.max(1) {
+        return None;
+    }
+    let ratio = window.suspect as f64 / window.decoded as f64;
+    *window = DriftWindow::default();
+    Some(ratio)
+}
+
+// Check a decoded pump.fun trade; suspect events are counted and should be dropped
+pub async fn accept_pump_trade(event: &TradeEvent) -> bool {
+    let limits = DecodeLimits::from_env();
+    let issues = check_pump_trade(event, chrono::Utc::now().timestamp(), &limits);
+    accept("pump", &event.signature, issues).await
+}
+
+// Check a decoded PumpSwap trade; suspect events are counted and should be dropped
+pub async fn accept_amm_trade(event: &AmmTradeEvent) -> bool {
+    let limits = DecodeLimits::from_env();
+    let issues = check_amm_trade(event, chrono::Utc::now().timestamp(), &limits);
+    accept("amm", &event.signature, issues).await
+}
+
+async fn accept(program: &'static str, signature: &str, issues: Vec<&'static str>) -> bool {
+    let (window_size, alert_ratio) = drift_config();
+    let suspect = !issues.is_empty();
+
+    if suspect {
+        metrics::record_parse_failure();
+        warn!(
+            "Suspect {} decode in {}: {}",
+            program,
+            signature,
+            issues.join(", ")
+        );
+        if let Err(e) = record_issues(program, &issues).await {
+            warn!("Failed to record suspect decode: {}", e);
+        }
+    }
+
+    if let Some(ratio) = record_window(program, suspect, window_size)
+        && ratio >= alert_ratio
+    {
+        alert_drift(program, ratio, window_size).await;
+    }
+    !suspect
+}
+
+async fn record_issues(program: &str, issues: &[&'static str]) -> Result<()> {
+    let mut con = redis_helper::get_connection().await?;
+    let mut pipe = redis::pipe();
+    for issue in issues {
+        pipe.hincr(
+            DECODE_STATS_KEY,
+            format!("suspect:{}:{}", program, issue),
+            1,
+        )
+        .ignore();
+    }
+    let _: () = pipe.query_async(&mut *con).await?;
+    Ok(())
+}
+
+async fn alert_drift(program: &'static str, ratio: f64, window_size: u64) {
+    error!(
+        "🧩 {:.0}% of the last {} {} decodes failed sanity checks, the event layout may have changed",
+        ratio * 100.0,
+        window_size,
+        program
+    );
+
+    let recorded = async {
+        let mut con = redis_helper::get_connection().await?;
+        let _: () = con
+            .hincr(DECODE_STATS_KEY, format!("drift_alerts:{}", program), 1)
+            .await?;
+        Ok::<_, anyhow::Error>(())
+    };
+    if let Err(e) = recorded.await {
+        warn!("Failed to record decode drift alert: {}", e);
+    }
+
+    let alert = StrategyAlert {
+        mint: format!("decode:{}", program),
+        strategy_name: "解码布局异常".to_string(),
+        message: localized!(
+            Language::from_env(),
+            "最近 {} 条 {} 交易事件中 {:.0}% 未通过解码校验，事件布局可能已变更",
+            "{2:.0}% of the last {0} {1} trade events failed decode validation, the event layout may have changed",
+            window_size,
+            program,
+            ratio * 100.0
+        ),
+        timestamp: chrono::Local::now().timestamp(),
+        severity: None,
+        params: None,
+        klines: Vec::new(),
+    };
+    let sent = match NotificationManager::new() {
+        Ok(manager) => manager.send_notification(&alert).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = sent {
+        warn!("Failed to notify decode drift: {}", e);
+    }
+}
+
+// Get suspect decode counts by program and reason
+pub async fn get_decode_stats() -> Result<HashMap<String, usize>> {
+    let mut con = redis_helper::get_connection().await?;
+    Ok(con.hgetall(DECODE_STATS_KEY).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_pump_trade() {
+        let limits = DecodeLimits {
+            max_clock_skew_secs: 600,
+            max_sol_lamports: 100_000 * 1_000_000_000,
+        };
+        let mut event = TradeEvent {
+            signature: "sig".to_string(),
+            slot: 1,
+            success: true,
+            mint: Pubkey::new_from_array([
+                // ed25519 base point, a known on-curve key
+                0x58, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+                0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+                0x66, 0x66, 0x66, 0x66,
+            ])
+            .to_string(),
+            user: "user".to_string(),
+            sol_amount: 1_000_000_000,
+            token_amount: 35_000_000_000_000,
+            is_buy: true,
+            timestamp: 1_700_000_000,
+            virtual_sol_reserves: 31_000_000_000,
+            virtual_token_reserves: 1_038_000_000_000_000,
+            real_sol_reserves: 1_000_000_000,
+            real_token_reserves: 758_000_000_000_000,
+        };
+        assert!(check_pump_trade(&event, 1_700_000_030, &limits).is_empty());
+
+        event.timestamp = 42;
+        event.token_amount = u64::MAX;
+        assert_eq!(
+            check_pump_trade(&event, 1_700_000_030, &limits),
+            vec!["timestamp_out_of_range", "token_amount_above_supply"]
+        );
+
+        assert_eq!(record_window("test", true, 2), None);
+        assert_eq!(record_window("test", false, 2), Some(0.5));
+    }
+}
@@ -0,0 +1,297 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use solana_client::rpc_client::RpcClient;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::proxy::ProxyConfig;
+use crate::websocket;
+
+// Timeout applied to each network check
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 单项诊断结果
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+    /// 失败时的修复建议
+    pub hint: Option<String>,
+}
+
+impl CheckResult {
+    fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ok: true,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn fail(name: impl Into<String>, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ok: false,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+// Check that an environment variable is set and not blank
+fn check_env(var: &str, hint: &str) -> CheckResult {
+    match std::env::var(var) {
+        Ok(value) if !value.trim().is_empty() => CheckResult::pass(var, "set"),
+        _ => CheckResult::fail(var, "not set", hint),
+    }
+}
+
+pub async fn check_redis() -> CheckResult {
+    let redis_url =
+        std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379/".to_string());
+    let hint = "Start Redis or set REDIS_URL to a reachable instance";
+
+    let ping = async {
+        let mut con = redis::Client::open(redis_url.as_str())?
+            .get_multiplexed_async_connection()
+            .await?;
+        let pong: String = con.ping().await?;
+        Ok::<_, anyhow::Error>(pong)
+    };
+    match tokio::time::timeout(CHECK_TIMEOUT, ping).await {
+        Ok(Ok(_)) => CheckResult::pass("Redis", format!("{} reachable", redis_url)),
+        Ok(Err(e)) => CheckResult::fail("Redis", format!("{}: {}", redis_url, e), hint),
+        Err(_) => CheckResult::fail("Redis", format!("{}: timed out", redis_url), hint),
+    }
+}
+
+pub async fn check_rpc_endpoints() -> Vec<CheckResult> {
+    let Ok(endpoints) = std::env::var("RPC_ENDPOINTS") else {
+        return vec![check_env(
+            "RPC_ENDPOINTS",
+            "Set RPC_ENDPOINTS to a comma-separated list of Solana RPC URLs",
+        )];
+    };
+
+    let mut results = Vec::new();
+    for url in endpoints.split(',').map(|s| s.trim().to_string()) {
+        let name = format!("RPC {}", url);
+        let health = tokio::task::spawn_blocking({
+            let url = url.clone();
+            move || RpcClient::new(url).get_health()
+        });
+        let result = match tokio::time::timeout(CHECK_TIMEOUT, health).await {
+            Ok(Ok(Ok(()))) => CheckResult::pass(name, "healthy"),
+            Ok(Ok(Err(e))) => CheckResult::fail(
+                name,
+                e.to_string(),
+                "Check the URL and API key, or remove the endpoint from RPC_ENDPOINTS",
+            ),
+            Ok(Err(e)) => CheckResult::fail(name, e.to_string(), "Retry the check"),
+            Err(_) => CheckResult::fail(
+                name,
+                "timed out",
+                "The endpoint is unreachable or too slow; check network access",
+            ),
+        };
+        results.push(result);
+    }
+    results
+}
+
+// Check the websocket endpoint and, when configured, the redundant one
+pub async fn check_websockets() -> Vec<CheckResult> {
+    let Ok(endpoint) = std::env::var("RPC_WEBSOCKET_ENDPOINT") else {
+        return vec![check_env(
+            "RPC_WEBSOCKET_ENDPOINT",
+            "Set RPC_WEBSOCKET_ENDPOINT to a Solana websocket URL (wss://...)",
+        )];
+    };
+    let mut results = vec![check_websocket("WebSocket", &endpoint).await];
+    if let Ok(secondary) = std::env::var("RPC_WEBSOCKET_SECONDARY_ENDPOINT")
+        && !secondary.trim().is_empty()
+    {
+        results.push(check_websocket("WebSocket (secondary)", secondary.trim()).await);
+    }
+    results
+}
+
+async fn check_websocket(name: &str, endpoint: &str) -> CheckResult {
+    let proxy = match ProxyConfig::from_env() {
+        Ok(proxy) => proxy,
+        Err(e) => {
+            return CheckResult::fail(
+                name,
+                format!("WS_PROXY: {}", e),
+                "Set WS_PROXY to socks5://[user:pass@]host:port or http://[user:pass@]host:port",
+            );
+        }
+    };
+    match tokio::time::timeout(
+        CHECK_TIMEOUT,
+        websocket::connect_websocket(endpoint, proxy.as_ref()),
+    )
+    .await
+    {
+        Ok(Ok(_)) => CheckResult::pass(name, format!("{} connectable", endpoint)),
+        Ok(Err(e)) => CheckResult::fail(
+            name,
+            format!("{}: {}", endpoint, e),
+            "Check RPC_WEBSOCKET_ENDPOINT; it must be a ws:// or wss:// URL that accepts subscriptions",
+        ),
+        Err(_) => CheckResult::fail(
+            name,
+            format!("{}: timed out", endpoint),
+            "The websocket endpoint is unreachable; check network access or set WS_PROXY",
+        ),
+    }
+}
+
+pub fn check_notification() -> CheckResult {
+    let name = "Notification";
+    let enabled = std::env::var("NOTIFICATION_ENABLED")
+        .unwrap_or_else(|_| "true".to_string())
+        .parse::<bool>()
+        .unwrap_or(true);
+    if !enabled {
+        return CheckResult::pass(name, "disabled");
+    }
+
+    let webhook = std::env::var("NOTIFICATION_WEBHOOK_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty());
+    let script_path = std::env::var("NOTIFICATION_SCRIPT_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("./scripts/notify.sh"));
+
+    if script_path.is_file() {
+        if !is_executable(&script_path) {
+            return CheckResult::fail(
+                name,
+                format!("script {:?} is not executable", script_path),
+                format!("Run chmod +x {}", script_path.display()),
+            );
+        }
+        return CheckResult::pass(name, format!("script {:?}", script_path));
+    }
+    match webhook {
+        Some(url) => CheckResult::pass(name, format!("webhook {}", url)),
+        None => CheckResult::fail(
+            name,
+            format!(
+                "script {:?} not found and no webhook configured",
+                script_path
+            ),
+            "Copy scripts/notify.sh.example to the script path, set NOTIFICATION_WEBHOOK_URL, or set NOTIFICATION_ENABLED=false",
+        ),
+    }
+}
+
+// Whether the file has an execute bit; permissions aren't checked off Unix
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// 运行所有诊断检查
+pub async fn run_all() -> Vec<CheckResult> {
+    let mut results = vec![check_redis().await];
+    results.extend(check_rpc_endpoints().await);
+    results.extend(check_websockets().await);
+    results.push(check_notification());
+    results
+}
+
+/// 冗余端点只要有一个可用即可：有可用端点时不可用的端点只打印警告，返回空；
+/// 全部不可用时返回所有失败项
+pub fn require_any(results: Vec<CheckResult>) -> Vec<CheckResult> {
+    if !results.iter().any(|r| r.ok) {
+        return results;
+    }
+    for result in results.iter().filter(|r| !r.ok) {
+        println!("⚠️ {}: {}", result.name, result.detail);
+    }
+    Vec::new()
+}
+
+/// 打印诊断报告，返回失败项数量
+pub fn print_report(results: &[CheckResult]) -> usize {
+    for result in results {
+        if result.ok {
+            println!("✅ {}: {}", result.name, result.detail);
+        } else {
+            println!("❌ {}: {}", result.name, result.detail);
+            if let Some(hint) = &result.hint {
+                println!("   💡 {}", hint);
+            }
+        }
+    }
+    results.iter().filter(|r| !r.ok).count()
+}
+
+/// 启动前校验服务所需的配置，失败时返回可操作的错误信息。
+/// `check_endpoints` 为true时（长期运行的服务）还检查所需的RPC和WebSocket端点可以连接
+pub async fn validate_startup(required_env: &[&str], check_endpoints: bool) -> Result<()> {
+    let mut results: Vec<CheckResult> = required_env
+        .iter()
+        .map(|var| check_env(var, &format!("Set {} in .env (see .env.example)", var)))
+        .collect();
+    results.push(check_redis().await);
+    if check_endpoints && results.iter().all(|r| r.ok) {
+        if required_env.contains(&"RPC_ENDPOINTS") {
+            results.extend(require_any(check_rpc_endpoints().await));
+        }
+        if required_env.contains(&"RPC_WEBSOCKET_ENDPOINT") {
+            results.extend(require_any(check_websockets().await));
+        }
+    }
+
+    let failed: Vec<&CheckResult> = results.iter().filter(|r| !r.ok).collect();
+    if failed.is_empty() {
+        return Ok(());
+    }
+
+    let report = failed
+        .iter()
+        .map(|r| {
+            format!(
+                "  - {}: {} ({})",
+                r.name,
+                r.detail,
+                r.hint.as_deref().unwrap_or("")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Err(anyhow::anyhow!(
+        "Startup validation failed:\n{}\nRun `pump-kmonitor doctor` for a full diagnosis",
+        report
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_require_any() {
+        let up = CheckResult::pass("RPC a", "healthy");
+        let down = CheckResult::fail("RPC b", "timed out", "check network access");
+
+        // One reachable endpoint is enough
+        assert!(require_any(vec![up, down.clone()]).is_empty());
+        let failed = require_any(vec![down.clone(), down]);
+        assert_eq!(failed.len(), 2);
+        assert!(failed.iter().all(|r| !r.ok));
+    }
+}
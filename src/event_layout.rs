@@ -0,0 +1,414 @@
+// Versioned layouts of the Anchor events decoded from program logs. Program
+// upgrades append fields to their events, so decoding with one fixed layout
+// either rejects old events or, once offsets shift, reads garbage amounts. An
+// event is matched by its discriminator and decoded with the newest known
+// version that fits its length. Bytes past that version's fields belong to
+// fields this build doesn't know yet and are ignored.
+
+use std::collections::HashSet;
+use std::sync::{LazyLock, Mutex};
+use tracing::info;
+
+use crate::constant::{
+    AMM_BUY_EVENT_DISCRIMINATOR, AMM_SELL_EVENT_DISCRIMINATOR, PUMP_TRADE_EVENT_DISCRIMINATOR,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    Pubkey,
+    U64,
+    I64,
+    Bool,
+}
+
+impl FieldType {
+    pub const fn size(self) -> usize {
+        match self {
+            FieldType::Pubkey => 32,
+            FieldType::U64 | FieldType::I64 => 8,
+            FieldType::Bool => 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct EventLayout {
+    pub event: &'static str,
+    pub version: u8,
+    pub fields: &'static [(&'static str, FieldType)],
+}
+
+impl EventLayout {
+    // Bytes of an event in this layout, including the discriminator
+    pub const fn size(&self) -> usize {
+        let mut size = 8;
+        let mut i = 0;
+        while i < self.fields.len() {
+            size += self.fields[i].1.size();
+            i += 1;
+        }
+        size
+    }
+
+    fn field(&self, name: &str) -> Option<(usize, FieldType)> {
+        let mut offset = 8;
+        for (field, kind) in self.fields {
+            if *field == name {
+                return Some((offset, *kind));
+            }
+            offset += kind.size();
+        }
+        None
+    }
+}
+
+// Every known layout of one event, oldest first
+#[derive(Debug)]
+pub struct EventVersions {
+    pub discriminator: [u8; 8],
+    pub layouts: &'static [EventLayout],
+}
+
+use FieldType::{Bool, I64, Pubkey, U64};
+
+const PUMP_TRADE_V1: &[(&str, FieldType)] = &[
+    ("mint", Pubkey),
+    ("sol_amount", U64),
+    ("token_amount", U64),
+    ("is_buy", Bool),
+    ("user", Pubkey),
+    ("timestamp", I64),
+    ("virtual_sol_reserves", U64),
+    ("virtual_token_reserves", U64),
+];
+
+const PUMP_TRADE_V2: &[(&str, FieldType)] = &[
+    ("mint", Pubkey),
+    ("sol_amount", U64),
+    ("token_amount", U64),
+    ("is_buy", Bool),
+    ("user", Pubkey),
+    ("timestamp", I64),
+    ("virtual_sol_reserves", U64),
+    ("virtual_token_reserves", U64),
+    ("real_sol_reserves", U64),
+    ("real_token_reserves", U64),
+];
+
+// Protocol and creator fees
+const PUMP_TRADE_V3: &[(&str, FieldType)] = &[
+    ("mint", Pubkey),
+    ("sol_amount", U64),
+    ("token_amount", U64),
+    ("is_buy", Bool),
+    ("user", Pubkey),
+    ("timestamp", I64),
+    ("virtual_sol_reserves", U64),
+    ("virtual_token_reserves", U64),
+    ("real_sol_reserves", U64),
+    ("real_token_reserves", U64),
+    ("fee_recipient", Pubkey),
+    ("fee_basis_points", U64),
+    ("fee", U64),
+    ("creator", Pubkey),
+    ("creator_fee_basis_points", U64),
+    ("creator_fee", U64),
+];
+
+pub static PUMP_TRADE_EVENT: EventVersions = EventVersions {
+    discriminator: PUMP_TRADE_EVENT_DISCRIMINATOR,
+    layouts: &[
+        EventLayout {
+            event: "TradeEvent",
+            version: 1,
+            fields: PUMP_TRADE_V1,
+        },
+        EventLayout {
+            event: "TradeEvent",
+            version: 2,
+            fields: PUMP_TRADE_V2,
+        },
+        EventLayout {
+            event: "TradeEvent",
+            version: 3,
+            fields: PUMP_TRADE_V3,
+        },
+    ],
+};
+
+// PumpSwap buy and sell events share one shape. The amounts are named from
+// the side of the trade: base out and quote in for buys, the reverse for sells.
+const AMM_BUY_V1: &[(&str, FieldType)] = &[
+    ("timestamp", I64),
+    ("base_amount_out", U64),
+    ("max_quote_amount_in", U64),
+    ("user_base_token_reserves", U64),
+    ("user_quote_token_reserves", U64),
+    ("pool_base_token_reserves", U64),
+    ("pool_quote_token_reserves", U64),
+    ("quote_amount_in", U64),
+    ("lp_fee_basis_points", U64),
+    ("lp_fee", U64),
+    ("protocol_fee_basis_points", U64),
+    ("protocol_fee", U64),
+    ("quote_amount_in_with_lp_fee", U64),
+    ("user_quote_amount_in", U64),
+    ("pool", Pubkey),
+    ("user", Pubkey),
+    ("user_base_token_account", Pubkey),
+    ("user_quote_token_account", Pubkey),
+    ("protocol_fee_recipient", Pubkey),
+    ("protocol_fee_recipient_token_account", Pubkey),
+];
+
+// Coin creator fees
+const AMM_BUY_V2: &[(&str, FieldType)] = &[
+    ("timestamp", I64),
+    ("base_amount_out", U64),
+    ("max_quote_amount_in", U64),
+    ("user_base_token_reserves", U64),
+    ("user_quote_token_reserves", U64),
+    ("pool_base_token_reserves", U64),
+    ("pool_quote_token_reserves", U64),
+    ("quote_amount_in", U64),
+    ("lp_fee_basis_points", U64),
+    ("lp_fee", U64),
+    ("protocol_fee_basis_points", U64),
+    ("protocol_fee", U64),
+    ("quote_amount_in_with_lp_fee", U64),
+    ("user_quote_amount_in", U64),
+    ("pool", Pubkey),
+    ("user", Pubkey),
+    ("user_base_token_account", Pubkey),
+    ("user_quote_token_account", Pubkey),
+    ("protocol_fee_recipient", Pubkey),
+    ("protocol_fee_recipient_token_account", Pubkey),
+    ("coin_creator", Pubkey),
+    ("coin_creator_fee_basis_points", U64),
+    ("coin_creator_fee", U64),
+];
+
+const AMM_SELL_V1: &[(&str, FieldType)] = &[
+    ("timestamp", I64),
+    ("base_amount_in", U64),
+    ("min_quote_amount_out", U64),
+    ("user_base_token_reserves", U64),
+    ("user_quote_token_reserves", U64),
+    ("pool_base_token_reserves", U64),
+    ("pool_quote_token_reserves", U64),
+    ("quote_amount_out", U64),
+    ("lp_fee_basis_points", U64),
+    ("lp_fee", U64),
+    ("protocol_fee_basis_points", U64),
+    ("protocol_fee", U64),
+    ("quote_amount_out_without_lp_fee", U64),
+    ("user_quote_amount_out", U64),
+    ("pool", Pubkey),
+    ("user", Pubkey),
+    ("user_base_token_account", Pubkey),
+    ("user_quote_token_account", Pubkey),
+    ("protocol_fee_recipient", Pubkey),
+    ("protocol_fee_recipient_token_account", Pubkey),
+];
+
+const AMM_SELL_V2: &[(&str, FieldType)] = &[
+    ("timestamp", I64),
+    ("base_amount_in", U64),
+    ("min_quote_amount_out", U64),
+    ("user_base_token_reserves", U64),
+    ("user_quote_token_reserves", U64),
+    ("pool_base_token_reserves", U64),
+    ("pool_quote_token_reserves", U64),
+    ("quote_amount_out", U64),
+    ("lp_fee_basis_points", U64),
+    ("lp_fee", U64),
+    ("protocol_fee_basis_points", U64),
+    ("protocol_fee", U64),
+    ("quote_amount_out_without_lp_fee", U64),
+    ("user_quote_amount_out", U64),
+    ("pool", Pubkey),
+    ("user", Pubkey),
+    ("user_base_token_account", Pubkey),
+    ("user_quote_token_account", Pubkey),
+    ("protocol_fee_recipient", Pubkey),
+    ("protocol_fee_recipient_token_account", Pubkey),
+    ("coin_creator", Pubkey),
+    ("coin_creator_fee_basis_points", U64),
+    ("coin_creator_fee", U64),
+];
+
+pub static AMM_BUY_EVENT: EventVersions = EventVersions {
+    discriminator: AMM_BUY_EVENT_DISCRIMINATOR,
+    layouts: &[
+        EventLayout {
+            event: "BuyEvent",
+            version: 1,
+            fields: AMM_BUY_V1,
+        },
+        EventLayout {
+            event: "BuyEvent",
+            version: 2,
+            fields: AMM_BUY_V2,
+        },
+    ],
+};
+
+pub static AMM_SELL_EVENT: EventVersions = EventVersions {
+    discriminator: AMM_SELL_EVENT_DISCRIMINATOR,
+    layouts: &[
+        EventLayout {
+            event: "SellEvent",
+            version: 1,
+            fields: AMM_SELL_V1,
+        },
+        EventLayout {
+            event: "SellEvent",
+            version: 2,
+            fields: AMM_SELL_V2,
+        },
+    ],
+};
+
+// Layout versions already reported to carry unknown trailing fields
+static EXTENDED_LAYOUTS: LazyLock<Mutex<HashSet<(&'static str, u8)>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+// An event decoded with one layout version; fields missing from that version read as None
+pub struct DecodedEvent<'a> {
+    pub layout: &'static EventLayout,
+    data: &'a [u8],
+}
+
+impl DecodedEvent<'_> {
+    fn bytes(&self, name: &str, kind: FieldType) -> Option<&[u8]> {
+        let (offset, field_kind) = self.layout.field(name)?;
+        if field_kind != kind {
+            return None;
+        }
+        self.data.get(offset..offset + kind.size())
+    }
+
+    pub fn u64(&self, name: &str) -> Option<u64> {
+        Some(u64::from_le_bytes(self.bytes(name, U64)?.try_into().ok()?))
+    }
+
+    pub fn i64(&self, name: &str) -> Option<i64> {
+        Some(i64::from_le_bytes(self.bytes(name, I64)?.try_into().ok()?))
+    }
+
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        Some(self.bytes(name, Bool)?[0] != 0)
+    }
+
+    pub fn pubkey(&self, name: &str) -> Option<String> {
+        Some(bs58::encode(self.bytes(name, Pubkey)?).into_string())
+    }
+}
+
+// Decode an event as one of the given events, with the newest layout its length allows
+pub fn decode<'a>(data: &'a [u8], events: &[&'static EventVersions]) -> Option<DecodedEvent<'a>> {
+    let discriminator = data.get(..8)?;
+    let versions = events
+        .iter()
+        .find(|event| event.discriminator == discriminator)?;
+    let layout = versions
+        .layouts
+        .iter()
+        .rev()
+        .find(|layout| layout.size() <= data.len())?;
+
+    if data.len() > layout.size()
+        && std::ptr::eq(layout, versions.layouts.last()?)
+        && EXTENDED_LAYOUTS
+            .lock()
+            .unwrap()
+            .insert((layout.event, layout.version))
+    {
+        info!(
+            "{} events are longer than layout v{} ({} > {} bytes), decoding its fields and ignoring the rest",
+            layout.event,
+            layout.version,
+            data.len(),
+            layout.size()
+        );
+    }
+    Some(DecodedEvent { layout, data })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    fn event_discriminator(name: &str) -> [u8; 8] {
+        Sha256::digest(format!("event:{}", name)).as_slice()[..8]
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_layouts() {
+        assert_eq!(
+            event_discriminator("TradeEvent"),
+            PUMP_TRADE_EVENT_DISCRIMINATOR
+        );
+        assert_eq!(event_discriminator("BuyEvent"), AMM_BUY_EVENT_DISCRIMINATOR);
+        assert_eq!(
+            event_discriminator("SellEvent"),
+            AMM_SELL_EVENT_DISCRIMINATOR
+        );
+
+        let sizes = |versions: &EventVersions| -> Vec<usize> {
+            versions.layouts.iter().map(|l| l.size()).collect()
+        };
+        assert_eq!(sizes(&PUMP_TRADE_EVENT), vec![113, 129, 225]);
+        assert_eq!(sizes(&AMM_BUY_EVENT), vec![312, 360]);
+        assert_eq!(sizes(&AMM_SELL_EVENT), vec![312, 360]);
+
+        // Newer versions only append fields
+        for versions in [&PUMP_TRADE_EVENT, &AMM_BUY_EVENT, &AMM_SELL_EVENT] {
+            for pair in versions.layouts.windows(2) {
+                assert!(pair[1].fields.starts_with(pair[0].fields));
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut data = PUMP_TRADE_EVENT_DISCRIMINATOR.to_vec();
+        data.extend([1u8; 32]); // mint
+        data.extend(5u64.to_le_bytes()); // sol_amount
+        data.extend(7u64.to_le_bytes()); // token_amount
+        data.push(1); // is_buy
+        data.extend([2u8; 32]); // user
+        data.extend(1_700_000_000i64.to_le_bytes());
+        data.extend(11u64.to_le_bytes());
+        data.extend(13u64.to_le_bytes());
+
+        let v1 = decode(&data, &[&PUMP_TRADE_EVENT]).unwrap();
+        assert_eq!(v1.layout.version, 1);
+        assert_eq!(v1.u64("sol_amount"), Some(5));
+        assert_eq!(v1.bool("is_buy"), Some(true));
+        assert_eq!(v1.i64("timestamp"), Some(1_700_000_000));
+        assert_eq!(v1.u64("real_sol_reserves"), None);
+
+        data.extend(17u64.to_le_bytes());
+        data.extend(19u64.to_le_bytes());
+        // Fields of a future version don't shift the known ones
+        data.extend([9u8; 40]);
+        let v2 = decode(&data, &[&PUMP_TRADE_EVENT]).unwrap();
+        assert_eq!(v2.layout.version, 2);
+        assert_eq!(v2.u64("virtual_token_reserves"), Some(13));
+        assert_eq!(v2.u64("real_token_reserves"), Some(19));
+        assert_eq!(
+            v2.pubkey("user"),
+            Some(bs58::encode([2u8; 32]).into_string())
+        );
+        // Wrong type or unknown name
+        assert_eq!(v2.i64("sol_amount"), None);
+        assert_eq!(v2.u64("fee"), None);
+
+        assert!(decode(&data[..100], &[&PUMP_TRADE_EVENT]).is_none());
+        assert!(decode(&data, &[&AMM_BUY_EVENT, &AMM_SELL_EVENT]).is_none());
+    }
+}
@@ -0,0 +1,297 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use serde_json::{Value, json};
+use solana_client::rpc_request::RpcRequest;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::instance_lock;
+use crate::localized;
+use crate::notification::NotificationManager;
+use crate::notification_format::Language;
+use crate::redis_helper;
+use crate::strategy::StrategyAlert;
+use crate::{get_rpc_client_with_retry, remote_config};
+
+// Redis hash of detected feed gaps, field "{kind}:{monitor}"
+const FEED_STATS_KEY: &str = "stats:feed";
+
+// Weight of the latest interval in the moving average of the message rate
+const BASELINE_ALPHA: f64 = 0.1;
+
+#[derive(Debug, Clone)]
+pub struct FeedGapConfig {
+    // How often the message rate is checked
+    pub check_interval: Duration,
+    // Rate below this share of the baseline counts as a drop
+    pub drop_ratio: f64,
+    // Seconds without any notification that count as a gap on a busy feed
+    pub silence_secs: i64,
+    // Baseline rate (messages/sec) below which the feed is too quiet to judge
+    pub min_baseline_rate: f64,
+}
+
+impl FeedGapConfig {
+    pub fn from_env() -> Self {
+        Self {
+            check_interval: Duration::from_secs(
+                std::env::var("FEED_GAP_CHECK_SECS")
+                    .unwrap_or_else(|_| "15".to_string())
+                    .parse()
+                    .unwrap_or(15),
+            ),
+            drop_ratio: remote_config::var("FEED_GAP_DROP_RATIO")
+                .unwrap_or_else(|_| "0.2".to_string())
+                .parse()
+                .unwrap_or(0.2),
+            silence_secs: remote_config::var("FEED_GAP_SILENCE_SECS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            min_baseline_rate: remote_config::var("FEED_GAP_MIN_RATE")
+                .unwrap_or_else(|_| "0.5".to_string())
+                .parse()
+                .unwrap_or(0.5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GapKind {
+    // The program kept transacting on chain but the notifications stopped arriving
+    FeedFailure,
+    // The program itself went quiet, the feed is delivering what exists
+    MarketSilence,
+}
+
+impl GapKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            GapKind::FeedFailure => "feed_failure",
+            GapKind::MarketSilence => "market_silence",
+        }
+    }
+}
+
+// Whether the last interval looks like a gap against the feed's usual rate
+pub fn is_gap(rate: f64, baseline: f64, idle_secs: i64, config: &FeedGapConfig) -> bool {
+    if baseline < config.min_baseline_rate {
+        return false;
+    }
+    rate < baseline * config.drop_ratio || idle_secs >= config.silence_secs
+}
+
+// Compare what we received with what the program actually did on chain.
+// Missing more than half of the program's transactions means the feed is failing.
+pub fn classify(received: u64, on_chain: u64) -> GapKind {
+    if received * 2 < on_chain {
+        GapKind::FeedFailure
+    } else {
+        GapKind::MarketSilence
+    }
+}
+
+// Message continuity of one websocket subscription. The message loop records
+// notifications; a checker compares the rate with its moving average.
+pub struct FeedMonitor {
+    monitor_name: String,
+    program: String,
+    received: AtomicU64,
+    last_message_at: AtomicI64,
+}
+
+impl FeedMonitor {
+    pub fn new(monitor_name: &str, program: &str) -> Self {
+        Self {
+            monitor_name: monitor_name.to_string(),
+            program: program.to_string(),
+            received: AtomicU64::new(0),
+            last_message_at: AtomicI64::new(chrono::Utc::now().timestamp()),
+        }
+    }
+
+    pub fn record_message(&self) {
+        self.received.fetch_add(1, Ordering::Relaxed);
+        self.last_message_at
+            .store(chrono::Utc::now().timestamp(), Ordering::Relaxed);
+    }
+
+    // Check the feed every interval until the task is aborted with its connection
+    pub async fn run_checker(&self) {
+        let config = FeedGapConfig::from_env();
+        let mut interval = tokio::time::interval(config.check_interval);
+        interval.tick().await;
+
+        let mut baseline: Option<f64> = None;
+        // Start of the current gap, alerted once when it opens
+        let mut gap_started: Option<i64> = None;
+        loop {
+            interval.tick().await;
+            let config = FeedGapConfig::from_env();
+            let now = chrono::Utc::now().timestamp();
+            let count = self.received.swap(0, Ordering::Relaxed);
+            let rate = count as f64 / config.check_interval.as_secs_f64().max(1.0);
+            let idle_secs = now - self.last_message_at.load(Ordering::Relaxed);
+
+            let Some(average) = baseline else {
+                baseline = Some(rate);
+                continue;
+            };
+
+            if is_gap(rate, average, idle_secs, &config) {
+                // The baseline is frozen during a gap so it keeps describing the normal rate
+                if gap_started.is_none() {
+                    let since = now - config.check_interval.as_secs() as i64;
+                    gap_started = Some(since);
+                    self.alert_gap(since, count, rate, average).await;
+                }
+                continue;
+            }
+
+            if let Some(since) = gap_started.take() {
+                info!(
+                    "✅ {} feed recovered after {}s at {:.2} msg/s",
+                    self.monitor_name,
+                    now - since,
+                    rate
+                );
+            }
+            baseline = Some(average * (1.0 - BASELINE_ALPHA) + rate * BASELINE_ALPHA);
+        }
+    }
+
+    async fn alert_gap(&self, since: i64, received: u64, rate: f64, baseline: f64) {
+        // The lock holder alerts for its own feed, a standby stays quiet
+        if instance_lock::is_read_only() {
+            return;
+        }
+        let kind = match count_program_signatures(&self.program, since).await {
+            Ok(on_chain) => classify(received, on_chain),
+            Err(e) => {
+                // Without the chain's view, assume the worst
+                warn!("Failed to count {} signatures: {}", self.program, e);
+                GapKind::FeedFailure
+            }
+        };
+
+        let recorded = async {
+            let mut con = redis_helper::get_connection().await?;
+            let _: () = con
+                .hincr(
+                    FEED_STATS_KEY,
+                    format!("{}:{}", kind.as_str(), self.monitor_name),
+                    1,
+                )
+                .await?;
+            Ok::<_, anyhow::Error>(())
+        };
+        if let Err(e) = recorded.await {
+            warn!("Failed to record feed gap: {}", e);
+        }
+
+        let language = Language::from_env();
+        let (strategy_name, message) = match kind {
+            GapKind::FeedFailure => {
+                error!(
+                    "📉 {} feed gap: {:.2} msg/s against a usual {:.2} msg/s while the program kept trading",
+                    self.monitor_name, rate, baseline
+                );
+                (
+                    "数据流中断",
+                    localized!(
+                        language,
+                        "{} 消息速率降至 {:.2}/s（通常 {:.2}/s），链上交易仍在继续，可能漏接数据",
+                        "{} message rate fell to {:.2}/s (usually {:.2}/s) while trading continues on chain, data may be missed",
+                        self.monitor_name,
+                        rate,
+                        baseline
+                    ),
+                )
+            }
+            GapKind::MarketSilence => {
+                warn!(
+                    "🔇 {} feed quiet: {:.2} msg/s against a usual {:.2} msg/s, the program is quiet on chain too",
+                    self.monitor_name, rate, baseline
+                );
+                (
+                    "市场静默",
+                    localized!(
+                        language,
+                        "{} 消息速率降至 {:.2}/s（通常 {:.2}/s），链上交易同样减少，数据流正常",
+                        "{} message rate fell to {:.2}/s (usually {:.2}/s) and trading slowed on chain too, the feed is healthy",
+                        self.monitor_name,
+                        rate,
+                        baseline
+                    ),
+                )
+            }
+        };
+        let alert = StrategyAlert {
+            mint: format!("feed:{}", self.monitor_name),
+            strategy_name: strategy_name.to_string(),
+            message,
+            timestamp: chrono::Local::now().timestamp(),
+            severity: None,
+            params: None,
+            klines: Vec::new(),
+        };
+        let sent = match NotificationManager::new() {
+            Ok(manager) => manager.send_notification(&alert).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = sent {
+            warn!("Failed to notify feed gap: {}", e);
+        }
+    }
+}
+
+// Count the program's transactions since a unix timestamp, from its latest signatures
+async fn count_program_signatures(program: &str, since: i64) -> Result<u64> {
+    let params = json!([program, { "limit": 1000, "commitment": "confirmed" }]);
+    let signatures: Vec<Value> = get_rpc_client_with_retry(
+        |client| {
+            client
+                .send(RpcRequest::GetSignaturesForAddress, params.clone())
+                .map_err(anyhow::Error::from)
+        },
+        2,
+    )
+    .await?;
+    Ok(signatures
+        .iter()
+        .filter_map(|s| s.get("blockTime").and_then(|t| t.as_i64()))
+        .filter(|block_time| *block_time >= since)
+        .count() as u64)
+}
+
+// Get detected feed gap counts by kind and monitor
+pub async fn get_feed_stats() -> Result<HashMap<String, usize>> {
+    let mut con = redis_helper::get_connection().await?;
+    Ok(con.hgetall(FEED_STATS_KEY).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gap_detection() {
+        let config = FeedGapConfig {
+            check_interval: Duration::from_secs(15),
+            drop_ratio: 0.2,
+            silence_secs: 60,
+            min_baseline_rate: 0.5,
+        };
+        assert!(!is_gap(8.0, 10.0, 0, &config));
+        assert!(is_gap(1.0, 10.0, 0, &config));
+        assert!(is_gap(5.0, 10.0, 90, &config));
+        // Too quiet to tell a gap from normal variation
+        assert!(!is_gap(0.0, 0.2, 90, &config));
+
+        assert_eq!(classify(3, 400), GapKind::FeedFailure);
+        assert_eq!(classify(3, 4), GapKind::MarketSilence);
+        assert_eq!(classify(0, 0), GapKind::MarketSilence);
+    }
+}
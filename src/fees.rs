@@ -0,0 +1,121 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::redis_helper;
+
+/// 单个池子一小时内的手续费汇总（SOL）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeeStats {
+    pub hour: i64,
+    pub lp_fee: Decimal,
+    pub protocol_fee: Decimal,
+    pub creator_fee: Decimal,
+    pub volume_sol: Decimal,
+    pub trades: u64,
+}
+
+impl FeeStats {
+    fn merge(&mut self, other: &FeeStats) {
+        self.lp_fee += other.lp_fee;
+        self.protocol_fee += other.protocol_fee;
+        self.creator_fee += other.creator_fee;
+        self.volume_sol += other.volume_sol;
+        self.trades += other.trades;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolFees {
+    pub pool: String,
+    pub total: FeeStats,
+    pub hourly: Vec<FeeStats>,
+}
+
+// Hash of hour timestamp -> fee stats for a pool
+fn get_fees_key(pool: &str) -> String {
+    format!("fees:{}", pool)
+}
+
+// Number of hourly buckets kept per pool
+fn retention_hours() -> i64 {
+    std::env::var("FEE_STATS_RETENTION_HOURS")
+        .unwrap_or_else(|_| "168".to_string())
+        .parse()
+        .unwrap_or(168)
+}
+
+fn get_hour_timestamp(timestamp: i64) -> i64 {
+    timestamp - timestamp.rem_euclid(3600)
+}
+
+// Add the fees of one AMM trade to its pool's hourly bucket
+pub async fn record_fees(
+    pool: &str,
+    timestamp: i64,
+    lp_fee: Decimal,
+    protocol_fee: Decimal,
+    creator_fee: Decimal,
+    volume_sol: Decimal,
+) -> Result<()> {
+    let hour = get_hour_timestamp(timestamp);
+    let key = get_fees_key(pool);
+    let mut con = redis_helper::get_connection().await?;
+
+    let existing: Option<String> = con.hget(&key, hour).await?;
+    let is_new_hour = existing.is_none();
+    let mut stats: FeeStats = existing
+        .and_then(|d| serde_json::from_str(&d).ok())
+        .unwrap_or(FeeStats {
+            hour,
+            ..Default::default()
+        });
+    stats.merge(&FeeStats {
+        hour,
+        lp_fee,
+        protocol_fee,
+        creator_fee,
+        volume_sol,
+        trades: 1,
+    });
+    let _: () = con.hset(&key, hour, serde_json::to_string(&stats)?).await?;
+
+    // Drop expired buckets when a new hour starts
+    if is_new_hour {
+        let cutoff = hour - retention_hours() * 3600;
+        let hours: Vec<i64> = con.hkeys(&key).await?;
+        let expired: Vec<i64> = hours.into_iter().filter(|h| *h < cutoff).collect();
+        if !expired.is_empty() {
+            let _: () = con.hdel(&key, expired).await?;
+        }
+        let _: () = con.expire(&key, retention_hours() * 3600).await?;
+    }
+    Ok(())
+}
+
+// Get hourly fee stats for a pool (oldest first), optionally limited to the last N hours
+pub async fn get_pool_fees(pool: &str, hours: Option<i64>) -> Result<PoolFees> {
+    let mut con = redis_helper::get_connection().await?;
+    let data: HashMap<i64, String> = con.hgetall(get_fees_key(pool)).await?;
+
+    let cutoff = hours.map(|h| get_hour_timestamp(chrono::Utc::now().timestamp()) - (h - 1) * 3600);
+    let mut hourly: Vec<FeeStats> = data
+        .into_iter()
+        .filter(|(hour, _)| cutoff.is_none_or(|c| *hour >= c))
+        .filter_map(|(_, d)| serde_json::from_str(&d).ok())
+        .collect();
+    hourly.sort_by_key(|s| s.hour);
+
+    let mut total = FeeStats::default();
+    for stats in &hourly {
+        total.merge(stats);
+    }
+
+    Ok(PoolFees {
+        pool: pool.to_string(),
+        total,
+        hourly,
+    })
+}
@@ -0,0 +1,281 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use serde::Serialize;
+use std::collections::HashMap;
+use tracing::info;
+
+use crate::redis_helper;
+
+// Real token reserves of a fresh bonding curve (793.1M tokens, 6 decimals)
+const INITIAL_REAL_TOKEN_RESERVES: u64 = 793_100_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Milestone {
+    Created,
+    // Cumulative volume reached FUNNEL_VOLUME_MILESTONE_SOL
+    Volume,
+    // Half of the bonding curve's tokens sold
+    HalfCurve,
+    Graduated,
+    // Price fell FUNNEL_RUG_DROP_PCT from its peak
+    Rugged,
+}
+
+impl Milestone {
+    pub const ALL: [Milestone; 5] = [
+        Milestone::Created,
+        Milestone::Volume,
+        Milestone::HalfCurve,
+        Milestone::Graduated,
+        Milestone::Rugged,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Milestone::Created => "created",
+            Milestone::Volume => "volume",
+            Milestone::HalfCurve => "half_curve",
+            Milestone::Graduated => "graduated",
+            Milestone::Rugged => "rugged",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FunnelConfig {
+    pub volume_milestone_sol: Decimal,
+    pub rug_drop_pct: Decimal,
+    pub retention_secs: i64,
+}
+
+impl FunnelConfig {
+    pub fn from_env() -> Self {
+        Self {
+            volume_milestone_sol: std::env::var("FUNNEL_VOLUME_MILESTONE_SOL")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(Decimal::TEN),
+            rug_drop_pct: std::env::var("FUNNEL_RUG_DROP_PCT")
+                .unwrap_or_else(|_| "90".to_string())
+                .parse()
+                .unwrap_or(Decimal::new(90, 0)),
+            retention_secs: std::env::var("FUNNEL_RETENTION_SECS")
+                .unwrap_or_else(|_| "604800".to_string())
+                .parse()
+                .unwrap_or(604800),
+        }
+    }
+}
+
+// One stage of the funnel: launches in the window that reached the milestone
+#[derive(Debug, Clone, Serialize)]
+pub struct FunnelStage {
+    pub milestone: String,
+    pub count: usize,
+    // Share of the window's launches (%)
+    pub pct: f64,
+    // Median seconds from launch to the milestone
+    pub median_secs: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FunnelStats {
+    pub window_secs: i64,
+    pub launches: usize,
+    pub stages: Vec<FunnelStage>,
+}
+
+// Redis ZSET of mints by the time they reached a milestone
+fn get_milestone_key(milestone: Milestone) -> String {
+    format!("funnel:{}", milestone.as_str())
+}
+
+// Redis hash of a launch's running volume, peak price and curve progress, present only for
+// launches whose create event was observed
+fn get_progress_key(mint: &str) -> String {
+    format!("funnel:mint:{}", mint)
+}
+
+// Share of the bonding curve's tokens sold, from the curve's real token reserves
+pub fn curve_progress(real_token_reserves: u64) -> f64 {
+    1.0 - real_token_reserves.min(INITIAL_REAL_TOKEN_RESERVES) as f64
+        / INITIAL_REAL_TOKEN_RESERVES as f64
+}
+
+// Record a milestone the first time a mint reaches it
+async fn record_milestone(mint: &str, milestone: Milestone, timestamp: i64) -> Result<()> {
+    let mut con = redis_helper::get_connection().await?;
+    let added: usize = redis::cmd("ZADD")
+        .arg(get_milestone_key(milestone))
+        .arg("NX")
+        .arg(timestamp)
+        .arg(mint)
+        .query_async(&mut *con)
+        .await?;
+    if added > 0 && milestone != Milestone::Created {
+        info!(
+            "🪜 {} reached funnel milestone {}",
+            mint,
+            milestone.as_str()
+        );
+    }
+    Ok(())
+}
+
+// Start tracking a launch and drop milestones past the retention
+pub async fn record_created(mint: &str, timestamp: i64) -> Result<()> {
+    let config = FunnelConfig::from_env();
+    {
+        let mut con = redis_helper::get_connection().await?;
+        let key = get_progress_key(mint);
+        let _: () = con.hset_nx(&key, "volume", 0).await?;
+        let _: () = con.expire(&key, config.retention_secs).await?;
+        let mut pipe = redis::pipe();
+        for milestone in Milestone::ALL {
+            pipe.zrembyscore(
+                get_milestone_key(milestone),
+                "-inf",
+                timestamp - config.retention_secs,
+            )
+            .ignore();
+        }
+        let _: () = pipe.query_async(&mut *con).await?;
+    }
+    record_milestone(mint, Milestone::Created, timestamp).await
+}
+
+// Update a tracked launch with a trade. `curve_progress` is given for bonding
+// curve trades; `graduated` marks trades on the AMM pool.
+pub async fn record_trade(
+    mint: &str,
+    price: Decimal,
+    sol_amount: Decimal,
+    timestamp: i64,
+    curve_progress: Option<f64>,
+    graduated: bool,
+) -> Result<()> {
+    let config = FunnelConfig::from_env();
+    let key = get_progress_key(mint);
+    let (volume, peak) = {
+        let mut con = redis_helper::get_connection().await?;
+        let tracked: bool = con.exists(&key).await?;
+        if !tracked {
+            return Ok(());
+        }
+        let volume: f64 = con
+            .hincr(&key, "volume", sol_amount.to_f64().unwrap_or(0.0))
+            .await?;
+        let peak: Option<String> = con.hget(&key, "peak").await?;
+        let peak = peak.and_then(|p| p.parse::<Decimal>().ok());
+        if peak.is_none_or(|peak| price > peak) {
+            let _: () = con.hset(&key, "peak", price.to_string()).await?;
+        }
+        if let Some(progress) = curve_progress {
+            let _: () = con.hset(&key, "curve", progress).await?;
+        }
+        (volume, peak)
+    };
+
+    if volume >= config.volume_milestone_sol.to_f64().unwrap_or(f64::MAX) {
+        record_milestone(mint, Milestone::Volume, timestamp).await?;
+    }
+    if curve_progress.is_some_and(|p| p >= 0.5) {
+        record_milestone(mint, Milestone::HalfCurve, timestamp).await?;
+    }
+    if graduated || curve_progress.is_some_and(|p| p >= 1.0) {
+        record_milestone(mint, Milestone::Graduated, timestamp).await?;
+    }
+    if let Some(peak) = peak {
+        let floor = peak * (Decimal::ONE_HUNDRED - config.rug_drop_pct) / Decimal::ONE_HUNDRED;
+        if price <= floor {
+            record_milestone(mint, Milestone::Rugged, timestamp).await?;
+        }
+    }
+    Ok(())
+}
+
+// Latest bonding curve progress (0-1) of a tracked launch
+pub async fn get_curve_progress(mint: &str) -> Result<Option<f64>> {
+    let mut con = redis_helper::get_connection().await?;
+    Ok(con.hget(get_progress_key(mint), "curve").await?)
+}
+
+// Summarize how many launches of the window reached a milestone and how fast
+pub fn build_stage(
+    milestone: Milestone,
+    launches: &HashMap<String, i64>,
+    reached: &[(String, i64)],
+) -> FunnelStage {
+    let mut durations: Vec<i64> = reached
+        .iter()
+        .filter_map(|(mint, at)| launches.get(mint).map(|created| (at - created).max(0)))
+        .collect();
+    durations.sort_unstable();
+    let count = durations.len();
+    FunnelStage {
+        milestone: milestone.as_str().to_string(),
+        count,
+        pct: if launches.is_empty() {
+            0.0
+        } else {
+            count as f64 * 100.0 / launches.len() as f64
+        },
+        median_secs: durations.get(count / 2).copied(),
+    }
+}
+
+// Funnel of the launches created in the last `window_secs`
+pub async fn get_funnel(window_secs: i64) -> Result<FunnelStats> {
+    let since = chrono::Utc::now().timestamp() - window_secs;
+    let mut con = redis_helper::get_connection().await?;
+    let mut by_milestone = Vec::new();
+    for milestone in Milestone::ALL {
+        // Later milestones can only be reached after the launch
+        let reached: Vec<(String, i64)> = con
+            .zrangebyscore_withscores(get_milestone_key(milestone), since, "+inf")
+            .await?;
+        by_milestone.push((milestone, reached));
+    }
+    drop(con);
+
+    let launches: HashMap<String, i64> = by_milestone[0].1.iter().cloned().collect();
+    Ok(FunnelStats {
+        window_secs,
+        launches: launches.len(),
+        stages: by_milestone
+            .iter()
+            .map(|(milestone, reached)| build_stage(*milestone, &launches, reached))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_funnel_stage() {
+        assert_eq!(curve_progress(INITIAL_REAL_TOKEN_RESERVES), 0.0);
+        assert_eq!(curve_progress(INITIAL_REAL_TOKEN_RESERVES / 2), 0.5);
+        assert_eq!(curve_progress(0), 1.0);
+
+        let launches: HashMap<String, i64> = [
+            ("a".to_string(), 100),
+            ("b".to_string(), 200),
+            ("c".to_string(), 300),
+            ("d".to_string(), 400),
+        ]
+        .into();
+        // "x" launched before the window and is left out
+        let reached = vec![
+            ("a".to_string(), 160),
+            ("b".to_string(), 500),
+            ("x".to_string(), 600),
+        ];
+        let stage = build_stage(Milestone::Graduated, &launches, &reached);
+        assert_eq!(stage.count, 2);
+        assert_eq!(stage.pct, 50.0);
+        assert_eq!(stage.median_secs, Some(300));
+    }
+}
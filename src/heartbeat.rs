@@ -0,0 +1,303 @@
+// Liveness heartbeats between services. Every long-running service refreshes
+// a Redis key with a TTL; a service whose key expired has stopped beating,
+// whether it crashed, hung or lost Redis. The last heartbeat of each service
+// is also kept in a registry hash, so expired services are still listed.
+
+use anyhow::Result;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::localized;
+use crate::notification::NotificationManager;
+use crate::notification_format::Language;
+use crate::redis_helper;
+use crate::sharding::ShardConfig;
+use crate::strategy::StrategyAlert;
+use crate::supervisor;
+
+// How often services refresh their heartbeat and the watchdog checks them
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+// Redis hash of service -> last Heartbeat
+const REGISTRY_KEY: &str = "heartbeats";
+
+// Heartbeat written by a running service
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Heartbeat {
+    pub service: String,
+    pub instance: String,
+    pub started_at: i64,
+    pub timestamp: i64,
+}
+
+// Liveness of one service as reported by /api/health
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceHealth {
+    pub service: String,
+    pub instance: String,
+    pub started_at: i64,
+    pub last_seen: i64,
+    pub age_secs: i64,
+    pub alive: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    // False when any known service's heartbeat expired
+    pub healthy: bool,
+    pub services: Vec<ServiceHealth>,
+}
+
+// Seconds a heartbeat stays valid; a service is reported down after this long
+fn heartbeat_ttl_secs() -> u64 {
+    std::env::var("HEARTBEAT_TTL_SECS")
+        .unwrap_or_else(|_| "60".to_string())
+        .parse::<u64>()
+        .unwrap_or(60)
+        .max(HEARTBEAT_INTERVAL.as_secs() * 2)
+}
+
+// Services silent for longer than this are dropped from the registry
+fn forget_after_secs() -> i64 {
+    std::env::var("HEARTBEAT_FORGET_SECS")
+        .unwrap_or_else(|_| "86400".to_string())
+        .parse()
+        .unwrap_or(86400)
+}
+
+fn watchdog_enabled() -> bool {
+    std::env::var("HEARTBEAT_WATCHDOG_ENABLED")
+        .unwrap_or_else(|_| "false".to_string())
+        .parse()
+        .unwrap_or(false)
+}
+
+fn get_heartbeat_key(service: &str) -> String {
+    format!("heartbeat:{}", service)
+}
+
+// Marks an outage of a service as alerted, keyed by its last heartbeat
+fn get_outage_key(service: &str, last_seen: i64) -> String {
+    format!("heartbeat:alerted:{}:{}", service, last_seen)
+}
+
+// Name a monitor reports under; each shard beats separately so one dead shard is noticed
+pub fn service_name(base: &str, shard: &ShardConfig) -> String {
+    if shard.is_sharded() {
+        format!("{}#{}", base, shard.index)
+    } else {
+        base.to_string()
+    }
+}
+
+// Host and pid of this process
+pub fn instance_name() -> String {
+    format!(
+        "{}:{}",
+        std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string()),
+        std::process::id()
+    )
+}
+
+async fn beat(heartbeat: &Heartbeat) -> Result<()> {
+    let data = serde_json::to_string(heartbeat)?;
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = redis::pipe()
+        .set_ex(
+            get_heartbeat_key(&heartbeat.service),
+            &data,
+            heartbeat_ttl_secs(),
+        )
+        .hset(REGISTRY_KEY, &heartbeat.service, &data)
+        .query_async(&mut *con)
+        .await?;
+    Ok(())
+}
+
+// Keep this process's heartbeat fresh for as long as it runs
+pub fn spawn(service: String) -> JoinHandle<()> {
+    supervisor::spawn_supervised("heartbeat", async move {
+        let started_at = chrono::Utc::now().timestamp();
+        let instance = instance_name();
+        let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+        loop {
+            interval.tick().await;
+            let heartbeat = Heartbeat {
+                service: service.clone(),
+                instance: instance.clone(),
+                started_at,
+                timestamp: chrono::Utc::now().timestamp(),
+            };
+            if let Err(e) = beat(&heartbeat).await {
+                warn!("Failed to write heartbeat of {}: {}", service, e);
+            }
+        }
+    })
+}
+
+// Health of a registered service, given whether its heartbeat key still exists
+pub fn service_health(heartbeat: Heartbeat, alive: bool, now: i64) -> ServiceHealth {
+    ServiceHealth {
+        age_secs: (now - heartbeat.timestamp).max(0),
+        service: heartbeat.service,
+        instance: heartbeat.instance,
+        started_at: heartbeat.started_at,
+        last_seen: heartbeat.timestamp,
+        alive,
+    }
+}
+
+// Every service that sent a heartbeat recently, sorted by name
+pub async fn get_health() -> Result<HealthReport> {
+    let mut con = redis_helper::get_connection().await?;
+    let data: HashMap<String, String> = con.hgetall(REGISTRY_KEY).await?;
+    let now = chrono::Utc::now().timestamp();
+    let forget_after = forget_after_secs();
+
+    let mut heartbeats = Vec::new();
+    let mut forgotten = Vec::new();
+    for (service, d) in data {
+        match serde_json::from_str::<Heartbeat>(&d) {
+            Ok(heartbeat) if now - heartbeat.timestamp <= forget_after => {
+                heartbeats.push(heartbeat)
+            }
+            _ => forgotten.push(service),
+        }
+    }
+    if !forgotten.is_empty() {
+        let _: () = con.hdel(REGISTRY_KEY, &forgotten).await?;
+    }
+    heartbeats.sort_by(|a, b| a.service.cmp(&b.service));
+
+    let mut pipe = redis::pipe();
+    for heartbeat in &heartbeats {
+        pipe.exists(get_heartbeat_key(&heartbeat.service));
+    }
+    let alive: Vec<bool> = if heartbeats.is_empty() {
+        Vec::new()
+    } else {
+        pipe.query_async(&mut *con).await?
+    };
+
+    let services: Vec<ServiceHealth> = heartbeats
+        .into_iter()
+        .zip(alive)
+        .map(|(heartbeat, alive)| service_health(heartbeat, alive, now))
+        .collect();
+    Ok(HealthReport {
+        healthy: services.iter().all(|service| service.alive),
+        services,
+    })
+}
+
+// Claim the alert for an outage, so only one watchdog sends it
+async fn claim_outage(service: &ServiceHealth) -> Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    let claimed: Option<String> = redis::cmd("SET")
+        .arg(get_outage_key(&service.service, service.last_seen))
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(forget_after_secs().max(1))
+        .query_async(&mut *con)
+        .await?;
+    Ok(claimed.is_some())
+}
+
+async fn alert_outage(service: &ServiceHealth) -> Result<()> {
+    let alert = StrategyAlert {
+        mint: format!("service:{}", service.service),
+        strategy_name: "服务心跳超时".to_string(),
+        message: localized!(
+            Language::from_env(),
+            "服务 {} 已 {} 秒没有心跳（实例 {}）",
+            "Service {} has sent no heartbeat for {} seconds (instance {})",
+            service.service,
+            service.age_secs,
+            service.instance
+        ),
+        timestamp: chrono::Local::now().timestamp(),
+        severity: None,
+        params: None,
+        klines: Vec::new(),
+    };
+    NotificationManager::new()?
+        .send_one_shot_notification(&alert)
+        .await
+}
+
+// Alert once per outage when a service's heartbeat expires, if enabled.
+// Several processes may run the watchdog; each outage is alerted only once.
+pub fn spawn_watchdog() -> Option<JoinHandle<()>> {
+    if !watchdog_enabled() {
+        return None;
+    }
+    info!(
+        "🐕 Heartbeat watchdog enabled: alerting on services silent for {}s",
+        heartbeat_ttl_secs()
+    );
+    Some(supervisor::spawn_supervised("heartbeat_watchdog", async {
+        let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+        loop {
+            interval.tick().await;
+            let report = match get_health().await {
+                Ok(report) => report,
+                Err(e) => {
+                    warn!("Heartbeat watchdog failed to read heartbeats: {}", e);
+                    continue;
+                }
+            };
+            for service in report.services.iter().filter(|service| !service.alive) {
+                match claim_outage(service).await {
+                    Ok(true) => {
+                        warn!(
+                            "💔 Service {} has not sent a heartbeat for {}s",
+                            service.service, service.age_secs
+                        );
+                        if let Err(e) = alert_outage(service).await {
+                            warn!("Failed to alert outage of {}: {}", service.service, e);
+                        }
+                    }
+                    Ok(false) => {}
+                    Err(e) => warn!("Failed to claim outage of {}: {}", service.service, e),
+                }
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_health() {
+        assert_eq!(
+            service_name("monitor", &ShardConfig::new(0, 1).unwrap()),
+            "monitor"
+        );
+        assert_eq!(
+            service_name("monitor", &ShardConfig::new(2, 3).unwrap()),
+            "monitor#2"
+        );
+
+        let heartbeat = Heartbeat {
+            service: "web".to_string(),
+            instance: "host:1".to_string(),
+            started_at: 100,
+            timestamp: 160,
+        };
+        let health = service_health(heartbeat, false, 250);
+        assert_eq!(health.last_seen, 160);
+        assert_eq!(health.age_secs, 90);
+        assert!(!health.alive);
+        assert_eq!(
+            get_outage_key("monitor#2", 160),
+            "heartbeat:alerted:monitor#2:160"
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::kline::{KLineData, KLineManager};
+
+// Number of volume buckets, 1 = lowest volume, VOLUME_BUCKETS = highest
+const VOLUME_BUCKETS: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeatmapEntry {
+    pub mint: String,
+    pub complete: bool,
+    pub open: Decimal,
+    pub close: Decimal,
+    pub change_pct: Decimal,
+    pub volume_sol: Decimal,
+    pub net_flow_sol: Decimal,
+    pub volume_bucket: usize,
+    pub kline_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeatmapData {
+    pub window: String,
+    pub window_secs: i64,
+    pub generated_at: i64,
+    pub entries: Vec<HeatmapEntry>,
+}
+
+// Build heatmap entries for every active mint from candles within the window
+pub async fn compute_heatmap(
+    manager: &KLineManager,
+    window: &str,
+    window_secs: i64,
+) -> anyhow::Result<HeatmapData> {
+    let now = chrono::Utc::now().timestamp();
+    let since = now - window_secs;
+    let max_klines = (window_secs / 60 + 1) as usize;
+
+    // One scan for all mints instead of one scan per mint
+    let latest = manager.get_latest_klines(max_klines).await?;
+    let mut mint_klines: HashMap<String, Vec<KLineData>> = HashMap::new();
+    for (mint, kline) in latest {
+        if kline.timestamp >= since {
+            mint_klines.entry(mint).or_default().push(kline);
+        }
+    }
+
+    let completed: HashMap<String, bool> = manager
+        .get_active_mints()
+        .await?
+        .into_iter()
+        .map(|(mint, _, complete)| (mint, complete))
+        .collect();
+
+    let mut entries: Vec<HeatmapEntry> = mint_klines
+        .into_iter()
+        .filter_map(|(mint, klines)| {
+            let complete = completed.get(&mint).copied().unwrap_or(false);
+            build_entry(mint, complete, klines)
+        })
+        .collect();
+    assign_volume_buckets(&mut entries);
+
+    Ok(HeatmapData {
+        window: window.to_string(),
+        window_secs,
+        generated_at: now,
+        entries,
+    })
+}
+
+fn build_entry(mint: String, complete: bool, mut klines: Vec<KLineData>) -> Option<HeatmapEntry> {
+    klines.sort_by_key(|k| k.timestamp);
+    let open: Decimal = klines.first()?.open.parse().ok()?;
+    let close: Decimal = klines.last()?.close.parse().ok()?;
+    let change_pct = if open > Decimal::ZERO {
+        ((close - open) / open * Decimal::new(100, 0)).round_dp(2)
+    } else {
+        Decimal::ZERO
+    };
+    let volume_sol = klines
+        .iter()
+        .filter_map(|k| k.volume_sol.parse::<Decimal>().ok())
+        .sum();
+    let net_flow_sol = klines
+        .iter()
+        .filter_map(|k| k.net_flow_sol.parse::<Decimal>().ok())
+        .sum();
+
+    Some(HeatmapEntry {
+        mint,
+        complete,
+        open,
+        close,
+        change_pct,
+        volume_sol,
+        net_flow_sol,
+        volume_bucket: 0,
+        kline_count: klines.len(),
+    })
+}
+
+// Rank entries by volume and split them into equal-sized buckets,
+// sorting the result by volume (largest first) for rendering
+fn assign_volume_buckets(entries: &mut [HeatmapEntry]) {
+    entries.sort_by_key(|e| e.volume_sol);
+    let total = entries.len();
+    for (rank, entry) in entries.iter_mut().enumerate() {
+        entry.volume_bucket = rank * VOLUME_BUCKETS / total + 1;
+    }
+    entries.reverse();
+}
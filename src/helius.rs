@@ -0,0 +1,293 @@
+// Trade ingestion from Helius webhooks, for setups without a stable websocket
+// RPC. Enhanced and raw webhook transactions are turned into logsNotification-
+// shaped messages and go through the same parsers as the websocket monitors.
+
+use anyhow::Result;
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{HeaderMap, StatusCode, header::AUTHORIZATION},
+    routing::post,
+};
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::watch;
+use tracing::{debug, error, info, warn};
+
+use crate::admin;
+use crate::api_tokens::{self, TokenScope};
+use crate::block_feed;
+use crate::constant::{PUMP_AMM_PROGRAM, PUMP_PROGRAM};
+use crate::instance_lock;
+use crate::kline::KLineManager;
+use crate::monitor_control;
+use crate::pump;
+use crate::pump_amm;
+use crate::supervisor;
+use crate::websocket::SignatureDedup;
+
+#[derive(Clone)]
+struct WebhookState {
+    kline_manager: Arc<KLineManager>,
+    // Value Helius sends in the Authorization header (the webhook's authHeader)
+    auth: Option<Arc<String>>,
+    // Transactions already handled, as Helius redelivers a payload it got no answer for
+    dedup: Arc<SignatureDedup>,
+    // Paused states of the PUMP and AMM monitors from the control channel
+    pump_control: watch::Receiver<bool>,
+    amm_control: watch::Receiver<bool>,
+}
+
+// Log lines of an enhanced transaction's instructions of one program, rebuilt
+// from its self-CPI events like in block ingestion
+fn enhanced_logs(transaction: &Value, program: &str) -> Option<Vec<String>> {
+    let instructions = transaction.get("instructions")?.as_array()?;
+    let mut logs = Vec::new();
+    for instruction in instructions {
+        let nested = instruction
+            .get("innerInstructions")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for instruction in std::iter::once(instruction).chain(nested) {
+            if instruction.get("programId").and_then(Value::as_str) != Some(program) {
+                continue;
+            }
+            let data = instruction
+                .get("data")
+                .and_then(Value::as_str)
+                .and_then(|data| bs58::decode(data).into_vec().ok());
+            if let Some(data) = data {
+                block_feed::instruction_logs(program, &data, &mut logs);
+            }
+        }
+    }
+    logs.iter()
+        .any(|log| log.starts_with("Program data: "))
+        .then_some(logs)
+}
+
+// Signature of a raw or enhanced webhook transaction
+fn transaction_signature(transaction: &Value) -> Option<&str> {
+    transaction
+        .get("signature")
+        .or_else(|| transaction.pointer("/transaction/signatures/0"))
+        .and_then(Value::as_str)
+}
+
+// The payload's transactions that the dedup hasn't seen yet
+fn unseen_transactions(payload: &Value, dedup: &SignatureDedup) -> Value {
+    let transactions = payload.as_array().map(Vec::as_slice).unwrap_or_default();
+    Value::Array(
+        transactions
+            .iter()
+            .filter(|tx| dedup.first_seen(transaction_signature(tx).unwrap_or_default()))
+            .cloned()
+            .collect(),
+    )
+}
+
+// Messages of a webhook payload for one program. Raw webhooks carry the
+// getTransaction layout, enhanced ones Helius' parsed layout; failed
+// transactions are skipped.
+pub fn messages_from_webhook(payload: &Value, program: &str) -> Vec<Value> {
+    let Some(transactions) = payload.as_array() else {
+        return Vec::new();
+    };
+    transactions
+        .iter()
+        .filter_map(|tx| {
+            let slot = tx.get("slot").and_then(Value::as_u64).unwrap_or_default();
+            if tx.get("meta").is_some() {
+                return block_feed::transaction_message(tx, &[program], slot);
+            }
+            if !tx.get("transactionError").is_none_or(Value::is_null) {
+                return None;
+            }
+            let signature = tx.get("signature")?.as_str()?;
+            let logs = enhanced_logs(tx, program)?;
+            Some(block_feed::logs_notification(signature, slot, logs))
+        })
+        .collect()
+}
+
+async fn receive_webhook(
+    State(state): State<WebhookState>,
+    headers: HeaderMap,
+    Json(payload): Json<Value>,
+) -> StatusCode {
+    let provided = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    let authorized = match provided {
+        Some(value)
+            if state
+                .auth
+                .as_ref()
+                .is_some_and(|auth| admin::token_matches(auth, value)) =>
+        {
+            true
+        }
+        // Helius sends the authHeader verbatim, so the token may come with or without "Bearer "
+        Some(value) => {
+            let token = value.strip_prefix("Bearer ").unwrap_or(value);
+            api_tokens::authorize(token, TokenScope::Ingest).await
+        }
+        None => false,
+    };
+    if !authorized {
+        warn!("Rejected webhook request: bad Authorization header");
+        return StatusCode::UNAUTHORIZED;
+    }
+    // A standby leaves the trades to the lock holder
+    if instance_lock::is_read_only() {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    }
+
+    let pump_paused = *state.pump_control.borrow();
+    let amm_paused = *state.amm_control.borrow();
+    if pump_paused && amm_paused {
+        return StatusCode::OK;
+    }
+    let payload = unseen_transactions(&payload, &state.dedup);
+    let pump_messages = if pump_paused {
+        Vec::new()
+    } else {
+        messages_from_webhook(&payload, PUMP_PROGRAM)
+    };
+    let amm_messages = if amm_paused {
+        Vec::new()
+    } else {
+        messages_from_webhook(&payload, PUMP_AMM_PROGRAM)
+    };
+    for message in pump_messages {
+        if let Err(e) = pump::handle_pump_message(&message, Arc::clone(&state.kline_manager)).await
+        {
+            debug!("Webhook Pump message handling failed: {}", e);
+        }
+    }
+    for message in amm_messages {
+        if let Err(e) =
+            pump_amm::handle_amm_message(&message, Arc::clone(&state.kline_manager)).await
+        {
+            debug!("Webhook AMM message handling failed: {}", e);
+        }
+    }
+    StatusCode::OK
+}
+
+// Serve POST /webhooks/helius and run the idle cleanup the monitors would run.
+// Requests are ignored while the PUMP or AMM monitor is paused through the
+// admin API, for that program, and while this instance is a standby.
+pub async fn start_webhook_server(kline_manager: Arc<KLineManager>, port: u16) -> Result<()> {
+    let auth = std::env::var("HELIUS_WEBHOOK_AUTH")
+        .ok()
+        .filter(|auth| !auth.trim().is_empty());
+    if auth.is_none() {
+        info!("HELIUS_WEBHOOK_AUTH unset, only API tokens with the ingest scope are accepted");
+    }
+
+    let cleanup_manager = Arc::clone(&kline_manager);
+    supervisor::spawn_supervised("kline_cleanup", async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+        loop {
+            interval.tick().await;
+            if instance_lock::is_read_only() {
+                continue;
+            }
+            if let Err(e) = cleanup_manager.cleanup_idle_klines().await {
+                error!("Webhook K-line cleanup failed: {}", e);
+            }
+        }
+    });
+
+    let app = Router::new()
+        .route("/webhooks/helius", post(receive_webhook))
+        .with_state(WebhookState {
+            kline_manager,
+            auth: auth.map(Arc::new),
+            dedup: Arc::new(SignatureDedup::from_env()),
+            pump_control: monitor_control::spawn_listener("PUMP").await?,
+            amm_control: monitor_control::spawn_listener("AMM").await?,
+        });
+    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
+    info!("Webhook ingestion listening on http://0.0.0.0:{}", port);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant::{ANCHOR_EVENT_IX_TAG, SELL_IX_DISCRIMINATOR};
+    use serde_json::json;
+
+    #[test]
+    fn test_messages_from_webhook() {
+        let sell = bs58::encode(SELL_IX_DISCRIMINATOR).into_string();
+        let mut event = ANCHOR_EVENT_IX_TAG.to_vec();
+        event.extend_from_slice(&[1, 2, 3]);
+        let event = bs58::encode(event).into_string();
+
+        let payload = json!([
+            {
+                "signature": "enhanced",
+                "slot": 7,
+                "transactionError": null,
+                "instructions": [{
+                    "programId": PUMP_PROGRAM,
+                    "data": sell,
+                    "innerInstructions": [
+                        { "programId": "11111111111111111111111111111111", "data": "" },
+                        { "programId": PUMP_PROGRAM, "data": event }
+                    ]
+                }]
+            },
+            {
+                "signature": "failed",
+                "slot": 8,
+                "transactionError": { "InstructionError": [0, "Custom"] },
+                "instructions": [{ "programId": PUMP_PROGRAM, "data": event }]
+            },
+            {
+                "slot": 9,
+                "transaction": { "signatures": ["raw"], "message": { "accountKeys": [], "instructions": [] } },
+                "meta": { "err": null, "logMessages": ["Program data: AQID"] }
+            }
+        ]);
+
+        let messages = messages_from_webhook(&payload, PUMP_PROGRAM);
+        assert_eq!(messages.len(), 2);
+        let value = &messages[0]["params"]["result"]["value"];
+        assert_eq!(value["signature"], "enhanced");
+        assert_eq!(messages[0]["params"]["result"]["context"]["slot"], 7);
+        assert_eq!(
+            value["logs"],
+            json!([
+                format!("Program {} invoke [1]", PUMP_PROGRAM),
+                "Program log: Instruction: Sell",
+                "Program data: AQID"
+            ])
+        );
+        assert_eq!(messages[1]["params"]["result"]["value"]["signature"], "raw");
+        // Raw transactions keep their logs for every program, the parsers sort them out
+        assert_eq!(messages_from_webhook(&payload, PUMP_AMM_PROGRAM).len(), 1);
+
+        // A redelivered payload is only handled once
+        let dedup = SignatureDedup::new(10);
+        assert_eq!(
+            unseen_transactions(&payload, &dedup)
+                .as_array()
+                .unwrap()
+                .len(),
+            3
+        );
+        assert!(
+            unseen_transactions(&payload, &dedup)
+                .as_array()
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(transaction_signature(&payload[2]), Some("raw"));
+    }
+}
@@ -0,0 +1,222 @@
+use anyhow::Result;
+use base64::Engine;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use solana_client::rpc_request::RpcRequest;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::constant::TOKEN_2022_PROGRAM;
+use crate::kline::KLineManager;
+use crate::token_info::{self, TokenProgram};
+use crate::{get_rpc_client_with_retry, redis_helper, supervisor};
+
+// Offset of the amount in the token account layout: mint (32) | owner (32) | amount (8)
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+
+// Size of a plain SPL token account, Token-2022 accounts can be longer
+const TOKEN_ACCOUNT_LEN: usize = 165;
+
+/// 某一时刻的持有人数量
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HolderSnapshot {
+    pub timestamp: i64,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct HolderTrackerConfig {
+    // Seconds between snapshots, 0 disables tracking
+    pub interval_secs: u64,
+    // Most recently active mints snapshotted each round (getProgramAccounts is expensive)
+    pub max_mints: usize,
+    // Snapshots older than this are dropped
+    pub retention_secs: i64,
+}
+
+impl HolderTrackerConfig {
+    pub fn from_env() -> Self {
+        Self {
+            interval_secs: std::env::var("HOLDERS_SNAPSHOT_INTERVAL_SECS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0),
+            max_mints: std::env::var("HOLDERS_MAX_MINTS")
+                .unwrap_or_else(|_| "20".to_string())
+                .parse()
+                .unwrap_or(20),
+            retention_secs: std::env::var("HOLDERS_RETENTION_SECS")
+                .unwrap_or_else(|_| "7200".to_string())
+                .parse()
+                .unwrap_or(7200),
+        }
+    }
+}
+
+// Generate Redis key for the holder snapshots of a mint (ZSET scored by time)
+fn get_holders_key(mint: &str) -> String {
+    format!("holders:{}", mint)
+}
+
+// Count token accounts of a mint holding a non-zero balance. Only the amount
+// of each account is fetched.
+pub async fn count_holders(mint: &str) -> Result<u64> {
+    let program = token_info::get_mint_info_cached(mint).await?.program;
+    let mut filters = vec![json!({ "memcmp": { "offset": 0, "bytes": mint } })];
+    let program_id = match program {
+        TokenProgram::Spl => {
+            filters.push(json!({ "dataSize": TOKEN_ACCOUNT_LEN }));
+            spl_token::id().to_string()
+        }
+        TokenProgram::Token2022 => TOKEN_2022_PROGRAM.to_string(),
+    };
+    let params = json!([program_id, {
+        "encoding": "base64",
+        "commitment": "confirmed",
+        "dataSlice": { "offset": TOKEN_ACCOUNT_AMOUNT_OFFSET, "length": 8 },
+        "filters": filters,
+    }]);
+    let accounts: Vec<Value> = get_rpc_client_with_retry(
+        |client| {
+            client
+                .send(RpcRequest::GetProgramAccounts, params.clone())
+                .map_err(anyhow::Error::from)
+        },
+        2,
+    )
+    .await?;
+    Ok(accounts
+        .iter()
+        .filter_map(|a| a["account"]["data"][0].as_str())
+        .filter_map(|data| base64::engine::general_purpose::STANDARD.decode(data).ok())
+        .filter(|amount| amount.iter().any(|b| *b != 0))
+        .count() as u64)
+}
+
+// Store a snapshot and drop the ones past the retention
+pub async fn record_snapshot(
+    mint: &str,
+    snapshot: HolderSnapshot,
+    retention_secs: i64,
+) -> Result<()> {
+    let key = get_holders_key(mint);
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = con
+        .zadd(
+            &key,
+            format!("{}:{}", snapshot.timestamp, snapshot.count),
+            snapshot.timestamp,
+        )
+        .await?;
+    let _: () = con
+        .zrembyscore(&key, "-inf", snapshot.timestamp - retention_secs)
+        .await?;
+    let _: () = con.expire(&key, retention_secs).await?;
+    Ok(())
+}
+
+// Snapshots of a mint taken at or after `since`, oldest first
+pub async fn get_snapshots(mint: &str, since: i64) -> Result<Vec<HolderSnapshot>> {
+    let mut con = redis_helper::get_connection().await?;
+    let members: Vec<String> = con
+        .zrangebyscore(get_holders_key(mint), since, "+inf")
+        .await?;
+    Ok(members
+        .iter()
+        .filter_map(|member| {
+            let (timestamp, count) = member.split_once(':')?;
+            Some(HolderSnapshot {
+                timestamp: timestamp.parse().ok()?,
+                count: count.parse().ok()?,
+            })
+        })
+        .collect())
+}
+
+// Holder growth per minute between the first and last snapshot. None with fewer
+// than two snapshots or when they are less than a minute apart.
+pub fn growth_per_minute(snapshots: &[HolderSnapshot]) -> Option<f64> {
+    let (first, last) = (snapshots.first()?, snapshots.last()?);
+    let elapsed = last.timestamp - first.timestamp;
+    if elapsed < 60 {
+        return None;
+    }
+    Some((last.count as f64 - first.count as f64) / (elapsed as f64 / 60.0))
+}
+
+// Periodically snapshot the holder count of the most recently active mints
+pub fn spawn_snapshot_task(
+    kline_manager: Arc<KLineManager>,
+) -> Option<tokio::task::JoinHandle<()>> {
+    let config = HolderTrackerConfig::from_env();
+    if config.interval_secs == 0 {
+        return None;
+    }
+
+    info!(
+        "👥 Holder tracking enabled: every {}s for the {} most active mints",
+        config.interval_secs, config.max_mints
+    );
+
+    Some(supervisor::spawn_supervised(
+        "holder_snapshots",
+        async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+            loop {
+                interval.tick().await;
+                let mut active = match kline_manager.get_active_mints().await {
+                    Ok(active) => active,
+                    Err(e) => {
+                        warn!("Failed to list active mints for holder snapshots: {}", e);
+                        continue;
+                    }
+                };
+                active.sort_by_key(|(_, last_activity, _)| std::cmp::Reverse(*last_activity));
+
+                let now = chrono::Utc::now().timestamp();
+                for (mint, _, _) in active.into_iter().take(config.max_mints) {
+                    match count_holders(&mint).await {
+                        Ok(count) => {
+                            debug!("👥 {} has {} holders", mint, count);
+                            let snapshot = HolderSnapshot {
+                                timestamp: now,
+                                count,
+                            };
+                            if let Err(e) =
+                                record_snapshot(&mint, snapshot, config.retention_secs).await
+                            {
+                                warn!("Failed to record holder snapshot of {}: {}", mint, e);
+                            }
+                        }
+                        Err(e) => warn!("Failed to count holders of {}: {}", mint, e),
+                    }
+                }
+            }
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_growth_per_minute() {
+        let snapshot = |timestamp, count| HolderSnapshot { timestamp, count };
+        assert_eq!(
+            growth_per_minute(&[snapshot(0, 100), snapshot(60, 110), snapshot(300, 150)]),
+            Some(10.0)
+        );
+        assert_eq!(
+            growth_per_minute(&[snapshot(0, 100), snapshot(120, 90)]),
+            Some(-5.0)
+        );
+        assert_eq!(
+            growth_per_minute(&[snapshot(0, 100), snapshot(30, 150)]),
+            None
+        );
+        assert_eq!(growth_per_minute(&[snapshot(0, 100)]), None);
+    }
+}
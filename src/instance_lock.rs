@@ -0,0 +1,275 @@
+// Instance locks keep two copies of the same monitor from ingesting into one
+// Redis and counting every trade twice. Each monitor, and each shard of a
+// sharded monitor, holds a lock key with a TTL that it refreshes while it runs.
+// A second copy either refuses to start or runs read-only as a standby: it
+// decodes nothing and writes nothing, and takes the lock over once the
+// holder's lock expires. A process running several monitors holds all of their
+// locks, or none of them while it is a standby.
+
+use anyhow::Result;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::{redis_helper, supervisor};
+
+// How often the holder refreshes its lock and a standby tries to take it over
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+// Extend the lock only while this instance still holds it
+const REFRESH_SCRIPT: &str = "if redis.call('GET', KEYS[1]) == ARGV[1] then \
+     return redis.call('EXPIRE', KEYS[1], ARGV[2]) else return 0 end";
+
+// Delete the lock only while this instance still holds it
+const RELEASE_SCRIPT: &str = "if redis.call('GET', KEYS[1]) == ARGV[1] then \
+     return redis.call('DEL', KEYS[1]) else return 0 end";
+
+// Set while this process is a standby that must not write trades
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+// Lock keys of this process and the instance name they hold, set by `init`
+static LOCKS: OnceLock<(Vec<String>, String)> = OnceLock::new();
+
+// Set on shutdown so the keeper stops taking the locks again
+static RELEASED: AtomicBool = AtomicBool::new(false);
+
+// What a monitor does when another instance already holds its lock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    // Exit with an error naming the running instance
+    Refuse,
+    // Start as a read-only standby
+    ReadOnly,
+    // Don't take a lock at all, for intentional duplicate setups
+    Off,
+}
+
+impl LockMode {
+    pub fn parse(mode: &str) -> Result<Self> {
+        match mode.trim().to_ascii_lowercase().as_str() {
+            "" | "refuse" => Ok(Self::Refuse),
+            "read-only" | "readonly" => Ok(Self::ReadOnly),
+            "off" => Ok(Self::Off),
+            other => anyhow::bail!(
+                "Unknown INSTANCE_LOCK_MODE {:?} (expected refuse, read-only or off)",
+                other
+            ),
+        }
+    }
+
+    pub fn from_env() -> Result<Self> {
+        Self::parse(&std::env::var("INSTANCE_LOCK_MODE").unwrap_or_default())
+    }
+}
+
+// Seconds a lock outlives its last refresh, so a crashed holder is replaced after this long
+fn lock_ttl_secs() -> u64 {
+    std::env::var("INSTANCE_LOCK_TTL_SECS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse::<u64>()
+        .unwrap_or(30)
+        .max(REFRESH_INTERVAL.as_secs() * 2)
+}
+
+pub fn get_lock_key(service: &str) -> String {
+    format!("instance:lock:{}", service)
+}
+
+// Host and pid plus a random suffix, so a restarted process with a reused pid
+// doesn't mistake the old lock for its own
+fn instance_name() -> String {
+    format!(
+        "{}:{}:{:08x}",
+        std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string()),
+        std::process::id(),
+        rand::random::<u32>()
+    )
+}
+
+// Whether trades must be skipped because another instance holds the lock
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+async fn acquire(key: &str, instance: &str, ttl: u64) -> Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    let acquired: Option<String> = redis::cmd("SET")
+        .arg(key)
+        .arg(instance)
+        .arg("NX")
+        .arg("EX")
+        .arg(ttl)
+        .query_async(&mut *con)
+        .await?;
+    Ok(acquired.is_some())
+}
+
+async fn refresh(key: &str, instance: &str, ttl: u64) -> Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    let refreshed: i64 = redis::cmd("EVAL")
+        .arg(REFRESH_SCRIPT)
+        .arg(1)
+        .arg(key)
+        .arg(instance)
+        .arg(ttl)
+        .query_async(&mut *con)
+        .await?;
+    Ok(refreshed == 1)
+}
+
+async fn release(key: &str, instance: &str) -> Result<()> {
+    let mut con = redis_helper::get_connection().await?;
+    let _: i64 = redis::cmd("EVAL")
+        .arg(RELEASE_SCRIPT)
+        .arg(1)
+        .arg(key)
+        .arg(instance)
+        .query_async(&mut *con)
+        .await?;
+    Ok(())
+}
+
+async fn release_all(keys: &[String], instance: &str) {
+    for key in keys {
+        if let Err(e) = release(key, instance).await {
+            warn!("Failed to release the instance lock {}: {}", key, e);
+        }
+    }
+}
+
+// Take every lock or none: the key already held by another instance is
+// returned after releasing the ones just taken
+async fn acquire_all(keys: &[String], instance: &str, ttl: u64) -> Result<Option<String>> {
+    for (i, key) in keys.iter().enumerate() {
+        if !acquire(key, instance, ttl).await? {
+            release_all(&keys[..i], instance).await;
+            return Ok(Some(key.clone()));
+        }
+    }
+    Ok(None)
+}
+
+// Refresh every lock; false after one was lost, with the others released
+async fn refresh_all(keys: &[String], instance: &str, ttl: u64) -> Result<bool> {
+    for key in keys {
+        // A lock that expired during a Redis outage is taken again if still free
+        let held = match refresh(key, instance, ttl).await? {
+            true => true,
+            false => acquire(key, instance, ttl).await?,
+        };
+        if !held {
+            release_all(keys, instance).await;
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+async fn holder(key: &str) -> Result<Option<String>> {
+    let mut con = redis_helper::get_connection().await?;
+    Ok(redis::cmd("GET").arg(key).query_async(&mut *con).await?)
+}
+
+// Take the instance locks of the services a process runs, such as "monitor" or
+// "monitor-amm#1", or deal with the running holder as INSTANCE_LOCK_MODE says.
+// The locks are then kept in the background: the holder refreshes them, and a
+// standby, or a holder that lost one, keeps trying to take them all.
+pub async fn init(services: &[String]) -> Result<()> {
+    let service = services.join(", ");
+    let mode = LockMode::from_env()?;
+    if mode == LockMode::Off {
+        info!("Instance lock of {} disabled", service);
+        return Ok(());
+    }
+
+    let keys: Vec<String> = services.iter().map(|s| get_lock_key(s)).collect();
+    let instance = instance_name();
+    let ttl = lock_ttl_secs();
+    if let Some(key) = acquire_all(&keys, &instance, ttl).await? {
+        let running = holder(&key)
+            .await?
+            .unwrap_or_else(|| "an instance whose lock just expired".to_string());
+        let service = key.trim_start_matches("instance:lock:");
+        if mode == LockMode::Refuse {
+            anyhow::bail!(
+                "{} is already running against this Redis ({}). Stop it first, set \
+                 INSTANCE_LOCK_MODE=read-only to start a standby, or INSTANCE_LOCK_MODE=off \
+                 to run both on purpose",
+                service,
+                running
+            );
+        }
+        READ_ONLY.store(true, Ordering::Relaxed);
+        warn!(
+            "🔒 {} is already running ({}), starting read-only until its lock expires",
+            service, running
+        );
+    } else {
+        info!(
+            "🔒 Holding the instance lock of {} as {}",
+            service, instance
+        );
+    }
+
+    let _ = LOCKS.set((keys.clone(), instance.clone()));
+    supervisor::spawn_supervised("instance_lock", async move {
+        loop {
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+            if RELEASED.load(Ordering::Relaxed) {
+                break;
+            }
+            let held = if is_read_only() {
+                acquire_all(&keys, &instance, ttl)
+                    .await
+                    .map(|blocked| blocked.is_none())
+            } else {
+                refresh_all(&keys, &instance, ttl).await
+            };
+            match (is_read_only(), held) {
+                (true, Ok(true)) => {
+                    READ_ONLY.store(false, Ordering::Relaxed);
+                    info!(
+                        "🔓 Took over the instance lock of {}, writing trades",
+                        service
+                    );
+                }
+                (false, Ok(false)) => {
+                    READ_ONLY.store(true, Ordering::Relaxed);
+                    error!(
+                        "Lost the instance lock of {} to another instance, switching to read-only",
+                        service
+                    );
+                }
+                (_, Err(e)) => warn!("Failed to refresh the instance lock of {}: {}", service, e),
+                _ => {}
+            }
+        }
+    });
+    Ok(())
+}
+
+// Give up the locks on shutdown, so a restarted monitor or a standby can take
+// them at once instead of waiting for them to expire. Writes stop from here on.
+pub async fn shutdown() {
+    RELEASED.store(true, Ordering::Relaxed);
+    READ_ONLY.store(true, Ordering::Relaxed);
+    if let Some((keys, instance)) = LOCKS.get() {
+        release_all(keys, instance).await;
+        info!("🔓 Released the instance locks {}", keys.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_mode() {
+        assert_eq!(LockMode::parse("").unwrap(), LockMode::Refuse);
+        assert_eq!(LockMode::parse("Read-Only").unwrap(), LockMode::ReadOnly);
+        assert_eq!(LockMode::parse("off").unwrap(), LockMode::Off);
+        assert!(LockMode::parse("standby").is_err());
+        assert_eq!(get_lock_key("monitor-amm#1"), "instance:lock:monitor-amm#1");
+    }
+}
@@ -1,32 +1,470 @@
+use crate::annotations;
+use crate::anomaly;
+pub use crate::api::{CandleGap, KLineData};
+use crate::candle_hub::CandleUpdate;
+use crate::candle_preagg::PendingCandles;
+use crate::clock::{self, SharedClock};
+use crate::kline_store::{self, CandleEvent, KLineStore, MINT_COMPLETE_KEY, MINT_VOLUME_KEY};
+use crate::metrics;
 use crate::redis_helper;
+use crate::trades::{TradeRecord, TradeSource};
 use chrono::{Local, TimeZone, Timelike};
 use redis::AsyncCommands;
 use rust_decimal::Decimal;
-use serde::{Deserialize, Serialize};
-use std::time::Duration;
-use tracing::{info, warn};
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct KLineData {
-    pub timestamp: i64,       // Timestamp representing the start time of this K-line
-    pub open: String,         // Opening price (stored as String for Decimal)
-    pub high: String,         // Highest price
-    pub low: String,          // Lowest price
-    pub close: String,        // Closing price
-    pub volume_sol: String,   // Trading volume (SOL)
-    pub volume_token: String, // Trading volume (Token)
-    pub net_flow_sol: String, // Net flow (buy - sell) in SOL
-    pub last_update: u64,     // Last update timestamp (seconds)
+use serde::Serialize;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{Instrument, info, info_span, instrument, warn};
+
+// A minute candle just written to the store, with what publishing it needs
+struct StoredCandle {
+    kline: KLineData,
+    // The stored candle before the merge, None when the merge opened it
+    before: Option<KLineData>,
+    opened: bool,
+    complete: bool,
+    sol_volume: Decimal,
+}
+
+// Storage the idle cleanup would reclaim for one mint
+#[derive(Debug, Clone, Serialize)]
+pub struct CompactionEntry {
+    pub mint: String,
+    pub idle_secs: u64,
+    pub keys: usize,
+    pub bytes: u64,
+}
+
+// What a compaction run would reclaim, largest mints first
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CompactionReport {
+    pub entries: Vec<CompactionEntry>,
+    pub total_keys: usize,
+    pub total_bytes: u64,
+}
+
+impl CompactionReport {
+    pub fn new(mut entries: Vec<CompactionEntry>) -> Self {
+        entries.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.mint.cmp(&b.mint)));
+        Self {
+            total_keys: entries.iter().map(|e| e.keys).sum(),
+            total_bytes: entries.iter().map(|e| e.bytes).sum(),
+            entries,
+        }
+    }
+}
+
+// Reject trades that would corrupt a candle
+fn validate_trade(trade: &TradeRecord) -> Result<(), &'static str> {
+    if trade.price <= Decimal::ZERO {
+        return Err("non_positive_price");
+    }
+    if trade.sol_amount < Decimal::ZERO {
+        return Err("negative_sol_volume");
+    }
+    if trade.token_amount < Decimal::ZERO {
+        return Err("negative_token_volume");
+    }
+    Ok(())
+}
+
+// Merges consecutive minute candles (oldest first) into candles of a longer
+// interval aligned to multiples of it. Emits each candle once the next one starts.
+pub struct KLineAggregator {
+    interval_secs: i64,
+    current: Option<KLineData>,
+}
+
+impl KLineAggregator {
+    pub fn new(interval_secs: i64) -> Self {
+        Self {
+            interval_secs: interval_secs.max(60),
+            current: None,
+        }
+    }
+
+    // Add a minute candle, returning the previous interval's candle once it is complete
+    pub fn push(&mut self, kline: KLineData) -> Option<KLineData> {
+        let bucket = kline.timestamp - kline.timestamp.rem_euclid(self.interval_secs);
+        match self.current.as_mut() {
+            Some(current) if current.timestamp == bucket => {
+                merge_kline(current, &kline);
+                None
+            }
+            _ => {
+                let mut started = kline;
+                started.timestamp = bucket;
+                self.current.replace(started)
+            }
+        }
+    }
+
+    // The last, possibly partial, candle
+    pub fn finish(self) -> Option<KLineData> {
+        self.current
+    }
+}
+
+// Higher timeframes (seconds) that minute candles are rolled up into on write,
+// from KLINE_ROLLUP_INTERVALS. Only whole minutes above one minute are kept.
+// Parsed once, since every trade write needs them.
+pub fn rollup_intervals() -> &'static [i64] {
+    static INTERVALS: LazyLock<Vec<i64>> = LazyLock::new(|| {
+        let mut intervals: Vec<i64> = std::env::var("KLINE_ROLLUP_INTERVALS")
+            .unwrap_or_else(|_| "5m,15m,1h,4h".to_string())
+            .split(',')
+            .filter_map(crate::parse_window)
+            .filter(|secs| *secs > 60 && secs % 60 == 0)
+            .collect();
+        intervals.sort();
+        intervals.dedup();
+        intervals
+    });
+    &INTERVALS
+}
+
+// Short name of an interval as used in Redis keys, e.g. "5m", "1h" or "1d"
+pub fn interval_label(interval_secs: i64) -> String {
+    if interval_secs % 86400 == 0 {
+        format!("{}d", interval_secs / 86400)
+    } else if interval_secs % 3600 == 0 {
+        format!("{}h", interval_secs / 3600)
+    } else {
+        format!("{}m", interval_secs / 60)
+    }
+}
+
+// Gaps longer than this many candles are reported but never filled
+const MAX_FILL_CANDLES: i64 = 1440;
+
+// How the candle API treats intervals without trades between stored candles
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum GapMode {
+    // Return stored candles only, leaving charts to connect across gaps
+    #[default]
+    None,
+    // Also list the missing ranges
+    Markers,
+    // Insert flat zero-volume candles at the previous close, and list them
+    Fill,
+}
+
+impl std::str::FromStr for GapMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(GapMode::None),
+            "markers" => Ok(GapMode::Markers),
+            "fill" => Ok(GapMode::Fill),
+            _ => Err(anyhow::anyhow!(
+                "Invalid gaps mode: {} (expected none, markers or fill)",
+                s
+            )),
+        }
+    }
+}
+
+// Missing candles between consecutive candles (oldest first) of an interval
+pub fn find_gaps(klines: &[KLineData], interval_secs: i64) -> Vec<CandleGap> {
+    klines
+        .windows(2)
+        .filter_map(|pair| {
+            let candles = (pair[1].timestamp - pair[0].timestamp) / interval_secs - 1;
+            (candles > 0).then(|| CandleGap {
+                from: pair[0].timestamp + interval_secs,
+                to: pair[1].timestamp - interval_secs,
+                candles,
+                filled: false,
+            })
+        })
+        .collect()
+}
+
+// Flat candle at the previous close for an interval without trades
+fn gap_kline(previous: &KLineData, timestamp: i64) -> KLineData {
+    KLineData {
+        timestamp,
+        open: previous.close.clone(),
+        high: previous.close.clone(),
+        low: previous.close.clone(),
+        close: previous.close.clone(),
+        volume_sol: "0".to_string(),
+        volume_token: "0".to_string(),
+        net_flow_sol: "0".to_string(),
+        last_update: previous.last_update,
+        source: previous.source,
+        liquidity_sol: previous.liquidity_sol.clone(),
+        turnover: None,
+        anomaly_score: None,
+        close_time: None,
+    }
+}
+
+// Fill the gaps between candles (oldest first) with flat candles, returning
+// the filled series and its gaps. Gaps over MAX_FILL_CANDLES stay unfilled.
+pub fn fill_gaps(klines: Vec<KLineData>, interval_secs: i64) -> (Vec<KLineData>, Vec<CandleGap>) {
+    let mut gaps = find_gaps(&klines, interval_secs);
+    let mut filled = Vec::with_capacity(klines.len());
+    let mut pending = gaps.iter_mut().peekable();
+    for kline in klines {
+        if let Some(gap) = pending.next_if(|gap| gap.to < kline.timestamp)
+            && gap.candles <= MAX_FILL_CANDLES
+            && let Some(previous) = filled.last().cloned()
+        {
+            filled.extend(
+                (0..gap.candles).map(|i| gap_kline(&previous, gap.from + i * interval_secs)),
+            );
+            gap.filled = true;
+        }
+        filled.push(kline);
+    }
+    (filled, gaps)
+}
+
+// A one-trade candle for the minute starting at `minute_ts`
+pub fn trade_kline(trade: &TradeRecord, minute_ts: i64, now: u64) -> KLineData {
+    let price = trade.price.to_string();
+    let net_flow = if trade.is_buy {
+        trade.sol_amount
+    } else {
+        -trade.sol_amount
+    };
+    let mut kline = KLineData {
+        timestamp: minute_ts,
+        open: price.clone(),
+        high: price.clone(),
+        low: price.clone(),
+        close: price,
+        volume_sol: trade.sol_amount.to_string(),
+        volume_token: trade.token_amount.to_string(),
+        net_flow_sol: net_flow.to_string(),
+        last_update: now,
+        source: trade.source,
+        liquidity_sol: None,
+        turnover: None,
+        anomaly_score: anomaly::score_trade(trade),
+        close_time: Some(trade.timestamp),
+    };
+    update_turnover(&mut kline, trade.liquidity_sol);
+    kline
+}
+
+// Fold a candle into an aggregated one. The close is taken from whichever has
+// the later close time, so trades or minutes arriving out of order don't move it.
+pub fn merge_kline(into: &mut KLineData, next: &KLineData) {
+    let decimal = |value: &str| value.parse::<Decimal>().unwrap_or(Decimal::ZERO);
+    if decimal(&next.high) > decimal(&into.high) {
+        into.high = next.high.clone();
+    }
+    if decimal(&next.low) < decimal(&into.low) {
+        into.low = next.low.clone();
+    }
+    if next.close_time >= into.close_time {
+        into.close = next.close.clone();
+        into.close_time = next.close_time;
+    }
+    into.volume_sol = (decimal(&into.volume_sol) + decimal(&next.volume_sol)).to_string();
+    into.volume_token = (decimal(&into.volume_token) + decimal(&next.volume_token)).to_string();
+    into.net_flow_sol = (decimal(&into.net_flow_sol) + decimal(&next.net_flow_sol)).to_string();
+    into.last_update = into.last_update.max(next.last_update);
+    if next.source.is_some() {
+        into.source = next.source;
+    }
+    into.anomaly_score = max_score(into.anomaly_score, next.anomaly_score);
+    let liquidity = next
+        .liquidity_sol
+        .as_deref()
+        .and_then(|l| l.parse::<Decimal>().ok());
+    update_turnover(into, liquidity);
+}
+
+// Fold a candle built from new trades into the stored candle of a rolled-up
+// interval starting at `bucket`, or start that candle
+fn merge_rollup(
+    delta: &KLineData,
+    bucket: i64,
+    stored: Option<KLineData>,
+    current_time: u64,
+) -> KLineData {
+    let mut rollup = match stored {
+        Some(mut rollup) => {
+            merge_kline(&mut rollup, delta);
+            rollup
+        }
+        None => KLineData {
+            timestamp: bucket,
+            ..delta.clone()
+        },
+    };
+    rollup.last_update = current_time;
+    rollup
+}
+
+// Largest relative move, in percent, of a candle's high, low or close between two versions
+fn ohlc_change_pct(before: &KLineData, after: &KLineData) -> Decimal {
+    [
+        (&before.high, &after.high),
+        (&before.low, &after.low),
+        (&before.close, &after.close),
+    ]
+    .into_iter()
+    .filter_map(|(before, after)| {
+        let before: Decimal = before.parse().ok()?;
+        let after: Decimal = after.parse().ok()?;
+        (before > Decimal::ZERO).then(|| ((after - before) / before).abs() * Decimal::ONE_HUNDRED)
+    })
+    .max()
+    .unwrap_or_default()
+}
+
+// Price move (%) a late trade must cause on a closed candle to re-emit its close event
+fn correction_min_pct() -> Decimal {
+    std::env::var("CANDLE_CORRECTION_MIN_PCT")
+        .unwrap_or_else(|_| "1".to_string())
+        .parse()
+        .unwrap_or(Decimal::ONE)
+}
+
+fn max_score(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MintActivity {
-    pub timestamp: u64,
-    pub complete: bool,
+// Record the pool liquidity snapshot of a trade, if any, and recompute the
+// candle's turnover against the latest known liquidity
+fn update_turnover(kline: &mut KLineData, liquidity_sol: Option<Decimal>) {
+    if let Some(liquidity) = liquidity_sol.filter(|l| *l > Decimal::ZERO) {
+        kline.liquidity_sol = Some(liquidity.to_string());
+    }
+    let liquidity = kline
+        .liquidity_sol
+        .as_deref()
+        .and_then(|l| l.parse::<Decimal>().ok())
+        .filter(|l| *l > Decimal::ZERO);
+    let volume = kline.volume_sol.parse::<Decimal>().ok();
+    if let (Some(liquidity), Some(volume)) = (liquidity, volume) {
+        kline.turnover = Some((volume / liquidity).round_dp(6).normalize().to_string());
+    }
+}
+
+// Repair a stored candle before updating it, returning the reasons for each repair.
+// Prices fall back to the incoming trade price, volumes to zero, and high/low are
+// widened so they always bound open and close.
+fn repair_kline(kline: &mut KLineData, price: Decimal) -> Vec<&'static str> {
+    let mut repairs = Vec::new();
+    let positive = |value: &str| value.parse::<Decimal>().ok().filter(|v| *v > Decimal::ZERO);
+    let non_negative = |value: &str| {
+        value
+            .parse::<Decimal>()
+            .ok()
+            .filter(|v| *v >= Decimal::ZERO)
+    };
+
+    let open = positive(&kline.open).unwrap_or_else(|| {
+        repairs.push("invalid_open");
+        price
+    });
+    let close = positive(&kline.close).unwrap_or_else(|| {
+        repairs.push("invalid_close");
+        price
+    });
+    let mut low = positive(&kline.low).unwrap_or_else(|| {
+        repairs.push("invalid_low");
+        open.min(close)
+    });
+    let mut high = positive(&kline.high).unwrap_or_else(|| {
+        repairs.push("invalid_high");
+        open.max(close)
+    });
+    if low > open.min(close) {
+        repairs.push("low_above_body");
+        low = open.min(close);
+    }
+    if high < open.max(close) {
+        repairs.push("high_below_body");
+        high = open.max(close);
+    }
+    let volume_sol = non_negative(&kline.volume_sol).unwrap_or_else(|| {
+        repairs.push("invalid_volume_sol");
+        Decimal::ZERO
+    });
+    let volume_token = non_negative(&kline.volume_token).unwrap_or_else(|| {
+        repairs.push("invalid_volume_token");
+        Decimal::ZERO
+    });
+    let net_flow_sol = kline.net_flow_sol.parse::<Decimal>().unwrap_or_else(|_| {
+        repairs.push("invalid_net_flow");
+        Decimal::ZERO
+    });
+
+    if !repairs.is_empty() {
+        kline.open = open.to_string();
+        kline.close = close.to_string();
+        kline.low = low.to_string();
+        kline.high = high.to_string();
+        kline.volume_sol = volume_sol.to_string();
+        kline.volume_token = volume_token.to_string();
+        kline.net_flow_sol = net_flow_sol.to_string();
+    }
+    repairs
+}
+
+// Share of monitored mints with less cumulative SOL volume than `mint` (0-1),
+// None when the mint has no recorded volume
+pub async fn get_volume_percentile(mint: &str) -> anyhow::Result<Option<f64>> {
+    let mut con = redis_helper::get_connection().await?;
+    let volumes: HashMap<String, f64> = con.hgetall(MINT_VOLUME_KEY).await?;
+    let Some(volume) = volumes.get(mint) else {
+        return Ok(None);
+    };
+    let below = volumes.values().filter(|v| *v < volume).count();
+    Ok(Some(
+        below as f64 / volumes.len().saturating_sub(1).max(1) as f64,
+    ))
+}
+
+// Whether a mint's bonding curve completed
+pub async fn is_mint_complete(mint: &str) -> anyhow::Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    Ok(con.sismember(MINT_COMPLETE_KEY, mint).await?)
 }
 
 pub struct KLineManager {
     idle_timeout: Duration,
+    // Where candles and mint activity are kept, from KLINE_STORE
+    store: Box<dyn KLineStore>,
+    // Source of "now" for activity tracking and cleanup; candles are bucketed by trade timestamps
+    clock: SharedClock,
+    // Serialize writes per mint so candle read-modify-write doesn't race, while
+    // trades for mints in different shards are applied concurrently
+    mint_locks: Vec<Mutex<()>>,
+    // Trades buffered in memory until the next flush, when pre-aggregation is on
+    pending: Option<std::sync::Mutex<PendingCandles>>,
+}
+
+// Number of per-mint lock shards
+fn lock_shards() -> usize {
+    std::env::var("KLINE_LOCK_SHARDS")
+        .unwrap_or_else(|_| "64".to_string())
+        .parse()
+        .unwrap_or(64)
+}
+
+fn new_mint_locks() -> Vec<Mutex<()>> {
+    (0..lock_shards().max(1)).map(|_| Mutex::new(())).collect()
+}
+
+// Shard index of a mint
+fn shard_index(mint: &str, shards: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    mint.hash(&mut hasher);
+    (hasher.finish() % shards as u64) as usize
 }
 
 impl KLineManager {
@@ -42,6 +480,10 @@ impl KLineManager {
 
         Ok(Self {
             idle_timeout: Duration::from_secs(timeout_secs),
+            store: kline_store::from_env()?,
+            clock: clock::system_clock(),
+            mint_locks: new_mint_locks(),
+            pending: None,
         })
     }
 
@@ -51,11 +493,90 @@ impl KLineManager {
 
         Ok(Self {
             idle_timeout: Duration::from_secs(timeout_secs),
+            store: kline_store::from_env()?,
+            clock: clock::system_clock(),
+            mint_locks: new_mint_locks(),
+            pending: None,
+        })
+    }
+
+    // Lock serializing the writes of a mint
+    fn mint_lock(&self, mint: &str) -> &Mutex<()> {
+        &self.mint_locks[shard_index(mint, self.mint_locks.len())]
+    }
+
+    // Use a different clock, e.g. a replay clock so idle cleanup follows recorded time
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    // Keep candles in a different backend than the one KLINE_STORE selects, e.g.
+    // MemoryKLineStore in tests
+    pub fn with_store(mut self, store: Box<dyn KLineStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    // Build candles in memory and write them to the store only on `flush_pending`
+    pub fn with_preaggregation(mut self) -> Self {
+        self.pending = Some(std::sync::Mutex::new(PendingCandles::default()));
+        self
+    }
+
+    // Number of candles buffered in memory and not written yet
+    pub fn pending_len(&self) -> usize {
+        self.pending.as_ref().map_or(0, |pending| {
+            pending.lock().unwrap_or_else(|e| e.into_inner()).len()
         })
     }
 
+    // Merge the buffered candles into the store. Candles that fail to be written are
+    // kept for the next flush. Returns the number of candles written.
+    pub async fn flush_pending(&self) -> anyhow::Result<usize> {
+        let Some(pending) = &self.pending else {
+            return Ok(0);
+        };
+        // Nothing after the drain returns early: each drained candle is either
+        // written or requeued, so a failing store can't drop buffered trades
+        let drained = pending.lock().unwrap_or_else(|e| e.into_inner()).drain();
+        if drained.is_empty() {
+            return Ok(0);
+        }
+
+        let mut flushed = 0;
+        let mut failed = 0;
+        for (mint, candle) in drained {
+            let _guard = self.mint_lock(&mint).lock().await;
+            match self
+                .store_candle(&mint, &candle.delta, candle.complete)
+                .await
+            {
+                Ok(stored) => {
+                    flushed += 1;
+                    // Already written, so a failure here must not requeue the candle
+                    if let Err(e) = self.announce_candle(&mint, stored).await {
+                        warn!("Failed to publish flushed candle of {}: {}", mint, e);
+                    }
+                }
+                Err(e) => {
+                    failed += 1;
+                    warn!("Failed to flush candle of {}: {}", mint, e);
+                    pending
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .requeue(&mint, candle);
+                }
+            }
+        }
+        if failed > 0 {
+            warn!("{} candles kept in memory for the next flush", failed);
+        }
+        Ok(flushed)
+    }
+
     // Get the minute timestamp that a given timestamp belongs to
-    fn get_minute_timestamp(timestamp: i64) -> i64 {
+    pub fn get_minute_timestamp(timestamp: i64) -> i64 {
         // Convert timestamp to DateTime, then set seconds and nanoseconds to 0 to get the whole minute
         if let Some(dt) = Local.timestamp_opt(timestamp, 0).single() {
             let dt_without_seconds = dt.with_second(0).unwrap().with_nanosecond(0).unwrap();
@@ -66,18 +587,14 @@ impl KLineManager {
         }
     }
 
-    // Generate Redis key for mint last activity
-    fn get_mint_activity_key(mint: &str) -> String {
-        format!("mint_activity:{}", mint)
-    }
-
-    // Generate Redis key
-    fn get_kline_key(mint: &str, timestamp: i64) -> String {
-        format!("kline:{}:{}", mint, timestamp)
+    // Index K-lines stored before the index existed, so range reads see every candle
+    pub async fn rebuild_kline_index(&self) -> anyhow::Result<usize> {
+        self.store.reindex().await
     }
 
-    fn get_mint_pattern(mint: &str) -> String {
-        format!("kline:{}:*", mint)
+    // Get the integrity counters recorded by the monitors
+    pub async fn get_integrity_stats(&self) -> anyhow::Result<HashMap<String, usize>> {
+        self.store.integrity_stats().await
     }
 
     // Add trading data
@@ -91,54 +608,154 @@ impl KLineManager {
         is_buy: bool,
         complete: bool,
     ) -> anyhow::Result<()> {
-        let minute_ts = Self::get_minute_timestamp(timestamp);
-        let key = Self::get_kline_key(mint, minute_ts);
-        let current_time = chrono::Utc::now().timestamp() as u64;
+        let trade = TradeRecord {
+            signature: String::new(),
+            slot: 0,
+            mint: mint.to_string(),
+            user: String::new(),
+            is_buy,
+            price,
+            sol_amount: sol_volume,
+            token_amount: token_volume,
+            timestamp,
+            source: Some(if complete {
+                TradeSource::Amm
+            } else {
+                TradeSource::Pump
+            }),
+            liquidity_sol: None,
+        };
+        let _guard = self.mint_lock(mint).lock().await;
+        self.apply_trade(&trade, complete).await?;
+        Ok(())
+    }
 
-        let mut con = redis_helper::get_connection().await?;
+    // Add trading data, store the tick trade it came from and update wallet positions.
+    // Writes hold the mint's lock so tick order matches candle updates.
+    #[instrument(name = "redis_write", skip_all, fields(mint = %trade.mint))]
+    pub async fn add_trade_record(
+        &self,
+        trade: &TradeRecord,
+        complete: bool,
+    ) -> anyhow::Result<()> {
+        let _guard = self
+            .mint_lock(&trade.mint)
+            .lock()
+            .instrument(info_span!("mint_lock_wait"))
+            .await;
+        // Time the writes only, not the wait for the mint lock
+        let started = Instant::now();
+        if !self.apply_trade(trade, complete).await? {
+            return Ok(());
+        }
+        self.store.record_trade(trade).await?;
+        metrics::record_trade(started.elapsed());
+        Ok(())
+    }
 
-        // Check if K-line for this minute already exists
-        let existing: Option<String> = con.get(&key).await?;
+    // Apply a trade to its minute candle, or buffer it until the next flush when
+    // candles are pre-aggregated. Returns false if the trade was rejected.
+    async fn apply_trade(&self, trade: &TradeRecord, complete: bool) -> anyhow::Result<bool> {
+        if let Err(reason) = validate_trade(trade) {
+            warn!(
+                "⚠️ Rejected trade for mint {} ({}): price={}, sol={}, tokens={}, signature={}",
+                trade.mint,
+                reason,
+                trade.price,
+                trade.sol_amount,
+                trade.token_amount,
+                trade.signature
+            );
+            self.store
+                .count_integrity(&format!("rejected:{}", reason))
+                .await?;
+            return Ok(false);
+        }
 
-        let kline = if let Some(existing_data) = existing {
-            // Update existing K-line
-            let mut kline: KLineData = serde_json::from_str(&existing_data)?;
-
-            let price_str = price.to_string();
-            let high_decimal: Decimal = kline.high.parse().unwrap_or(Decimal::ZERO);
-            let low_decimal: Decimal = kline.low.parse().unwrap_or(price);
-            let vol_sol_decimal: Decimal = kline.volume_sol.parse().unwrap_or(Decimal::ZERO);
-            let vol_token_decimal: Decimal = kline.volume_token.parse().unwrap_or(Decimal::ZERO);
-            let net_flow_decimal: Decimal = kline.net_flow_sol.parse().unwrap_or(Decimal::ZERO);
-
-            // Update highest and lowest prices
-            if price > high_decimal {
-                kline.high = price_str.clone();
-            }
-            if price < low_decimal {
-                kline.low = price_str.clone();
-            }
+        let minute_ts = Self::get_minute_timestamp(trade.timestamp);
+        let delta = trade_kline(trade, minute_ts, self.clock.now() as u64);
+        if let Some(pending) = &self.pending {
+            pending
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .add(&trade.mint, delta, complete);
+            return Ok(true);
+        }
+        self.merge_candle(&trade.mint, &delta, complete).await?;
+        Ok(true)
+    }
 
-            // Update closing price
-            kline.close = price_str.clone();
-            kline.last_update = current_time;
+    // Merge a candle built from new trades into the stored candle of its minute,
+    // creating it if needed, and publish the result
+    async fn merge_candle(
+        &self,
+        mint: &str,
+        delta: &KLineData,
+        complete: bool,
+    ) -> anyhow::Result<()> {
+        let stored = self.store_candle(mint, delta, complete).await?;
+        self.announce_candle(mint, stored).await
+    }
 
-            // Accumulate trading volume
-            kline.volume_sol = (vol_sol_decimal + sol_volume).to_string();
-            kline.volume_token = (vol_token_decimal + token_volume).to_string();
+    // The write half of `merge_candle`: once this succeeds the trades are in the
+    // store, and merging the same delta again would count them twice
+    async fn store_candle(
+        &self,
+        mint: &str,
+        delta: &KLineData,
+        complete: bool,
+    ) -> anyhow::Result<StoredCandle> {
+        let minute_ts = delta.timestamp;
+        let current_time = self.clock.now() as u64;
+        let price: Decimal = delta.close.parse().unwrap_or(Decimal::ZERO);
+        let sol_volume: Decimal = delta.volume_sol.parse().unwrap_or(Decimal::ZERO);
 
-            // Update net flow (buy is positive, sell is negative)
-            let flow_change = if is_buy { sol_volume } else { -sol_volume };
-            kline.net_flow_sol = (net_flow_decimal + flow_change).to_string();
+        // Read the minute's candle and the rolled-up candles containing it at once
+        let mut at = vec![(60, minute_ts)];
+        at.extend(
+            rollup_intervals()
+                .iter()
+                .map(|&interval| (interval, minute_ts - minute_ts.rem_euclid(interval))),
+        );
+        let mut stored = self.store.get_candles(mint, &at).await?.into_iter();
+        let existing = stored.next().flatten();
+        let opened = existing.is_none();
+        let mut before = None;
+
+        let kline = if let Some(mut kline) = existing {
+            // Update existing K-line
+            let repairs = repair_kline(&mut kline, delta.open.parse().unwrap_or(price));
+            if !repairs.is_empty() {
+                warn!(
+                    "⚠️ Repaired K-line for mint {} at {}: {}",
+                    mint,
+                    minute_ts,
+                    repairs.join(", ")
+                );
+                for reason in repairs {
+                    self.store
+                        .count_integrity(&format!("repaired:{}", reason))
+                        .await?;
+                }
+            }
+            before = Some(kline.clone());
 
-            // Check if low price is zero and fix it
-            if kline.low == "0" {
+            // A candle spanning graduation takes the newer source, since graduation is one-way
+            if delta.source.is_some()
+                && kline.source != delta.source
+                && let Some(previous) = kline.source
+            {
                 warn!(
-                    "⚠️ Low price for mint {} is zero, setting to current price: {}, timestamp: {}",
-                    mint, price, timestamp
+                    "⚠️ K-line for mint {} at {} switched source from {:?} to {:?}",
+                    mint, minute_ts, previous, delta.source
                 );
+                self.store.count_integrity("mixed_source").await?;
             }
 
+            // Extend highest/lowest prices, take the closing price and accumulate volumes and net flow
+            merge_kline(&mut kline, delta);
+            kline.last_update = current_time;
+
             // Check if high price has increased more than 1000% compared to open price
             let open_decimal: Decimal = kline.open.parse().unwrap_or(Decimal::ZERO);
             if open_decimal > Decimal::ZERO {
@@ -150,210 +767,643 @@ impl KLineManager {
                     let increase_percentage = price_increase * Decimal::new(100, 0);
                     warn!(
                         "⚠️ Mint {} surged {:.2}% - Open: {}, High: {}, Current: {}, timestamp: {}",
-                        mint, increase_percentage, kline.open, kline.high, price, timestamp
+                        mint, increase_percentage, kline.open, kline.high, price, minute_ts
                     );
                 }
             }
 
             kline
         } else {
-            // Create new K-line
-            let initial_net_flow = if is_buy { sol_volume } else { -sol_volume };
-            KLineData {
-                timestamp: minute_ts,
-                open: price.to_string(),
-                high: price.to_string(),
-                low: price.to_string(),
-                close: price.to_string(),
-                volume_sol: sol_volume.to_string(),
-                volume_token: token_volume.to_string(),
-                net_flow_sol: initial_net_flow.to_string(),
-                last_update: current_time,
-            }
+            let mut kline = delta.clone();
+            kline.last_update = current_time;
+            kline
         };
 
-        // Save to Redis without expiration time (we handle cleanup manually)
-        let kline_json = serde_json::to_string(&kline)?;
-        let _: () = con.set(&key, kline_json).await?;
+        // Save the minute candle together with its rollups
+        let mut candles = vec![(60, kline.clone())];
+        for ((interval, bucket), rollup) in at[1..].iter().zip(stored) {
+            candles.push((
+                *interval,
+                merge_rollup(delta, *bucket, rollup, current_time),
+            ));
+        }
+        self.store.put_candles(mint, &candles).await?;
 
-        // Update mint's last activity time and complete status
-        let activity_key = Self::get_mint_activity_key(mint);
-        let activity = MintActivity {
-            timestamp: current_time,
+        Ok(StoredCandle {
+            kline,
+            before,
+            opened,
             complete,
+            sol_volume,
+        })
+    }
+
+    // Publish a stored candle and record the mint's activity
+    async fn announce_candle(&self, mint: &str, stored: StoredCandle) -> anyhow::Result<()> {
+        let StoredCandle {
+            kline,
+            before,
+            opened,
+            complete,
+            sol_volume,
+        } = stored;
+        let minute_ts = kline.timestamp;
+        let current_time = kline.last_update;
+
+        // A new minute started for this mint, so its previous candle is closed.
+        // Published once the new candle is stored, so consumers read current data.
+        if opened
+            && let Err(e) = self
+                .store
+                .publish_candle(
+                    mint,
+                    CandleEvent::Closed {
+                        closed_before: minute_ts,
+                    },
+                )
+                .await
+        {
+            warn!("Failed to publish candle-close event for {}: {}", mint, e);
+        }
+
+        // A late trade on a candle whose close was already published re-emits the
+        // close when it moves the candle's prices enough to matter to strategies
+        if let Some(before) = &before
+            && minute_ts + 60 <= current_time as i64
+            && ohlc_change_pct(before, &kline) >= correction_min_pct()
+        {
+            let later = self
+                .store
+                .candle_times(mint, 60, Some(minute_ts + 1), None, Some(1))
+                .await?;
+            if !later.is_empty()
+                && let Err(e) = self
+                    .store
+                    .publish_candle(
+                        mint,
+                        CandleEvent::Corrected {
+                            candle_ts: minute_ts,
+                        },
+                    )
+                    .await
+            {
+                warn!("Failed to publish candle correction for {}: {}", mint, e);
+            }
+        }
+
+        // Fan the updated candle out to live subscribers
+        let update = CandleUpdate {
+            mint: mint.to_string(),
+            kline,
+            opened,
         };
-        let activity_json = serde_json::to_string(&activity)?;
-        let _: () = con.set(&activity_key, activity_json).await?;
+        if let Err(e) = self
+            .store
+            .publish_candle(mint, CandleEvent::Updated(Box::new(update)))
+            .await
+        {
+            warn!("Failed to publish candle update for {}: {}", mint, e);
+        }
 
-        Ok(())
+        // Update mint's last activity time, complete status and volume
+        self.store
+            .record_activity(mint, current_time, complete, sol_volume)
+            .await
+    }
+
+    // Recompute the rolled-up candles overlapping [from, to] (both optional) from
+    // the stored minute candles, e.g. after minute candles were deleted. Returns
+    // the number of rolled-up candles written.
+    pub async fn rebuild_rollups(
+        &self,
+        mint: &str,
+        from: Option<i64>,
+        to: Option<i64>,
+    ) -> anyhow::Result<usize> {
+        let mut written = 0;
+        for &interval in rollup_intervals() {
+            let start = from.map(|from| from - from.rem_euclid(interval));
+            let end = to.map(|to| to - to.rem_euclid(interval) + interval - 1);
+            self.store
+                .delete_candles(mint, interval, start, end)
+                .await?;
+
+            let mut aggregator = KLineAggregator::new(interval);
+            let mut rollups = Vec::new();
+            for kline in self.get_klines_in_range(mint, 60, start, end, None).await? {
+                rollups.extend(aggregator.push(kline));
+            }
+            rollups.extend(aggregator.finish());
+
+            let candles: Vec<(i64, KLineData)> = rollups
+                .into_iter()
+                .map(|rollup| (interval, rollup))
+                .collect();
+            self.store.put_candles(mint, &candles).await?;
+            written += candles.len();
+        }
+        Ok(written)
+    }
+
+    // Roll up the minute candles of every indexed mint into the configured
+    // intervals, for candles stored before rollups existed
+    pub async fn rebuild_all_rollups(&self) -> anyhow::Result<usize> {
+        let mut written = 0;
+        for (mint, _, _) in self.store.active_mints().await? {
+            written += self.rebuild_rollups(&mint, None, None).await?;
+        }
+        Ok(written)
+    }
+
+    // Seconds a mint has been inactive, None while it is within the idle timeout
+    // or isn't tracked
+    async fn idle_secs(&self, mint: &str, current_time: u64) -> anyhow::Result<Option<u64>> {
+        let Some(last_activity) = self.store.last_activity(mint).await? else {
+            return Ok(None);
+        };
+        let idle_secs = current_time.saturating_sub(last_activity);
+        Ok((idle_secs > self.idle_timeout.as_secs()).then_some(idle_secs))
     }
 
     // Check and delete all K-lines for inactive mints
     pub async fn cleanup_idle_klines(&self) -> anyhow::Result<()> {
-        let mut con = redis_helper::get_connection().await?;
-        let current_time = chrono::Utc::now().timestamp() as u64;
-
-        // Get all mint activity keys
-        let activity_pattern = "mint_activity:*";
-        let activity_keys: Vec<String> = con.keys(activity_pattern).await?;
-
-        for activity_key in activity_keys {
-            // Get the last activity data for this mint
-            if let Ok(Some(activity_str)) = con.get::<&str, Option<String>>(&activity_key).await {
-                // Parse as JSON format
-                let last_activity =
-                    if let Ok(activity) = serde_json::from_str::<MintActivity>(&activity_str) {
-                        activity.timestamp
-                    } else {
-                        continue;
-                    };
-
-                // Check if this mint is inactive
-                if current_time - last_activity > self.idle_timeout.as_secs() {
-                    // Extract mint address from the activity key
-                    let mint = activity_key.strip_prefix("mint_activity:").unwrap_or("");
-
-                    if !mint.is_empty() {
-                        // Find and delete all K-lines for this inactive mint
-                        let kline_pattern = Self::get_mint_pattern(mint);
-                        let kline_keys: Vec<String> = con.keys(&kline_pattern).await?;
-
-                        if !kline_keys.is_empty() {
-                            info!(
-                                "🗑️ Mint {} inactive for {} seconds, deleting {} K-lines",
-                                mint,
-                                current_time - last_activity,
-                                kline_keys.len()
-                            );
-
-                            // Delete all K-lines for this mint
-                            for key in &kline_keys {
-                                let _: () = con.del(key).await?;
-                            }
-
-                            // Also delete the activity tracking key
-                            let _: () = con.del(&activity_key).await?;
-                        }
-                    }
-                }
-            }
+        let current_time = self.clock.now() as u64;
+        let cutoff = current_time.saturating_sub(self.idle_timeout.as_secs());
+
+        for mint in self.store.idle_mints(cutoff).await? {
+            // Hold the mint's lock so a trade arriving now isn't deleted with its candles
+            let _guard = self.mint_lock(&mint).lock().await;
+
+            let Some(idle_secs) = self.idle_secs(&mint, current_time).await? else {
+                continue;
+            };
+            let deleted = self.store.delete_mint(&mint).await?;
+            info!(
+                "🗑️ Mint {} inactive for {} seconds, deleted {} K-line entries",
+                mint, idle_secs, deleted
+            );
         }
 
         Ok(())
     }
 
-    // Get all K-line data for the specified mint
+    // Report what cleanup_idle_klines would delete right now, without deleting.
+    // Sizes are the store's estimate: MEMORY USAGE per key for Redis.
+    pub async fn compaction_report(&self) -> anyhow::Result<CompactionReport> {
+        let current_time = self.clock.now() as u64;
+        let cutoff = current_time.saturating_sub(self.idle_timeout.as_secs());
+
+        let mut entries = Vec::new();
+        for mint in self.store.idle_mints(cutoff).await? {
+            let Some(idle_secs) = self.idle_secs(&mint, current_time).await? else {
+                continue;
+            };
+            let (keys, bytes) = self.store.mint_usage(&mint).await?;
+            if keys == 0 {
+                continue;
+            }
+            entries.push(CompactionEntry {
+                mint,
+                idle_secs,
+                keys,
+                bytes,
+            });
+        }
+
+        Ok(CompactionReport::new(entries))
+    }
+
+    // Get all K-line data of an interval (60 for minute candles) for the specified mint
     pub async fn get_klines_for_mint(
         &self,
         mint: &str,
+        interval_secs: i64,
         limit: Option<usize>,
     ) -> anyhow::Result<Vec<KLineData>> {
-        let mut con = redis_helper::get_connection().await?;
-        let pattern = Self::get_mint_pattern(mint);
-
-        let keys: Vec<String> = con.keys(&pattern).await?;
-        let mut klines = Vec::new();
+        self.get_klines_in_range(mint, interval_secs, None, None, limit)
+            .await
+    }
 
-        for key in keys {
-            if let Ok(Some(data)) = con.get::<&str, Option<String>>(&key).await {
-                if let Ok(kline) = serde_json::from_str::<KLineData>(&data) {
-                    klines.push(kline);
-                }
-            }
+    // Get the K-lines of an interval of a mint starting within [from, to] (both
+    // optional), oldest first. With a limit only the latest N in the range are
+    // returned. Intervals other than 60 must be listed in KLINE_ROLLUP_INTERVALS.
+    pub async fn get_klines_in_range(
+        &self,
+        mint: &str,
+        interval_secs: i64,
+        from: Option<i64>,
+        to: Option<i64>,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<KLineData>> {
+        if interval_secs != 60 && !rollup_intervals().contains(&interval_secs) {
+            anyhow::bail!(
+                "{} K-lines are not rolled up, add the interval to KLINE_ROLLUP_INTERVALS",
+                interval_label(interval_secs)
+            );
         }
+        let timestamps = self
+            .store
+            .candle_times(mint, interval_secs, from, to, limit)
+            .await?;
+        self.get_candles_at(mint, interval_secs, &timestamps).await
+    }
 
-        // Sort by timestamp
-        klines.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    // Start times of the minute K-lines of a mint within [from, to], oldest first
+    pub async fn get_kline_timestamps(
+        &self,
+        mint: &str,
+        from: Option<i64>,
+        to: Option<i64>,
+    ) -> anyhow::Result<Vec<i64>> {
+        self.store.candle_times(mint, 60, from, to, None).await
+    }
 
-        // If limit is specified, only return the latest N entries
-        if let Some(limit) = limit {
-            if klines.len() > limit {
-                klines = klines.into_iter().rev().take(limit).rev().collect();
-            }
+    // Load the minute K-lines of a mint starting at the given times, skipping missing ones
+    pub async fn get_klines_at(
+        &self,
+        mint: &str,
+        timestamps: &[i64],
+    ) -> anyhow::Result<Vec<KLineData>> {
+        self.get_candles_at(mint, 60, timestamps).await
+    }
+
+    async fn get_candles_at(
+        &self,
+        mint: &str,
+        interval_secs: i64,
+        timestamps: &[i64],
+    ) -> anyhow::Result<Vec<KLineData>> {
+        let at: Vec<(i64, i64)> = timestamps.iter().map(|ts| (interval_secs, *ts)).collect();
+        Ok(self
+            .store
+            .get_candles(mint, &at)
+            .await?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    // Delete the K-lines of a mint whose start time falls within [from, to], returns the number deleted
+    pub async fn delete_klines_in_range(
+        &self,
+        mint: &str,
+        from: i64,
+        to: i64,
+    ) -> anyhow::Result<usize> {
+        let _guard = self.mint_lock(mint).lock().await;
+        let deleted = self
+            .store
+            .delete_candles(mint, 60, Some(from), Some(to))
+            .await?;
+        if deleted > 0 {
+            info!(
+                "🗑️ Deleted {} K-lines for mint {} in range {} - {}",
+                deleted, mint, from, to
+            );
+            // Rolled-up candles overlapping the range no longer match their minutes
+            self.rebuild_rollups(mint, Some(from), Some(to)).await?;
+        }
+        // Alert markers on the deleted candles would otherwise point at nothing
+        let annotations = annotations::delete_annotations(mint, from, to).await?;
+        if annotations > 0 {
+            info!(
+                "🗑️ Deleted {} annotations for mint {} in range {} - {}",
+                annotations, mint, from, to
+            );
         }
 
-        Ok(klines)
+        Ok(deleted)
     }
 
-    // Get the latest K-line data (grouped by mint)
+    // Get the latest K-line data of each tracked mint, newest first
     pub async fn get_latest_klines(
         &self,
         limit_per_mint: usize,
     ) -> anyhow::Result<Vec<(String, KLineData)>> {
-        let mut con = redis_helper::get_connection().await?;
-        let keys: Vec<String> = con.keys("kline:*:*").await?;
-
-        use std::collections::HashMap;
-        let mut mint_klines: HashMap<String, Vec<KLineData>> = HashMap::new();
-
-        for key in keys {
-            if let Ok(Some(data)) = con.get::<&str, Option<String>>(&key).await {
-                if let Ok(kline) = serde_json::from_str::<KLineData>(&data) {
-                    // Extract mint from key
-                    let parts: Vec<&str> = key.split(':').collect();
-                    if parts.len() >= 2 {
-                        let mint = parts[1].to_string();
-                        mint_klines.entry(mint).or_insert_with(Vec::new).push(kline);
-                    }
-                }
-            }
-        }
-
         let mut result = Vec::new();
-        for (mint, mut klines) in mint_klines {
-            // Sort by timestamp, take the latest ones
-            klines.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-            klines.truncate(limit_per_mint);
-
-            for kline in klines {
+        for (mint, _, _) in self.store.active_mints().await? {
+            let klines = self
+                .get_klines_for_mint(&mint, 60, Some(limit_per_mint))
+                .await?;
+            for kline in klines.into_iter().rev() {
                 result.push((mint.clone(), kline));
             }
         }
-
         Ok(result)
     }
 
+    // Get the minute candle count and cumulative SOL volume of each mint, in input order
+    pub async fn get_mint_sizes(&self, mints: &[String]) -> anyhow::Result<Vec<(usize, Decimal)>> {
+        self.store.mint_sizes(mints).await
+    }
+
     // Get statistics
     pub async fn get_stats(&self) -> anyhow::Result<(usize, usize)> {
-        let mut con = redis_helper::get_connection().await?;
-        let keys: Vec<String> = con.keys("kline:*:*").await?;
-
-        let mut mints = std::collections::HashSet::new();
-        for key in &keys {
-            let parts: Vec<&str> = key.split(':').collect();
-            if parts.len() >= 2 {
-                mints.insert(parts[1].to_string());
-            }
-        }
-
-        Ok((mints.len(), keys.len()))
+        self.store.stats().await
     }
 
-    // Get active mint statistics
+    // Get active mint statistics, most recent first
     pub async fn get_active_mints(&self) -> anyhow::Result<Vec<(String, u64, bool)>> {
-        let mut con = redis_helper::get_connection().await?;
-        let activity_keys: Vec<String> = con.keys("mint_activity:*").await?;
-
-        let mut active_mints = Vec::new();
-        for activity_key in activity_keys {
-            if let Ok(Some(activity_str)) = con.get::<&str, Option<String>>(&activity_key).await {
-                let mint = activity_key
-                    .strip_prefix("mint_activity:")
-                    .unwrap_or("")
-                    .to_string();
-
-                if !mint.is_empty() {
-                    // Parse as JSON format
-                    if let Ok(activity) = serde_json::from_str::<MintActivity>(&activity_str) {
-                        active_mints.push((mint, activity.timestamp, activity.complete));
-                    }
-                }
-            }
+        self.store.active_mints().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness;
+
+    fn kline(open: &str, high: &str, low: &str, close: &str, volume_sol: &str) -> KLineData {
+        KLineData {
+            open: open.to_string(),
+            high: high.to_string(),
+            low: low.to_string(),
+            volume_sol: volume_sol.to_string(),
+            ..test_harness::candle(0, close)
         }
+    }
+
+    #[test]
+    fn test_repair_kline() {
+        let mut valid = kline("2", "3", "1", "2", "5");
+        assert!(repair_kline(&mut valid, Decimal::from(2)).is_empty());
 
-        // Sort by last activity time (most recent first)
-        active_mints.sort_by(|a, b| b.1.cmp(&a.1));
+        let mut broken = kline("2", "1", "0", "3", "-1");
+        let repairs = repair_kline(&mut broken, Decimal::from(2));
+        assert_eq!(
+            repairs,
+            vec!["invalid_low", "high_below_body", "invalid_volume_sol"]
+        );
+        assert_eq!(broken.low, "2");
+        assert_eq!(broken.high, "3");
+        assert_eq!(broken.volume_sol, "0");
+    }
+
+    #[test]
+    fn test_candle_gaps() {
+        let at = |timestamp: i64, close: &str| {
+            let mut k = kline(close, close, close, close, "1");
+            k.timestamp = timestamp;
+            k
+        };
+        let klines = vec![at(60, "1"), at(120, "2"), at(300, "3"), at(360, "4")];
+        assert_eq!(
+            find_gaps(&klines, 60),
+            vec![CandleGap {
+                from: 180,
+                to: 240,
+                candles: 2,
+                filled: false,
+            }]
+        );
+
+        let (filled, gaps) = fill_gaps(klines, 60);
+        let series: Vec<(i64, &str, &str)> = filled
+            .iter()
+            .map(|k| (k.timestamp, k.close.as_str(), k.volume_sol.as_str()))
+            .collect();
+        assert_eq!(
+            series,
+            vec![
+                (60, "1", "1"),
+                (120, "2", "1"),
+                (180, "2", "0"),
+                (240, "2", "0"),
+                (300, "3", "1"),
+                (360, "4", "1"),
+            ]
+        );
+        assert!(gaps[0].filled);
+
+        // Gaps longer than a day are only reported
+        let (filled, gaps) = fill_gaps(vec![at(0, "1"), at(60 * 2000, "2")], 60);
+        assert_eq!(filled.len(), 2);
+        assert_eq!((gaps[0].candles, gaps[0].filled), (1999, false));
+        assert_eq!("FILL".parse::<GapMode>().unwrap(), GapMode::Fill);
+        assert!("interpolate".parse::<GapMode>().is_err());
+    }
+
+    #[test]
+    fn test_aggregate_klines() {
+        let mut aggregator = KLineAggregator::new(300);
+        let mut minute = |timestamp: i64, open: &str, high: &str, low: &str, close: &str| {
+            let mut k = kline(open, high, low, close, "1");
+            k.timestamp = timestamp;
+            aggregator.push(k)
+        };
+        assert!(minute(1_700_000_100, "2", "3", "1", "2.5").is_none());
+        assert!(minute(1_700_000_160, "2.5", "4", "2", "3").is_none());
+        // 1_700_000_400 starts the next 5-minute bucket
+        let first = minute(1_700_000_400, "3", "3", "3", "3").unwrap();
+        assert_eq!(first.timestamp, 1_700_000_100);
+        assert_eq!(
+            (first.open.as_str(), first.high.as_str(), first.low.as_str()),
+            ("2", "4", "1")
+        );
+        assert_eq!(first.close, "3");
+        assert_eq!(first.volume_sol, "2");
+
+        let last = aggregator.finish().unwrap();
+        assert_eq!(last.timestamp, 1_700_000_400);
+    }
+
+    #[test]
+    fn test_merge_close_order() {
+        let closed_at = |close: &str, close_time: i64| {
+            let mut k = kline(close, close, close, close, "1");
+            k.close_time = Some(close_time);
+            k
+        };
+        // A late trade keeps the close of the newer one
+        let mut candle = closed_at("2", 1_700_000_050);
+        merge_kline(&mut candle, &closed_at("1", 1_700_000_010));
+        assert_eq!(
+            (candle.close.as_str(), candle.close_time),
+            ("2", Some(1_700_000_050))
+        );
+        merge_kline(&mut candle, &closed_at("3", 1_700_000_055));
+        assert_eq!(candle.close, "3");
+
+        // A minute written after a later one of its bucket doesn't move the rollup's close
+        let rollup = merge_rollup(&closed_at("5", 1_700_000_170), 1_700_000_100, None, 0);
+        let rollup = merge_rollup(
+            &closed_at("4", 1_700_000_110),
+            1_700_000_100,
+            Some(rollup),
+            0,
+        );
+        assert_eq!(
+            (rollup.timestamp, rollup.close.as_str()),
+            (1_700_000_100, "5")
+        );
+        assert_eq!(rollup.volume_sol, "2");
+
+        // Candles stored before close times were tracked take the merged close
+        let mut legacy = kline("1", "1", "1", "1", "1");
+        merge_kline(&mut legacy, &closed_at("2", 1_700_000_000));
+        assert_eq!(legacy.close, "2");
+    }
+
+    #[test]
+    fn test_update_turnover() {
+        // Bonding curve trades carry no liquidity snapshot
+        let mut candle = kline("1", "1", "1", "1", "5");
+        update_turnover(&mut candle, None);
+        assert_eq!(candle.turnover, None);
+
+        update_turnover(&mut candle, Some(Decimal::from(40)));
+        assert_eq!(candle.liquidity_sol.as_deref(), Some("40"));
+        assert_eq!(candle.turnover.as_deref(), Some("0.125"));
+
+        // Later trades without a snapshot keep using the last known liquidity
+        candle.volume_sol = "10".to_string();
+        update_turnover(&mut candle, None);
+        assert_eq!(candle.turnover.as_deref(), Some("0.25"));
+    }
+
+    #[test]
+    fn test_compaction_report() {
+        let entry = |mint: &str, keys: usize, bytes: u64| CompactionEntry {
+            mint: mint.to_string(),
+            idle_secs: 120,
+            keys,
+            bytes,
+        };
+        let report = CompactionReport::new(vec![
+            entry("a", 3, 100),
+            entry("b", 5, 400),
+            entry("c", 2, 100),
+        ]);
+        let order: Vec<&str> = report.entries.iter().map(|e| e.mint.as_str()).collect();
+        assert_eq!(order, ["b", "a", "c"]);
+        assert_eq!(report.total_keys, 10);
+        assert_eq!(report.total_bytes, 600);
+        assert_eq!(CompactionReport::new(Vec::new()).total_bytes, 0);
+    }
+
+    #[test]
+    fn test_ohlc_change_pct() {
+        let before = kline("1.0", "1.2", "0.9", "1.0", "5");
+        assert_eq!(ohlc_change_pct(&before, &before), Decimal::ZERO);
+        // A late trade only moving the volume is not a correction
+        assert_eq!(
+            ohlc_change_pct(&before, &kline("1.0", "1.2", "0.9", "1.0", "50")),
+            Decimal::ZERO
+        );
+        // A new low 10% under the previous one dominates the 2% close move
+        let after = kline("1.0", "1.2", "0.81", "0.98", "6");
+        assert_eq!(ohlc_change_pct(&before, &after), Decimal::new(10, 0));
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_manager() {
+        let store = kline_store::MemoryKLineStore::default();
+        let manager = KLineManager::new()
+            .await
+            .unwrap()
+            .with_store(Box::new(store.clone()));
+        let start = 1_700_000_100;
+        let trade = |offset: i64, price: Decimal, sol: i64, is_buy: bool| {
+            manager.add_trade(
+                "Mint",
+                start + offset,
+                price,
+                Decimal::from(sol),
+                Decimal::from(sol * 10),
+                is_buy,
+                false,
+            )
+        };
+        trade(5, Decimal::ONE, 1, true).await.unwrap();
+        trade(30, Decimal::new(15, 1), 2, true).await.unwrap();
+        trade(65, Decimal::new(8, 1), 1, false).await.unwrap();
+        // Rejected and counted, without touching the candles
+        trade(70, Decimal::ZERO, 1, true).await.unwrap();
+
+        let minutes = manager.get_klines_for_mint("Mint", 60, None).await.unwrap();
+        assert_eq!(minutes.len(), 2);
+        assert_eq!(minutes[0].high, "1.5");
+        assert_eq!(minutes[1].close, "0.8");
+
+        let decimal = |value: &str| value.parse::<Decimal>().unwrap();
+        let rollup = manager
+            .get_klines_for_mint("Mint", 300, None)
+            .await
+            .unwrap();
+        assert_eq!(rollup.len(), 1);
+        assert_eq!(rollup[0].timestamp, start);
+        assert_eq!(decimal(&rollup[0].low), Decimal::new(8, 1));
+        assert_eq!(decimal(&rollup[0].close), Decimal::new(8, 1));
+        assert_eq!(decimal(&rollup[0].volume_sol), Decimal::from(4));
+
+        // Rolled-up candles follow the minutes they are rebuilt from
+        store
+            .delete_candles("Mint", 60, Some(start + 60), Some(start + 60))
+            .await
+            .unwrap();
+        manager
+            .rebuild_rollups("Mint", Some(start + 60), Some(start + 60))
+            .await
+            .unwrap();
+        let rollup = manager
+            .get_klines_for_mint("Mint", 300, None)
+            .await
+            .unwrap();
+        assert_eq!(decimal(&rollup[0].close), Decimal::new(15, 1));
+        assert_eq!(decimal(&rollup[0].volume_sol), Decimal::from(3));
+
+        let active = manager.get_active_mints().await.unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].0, "Mint");
+        assert_eq!(
+            manager.get_integrity_stats().await.unwrap()["rejected:non_positive_price"],
+            1
+        );
+        assert_eq!(manager.get_stats().await.unwrap(), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_flush_pending() {
+        let manager = KLineManager::new()
+            .await
+            .unwrap()
+            .with_store(Box::new(kline_store::MemoryKLineStore::default()))
+            .with_preaggregation();
+        let start = 1_700_000_100;
+        for (offset, price) in [(5, 2), (20, 3), (65, 1)] {
+            manager
+                .add_trade(
+                    "Mint",
+                    start + offset,
+                    Decimal::from(price),
+                    Decimal::ONE,
+                    Decimal::TEN,
+                    true,
+                    false,
+                )
+                .await
+                .unwrap();
+        }
+        // Buffered until the flush
+        assert_eq!(manager.pending_len(), 2);
+        assert!(
+            manager
+                .get_klines_for_mint("Mint", 60, None)
+                .await
+                .unwrap()
+                .is_empty()
+        );
 
-        Ok(active_mints)
+        assert_eq!(manager.flush_pending().await.unwrap(), 2);
+        assert_eq!(manager.pending_len(), 0);
+        let minutes = manager.get_klines_for_mint("Mint", 60, None).await.unwrap();
+        assert_eq!(minutes.len(), 2);
+        assert_eq!(minutes[0].high, "3");
+        assert_eq!(minutes[0].volume_sol, "2");
+        let rollup = manager
+            .get_klines_for_mint("Mint", 300, None)
+            .await
+            .unwrap();
+        assert_eq!(rollup[0].low, "1");
     }
 }
@@ -0,0 +1,308 @@
+use anyhow::{Result, anyhow};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::kline::KLineData;
+use crate::trades::TradeSource;
+
+// First byte of a compact candle. JSON candles always start with '{'.
+const COMPACT_V2_TAG: u8 = 0x02;
+// Compact candle followed by its trade source
+const COMPACT_V3_TAG: u8 = 0x03;
+// Compact candle followed by its trade source, pool liquidity and turnover
+const COMPACT_V4_TAG: u8 = 0x04;
+// Compact candle followed by the v4 fields and its anomaly score
+const COMPACT_V5_TAG: u8 = 0x05;
+// Compact candle followed by the v5 fields and its close time
+const COMPACT_V6_TAG: u8 = 0x06;
+
+// Optional candle fields stored after the compact candle
+type CompactExtras = (Option<TradeSource>, Option<Fixed>, Option<Fixed>);
+
+/// K线存储格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageVersion {
+    /// v1: JSON with stringified decimals
+    Json,
+    /// v2: binary, fixed-point integers with OHLC deltas from open
+    Compact,
+}
+
+impl StorageVersion {
+    // Read the write format from KLINE_STORAGE_VERSION (1 = JSON, 2 = compact)
+    pub fn from_env() -> Self {
+        match std::env::var("KLINE_STORAGE_VERSION").as_deref() {
+            Ok("2") => StorageVersion::Compact,
+            _ => StorageVersion::Json,
+        }
+    }
+}
+
+// Decimal split into an integer mantissa and a decimal scale
+#[derive(Debug, Serialize, Deserialize)]
+struct Fixed {
+    mantissa: i128,
+    scale: u32,
+}
+
+impl Fixed {
+    fn from_decimal(value: Decimal) -> Self {
+        let value = value.normalize();
+        Self {
+            mantissa: value.mantissa(),
+            scale: value.scale(),
+        }
+    }
+
+    fn to_decimal(&self) -> Result<Decimal> {
+        Decimal::try_from_i128_with_scale(self.mantissa, self.scale)
+            .map_err(|e| anyhow!("invalid fixed-point value: {}", e))
+    }
+}
+
+// Compact candle layout. Prices share one scale; high, low and close are
+// stored as deltas from open so varint encoding keeps them small.
+#[derive(Debug, Serialize, Deserialize)]
+struct CompactKLine {
+    timestamp: i64,
+    price_scale: u32,
+    open: i128,
+    high_delta: i128,
+    low_delta: i128,
+    close_delta: i128,
+    volume_sol: Fixed,
+    volume_token: Fixed,
+    net_flow_sol: Fixed,
+    // Seconds between the candle start and its last update
+    last_update_offset: i64,
+}
+
+fn parse_decimal(field: &str, value: &str) -> Result<Decimal> {
+    value
+        .parse()
+        .map_err(|e| anyhow!("invalid {} '{}': {}", field, value, e))
+}
+
+// Rescale a price mantissa to the shared price scale
+fn rescale(value: Decimal, scale: u32) -> Option<i128> {
+    10i128
+        .checked_pow(scale - value.scale())
+        .and_then(|factor| value.mantissa().checked_mul(factor))
+}
+
+fn to_compact(kline: &KLineData) -> Result<Option<CompactKLine>> {
+    let prices = [
+        parse_decimal("open", &kline.open)?.normalize(),
+        parse_decimal("high", &kline.high)?.normalize(),
+        parse_decimal("low", &kline.low)?.normalize(),
+        parse_decimal("close", &kline.close)?.normalize(),
+    ];
+    let price_scale = prices.iter().map(|p| p.scale()).max().unwrap_or(0);
+
+    let mut mantissas = [0i128; 4];
+    for (mantissa, price) in mantissas.iter_mut().zip(prices) {
+        match rescale(price, price_scale) {
+            Some(m) => *mantissa = m,
+            // Does not fit a shared scale, caller falls back to JSON
+            None => return Ok(None),
+        }
+    }
+    let [open, high, low, close] = mantissas;
+
+    Ok(Some(CompactKLine {
+        timestamp: kline.timestamp,
+        price_scale,
+        open,
+        high_delta: high - open,
+        low_delta: low - open,
+        close_delta: close - open,
+        volume_sol: Fixed::from_decimal(parse_decimal("volume_sol", &kline.volume_sol)?),
+        volume_token: Fixed::from_decimal(parse_decimal("volume_token", &kline.volume_token)?),
+        net_flow_sol: Fixed::from_decimal(parse_decimal("net_flow_sol", &kline.net_flow_sol)?),
+        last_update_offset: kline.last_update as i64 - kline.timestamp,
+    }))
+}
+
+fn from_compact(compact: CompactKLine, source: Option<TradeSource>) -> Result<KLineData> {
+    let price = |mantissa: i128| -> Result<String> {
+        Ok(Fixed {
+            mantissa,
+            scale: compact.price_scale,
+        }
+        .to_decimal()?
+        .normalize()
+        .to_string())
+    };
+
+    Ok(KLineData {
+        timestamp: compact.timestamp,
+        open: price(compact.open)?,
+        high: price(compact.open + compact.high_delta)?,
+        low: price(compact.open + compact.low_delta)?,
+        close: price(compact.open + compact.close_delta)?,
+        volume_sol: compact.volume_sol.to_decimal()?.to_string(),
+        volume_token: compact.volume_token.to_decimal()?.to_string(),
+        net_flow_sol: compact.net_flow_sol.to_decimal()?.to_string(),
+        last_update: (compact.timestamp + compact.last_update_offset) as u64,
+        source,
+        liquidity_sol: None,
+        turnover: None,
+        anomaly_score: None,
+        close_time: None,
+    })
+}
+
+fn optional_fixed(field: &str, value: Option<&str>) -> Result<Option<Fixed>> {
+    value
+        .map(|v| parse_decimal(field, v).map(Fixed::from_decimal))
+        .transpose()
+}
+
+fn optional_decimal(value: Option<Fixed>) -> Result<Option<String>> {
+    value
+        .map(|v| Ok(v.to_decimal()?.normalize().to_string()))
+        .transpose()
+}
+
+/// 编码K线，compact格式无法表示时回退为JSON
+pub fn encode_kline(kline: &KLineData, version: StorageVersion) -> Result<Vec<u8>> {
+    let compact = match version {
+        StorageVersion::Compact => to_compact(kline)?,
+        StorageVersion::Json => None,
+    };
+    match compact {
+        Some(compact) => {
+            let extras: CompactExtras = (
+                kline.source,
+                optional_fixed("liquidity_sol", kline.liquidity_sol.as_deref())?,
+                optional_fixed("turnover", kline.turnover.as_deref())?,
+            );
+            let mut bytes = vec![COMPACT_V6_TAG];
+            bytes.extend(bincode::serde::encode_to_vec(
+                (&compact, extras, kline.anomaly_score, kline.close_time),
+                bincode::config::standard(),
+            )?);
+            Ok(bytes)
+        }
+        None => Ok(serde_json::to_vec(kline)?),
+    }
+}
+
+/// 解码K线，自动识别JSON和compact格式
+pub fn decode_kline(bytes: &[u8]) -> Result<KLineData> {
+    match bytes.split_first() {
+        Some((&COMPACT_V2_TAG, payload)) => {
+            let (compact, _): (CompactKLine, usize) =
+                bincode::serde::decode_from_slice(payload, bincode::config::standard())?;
+            from_compact(compact, None)
+        }
+        Some((&COMPACT_V3_TAG, payload)) => {
+            let ((compact, source), _): ((CompactKLine, Option<TradeSource>), usize) =
+                bincode::serde::decode_from_slice(payload, bincode::config::standard())?;
+            from_compact(compact, source)
+        }
+        Some((&COMPACT_V4_TAG, payload)) => {
+            let ((compact, (source, liquidity_sol, turnover)), _): (
+                (CompactKLine, CompactExtras),
+                usize,
+            ) = bincode::serde::decode_from_slice(payload, bincode::config::standard())?;
+            let mut kline = from_compact(compact, source)?;
+            kline.liquidity_sol = optional_decimal(liquidity_sol)?;
+            kline.turnover = optional_decimal(turnover)?;
+            Ok(kline)
+        }
+        Some((&COMPACT_V5_TAG, payload)) => {
+            let ((compact, (source, liquidity_sol, turnover), anomaly_score), _): (
+                (CompactKLine, CompactExtras, Option<f64>),
+                usize,
+            ) = bincode::serde::decode_from_slice(payload, bincode::config::standard())?;
+            let mut kline = from_compact(compact, source)?;
+            kline.liquidity_sol = optional_decimal(liquidity_sol)?;
+            kline.turnover = optional_decimal(turnover)?;
+            kline.anomaly_score = anomaly_score;
+            Ok(kline)
+        }
+        Some((&COMPACT_V6_TAG, payload)) => {
+            let ((compact, (source, liquidity_sol, turnover), anomaly_score, close_time), _): (
+                (CompactKLine, CompactExtras, Option<f64>, Option<i64>),
+                usize,
+            ) = bincode::serde::decode_from_slice(payload, bincode::config::standard())?;
+            let mut kline = from_compact(compact, source)?;
+            kline.liquidity_sol = optional_decimal(liquidity_sol)?;
+            kline.turnover = optional_decimal(turnover)?;
+            kline.anomaly_score = anomaly_score;
+            kline.close_time = close_time;
+            Ok(kline)
+        }
+        _ => Ok(serde_json::from_slice(bytes)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness;
+
+    fn sample_kline() -> KLineData {
+        KLineData {
+            open: "0.0000000281234".to_string(),
+            high: "0.00000003".to_string(),
+            low: "0.000000027".to_string(),
+            volume_sol: "12.345678901".to_string(),
+            volume_token: "438912345.123456".to_string(),
+            net_flow_sol: "-3.5".to_string(),
+            last_update: 1_700_000_087,
+            source: Some(TradeSource::Amm),
+            liquidity_sol: Some("85.2".to_string()),
+            turnover: Some("0.144902".to_string()),
+            anomaly_score: Some(4.25),
+            close_time: Some(1_700_000_042),
+            ..test_harness::candle(1_700_000_040, "0.0000000295")
+        }
+    }
+
+    #[test]
+    fn test_compact_roundtrip() {
+        let kline = sample_kline();
+        let bytes = encode_kline(&kline, StorageVersion::Compact).unwrap();
+        assert_eq!(bytes[0], COMPACT_V6_TAG);
+        assert!(bytes.len() < serde_json::to_vec(&kline).unwrap().len() / 2);
+
+        let decoded = decode_kline(&bytes).unwrap();
+        assert_eq!(decoded.timestamp, kline.timestamp);
+        assert_eq!(decoded.last_update, kline.last_update);
+        assert_eq!(decoded.source, kline.source);
+        assert_eq!(decoded.liquidity_sol, kline.liquidity_sol);
+        assert_eq!(decoded.turnover, kline.turnover);
+        assert_eq!(decoded.anomaly_score, kline.anomaly_score);
+        assert_eq!(decoded.close_time, kline.close_time);
+        for (stored, original) in [
+            (&decoded.open, &kline.open),
+            (&decoded.high, &kline.high),
+            (&decoded.low, &kline.low),
+            (&decoded.close, &kline.close),
+            (&decoded.volume_sol, &kline.volume_sol),
+            (&decoded.volume_token, &kline.volume_token),
+            (&decoded.net_flow_sol, &kline.net_flow_sol),
+        ] {
+            assert_eq!(
+                stored.parse::<Decimal>().unwrap(),
+                original.parse::<Decimal>().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_legacy_json() {
+        let kline = sample_kline();
+        let bytes = encode_kline(&kline, StorageVersion::Json).unwrap();
+        let decoded = decode_kline(&bytes).unwrap();
+        assert_eq!(decoded.close, kline.close);
+
+        // Candles written before source tagging decode without a source
+        let compact = to_compact(&kline).unwrap().unwrap();
+        let mut bytes = vec![COMPACT_V2_TAG];
+        bytes.extend(bincode::serde::encode_to_vec(&compact, bincode::config::standard()).unwrap());
+        assert_eq!(decode_kline(&bytes).unwrap().source, None);
+    }
+}
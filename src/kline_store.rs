@@ -0,0 +1,1067 @@
+// Storage backends of K-line data. KLineManager builds the candles and keeps
+// them in a KLineStore, together with the per-mint activity the idle cleanup
+// and the strategy universe rely on. The store also counts integrity problems,
+// publishes candle events and keeps tick trades and positions. Redis is the
+// default backend; the memory store keeps candles, activity and counters in the
+// process and hands candle events to in-process consumers only.
+
+use anyhow::Result;
+use futures_util::future::BoxFuture;
+use redis::{AsyncCommands, aio::ConnectionManager};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, LazyLock, Mutex};
+use tracing::warn;
+
+use crate::annotations;
+use crate::candle_hub::{self, CandleUpdate};
+use crate::candle_stream;
+use crate::kline::{KLineData, interval_label, rollup_intervals};
+use crate::kline_codec::{self, StorageVersion};
+use crate::limits;
+use crate::message_bus;
+use crate::positions;
+use crate::redis_helper;
+use crate::trades::{self, TradeRecord, TradeSource};
+
+// Redis hash of cumulative traded SOL per mint, used to filter the strategy universe
+pub const MINT_VOLUME_KEY: &str = "mint_volume";
+
+// Redis ZSET of tracked mints scored by their last activity time (seconds), so idle
+// mints are found with ZRANGEBYSCORE instead of scanning per-mint keys
+pub const MINT_ACTIVITY_KEY: &str = "mint_activity";
+
+// Redis set of mints whose bonding curve completed
+pub const MINT_COMPLETE_KEY: &str = "mint_complete";
+
+// Redis hash of integrity counters (rejected trades and repaired candles by reason)
+const INTEGRITY_STATS_KEY: &str = "stats:integrity";
+
+// Prefix of the per-mint activity keys (JSON MintActivity) written by earlier versions
+const LEGACY_ACTIVITY_PREFIX: &str = "mint_activity:";
+
+// Activity record of the legacy per-mint keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MintActivity {
+    pub timestamp: u64,
+    pub complete: bool,
+}
+
+// What happened to a mint's candles, for consumers of the candle events
+#[derive(Debug, Clone)]
+pub enum CandleEvent {
+    // The first trade of a new minute opened a candle, so the candles before it are closed
+    Closed { closed_before: i64 },
+    // A late trade changed a candle whose close was already published
+    Corrected { candle_ts: i64 },
+    // A candle changed, for live subscribers
+    Updated(Box<CandleUpdate>),
+}
+
+// Candle storage. Candles are addressed by mint, interval (60 for minute
+// candles) and start time; minute candles count towards the sizes and stats.
+pub trait KLineStore: Send + Sync {
+    // Candles at the given (interval, start time) pairs, None where missing
+    fn get_candles<'a>(
+        &'a self,
+        mint: &'a str,
+        at: &'a [(i64, i64)],
+    ) -> BoxFuture<'a, Result<Vec<Option<KLineData>>>>;
+
+    // Write (interval, candle) pairs, replacing stored candles with the same start
+    fn put_candles<'a>(
+        &'a self,
+        mint: &'a str,
+        candles: &'a [(i64, KLineData)],
+    ) -> BoxFuture<'a, Result<()>>;
+
+    // Start times of the stored candles of an interval within [from, to] (both
+    // optional), oldest first. With a limit only the latest N are returned.
+    fn candle_times<'a>(
+        &'a self,
+        mint: &'a str,
+        interval_secs: i64,
+        from: Option<i64>,
+        to: Option<i64>,
+        limit: Option<usize>,
+    ) -> BoxFuture<'a, Result<Vec<i64>>>;
+
+    // Delete the candles of an interval starting within [from, to], returns the number deleted
+    fn delete_candles<'a>(
+        &'a self,
+        mint: &'a str,
+        interval_secs: i64,
+        from: Option<i64>,
+        to: Option<i64>,
+    ) -> BoxFuture<'a, Result<usize>>;
+
+    // Record a write to a mint's candles: its activity time, completion and traded SOL
+    fn record_activity<'a>(
+        &'a self,
+        mint: &'a str,
+        timestamp: u64,
+        complete: bool,
+        volume_sol: Decimal,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    // Last activity time of a mint, None when it isn't tracked
+    fn last_activity<'a>(&'a self, mint: &'a str) -> BoxFuture<'a, Result<Option<u64>>>;
+
+    // Tracked mints with their last activity and completion, most recent first
+    fn active_mints<'a>(&'a self) -> BoxFuture<'a, Result<Vec<(String, u64, bool)>>>;
+
+    // Tracked mints whose last activity is before `cutoff`
+    fn idle_mints<'a>(&'a self, cutoff: u64) -> BoxFuture<'a, Result<Vec<String>>>;
+
+    // Delete every candle and record of a mint and stop tracking it, returns the
+    // number of candle entries deleted
+    fn delete_mint<'a>(&'a self, mint: &'a str) -> BoxFuture<'a, Result<usize>>;
+
+    // Minute candle count and cumulative SOL volume of each mint, in input order
+    fn mint_sizes<'a>(
+        &'a self,
+        mints: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<(usize, Decimal)>>>;
+
+    // Number of mints with minute candles and of minute candles
+    fn stats<'a>(&'a self) -> BoxFuture<'a, Result<(usize, usize)>>;
+
+    // Hand a candle event of a mint to its consumers
+    fn publish_candle<'a>(&'a self, mint: &'a str, event: CandleEvent)
+    -> BoxFuture<'a, Result<()>>;
+
+    // Count a rejected trade or repaired candle under an integrity counter
+    fn count_integrity<'a>(&'a self, counter: &'a str) -> BoxFuture<'a, Result<()>>;
+
+    // Integrity counters recorded so far
+    fn integrity_stats<'a>(&'a self) -> BoxFuture<'a, Result<HashMap<String, usize>>>;
+
+    // Keep the tick of an applied trade and update the trading wallet's position
+    fn record_trade<'a>(&'a self, trade: &'a TradeRecord) -> BoxFuture<'a, Result<()>>;
+
+    // Number of entries deleting a mint would remove and their estimated size in
+    // bytes, (0, 0) when the mint has no candles
+    fn mint_usage<'a>(&'a self, mint: &'a str) -> BoxFuture<'a, Result<(usize, u64)>>;
+
+    // Index candles stored before the index existed, returns the number indexed
+    fn reindex<'a>(&'a self) -> BoxFuture<'a, Result<usize>>;
+}
+
+// The store selected by KLINE_STORE: "redis" (default) or "memory". Every
+// manager of a process shares the same memory store.
+pub fn from_env() -> Result<Box<dyn KLineStore>> {
+    let store = std::env::var("KLINE_STORE").unwrap_or_else(|_| "redis".to_string());
+    match store.to_ascii_lowercase().as_str() {
+        "redis" => Ok(Box::new(RedisKLineStore::new(StorageVersion::from_env()))),
+        "memory" => Ok(Box::new(SHARED_MEMORY_STORE.clone())),
+        _ => anyhow::bail!(
+            "Unsupported KLINE_STORE: {} (expected redis or memory)",
+            store
+        ),
+    }
+}
+
+static SHARED_MEMORY_STORE: LazyLock<MemoryKLineStore> = LazyLock::new(MemoryKLineStore::default);
+
+fn score_range(from: Option<i64>, to: Option<i64>) -> (String, String) {
+    (
+        from.map_or("-inf".to_string(), |from| from.to_string()),
+        to.map_or("+inf".to_string(), |to| to.to_string()),
+    )
+}
+
+// Candles in Redis: one key per candle holding the encoded candle, and a ZSET
+// index of start times per mint and interval
+pub struct RedisKLineStore {
+    storage_version: StorageVersion,
+}
+
+impl RedisKLineStore {
+    pub fn new(storage_version: StorageVersion) -> Self {
+        Self { storage_version }
+    }
+
+    // Redis key of a candle; minute candles keep `kline:{mint}:{ts}` and
+    // rolled-up ones add the interval, e.g. `kline:{mint}:5m:{ts}`
+    pub fn candle_key(mint: &str, interval_secs: i64, timestamp: i64) -> String {
+        if interval_secs == 60 {
+            format!("kline:{}:{}", mint, timestamp)
+        } else {
+            format!(
+                "kline:{}:{}:{}",
+                mint,
+                interval_label(interval_secs),
+                timestamp
+            )
+        }
+    }
+
+    // Index of a mint's candles (ZSET of start times), e.g. `kline_index:{mint}`
+    // for minute candles and `kline_index:{mint}:5m` for rolled-up ones
+    pub fn index_key(mint: &str, interval_secs: i64) -> String {
+        if interval_secs == 60 {
+            format!("kline_index:{}", mint)
+        } else {
+            format!("kline_index:{}:{}", mint, interval_label(interval_secs))
+        }
+    }
+
+    // Mint and start time of a minute candle key, None for rolled-up candles
+    pub fn parse_kline_key(key: &str) -> Option<(&str, i64)> {
+        let mut parts = key.split(':');
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some("kline"), Some(mint), Some(ts), None) => Some((mint, ts.parse().ok()?)),
+            _ => None,
+        }
+    }
+
+    // Keys holding a mint's candles of every configured interval and their
+    // indexes, found through the indexes. Candles stored before the index
+    // existed are only listed once migration 1 has indexed them. Rollups of
+    // intervals since removed from KLINE_ROLLUP_INTERVALS aren't listed.
+    pub async fn mint_keys(con: &mut ConnectionManager, mint: &str) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        for interval in std::iter::once(60).chain(rollup_intervals().iter().copied()) {
+            let index_key = Self::index_key(mint, interval);
+            let times: Vec<i64> = con.zrange(&index_key, 0, -1).await?;
+            if times.is_empty() {
+                continue;
+            }
+            keys.push(index_key);
+            keys.extend(times.iter().map(|ts| Self::candle_key(mint, interval, *ts)));
+        }
+        Ok(keys)
+    }
+
+    // Keys of a mint's stored tick trades, annotations and positions
+    async fn record_keys(con: &mut ConnectionManager, mint: &str) -> Result<Vec<String>> {
+        let mut keys = vec![
+            trades::get_trades_key(mint),
+            annotations::get_annotations_key(mint),
+        ];
+        keys.extend(positions::get_position_keys(con, mint).await?);
+        Ok(keys)
+    }
+
+    // Recompute each mint's cumulative SOL volume from its stored candles, so mints
+    // traded before the volume hash existed pass the strategy universe filter.
+    // Returns the number of mints updated.
+    pub async fn rebuild_mint_volume(&self) -> Result<usize> {
+        let mut con = redis_helper::get_connection().await?;
+        let keys = redis_helper::scan_keys(&mut con, "kline:*:*").await?;
+
+        let mut volumes: HashMap<String, Decimal> = HashMap::new();
+        for key in &keys {
+            let Some((mint, _)) = Self::parse_kline_key(key) else {
+                continue;
+            };
+            let Some(data) = con.get::<_, Option<Vec<u8>>>(key).await? else {
+                continue;
+            };
+            match kline_codec::decode_kline(&data) {
+                Ok(kline) => {
+                    *volumes.entry(mint.to_string()).or_default() +=
+                        kline.volume_sol.parse().unwrap_or(Decimal::ZERO);
+                }
+                Err(e) => warn!("Skipping undecodable K-line {}: {}", key, e),
+            }
+        }
+
+        let volumes: Vec<(&String, &Decimal)> = volumes.iter().collect();
+        for chunk in volumes.chunks(limits::limits().redis_pipeline_size) {
+            let mut pipe = redis::pipe();
+            for (mint, volume) in chunk {
+                pipe.hset(MINT_VOLUME_KEY, *mint, volume.to_f64().unwrap_or_default())
+                    .ignore();
+            }
+            let _: () = pipe.query_async(&mut *con).await?;
+        }
+        Ok(volumes.len())
+    }
+
+    // Tag candles stored before source tagging. Mints that haven't graduated only
+    // traded on the bonding curve; candles of graduated mints are left untagged
+    // since they can't be attributed after the fact. Returns the number of candles tagged.
+    pub async fn backfill_kline_source(&self) -> Result<usize> {
+        let mut con = redis_helper::get_connection().await?;
+        let keys = redis_helper::scan_keys(&mut con, "kline:*:*").await?;
+
+        let mut graduated: HashMap<String, bool> = HashMap::new();
+        let mut tagged = 0;
+        for key in &keys {
+            let Some((mint, _)) = Self::parse_kline_key(key) else {
+                continue;
+            };
+            if !graduated.contains_key(mint) {
+                // Without an activity record the mint's state is unknown, treat it as graduated
+                let complete = Self::get_mint_complete(&mut con, mint)
+                    .await?
+                    .unwrap_or(true);
+                graduated.insert(mint.to_string(), complete);
+            }
+            if graduated[mint] {
+                continue;
+            }
+
+            let Some(data) = con.get::<_, Option<Vec<u8>>>(key).await? else {
+                continue;
+            };
+            let mut kline = match kline_codec::decode_kline(&data) {
+                Ok(kline) => kline,
+                Err(e) => {
+                    warn!("Skipping undecodable K-line {}: {}", key, e);
+                    continue;
+                }
+            };
+            if kline.source.is_some() {
+                continue;
+            }
+            kline.source = Some(TradeSource::Pump);
+            let _: () = con
+                .set(
+                    key,
+                    kline_codec::encode_kline(&kline, self.storage_version)?,
+                )
+                .await?;
+            tagged += 1;
+        }
+        Ok(tagged)
+    }
+
+    // Whether a mint's bonding curve completed, None without an activity record.
+    // Falls back to the legacy per-mint key for data not migrated yet.
+    async fn get_mint_complete(con: &mut ConnectionManager, mint: &str) -> Result<Option<bool>> {
+        let score: Option<f64> = con.zscore(MINT_ACTIVITY_KEY, mint).await?;
+        if score.is_some() {
+            return Ok(Some(con.sismember(MINT_COMPLETE_KEY, mint).await?));
+        }
+        let legacy: Option<String> = con
+            .get(format!("{}{}", LEGACY_ACTIVITY_PREFIX, mint))
+            .await?;
+        Ok(legacy
+            .and_then(|a| serde_json::from_str::<MintActivity>(&a).ok())
+            .map(|a| a.complete))
+    }
+
+    // Move the legacy per-mint activity keys into the activity ZSET and the
+    // completed set. A newer activity already in the ZSET is kept. Returns the
+    // number of mints moved.
+    pub async fn migrate_mint_activity(&self) -> Result<usize> {
+        let mut con = redis_helper::clone_connection().await?;
+        let keys =
+            redis_helper::scan_keys(&mut con, &format!("{}*", LEGACY_ACTIVITY_PREFIX)).await?;
+
+        let mut moved = 0;
+        for chunk in keys.chunks(limits::limits().redis_pipeline_size) {
+            let values: Vec<Option<String>> = con.mget(chunk).await?;
+            let mut pipe = redis::pipe();
+            for (key, value) in chunk.iter().zip(values) {
+                let Some(mint) = key.strip_prefix(LEGACY_ACTIVITY_PREFIX) else {
+                    continue;
+                };
+                let activity = value.and_then(|a| serde_json::from_str::<MintActivity>(&a).ok());
+                if let Some(activity) = activity
+                    && !mint.is_empty()
+                {
+                    pipe.cmd("ZADD")
+                        .arg(MINT_ACTIVITY_KEY)
+                        .arg("GT")
+                        .arg(activity.timestamp)
+                        .arg(mint)
+                        .ignore();
+                    if activity.complete {
+                        pipe.sadd(MINT_COMPLETE_KEY, mint).ignore();
+                    }
+                    moved += 1;
+                }
+                pipe.del(key).ignore();
+            }
+            let _: () = pipe.query_async(&mut con).await?;
+        }
+        Ok(moved)
+    }
+}
+
+impl KLineStore for RedisKLineStore {
+    fn get_candles<'a>(
+        &'a self,
+        mint: &'a str,
+        at: &'a [(i64, i64)],
+    ) -> BoxFuture<'a, Result<Vec<Option<KLineData>>>> {
+        Box::pin(async move {
+            if at.is_empty() {
+                return Ok(Vec::new());
+            }
+            let keys: Vec<String> = at
+                .iter()
+                .map(|(interval, ts)| Self::candle_key(mint, *interval, *ts))
+                .collect();
+            let mut con = redis_helper::get_connection().await?;
+            let values: Vec<Option<Vec<u8>>> = con.mget(&keys).await?;
+            Ok(values
+                .into_iter()
+                .zip(&keys)
+                .map(|(data, key)| {
+                    kline_codec::decode_kline(&data?)
+                        .inspect_err(|e| warn!("Skipping undecodable K-line {}: {}", key, e))
+                        .ok()
+                })
+                .collect())
+        })
+    }
+
+    fn put_candles<'a>(
+        &'a self,
+        mint: &'a str,
+        candles: &'a [(i64, KLineData)],
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut con = redis_helper::get_connection().await?;
+            for chunk in candles.chunks(limits::limits().redis_pipeline_size) {
+                let mut pipe = redis::pipe();
+                for (interval, kline) in chunk {
+                    // No expiration, idle mints are deleted by the cleanup
+                    pipe.set(
+                        Self::candle_key(mint, *interval, kline.timestamp),
+                        kline_codec::encode_kline(kline, self.storage_version)?,
+                    )
+                    .ignore()
+                    .zadd(
+                        Self::index_key(mint, *interval),
+                        kline.timestamp,
+                        kline.timestamp,
+                    )
+                    .ignore();
+                }
+                let _: () = pipe.query_async(&mut *con).await?;
+            }
+            Ok(())
+        })
+    }
+
+    fn candle_times<'a>(
+        &'a self,
+        mint: &'a str,
+        interval_secs: i64,
+        from: Option<i64>,
+        to: Option<i64>,
+        limit: Option<usize>,
+    ) -> BoxFuture<'a, Result<Vec<i64>>> {
+        Box::pin(async move {
+            let mut con = redis_helper::get_connection().await?;
+            let index_key = Self::index_key(mint, interval_secs);
+            let (min, max) = score_range(from, to);
+            let mut times: Vec<i64> = match limit {
+                Some(limit) => {
+                    con.zrevrangebyscore_limit(&index_key, &max, &min, 0, limit as isize)
+                        .await?
+                }
+                None => con.zrangebyscore(&index_key, &min, &max).await?,
+            };
+            times.sort();
+            Ok(times)
+        })
+    }
+
+    fn delete_candles<'a>(
+        &'a self,
+        mint: &'a str,
+        interval_secs: i64,
+        from: Option<i64>,
+        to: Option<i64>,
+    ) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(async move {
+            let times = self
+                .candle_times(mint, interval_secs, from, to, None)
+                .await?;
+            let (min, max) = score_range(from, to);
+            let mut con = redis_helper::get_connection().await?;
+            let _: () = con
+                .zrembyscore(Self::index_key(mint, interval_secs), &min, &max)
+                .await?;
+            let keys: Vec<String> = times
+                .iter()
+                .map(|ts| Self::candle_key(mint, interval_secs, *ts))
+                .collect();
+            for chunk in keys.chunks(limits::limits().redis_pipeline_size) {
+                let _: () = con.del(chunk).await?;
+            }
+            Ok(keys.len())
+        })
+    }
+
+    fn record_activity<'a>(
+        &'a self,
+        mint: &'a str,
+        timestamp: u64,
+        complete: bool,
+        volume_sol: Decimal,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            // Activity time, complete status and volume in one round trip
+            let mut pipe = redis::pipe();
+            pipe.zadd(MINT_ACTIVITY_KEY, mint, timestamp).ignore();
+            if complete {
+                pipe.sadd(MINT_COMPLETE_KEY, mint).ignore();
+            }
+            pipe.hincr(
+                MINT_VOLUME_KEY,
+                mint,
+                volume_sol.to_f64().unwrap_or_default(),
+            )
+            .ignore();
+            let mut con = redis_helper::get_connection().await?;
+            let _: () = pipe.query_async(&mut *con).await?;
+            Ok(())
+        })
+    }
+
+    fn last_activity<'a>(&'a self, mint: &'a str) -> BoxFuture<'a, Result<Option<u64>>> {
+        Box::pin(async move {
+            let mut con = redis_helper::get_connection().await?;
+            Ok(con.zscore(MINT_ACTIVITY_KEY, mint).await?)
+        })
+    }
+
+    fn active_mints<'a>(&'a self) -> BoxFuture<'a, Result<Vec<(String, u64, bool)>>> {
+        Box::pin(async move {
+            let mut con = redis_helper::get_connection().await?;
+            let activity: Vec<(String, u64)> =
+                con.zrevrange_withscores(MINT_ACTIVITY_KEY, 0, -1).await?;
+            let complete: HashSet<String> = con.smembers(MINT_COMPLETE_KEY).await?;
+            Ok(activity
+                .into_iter()
+                .map(|(mint, timestamp)| {
+                    let is_complete = complete.contains(&mint);
+                    (mint, timestamp, is_complete)
+                })
+                .collect())
+        })
+    }
+
+    fn idle_mints<'a>(&'a self, cutoff: u64) -> BoxFuture<'a, Result<Vec<String>>> {
+        Box::pin(async move {
+            let mut con = redis_helper::get_connection().await?;
+            Ok(con
+                .zrangebyscore(MINT_ACTIVITY_KEY, "-inf", format!("({}", cutoff))
+                .await?)
+        })
+    }
+
+    fn delete_mint<'a>(&'a self, mint: &'a str) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(async move {
+            let mut con = redis_helper::clone_connection().await?;
+            let mut keys = Self::mint_keys(&mut con, mint).await?;
+            let deleted = keys.len();
+            // Also the stored tick trades, annotations and positions
+            keys.extend(Self::record_keys(&mut con, mint).await?);
+            for chunk in keys.chunks(limits::limits().redis_pipeline_size) {
+                let _: () = con.del(chunk).await?;
+            }
+
+            let _: () = redis::pipe()
+                .zrem(MINT_ACTIVITY_KEY, mint)
+                .ignore()
+                .srem(MINT_COMPLETE_KEY, mint)
+                .ignore()
+                .hdel(MINT_VOLUME_KEY, mint)
+                .ignore()
+                .query_async(&mut con)
+                .await?;
+            Ok(deleted)
+        })
+    }
+
+    fn mint_sizes<'a>(
+        &'a self,
+        mints: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<(usize, Decimal)>>> {
+        Box::pin(async move {
+            if mints.is_empty() {
+                return Ok(Vec::new());
+            }
+            let mut con = redis_helper::get_connection().await?;
+            let mut values: Vec<redis::Value> = Vec::with_capacity(mints.len() * 2);
+            for chunk in mints.chunks(limits::limits().redis_pipeline_size) {
+                let mut pipe = redis::pipe();
+                for mint in chunk {
+                    pipe.zcard(Self::index_key(mint, 60))
+                        .hget(MINT_VOLUME_KEY, mint);
+                }
+                let chunk_values: Vec<redis::Value> = pipe.query_async(&mut *con).await?;
+                values.extend(chunk_values);
+            }
+
+            values
+                .chunks(2)
+                .map(|pair| {
+                    let count: usize = redis::from_redis_value(&pair[0])?;
+                    let volume: Option<String> = redis::from_redis_value(&pair[1])?;
+                    let volume = volume.and_then(|v| v.parse().ok()).unwrap_or_default();
+                    Ok((count, volume))
+                })
+                .collect()
+        })
+    }
+
+    fn stats<'a>(&'a self) -> BoxFuture<'a, Result<(usize, usize)>> {
+        Box::pin(async move {
+            // Indexed minute candles of the tracked mints, rolled-up ones are derived from them
+            let mut con = redis_helper::get_connection().await?;
+            let mints: Vec<String> = con.zrange(MINT_ACTIVITY_KEY, 0, -1).await?;
+            let mut counts: Vec<usize> = Vec::with_capacity(mints.len());
+            for chunk in mints.chunks(limits::limits().redis_pipeline_size) {
+                let mut pipe = redis::pipe();
+                for mint in chunk {
+                    pipe.zcard(Self::index_key(mint, 60));
+                }
+                let chunk_counts: Vec<usize> = pipe.query_async(&mut *con).await?;
+                counts.extend(chunk_counts);
+            }
+            Ok((
+                counts.iter().filter(|count| **count > 0).count(),
+                counts.iter().sum(),
+            ))
+        })
+    }
+
+    fn publish_candle<'a>(
+        &'a self,
+        mint: &'a str,
+        event: CandleEvent,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut con = redis_helper::clone_connection().await?;
+            match event {
+                CandleEvent::Closed { closed_before } => {
+                    candle_stream::publish_candle_closed(&mut con, mint, closed_before).await
+                }
+                CandleEvent::Corrected { candle_ts } => {
+                    candle_stream::publish_candle_corrected(&mut con, mint, candle_ts).await
+                }
+                CandleEvent::Updated(update) => candle_hub::publish(&mut con, *update).await,
+            }
+        })
+    }
+
+    fn count_integrity<'a>(&'a self, counter: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut con = redis_helper::get_connection().await?;
+            let _: () = con.hincr(INTEGRITY_STATS_KEY, counter, 1).await?;
+            Ok(())
+        })
+    }
+
+    fn integrity_stats<'a>(&'a self) -> BoxFuture<'a, Result<HashMap<String, usize>>> {
+        Box::pin(async move {
+            let mut con = redis_helper::get_connection().await?;
+            Ok(con.hgetall(INTEGRITY_STATS_KEY).await?)
+        })
+    }
+
+    fn record_trade<'a>(&'a self, trade: &'a TradeRecord) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut con = redis_helper::clone_connection().await?;
+            trades::push_trade(&mut con, trade).await?;
+            positions::record_trade(&mut con, trade).await
+        })
+    }
+
+    fn mint_usage<'a>(&'a self, mint: &'a str) -> BoxFuture<'a, Result<(usize, u64)>> {
+        Box::pin(async move {
+            let mut con = redis_helper::clone_connection().await?;
+            let mut keys = Self::mint_keys(&mut con, mint).await?;
+            if keys.is_empty() {
+                return Ok((0, 0));
+            }
+            keys.extend(Self::record_keys(&mut con, mint).await?);
+
+            // Sizes come from MEMORY USAGE, Redis' estimate per key
+            let mut bytes = 0;
+            for chunk in keys.chunks(limits::limits().redis_pipeline_size) {
+                let mut pipe = redis::pipe();
+                for key in chunk {
+                    pipe.cmd("MEMORY").arg("USAGE").arg(key);
+                }
+                let sizes: Vec<Option<u64>> = pipe.query_async(&mut con).await?;
+                bytes += sizes.into_iter().flatten().sum::<u64>();
+            }
+            Ok((keys.len(), bytes))
+        })
+    }
+
+    fn reindex<'a>(&'a self) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(async move {
+            let mut con = redis_helper::get_connection().await?;
+            let keys = redis_helper::scan_keys(&mut con, "kline:*:*").await?;
+
+            let mut indexed = 0;
+            for key in &keys {
+                let mut parts = key.splitn(3, ':').skip(1);
+                let (Some(mint), Some(Ok(ts))) =
+                    (parts.next(), parts.next().map(str::parse::<i64>))
+                else {
+                    continue;
+                };
+                let added: usize = con.zadd(Self::index_key(mint, 60), ts, ts).await?;
+                indexed += added;
+            }
+            Ok(indexed)
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    // Candles by (mint, interval), keyed by start time
+    candles: HashMap<(String, i64), BTreeMap<i64, KLineData>>,
+    activity: HashMap<String, u64>,
+    complete: HashSet<String>,
+    volume: HashMap<String, Decimal>,
+    integrity: HashMap<String, usize>,
+}
+
+// Candles in process memory. Clones share the same candles; nothing survives
+// a restart and other processes don't see them. Tick trades and positions
+// aren't kept, and candle events only reach this process and the message bus.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryKLineStore {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+impl MemoryKLineStore {
+    // Run `f` on the locked state and return its result as an already completed future
+    fn with_state<'a, T: Send + 'a>(
+        &self,
+        f: impl FnOnce(&mut MemoryState) -> T,
+    ) -> BoxFuture<'a, Result<T>> {
+        let value = f(&mut self.state.lock().unwrap_or_else(|e| e.into_inner()));
+        Box::pin(std::future::ready(Ok(value)))
+    }
+}
+
+impl KLineStore for MemoryKLineStore {
+    fn get_candles<'a>(
+        &'a self,
+        mint: &'a str,
+        at: &'a [(i64, i64)],
+    ) -> BoxFuture<'a, Result<Vec<Option<KLineData>>>> {
+        self.with_state(|state| {
+            at.iter()
+                .map(|(interval, ts)| {
+                    state
+                        .candles
+                        .get(&(mint.to_string(), *interval))
+                        .and_then(|candles| candles.get(ts))
+                        .cloned()
+                })
+                .collect()
+        })
+    }
+
+    fn put_candles<'a>(
+        &'a self,
+        mint: &'a str,
+        candles: &'a [(i64, KLineData)],
+    ) -> BoxFuture<'a, Result<()>> {
+        self.with_state(|state| {
+            for (interval, kline) in candles {
+                state
+                    .candles
+                    .entry((mint.to_string(), *interval))
+                    .or_default()
+                    .insert(kline.timestamp, kline.clone());
+            }
+        })
+    }
+
+    fn candle_times<'a>(
+        &'a self,
+        mint: &'a str,
+        interval_secs: i64,
+        from: Option<i64>,
+        to: Option<i64>,
+        limit: Option<usize>,
+    ) -> BoxFuture<'a, Result<Vec<i64>>> {
+        self.with_state(|state| {
+            let Some(candles) = state.candles.get(&(mint.to_string(), interval_secs)) else {
+                return Vec::new();
+            };
+            let in_range = candles
+                .range(from.unwrap_or(i64::MIN)..=to.unwrap_or(i64::MAX))
+                .map(|(ts, _)| *ts);
+            let mut times: Vec<i64> = in_range.rev().take(limit.unwrap_or(usize::MAX)).collect();
+            times.reverse();
+            times
+        })
+    }
+
+    fn delete_candles<'a>(
+        &'a self,
+        mint: &'a str,
+        interval_secs: i64,
+        from: Option<i64>,
+        to: Option<i64>,
+    ) -> BoxFuture<'a, Result<usize>> {
+        self.with_state(|state| {
+            let Some(candles) = state.candles.get_mut(&(mint.to_string(), interval_secs)) else {
+                return 0;
+            };
+            let before = candles.len();
+            candles.retain(|ts, _| {
+                from.is_some_and(|from| *ts < from) || to.is_some_and(|to| *ts > to)
+            });
+            before - candles.len()
+        })
+    }
+
+    fn record_activity<'a>(
+        &'a self,
+        mint: &'a str,
+        timestamp: u64,
+        complete: bool,
+        volume_sol: Decimal,
+    ) -> BoxFuture<'a, Result<()>> {
+        self.with_state(|state| {
+            state.activity.insert(mint.to_string(), timestamp);
+            if complete {
+                state.complete.insert(mint.to_string());
+            }
+            *state.volume.entry(mint.to_string()).or_default() += volume_sol;
+        })
+    }
+
+    fn last_activity<'a>(&'a self, mint: &'a str) -> BoxFuture<'a, Result<Option<u64>>> {
+        self.with_state(|state| state.activity.get(mint).copied())
+    }
+
+    fn active_mints<'a>(&'a self) -> BoxFuture<'a, Result<Vec<(String, u64, bool)>>> {
+        self.with_state(|state| {
+            let mut mints: Vec<(String, u64, bool)> = state
+                .activity
+                .iter()
+                .map(|(mint, ts)| (mint.clone(), *ts, state.complete.contains(mint)))
+                .collect();
+            mints.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            mints
+        })
+    }
+
+    fn idle_mints<'a>(&'a self, cutoff: u64) -> BoxFuture<'a, Result<Vec<String>>> {
+        self.with_state(|state| {
+            state
+                .activity
+                .iter()
+                .filter(|(_, ts)| **ts < cutoff)
+                .map(|(mint, _)| mint.clone())
+                .collect()
+        })
+    }
+
+    fn delete_mint<'a>(&'a self, mint: &'a str) -> BoxFuture<'a, Result<usize>> {
+        self.with_state(|state| {
+            let mut deleted = 0;
+            state.candles.retain(|(candle_mint, _), candles| {
+                let keep = candle_mint != mint;
+                if !keep {
+                    deleted += candles.len();
+                }
+                keep
+            });
+            state.activity.remove(mint);
+            state.complete.remove(mint);
+            state.volume.remove(mint);
+            deleted
+        })
+    }
+
+    fn mint_sizes<'a>(
+        &'a self,
+        mints: &'a [String],
+    ) -> BoxFuture<'a, Result<Vec<(usize, Decimal)>>> {
+        self.with_state(|state| {
+            mints
+                .iter()
+                .map(|mint| {
+                    let count = state
+                        .candles
+                        .get(&(mint.clone(), 60))
+                        .map_or(0, |candles| candles.len());
+                    (count, state.volume.get(mint).copied().unwrap_or_default())
+                })
+                .collect()
+        })
+    }
+
+    fn stats<'a>(&'a self) -> BoxFuture<'a, Result<(usize, usize)>> {
+        self.with_state(|state| {
+            let minute: Vec<usize> = state
+                .candles
+                .iter()
+                .filter(|((_, interval), candles)| *interval == 60 && !candles.is_empty())
+                .map(|(_, candles)| candles.len())
+                .collect();
+            (minute.len(), minute.iter().sum())
+        })
+    }
+
+    fn publish_candle<'a>(
+        &'a self,
+        mint: &'a str,
+        event: CandleEvent,
+    ) -> BoxFuture<'a, Result<()>> {
+        match event {
+            CandleEvent::Closed { closed_before } => {
+                message_bus::publish_candle_closed(mint, closed_before, None)
+            }
+            CandleEvent::Corrected { candle_ts } => {
+                message_bus::publish_candle_closed(mint, candle_ts + 60, Some(candle_ts))
+            }
+            CandleEvent::Updated(update) => candle_hub::hub().publish(*update),
+        }
+        Box::pin(std::future::ready(Ok(())))
+    }
+
+    fn count_integrity<'a>(&'a self, counter: &'a str) -> BoxFuture<'a, Result<()>> {
+        self.with_state(|state| {
+            *state.integrity.entry(counter.to_string()).or_default() += 1;
+        })
+    }
+
+    fn integrity_stats<'a>(&'a self) -> BoxFuture<'a, Result<HashMap<String, usize>>> {
+        self.with_state(|state| state.integrity.clone())
+    }
+
+    fn record_trade<'a>(&'a self, _trade: &'a TradeRecord) -> BoxFuture<'a, Result<()>> {
+        Box::pin(std::future::ready(Ok(())))
+    }
+
+    fn mint_usage<'a>(&'a self, mint: &'a str) -> BoxFuture<'a, Result<(usize, u64)>> {
+        self.with_state(|state| {
+            // Sized as compactly encoded candles, the in-memory size isn't tracked
+            state
+                .candles
+                .iter()
+                .filter(|((candle_mint, _), _)| candle_mint == mint)
+                .flat_map(|(_, candles)| candles.values())
+                .fold((0, 0), |(entries, bytes), kline| {
+                    let size = kline_codec::encode_kline(kline, StorageVersion::Compact)
+                        .map_or(0, |data| data.len() as u64);
+                    (entries + 1, bytes + size)
+                })
+        })
+    }
+
+    fn reindex<'a>(&'a self) -> BoxFuture<'a, Result<usize>> {
+        Box::pin(std::future::ready(Ok(0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::candle;
+
+    #[test]
+    fn test_redis_keys() {
+        assert_eq!(interval_label(300), "5m");
+        assert_eq!(interval_label(3600), "1h");
+        assert_eq!(interval_label(14400), "4h");
+        assert_eq!(interval_label(86400), "1d");
+        assert_eq!(
+            RedisKLineStore::candle_key("Mint", 300, 1_700_000_100),
+            "kline:Mint:5m:1700000100"
+        );
+        assert_eq!(
+            RedisKLineStore::candle_key("Mint", 60, 1_700_000_100),
+            "kline:Mint:1700000100"
+        );
+        assert_eq!(
+            RedisKLineStore::index_key("Mint", 3600),
+            "kline_index:Mint:1h"
+        );
+        assert_eq!(
+            RedisKLineStore::parse_kline_key("kline:Mint:1700000100"),
+            Some(("Mint", 1_700_000_100))
+        );
+        assert_eq!(
+            RedisKLineStore::parse_kline_key("kline:Mint:5m:1700000100"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        let store = MemoryKLineStore::default();
+        let candles = vec![
+            (60, candle(60, "1")),
+            (60, candle(120, "2")),
+            (60, candle(180, "3")),
+            (300, candle(0, "3")),
+        ];
+        store.put_candles("A", &candles).await.unwrap();
+
+        assert_eq!(
+            store.candle_times("A", 60, None, None, None).await.unwrap(),
+            vec![60, 120, 180]
+        );
+        // The limit keeps the latest candles in the range
+        assert_eq!(
+            store
+                .candle_times("A", 60, Some(60), Some(150), Some(1))
+                .await
+                .unwrap(),
+            vec![120]
+        );
+        let found = store
+            .get_candles("A", &[(60, 120), (60, 240), (300, 0)])
+            .await
+            .unwrap();
+        assert_eq!(found[0].as_ref().map(|k| k.close.as_str()), Some("2"));
+        assert!(found[1].is_none());
+        assert!(found[2].is_some());
+
+        assert_eq!(
+            store
+                .delete_candles("A", 60, Some(100), None)
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(store.stats().await.unwrap(), (1, 1));
+
+        store
+            .record_activity("A", 1_000, false, Decimal::ONE)
+            .await
+            .unwrap();
+        store
+            .record_activity("B", 2_000, true, Decimal::TWO)
+            .await
+            .unwrap();
+        store
+            .record_activity("A", 1_100, false, Decimal::ONE)
+            .await
+            .unwrap();
+        assert_eq!(
+            store.active_mints().await.unwrap(),
+            vec![
+                ("B".to_string(), 2_000, true),
+                ("A".to_string(), 1_100, false)
+            ]
+        );
+        assert_eq!(
+            store.idle_mints(1_500).await.unwrap(),
+            vec!["A".to_string()]
+        );
+        assert_eq!(
+            store.mint_sizes(&["A".to_string()]).await.unwrap(),
+            vec![(1, Decimal::TWO)]
+        );
+
+        assert_eq!(store.delete_mint("A").await.unwrap(), 2);
+        assert_eq!(store.last_activity("A").await.unwrap(), None);
+        assert_eq!(store.stats().await.unwrap(), (0, 0));
+    }
+}
@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::redis_helper;
+use crate::trades::TradeRecord;
+
+// Redis hash of wallet -> WalletLabel
+const LABELS_KEY: &str = "wallet:labels";
+
+/// 钱包类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelCategory {
+    /// 交易所热钱包
+    #[serde(alias = "cex")]
+    Exchange,
+    /// 狙击钱包
+    Sniper,
+    /// MEV机器人
+    Mev,
+    /// 其他交易机器人
+    Bot,
+    /// 其他已知实体
+    Other,
+}
+
+impl std::str::FromStr for LabelCategory {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "exchange" | "cex" => Ok(LabelCategory::Exchange),
+            "sniper" => Ok(LabelCategory::Sniper),
+            "mev" => Ok(LabelCategory::Mev),
+            "bot" => Ok(LabelCategory::Bot),
+            "other" | "" => Ok(LabelCategory::Other),
+            _ => Err(anyhow::anyhow!("Invalid label category: {}", s)),
+        }
+    }
+}
+
+/// 钱包标签，如 "Binance 热钱包"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WalletLabel {
+    pub name: String,
+    pub category: LabelCategory,
+}
+
+/// 导入文件中的一条标签
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct LabelEntry {
+    pub wallet: String,
+    pub name: String,
+    #[serde(default = "default_category")]
+    pub category: LabelCategory,
+}
+
+fn default_category() -> LabelCategory {
+    LabelCategory::Other
+}
+
+/// 附带钱包标签的交易，标签字段在交易字段之外追加
+#[derive(Debug, Clone, Serialize)]
+pub struct LabeledTrade {
+    #[serde(flatten)]
+    pub trade: TradeRecord,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<WalletLabel>,
+}
+
+/// 解析标签文件：JSON数组（`[{"wallet", "name", "category"}]`），
+/// 或每行 `wallet,name,category` 的CSV（可以有表头，类别可省略，#开头为注释）
+pub fn parse_labels(content: &str) -> Result<Vec<LabelEntry>> {
+    if content.trim_start().starts_with('[') {
+        return serde_json::from_str(content).context("Invalid JSON label file");
+    }
+
+    let mut entries = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if i == 0 && fields[0].eq_ignore_ascii_case("wallet") {
+            continue;
+        }
+        let (wallet, name) = match fields.as_slice() {
+            [wallet, name, ..] if !wallet.is_empty() && !name.is_empty() => (wallet, name),
+            _ => anyhow::bail!("Line {}: expected wallet,name[,category]", i + 1),
+        };
+        let category = fields
+            .get(2)
+            .map(|c| c.parse())
+            .transpose()
+            .with_context(|| format!("Line {}", i + 1))?
+            .unwrap_or(LabelCategory::Other);
+        entries.push(LabelEntry {
+            wallet: wallet.to_string(),
+            name: name.to_string(),
+            category,
+        });
+    }
+    Ok(entries)
+}
+
+/// 写入标签，已有标签的钱包会被覆盖，返回写入数量
+pub async fn set_labels(entries: &[LabelEntry]) -> Result<usize> {
+    if entries.is_empty() {
+        return Ok(0);
+    }
+    let mut items = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let label = WalletLabel {
+            name: entry.name.clone(),
+            category: entry.category,
+        };
+        items.push((entry.wallet.clone(), serde_json::to_string(&label)?));
+    }
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = con.hset_multiple(LABELS_KEY, &items).await?;
+    Ok(items.len())
+}
+
+/// 删除钱包标签，返回是否存在
+pub async fn remove_label(wallet: &str) -> Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    let removed: usize = con.hdel(LABELS_KEY, wallet).await?;
+    Ok(removed > 0)
+}
+
+/// 所有钱包标签，按钱包地址排序
+pub async fn get_all_labels() -> Result<Vec<(String, WalletLabel)>> {
+    let mut con = redis_helper::get_connection().await?;
+    let data: HashMap<String, String> = con.hgetall(LABELS_KEY).await?;
+    let mut labels: Vec<(String, WalletLabel)> = data
+        .into_iter()
+        .filter_map(|(wallet, d)| Some((wallet, serde_json::from_str(&d).ok()?)))
+        .collect();
+    labels.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(labels)
+}
+
+/// 查询一组钱包的标签，没有标签的钱包不在结果中
+pub async fn get_labels<'a>(
+    wallets: impl IntoIterator<Item = &'a str>,
+) -> Result<HashMap<String, WalletLabel>> {
+    let wallets: Vec<&str> = wallets
+        .into_iter()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    if wallets.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut con = redis_helper::get_connection().await?;
+    let data: Vec<Option<String>> = redis::cmd("HMGET")
+        .arg(LABELS_KEY)
+        .arg(&wallets)
+        .query_async(&mut *con)
+        .await?;
+    Ok(wallets
+        .into_iter()
+        .zip(data)
+        .filter_map(|(wallet, d)| Some((wallet.to_string(), serde_json::from_str(&d?).ok()?)))
+        .collect())
+}
+
+/// 给交易附上交易者的标签
+pub async fn label_trades(trades: Vec<TradeRecord>) -> Result<Vec<LabeledTrade>> {
+    let labels = get_labels(trades.iter().map(|t| t.user.as_str())).await?;
+    Ok(trades
+        .into_iter()
+        .map(|trade| LabeledTrade {
+            label: labels.get(&trade.user).cloned(),
+            trade,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_labels() {
+        let csv = "wallet,name,category\n# comment\nAAA, Binance 1 ,cex\nBBB,Some Bot\n";
+        let entries = parse_labels(csv).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "Binance 1");
+        assert_eq!(entries[0].category, LabelCategory::Exchange);
+        assert_eq!(entries[1].category, LabelCategory::Other);
+        assert!(parse_labels("CCC,x,whale").is_err());
+        assert!(parse_labels("CCC").is_err());
+
+        let json = r#"[{"wallet": "DDD", "name": "jito", "category": "mev"}, {"wallet": "EEE", "name": "x"}]"#;
+        let entries = parse_labels(json).unwrap();
+        assert_eq!(entries[0].category, LabelCategory::Mev);
+        assert_eq!(entries[1].category, LabelCategory::Other);
+        assert!(parse_labels(r#"[{"wallet": "F", "name": "x", "category": "whale"}]"#).is_err());
+    }
+}
@@ -0,0 +1,575 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use solana_client::rpc_request::RpcRequest;
+use std::collections::HashMap;
+use tracing::{debug, info, warn};
+
+use crate::alerts;
+use crate::localized;
+use crate::notification::NotificationManager;
+use crate::notification_format::Language;
+use crate::remote_config;
+use crate::severity;
+use crate::strategy::StrategyAlert;
+use crate::supervisor;
+use crate::{get_rpc_client_with_retry, redis_helper};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchBuyer {
+    pub wallet: String,
+    pub sol_amount: String,
+    pub token_amount: String,
+    pub slot: u64,
+    /// Wallet that funded this buyer (source of its earliest recent SOL transfer in)
+    pub funder: Option<String>,
+    #[serde(default)]
+    pub signature: String,
+    /// Creator buy, or a buy bundled into the create transaction or slot
+    #[serde(default)]
+    pub insider: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchInfo {
+    pub mint: String,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub creator: String,
+    pub created_at: i64,
+    pub slot: u64,
+    /// Signature of the create transaction
+    #[serde(default)]
+    pub signature: String,
+    pub buyers: Vec<LaunchBuyer>,
+    /// Share of the supply (%) bought by the creator at launch
+    #[serde(default)]
+    pub dev_buy_pct: f64,
+    /// Other wallets that bought in the create transaction or slot
+    #[serde(default)]
+    pub bundled_wallets: Vec<String>,
+    /// Share of the supply (%) bought by the creator and bundled wallets at launch
+    #[serde(default)]
+    pub insider_allocation_pct: f64,
+    /// Warning flag: insiders took at least LAUNCH_INSIDER_ALERT_PCT of the supply
+    #[serde(default)]
+    pub insider_heavy: bool,
+    /// Whether funding analysis of the first buyers has completed
+    pub analyzed: bool,
+    /// Size of the largest group of first buyers sharing one funder
+    pub largest_funder_cluster: usize,
+    /// Warning flag: many first buyers were funded by the same source
+    pub sniper_cluster: bool,
+}
+
+pub struct LaunchConfig {
+    pub first_buyers: usize,
+    pub cluster_min_wallets: usize,
+    pub record_ttl_secs: u64,
+    pub alert_enabled: bool,
+    pub bundle_slots: u64,
+    pub insider_alert_pct: f64,
+}
+
+impl LaunchConfig {
+    pub fn from_env() -> Self {
+        Self {
+            first_buyers: remote_config::var("LAUNCH_FIRST_BUYERS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+            cluster_min_wallets: remote_config::var("SNIPER_CLUSTER_MIN_WALLETS")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .unwrap_or(3),
+            record_ttl_secs: std::env::var("LAUNCH_RECORD_TTL_SECS")
+                .unwrap_or_else(|_| "86400".to_string())
+                .parse()
+                .unwrap_or(86400),
+            alert_enabled: remote_config::var("LAUNCH_ALERT_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            bundle_slots: remote_config::var("LAUNCH_BUNDLE_SLOTS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0),
+            insider_alert_pct: remote_config::var("LAUNCH_INSIDER_ALERT_PCT")
+                .unwrap_or_else(|_| "20".to_string())
+                .parse()
+                .unwrap_or(20.0),
+        }
+    }
+}
+
+// Generate Redis key for launch metadata
+pub fn get_launch_key(mint: &str) -> String {
+    format!("launch:{}", mint)
+}
+
+// Generate Redis key claimed by the process that analyzes a launch
+fn get_analysis_claim_key(mint: &str) -> String {
+    format!("launch:{}:analyzed", mint)
+}
+
+// Sorted set of launched mints scored by creation time, so launches are
+// listed without scanning for their keys
+const LAUNCH_INDEX_KEY: &str = "launches:created";
+
+// Mints whose launch was recorded and hasn't expired yet, oldest first.
+// Index entries older than the launch record TTL are dropped on the way.
+pub async fn get_recorded_launches() -> Result<Vec<String>> {
+    let config = LaunchConfig::from_env();
+    let expired_before = chrono::Utc::now().timestamp() - config.record_ttl_secs as i64;
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = con
+        .zrembyscore(LAUNCH_INDEX_KEY, "-inf", format!("({}", expired_before))
+        .await?;
+    Ok(con.zrange(LAUNCH_INDEX_KEY, 0, -1).await?)
+}
+
+// Record a newly created token so its first buyers can be tracked
+pub async fn record_create(
+    mint: &str,
+    name: &str,
+    symbol: &str,
+    uri: &str,
+    creator: &str,
+    slot: u64,
+    signature: &str,
+) -> Result<()> {
+    let config = LaunchConfig::from_env();
+    let info = LaunchInfo {
+        mint: mint.to_string(),
+        name: name.to_string(),
+        symbol: symbol.to_string(),
+        uri: uri.to_string(),
+        creator: creator.to_string(),
+        created_at: chrono::Utc::now().timestamp(),
+        slot,
+        signature: signature.to_string(),
+        buyers: Vec::new(),
+        dev_buy_pct: 0.0,
+        bundled_wallets: Vec::new(),
+        insider_allocation_pct: 0.0,
+        insider_heavy: false,
+        analyzed: false,
+        largest_funder_cluster: 0,
+        sniper_cluster: false,
+    };
+
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = redis::pipe()
+        .set_ex(
+            get_launch_key(mint),
+            serde_json::to_string(&info)?,
+            config.record_ttl_secs,
+        )
+        .ignore()
+        .zadd(LAUNCH_INDEX_KEY, mint, info.created_at)
+        .ignore()
+        .query_async(&mut *con)
+        .await?;
+    info!(
+        "🆕 New launch recorded: {} ({}) mint= {}",
+        name, symbol, mint
+    );
+    Ok(())
+}
+
+// Whether a buy falls in the launch window: the create transaction or the
+// bundle slots after it. Later buys, by the creator too, are ordinary buys.
+fn is_insider_buy(info: &LaunchInfo, signature: &str, slot: u64, bundle_slots: u64) -> bool {
+    (!info.signature.is_empty() && signature == info.signature) || slot <= info.slot + bundle_slots
+}
+
+// Share of the fixed 1B token supply bought by the given buyers, in percent
+fn supply_pct<'a>(buyers: impl Iterator<Item = &'a LaunchBuyer>) -> f64 {
+    let tokens: Decimal = buyers
+        .filter_map(|b| b.token_amount.parse::<Decimal>().ok())
+        .sum();
+    (tokens / Decimal::new(1_000_000_000, 0) * Decimal::ONE_HUNDRED)
+        .to_f64()
+        .unwrap_or(0.0)
+}
+
+// Recompute the dev buy and insider allocation from the insider buyers
+fn update_insider_allocation(info: &mut LaunchInfo, insider_alert_pct: f64) {
+    info.dev_buy_pct = supply_pct(
+        info.buyers
+            .iter()
+            .filter(|b| b.insider && b.wallet == info.creator),
+    );
+    info.insider_allocation_pct = supply_pct(info.buyers.iter().filter(|b| b.insider));
+    info.bundled_wallets = info
+        .buyers
+        .iter()
+        .filter(|b| b.insider && b.wallet != info.creator)
+        .map(|b| b.wallet.clone())
+        .collect();
+    info.insider_heavy = info.insider_allocation_pct >= insider_alert_pct;
+}
+
+// Append a buyer to the launch record while fewer than N buyers are known.
+// Insider buys are always recorded, and repeated insider buys are added up.
+// Once the N-th distinct buyer is recorded, funding analysis is started.
+pub async fn record_buyer(
+    mint: &str,
+    wallet: &str,
+    sol_amount: Decimal,
+    token_amount: Decimal,
+    slot: u64,
+    signature: &str,
+) -> Result<()> {
+    let config = LaunchConfig::from_env();
+    let key = get_launch_key(mint);
+
+    let mut pushed = false;
+    let completed = {
+        // Hold the connection for the whole read-modify-write
+        let mut con = redis_helper::get_connection().await?;
+        let existing: Option<String> = con.get(&key).await?;
+        let Some(data) = existing else {
+            // Not a launch we saw being created
+            return Ok(());
+        };
+        let mut info: LaunchInfo = serde_json::from_str(&data)?;
+        let insider = is_insider_buy(&info, signature, slot, config.bundle_slots);
+        let was_heavy = info.insider_heavy;
+
+        if let Some(buyer) = info.buyers.iter_mut().find(|b| b.wallet == wallet) {
+            if !(insider && buyer.insider) {
+                return Ok(());
+            }
+            let add = |total: &str, amount: Decimal| {
+                (total.parse::<Decimal>().unwrap_or_default() + amount).to_string()
+            };
+            buyer.sol_amount = add(&buyer.sol_amount, sol_amount);
+            buyer.token_amount = add(&buyer.token_amount, token_amount);
+        } else if insider || info.buyers.len() < config.first_buyers {
+            pushed = true;
+            info.buyers.push(LaunchBuyer {
+                wallet: wallet.to_string(),
+                sol_amount: sol_amount.to_string(),
+                token_amount: token_amount.to_string(),
+                slot,
+                funder: None,
+                signature: signature.to_string(),
+                insider,
+            });
+        } else {
+            return Ok(());
+        }
+
+        if insider {
+            update_insider_allocation(&mut info, config.insider_alert_pct);
+            if info.insider_heavy && !was_heavy {
+                warn!(
+                    "👥 Insider-heavy launch {}: creator and {} bundled wallets bought {:.2}% of supply",
+                    mint,
+                    info.bundled_wallets.len(),
+                    info.insider_allocation_pct
+                );
+            }
+        }
+        let _: () = con
+            .set_ex(&key, serde_json::to_string(&info)?, config.record_ttl_secs)
+            .await?;
+
+        // Only the buy that brings the count to N starts the analysis
+        pushed && !info.analyzed && info.buyers.len() == config.first_buyers
+    };
+
+    if completed {
+        let mint = mint.to_string();
+        supervisor::spawn_supervised("launch_analysis", async move {
+            if let Err(e) = analyze_first_buyers(&mint).await {
+                warn!("First buyers analysis failed for {}: {}", mint, e);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+// Resolve funders of the first buyers and flag bundled snipes
+pub async fn analyze_first_buyers(mint: &str) -> Result<()> {
+    let config = LaunchConfig::from_env();
+    let Some(mut info) = get_launch(mint).await? else {
+        return Ok(());
+    };
+    if info.analyzed || !claim_analysis(mint, config.record_ttl_secs).await? {
+        return Ok(());
+    }
+
+    for buyer in info.buyers.iter_mut() {
+        match find_funder(&buyer.wallet).await {
+            Ok(funder) => buyer.funder = funder,
+            Err(e) => debug!("Failed to resolve funder for {}: {}", buyer.wallet, e),
+        }
+    }
+
+    info.largest_funder_cluster = largest_funder_cluster(&info.buyers);
+    info.sniper_cluster = info.largest_funder_cluster >= config.cluster_min_wallets;
+    info.analyzed = true;
+
+    redis_helper::setex(
+        get_launch_key(mint),
+        serde_json::to_string(&info)?,
+        config.record_ttl_secs,
+    )
+    .await?;
+
+    if info.sniper_cluster {
+        warn!(
+            "🎯 Sniper cluster detected for {}: {} of first {} buyers share one funder",
+            mint,
+            info.largest_funder_cluster,
+            info.buyers.len()
+        );
+    }
+
+    if config.alert_enabled {
+        send_launch_alert(&info).await?;
+    }
+
+    Ok(())
+}
+
+// Claim the analysis of a launch, so its alert is sent only once even when
+// several monitors record the same launch
+async fn claim_analysis(mint: &str, ttl_secs: u64) -> Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    let claimed: Option<String> = redis::cmd("SET")
+        .arg(get_analysis_claim_key(mint))
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(ttl_secs.max(1))
+        .query_async(&mut *con)
+        .await?;
+    Ok(claimed.is_some())
+}
+
+// Count buyers per funder and return the largest group
+fn largest_funder_cluster(buyers: &[LaunchBuyer]) -> usize {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for funder in buyers.iter().filter_map(|b| b.funder.as_deref()) {
+        *counts.entry(funder).or_default() += 1;
+    }
+    counts.values().copied().max().unwrap_or(0)
+}
+
+// Find the source of the oldest SOL transfer into a wallet among its recent signatures
+pub async fn find_funder(wallet: &str) -> Result<Option<String>> {
+    let params = json!([wallet, { "limit": 20 }]);
+    let signatures: Vec<Value> = get_rpc_client_with_retry(
+        |client| {
+            client
+                .send(RpcRequest::GetSignaturesForAddress, params.clone())
+                .map_err(anyhow::Error::from)
+        },
+        2,
+    )
+    .await?;
+
+    // Signatures are returned newest first; the oldest one is the likely funding transfer
+    let Some(signature) = signatures
+        .last()
+        .and_then(|s| s.get("signature"))
+        .and_then(|s| s.as_str())
+    else {
+        return Ok(None);
+    };
+
+    let params = json!([
+        signature,
+        { "encoding": "jsonParsed", "maxSupportedTransactionVersion": 0 }
+    ]);
+    let transaction: Value = get_rpc_client_with_retry(
+        |client| {
+            client
+                .send(RpcRequest::GetTransaction, params.clone())
+                .map_err(anyhow::Error::from)
+        },
+        2,
+    )
+    .await?;
+
+    Ok(find_transfer_source(&transaction, wallet))
+}
+
+// Look for a system transfer to `wallet` in the top-level and inner instructions
+fn find_transfer_source(transaction: &Value, wallet: &str) -> Option<String> {
+    let top_level = transaction
+        .pointer("/transaction/message/instructions")
+        .and_then(|i| i.as_array())
+        .into_iter()
+        .flatten();
+    let inner = transaction
+        .pointer("/meta/innerInstructions")
+        .and_then(|i| i.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|group| group.get("instructions").and_then(|i| i.as_array()))
+        .flatten();
+
+    top_level.chain(inner).find_map(|instruction| {
+        let parsed = instruction.get("parsed")?;
+        if instruction.get("program")?.as_str()? != "system"
+            || parsed.get("type")?.as_str()? != "transfer"
+        {
+            return None;
+        }
+        let info = parsed.get("info")?;
+        if info.get("destination")?.as_str()? != wallet {
+            return None;
+        }
+        Some(info.get("source")?.as_str()?.to_string())
+    })
+}
+
+async fn send_launch_alert(info: &LaunchInfo) -> Result<()> {
+    let notification_manager = NotificationManager::new()?;
+    let language = Language::from_env();
+    let warning = if info.sniper_cluster {
+        localized!(
+            language,
+            "⚠️ 狙击集群: 首批{}个买家中有{}个由同一地址资助",
+            "⚠️ Sniper cluster: {1} of the first {0} buyers were funded by one address",
+            info.buyers.len(),
+            info.largest_funder_cluster
+        )
+    } else {
+        localized!(language, "未发现狙击集群", "No sniper cluster found")
+    };
+    let insiders = localized!(
+        language,
+        "{}内部初始持仓 {:.2}%（开发者 {:.2}%，捆绑钱包 {} 个）",
+        "{}Insiders hold {:.2}% at launch (developer {:.2}%, {} bundled wallets)",
+        if info.insider_heavy { "⚠️ " } else { "" },
+        info.insider_allocation_pct,
+        info.dev_buy_pct,
+        info.bundled_wallets.len()
+    );
+    let mut alert = StrategyAlert {
+        mint: info.mint.clone(),
+        strategy_name: "新币首批买家分析".to_string(),
+        message: format!(
+            "{} ({}) - {} | {}",
+            info.name, info.symbol, warning, insiders
+        ),
+        timestamp: chrono::Local::now().timestamp(),
+        severity: None,
+        params: None,
+        klines: Vec::new(),
+    };
+    severity::score_alert(&mut alert).await;
+    alerts::record_alert(&alert).await?;
+    notification_manager.send_notification(&alert).await
+}
+
+// Get launch metadata for a mint
+pub async fn get_launch(mint: &str) -> Result<Option<LaunchInfo>> {
+    let data: Option<String> = redis_helper::get(get_launch_key(mint)).await?;
+    Ok(data.and_then(|d| serde_json::from_str(&d).ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buyer(wallet: &str, funder: Option<&str>) -> LaunchBuyer {
+        LaunchBuyer {
+            wallet: wallet.to_string(),
+            sol_amount: "1".to_string(),
+            token_amount: "1000".to_string(),
+            slot: 1,
+            funder: funder.map(|f| f.to_string()),
+            signature: String::new(),
+            insider: false,
+        }
+    }
+
+    #[test]
+    fn test_largest_funder_cluster() {
+        let buyers = vec![
+            buyer("a", Some("f1")),
+            buyer("b", Some("f1")),
+            buyer("c", Some("f2")),
+            buyer("d", None),
+            buyer("e", Some("f1")),
+        ];
+        assert_eq!(largest_funder_cluster(&buyers), 3);
+        assert_eq!(largest_funder_cluster(&[]), 0);
+    }
+
+    #[test]
+    fn test_insider_allocation() {
+        let mut info = LaunchInfo {
+            mint: "mint".to_string(),
+            name: "name".to_string(),
+            symbol: "SYM".to_string(),
+            uri: String::new(),
+            creator: "dev".to_string(),
+            created_at: 0,
+            slot: 100,
+            signature: "create".to_string(),
+            buyers: Vec::new(),
+            dev_buy_pct: 0.0,
+            bundled_wallets: Vec::new(),
+            insider_allocation_pct: 0.0,
+            insider_heavy: false,
+            analyzed: false,
+            largest_funder_cluster: 0,
+            sniper_cluster: false,
+        };
+        // A creator buy after the launch window is an ordinary buy
+        assert!(!is_insider_buy(&info, "later", 500, 0));
+        assert!(is_insider_buy(&info, "create", 100, 0));
+        assert!(is_insider_buy(&info, "other", 101, 1));
+        assert!(!is_insider_buy(&info, "other", 101, 0));
+
+        for (wallet, tokens, insider) in [
+            ("dev", "100000000", true),
+            ("a", "50000000", true),
+            ("c", "30000000", false),
+        ] {
+            let mut b = buyer(wallet, None);
+            b.token_amount = tokens.to_string();
+            b.insider = insider;
+            info.buyers.push(b);
+        }
+        update_insider_allocation(&mut info, 15.0);
+        assert_eq!(info.dev_buy_pct, 10.0);
+        assert_eq!(info.insider_allocation_pct, 15.0);
+        assert_eq!(info.bundled_wallets, vec!["a"]);
+        assert!(info.insider_heavy);
+    }
+
+    #[test]
+    fn test_find_transfer_source() {
+        let transaction = json!({
+            "transaction": { "message": { "instructions": [
+                { "program": "spl-token", "parsed": { "type": "transfer", "info": {} } }
+            ] } },
+            "meta": { "innerInstructions": [ { "instructions": [
+                {
+                    "program": "system",
+                    "parsed": {
+                        "type": "transfer",
+                        "info": { "source": "funder", "destination": "wallet", "lamports": 1 }
+                    }
+                }
+            ] } ] }
+        });
+        assert_eq!(
+            find_transfer_source(&transaction, "wallet"),
+            Some("funder".to_string())
+        );
+        assert_eq!(find_transfer_source(&transaction, "other"), None);
+    }
+}
@@ -1,135 +1,201 @@
-use std::{env, sync::Arc, time::Duration};
-
-use anyhow::Result;
-use rand::seq::IndexedRandom;
-use solana_client::rpc_client::RpcClient;
-use tokio::sync::OnceCell;
-use tracing::{debug, warn};
-
+#[cfg(feature = "server")]
+pub mod admin;
+#[cfg(feature = "server")]
+pub mod aggregate_cache;
+#[cfg(feature = "server")]
+pub mod alerts;
+#[cfg(feature = "server")]
+pub mod annotations;
+#[cfg(feature = "server")]
+pub mod anomaly;
+pub mod api;
+#[cfg(feature = "server")]
+pub mod api_tokens;
+#[cfg(feature = "server")]
+pub mod backtest;
+#[cfg(feature = "server")]
+pub mod block_feed;
+#[cfg(feature = "server")]
+pub mod cached_set;
+#[cfg(feature = "server")]
+pub mod candle_cache;
+#[cfg(feature = "server")]
+pub mod candle_hub;
+#[cfg(feature = "server")]
+pub mod candle_preagg;
+#[cfg(feature = "server")]
+pub mod candle_stream;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "server")]
+pub mod clock;
+#[cfg(feature = "server")]
+pub mod clusters;
+#[cfg(feature = "server")]
 pub mod constant;
+#[cfg(feature = "server")]
+pub mod curve_phase;
+#[cfg(feature = "server")]
+pub mod decode_check;
+#[cfg(feature = "server")]
+pub mod doctor;
+#[cfg(feature = "server")]
+pub mod event_layout;
+#[cfg(feature = "server")]
+pub mod feed_health;
+#[cfg(feature = "server")]
+pub mod fees;
+#[cfg(feature = "server")]
+pub mod funnel;
+#[cfg(feature = "server")]
+pub mod heartbeat;
+#[cfg(feature = "server")]
+pub mod heatmap;
+#[cfg(feature = "server")]
+pub mod helius;
+#[cfg(feature = "server")]
+pub mod holders;
+#[cfg(feature = "server")]
+pub mod instance_lock;
+#[cfg(feature = "server")]
 pub mod kline;
+#[cfg(feature = "server")]
+pub mod kline_codec;
+#[cfg(feature = "server")]
+pub mod kline_store;
+#[cfg(feature = "server")]
+pub mod labels;
+#[cfg(feature = "server")]
+pub mod launch;
+#[cfg(feature = "server")]
+pub mod limits;
+#[cfg(feature = "server")]
 pub mod logger;
+#[cfg(feature = "server")]
+pub mod market_beta;
+#[cfg(feature = "server")]
+pub mod message_bus;
+#[cfg(feature = "server")]
+pub mod metrics;
+#[cfg(feature = "server")]
+pub mod micro_filter;
+#[cfg(feature = "server")]
+pub mod migrations;
+#[cfg(feature = "server")]
+pub mod monitor_all;
+#[cfg(feature = "server")]
+pub mod monitor_control;
+#[cfg(feature = "server")]
 pub mod notification;
+#[cfg(feature = "server")]
+pub mod notification_actions;
+#[cfg(feature = "server")]
+pub mod notification_format;
+#[cfg(feature = "server")]
+pub mod notification_profiles;
+#[cfg(feature = "server")]
+pub mod pnl;
+#[cfg(feature = "server")]
+pub mod positions;
+#[cfg(feature = "server")]
+pub mod price_alerts;
+#[cfg(feature = "server")]
+pub mod price_feed;
+#[cfg(feature = "server")]
+pub mod priority_fee;
+#[cfg(feature = "server")]
+pub mod proxy;
+#[cfg(feature = "server")]
 pub mod pump;
+#[cfg(feature = "server")]
 pub mod pump_amm;
+#[cfg(feature = "server")]
+pub mod quote;
+#[cfg(feature = "server")]
 pub mod redis_helper;
+#[cfg(feature = "server")]
+pub mod remote_config;
+#[cfg(feature = "server")]
+mod rpc;
+#[cfg(feature = "server")]
+pub mod rules;
+#[cfg(feature = "server")]
+pub mod severity;
+#[cfg(feature = "server")]
+pub mod sharding;
+#[cfg(feature = "server")]
+pub mod smart_money;
+#[cfg(feature = "server")]
+pub mod spam;
+#[cfg(feature = "server")]
 pub mod strategy;
+#[cfg(feature = "server")]
+pub mod supervisor;
+#[cfg(feature = "server")]
+pub mod suppression;
+#[cfg(all(test, feature = "server"))]
+mod test_harness;
+#[cfg(feature = "server")]
+pub mod token_info;
+#[cfg(feature = "server")]
+pub mod token_metadata;
+#[cfg(feature = "server")]
+pub mod trades;
+#[cfg(feature = "server")]
+pub mod tui;
+#[cfg(feature = "server")]
+pub mod verify;
+#[cfg(feature = "server")]
+pub mod wallets;
+#[cfg(feature = "server")]
+pub mod watchlist;
+#[cfg(feature = "server")]
 pub mod web;
+#[cfg(feature = "server")]
 pub mod websocket;
-
-pub fn get_random_rpc_url() -> Result<String> {
-    let cluster_urls = env::var("RPC_ENDPOINTS")?
-        .split(",")
-        .map(|s| s.trim().to_string())
-        .collect::<Vec<String>>();
-    let random_url = cluster_urls
-        .choose(&mut rand::rng())
-        .expect("No RPC endpoints configured")
-        .clone();
-
-    debug!("Choose rpc: {}", random_url);
-    return Ok(random_url);
-}
-
-// Global RPC client pool
-static RPC_CLIENT_POOL: OnceCell<Vec<Arc<RpcClient>>> = OnceCell::const_new();
-
-// Initialize the RPC client pool with timeout configurations
-pub async fn init_rpc_client_pool() -> Result<()> {
-    let cluster_urls = env::var("RPC_ENDPOINTS")?
-        .split(",")
-        .map(|s| s.trim().to_string())
-        .collect::<Vec<String>>();
-
-    let clients: Vec<Arc<RpcClient>> = cluster_urls
-        .into_iter()
-        .map(|url| Arc::new(RpcClient::new(url)))
-        .collect();
-
-    RPC_CLIENT_POOL
-        .set(clients)
-        .map_err(|_| anyhow::anyhow!("Failed to initialize RPC client pool"))?;
-    debug!(
-        "Initialized RPC client pool with {} clients",
-        RPC_CLIENT_POOL.get().unwrap().len()
-    );
-    Ok(())
-}
-
-// Get a random RPC client from the pool with retry logic
-pub fn get_rpc_client() -> Result<Arc<RpcClient>> {
-    let pool = RPC_CLIENT_POOL
-        .get()
-        .ok_or_else(|| anyhow::anyhow!("RPC client pool not initialized"))?;
-    let client = pool
-        .choose(&mut rand::rng())
-        .ok_or_else(|| anyhow::anyhow!("No RPC clients available in pool"))?
-        .clone();
-    Ok(client)
-}
-
-// Health check for RPC clients
-pub async fn check_rpc_client_health() -> Result<usize> {
-    let pool = RPC_CLIENT_POOL
-        .get()
-        .ok_or_else(|| anyhow::anyhow!("RPC client pool not initialized"))?;
-
-    let mut healthy_count = 0;
-    for (i, client) in pool.iter().enumerate() {
-        match client.get_health() {
-            Ok(_) => {
-                healthy_count += 1;
-                debug!("RPC client {} is healthy", i);
-            }
-            Err(e) => {
-                warn!("RPC client {} is unhealthy: {}", i, e);
-            }
-        }
+#[cfg(feature = "server")]
+pub mod window_alerts;
+
+#[cfg(feature = "server")]
+pub use rpc::*;
+
+// Parse a window string like "30s", "15m", "1h" or "1d" into seconds
+pub fn parse_window(window: &str) -> Option<i64> {
+    let window = window.trim();
+    // The unit may be any character, so split on a char boundary
+    let (split, unit) = window.char_indices().last()?;
+    let value: i64 = window[..split].parse().ok()?;
+    let multiplier = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        _ => return None,
+    };
+    if value <= 0 {
+        return None;
     }
-
-    debug!(
-        "RPC client pool health: {}/{} clients healthy",
-        healthy_count,
-        pool.len()
-    );
-    Ok(healthy_count)
+    value.checked_mul(multiplier)
 }
 
-// Get RPC client with retry mechanism for failed requests
-pub async fn get_rpc_client_with_retry<T, F>(operation: F, max_retries: u32) -> Result<T>
-where
-    F: Fn(Arc<RpcClient>) -> Result<T> + Send + Sync,
-{
-    let mut last_error = None;
-
-    for attempt in 0..=max_retries {
-        match get_rpc_client() {
-            Ok(client) => {
-                match operation(client) {
-                    Ok(result) => return Ok(result),
-                    Err(e) => {
-                        warn!("RPC operation failed on attempt {}: {}", attempt + 1, e);
-                        last_error = Some(e);
-
-                        if attempt < max_retries {
-                            // Exponential backoff
-                            let delay = Duration::from_millis(100 * (2_u64.pow(attempt)));
-                            tokio::time::sleep(delay).await;
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                warn!("Failed to get RPC client on attempt {}: {}", attempt + 1, e);
-                last_error = Some(e);
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_window() {
+        assert_eq!(parse_window("30s"), Some(30));
+        assert_eq!(parse_window(" 15m "), Some(900));
+        assert_eq!(parse_window("1h"), Some(3600));
+        assert_eq!(parse_window("1d"), Some(86400));
+        assert_eq!(parse_window("5分"), None);
+        assert_eq!(parse_window("分"), None);
+        assert_eq!(parse_window("m"), None);
+        assert_eq!(parse_window(""), None);
+        assert_eq!(parse_window("0m"), None);
+        assert_eq!(parse_window("-5m"), None);
+        assert_eq!(parse_window("5x"), None);
+        assert_eq!(parse_window(&format!("{}d", i64::MAX)), None);
+        assert_eq!(parse_window(&format!("{}s", i64::MAX)), Some(i64::MAX));
     }
-
-    Err(last_error.unwrap_or_else(|| anyhow::anyhow!("All retry attempts failed")))
-}
-
-// Legacy function for backward compatibility
-pub fn get_rpc_client_blocking() -> Result<Arc<RpcClient>> {
-    get_rpc_client()
 }
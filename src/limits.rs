@@ -0,0 +1,96 @@
+use std::num::NonZeroUsize;
+use std::sync::{Arc, LazyLock};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+// In-flight trade tasks allowed per CPU when MAX_INFLIGHT_TRADES is unset
+const INFLIGHT_TRADES_PER_CPU: usize = 256;
+
+// CPUs this process may use. std honours the cgroup CPU quota and the affinity
+// mask, so a container limited to 1.5 CPUs reports 1 rather than the host's count.
+pub fn available_cpus() -> usize {
+    std::thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1)
+}
+
+// Bounds on the work a service runs at once
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResourceLimits {
+    // Tokio runtime worker threads
+    pub worker_threads: usize,
+    // Trade-processing tasks in flight before the feed waits for one to finish
+    pub max_inflight_trades: usize,
+    // Commands sent to Redis in one pipeline by bulk operations
+    pub redis_pipeline_size: usize,
+}
+
+impl ResourceLimits {
+    // Defaults sized for the given CPU count
+    pub fn for_cpus(cpus: usize) -> Self {
+        let cpus = cpus.max(1);
+        Self {
+            worker_threads: cpus,
+            max_inflight_trades: cpus * INFLIGHT_TRADES_PER_CPU,
+            redis_pipeline_size: 500,
+        }
+    }
+
+    // WORKER_THREADS, MAX_INFLIGHT_TRADES and REDIS_PIPELINE_SIZE, defaulting
+    // from the available CPUs. Zero is treated as one.
+    pub fn from_env() -> Self {
+        let defaults = Self::for_cpus(available_cpus());
+        Self {
+            worker_threads: std::env::var("WORKER_THREADS")
+                .unwrap_or_else(|_| defaults.worker_threads.to_string())
+                .parse()
+                .unwrap_or(defaults.worker_threads)
+                .max(1),
+            max_inflight_trades: std::env::var("MAX_INFLIGHT_TRADES")
+                .unwrap_or_else(|_| defaults.max_inflight_trades.to_string())
+                .parse()
+                .unwrap_or(defaults.max_inflight_trades)
+                .max(1),
+            redis_pipeline_size: std::env::var("REDIS_PIPELINE_SIZE")
+                .unwrap_or_else(|_| defaults.redis_pipeline_size.to_string())
+                .parse()
+                .unwrap_or(defaults.redis_pipeline_size)
+                .max(1),
+        }
+    }
+}
+
+// Limits of this process, read once
+static LIMITS: LazyLock<ResourceLimits> = LazyLock::new(ResourceLimits::from_env);
+
+// Permits of the trade-processing tasks
+static TRADE_PERMITS: LazyLock<Arc<Semaphore>> =
+    LazyLock::new(|| Arc::new(Semaphore::new(limits().max_inflight_trades)));
+
+pub fn limits() -> &'static ResourceLimits {
+    &LIMITS
+}
+
+// Wait for room to process another trade. Holding the permit for the task's
+// lifetime pushes back on the websocket reader instead of piling up tasks.
+pub async fn acquire_trade_permit() -> OwnedSemaphorePermit {
+    TRADE_PERMITS
+        .clone()
+        .acquire_owned()
+        .await
+        .expect("trade semaphore is never closed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_for_cpus() {
+        let limits = ResourceLimits::for_cpus(2);
+        assert_eq!(limits.worker_threads, 2);
+        assert_eq!(limits.max_inflight_trades, 512);
+        assert_eq!(limits.redis_pipeline_size, 500);
+        assert_eq!(ResourceLimits::for_cpus(0), ResourceLimits::for_cpus(1));
+        assert!(available_cpus() >= 1);
+    }
+}
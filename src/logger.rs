@@ -1,19 +1,78 @@
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-pub fn init(enable_console: bool) {
+// Flushes buffered spans to the OTLP collector when dropped at exit
+pub struct TelemetryGuard {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take()
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("Failed to flush traces: {}", e);
+        }
+    }
+}
+
+// OTLP/HTTP span exporter, enabled when an OTLP endpoint is configured. The exporter
+// reads the standard OTEL_EXPORTER_OTLP_* variables itself.
+fn tracer_provider(service: &str) -> Option<SdkTracerProvider> {
+    let configured = [
+        "OTEL_EXPORTER_OTLP_ENDPOINT",
+        "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+    ]
+    .iter()
+    .any(|var| std::env::var(var).is_ok_and(|v| !v.is_empty()));
+    if !configured {
+        return None;
+    }
+    let exporter = match SpanExporter::builder().with_http().build() {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            eprintln!("Failed to create OTLP exporter: {}", e);
+            return None;
+        }
+    };
+
+    // OTEL_SERVICE_NAME overrides the per-command default
+    let mut resource = Resource::builder();
+    if std::env::var("OTEL_SERVICE_NAME").is_err() {
+        resource = resource.with_service_name(format!("pump-kmonitor-{}", service));
+    }
+    Some(
+        SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(resource.build())
+            .build(),
+    )
+}
+
+pub fn init(enable_console: bool, service: &str) -> TelemetryGuard {
     let file_appender = RollingFileAppender::new(Rotation::DAILY, "logs", "app.log");
     let file_layer = tracing_subscriber::fmt::layer().with_writer(file_appender);
 
+    let provider = tracer_provider(service);
+    let otel_layer = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer("pump-kmonitor"))
+    });
+
     let registry = tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
         )
-        .with(file_layer);
+        .with(file_layer)
+        .with(otel_layer);
 
     if enable_console {
         registry.with(tracing_subscriber::fmt::layer()).init();
     } else {
         registry.init();
     }
+
+    TelemetryGuard { provider }
 }
@@ -1,13 +1,20 @@
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{Context, Result};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use pump_kmonitor::kline::KLineManager;
-use pump_kmonitor::notification::NotificationManager;
+use pump_kmonitor::labels::{self, LabelCategory, LabelEntry};
+use pump_kmonitor::notification::{self, NotificationManager};
+use pump_kmonitor::price_alerts::{self, PriceAlertTarget};
+use pump_kmonitor::rules::{self, RuleCondition};
 use pump_kmonitor::strategy::StrategyEngine;
 use pump_kmonitor::{
-    check_rpc_client_health, init_rpc_client_pool, logger, pump, pump_amm, redis_helper, web,
+    alerts, backtest, candle_hub, candle_preagg, check_rpc_client_health, doctor, heartbeat,
+    helius, holders, init_rpc_client_pool, instance_lock, limits, logger, metrics, migrations,
+    monitor_all, notification_actions, price_feed, priority_fee, pump, pump_amm, quote,
+    redis_helper, remote_config, sharding, smart_money, supervisor, token_metadata, tui, verify,
+    wallets, watchlist, web,
 };
+use rust_decimal::Decimal;
 use std::sync::Arc;
-use tokio::sync::Mutex;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -22,33 +29,293 @@ enum Commands {
     Monitor,
     /// Start the AMM monitoring service (WebSocket connection to Pump AMM)
     MonitorAmm,
+    /// Monitor Pump.fun and Pump AMM in one process over a shared WebSocket connection
+    MonitorAll,
     /// Start the web service (HTTP API and web interface)
     Web {
         #[arg(long, default_value = "8080")]
         port: u16,
     },
+    /// Ingest Pump and AMM trades from Helius webhooks instead of a WebSocket
+    Webhook {
+        #[arg(long, default_value = "8081")]
+        port: u16,
+    },
     /// Run strategy detection
     Strategy {
         /// Run strategy detection once and exit
         #[arg(long)]
         once: bool,
-        /// Interval in seconds for continuous detection (default: 30)
-        #[arg(long, default_value = "10")]
+        /// Use the legacy interval scan instead of consuming candle-close events
+        #[arg(long)]
+        scan: bool,
+        /// Check each mint when the monitors push its candle close through the candle hub, keeping candles in memory
+        #[arg(long, conflicts_with = "scan")]
+        hub: bool,
+        /// Scan period in seconds, rounded up to whole minutes and aligned to candle closes (default: 60)
+        #[arg(long, default_value = "60")]
         interval: u64,
+        /// Consumer group shared by strategy workers
+        #[arg(long, default_value = "strategy")]
+        group: String,
+        /// Consumer name, unique and stable per worker so pending events are replayed after a restart (default: hostname)
+        #[arg(long)]
+        consumer: Option<String>,
+    },
+    /// Send a test alert through every configured notification channel
+    NotifyTest,
+    /// Check configuration and connectivity (Redis, RPC, WebSocket, notifications)
+    Doctor,
+    /// Recompute candles from stored tick trades and report mismatches
+    Verify {
+        /// Verify a single mint instead of a random sample
+        #[arg(long)]
+        mint: Option<String>,
+        /// Number of active mints to sample
+        #[arg(long, default_value = "10")]
+        sample: usize,
+    },
+    /// Backtest the rising strategy over stored candles for a grid of parameters
+    Sweep {
+        /// Backtest a single mint instead of a random sample
+        #[arg(long)]
+        mint: Option<String>,
+        /// Number of active mints to sample
+        #[arg(long, default_value = "50")]
+        sample: usize,
+        /// Consecutive rising candle counts to try
+        #[arg(long, value_delimiter = ',', default_value = "3,4,5,6")]
+        counts: Vec<usize>,
+        /// Minimum gains per candle (%) to try
+        #[arg(long, value_delimiter = ',', default_value = "0.5,1,1.5,2,2.5,3")]
+        min_gains: Vec<Decimal>,
+        /// Forward return horizon, e.g. "15m" or "1h"
+        #[arg(long, default_value = "15m")]
+        horizon: String,
+    },
+    /// Start the terminal dashboard (live mints, sparklines and alerts)
+    Tui {
+        /// Refresh interval in seconds
+        #[arg(long, default_value = "2")]
+        refresh: u64,
+    },
+    /// Delete the K-lines of a mint within a time range (inclusive, unix seconds)
+    Purge {
+        mint: String,
+        /// Start timestamp (unix seconds)
+        #[arg(long)]
+        from: i64,
+        /// End timestamp (unix seconds)
+        #[arg(long)]
+        to: i64,
+    },
+    /// Delete the data of idle mints now, reporting the keys and bytes reclaimed per mint
+    Compact {
+        /// Only report what would be reclaimed, without deleting
+        #[arg(long)]
+        dry_run: bool,
+        /// Number of largest mints to list
+        #[arg(long, default_value = "20")]
+        top: usize,
+    },
+    /// Upgrade stored Redis data to the current schema with versioned migrations
+    Migrate {
+        /// List pending migrations without applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print the latest price, 5m/1h change, volume and market cap of a mint from Redis
+    Price {
+        mint: String,
+        /// Print the quote as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Manage custom per-mint alert rules
+    Rule {
+        #[command(subcommand)]
+        action: RuleAction,
+    },
+    /// Manage one-shot price and market cap alerts
+    PriceAlert {
+        #[command(subcommand)]
+        action: PriceAlertAction,
+    },
+    /// Manage tracked wallets and smart-money discovery
+    Wallet {
+        #[command(subcommand)]
+        action: WalletAction,
+    },
+    /// Manage wallet labels (exchanges, snipers, MEV bots) shown on trades and flows
+    Label {
+        #[command(subcommand)]
+        action: LabelAction,
+    },
+    /// Manage the mint watchlist used by allowlist mode (WATCHLIST_ONLY=true)
+    Watchlist {
+        #[command(subcommand)]
+        action: WatchlistAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum WalletAction {
+    /// Alert whenever this wallet buys
+    Track { wallet: String },
+    /// Stop alerting on this wallet
+    Untrack { wallet: String },
+    /// List tracked wallets
+    List,
+    /// Run smart-money discovery once and print the ranking
+    Discover,
+}
+
+#[derive(Subcommand)]
+enum LabelAction {
+    /// Import labels from a CSV (wallet,name[,category]) or JSON array file
+    Import { file: String },
+    /// Label a single wallet
+    Add {
+        wallet: String,
+        name: String,
+        /// exchange, sniper, mev, bot or other
+        #[arg(long, default_value = "other")]
+        category: LabelCategory,
+    },
+    /// Remove a wallet's label
+    Remove { wallet: String },
+    /// List labeled wallets
+    List,
+}
+
+#[derive(Subcommand)]
+enum WatchlistAction {
+    /// Add a mint to the watchlist
+    Add { mint: String },
+    /// Remove a mint from the watchlist
+    Remove { mint: String },
+    /// List watched mints
+    List,
+}
+
+#[derive(Subcommand)]
+enum PriceAlertAction {
+    /// Alert once when a mint reaches a price or market cap (exactly one target)
+    Add {
+        mint: String,
+        /// Alert when price (SOL) rises to this value
+        #[arg(long)]
+        price_above: Option<Decimal>,
+        /// Alert when price (SOL) falls to this value
+        #[arg(long)]
+        price_below: Option<Decimal>,
+        /// Alert when market cap (SOL) rises to this value
+        #[arg(long)]
+        market_cap_above: Option<Decimal>,
+        /// Alert when market cap (SOL) falls to this value
+        #[arg(long)]
+        market_cap_below: Option<Decimal>,
+        /// Optional note included in the alert
+        #[arg(long)]
+        note: Option<String>,
+    },
+    /// List pending price alerts for a mint
+    List { mint: String },
+    /// Remove a pending price alert by id
+    Remove { mint: String, id: String },
+}
+
+#[derive(Subcommand)]
+enum RuleAction {
+    /// Add a rule for a mint (exactly one condition)
+    Add {
+        mint: String,
+        /// Alert when price (SOL) is above this value
+        #[arg(long)]
+        price_above: Option<Decimal>,
+        /// Alert when price (SOL) is below this value
+        #[arg(long)]
+        price_below: Option<Decimal>,
+        /// Alert when market cap (SOL) is above this value
+        #[arg(long)]
+        market_cap_above: Option<Decimal>,
+        /// Alert when net flow (SOL) over the window is above this value
+        #[arg(long)]
+        net_flow_above: Option<Decimal>,
+        /// Alert when turnover (volume / pool liquidity) over the window is above this value, AMM only
+        #[arg(long)]
+        turnover_above: Option<Decimal>,
+        /// Net flow and turnover window in minutes
+        #[arg(long, default_value = "5")]
+        window: usize,
+        /// Optional note included in the alert
+        #[arg(long)]
+        note: Option<String>,
     },
+    /// List rules for a mint
+    List { mint: String },
+    /// Remove a rule by id
+    Remove { mint: String, id: String },
 }
 
-#[tokio::main]
-pub async fn main() -> Result<()> {
+pub fn main() -> Result<()> {
     dotenvy::dotenv().ok();
-    let cli = Cli::parse();
-    logger::init(true);
+    // Size the runtime from the CPUs the container grants, not the host's
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(limits::limits().worker_threads)
+        .enable_all()
+        .build()?
+        .block_on(run())
+}
+
+async fn run() -> Result<()> {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches)?;
+    // Console logging would draw over the terminal dashboard or mix into scripted output
+    let _telemetry = logger::init(
+        !matches!(cli.command, Commands::Tui { .. } | Commands::Price { .. }),
+        matches.subcommand_name().unwrap_or("cli"),
+    );
+    supervisor::install_panic_hook();
+
+    // Doctor runs its own checks and must work even when the config is broken
+    if matches!(cli.command, Commands::Doctor) {
+        return run_doctor().await;
+    }
+
+    // Quick queries only read Redis and keep stdout to the result
+    if let Commands::Price { mint, json } = &cli.command {
+        redis_helper::init_pool().await?;
+        return run_price(mint, *json).await;
+    }
+
+    // Fail fast with an actionable report instead of deep inside a service
+    let required_env: &[&str] = match cli.command {
+        Commands::Monitor | Commands::MonitorAmm | Commands::MonitorAll => {
+            &["RPC_ENDPOINTS", "RPC_WEBSOCKET_ENDPOINT"]
+        }
+        _ => &["RPC_ENDPOINTS"],
+    };
+    // Long-running services also make sure their endpoints are reachable
+    let check_endpoints = matches!(
+        cli.command,
+        Commands::Monitor
+            | Commands::MonitorAmm
+            | Commands::MonitorAll
+            | Commands::Web { .. }
+            | Commands::Webhook { .. }
+            | Commands::Strategy { .. }
+    );
+    doctor::validate_startup(required_env, check_endpoints).await?;
 
     // Initialize Redis connection pool
     println!("🔄 Initializing Redis connection pool...");
     redis_helper::init_pool().await?;
     println!("✅ Redis connection pool initialized successfully");
 
+    // Runtime overrides of filters, thresholds and toggles shared by all services
+    remote_config::start().await;
+
     // Initialize RPC client pool
     println!("🔄 Initializing RPC client pool...");
     init_rpc_client_pool().await?;
@@ -68,7 +335,7 @@ pub async fn main() -> Result<()> {
     }
 
     // Start periodic health monitoring
-    tokio::spawn(async {
+    supervisor::spawn_supervised("rpc_health", async {
         let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(300)); // 5 minutes
         loop {
             interval.tick().await;
@@ -78,6 +345,33 @@ pub async fn main() -> Result<()> {
         }
     });
 
+    // Every long-running service drains the shared queue of failed notifications
+    if matches!(
+        cli.command,
+        Commands::Monitor
+            | Commands::MonitorAmm
+            | Commands::MonitorAll
+            | Commands::Web { .. }
+            | Commands::Webhook { .. }
+            | Commands::Strategy { .. }
+    ) && let Err(e) = notification::start_retry_worker()
+    {
+        tracing::warn!("Failed to start notification retry worker: {}", e);
+    }
+
+    // Services keep working on older data, but some features need the migrations
+    if !matches!(cli.command, Commands::Migrate { .. }) {
+        match migrations::get_schema_version().await {
+            Ok(version) if version < migrations::latest_version() => println!(
+                "⚠️ Stored data is at schema version {} (latest {}), run `pump-kmonitor migrate` to upgrade",
+                version,
+                migrations::latest_version()
+            ),
+            Ok(_) => {}
+            Err(e) => println!("⚠️ Failed to read schema version: {}", e),
+        }
+    }
+
     match cli.command {
         Commands::Monitor => {
             println!("🔍 Starting monitoring service...");
@@ -87,84 +381,372 @@ pub async fn main() -> Result<()> {
             println!("🔍 Starting AMM monitoring service...");
             start_monitor_amm_service().await?;
         }
+        Commands::MonitorAll => {
+            println!("🔍 Starting Pump and AMM monitoring service...");
+            start_monitor_all_service().await?;
+        }
         Commands::Web { port } => {
             println!("🌐 Starting web service...");
             start_web_service(port).await?;
         }
-        Commands::Strategy { once, interval } => {
+        Commands::Webhook { port } => {
+            println!("🪝 Starting webhook ingestion service...");
+            start_webhook_service(port).await?;
+        }
+        Commands::Strategy {
+            once,
+            scan,
+            hub,
+            interval,
+            group,
+            consumer,
+        } => {
             println!("🎯 Starting strategy detection...");
-            start_strategy_service(once, interval).await?;
+            start_strategy_service(once, scan, hub, interval, group, consumer).await?;
+        }
+        Commands::Doctor | Commands::Price { .. } => {
+            anyhow::bail!("doctor and price run before service initialization");
+        }
+        Commands::NotifyTest => {
+            println!("📨 Sending test notification...");
+            run_notify_test().await?;
+        }
+        Commands::Verify { mint, sample } => {
+            println!("🔎 Verifying candles against tick trades...");
+            run_verify(mint, sample).await?;
+        }
+        Commands::Sweep {
+            mint,
+            sample,
+            counts,
+            min_gains,
+            horizon,
+        } => {
+            run_sweep(mint, sample, &counts, &min_gains, &horizon).await?;
+        }
+        Commands::Tui { refresh } => {
+            tui::run(refresh).await?;
+        }
+        Commands::Purge { mint, from, to } => {
+            run_purge(&mint, from, to).await?;
+        }
+        Commands::Compact { dry_run, top } => {
+            run_compact(dry_run, top).await?;
+        }
+        Commands::Migrate { dry_run } => {
+            run_migrate(dry_run).await?;
+        }
+        Commands::Rule { action } => {
+            run_rule_command(action).await?;
+        }
+        Commands::PriceAlert { action } => {
+            run_price_alert_command(action).await?;
+        }
+        Commands::Wallet { action } => {
+            run_wallet_command(action).await?;
+        }
+        Commands::Label { action } => {
+            run_label_command(action).await?;
+        }
+        Commands::Watchlist { action } => {
+            run_watchlist_command(action).await?;
         }
     }
 
     Ok(())
 }
 
-async fn start_monitor_service() -> Result<()> {
-    let websocket_endpoint = std::env::var("RPC_WEBSOCKET_ENDPOINT")
-        .expect("RPC_WEBSOCKET_ENDPOINT environment variable is required");
+// The websocket endpoint, plus the redundant one when configured
+fn websocket_endpoints() -> Result<Vec<String>> {
+    let mut endpoints = vec![
+        std::env::var("RPC_WEBSOCKET_ENDPOINT")
+            .context("RPC_WEBSOCKET_ENDPOINT environment variable is required")?,
+    ];
+    if let Ok(secondary) = std::env::var("RPC_WEBSOCKET_SECONDARY_ENDPOINT")
+        && !secondary.trim().is_empty()
+    {
+        endpoints.push(secondary.trim().to_string());
+    }
+    Ok(endpoints)
+}
 
-    // Create KLineManager for monitoring service
-    let kline_manager = Arc::new(Mutex::new(
-        KLineManager::new()
-            .await
-            .expect("Failed to connect to Redis"),
-    ));
+// KLineManager for a monitor, building candles in memory when pre-aggregation is enabled
+async fn monitor_kline_manager() -> Result<Arc<KLineManager>> {
+    let preagg = candle_preagg::PreaggConfig::from_env();
+    let mut kline_manager = KLineManager::new()
+        .await
+        .context("Failed to create KLineManager")?;
+    if preagg.enabled {
+        kline_manager = kline_manager.with_preaggregation();
+    }
+    let kline_manager = Arc::new(kline_manager);
+    if preagg.enabled {
+        candle_preagg::spawn_flush_task(kline_manager.clone(), &preagg);
+    }
+    Ok(kline_manager)
+}
 
-    println!("📡 Connecting to WebSocket: {}", websocket_endpoint);
+// Resolves when the process is asked to stop with ctrl-c or SIGTERM
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
 
-    // Start WebSocket monitoring (this will run indefinitely)
-    pump::connect_websocket(&websocket_endpoint, kline_manager).await
+// Run a monitor until its websocket gives up or the process is asked to stop,
+// then write the candles still buffered in memory
+async fn run_monitor(
+    monitor: impl std::future::Future<Output = Result<()>>,
+    kline_manager: Arc<KLineManager>,
+) -> Result<()> {
+    let result = tokio::select! {
+        result = monitor => result,
+        _ = shutdown_signal() => {
+            println!("🛑 Shutting down monitor...");
+            Ok(())
+        }
+    };
+    match kline_manager.flush_pending().await {
+        Ok(0) => {}
+        Ok(flushed) => println!("💾 Flushed {} buffered candles", flushed),
+        Err(e) => println!("⚠️ Failed to flush buffered candles: {}", e),
+    }
+    if kline_manager.pending_len() > 0 {
+        println!(
+            "⚠️ {} buffered candles could not be written",
+            kline_manager.pending_len()
+        );
+    }
+    instance_lock::shutdown().await;
+    result
 }
 
-async fn start_monitor_amm_service() -> Result<()> {
-    let websocket_endpoint = std::env::var("RPC_WEBSOCKET_ENDPOINT")
-        .expect("RPC_WEBSOCKET_ENDPOINT environment variable is required");
+// Start a monitor service and run `connect` on the websocket endpoints until it
+// stops. The first of `programs` decides the shard, and every program registers
+// it so peers of each one see this process. `services` are the names the
+// instance lock and heartbeats are held under, `metrics_service` the name
+// metrics are reported under and `feed` names the websocket in the log.
+async fn start_monitor<Fut>(
+    feed: &str,
+    programs: &[&str],
+    services: &[&str],
+    metrics_service: &str,
+    connect: impl FnOnce(Vec<String>, Arc<KLineManager>) -> Fut,
+) -> Result<()>
+where
+    Fut: std::future::Future<Output = Result<()>>,
+{
+    let websocket_endpoints = websocket_endpoints()?;
 
-    // Create KLineManager for AMM monitoring service
-    let kline_manager = Arc::new(Mutex::new(
-        KLineManager::new()
-            .await
-            .expect("Failed to connect to Redis"),
-    ));
+    // One KLineManager and one set of background tasks per process
+    let kline_manager = monitor_kline_manager().await?;
+
+    // Index K-lines stored by earlier versions for range queries
+    match kline_manager.rebuild_kline_index().await {
+        Ok(0) => {}
+        Ok(indexed) => println!("🗂️ Indexed {} existing K-lines", indexed),
+        Err(e) => println!("⚠️ Failed to rebuild K-line index: {}", e),
+    }
+
+    // Start background candle audit if enabled
+    verify::spawn_audit_task(kline_manager.clone());
+
+    // Record SOL/USD history for USD candle series if enabled
+    price_feed::spawn_history_task();
+
+    if watchlist::allowlist_enabled() {
+        println!("📋 Allowlist mode: only watchlist mints are processed");
+    }
+
+    print_resource_limits();
+
+    let shard = sharding::init(programs[0]).await?;
+    for program in &programs[1..] {
+        sharding::init(program).await?;
+    }
+    if shard.is_sharded() {
+        println!(
+            "🧩 Sharding mode: handling shard {} of {}",
+            shard.index, shard.count
+        );
+    }
+    let services: Vec<String> = services
+        .iter()
+        .map(|service| heartbeat::service_name(service, &shard))
+        .collect();
+    instance_lock::init(&services).await?;
+    if instance_lock::is_read_only() {
+        println!("🔒 Another instance is running, starting read-only as a standby");
+    }
+    for service in &services {
+        heartbeat::spawn(service.clone());
+    }
+    metrics::spawn(heartbeat::service_name(metrics_service, &shard));
+
+    println!(
+        "📡 Connecting to {}: {}",
+        feed,
+        websocket_endpoints.join(", ")
+    );
+
+    // Start WebSocket monitoring (this will run indefinitely)
+    run_monitor(
+        connect(websocket_endpoints, kline_manager.clone()),
+        kline_manager,
+    )
+    .await
+}
 
-    println!("📡 Connecting to AMM WebSocket: {}", websocket_endpoint);
+async fn start_monitor_service() -> Result<()> {
+    start_monitor(
+        "WebSocket",
+        &["PUMP"],
+        &["monitor"],
+        "monitor",
+        |endpoints, kline_manager| async move {
+            pump::connect_websocket(&endpoints, kline_manager).await
+        },
+    )
+    .await
+}
+
+async fn start_monitor_amm_service() -> Result<()> {
+    start_monitor(
+        "AMM WebSocket",
+        &["AMM"],
+        &["monitor-amm"],
+        "monitor-amm",
+        |endpoints, kline_manager| async move {
+            pump_amm::connect_websocket(&endpoints, kline_manager).await
+        },
+    )
+    .await
+}
 
-    // Start AMM WebSocket monitoring (this will run indefinitely)
-    pump_amm::connect_websocket(&websocket_endpoint, kline_manager).await
+// Holding the locks of both monitors keeps a separate monitor or monitor-amm out
+async fn start_monitor_all_service() -> Result<()> {
+    start_monitor(
+        "Pump and AMM WebSocket",
+        &["PUMP", "AMM"],
+        &["monitor", "monitor-amm"],
+        "monitor-all",
+        |endpoints, kline_manager| async move {
+            monitor_all::connect_websocket(&endpoints, kline_manager).await
+        },
+    )
+    .await
 }
 
 async fn start_web_service(port: u16) -> Result<()> {
     // Create KLineManager for web service
-    let kline_manager = Arc::new(Mutex::new(
+    let kline_manager = Arc::new(
         KLineManager::new()
             .await
-            .expect("Failed to connect to Redis"),
-    ));
+            .context("Failed to create KLineManager")?,
+    );
 
     println!(
         "🌐 Web interface will be available at http://localhost:{}",
         port
     );
 
+    // Receive live candle updates from the monitors for streaming endpoints
+    candle_hub::spawn_redis_bridge();
+
+    // Handle the Telegram alert buttons when TELEGRAM_ACTIONS is on
+    notification_actions::spawn_bot_loop();
+
+    // Report this service's liveness and alert on services that stopped reporting theirs
+    heartbeat::spawn("web".to_string());
+    heartbeat::spawn_watchdog();
+
     // Start web server (this will run indefinitely)
     web::start_web_server(kline_manager, port).await
 }
 
-async fn start_strategy_service(once: bool, interval: u64) -> Result<()> {
+async fn start_webhook_service(port: u16) -> Result<()> {
+    // Create KLineManager for webhook ingestion
+    let kline_manager = Arc::new(
+        KLineManager::new()
+            .await
+            .context("Failed to create KLineManager")?,
+    );
+
+    if watchlist::allowlist_enabled() {
+        println!("📋 Allowlist mode: only watchlist mints are processed");
+    }
+
+    print_resource_limits();
+
+    // Holding the locks of both monitors keeps them and a second webhook service out
+    let services = ["monitor".to_string(), "monitor-amm".to_string()];
+    instance_lock::init(&services).await?;
+    if instance_lock::is_read_only() {
+        println!("🔒 Another instance is running, starting read-only as a standby");
+    }
+
+    heartbeat::spawn("webhook".to_string());
+    metrics::spawn("webhook".to_string());
+
+    println!(
+        "🪝 Point Helius webhooks at http://<host>:{}/webhooks/helius",
+        port
+    );
+
+    // Start webhook server (this will run indefinitely)
+    run_monitor(
+        helius::start_webhook_server(kline_manager.clone(), port),
+        kline_manager,
+    )
+    .await
+}
+
+async fn start_strategy_service(
+    once: bool,
+    scan: bool,
+    hub: bool,
+    interval: u64,
+    group: String,
+    consumer: Option<String>,
+) -> Result<()> {
     // Create KLineManager for strategy service
-    let kline_manager = Arc::new(Mutex::new(
+    let kline_manager = Arc::new(
         KLineManager::new()
             .await
-            .expect("Failed to connect to Redis"),
-    ));
+            .context("Failed to create KLineManager")?,
+    );
 
     // Create notification manager
     let notification_manager =
-        NotificationManager::new().expect("Failed to create notification manager");
+        NotificationManager::new().context("Failed to create notification manager")?;
 
-    // Check if notification script is available
-    if notification_manager.is_enabled() && !notification_manager.check_script_availability() {
+    // Check if notification script is available; without it, alerts still go
+    // to the webhook and Telegram channels when configured
+    let channels = notification_manager.configured_channels();
+    if notification_manager.is_enabled()
+        && !notification_manager.check_script_availability()
+        && !channels.contains(&"script")
+    {
+        println!(
+            "✅ 通知功能已启用，通知脚本不存在，使用渠道: {}",
+            channels.join(", ")
+        );
+    } else if notification_manager.is_enabled() && !notification_manager.check_script_availability()
+    {
         println!(
             "⚠️  通知脚本不存在: {:?}",
             notification_manager.get_script_path()
@@ -179,17 +761,546 @@ async fn start_strategy_service(once: bool, interval: u64) -> Result<()> {
         println!("ℹ️  通知功能已禁用");
     }
 
+    // Start periodic smart-money discovery, priority fee sampling, holder snapshots
+    // and metadata refreshes unless running a single pass
+    if !once {
+        heartbeat::spawn("strategy".to_string());
+        metrics::spawn("strategy".to_string());
+        smart_money::spawn_discovery_task();
+        priority_fee::spawn_sampler_task();
+        holders::spawn_snapshot_task(Arc::clone(&kline_manager));
+        alerts::spawn_outcome_task(Arc::clone(&kline_manager));
+        token_metadata::spawn_refresh_task(Arc::clone(&kline_manager));
+    }
+
     // Create strategy engine
     let mut strategy_engine = StrategyEngine::new(kline_manager, notification_manager);
 
+    // Pre-load recent candles so the first checks after a restart don't read every mint's history.
+    // The hub subscriber warms up itself once it is subscribed
+    if !once
+        && !hub
+        && let Err(e) = strategy_engine.warm_up().await
+    {
+        println!("⚠️ 策略预热失败: {}", e);
+    }
+
     if once {
         println!("🔍 执行一次性策略检测...");
         strategy_engine.run_strategy_check().await?;
         println!("✅ 策略检测完成");
-    } else {
-        println!("🔄 启动持续策略检测，间隔: {}秒", interval);
+    } else if scan {
+        println!("🔄 启动持续策略检测，对齐K线收盘，周期: {}秒", interval);
         strategy_engine.run_continuous_check(interval).await?;
+    } else if hub {
+        // The monitors mirror their candle updates to Redis pub/sub for this process
+        candle_hub::spawn_redis_bridge();
+        println!("📡 订阅K线中心的K线更新");
+        strategy_engine.run_hub_subscriber().await?;
+    } else {
+        let consumer = consumer.unwrap_or_else(|| {
+            std::env::var("HOSTNAME").unwrap_or_else(|_| "strategy".to_string())
+        });
+        println!(
+            "📥 消费K线收盘事件流, group: {}, consumer: {}",
+            group, consumer
+        );
+        strategy_engine
+            .run_stream_consumer(&group, &consumer)
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn print_resource_limits() {
+    let limits = limits::limits();
+    println!(
+        "⚙️ Resource limits: {} CPUs, {} worker threads, {} in-flight trades, Redis pipelines of {}",
+        limits::available_cpus(),
+        limits.worker_threads,
+        limits.max_inflight_trades,
+        limits.redis_pipeline_size
+    );
+}
+
+async fn run_doctor() -> Result<()> {
+    println!("🩺 Checking configuration and connectivity...");
+    let results = doctor::run_all().await;
+    let failed = doctor::print_report(&results);
+    if failed > 0 {
+        return Err(anyhow::anyhow!(
+            "{} of {} checks failed",
+            failed,
+            results.len()
+        ));
+    }
+    println!("✅ All {} checks passed", results.len());
+    Ok(())
+}
+
+async fn run_notify_test() -> Result<()> {
+    let notification_manager =
+        NotificationManager::new().context("Failed to create notification manager")?;
+
+    let results = notification_manager.test_channels().await;
+    let failed = results.iter().filter(|r| !r.success).count();
+
+    for result in &results {
+        if result.success {
+            println!("✅ {}: OK ({}ms)", result.channel, result.duration_ms);
+        } else {
+            println!(
+                "❌ {}: {} ({}ms)",
+                result.channel,
+                result.error.as_deref().unwrap_or("unknown error"),
+                result.duration_ms
+            );
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow::anyhow!(
+            "{} of {} notification channels failed",
+            failed,
+            results.len()
+        ));
+    }
+    Ok(())
+}
+
+async fn run_verify(mint: Option<String>, sample: usize) -> Result<()> {
+    let kline_manager = KLineManager::new()
+        .await
+        .context("Failed to create KLineManager")?;
+
+    let reports = match mint {
+        Some(mint) => vec![verify::verify_mint(&kline_manager, &mint).await?],
+        None => verify::verify_sample(&kline_manager, sample).await?,
+    };
+
+    let mut inconsistent = 0;
+    for report in &reports {
+        if report.is_consistent() {
+            println!(
+                "✅ {}: {} candles consistent ({} trades)",
+                report.mint, report.candles_checked, report.trades
+            );
+            continue;
+        }
+        inconsistent += 1;
+        println!(
+            "❌ {}: {} candles checked, {} missing, {} mismatched fields",
+            report.mint,
+            report.candles_checked,
+            report.missing_candles.len(),
+            report.mismatches.len()
+        );
+        for mismatch in &report.mismatches {
+            println!(
+                "   - {} {}: stored={} recomputed={}",
+                mismatch.timestamp, mismatch.field, mismatch.stored, mismatch.recomputed
+            );
+        }
+    }
+
+    println!(
+        "🔎 Verified {} mints, {} inconsistent",
+        reports.len(),
+        inconsistent
+    );
+    Ok(())
+}
+
+async fn run_sweep(
+    mint: Option<String>,
+    sample: usize,
+    counts: &[usize],
+    min_gains: &[Decimal],
+    horizon: &str,
+) -> Result<()> {
+    let horizon_secs = pump_kmonitor::parse_window(horizon)
+        .ok_or_else(|| anyhow::anyhow!("Invalid horizon: {}", horizon))?;
+    let kline_manager = KLineManager::new().await?;
+    let klines_by_mint = backtest::load_klines(&kline_manager, mint.as_deref(), sample).await?;
+    let candles: usize = klines_by_mint.iter().map(Vec::len).sum();
+    println!(
+        "📐 Sweeping {} combinations over {} mints ({} candles), {} forward returns",
+        counts.len() * min_gains.len(),
+        klines_by_mint.len(),
+        candles,
+        horizon
+    );
+
+    println!(
+        "{:>6} {:>9} {:>8} {:>10} {:>12} {:>9}",
+        "count", "min_gain", "alerts", "evaluated", "avg_return", "hit_rate"
+    );
+    let format_pct = |value: Option<Decimal>| match value {
+        Some(value) => format!("{:.2}%", value),
+        None => "-".to_string(),
+    };
+    for result in backtest::sweep(&klines_by_mint, counts, min_gains, horizon_secs) {
+        println!(
+            "{:>6} {:>8}% {:>8} {:>10} {:>12} {:>9}",
+            result.consecutive_count,
+            result.min_gain_threshold,
+            result.alerts,
+            result.evaluated,
+            format_pct(result.avg_return),
+            format_pct(result.hit_rate.map(|rate| rate * Decimal::from(100))),
+        );
+    }
+    Ok(())
+}
+
+async fn run_purge(mint: &str, from: i64, to: i64) -> Result<()> {
+    if from > to {
+        return Err(anyhow::anyhow!("--from must not be after --to"));
+    }
+    let kline_manager = KLineManager::new().await?;
+    let deleted = kline_manager.delete_klines_in_range(mint, from, to).await?;
+    println!(
+        "🗑️ Deleted {} K-lines for {} between {} and {}",
+        deleted, mint, from, to
+    );
+    Ok(())
+}
+
+async fn run_compact(dry_run: bool, top: usize) -> Result<()> {
+    let kline_manager = KLineManager::new().await?;
+    let report = kline_manager.compaction_report().await?;
+    if report.entries.is_empty() {
+        println!("✅ No idle mints to compact");
+        return Ok(());
+    }
+
+    for entry in report.entries.iter().take(top) {
+        println!(
+            "  {}  idle {}s  {} keys  {:.1} KiB",
+            entry.mint,
+            entry.idle_secs,
+            entry.keys,
+            entry.bytes as f64 / 1024.0
+        );
+    }
+    if report.entries.len() > top {
+        println!("  ... {} more mints", report.entries.len() - top);
+    }
+    let summary = format!(
+        "{} keys ({:.1} MiB) across {} idle mints",
+        report.total_keys,
+        report.total_bytes as f64 / (1024.0 * 1024.0),
+        report.entries.len()
+    );
+    if dry_run {
+        println!("🔍 Compaction would reclaim {}", summary);
+        return Ok(());
+    }
+
+    kline_manager.cleanup_idle_klines().await?;
+    println!("🗑️ Reclaimed {}", summary);
+    Ok(())
+}
+
+async fn run_price(mint: &str, json: bool) -> Result<()> {
+    let kline_manager = KLineManager::new().await?;
+    let quote = quote::get_quote(&kline_manager, mint)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("No K-lines stored for {}", mint))?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&quote)?);
+        return Ok(());
     }
 
+    let change = |window: &quote::WindowChange| match window.change_pct {
+        Some(pct) => format!("{:+.2}%  (volume {:.4} SOL)", pct, window.volume_sol),
+        None => format!("n/a  (volume {:.4} SOL)", window.volume_sol),
+    };
+    let last_trade = chrono::DateTime::from_timestamp(quote.last_trade_at, 0)
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_default();
+    println!("🪙 {}", quote.mint);
+    println!("  Price:       {} SOL", quote.price);
+    println!("  Market cap:  {} SOL", quote.market_cap_sol);
+    println!("  5m change:   {}", change(&quote.change_5m));
+    println!("  1h change:   {}", change(&quote.change_1h));
+    println!(
+        "  Last trade:  {} ({}s ago)",
+        last_trade,
+        chrono::Utc::now().timestamp() - quote.last_trade_at
+    );
+    Ok(())
+}
+
+async fn run_migrate(dry_run: bool) -> Result<()> {
+    let current = migrations::get_schema_version().await?;
+    let pending = migrations::pending_migrations(current);
+    println!(
+        "🧬 Schema version {} (latest {})",
+        current,
+        migrations::latest_version()
+    );
+    if pending.is_empty() {
+        println!("✅ Stored data is up to date");
+        return Ok(());
+    }
+
+    if dry_run {
+        for migration in &pending {
+            println!(
+                "  {} {}: {}",
+                migration.version, migration.name, migration.description
+            );
+        }
+        println!("{} pending migrations", pending.len());
+        return Ok(());
+    }
+
+    for (migration, summary) in migrations::run_pending().await? {
+        println!(
+            "✅ Applied migration {} {}: {}",
+            migration.version, migration.name, summary
+        );
+    }
+    Ok(())
+}
+
+async fn run_rule_command(action: RuleAction) -> Result<()> {
+    match action {
+        RuleAction::Add {
+            mint,
+            price_above,
+            price_below,
+            market_cap_above,
+            net_flow_above,
+            turnover_above,
+            window,
+            note,
+        } => {
+            let conditions: Vec<RuleCondition> = [
+                price_above.map(|value| RuleCondition::PriceAbove { value }),
+                price_below.map(|value| RuleCondition::PriceBelow { value }),
+                market_cap_above.map(|value| RuleCondition::MarketCapAbove { value }),
+                net_flow_above.map(|value| RuleCondition::NetFlowAbove {
+                    value,
+                    window_minutes: window,
+                }),
+                turnover_above.map(|value| RuleCondition::TurnoverAbove {
+                    value,
+                    window_minutes: window,
+                }),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+
+            let [condition] = <[RuleCondition; 1]>::try_from(conditions).map_err(|_| {
+                anyhow::anyhow!(
+                    "Specify exactly one of --price-above, --price-below, --market-cap-above, --net-flow-above, --turnover-above"
+                )
+            })?;
+            let rule = rules::add_rule(&mint, condition, note).await?;
+            println!("✅ Rule {} added for {}", rule.id, rule.mint);
+        }
+        RuleAction::List { mint } => {
+            let mint_rules = rules::get_rules(&mint).await?;
+            if mint_rules.is_empty() {
+                println!("ℹ️  No rules for {}", mint);
+            }
+            for rule in mint_rules {
+                println!(
+                    "{} {} note={}",
+                    rule.id,
+                    serde_json::to_string(&rule.condition)?,
+                    rule.note.unwrap_or_default()
+                );
+            }
+        }
+        RuleAction::Remove { mint, id } => {
+            if rules::remove_rule(&mint, &id).await? {
+                println!("🗑️ Rule {} removed", id);
+            } else {
+                println!("⚠️  Rule {} not found for {}", id, mint);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn run_price_alert_command(action: PriceAlertAction) -> Result<()> {
+    match action {
+        PriceAlertAction::Add {
+            mint,
+            price_above,
+            price_below,
+            market_cap_above,
+            market_cap_below,
+            note,
+        } => {
+            let targets: Vec<PriceAlertTarget> = [
+                price_above.map(|value| PriceAlertTarget::PriceAbove { value }),
+                price_below.map(|value| PriceAlertTarget::PriceBelow { value }),
+                market_cap_above.map(|value| PriceAlertTarget::MarketCapAbove { value }),
+                market_cap_below.map(|value| PriceAlertTarget::MarketCapBelow { value }),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+
+            let [target] = <[PriceAlertTarget; 1]>::try_from(targets).map_err(|_| {
+                anyhow::anyhow!(
+                    "Specify exactly one of --price-above, --price-below, --market-cap-above, --market-cap-below"
+                )
+            })?;
+            let price_alert = price_alerts::add_price_alert(&mint, target, note).await?;
+            println!(
+                "✅ Price alert {} added for {}",
+                price_alert.id, price_alert.mint
+            );
+        }
+        PriceAlertAction::List { mint } => {
+            let pending = price_alerts::get_price_alerts(&mint).await?;
+            if pending.is_empty() {
+                println!("ℹ️  No pending price alerts for {}", mint);
+            }
+            for price_alert in pending {
+                println!(
+                    "{} {} note={}",
+                    price_alert.id,
+                    serde_json::to_string(&price_alert.target)?,
+                    price_alert.note.unwrap_or_default()
+                );
+            }
+        }
+        PriceAlertAction::Remove { mint, id } => {
+            if price_alerts::remove_price_alert(&mint, &id).await? {
+                println!("🗑️ Price alert {} removed", id);
+            } else {
+                println!("⚠️  Price alert {} not found for {}", id, mint);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn run_watchlist_command(action: WatchlistAction) -> Result<()> {
+    match action {
+        WatchlistAction::Add { mint } => {
+            if watchlist::add_mint(&mint).await? {
+                println!("✅ {} added to watchlist", mint);
+            } else {
+                println!("ℹ️  {} is already in the watchlist", mint);
+            }
+        }
+        WatchlistAction::Remove { mint } => {
+            if watchlist::remove_mint(&mint).await? {
+                println!("🗑️ {} removed from watchlist", mint);
+            } else {
+                println!("⚠️  {} is not in the watchlist", mint);
+            }
+        }
+        WatchlistAction::List => {
+            let mints = watchlist::get_watchlist().await?;
+            if mints.is_empty() {
+                println!("ℹ️  Watchlist is empty");
+            }
+            for mint in mints {
+                println!("{}", mint);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn run_label_command(action: LabelAction) -> Result<()> {
+    match action {
+        LabelAction::Import { file } => {
+            let content = std::fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read label file {}", file))?;
+            let entries = labels::parse_labels(&content)?;
+            let count = labels::set_labels(&entries).await?;
+            println!("✅ Imported {} wallet labels from {}", count, file);
+        }
+        LabelAction::Add {
+            wallet,
+            name,
+            category,
+        } => {
+            let entry = LabelEntry {
+                wallet: wallet.clone(),
+                name,
+                category,
+            };
+            labels::set_labels(&[entry]).await?;
+            println!("🏷️ Labeled {}", wallet);
+        }
+        LabelAction::Remove { wallet } => {
+            if labels::remove_label(&wallet).await? {
+                println!("🗑️ Removed label of {}", wallet);
+            } else {
+                println!("⚠️  {} has no label", wallet);
+            }
+        }
+        LabelAction::List => {
+            let all = labels::get_all_labels().await?;
+            if all.is_empty() {
+                println!("ℹ️  No wallet labels");
+            }
+            for (wallet, label) in all {
+                println!("{} {:?} {}", wallet, label.category, label.name);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn run_wallet_command(action: WalletAction) -> Result<()> {
+    match action {
+        WalletAction::Track { wallet } => {
+            if wallets::track_wallet(&wallet).await? {
+                println!("👀 Tracking {}", wallet);
+            } else {
+                println!("ℹ️  {} is already tracked", wallet);
+            }
+        }
+        WalletAction::Untrack { wallet } => {
+            if wallets::untrack_wallet(&wallet).await? {
+                println!("🗑️ Stopped tracking {}", wallet);
+            } else {
+                println!("⚠️  {} is not tracked", wallet);
+            }
+        }
+        WalletAction::List => {
+            let tracked = wallets::get_tracked_wallets().await?;
+            if tracked.is_empty() {
+                println!("ℹ️  No tracked wallets");
+            }
+            for wallet in tracked {
+                println!("{}", wallet);
+            }
+        }
+        WalletAction::Discover => {
+            let ranked = smart_money::discover(&smart_money::SmartMoneyConfig::from_env()).await?;
+            if ranked.is_empty() {
+                println!("ℹ️  No smart-money wallets found");
+            }
+            for (rank, wallet) in ranked.iter().enumerate() {
+                println!(
+                    "{:>3}. {} early={} profitable={} win_rate={:.2} pnl={:.4} SOL",
+                    rank + 1,
+                    wallet.wallet,
+                    wallet.early_mints,
+                    wallet.profitable_mints,
+                    wallet.win_rate,
+                    wallet.total_pnl_sol
+                );
+            }
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,245 @@
+// Rolling correlation and beta of each mint against a market index built from
+// the monitored mints. The index is the equal-weighted mean of the one-minute
+// log returns of the most recently active mints, so a single large token does
+// not dominate it. It is rebuilt at most once a minute and shared through Redis
+// by the web and strategy services.
+
+use anyhow::Result;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::debug;
+
+use crate::kline::{KLineData, KLineManager};
+use crate::redis_helper;
+use crate::remote_config;
+
+// Cached MarketIndex JSON
+const MARKET_INDEX_KEY: &str = "market:index";
+const MARKET_INDEX_TTL_SECS: u64 = 60;
+
+#[derive(Debug, Clone)]
+pub struct MarketBetaConfig {
+    // Minutes of returns the index and the regressions span
+    pub window_minutes: usize,
+    // Most recently active mints that make up the index
+    pub index_mints: usize,
+    // Mints that must have a return in a minute for it to count in the index
+    pub min_mints: usize,
+    // Minutes a mint must share with the index to get a beta
+    pub min_points: usize,
+}
+
+impl MarketBetaConfig {
+    pub fn from_env() -> Self {
+        Self {
+            window_minutes: remote_config::var("MARKET_BETA_WINDOW_MINUTES")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            index_mints: std::env::var("MARKET_INDEX_MINTS")
+                .unwrap_or_else(|_| "200".to_string())
+                .parse()
+                .unwrap_or(200),
+            min_mints: std::env::var("MARKET_INDEX_MIN_MINTS")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .unwrap_or(3),
+            min_points: remote_config::var("MARKET_BETA_MIN_POINTS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+        }
+    }
+}
+
+// Mean one-minute log return of the index mints, keyed by candle start time
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MarketIndex {
+    pub returns: BTreeMap<i64, f64>,
+    pub mints: usize,
+    pub window_minutes: usize,
+    pub built_at: i64,
+}
+
+// Regression of a mint's returns on the index returns
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MarketBeta {
+    pub beta: f64,
+    pub correlation: f64,
+    // Minutes both series have a return for
+    pub points: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MintIndicators {
+    pub mint: String,
+    pub window_minutes: usize,
+    pub index_mints: usize,
+    // None until the mint shares enough minutes with the index
+    pub market: Option<MarketBeta>,
+}
+
+// Log returns between consecutive one-minute candles; gaps break the series
+pub fn minute_returns(klines: &[KLineData]) -> BTreeMap<i64, f64> {
+    let closes: Vec<(i64, f64)> = klines
+        .iter()
+        .filter_map(|k| Some((k.timestamp, k.close.parse::<f64>().ok()?)))
+        .filter(|(_, close)| *close > 0.0)
+        .collect();
+    closes
+        .windows(2)
+        .filter(|pair| pair[1].0 - pair[0].0 == 60)
+        .map(|pair| (pair[1].0, (pair[1].1 / pair[0].1).ln()))
+        .collect()
+}
+
+// Equal-weighted mean return per minute over the minutes enough mints traded in
+pub fn build_index(series: &[BTreeMap<i64, f64>], min_mints: usize) -> BTreeMap<i64, f64> {
+    let mut sums: BTreeMap<i64, (f64, usize)> = BTreeMap::new();
+    for returns in series {
+        for (ts, r) in returns {
+            let entry = sums.entry(*ts).or_default();
+            entry.0 += r;
+            entry.1 += 1;
+        }
+    }
+    sums.into_iter()
+        .filter(|(_, (_, count))| *count >= min_mints.max(1))
+        .map(|(ts, (sum, count))| (ts, sum / count as f64))
+        .collect()
+}
+
+// Beta and correlation over the shared minutes; None with fewer than
+// `min_points` of them or a flat series
+pub fn market_beta(
+    returns: &BTreeMap<i64, f64>,
+    index: &BTreeMap<i64, f64>,
+    min_points: usize,
+) -> Option<MarketBeta> {
+    let pairs: Vec<(f64, f64)> = returns
+        .iter()
+        .filter_map(|(ts, r)| Some((*index.get(ts)?, *r)))
+        .collect();
+    let n = pairs.len();
+    if n < min_points.max(2) {
+        return None;
+    }
+
+    let mean_m = pairs.iter().map(|(m, _)| m).sum::<f64>() / n as f64;
+    let mean_r = pairs.iter().map(|(_, r)| r).sum::<f64>() / n as f64;
+    let (mut cov, mut var_m, mut var_r) = (0.0, 0.0, 0.0);
+    for (m, r) in &pairs {
+        cov += (m - mean_m) * (r - mean_r);
+        var_m += (m - mean_m).powi(2);
+        var_r += (r - mean_r).powi(2);
+    }
+    if var_m <= f64::EPSILON || var_r <= f64::EPSILON {
+        return None;
+    }
+    Some(MarketBeta {
+        beta: cov / var_m,
+        correlation: cov / (var_m.sqrt() * var_r.sqrt()),
+        points: n,
+    })
+}
+
+// Market index over the configured window, from the Redis cache when fresh
+pub async fn get_market_index(
+    kline_manager: &KLineManager,
+    config: &MarketBetaConfig,
+) -> Result<MarketIndex> {
+    {
+        let mut con = redis_helper::get_connection().await?;
+        let cached: Option<String> = con.get(MARKET_INDEX_KEY).await?;
+        if let Some(index) = cached.and_then(|d| serde_json::from_str::<MarketIndex>(&d).ok())
+            && index.window_minutes == config.window_minutes
+        {
+            return Ok(index);
+        }
+    }
+
+    let mints = kline_manager.get_active_mints().await?;
+    let mut series = Vec::new();
+    for (mint, _, _) in mints.iter().take(config.index_mints) {
+        let klines = kline_manager
+            .get_klines_for_mint(mint, 60, Some(config.window_minutes + 1))
+            .await?;
+        let returns = minute_returns(&klines);
+        if !returns.is_empty() {
+            series.push(returns);
+        }
+    }
+
+    let index = MarketIndex {
+        returns: build_index(&series, config.min_mints),
+        mints: series.len(),
+        window_minutes: config.window_minutes,
+        built_at: chrono::Utc::now().timestamp(),
+    };
+    debug!(
+        "Market index rebuilt from {} mints, {} minutes",
+        index.mints,
+        index.returns.len()
+    );
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = con
+        .set_ex(
+            MARKET_INDEX_KEY,
+            serde_json::to_string(&index)?,
+            MARKET_INDEX_TTL_SECS,
+        )
+        .await?;
+    Ok(index)
+}
+
+// Correlation and beta of a mint to the market index
+pub async fn get_mint_indicators(
+    kline_manager: &KLineManager,
+    mint: &str,
+) -> Result<MintIndicators> {
+    let config = MarketBetaConfig::from_env();
+    let index = get_market_index(kline_manager, &config).await?;
+    let klines = kline_manager
+        .get_klines_for_mint(mint, 60, Some(config.window_minutes + 1))
+        .await?;
+    Ok(MintIndicators {
+        mint: mint.to_string(),
+        window_minutes: config.window_minutes,
+        index_mints: index.mints,
+        market: market_beta(&minute_returns(&klines), &index.returns, config.min_points),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_market_beta() {
+        let market = [0.01, -0.02, 0.03, 0.0, -0.01, 0.02];
+        let series = |scale: f64, noise: &[f64]| -> BTreeMap<i64, f64> {
+            market
+                .iter()
+                .zip(noise)
+                .enumerate()
+                .map(|(i, (m, e))| (i as i64 * 60, m * scale + e))
+                .collect()
+        };
+        let index = build_index(&[series(1.0, &[0.0; 6])], 1);
+
+        // A mint moving twice as much as the market
+        let beta = market_beta(&series(2.0, &[0.0; 6]), &index, 5).unwrap();
+        assert!((beta.beta - 2.0).abs() < 1e-9);
+        assert!((beta.correlation - 1.0).abs() < 1e-9);
+        assert_eq!(beta.points, 6);
+
+        // Idiosyncratic moves have a low correlation
+        let own = series(0.0, &[0.02, 0.05, 0.01, -0.04, 0.03, 0.06]);
+        assert!(market_beta(&own, &index, 5).unwrap().correlation.abs() < 0.1);
+        assert!(market_beta(&own, &index, 7).is_none());
+
+        // Minutes with too few mints are left out of the index
+        assert!(build_index(&[series(1.0, &[0.0; 6])], 2).is_empty());
+    }
+}
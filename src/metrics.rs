@@ -0,0 +1,306 @@
+// Prometheus metrics. Every service counts into process-wide counters and
+// publishes a snapshot of them to Redis; the web service's /metrics endpoint
+// renders the snapshots of all services. Processes started with METRICS_PORT
+// also serve their own counters directly, for scraping without the web service.
+
+use anyhow::Result;
+use axum::{Router, http::header, response::IntoResponse, routing::get};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::{heartbeat, redis_helper, supervisor};
+
+// Redis hash of published snapshots, field "{service}@{instance}"
+const METRICS_KEY: &str = "metrics:services";
+
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(15);
+
+// Snapshots older than this belong to stopped processes and are dropped
+const STALE_SECS: i64 = 300;
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+// Upper bounds in seconds of the trade write latency buckets
+const WRITE_BUCKETS: [f64; 10] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+static TRADES_PROCESSED: AtomicU64 = AtomicU64::new(0);
+static PARSE_FAILURES: AtomicU64 = AtomicU64::new(0);
+static RECONNECTS: AtomicU64 = AtomicU64::new(0);
+static ALERTS_FIRED: AtomicU64 = AtomicU64::new(0);
+static WRITE_BUCKET_COUNTS: [AtomicU64; WRITE_BUCKETS.len()] =
+    [const { AtomicU64::new(0) }; WRITE_BUCKETS.len()];
+static WRITE_COUNT: AtomicU64 = AtomicU64::new(0);
+static WRITE_MICROS: AtomicU64 = AtomicU64::new(0);
+
+// Count a trade written to Redis and how long its writes took
+pub fn record_trade(write_time: Duration) {
+    TRADES_PROCESSED.fetch_add(1, Ordering::Relaxed);
+    let secs = write_time.as_secs_f64();
+    if let Some(bucket) = WRITE_BUCKETS.iter().position(|bound| secs <= *bound) {
+        WRITE_BUCKET_COUNTS[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+    WRITE_COUNT.fetch_add(1, Ordering::Relaxed);
+    WRITE_MICROS.fetch_add(write_time.as_micros() as u64, Ordering::Relaxed);
+}
+
+// Count an event that was seen but could not be decoded, or was decoded implausibly
+pub fn record_parse_failure() {
+    PARSE_FAILURES.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_reconnect() {
+    RECONNECTS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn record_alerts(count: usize) {
+    ALERTS_FIRED.fetch_add(count as u64, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    pub service: String,
+    pub instance: String,
+    pub timestamp: i64,
+    pub trades_processed: u64,
+    pub parse_failures: u64,
+    pub reconnects: u64,
+    pub alerts_fired: u64,
+    pub panics: u64,
+    // Writes per latency bucket, not cumulative; slower writes only count in write_count
+    pub write_buckets: Vec<u64>,
+    pub write_count: u64,
+    pub write_sum_secs: f64,
+}
+
+// The counters of this process
+pub fn snapshot(service: &str) -> MetricsSnapshot {
+    MetricsSnapshot {
+        service: service.to_string(),
+        instance: heartbeat::instance_name(),
+        timestamp: chrono::Utc::now().timestamp(),
+        trades_processed: TRADES_PROCESSED.load(Ordering::Relaxed),
+        parse_failures: PARSE_FAILURES.load(Ordering::Relaxed),
+        reconnects: RECONNECTS.load(Ordering::Relaxed),
+        alerts_fired: ALERTS_FIRED.load(Ordering::Relaxed),
+        panics: supervisor::panic_count(),
+        write_buckets: WRITE_BUCKET_COUNTS
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect(),
+        write_count: WRITE_COUNT.load(Ordering::Relaxed),
+        write_sum_secs: WRITE_MICROS.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+    }
+}
+
+async fn publish(snapshot: &MetricsSnapshot) -> Result<()> {
+    let data = serde_json::to_string(snapshot)?;
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = con
+        .hset(
+            METRICS_KEY,
+            format!("{}@{}", snapshot.service, snapshot.instance),
+            data,
+        )
+        .await?;
+    Ok(())
+}
+
+// Snapshots published by running services, stale ones are removed
+pub async fn load_snapshots() -> Result<Vec<MetricsSnapshot>> {
+    let mut con = redis_helper::get_connection().await?;
+    let data: HashMap<String, String> = con.hgetall(METRICS_KEY).await?;
+    let now = chrono::Utc::now().timestamp();
+
+    let mut snapshots = Vec::new();
+    let mut stale = Vec::new();
+    for (field, d) in data {
+        match serde_json::from_str::<MetricsSnapshot>(&d) {
+            Ok(snapshot) if now - snapshot.timestamp <= STALE_SECS => snapshots.push(snapshot),
+            _ => stale.push(field),
+        }
+    }
+    if !stale.is_empty() {
+        let _: () = con.hdel(METRICS_KEY, &stale).await?;
+    }
+    snapshots.sort_by(|a, b| (&a.service, &a.instance).cmp(&(&b.service, &b.instance)));
+    Ok(snapshots)
+}
+
+// Name, help text and value of a counter metric
+type Counter = (&'static str, &'static str, fn(&MetricsSnapshot) -> u64);
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+// Render snapshots and extra gauges in the Prometheus text exposition format
+pub fn render(snapshots: &[MetricsSnapshot], gauges: &[(&str, &str, f64)]) -> String {
+    let mut out = String::new();
+    let labels = |s: &MetricsSnapshot| {
+        format!(
+            "service=\"{}\",instance=\"{}\"",
+            escape_label(&s.service),
+            escape_label(&s.instance)
+        )
+    };
+
+    let counters: [Counter; 5] = [
+        (
+            "kmonitor_trades_processed_total",
+            "Trades written to candles.",
+            |s| s.trades_processed,
+        ),
+        (
+            "kmonitor_parse_failures_total",
+            "Program events that failed to decode or decoded implausibly.",
+            |s| s.parse_failures,
+        ),
+        (
+            "kmonitor_websocket_reconnects_total",
+            "Websocket connections that failed and were retried.",
+            |s| s.reconnects,
+        ),
+        (
+            "kmonitor_alerts_fired_total",
+            "Strategy alerts fired, whether or not a notification was sent.",
+            |s| s.alerts_fired,
+        ),
+        (
+            "kmonitor_panics_total",
+            "Background tasks that panicked.",
+            |s| s.panics,
+        ),
+    ];
+    for (name, help, value) in counters {
+        write_header(&mut out, name, "counter", help);
+        for snapshot in snapshots {
+            let _ = writeln!(out, "{}{{{}}} {}", name, labels(snapshot), value(snapshot));
+        }
+    }
+
+    let name = "kmonitor_trade_write_duration_seconds";
+    write_header(
+        &mut out,
+        name,
+        "histogram",
+        "Time spent writing a trade's candle, tick and positions to Redis.",
+    );
+    for snapshot in snapshots {
+        let labels = labels(snapshot);
+        let mut cumulative = 0;
+        for (bound, count) in WRITE_BUCKETS.iter().zip(&snapshot.write_buckets) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"{}\"}} {}",
+                name, labels, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{},le=\"+Inf\"}} {}",
+            name, labels, snapshot.write_count
+        );
+        let _ = writeln!(
+            out,
+            "{}_sum{{{}}} {}",
+            name, labels, snapshot.write_sum_secs
+        );
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, snapshot.write_count);
+    }
+
+    for (name, help, value) in gauges {
+        write_header(&mut out, name, "gauge", help);
+        let _ = writeln!(out, "{} {}", name, value);
+    }
+    out
+}
+
+fn metrics_port() -> Option<u16> {
+    std::env::var("METRICS_PORT").ok()?.parse().ok()
+}
+
+// Publish this process's counters for the web service, and serve them on
+// METRICS_PORT when it is set
+pub fn spawn(service: String) -> JoinHandle<()> {
+    if let Some(port) = metrics_port() {
+        let service = service.clone();
+        supervisor::spawn_supervised("metrics_server", async move {
+            let app = Router::new().route(
+                "/metrics",
+                get(move || {
+                    let body = render(&[snapshot(&service)], &[]);
+                    async move { ([(header::CONTENT_TYPE, CONTENT_TYPE)], body).into_response() }
+                }),
+            );
+            info!("📈 Serving metrics at http://0.0.0.0:{}/metrics", port);
+            match tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await {
+                Ok(listener) => {
+                    if let Err(e) = axum::serve(listener, app).await {
+                        warn!("Metrics server stopped: {}", e);
+                    }
+                }
+                Err(e) => warn!("Failed to bind metrics port {}: {}", port, e),
+            }
+        });
+    }
+
+    supervisor::spawn_supervised("metrics", async move {
+        let mut interval = tokio::time::interval(PUBLISH_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = publish(&snapshot(&service)).await {
+                warn!("Failed to publish metrics of {}: {}", service, e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let snapshot = MetricsSnapshot {
+            service: "monitor#1".to_string(),
+            instance: "host:1".to_string(),
+            trades_processed: 7,
+            write_buckets: vec![2, 0, 3, 0, 0, 0, 0, 0, 0, 1],
+            write_count: 7,
+            write_sum_secs: 1.5,
+            ..Default::default()
+        };
+        let text = render(&[snapshot], &[("kmonitor_active_mints", "Mints.", 3.0)]);
+        let labels = "service=\"monitor#1\",instance=\"host:1\"";
+
+        assert!(text.contains(&format!(
+            "kmonitor_trades_processed_total{{{}}} 7\n",
+            labels
+        )));
+        assert!(text.contains(&format!(
+            "kmonitor_trade_write_duration_seconds_bucket{{{},le=\"0.005\"}} 5\n",
+            labels
+        )));
+        assert!(text.contains(&format!(
+            "kmonitor_trade_write_duration_seconds_bucket{{{},le=\"+Inf\"}} 7\n",
+            labels
+        )));
+        assert!(text.contains("# TYPE kmonitor_active_mints gauge\nkmonitor_active_mints 3\n"));
+        assert_eq!(escape_label("a\"b\\"), "a\\\"b\\\\");
+    }
+}
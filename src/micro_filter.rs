@@ -0,0 +1,193 @@
+use rust_decimal::Decimal;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+
+use crate::remote_config;
+
+#[derive(Debug, Clone)]
+pub struct MicroFilterConfig {
+    // Percent of the mint's volume over the window a trade must reach, 0 uses
+    // the fixed MIN_SOL_AMOUNT_* threshold only
+    pub volume_pct: Decimal,
+    // Percent of pool liquidity an AMM trade must reach, 0 ignores liquidity
+    pub liquidity_pct: Decimal,
+    // Length of the volume window in seconds
+    pub window_secs: i64,
+    // Bounds of the scaled threshold in SOL
+    pub floor_sol: Decimal,
+    pub cap_sol: Decimal,
+}
+
+impl MicroFilterConfig {
+    pub fn from_env() -> Self {
+        Self {
+            volume_pct: remote_config::var("MICRO_TRADE_VOLUME_PCT")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(Decimal::ZERO),
+            liquidity_pct: remote_config::var("MICRO_TRADE_LIQUIDITY_PCT")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(Decimal::ZERO),
+            window_secs: remote_config::var("MICRO_TRADE_WINDOW_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+            floor_sol: remote_config::var("MICRO_TRADE_FLOOR_SOL")
+                .unwrap_or_else(|_| "0.001".to_string())
+                .parse()
+                .unwrap_or_else(|_| Decimal::new(1, 3)),
+            cap_sol: remote_config::var("MICRO_TRADE_CAP_SOL")
+                .unwrap_or_else(|_| "1".to_string())
+                .parse()
+                .unwrap_or(Decimal::ONE),
+        }
+    }
+
+    pub fn is_adaptive(&self) -> bool {
+        self.volume_pct > Decimal::ZERO || self.liquidity_pct > Decimal::ZERO
+    }
+
+    // Smallest trade kept, given the mint's recent volume and, for AMM trades,
+    // the pool liquidity. Without scaling this is the fixed minimum.
+    pub fn threshold(
+        &self,
+        fixed_min: Decimal,
+        recent_volume: Decimal,
+        liquidity_sol: Option<Decimal>,
+    ) -> Decimal {
+        if !self.is_adaptive() {
+            return fixed_min;
+        }
+        let by_volume = recent_volume * self.volume_pct / Decimal::ONE_HUNDRED;
+        let by_liquidity = liquidity_sol
+            .map(|liquidity| liquidity * self.liquidity_pct / Decimal::ONE_HUNDRED)
+            .unwrap_or(Decimal::ZERO);
+        by_volume
+            .max(by_liquidity)
+            .max(self.floor_sol)
+            .min(self.cap_sol)
+    }
+}
+
+// SOL volume of one mint's trades per minute, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct RecentVolume {
+    minutes: VecDeque<(i64, Decimal)>,
+}
+
+impl RecentVolume {
+    // Volume of the `window_secs` before a trade, then count the trade itself
+    pub fn observe(&mut self, timestamp: i64, sol_amount: Decimal, window_secs: i64) -> Decimal {
+        while self
+            .minutes
+            .front()
+            .is_some_and(|(minute, _)| *minute <= timestamp - window_secs)
+        {
+            self.minutes.pop_front();
+        }
+        let volume = self.minutes.iter().map(|(_, volume)| volume).sum();
+
+        let minute = timestamp - timestamp.rem_euclid(60);
+        match self.minutes.back_mut() {
+            Some((last, volume)) if *last >= minute => *volume += sol_amount,
+            _ => self.minutes.push_back((minute, sol_amount)),
+        }
+        volume
+    }
+
+    fn last_minute(&self) -> Option<i64> {
+        self.minutes.back().map(|(minute, _)| *minute)
+    }
+}
+
+static VOLUMES: LazyLock<Mutex<HashMap<String, RecentVolume>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Whether a trade is large enough to go into candles. Every trade counts
+// towards its mint's recent volume, including dropped ones, so the threshold
+// follows the token's real activity. Volumes live in memory, so each monitor
+// process scales with the trades it sees.
+pub fn accept(
+    mint: &str,
+    timestamp: i64,
+    sol_amount: Decimal,
+    fixed_min: Decimal,
+    liquidity_sol: Option<Decimal>,
+) -> bool {
+    let config = MicroFilterConfig::from_env();
+    if !config.is_adaptive() {
+        return sol_amount >= fixed_min;
+    }
+
+    let recent_volume = {
+        let mut volumes = VOLUMES.lock().unwrap_or_else(|e| e.into_inner());
+        // Mints that stopped trading leave their volume behind; keep the map small
+        if volumes.len() > 1000 && !volumes.contains_key(mint) {
+            let cutoff = timestamp - config.window_secs;
+            volumes.retain(|_, v| v.last_minute().is_some_and(|minute| minute > cutoff));
+        }
+        volumes.entry(mint.to_string()).or_default().observe(
+            timestamp,
+            sol_amount,
+            config.window_secs,
+        )
+    };
+    sol_amount >= config.threshold(fixed_min, recent_volume, liquidity_sol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(volume_pct: &str, liquidity_pct: &str) -> MicroFilterConfig {
+        MicroFilterConfig {
+            volume_pct: volume_pct.parse().unwrap(),
+            liquidity_pct: liquidity_pct.parse().unwrap(),
+            window_secs: 300,
+            floor_sol: Decimal::new(1, 3),
+            cap_sol: Decimal::ONE,
+        }
+    }
+
+    #[test]
+    fn test_threshold() {
+        let fixed = Decimal::new(1, 2);
+        assert_eq!(
+            config("0", "0").threshold(fixed, Decimal::from(1000), None),
+            fixed
+        );
+
+        // 0.05% of 200 SOL of recent volume
+        let adaptive = config("0.05", "0");
+        assert_eq!(
+            adaptive.threshold(fixed, Decimal::from(200), None),
+            Decimal::new(1, 1)
+        );
+        // Quiet tokens fall to the floor, huge ones stop at the cap
+        assert_eq!(
+            adaptive.threshold(fixed, Decimal::ONE, None),
+            Decimal::new(1, 3)
+        );
+        assert_eq!(
+            adaptive.threshold(fixed, Decimal::from(100_000), None),
+            Decimal::ONE
+        );
+
+        let pooled = config("0.05", "0.01");
+        assert_eq!(
+            pooled.threshold(fixed, Decimal::ONE, Some(Decimal::from(5000))),
+            Decimal::new(5, 1)
+        );
+    }
+
+    #[test]
+    fn test_recent_volume() {
+        let mut volume = RecentVolume::default();
+        assert_eq!(volume.observe(1_000, Decimal::ONE, 300), Decimal::ZERO);
+        assert_eq!(volume.observe(1_010, Decimal::TWO, 300), Decimal::ONE);
+        assert_eq!(volume.observe(1_200, Decimal::ONE, 300), Decimal::from(3));
+        // The minute of the first two trades (960) left the window
+        assert_eq!(volume.observe(1_300, Decimal::ONE, 300), Decimal::ONE);
+    }
+}
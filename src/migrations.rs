@@ -0,0 +1,158 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use tracing::info;
+
+use crate::kline::KLineManager;
+use crate::kline_codec::StorageVersion;
+use crate::kline_store::RedisKLineStore;
+use crate::redis_helper;
+
+// Version of the last migration applied to the stored data
+const SCHEMA_VERSION_KEY: &str = "schema:version";
+
+// Held while migrations run so two migrate commands can't interleave
+const MIGRATION_LOCK_KEY: &str = "schema:migration_lock";
+const MIGRATION_LOCK_TTL_SECS: u64 = 3600;
+
+/// 存储数据的版本化迁移，按版本号顺序执行，每个迁移都必须可重复执行
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// 所有迁移，新迁移追加到末尾并使用递增的版本号
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "kline_index",
+        description: "Index existing K-lines in the per-mint timestamp ZSET",
+    },
+    Migration {
+        version: 2,
+        name: "mint_volume",
+        description: "Rebuild cumulative SOL volume per mint from stored K-lines",
+    },
+    Migration {
+        version: 3,
+        name: "kline_source",
+        description: "Tag K-lines of mints that never graduated with the bonding curve source",
+    },
+    Migration {
+        version: 4,
+        name: "mint_activity",
+        description: "Move per-mint activity keys into the activity ZSET",
+    },
+    Migration {
+        version: 5,
+        name: "kline_rollups",
+        description: "Roll up stored minute K-lines into the KLINE_ROLLUP_INTERVALS timeframes",
+    },
+];
+
+/// 最新的数据版本
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// 当前版本之后尚未执行的迁移
+pub fn pending_migrations(current: u32) -> Vec<Migration> {
+    MIGRATIONS
+        .iter()
+        .filter(|m| m.version > current)
+        .copied()
+        .collect()
+}
+
+/// 获取已执行到的数据版本，从未迁移过的数据为0
+pub async fn get_schema_version() -> Result<u32> {
+    Ok(redis_helper::get::<_, u32>(SCHEMA_VERSION_KEY)
+        .await?
+        .unwrap_or(0))
+}
+
+// Run one migration, returning a short summary of what it changed. Migrations
+// of data only earlier versions wrote go straight to Redis.
+async fn apply(migration: &Migration, kline_manager: &KLineManager) -> Result<String> {
+    let redis_store = RedisKLineStore::new(StorageVersion::from_env());
+    match migration.name {
+        "kline_index" => Ok(format!(
+            "indexed {} K-lines",
+            kline_manager.rebuild_kline_index().await?
+        )),
+        "mint_volume" => Ok(format!(
+            "rebuilt volume of {} mints",
+            redis_store.rebuild_mint_volume().await?
+        )),
+        "kline_source" => Ok(format!(
+            "tagged {} K-lines",
+            redis_store.backfill_kline_source().await?
+        )),
+        "mint_activity" => Ok(format!(
+            "moved activity of {} mints",
+            redis_store.migrate_mint_activity().await?
+        )),
+        "kline_rollups" => Ok(format!(
+            "wrote {} rolled-up K-lines",
+            kline_manager.rebuild_all_rollups().await?
+        )),
+        name => Err(anyhow::anyhow!("Unknown migration: {}", name)),
+    }
+}
+
+/// 按顺序执行所有待执行的迁移，每完成一个就记录版本，中断后重新执行会从下一个继续。
+/// 返回执行的迁移及其结果
+pub async fn run_pending() -> Result<Vec<(Migration, String)>> {
+    let locked: bool = {
+        let mut con = redis_helper::get_connection().await?;
+        redis::cmd("SET")
+            .arg(MIGRATION_LOCK_KEY)
+            .arg(chrono::Utc::now().timestamp())
+            .arg("NX")
+            .arg("EX")
+            .arg(MIGRATION_LOCK_TTL_SECS)
+            .query_async::<Option<String>>(&mut *con)
+            .await?
+            .is_some()
+    };
+    if !locked {
+        return Err(anyhow::anyhow!(
+            "Another migration is running (lock {} is held)",
+            MIGRATION_LOCK_KEY
+        ));
+    }
+
+    let result = run_locked().await;
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = con.del(MIGRATION_LOCK_KEY).await?;
+    result
+}
+
+async fn run_locked() -> Result<Vec<(Migration, String)>> {
+    let kline_manager = KLineManager::new().await?;
+    let mut applied = Vec::new();
+    for migration in pending_migrations(get_schema_version().await?) {
+        info!(
+            "Applying migration {} ({})",
+            migration.version, migration.name
+        );
+        let summary = apply(&migration, &kline_manager).await?;
+        redis_helper::set(SCHEMA_VERSION_KEY, migration.version).await?;
+        applied.push((migration, summary));
+    }
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_ordered() {
+        assert!(MIGRATIONS.windows(2).all(|w| w[0].version < w[1].version));
+        assert_eq!(pending_migrations(0).len(), MIGRATIONS.len());
+        assert!(pending_migrations(latest_version()).is_empty());
+        assert_eq!(pending_migrations(1)[0].version, 2);
+    }
+}
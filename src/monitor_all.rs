@@ -0,0 +1,104 @@
+// Pump and PumpSwap on one connection. Instead of a monitor process each, one
+// websocket subscribes to both programs and every transaction goes to the
+// parser of each program it invokes. The two monitors keep their own pause
+// state: a paused one has its transactions dropped while the other continues.
+
+use anyhow::Result;
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::constant::{PUMP_AMM_PROGRAM, PUMP_PROGRAM};
+use crate::kline::KLineManager;
+use crate::monitor_control;
+use crate::websocket::{self, WebSocketMonitor};
+use crate::{pump, pump_amm};
+
+// Monitor name of the shared connection, for WS_PROXY_ALL and INGESTION_MODE_ALL
+pub const MONITOR_NAME: &str = "ALL";
+
+// Whether a message's logs show the program being invoked, at any depth
+pub fn invokes(response: &Value, program: &str) -> bool {
+    let prefix = format!("Program {} invoke", program);
+    response
+        .pointer("/params/result/value/logs")
+        .and_then(Value::as_array)
+        .is_some_and(|logs| {
+            logs.iter()
+                .filter_map(Value::as_str)
+                .any(|log| log.starts_with(&prefix))
+        })
+}
+
+pub async fn handle_message(
+    response: &Value,
+    kline_manager: Arc<KLineManager>,
+    pump_paused: bool,
+    amm_paused: bool,
+) -> Result<()> {
+    // A failure of one parser doesn't keep the other from its events
+    let pump_result = if !pump_paused && invokes(response, PUMP_PROGRAM) {
+        pump::handle_pump_message(response, kline_manager.clone()).await
+    } else {
+        Ok(())
+    };
+    let amm_result = if !amm_paused && invokes(response, PUMP_AMM_PROGRAM) {
+        pump_amm::handle_amm_message(response, kline_manager).await
+    } else {
+        Ok(())
+    };
+    pump_result.and(amm_result)
+}
+
+pub async fn connect_websocket(
+    rpc_ws_endpoints: &[String],
+    kline_manager: Arc<KLineManager>,
+) -> Result<()> {
+    let pump_control = monitor_control::spawn_listener("PUMP").await?;
+    let amm_control = monitor_control::spawn_listener("AMM").await?;
+    let monitors = rpc_ws_endpoints
+        .iter()
+        .map(|endpoint| {
+            WebSocketMonitor::new(
+                endpoint.clone(),
+                Arc::clone(&kline_manager),
+                vec![PUMP_PROGRAM.to_string(), PUMP_AMM_PROGRAM.to_string()],
+                MONITOR_NAME.to_string(),
+            )
+        })
+        .collect();
+
+    websocket::start_redundant(
+        monitors,
+        move |response: &Value, kline_manager: Arc<KLineManager>| {
+            let response = response.clone();
+            let pump_paused = *pump_control.borrow();
+            let amm_paused = *amm_control.borrow();
+            async move { handle_message(&response, kline_manager, pump_paused, amm_paused).await }
+        },
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_invokes() {
+        let response = json!({ "params": { "result": { "value": { "logs": [
+            format!("Program {} invoke [1]", PUMP_AMM_PROGRAM),
+            format!("Program {} invoke [2]", PUMP_PROGRAM),
+            "Program log: Instruction: Buy",
+        ]}}}});
+        assert!(invokes(&response, PUMP_AMM_PROGRAM));
+        assert!(invokes(&response, PUMP_PROGRAM));
+
+        let response = json!({ "params": { "result": { "value": { "logs": [
+            format!("Program {} invoke [1]", PUMP_AMM_PROGRAM),
+            format!("Program log: {}", PUMP_PROGRAM),
+        ]}}}});
+        assert!(!invokes(&response, PUMP_PROGRAM));
+        assert!(!invokes(&json!({}), PUMP_AMM_PROGRAM));
+    }
+}
@@ -0,0 +1,138 @@
+// Pause and resume ingestion of running monitors. The admin API records paused
+// monitors in Redis and signals them on a pub/sub channel. Each monitor process
+// mirrors its own state into a watch channel: a paused monitor closes its
+// websocket and waits for resume instead of the process being killed.
+
+use anyhow::Result;
+use futures_util::StreamExt;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::redis_helper;
+use crate::supervisor;
+
+// Set of paused monitor names, so restarted monitors stay paused
+const PAUSED_KEY: &str = "monitor:paused";
+// Pub/sub channel carrying the name of a monitor whose state changed
+const CONTROL_CHANNEL: &str = "monitor:control";
+
+// Monitors that can be paused
+pub const MONITORS: &[&str] = &["PUMP", "AMM"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorState {
+    pub monitor: String,
+    pub paused: bool,
+}
+
+// Canonical monitor name, e.g. "amm" -> "AMM"
+pub fn parse_monitor(monitor: &str) -> Result<String> {
+    let name = monitor.trim().to_ascii_uppercase();
+    if !MONITORS.contains(&name.as_str()) {
+        anyhow::bail!(
+            "Unknown monitor: {} (expected one of {})",
+            monitor,
+            MONITORS.join(", ")
+        );
+    }
+    Ok(name)
+}
+
+pub async fn is_paused(monitor: &str) -> Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    Ok(con.sismember(PAUSED_KEY, monitor).await?)
+}
+
+// Pause or resume a monitor and signal it; false when it was already in that state
+pub async fn set_paused(monitor: &str, paused: bool) -> Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    let changed: usize = if paused {
+        con.sadd(PAUSED_KEY, monitor).await?
+    } else {
+        con.srem(PAUSED_KEY, monitor).await?
+    };
+    let _: () = con.publish(CONTROL_CHANNEL, monitor).await?;
+    Ok(changed > 0)
+}
+
+pub async fn get_states() -> Result<Vec<MonitorState>> {
+    let mut con = redis_helper::get_connection().await?;
+    let paused: Vec<String> = con.smembers(PAUSED_KEY).await?;
+    Ok(MONITORS
+        .iter()
+        .map(|monitor| MonitorState {
+            monitor: monitor.to_string(),
+            paused: paused.iter().any(|p| p == monitor),
+        })
+        .collect())
+}
+
+// Watch a monitor's paused state, starting from the stored one. The listener
+// re-reads the stored state after every reconnect so no signal is missed.
+pub async fn spawn_listener(monitor: &str) -> Result<watch::Receiver<bool>> {
+    let (sender, receiver) = watch::channel(is_paused(monitor).await?);
+    let monitor = monitor.to_string();
+    supervisor::spawn_supervised("monitor_control", async move {
+        loop {
+            if let Err(e) = run_listener(&monitor, &sender).await {
+                warn!("{} control channel disconnected: {}", monitor, e);
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+        }
+    });
+    Ok(receiver)
+}
+
+async fn run_listener(monitor: &str, sender: &watch::Sender<bool>) -> Result<()> {
+    let redis_url =
+        std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379/".to_string());
+    let mut pubsub = redis::Client::open(redis_url)?.get_async_pubsub().await?;
+    pubsub.subscribe(CONTROL_CHANNEL).await?;
+    update_state(monitor, sender).await?;
+
+    let mut messages = pubsub.on_message();
+    while let Some(message) = messages.next().await {
+        let target: String = message.get_payload()?;
+        if target == monitor {
+            update_state(monitor, sender).await?;
+        }
+    }
+    Err(anyhow::anyhow!("Redis pub/sub stream ended"))
+}
+
+async fn update_state(monitor: &str, sender: &watch::Sender<bool>) -> Result<()> {
+    let paused = is_paused(monitor).await?;
+    let changed = sender.send_if_modified(|state| std::mem::replace(state, paused) != paused);
+    if changed {
+        info!(
+            "{} ingestion {} by control channel",
+            monitor,
+            if paused { "paused" } else { "resumed" }
+        );
+    }
+    Ok(())
+}
+
+// Resolve once the monitor is paused; never for monitors without a control channel
+pub async fn wait_paused(control: &mut Option<watch::Receiver<bool>>) {
+    if let Some(control) = control
+        && control.wait_for(|paused| *paused).await.is_ok()
+    {
+        return;
+    }
+    std::future::pending().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_monitor() {
+        assert_eq!(parse_monitor("amm").unwrap(), "AMM");
+        assert_eq!(parse_monitor(" Pump ").unwrap(), "PUMP");
+        assert!(parse_monitor("raydium").is_err());
+    }
+}
@@ -1,21 +1,311 @@
+use crate::localized;
+use crate::message_bus;
+use crate::notification_actions;
+use crate::notification_format::NotificationFormat;
+use crate::notification_profiles::{self, NotificationProfile};
+use crate::priority_fee::{self, FeeContext};
+use crate::remote_config;
 use crate::strategy::StrategyAlert;
+use crate::supervisor;
+use crate::suppression::{self, SuppressionRule};
 use anyhow::Result;
+use chrono::Timelike;
 use redis::{AsyncCommands, Client as RedisClient};
+use serde::{Deserialize, Serialize};
 use serde_json;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::process::Command;
 use tracing::{error, info, warn};
 
+// Redis hashes of failed notifications by id: waiting for a retry, and retries exhausted
+const RETRY_QUEUE_KEY: &str = "notifications:retry";
+const DEAD_LETTER_KEY: &str = "notifications:dead_letter";
+
+/// 发送失败的通知，在重试队列中等待重试或已进入死信队列
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedNotification {
+    pub id: String,
+    /// 原始payload（单条告警或汇总）
+    pub payload: serde_json::Value,
+    /// payload包含的代币，发送成功后记录冷却
+    pub mints: Vec<String>,
+    /// 已发送的次数（含首次）
+    pub attempts: u32,
+    pub last_error: String,
+    /// 首次失败时间（秒）
+    pub failed_at: i64,
+    /// 下次重试时间（秒），死信中为最后一次失败的时间
+    pub next_retry_at: i64,
+    /// 发送给的通知方案，为空时是默认渠道
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+/// 重试队列和死信队列中的通知，按首次失败时间倒序
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedNotifications {
+    pub retrying: Vec<FailedNotification>,
+    pub dead_letter: Vec<FailedNotification>,
+}
+
+/// 第N次失败后的重试间隔（秒），按指数退避
+pub fn retry_delay(backoff_secs: u64, attempts: u32) -> u64 {
+    backoff_secs.saturating_mul(1 << attempts.saturating_sub(1).min(10))
+}
+
+/// Telegram单条消息的最大长度（字符）
+const TELEGRAM_MAX_MESSAGE_CHARS: usize = 4096;
+
+/// 把格式化消息转换为Telegram的Markdown：标题转为粗体，列表项前缀转为圆点
+pub fn telegram_markdown(message: &str) -> String {
+    message
+        .lines()
+        .map(|line| {
+            let heading = line.trim_start_matches('#');
+            if heading.len() < line.len() && heading.starts_with(' ') {
+                format!("*{}*", heading.trim())
+            } else if let Some(item) = line.strip_prefix("- ") {
+                format!("• {}", item)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Telegram sendMessage请求体，超长消息截断到Telegram的长度上限
+pub fn telegram_message(chat_id: &str, message: &str, markdown: bool) -> serde_json::Value {
+    let text = if markdown {
+        telegram_markdown(message)
+    } else {
+        message.to_string()
+    };
+    let text: String = text.chars().take(TELEGRAM_MAX_MESSAGE_CHARS).collect();
+    let mut body = serde_json::json!({
+        "chat_id": chat_id,
+        "text": text,
+        "disable_web_page_preview": true,
+    });
+    if markdown {
+        body["parse_mode"] = serde_json::json!("Markdown");
+    }
+    body
+}
+
+/// 单个通知渠道的测试结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelTestResult {
+    pub channel: String,
+    pub success: bool,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// 告警payload的schema版本，字段发生不兼容变更时递增
+pub const ALERT_SCHEMA_VERSION: u32 = 1;
+
+/// 告警payload中的K线（schema v1）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertCandle {
+    /// K线开始时间（秒）
+    pub timestamp: i64,
+    pub open: String,
+    pub high: String,
+    pub low: String,
+    pub close: String,
+    /// 成交量（SOL）
+    pub volume_sol: String,
+    /// 净流入（SOL），买入为正
+    pub net_flow_sol: String,
+}
+
+/// 所有通知渠道发送的告警payload（schema v1）。
+/// 与内部的 `StrategyAlert` 解耦：新增字段只能以可选字段的方式加入，
+/// 删除或修改字段时必须提升 `schema_version`。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertPayload {
+    /// 固定为 `ALERT_SCHEMA_VERSION`
+    pub schema_version: u32,
+    /// 固定为 "strategy_alert"
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub mint: String,
+    pub strategy_name: String,
+    pub message: String,
+    /// 告警时间（秒）
+    pub timestamp: i64,
+    /// 严重度（0-100），未评分时省略
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<u8>,
+    /// 适合直接展示的Markdown消息
+    pub formatted_message: String,
+    pub klines: Vec<AlertCandle>,
+    /// 告警时的网络优先费和拥堵情况，未采样时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<FeeContext>,
+    /// 原始告警对象，schema版本之前的通知脚本读取此字段，保留以兼容
+    pub alert: StrategyAlert,
+}
+
+impl AlertPayload {
+    pub fn from_alert(
+        alert: &StrategyAlert,
+        formatted_message: String,
+        network: Option<FeeContext>,
+    ) -> Self {
+        Self {
+            schema_version: ALERT_SCHEMA_VERSION,
+            kind: "strategy_alert".to_string(),
+            mint: alert.mint.clone(),
+            strategy_name: alert.strategy_name.clone(),
+            message: alert.message.clone(),
+            timestamp: alert.timestamp,
+            severity: alert.severity,
+            formatted_message,
+            klines: alert
+                .klines
+                .iter()
+                .map(|k| AlertCandle {
+                    timestamp: k.timestamp,
+                    open: k.open.clone(),
+                    high: k.high.clone(),
+                    low: k.low.clone(),
+                    close: k.close.clone(),
+                    volume_sol: k.volume_sol.clone(),
+                    net_flow_sol: k.net_flow_sol.clone(),
+                })
+                .collect(),
+            network,
+            alert: alert.clone(),
+        }
+    }
+}
+
+/// 汇总通知中的单条告警（schema v1）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestItem {
+    pub mint: String,
+    pub strategy_name: String,
+    pub message: String,
+    pub timestamp: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<u8>,
+}
+
+/// 同一轮策略检测触发多条告警时发送的汇总payload（schema v1），
+/// 版本规则与 `AlertPayload` 相同
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestPayload {
+    pub schema_version: u32,
+    /// 固定为 "strategy_digest"
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// 汇总时间（秒）
+    pub timestamp: i64,
+    pub formatted_message: String,
+    pub alerts: Vec<DigestItem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<FeeContext>,
+}
+
+impl DigestPayload {
+    pub fn from_alerts(
+        alerts: &[&StrategyAlert],
+        formatted_message: String,
+        network: Option<FeeContext>,
+    ) -> Self {
+        Self {
+            schema_version: ALERT_SCHEMA_VERSION,
+            kind: "strategy_digest".to_string(),
+            timestamp: chrono::Local::now().timestamp(),
+            formatted_message,
+            alerts: alerts
+                .iter()
+                .map(|alert| DigestItem {
+                    mint: alert.mint.clone(),
+                    strategy_name: alert.strategy_name.clone(),
+                    message: alert.message.clone(),
+                    timestamp: alert.timestamp,
+                    severity: alert.severity,
+                })
+                .collect(),
+            network,
+        }
+    }
+}
+
+/// 去掉同一轮中重复的告警：同一mint的同一策略只保留第一条，不同策略的告警都保留
+pub fn coalesce_alerts(alerts: &[StrategyAlert]) -> Vec<&StrategyAlert> {
+    let mut seen = std::collections::HashSet::new();
+    alerts
+        .iter()
+        .filter(|alert| seen.insert((alert.mint.as_str(), alert.strategy_name.as_str())))
+        .collect()
+}
+
+/// 告警数量达到阈值时合并为汇总通知，阈值为0表示不合并
+pub fn use_digest(threshold: usize, count: usize) -> bool {
+    threshold > 0 && count >= threshold
+}
+
 #[derive(Debug, Clone)]
 pub struct NotificationManager {
     /// 通知脚本路径
     script_path: PathBuf,
-    /// 是否启用通知
-    enabled: bool,
     /// Redis客户端，用于存储通知记录
     redis_client: RedisClient,
-    /// 通知冷却时间（秒）
-    notification_cooldown_seconds: u64,
+    /// 单次脚本执行超时时间
+    script_timeout: Duration,
+    /// 脚本执行失败后的重试次数
+    script_retries: u32,
+    /// Webhook地址（未配置时不启用webhook渠道）
+    webhook_url: Option<String>,
+    /// HTTP客户端，用于webhook和telegram渠道
+    http_client: reqwest::Client,
+    /// Telegram机器人Token（未配置时不启用telegram渠道）
+    telegram_bot_token: Option<String>,
+    /// 默认渠道发送到的Telegram聊天ID
+    telegram_chat_id: Option<String>,
+    /// Telegram Bot API地址，可指向自建的Bot API服务器
+    telegram_api_url: String,
+    /// 发送失败后的重试次数，用尽后进入死信队列
+    retry_attempts: u32,
+    /// 首次重试的等待时间（秒），之后每次翻倍
+    retry_backoff_secs: u64,
+    /// 死信队列最多保留的条数，超出时删除最早的
+    dead_letter_max: usize,
+    /// 消息的数字格式和语言
+    format: NotificationFormat,
+    /// 通知方案，告警按各方案的过滤条件额外分发
+    profiles: Vec<NotificationProfile>,
+    /// 屏蔽规则，命中的告警不发送通知
+    suppression_rules: Vec<SuppressionRule>,
+}
+
+/// 是否启用通知，每次发送时读取，运行时覆盖值无需重启即可生效
+fn notifications_enabled() -> bool {
+    remote_config::var("NOTIFICATION_ENABLED")
+        .unwrap_or_else(|_| "true".to_string())
+        .parse::<bool>()
+        .unwrap_or(true)
+}
+
+/// 通知冷却时间（秒），默认为600秒（10分钟）
+fn cooldown_seconds() -> u64 {
+    remote_config::var("NOTIFICATION_COOLDOWN_SECONDS")
+        .unwrap_or_else(|_| "600".to_string())
+        .parse::<u64>()
+        .unwrap_or(600)
+}
+
+/// 一批告警达到该数量时合并为一条汇总通知，0表示不合并
+fn digest_threshold() -> usize {
+    remote_config::var("NOTIFICATION_DIGEST_THRESHOLD")
+        .unwrap_or_else(|_| "3".to_string())
+        .parse::<usize>()
+        .unwrap_or(3)
 }
 
 impl NotificationManager {
@@ -28,123 +318,882 @@ impl NotificationManager {
                 PathBuf::from("./scripts/notify.sh")
             });
 
-        let enabled = std::env::var("NOTIFICATION_ENABLED")
-            .unwrap_or_else(|_| "true".to_string())
-            .parse::<bool>()
-            .unwrap_or(true);
+        let script_timeout = Duration::from_secs(
+            std::env::var("NOTIFICATION_SCRIPT_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse::<u64>()
+                .unwrap_or(30),
+        );
+        let script_retries = std::env::var("NOTIFICATION_SCRIPT_RETRIES")
+            .unwrap_or_else(|_| "1".to_string())
+            .parse::<u32>()
+            .unwrap_or(1);
 
-        // 从环境变量读取通知冷却时间，默认为600秒（10分钟）
-        let notification_cooldown_seconds = std::env::var("NOTIFICATION_COOLDOWN_SECONDS")
-            .unwrap_or_else(|_| "600".to_string())
+        let webhook_url = std::env::var("NOTIFICATION_WEBHOOK_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty());
+        let http_client = reqwest::Client::builder().timeout(script_timeout).build()?;
+        let telegram_bot_token = std::env::var("TELEGRAM_BOT_TOKEN")
+            .ok()
+            .filter(|token| !token.trim().is_empty());
+        let telegram_chat_id = std::env::var("TELEGRAM_CHAT_ID")
+            .ok()
+            .filter(|chat_id| !chat_id.trim().is_empty());
+        let telegram_api_url = std::env::var("TELEGRAM_API_URL")
+            .unwrap_or_else(|_| "https://api.telegram.org".to_string());
+        let retry_attempts = std::env::var("NOTIFICATION_RETRY_ATTEMPTS")
+            .unwrap_or_else(|_| "3".to_string())
+            .parse::<u32>()
+            .unwrap_or(3);
+        let retry_backoff_secs = std::env::var("NOTIFICATION_RETRY_BACKOFF_SECS")
+            .unwrap_or_else(|_| "60".to_string())
             .parse::<u64>()
-            .unwrap_or(600);
+            .unwrap_or(60);
+        let dead_letter_max = std::env::var("NOTIFICATION_DEAD_LETTER_MAX")
+            .unwrap_or_else(|_| "500".to_string())
+            .parse::<usize>()
+            .unwrap_or(500);
 
         // 连接Redis
         let redis_url =
             std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379/".to_string());
         let redis_client = RedisClient::open(redis_url)?;
 
+        let profiles = notification_profiles::load_profiles()?;
+        let suppression_rules = suppression::load_rules()?;
+
         info!(
-            "📱 通知管理器初始化完成 - 脚本路径: {:?}, 启用状态: {}, 冷却时间: {}秒",
-            script_path, enabled, notification_cooldown_seconds
+            "📱 通知管理器初始化完成 - 脚本路径: {:?}, 启用状态: {}, 冷却时间: {}秒, 脚本超时: {}秒, 重试次数: {}, Webhook: {}, Telegram: {}, 通知方案: {}, 屏蔽规则: {}",
+            script_path,
+            notifications_enabled(),
+            cooldown_seconds(),
+            script_timeout.as_secs(),
+            script_retries,
+            webhook_url.is_some(),
+            telegram_bot_token.is_some() && telegram_chat_id.is_some(),
+            profiles.len(),
+            suppression_rules.len()
         );
 
         Ok(Self {
             script_path,
-            enabled,
             redis_client,
-            notification_cooldown_seconds,
+            script_timeout,
+            script_retries,
+            webhook_url,
+            http_client,
+            telegram_bot_token,
+            telegram_chat_id,
+            telegram_api_url,
+            retry_attempts,
+            retry_backoff_secs,
+            dead_letter_max,
+            format: NotificationFormat::from_env(),
+            profiles,
+            suppression_rules,
         })
     }
 
     /// 发送通知
     pub async fn send_notification(&self, alert: &StrategyAlert) -> Result<()> {
-        if !self.enabled {
+        self.send_alert(alert, true).await
+    }
+
+    /// 发送不受冷却时间限制的通知，用于只触发一次的提醒
+    pub async fn send_one_shot_notification(&self, alert: &StrategyAlert) -> Result<()> {
+        self.send_alert(alert, false).await
+    }
+
+    async fn send_alert(&self, alert: &StrategyAlert, respect_cooldown: bool) -> Result<()> {
+        if !self.is_enabled() {
             info!("📢 通知已禁用，跳过发送");
             return Ok(());
         }
 
-        if !self.script_path.exists() {
+        if !self.has_any_channel() {
             warn!("⚠️ 通知脚本不存在: {:?}", self.script_path);
             return Ok(());
         }
 
+        if self.is_suppressed(alert).await {
+            return Ok(());
+        }
+
         // 检查是否在5分钟内已经通知过该代币
-        if self.should_skip_duplicate_notification(&alert.mint).await? {
+        if respect_cooldown && self.should_skip_duplicate_notification(&alert.mint).await? {
             info!(
                 "🔄 代币 {} 在{}s内已通知过，跳过重复通知",
-                &alert.mint, self.notification_cooldown_seconds,
+                &alert.mint,
+                cooldown_seconds(),
             );
             return Ok(());
         }
 
-        // 准备通知数据
-        let notification_data = serde_json::json!({
-            "type": "strategy_alert",
-            "alert": alert,
-            "formatted_message": self.format_alert_message(alert)
+        self.deliver_alert(alert).await
+    }
+
+    /// 发送单条告警并记录通知时间，调用方已完成开关、屏蔽和冷却检查
+    async fn deliver_alert(&self, alert: &StrategyAlert) -> Result<()> {
+        let network = self.fee_context().await;
+        let format = self.current_format().await;
+        self.mirror_to_bus(&format, &[alert], network.as_ref());
+        self.fan_out(&format, &[alert], network.as_ref()).await;
+        if self.has_default_channels() {
+            let payload = AlertPayload::from_alert(
+                alert,
+                self.format_alert_message(&format, alert, network.as_ref()),
+                network,
+            );
+            self.deliver_or_queue(None, &payload, &[alert.mint.as_str()])
+                .await?;
+        }
+
+        // 通知成功后，记录到Redis中，避免重复通知
+        if let Err(e) = self.record_notification(&alert.mint).await {
+            warn!("⚠️ 记录通知状态失败: {}", e);
+        }
+
+        Ok(())
+    }
+
+    /// 发送一轮策略检测产生的告警。
+    /// 冷却期外的告警数量达到 `NOTIFICATION_DIGEST_THRESHOLD` 时合并为一条汇总通知，否则逐条发送。
+    /// 返回已发送的告警，被冷却、静音或屏蔽以及发送失败的告警不在其中
+    pub async fn send_notifications<'a>(
+        &self,
+        alerts: &'a [StrategyAlert],
+    ) -> Result<Vec<&'a StrategyAlert>> {
+        if !self.is_enabled() || alerts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut pending: Vec<&StrategyAlert> = Vec::new();
+        for alert in coalesce_alerts(alerts) {
+            if self.is_suppressed(alert).await {
+                continue;
+            }
+            if !self.should_skip_duplicate_notification(&alert.mint).await? {
+                pending.push(alert);
+            }
+        }
+
+        if pending.is_empty() {
+            return Ok(Vec::new());
+        }
+        if !self.has_any_channel() {
+            warn!("⚠️ 通知脚本不存在: {:?}", self.script_path);
+            return Ok(Vec::new());
+        }
+
+        if !use_digest(digest_threshold(), pending.len()) {
+            let mut delivered = Vec::new();
+            for alert in pending {
+                match self.deliver_alert(alert).await {
+                    Ok(()) => delivered.push(alert),
+                    Err(e) => warn!("❌ 通知发送失败 {}: {}", alert.mint, e),
+                }
+            }
+            return Ok(delivered);
+        }
+
+        info!("📦 本轮触发 {} 条告警，合并为汇总通知", pending.len());
+        let network = self.fee_context().await;
+        let format = self.current_format().await;
+        self.mirror_to_bus(&format, &pending, network.as_ref());
+        self.fan_out(&format, &pending, network.as_ref()).await;
+        if self.has_default_channels() {
+            let payload = DigestPayload::from_alerts(
+                &pending,
+                self.format_digest_message(&format, &pending, network.as_ref()),
+                network,
+            );
+            let mints: Vec<&str> = pending.iter().map(|alert| alert.mint.as_str()).collect();
+            self.deliver_or_queue(None, &payload, &mints).await?;
+        }
+
+        for alert in &pending {
+            if let Err(e) = self.record_notification(&alert.mint).await {
+                warn!("⚠️ 记录通知状态失败: {}", e);
+            }
+        }
+        Ok(pending)
+    }
+
+    /// 告警的mint是否被静音或命中屏蔽规则，命中时记录原因
+    async fn is_suppressed(&self, alert: &StrategyAlert) -> bool {
+        match notification_actions::is_muted(&alert.mint).await {
+            Ok(true) => {
+                info!(
+                    "🔇 代币 {} 已静音，跳过告警 {}",
+                    alert.mint, alert.strategy_name
+                );
+                return true;
+            }
+            Ok(false) => {}
+            Err(e) => warn!("⚠️ 读取静音状态失败 {}: {}", alert.mint, e),
+        }
+        if self.suppression_rules.is_empty() {
+            return false;
+        }
+        match suppression::check_alert(&self.suppression_rules, alert).await {
+            Some(reason) => {
+                info!(
+                    "🔇 告警已屏蔽 {} - {} ({})",
+                    alert.mint, alert.strategy_name, reason
+                );
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 是否配置了可用的默认渠道（webhook、telegram或存在的通知脚本）
+    fn has_default_channels(&self) -> bool {
+        self.webhook_url.is_some() || self.has_telegram() || self.script_path.exists()
+    }
+
+    /// 默认渠道是否配置了Telegram机器人和聊天ID
+    fn has_telegram(&self) -> bool {
+        self.telegram_bot_token.is_some() && self.telegram_chat_id.is_some()
+    }
+
+    /// 是否有任何渠道接收告警：默认渠道、通知方案或消息总线
+    fn has_any_channel(&self) -> bool {
+        self.has_default_channels() || !self.profiles.is_empty() || message_bus::is_enabled()
+    }
+
+    /// 把告警逐条转发到消息总线（MESSAGE_BUS_URL），汇总通知也按单条告警转发
+    fn mirror_to_bus(
+        &self,
+        format: &NotificationFormat,
+        alerts: &[&StrategyAlert],
+        network: Option<&FeeContext>,
+    ) {
+        if !message_bus::is_enabled() {
+            return;
+        }
+        for alert in alerts {
+            message_bus::publish_alert(&AlertPayload::from_alert(
+                alert,
+                self.format_alert_message(format, alert, network),
+                network.cloned(),
+            ));
+        }
+    }
+
+    /// 按名称查找通知方案
+    fn find_profile(&self, name: &str) -> Result<&NotificationProfile> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.name == name)
+            .ok_or_else(|| anyhow::anyhow!("通知方案 {} 未配置", name))
+    }
+
+    /// 把告警分发给匹配的通知方案，每个方案只收到符合其过滤条件的告警。
+    /// 方案收到的告警达到汇总阈值时合并为一条汇总通知
+    async fn fan_out(
+        &self,
+        format: &NotificationFormat,
+        alerts: &[&StrategyAlert],
+        network: Option<&FeeContext>,
+    ) {
+        let hour = chrono::Local::now().hour();
+        for profile in &self.profiles {
+            let matched: Vec<&StrategyAlert> = alerts
+                .iter()
+                .copied()
+                .filter(|alert| profile.matches(alert, hour))
+                .collect();
+            if matched.is_empty() {
+                continue;
+            }
+
+            if !use_digest(digest_threshold(), matched.len()) {
+                for alert in matched {
+                    let payload = AlertPayload::from_alert(
+                        alert,
+                        self.format_alert_message(format, alert, network),
+                        network.cloned(),
+                    );
+                    if let Err(e) = self
+                        .deliver_or_queue(Some(profile), &payload, &[alert.mint.as_str()])
+                        .await
+                    {
+                        warn!(
+                            "❌ 通知方案 {} 发送失败 {}: {}",
+                            profile.name, alert.mint, e
+                        );
+                    }
+                }
+                continue;
+            }
+
+            let payload = DigestPayload::from_alerts(
+                &matched,
+                self.format_digest_message(format, &matched, network),
+                network.cloned(),
+            );
+            let mints: Vec<&str> = matched.iter().map(|alert| alert.mint.as_str()).collect();
+            if let Err(e) = self.deliver_or_queue(Some(profile), &payload, &mints).await {
+                warn!("❌ 通知方案 {} 汇总通知发送失败: {}", profile.name, e);
+            }
+        }
+    }
+
+    /// 所有渠道发送同一份payload，任一渠道成功即视为已通知。
+    /// 指定通知方案时使用该方案的渠道，否则使用默认渠道
+    async fn deliver<T: Serialize>(
+        &self,
+        profile: Option<&NotificationProfile>,
+        payload: &T,
+    ) -> Result<()> {
+        let channels = match profile {
+            Some(profile) => profile.channels(),
+            None => self.configured_channels(),
+        };
+        let mut last_error = None;
+        let mut delivered = false;
+        for channel in channels {
+            match self.send_via_channel(profile, channel, payload).await {
+                Ok(()) => delivered = true,
+                Err(e) => {
+                    warn!("⚠️ 通知渠道 {} 发送失败: {}", channel, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        match (delivered, last_error) {
+            (false, Some(e)) => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// 发送payload，所有渠道都失败时放入重试队列并返回错误
+    async fn deliver_or_queue<T: Serialize>(
+        &self,
+        profile: Option<&NotificationProfile>,
+        payload: &T,
+        mints: &[&str],
+    ) -> Result<()> {
+        let Err(e) = self.deliver(profile, payload).await else {
+            return Ok(());
+        };
+        let now = chrono::Utc::now().timestamp();
+        let failed = FailedNotification {
+            id: format!("{:x}", rand::random::<u64>()),
+            payload: serde_json::to_value(payload)?,
+            mints: mints.iter().map(|mint| mint.to_string()).collect(),
+            attempts: 1,
+            last_error: e.to_string(),
+            failed_at: now,
+            next_retry_at: now + retry_delay(self.retry_backoff_secs, 1) as i64,
+            profile: profile.map(|profile| profile.name.clone()),
+        };
+        let queued = if self.retry_attempts == 0 {
+            self.move_to_dead_letter(&failed).await
+        } else {
+            self.store_failed(RETRY_QUEUE_KEY, &failed).await
+        };
+        if let Err(queue_error) = queued {
+            warn!("⚠️ 失败通知入队失败: {}", queue_error);
+        }
+        Err(e)
+    }
+
+    async fn store_failed(&self, key: &str, failed: &FailedNotification) -> Result<()> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let _: () = conn
+            .hset(key, &failed.id, serde_json::to_string(failed)?)
+            .await?;
+        Ok(())
+    }
+
+    /// 放入死信队列，超出上限时删除最早失败的通知
+    async fn move_to_dead_letter(&self, failed: &FailedNotification) -> Result<()> {
+        error!(
+            "💀 通知 {} 重试 {} 次后仍失败，已进入死信队列: {}",
+            failed.id,
+            failed.attempts.saturating_sub(1),
+            failed.last_error
+        );
+        self.store_failed(DEAD_LETTER_KEY, failed).await?;
+
+        let mut dead_letter = self.load_failed(DEAD_LETTER_KEY).await?;
+        if dead_letter.len() > self.dead_letter_max {
+            let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+            let excess = dead_letter.len() - self.dead_letter_max;
+            dead_letter.sort_by_key(|failed| failed.failed_at);
+            for expired in &dead_letter[..excess] {
+                let _: () = conn.hdel(DEAD_LETTER_KEY, &expired.id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn load_failed(&self, key: &str) -> Result<Vec<FailedNotification>> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let data: Vec<String> = conn.hvals(key).await?;
+        let mut failed: Vec<FailedNotification> = data
+            .iter()
+            .filter_map(|d| serde_json::from_str(d).ok())
+            .collect();
+        failed.sort_by_key(|f| std::cmp::Reverse(f.failed_at));
+        Ok(failed)
+    }
+
+    /// 重试队列和死信队列中的全部通知
+    pub async fn get_failed_notifications(&self) -> Result<FailedNotifications> {
+        Ok(FailedNotifications {
+            retrying: self.load_failed(RETRY_QUEUE_KEY).await?,
+            dead_letter: self.load_failed(DEAD_LETTER_KEY).await?,
+        })
+    }
+
+    /// 从队列中取出失败的通知，通过删除认领，多个进程同时处理时只有一个能取到
+    async fn claim_failed(&self, key: &str, id: &str) -> Result<Option<FailedNotification>> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let data: Option<String> = conn.hget(key, id).await?;
+        let Some(data) = data else {
+            return Ok(None);
+        };
+        let removed: usize = conn.hdel(key, id).await?;
+        if removed == 0 {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&data)?))
+    }
+
+    /// 重新发送失败的通知，使用它原来的通知方案的渠道
+    async fn deliver_failed(&self, failed: &FailedNotification) -> Result<()> {
+        let profile = match &failed.profile {
+            Some(name) => Some(self.find_profile(name)?),
+            None => None,
+        };
+        self.deliver(profile, &failed.payload).await
+    }
+
+    /// 失败通知发送成功后记录冷却
+    async fn record_delivered(&self, failed: &FailedNotification) {
+        for mint in &failed.mints {
+            if let Err(e) = self.record_notification(mint).await {
+                warn!("⚠️ 记录通知状态失败: {}", e);
+            }
+        }
+    }
+
+    /// 重试到期的通知，重试次数用尽后放入死信队列，返回发送成功的数量
+    pub async fn retry_due(&self) -> Result<usize> {
+        if !self.is_enabled() {
+            return Ok(0);
+        }
+        let now = chrono::Utc::now().timestamp();
+        let mut delivered = 0;
+        for due in self.load_failed(RETRY_QUEUE_KEY).await? {
+            if due.next_retry_at > now {
+                continue;
+            }
+            let Some(mut failed) = self.claim_failed(RETRY_QUEUE_KEY, &due.id).await? else {
+                continue;
+            };
+            match self.deliver_failed(&failed).await {
+                Ok(()) => {
+                    info!(
+                        "✅ 通知 {} 第 {} 次重试发送成功",
+                        failed.id, failed.attempts
+                    );
+                    self.record_delivered(&failed).await;
+                    delivered += 1;
+                }
+                Err(e) => {
+                    failed.attempts += 1;
+                    failed.last_error = e.to_string();
+                    failed.next_retry_at =
+                        now + retry_delay(self.retry_backoff_secs, failed.attempts) as i64;
+                    if failed.attempts > self.retry_attempts {
+                        failed.next_retry_at = now;
+                        self.move_to_dead_letter(&failed).await?;
+                    } else {
+                        self.store_failed(RETRY_QUEUE_KEY, &failed).await?;
+                    }
+                }
+            }
+        }
+        Ok(delivered)
+    }
+
+    /// 立即重新发送重试队列或死信队列中的通知，返回是否存在。
+    /// 发送失败时放回死信队列并返回错误
+    pub async fn resend_failed(&self, id: &str) -> Result<bool> {
+        let mut claimed = self.claim_failed(DEAD_LETTER_KEY, id).await?;
+        if claimed.is_none() {
+            claimed = self.claim_failed(RETRY_QUEUE_KEY, id).await?;
+        }
+        let Some(mut failed) = claimed else {
+            return Ok(false);
+        };
+
+        match self.deliver_failed(&failed).await {
+            Ok(()) => {
+                info!("✅ 通知 {} 重新发送成功", failed.id);
+                self.record_delivered(&failed).await;
+                Ok(true)
+            }
+            Err(e) => {
+                failed.attempts += 1;
+                failed.last_error = e.to_string();
+                failed.next_retry_at = chrono::Utc::now().timestamp();
+                self.store_failed(DEAD_LETTER_KEY, &failed).await?;
+                Err(e)
+            }
+        }
+    }
+
+    /// 从重试队列和死信队列中删除通知，返回是否存在
+    pub async fn discard_failed(&self, id: &str) -> Result<bool> {
+        let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
+        let retrying: usize = conn.hdel(RETRY_QUEUE_KEY, id).await?;
+        let dead: usize = conn.hdel(DEAD_LETTER_KEY, id).await?;
+        Ok(retrying + dead > 0)
+    }
+
+    /// 通过指定渠道发送payload，指定通知方案时使用该方案的脚本和webhook地址
+    async fn send_via_channel<T: Serialize>(
+        &self,
+        profile: Option<&NotificationProfile>,
+        channel: &str,
+        payload: &T,
+    ) -> Result<()> {
+        let (script_path, webhook_url, telegram_chat_id) = match profile {
+            Some(profile) => (
+                profile.script_path.as_deref(),
+                profile.webhook_url.as_deref(),
+                profile.telegram_chat_id.as_deref(),
+            ),
+            None => (
+                Some(self.script_path.as_path()),
+                self.webhook_url.as_deref(),
+                self.telegram_chat_id.as_deref(),
+            ),
+        };
+        match channel {
+            "script" => {
+                let script_path =
+                    script_path.ok_or_else(|| anyhow::anyhow!("未配置通知脚本路径"))?;
+                self.send_via_script(script_path, payload).await
+            }
+            "webhook" => {
+                let url = webhook_url.ok_or_else(|| anyhow::anyhow!("未配置Webhook地址"))?;
+                self.send_via_webhook(url, payload).await
+            }
+            "telegram" => {
+                let chat_id =
+                    telegram_chat_id.ok_or_else(|| anyhow::anyhow!("未配置Telegram聊天ID"))?;
+                self.send_via_telegram(chat_id, payload).await
+            }
+            other => Err(anyhow::anyhow!("未知通知渠道: {}", other)),
+        }
+    }
+
+    /// 通过webhook渠道发送告警（POST JSON）
+    async fn send_via_webhook<T: Serialize>(&self, url: &str, payload: &T) -> Result<()> {
+        let response = self.http_client.post(url).json(payload).send().await?;
+        if response.status().is_success() {
+            info!("✅ Webhook通知发送成功");
+            Ok(())
+        } else {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("❌ Webhook通知发送失败: {} {}", status, body);
+            Err(anyhow::anyhow!("Webhook通知发送失败: {} {}", status, body))
+        }
+    }
+
+    /// 通过telegram渠道发送告警（Bot API sendMessage），发送payload中的格式化消息。
+    /// Markdown解析失败时（如消息中有未配对的 _ 或 *）改为纯文本重发。
+    /// 开启 `TELEGRAM_ACTIONS` 时单条告警附带操作按钮
+    async fn send_via_telegram<T: Serialize>(&self, chat_id: &str, payload: &T) -> Result<()> {
+        let token = self
+            .telegram_bot_token
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("未配置Telegram机器人Token"))?;
+        let payload = serde_json::to_value(payload)?;
+        let text = payload
+            .get("formatted_message")
+            .and_then(|message| message.as_str())
+            .unwrap_or_default();
+        let keyboard = (notification_actions::actions_enabled()
+            && payload.get("type").and_then(|kind| kind.as_str()) == Some("strategy_alert"))
+        .then(|| payload.get("mint").and_then(|mint| mint.as_str()))
+        .flatten()
+        .map(|mint| {
+            notification_actions::alert_keyboard(
+                mint,
+                notification_actions::public_url().as_deref(),
+                self.format.language,
+            )
         });
+        let url = format!(
+            "{}/bot{}/sendMessage",
+            self.telegram_api_url.trim_end_matches('/'),
+            token
+        );
+
+        // 错误信息中去掉URL，避免Token出现在日志中
+        let send = |mut body: serde_json::Value| {
+            if let Some(keyboard) = &keyboard {
+                body["reply_markup"] = keyboard.clone();
+            }
+            let request = self.http_client.post(&url).json(&body);
+            async move {
+                request
+                    .send()
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Telegram请求失败: {}", e.without_url()))
+            }
+        };
+        let mut response = send(telegram_message(chat_id, text, true)).await?;
+        if response.status() == reqwest::StatusCode::BAD_REQUEST {
+            let body = response.text().await.unwrap_or_default();
+            if !body.contains("can't parse entities") {
+                error!("❌ Telegram通知发送失败: 400 {}", body);
+                return Err(anyhow::anyhow!("Telegram通知发送失败: 400 {}", body));
+            }
+            warn!("⚠️ Telegram消息Markdown解析失败，改为纯文本发送: {}", body);
+            response = send(telegram_message(chat_id, text, false)).await?;
+        }
+        if response.status().is_success() {
+            info!("✅ Telegram通知发送成功");
+            Ok(())
+        } else {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("❌ Telegram通知发送失败: {} {}", status, body);
+            Err(anyhow::anyhow!("Telegram通知发送失败: {} {}", status, body))
+        }
+    }
+
+    /// 通过脚本渠道发送告警
+    async fn send_via_script<T: Serialize>(&self, script_path: &Path, payload: &T) -> Result<()> {
+        if !script_path.exists() {
+            return Err(anyhow::anyhow!("通知脚本不存在: {:?}", script_path));
+        }
 
-        // 执行通知脚本
+        // 执行通知脚本，失败或超时后按配置重试
+        let payload = serde_json::to_string(payload)?;
+        let mut attempt = 0;
+        loop {
+            match self.run_script(script_path, &payload).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < self.script_retries => {
+                    attempt += 1;
+                    warn!(
+                        "⚠️ 通知脚本执行失败，{}秒后重试 ({}/{}): {}",
+                        attempt, attempt, self.script_retries, e
+                    );
+                    tokio::time::sleep(Duration::from_secs(attempt as u64)).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// 执行一次通知脚本，超时后终止脚本进程
+    async fn run_script(&self, script_path: &Path, payload: &str) -> Result<()> {
         let output = Command::new("bash")
-            .arg(&self.script_path)
-            .arg(notification_data.to_string())
+            .arg(script_path)
+            .arg(payload)
+            // 超时时output future被丢弃，子进程随之被kill
+            .kill_on_drop(true)
             .output();
 
-        match output {
-            Ok(result) => {
+        match tokio::time::timeout(self.script_timeout, output).await {
+            Ok(Ok(result)) => {
                 if result.status.success() {
                     info!("✅ 通知脚本执行成功");
                     if !result.stdout.is_empty() {
                         info!("📤 脚本输出: {}", String::from_utf8_lossy(&result.stdout));
                     }
-
-                    // 通知成功后，记录到Redis中，避免重复通知
-                    if let Err(e) = self.record_notification(&alert.mint).await {
-                        warn!("⚠️ 记录通知状态失败: {}", e);
-                    }
+                    Ok(())
                 } else {
                     let stderr = String::from_utf8_lossy(&result.stderr);
                     error!("❌ 通知脚本执行失败: {}", stderr);
-                    return Err(anyhow::anyhow!("通知脚本执行失败: {}", stderr));
+                    Err(anyhow::anyhow!("通知脚本执行失败: {}", stderr))
                 }
             }
-            Err(e) => {
+            Ok(Err(e)) => {
                 error!("❌ 执行通知脚本时出错: {}", e);
-                return Err(anyhow::anyhow!("执行通知脚本时出错: {}", e));
+                Err(anyhow::anyhow!("执行通知脚本时出错: {}", e))
+            }
+            Err(_) => {
+                error!(
+                    "❌ 通知脚本执行超时 ({}秒)，已终止",
+                    self.script_timeout.as_secs()
+                );
+                Err(anyhow::anyhow!(
+                    "通知脚本执行超时 ({}秒)",
+                    self.script_timeout.as_secs()
+                ))
             }
         }
+    }
 
-        Ok(())
+    /// 已配置的通知渠道名称
+    /// 配置了webhook或telegram但脚本不存在时，只使用这些渠道
+    pub fn configured_channels(&self) -> Vec<&'static str> {
+        let mut channels = Vec::new();
+        if (self.webhook_url.is_none() && !self.has_telegram()) || self.script_path.exists() {
+            channels.push("script");
+        }
+        if self.webhook_url.is_some() {
+            channels.push("webhook");
+        }
+        if self.has_telegram() {
+            channels.push("telegram");
+        }
+        channels
+    }
+
+    /// 通过每个已配置渠道发送一条测试告警，并返回各渠道结果，
+    /// 通知方案的渠道名称为 "方案名/渠道"。测试告警不受启用状态和冷却时间影响
+    pub async fn test_channels(&self) -> Vec<ChannelTestResult> {
+        let alert = StrategyAlert {
+            mint: "TEST".to_string(),
+            strategy_name: "通知渠道测试".to_string(),
+            message: localized!(
+                self.format.language,
+                "这是一条测试告警，用于检查通知渠道配置",
+                "This is a test alert to check the notification channels"
+            ),
+            timestamp: chrono::Local::now().timestamp(),
+            severity: None,
+            params: None,
+            klines: Vec::new(),
+        };
+
+        let format = self.current_format().await;
+        let payload = AlertPayload::from_alert(
+            &alert,
+            self.format_alert_message(&format, &alert, None),
+            None,
+        );
+        let mut channels: Vec<(Option<&NotificationProfile>, &str)> = self
+            .configured_channels()
+            .into_iter()
+            .map(|channel| (None, channel))
+            .collect();
+        for profile in &self.profiles {
+            for channel in profile.channels() {
+                channels.push((Some(profile), channel));
+            }
+        }
+        let mut results = Vec::new();
+        for (profile, channel) in channels {
+            let started = Instant::now();
+            let result = self.send_via_channel(profile, channel, &payload).await;
+            results.push(ChannelTestResult {
+                channel: match profile {
+                    Some(profile) => format!("{}/{}", profile.name, channel),
+                    None => channel.to_string(),
+                },
+                success: result.is_ok(),
+                error: result.err().map(|e| e.to_string()),
+                duration_ms: started.elapsed().as_millis() as u64,
+            });
+        }
+        results
+    }
+
+    /// 获取最近一次优先费采样，失败时不影响通知发送
+    async fn fee_context(&self) -> Option<FeeContext> {
+        priority_fee::get_fee_context().await.unwrap_or_else(|e| {
+            warn!("⚠️ 获取优先费信息失败: {}", e);
+            None
+        })
+    }
+
+    /// 本次发送使用的格式：以USD显示时读取当前的SOL/USD汇率，没有汇率时按SOL显示
+    async fn current_format(&self) -> NotificationFormat {
+        self.format.with_current_rate().await
     }
 
     /// 格式化告警消息
-    fn format_alert_message(&self, alert: &StrategyAlert) -> String {
-        format!(
-            "## 🚀连续上涨📈
-- 🚨 策略告警
-- 📍 Token: {}
-- 🔍 策略: {}
-- 📊 详情: {}
-- ⏰ 时间: {}
-- 📈 K线数量: {}
-- 🔗 [GMGN](https://gmgn.ai/sol/token/{})",
+    fn format_alert_message(
+        &self,
+        format: &NotificationFormat,
+        alert: &StrategyAlert,
+        network: Option<&FeeContext>,
+    ) -> String {
+        let labels = format.labels();
+        let mut message = format!(
+            "## {}
+- 🚨 {}
+- 📍 {}: {}
+- 🔍 {}: {}
+- 📊 {}: {}
+- ⏰ {}: {}
+- 📈 {}: {}",
+            labels.alert_title,
+            labels.alert_kind,
+            labels.token,
             alert.mint,
-            alert.strategy_name,
+            labels.strategy,
+            format.language.strategy_label(&alert.strategy_name),
+            labels.details,
             alert.message,
-            chrono::DateTime::from_timestamp(alert.timestamp, 0)
-                .map(|dt| chrono::DateTime::<chrono::Local>::from(dt)
-                    .format("%Y-%m-%d %H:%M:%S")
-                    .to_string())
-                .unwrap_or_else(|| "未知时间".to_string()),
-            alert.klines.len(),
-            alert.mint
+            labels.time,
+            format.time(alert.timestamp),
+            labels.kline_count,
+            alert.klines.len()
+        );
+        if let Some(severity) = alert.severity {
+            message.push_str(&format!("\n- 🔥 {}: {}/100", labels.severity, severity));
+        }
+        if let Some(links_line) = format.links_line(&alert.mint) {
+            message.push_str(&format!("\n{}", links_line));
+        }
+        match network {
+            Some(network) => format!("{}\n{}", message, format.fee_line(network)),
+            None => message,
+        }
+    }
+
+    /// 格式化汇总消息
+    fn format_digest_message(
+        &self,
+        format: &NotificationFormat,
+        alerts: &[&StrategyAlert],
+        network: Option<&FeeContext>,
+    ) -> String {
+        let lines: Vec<String> = alerts
+            .iter()
+            .map(|alert| {
+                format!(
+                    "- {} {}: {}",
+                    format.mint_link(&alert.mint),
+                    format.language.strategy_label(&alert.strategy_name),
+                    alert.message
+                )
+            })
+            .collect();
+        let fee_line = network
+            .map(|network| format!("{}\n", format.fee_line(network)))
+            .unwrap_or_default();
+        let labels = format.labels();
+        format!(
+            "## {}\n- ⏰ {}: {}\n- 🚨 {}: {}\n{}{}",
+            labels.digest_title,
+            labels.time,
+            format.time(chrono::Local::now().timestamp()),
+            labels.digest_count,
+            alerts.len(),
+            fee_line,
+            lines.join("\n")
         )
     }
 
     /// 检查通知脚本是否可执行
     pub fn check_script_availability(&self) -> bool {
-        if !self.enabled {
+        if !self.is_enabled() {
             return true; // 如果禁用了通知，则认为"可用"
         }
 
@@ -158,7 +1207,7 @@ impl NotificationManager {
 
     /// 是否启用通知
     pub fn is_enabled(&self) -> bool {
-        self.enabled
+        notifications_enabled()
     }
 
     /// 检查是否应该跳过重复通知（5分钟内已通知过）
@@ -176,17 +1225,152 @@ impl NotificationManager {
         let mut conn = self.redis_client.get_multiplexed_async_connection().await?;
         let key = format!("notification:{}:recent", mint);
         let timestamp = chrono::Local::now().timestamp();
+        let cooldown = cooldown_seconds();
 
         // 设置键值，使用可配置的冷却时间
-        let _: () = conn
-            .set_ex(&key, timestamp, self.notification_cooldown_seconds)
-            .await?;
+        let _: () = conn.set_ex(&key, timestamp, cooldown).await?;
 
-        info!(
-            "📝 记录通知状态: {} (冷却时间: {}秒)",
-            mint, self.notification_cooldown_seconds
-        );
+        info!("📝 记录通知状态: {} (冷却时间: {}秒)", mint, cooldown);
 
         Ok(())
     }
 }
+
+/// 在后台定期重试发送失败的通知，多个服务同时运行时通过认领避免重复发送
+pub fn start_retry_worker() -> Result<()> {
+    let check_interval = Duration::from_secs(
+        std::env::var("NOTIFICATION_RETRY_CHECK_SECS")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()
+            .unwrap_or(30),
+    );
+    let manager = NotificationManager::new()?;
+    supervisor::spawn_supervised("notification_retry", async move {
+        loop {
+            tokio::time::sleep(check_interval).await;
+            match manager.retry_due().await {
+                Ok(0) => {}
+                Ok(delivered) => info!("📨 重试发送成功 {} 条通知", delivered),
+                Err(e) => warn!("⚠️ 重试失败通知时出错: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kline::KLineData;
+    use crate::test_harness;
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(60, 1), 60);
+        assert_eq!(retry_delay(60, 2), 120);
+        assert_eq!(retry_delay(60, 4), 480);
+        assert_eq!(retry_delay(60, 40), 60 * 1024);
+    }
+
+    #[test]
+    fn test_alert_payload_schema_v1() {
+        let alert = StrategyAlert {
+            mint: "Mint111".to_string(),
+            strategy_name: "连续上涨".to_string(),
+            message: "test".to_string(),
+            timestamp: 1_700_000_000,
+            severity: None,
+            params: None,
+            klines: vec![KLineData {
+                open: "1".to_string(),
+                low: "1".to_string(),
+                volume_sol: "3".to_string(),
+                volume_token: "4".to_string(),
+                net_flow_sol: "5".to_string(),
+                last_update: 1_700_000_000,
+                ..test_harness::candle(1_699_999_980, "2")
+            }],
+        };
+        let value = serde_json::to_value(AlertPayload::from_alert(
+            &alert,
+            "formatted".to_string(),
+            None,
+        ))
+        .unwrap();
+
+        assert_eq!(value["schema_version"], ALERT_SCHEMA_VERSION);
+        assert_eq!(value["type"], "strategy_alert");
+        assert_eq!(value["mint"], "Mint111");
+        assert_eq!(value["formatted_message"], "formatted");
+        assert_eq!(value["klines"][0]["close"], "2");
+        // 旧版脚本读取的字段
+        assert_eq!(value["alert"]["mint"], "Mint111");
+        assert_eq!(value["alert"]["strategy_name"], "连续上涨");
+        assert_eq!(value["alert"]["klines"][0]["close"], "2");
+    }
+
+    fn alert(mint: &str, strategy_name: &str) -> StrategyAlert {
+        StrategyAlert {
+            mint: mint.to_string(),
+            strategy_name: strategy_name.to_string(),
+            message: format!("{} {}", mint, strategy_name),
+            timestamp: 1_700_000_000,
+            severity: Some(3),
+            params: None,
+            klines: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_coalesce_alerts() {
+        let alerts = vec![
+            alert("A", "连续上涨"),
+            alert("A", "卖压衰竭反转"),
+            alert("B", "连续上涨"),
+            alert("A", "连续上涨"),
+        ];
+        let coalesced = coalesce_alerts(&alerts);
+        let keys: Vec<(&str, &str)> = coalesced
+            .iter()
+            .map(|a| (a.mint.as_str(), a.strategy_name.as_str()))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![("A", "连续上涨"), ("A", "卖压衰竭反转"), ("B", "连续上涨")]
+        );
+
+        assert!(!use_digest(0, 100));
+        assert!(!use_digest(3, 2));
+        assert!(use_digest(3, 3));
+
+        let payload = DigestPayload::from_alerts(&coalesced, "digest".to_string(), None);
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(value["type"], "strategy_digest");
+        assert_eq!(value["alerts"].as_array().unwrap().len(), 3);
+        assert_eq!(value["alerts"][1]["strategy_name"], "卖压衰竭反转");
+        assert_eq!(value["alerts"][1]["severity"], 3);
+    }
+
+    #[test]
+    fn test_telegram_message() {
+        let message = "## 策略告警\n- 🚨 连续上涨\n- 🔗 [GMGN](https://gmgn.ai/sol/token/M)";
+        assert_eq!(
+            telegram_markdown(message),
+            "*策略告警*\n• 🚨 连续上涨\n• 🔗 [GMGN](https://gmgn.ai/sol/token/M)"
+        );
+
+        let body = telegram_message("-100123", message, true);
+        assert_eq!(body["chat_id"], "-100123");
+        assert_eq!(body["parse_mode"], "Markdown");
+        let plain = telegram_message("-100123", message, false);
+        assert_eq!(plain["text"], message);
+        assert!(plain.get("parse_mode").is_none());
+
+        let long = "x".repeat(5000);
+        let body = telegram_message("1", &long, false);
+        assert_eq!(
+            body["text"].as_str().unwrap().len(),
+            TELEGRAM_MAX_MESSAGE_CHARS
+        );
+    }
+}
@@ -0,0 +1,326 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::localized;
+use crate::notification_format::{Language, chart_link};
+use crate::notification_profiles;
+use crate::redis_helper;
+use crate::supervisor;
+use crate::watchlist;
+
+/// 告警按钮"静音1小时"的静音时长（秒）
+pub const MUTE_SECS: u64 = 3600;
+
+/// getUpdates长轮询的等待时间（秒）
+const POLL_TIMEOUT_SECS: u64 = 30;
+
+/// Telegram告警按钮触发的操作，回调数据为 "操作:mint"
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertAction {
+    /// 静音mint的告警 `MUTE_SECS` 秒
+    Mute(String),
+    /// 把mint加入观察列表
+    Watch(String),
+}
+
+impl AlertAction {
+    /// 解析按钮的回调数据
+    pub fn parse(data: &str) -> Option<Self> {
+        let (action, mint) = data.split_once(':')?;
+        if mint.is_empty() {
+            return None;
+        }
+        match action {
+            "mute" => Some(AlertAction::Mute(mint.to_string())),
+            "watch" => Some(AlertAction::Watch(mint.to_string())),
+            _ => None,
+        }
+    }
+
+    /// 按钮的回调数据，mint地址最长44字符，不超过Telegram的64字节限制
+    pub fn callback_data(&self) -> String {
+        match self {
+            AlertAction::Mute(mint) => format!("mute:{}", mint),
+            AlertAction::Watch(mint) => format!("watch:{}", mint),
+        }
+    }
+
+    /// 执行操作，返回回复给点击者的提示
+    pub async fn run(&self, language: Language) -> Result<String> {
+        match self {
+            AlertAction::Mute(mint) => {
+                mute_mint(mint, MUTE_SECS).await?;
+                Ok(localized!(
+                    language,
+                    "已静音 {} 的告警 {} 分钟",
+                    "Muted alerts for {} for {} minutes",
+                    mint,
+                    MUTE_SECS / 60
+                ))
+            }
+            AlertAction::Watch(mint) => Ok(if watchlist::add_mint(mint).await? {
+                localized!(
+                    language,
+                    "已将 {} 加入观察列表",
+                    "Added {} to the watchlist",
+                    mint
+                )
+            } else {
+                localized!(
+                    language,
+                    "{} 已在观察列表中",
+                    "{} is already on the watchlist",
+                    mint
+                )
+            }),
+        }
+    }
+}
+
+/// 生成mint告警静音状态的Redis键
+fn get_mute_key(mint: &str) -> String {
+    format!("notification:{}:muted", mint)
+}
+
+/// 在 `secs` 秒内静音mint的告警，到期后自动恢复
+pub async fn mute_mint(mint: &str, secs: u64) -> Result<()> {
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = con
+        .set_ex(get_mute_key(mint), chrono::Utc::now().timestamp(), secs)
+        .await?;
+    Ok(())
+}
+
+/// mint的告警是否处于静音
+pub async fn is_muted(mint: &str) -> Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    Ok(con.exists(get_mute_key(mint)).await?)
+}
+
+/// 是否在Telegram告警上附带操作按钮（TELEGRAM_ACTIONS）
+pub fn actions_enabled() -> bool {
+    std::env::var("TELEGRAM_ACTIONS")
+        .unwrap_or_else(|_| "false".to_string())
+        .parse()
+        .unwrap_or(false)
+}
+
+/// 单条告警消息的内联按钮：静音1小时、加入观察列表，
+/// `public_url` 为Web服务的公开地址，设置时再附带打开图表的链接按钮
+pub fn alert_keyboard(mint: &str, public_url: Option<&str>, language: Language) -> Value {
+    let (mute, watch, chart) = match language {
+        Language::Zh => ("🔇 静音1小时", "⭐ 加入观察列表", "📈 打开图表"),
+        Language::En => ("🔇 Mute 1h", "⭐ Watch", "📈 Open chart"),
+    };
+    let mut rows = vec![json!([
+        {
+            "text": mute,
+            "callback_data": AlertAction::Mute(mint.to_string()).callback_data(),
+        },
+        {
+            "text": watch,
+            "callback_data": AlertAction::Watch(mint.to_string()).callback_data(),
+        },
+    ])];
+    if let Some(public_url) = public_url {
+        rows.push(json!([
+            { "text": chart, "url": chart_link(public_url).url(mint) }
+        ]));
+    }
+    json!({ "inline_keyboard": rows })
+}
+
+/// Web服务的公开地址（WEB_PUBLIC_URL）
+pub fn public_url() -> Option<String> {
+    std::env::var("WEB_PUBLIC_URL")
+        .ok()
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+}
+
+/// 开启告警按钮且配置了机器人时，在后台长轮询机器人的按钮回调并执行操作。
+/// Telegram只允许一个进程拉取更新，由Web服务运行
+pub fn spawn_bot_loop() -> Option<JoinHandle<()>> {
+    if !actions_enabled() {
+        return None;
+    }
+    let Some(token) = std::env::var("TELEGRAM_BOT_TOKEN")
+        .ok()
+        .filter(|token| !token.trim().is_empty())
+    else {
+        warn!("⚠️ 已开启TELEGRAM_ACTIONS，但未设置TELEGRAM_BOT_TOKEN");
+        return None;
+    };
+    let api_url = std::env::var("TELEGRAM_API_URL")
+        .unwrap_or_else(|_| "https://api.telegram.org".to_string());
+    let chats = allowed_chats();
+
+    Some(supervisor::spawn_supervised(
+        "telegram_actions",
+        async move {
+            let client = match reqwest::Client::builder()
+                .timeout(Duration::from_secs(POLL_TIMEOUT_SECS + 10))
+                .build()
+            {
+                Ok(client) => client,
+                Err(e) => {
+                    warn!("⚠️ 创建Telegram客户端失败: {}", e);
+                    return;
+                }
+            };
+            let base = format!("{}/bot{}", api_url.trim_end_matches('/'), token);
+            info!(
+                "🤖 Telegram告警按钮已开启，接受 {} 个聊天的操作",
+                chats.len()
+            );
+            let mut offset = 0;
+            loop {
+                match poll_updates(&client, &base, offset, &chats).await {
+                    Ok(next) => offset = next,
+                    Err(e) => {
+                        warn!("⚠️ 拉取Telegram更新失败: {}", e);
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                }
+            }
+        },
+    ))
+}
+
+/// 接受按钮操作的聊天：默认渠道和通知方案的Telegram聊天
+fn allowed_chats() -> HashSet<String> {
+    let mut chats: HashSet<String> = std::env::var("TELEGRAM_CHAT_ID")
+        .ok()
+        .filter(|chat_id| !chat_id.trim().is_empty())
+        .into_iter()
+        .collect();
+    match notification_profiles::load_profiles() {
+        Ok(profiles) => chats.extend(profiles.into_iter().filter_map(|p| p.telegram_chat_id)),
+        Err(e) => warn!("⚠️ 读取通知方案失败，只接受默认聊天的操作: {}", e),
+    }
+    chats
+}
+
+/// 拉取一批按钮回调并逐个处理，返回下一次拉取的offset
+async fn poll_updates(
+    client: &reqwest::Client,
+    base: &str,
+    offset: i64,
+    chats: &HashSet<String>,
+) -> Result<i64> {
+    // 错误信息中去掉URL，避免Token出现在日志中
+    let response: Value = client
+        .post(format!("{}/getUpdates", base))
+        .json(&json!({
+            "offset": offset,
+            "timeout": POLL_TIMEOUT_SECS,
+            "allowed_updates": ["callback_query"],
+        }))
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("Telegram请求失败: {}", e.without_url()))?
+        .json()
+        .await
+        .map_err(|e| anyhow::anyhow!("Telegram响应解析失败: {}", e.without_url()))?;
+    let Some(updates) = response.get("result").and_then(Value::as_array) else {
+        anyhow::bail!("Telegram返回错误: {}", response);
+    };
+
+    let mut next = offset;
+    for update in updates {
+        if let Some(id) = update.get("update_id").and_then(Value::as_i64) {
+            next = next.max(id + 1);
+        }
+        if let Some(callback) = update.get("callback_query") {
+            let reply = handle_callback(callback, chats).await;
+            if let Some(id) = callback.get("id").and_then(Value::as_str)
+                && let Err(e) = client
+                    .post(format!("{}/answerCallbackQuery", base))
+                    .json(&json!({ "callback_query_id": id, "text": reply }))
+                    .send()
+                    .await
+            {
+                warn!("⚠️ 回复Telegram按钮失败: {}", e.without_url());
+            }
+        }
+    }
+    Ok(next)
+}
+
+/// 处理一次按钮点击，返回回复给点击者的提示。只接受告警发送到的聊天中的点击
+async fn handle_callback(callback: &Value, chats: &HashSet<String>) -> String {
+    let chat_id = callback
+        .pointer("/message/chat/id")
+        .map(|id| match id {
+            Value::String(id) => id.clone(),
+            id => id.to_string(),
+        })
+        .unwrap_or_default();
+    let language = Language::from_env();
+    if !chats.contains(&chat_id) {
+        warn!("⚠️ 忽略来自未配置聊天 {} 的按钮操作", chat_id);
+        return localized!(
+            language,
+            "该聊天不接受告警操作",
+            "This chat does not accept alert actions"
+        );
+    }
+    let Some(action) = callback
+        .get("data")
+        .and_then(Value::as_str)
+        .and_then(AlertAction::parse)
+    else {
+        return localized!(language, "未知操作", "Unknown action");
+    };
+    match action.run(language).await {
+        Ok(reply) => {
+            info!("🤖 Telegram按钮操作: {}", reply);
+            reply
+        }
+        Err(e) => {
+            warn!("⚠️ 执行Telegram按钮操作失败 {:?}: {}", action, e);
+            localized!(
+                language,
+                "操作失败，请稍后重试",
+                "Action failed, try again later"
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alert_actions() {
+        let mint = "So11111111111111111111111111111111111111112";
+        let mute = AlertAction::Mute(mint.to_string());
+        assert!(mute.callback_data().len() <= 64);
+        assert_eq!(AlertAction::parse(&mute.callback_data()), Some(mute));
+        assert_eq!(
+            AlertAction::parse("watch:M"),
+            Some(AlertAction::Watch("M".to_string()))
+        );
+        assert_eq!(AlertAction::parse("watch:"), None);
+        assert_eq!(AlertAction::parse("delete:M"), None);
+
+        let keyboard = alert_keyboard("M", None, Language::Zh);
+        assert_eq!(keyboard["inline_keyboard"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            keyboard["inline_keyboard"][0][1]["callback_data"],
+            "watch:M"
+        );
+        let keyboard = alert_keyboard("M", Some("https://monitor.example.com/"), Language::En);
+        assert_eq!(keyboard["inline_keyboard"][0][0]["text"], "🔇 Mute 1h");
+        assert_eq!(
+            keyboard["inline_keyboard"][1][0]["url"],
+            "https://monitor.example.com/token/M"
+        );
+    }
+}
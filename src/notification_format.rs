@@ -0,0 +1,461 @@
+use rust_decimal::Decimal;
+use tracing::warn;
+
+use crate::price_feed;
+use crate::priority_fee::{Congestion, FeeContext};
+
+/// 通知模板的语言
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    Zh,
+    En,
+}
+
+// English display names of the built-in strategies and system alerts. Strategy
+// names stay Chinese as identifiers (cooldowns, stats, suppression, profiles),
+// only the notification text is translated
+const EN_STRATEGY_NAMES: &[(&str, &str)] = &[
+    ("连续递增上涨模式", "Consecutive rising candles"),
+    ("持有人增长", "Holder growth"),
+    ("卖压衰竭反转", "Sell exhaustion reversal"),
+    ("自定义规则", "Custom rule"),
+    ("告警失效", "Alert invalidated"),
+    ("价格提醒", "Price alert"),
+    ("跟踪钱包买入", "Tracked wallet buy"),
+    ("价格急变", "Sudden price move"),
+    ("交易异常", "Anomalous trade"),
+    ("联合曲线阶段", "Bonding curve phase"),
+    ("元数据变更", "Metadata changed"),
+    ("新币首批买家分析", "Launch first buyers"),
+    ("解码布局异常", "Decode layout drift"),
+    ("服务心跳超时", "Service heartbeat timeout"),
+    ("后台任务崩溃", "Background task panic"),
+    ("数据流中断", "Feed gap"),
+    ("市场静默", "Market silence"),
+    ("通知渠道测试", "Notification channel test"),
+];
+
+impl Language {
+    /// 从NOTIFICATION_LANGUAGE读取通知语言：`en` 为英文，其他为中文
+    pub fn from_env() -> Self {
+        match std::env::var("NOTIFICATION_LANGUAGE")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "en" => Language::En,
+            _ => Language::Zh,
+        }
+    }
+
+    /// 策略在通知中显示的名称，自定义策略没有译名时显示原名
+    pub fn strategy_label<'a>(&self, name: &'a str) -> &'a str {
+        match self {
+            Language::Zh => name,
+            Language::En => EN_STRATEGY_NAMES
+                .iter()
+                .find(|(zh, _)| *zh == name)
+                .map_or(name, |(_, en)| en),
+        }
+    }
+}
+
+/// 按通知语言选择中文或英文模板格式化文字，两个模板使用相同的参数：
+/// `localized!(language, "价格 {}", "price {}", price)`
+#[macro_export]
+macro_rules! localized {
+    ($language:expr, $zh:literal, $en:literal $(, $arg:expr)* $(,)?) => {
+        match $language {
+            $crate::notification_format::Language::Zh => format!($zh $(, $arg)*),
+            $crate::notification_format::Language::En => format!($en $(, $arg)*),
+        }
+    };
+}
+
+/// 告警消息中的链接，模板中的 `{mint}` 替换为代币地址
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertLink {
+    pub name: String,
+    pub template: String,
+}
+
+impl AlertLink {
+    pub fn url(&self, mint: &str) -> String {
+        self.template.replace("{mint}", mint)
+    }
+}
+
+// Link targets that can be enabled by name
+const BUILTIN_LINKS: &[(&str, &str)] = &[
+    ("GMGN", "https://gmgn.ai/sol/token/{mint}"),
+    ("DexScreener", "https://dexscreener.com/solana/{mint}"),
+    ("Solscan", "https://solscan.io/token/{mint}"),
+    ("Photon", "https://photon-sol.tinyastro.io/en/lp/{mint}"),
+    (
+        "BullX",
+        "https://neo.bullx.io/terminal?chainId=1399811149&address={mint}",
+    ),
+];
+
+/// 解析链接列表：逗号分隔，每项为内置名称（如 `gmgn`）或 `名称=URL模板`，
+/// 无法识别的项被忽略
+pub fn parse_links(spec: &str) -> Vec<AlertLink> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .filter_map(|item| match item.split_once('=') {
+            Some((name, template)) if template.contains("{mint}") => Some(AlertLink {
+                name: name.trim().to_string(),
+                template: template.trim().to_string(),
+            }),
+            Some(_) => None,
+            None => BUILTIN_LINKS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(item))
+                .map(|(name, template)| AlertLink {
+                    name: name.to_string(),
+                    template: template.to_string(),
+                }),
+        })
+        .collect()
+}
+
+/// 本服务图表页的链接，`base_url` 为Web服务的公开地址（`WEB_PUBLIC_URL`）
+pub fn chart_link(base_url: &str) -> AlertLink {
+    AlertLink {
+        name: "Chart".to_string(),
+        template: format!("{}/token/{{mint}}", base_url.trim_end_matches('/')),
+    }
+}
+
+/// 通知中金额的显示币种
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Currency {
+    Sol,
+    /// 按发送时的SOL/USD汇率换算
+    Usd(Decimal),
+}
+
+/// 通知模板中的固定文字
+pub struct Labels {
+    pub alert_title: &'static str,
+    pub alert_kind: &'static str,
+    pub token: &'static str,
+    pub strategy: &'static str,
+    pub details: &'static str,
+    pub time: &'static str,
+    pub kline_count: &'static str,
+    pub severity: &'static str,
+    pub unknown_time: &'static str,
+    pub digest_title: &'static str,
+    pub digest_count: &'static str,
+    pub suggested_fee: &'static str,
+    pub congestion: &'static str,
+    pub congestion_levels: [&'static str; 3],
+}
+
+const ZH_LABELS: Labels = Labels {
+    alert_title: "🚀连续上涨📈",
+    alert_kind: "策略告警",
+    token: "Token",
+    strategy: "策略",
+    details: "详情",
+    time: "时间",
+    kline_count: "K线数量",
+    severity: "严重度",
+    unknown_time: "未知时间",
+    digest_title: "📦 策略告警汇总",
+    digest_count: "触发数量",
+    suggested_fee: "建议优先费",
+    congestion: "拥堵",
+    congestion_levels: ["低", "中", "高"],
+};
+
+const EN_LABELS: Labels = Labels {
+    alert_title: "🚀 Strategy alert 📈",
+    alert_kind: "Strategy alert",
+    token: "Token",
+    strategy: "Strategy",
+    details: "Details",
+    time: "Time",
+    kline_count: "Candles",
+    severity: "Severity",
+    unknown_time: "unknown",
+    digest_title: "📦 Strategy alert digest",
+    digest_count: "Alerts",
+    suggested_fee: "Suggested priority fee",
+    congestion: "congestion",
+    congestion_levels: ["low", "medium", "high"],
+};
+
+/// 通知消息的数字格式和语言
+#[derive(Debug, Clone)]
+pub struct NotificationFormat {
+    pub language: Language,
+    pub currency: Currency,
+    /// 是否以USD显示（NOTIFICATION_CURRENCY=usd），汇率在发送时通过 `with_usd_rate` 设置
+    pub prefer_usd: bool,
+    /// 价格的小数位数
+    pub price_decimals: u32,
+    /// 成交量、净流入和市值的小数位数
+    pub amount_decimals: u32,
+    /// 千位分隔符，None表示不分隔
+    pub thousands_separator: Option<char>,
+    /// 告警消息中的链接，汇总消息使用第一个
+    pub links: Vec<AlertLink>,
+}
+
+impl Default for NotificationFormat {
+    fn default() -> Self {
+        Self {
+            language: Language::Zh,
+            currency: Currency::Sol,
+            prefer_usd: false,
+            price_decimals: 10,
+            amount_decimals: 2,
+            thousands_separator: Some(','),
+            links: parse_links("gmgn"),
+        }
+    }
+}
+
+impl NotificationFormat {
+    /// 从环境变量读取格式配置。金额先按SOL显示，USD显示在发送时设置汇率
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let prefer_usd = std::env::var("NOTIFICATION_CURRENCY")
+            .unwrap_or_default()
+            .eq_ignore_ascii_case("usd");
+        let thousands_separator = match std::env::var("NOTIFICATION_THOUSANDS_SEPARATOR") {
+            Ok(value) if value == "none" || value.is_empty() => None,
+            Ok(value) => value.chars().next(),
+            Err(_) => default.thousands_separator,
+        };
+        let mut links = std::env::var("NOTIFICATION_LINKS")
+            .map(|spec| parse_links(&spec))
+            .unwrap_or(default.links);
+        // 配置了Web服务的公开地址时，图表链接排在最前，汇总消息也链接到图表
+        if let Some(base_url) = std::env::var("WEB_PUBLIC_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+            && !links.is_empty()
+        {
+            links.insert(0, chart_link(base_url.trim()));
+        }
+        Self {
+            language: Language::from_env(),
+            currency: Currency::Sol,
+            prefer_usd,
+            price_decimals: std::env::var("NOTIFICATION_PRICE_DECIMALS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(default.price_decimals),
+            amount_decimals: std::env::var("NOTIFICATION_AMOUNT_DECIMALS")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse()
+                .unwrap_or(default.amount_decimals),
+            thousands_separator,
+            links,
+        }
+    }
+
+    /// 当前语言的模板文字
+    pub fn labels(&self) -> &'static Labels {
+        match self.language {
+            Language::Zh => &ZH_LABELS,
+            Language::En => &EN_LABELS,
+        }
+    }
+
+    /// 按配置的小数位数和千位分隔符格式化数字
+    pub fn number(&self, value: Decimal, decimals: u32) -> String {
+        let formatted = format!("{:.*}", decimals as usize, value.round_dp(decimals));
+        let (sign, unsigned) = match formatted.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", formatted.as_str()),
+        };
+        let (integer, fraction) = match unsigned.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (unsigned, None),
+        };
+
+        let mut grouped = String::new();
+        for (i, digit) in integer.chars().enumerate() {
+            if let Some(separator) = self.thousands_separator
+                && i > 0
+                && (integer.len() - i) % 3 == 0
+            {
+                grouped.push(separator);
+            }
+            grouped.push(digit);
+        }
+        match fraction {
+            Some(fraction) => format!("{}{}.{}", sign, grouped, fraction),
+            None => format!("{}{}", sign, grouped),
+        }
+    }
+
+    /// 当前的通知格式，用于生成告警消息中的价格和金额
+    pub async fn current() -> Self {
+        Self::from_env().with_current_rate().await
+    }
+
+    /// 以USD显示时读取价格源的SOL/USD汇率，没有汇率时按SOL显示
+    pub async fn with_current_rate(&self) -> Self {
+        if !self.prefer_usd {
+            return self.clone();
+        }
+        let rate = price_feed::sol_usd_price().await;
+        if rate.is_none() {
+            warn!("⚠️ 没有可用的SOL/USD汇率，通知金额按SOL显示");
+        }
+        self.with_usd_rate(rate)
+    }
+
+    /// 以USD显示时使用该SOL/USD汇率，没有有效汇率时按SOL显示
+    pub fn with_usd_rate(&self, rate: Option<Decimal>) -> Self {
+        let currency = match rate.filter(|rate| *rate > Decimal::ZERO) {
+            Some(rate) if self.prefer_usd => Currency::Usd(rate),
+            _ => Currency::Sol,
+        };
+        Self {
+            currency,
+            ..self.clone()
+        }
+    }
+
+    fn money(&self, sol: Decimal, decimals: u32) -> String {
+        match self.currency {
+            Currency::Sol => format!("{} SOL", self.number(sol, decimals)),
+            Currency::Usd(rate) => format!("${}", self.number(sol * rate, decimals)),
+        }
+    }
+
+    /// 以SOL计价的价格
+    pub fn price(&self, sol: Decimal) -> String {
+        self.money(sol, self.price_decimals)
+    }
+
+    /// 以SOL计的成交量、净流入或市值
+    pub fn amount(&self, sol: Decimal) -> String {
+        self.money(sol, self.amount_decimals)
+    }
+
+    /// 告警消息的链接行，未配置链接时为None
+    pub fn links_line(&self, mint: &str) -> Option<String> {
+        if self.links.is_empty() {
+            return None;
+        }
+        let links: Vec<String> = self
+            .links
+            .iter()
+            .map(|link| format!("[{}]({})", link.name, link.url(mint)))
+            .collect();
+        Some(format!("- 🔗 {}", links.join(" | ")))
+    }
+
+    /// 汇总消息中代币地址的链接，未配置链接时为纯文本
+    pub fn mint_link(&self, mint: &str) -> String {
+        match self.links.first() {
+            Some(link) => format!("[{}]({})", mint, link.url(mint)),
+            None => mint.to_string(),
+        }
+    }
+
+    /// 时间戳格式化为本地时间
+    pub fn time(&self, timestamp: i64) -> String {
+        chrono::DateTime::from_timestamp(timestamp, 0)
+            .map(|dt| {
+                chrono::DateTime::<chrono::Local>::from(dt)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|| self.labels().unknown_time.to_string())
+    }
+
+    /// 优先费和拥堵情况的展示行
+    pub fn fee_line(&self, network: &FeeContext) -> String {
+        let labels = self.labels();
+        let congestion = match network.congestion {
+            Congestion::Low => labels.congestion_levels[0],
+            Congestion::Medium => labels.congestion_levels[1],
+            Congestion::High => labels.congestion_levels[2],
+        };
+        format!(
+            "- ⛽ {}: {} microlamports/CU ({}: {}, p50/p90: {}/{})",
+            labels.suggested_fee,
+            self.number(Decimal::from(network.suggested), 0),
+            labels.congestion,
+            congestion,
+            network.p50,
+            network.p90
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_format() {
+        let format = NotificationFormat::default();
+        assert_eq!(format.number(Decimal::new(12345678, 1), 2), "1,234,567.80");
+        assert_eq!(format.number(Decimal::new(-1000, 0), 0), "-1,000");
+        assert_eq!(format.number(Decimal::new(999, 3), 2), "1.00");
+        assert_eq!(format.price(Decimal::new(281, 10)), "0.0000000281 SOL");
+
+        let format = NotificationFormat {
+            language: Language::En,
+            prefer_usd: true,
+            thousands_separator: None,
+            ..NotificationFormat::default()
+        };
+        // USD display needs a rate and falls back to SOL without one
+        assert_eq!(format.amount(Decimal::new(123, 1)), "12.30 SOL");
+        assert_eq!(
+            format.with_usd_rate(None).amount(Decimal::new(123, 1)),
+            "12.30 SOL"
+        );
+        let format = format.with_usd_rate(Some(Decimal::new(150, 0)));
+        assert_eq!(format.amount(Decimal::new(123, 1)), "$1845.00");
+        assert_eq!(format.labels().strategy, "Strategy");
+    }
+
+    #[test]
+    fn test_localized() {
+        let price = localized!(Language::En, "价格 {}", "price {}", 5);
+        assert_eq!(price, "price 5");
+        assert_eq!(localized!(Language::Zh, "价格 {}", "price {}", 5), "价格 5");
+        assert_eq!(
+            Language::En.strategy_label("卖压衰竭反转"),
+            "Sell exhaustion reversal"
+        );
+        assert_eq!(Language::Zh.strategy_label("卖压衰竭反转"), "卖压衰竭反转");
+        // 自定义策略没有译名
+        assert_eq!(Language::En.strategy_label("my strategy"), "my strategy");
+    }
+
+    #[test]
+    fn test_parse_links() {
+        let links =
+            parse_links("gmgn, dexscreener,unknown,Birdeye=https://birdeye.so/token/{mint},bad=x");
+        let names: Vec<&str> = links.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, vec!["GMGN", "DexScreener", "Birdeye"]);
+        assert_eq!(links[2].url("Mint1"), "https://birdeye.so/token/Mint1");
+
+        let format = NotificationFormat {
+            links: links[..2].to_vec(),
+            ..NotificationFormat::default()
+        };
+        assert_eq!(
+            format.links_line("M").unwrap(),
+            "- 🔗 [GMGN](https://gmgn.ai/sol/token/M) | [DexScreener](https://dexscreener.com/solana/M)"
+        );
+        assert_eq!(format.mint_link("M"), "[M](https://gmgn.ai/sol/token/M)");
+        assert_eq!(
+            chart_link("https://monitor.example.com/").url("M"),
+            "https://monitor.example.com/token/M"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::strategy::StrategyAlert;
+
+/// 通知方案：一个用户或团队自己的通知渠道、过滤条件、免打扰时段和关注列表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationProfile {
+    /// 方案名称，唯一
+    pub name: String,
+    /// Webhook地址
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// 通知脚本路径
+    #[serde(default)]
+    pub script_path: Option<PathBuf>,
+    /// Telegram聊天ID，使用全局的 `TELEGRAM_BOT_TOKEN` 发送
+    #[serde(default)]
+    pub telegram_chat_id: Option<String>,
+    /// 只接收这些策略的告警，为空时接收全部
+    #[serde(default)]
+    pub strategies: Vec<String>,
+    /// 关注列表：只接收这些mint的告警，为空时接收全部
+    #[serde(default)]
+    pub mints: Vec<String>,
+    /// 免打扰时段（本地时间，整点），如 "23-7" 表示23:00到次日7:00，期间的告警不发送
+    #[serde(default)]
+    pub quiet_hours: Option<String>,
+    /// 只接收严重度不低于该值的告警，未评分的告警不受限制
+    #[serde(default)]
+    pub min_severity: Option<u8>,
+}
+
+impl NotificationProfile {
+    /// 方案配置的渠道名称
+    pub fn channels(&self) -> Vec<&'static str> {
+        let mut channels = Vec::new();
+        if self.script_path.is_some() {
+            channels.push("script");
+        }
+        if self.webhook_url.is_some() {
+            channels.push("webhook");
+        }
+        if self.telegram_chat_id.is_some() {
+            channels.push("telegram");
+        }
+        channels
+    }
+
+    /// 给定的本地小时是否处于免打扰时段
+    pub fn is_quiet(&self, hour: u32) -> bool {
+        self.quiet_hours
+            .as_deref()
+            .and_then(parse_quiet_hours)
+            .is_some_and(|(start, end)| {
+                if start <= end {
+                    (start..end).contains(&hour)
+                } else {
+                    hour >= start || hour < end
+                }
+            })
+    }
+
+    /// 告警是否发送给该方案
+    pub fn matches(&self, alert: &StrategyAlert, hour: u32) -> bool {
+        !self.is_quiet(hour)
+            && (self.strategies.is_empty() || self.strategies.contains(&alert.strategy_name))
+            && (self.mints.is_empty() || self.mints.contains(&alert.mint))
+            && self
+                .min_severity
+                .is_none_or(|min| alert.severity.is_none_or(|severity| severity >= min))
+    }
+}
+
+/// 解析免打扰时段 "start-end"（0-23整点），格式错误时返回None
+pub fn parse_quiet_hours(hours: &str) -> Option<(u32, u32)> {
+    let (start, end) = hours.split_once('-')?;
+    let start: u32 = start.trim().parse().ok()?;
+    let end: u32 = end.trim().parse().ok()?;
+    (start < 24 && end < 24).then_some((start, end))
+}
+
+/// 从 `NOTIFICATION_PROFILES_PATH` 指向的JSON文件读取通知方案，未设置时没有方案。
+/// 方案名称重复、没有渠道、免打扰时段格式错误，或使用telegram渠道但未设置
+/// `TELEGRAM_BOT_TOKEN` 时返回错误
+pub fn load_profiles() -> Result<Vec<NotificationProfile>> {
+    let Some(path) = std::env::var("NOTIFICATION_PROFILES_PATH")
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+    else {
+        return Ok(Vec::new());
+    };
+    let data = std::fs::read_to_string(&path)
+        .with_context(|| format!("读取通知方案文件 {} 失败", path))?;
+    let profiles: Vec<NotificationProfile> =
+        serde_json::from_str(&data).with_context(|| format!("解析通知方案文件 {} 失败", path))?;
+    validate_profiles(&profiles)?;
+    if let Some(profile) = profiles.iter().find(|p| p.telegram_chat_id.is_some())
+        && std::env::var("TELEGRAM_BOT_TOKEN")
+            .ok()
+            .is_none_or(|token| token.trim().is_empty())
+    {
+        anyhow::bail!(
+            "通知方案 {} 使用telegram渠道，但未设置 TELEGRAM_BOT_TOKEN",
+            profile.name
+        );
+    }
+    Ok(profiles)
+}
+
+fn validate_profiles(profiles: &[NotificationProfile]) -> Result<()> {
+    for (i, profile) in profiles.iter().enumerate() {
+        if profiles[..i].iter().any(|p| p.name == profile.name) {
+            anyhow::bail!("通知方案名称重复: {}", profile.name);
+        }
+        if profile.channels().is_empty() {
+            anyhow::bail!("通知方案 {} 没有配置渠道", profile.name);
+        }
+        if let Some(hours) = &profile.quiet_hours
+            && parse_quiet_hours(hours).is_none()
+        {
+            anyhow::bail!("通知方案 {} 的免打扰时段格式错误: {}", profile.name, hours);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_matching() {
+        let profile: NotificationProfile = serde_json::from_str(
+            r#"{"name": "night", "webhook_url": "http://x", "strategies": ["持有人增长"], "quiet_hours": "23-7"}"#,
+        )
+        .unwrap();
+        let alert = StrategyAlert {
+            mint: "M".to_string(),
+            strategy_name: "持有人增长".to_string(),
+            message: String::new(),
+            timestamp: 0,
+            severity: None,
+            params: None,
+            klines: Vec::new(),
+        };
+        assert!(profile.matches(&alert, 12));
+        assert!(!profile.matches(&alert, 23));
+        assert!(!profile.matches(&alert, 3));
+        assert!(profile.matches(&alert, 7));
+
+        let picky = NotificationProfile {
+            min_severity: Some(60),
+            ..profile.clone()
+        };
+        assert!(picky.matches(&alert, 12));
+        let minor = StrategyAlert {
+            severity: Some(40),
+            ..alert.clone()
+        };
+        assert!(!picky.matches(&minor, 12));
+
+        let other = StrategyAlert {
+            strategy_name: "连续递增上涨模式".to_string(),
+            ..alert
+        };
+        assert!(!profile.matches(&other, 12));
+
+        assert_eq!(parse_quiet_hours("9-17"), Some((9, 17)));
+        assert_eq!(parse_quiet_hours("9-24"), None);
+        assert!(validate_profiles(&[profile.clone(), profile]).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::kline::KLineManager;
+use crate::positions::{self, WalletPosition};
+
+/// 钱包在单个mint上的盈亏估算（SOL）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MintPnl {
+    pub mint: String,
+    pub bought_sol: Decimal,
+    pub sold_sol: Decimal,
+    pub bought_token: Decimal,
+    pub sold_token: Decimal,
+    /// 估算的剩余持仓（不会小于0）
+    pub holding_token: Decimal,
+    /// 平均买入成本（SOL/token）
+    pub avg_cost: Decimal,
+    /// 最新K线收盘价，无K线时为空
+    pub price: Option<Decimal>,
+    pub realized_pnl_sol: Decimal,
+    pub unrealized_pnl_sol: Decimal,
+    pub total_pnl_sol: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletPnl {
+    pub wallet: String,
+    pub realized_pnl_sol: Decimal,
+    pub unrealized_pnl_sol: Decimal,
+    pub total_pnl_sol: Decimal,
+    pub mints: Vec<MintPnl>,
+}
+
+// Average-cost PnL. Tokens sold beyond what was bought while monitored are
+// treated as zero-cost, since their entry was not observed.
+pub fn compute_mint_pnl(mint: &str, position: &WalletPosition, price: Option<Decimal>) -> MintPnl {
+    let avg_cost = if position.bought_token > Decimal::ZERO {
+        position.bought_sol / position.bought_token
+    } else {
+        Decimal::ZERO
+    };
+    let matched_sold = position.sold_token.min(position.bought_token);
+    let realized_pnl_sol = position.sold_sol - matched_sold * avg_cost;
+    let holding_token = position.net_token().max(Decimal::ZERO);
+    let unrealized_pnl_sol = price
+        .map(|p| holding_token * (p - avg_cost))
+        .unwrap_or(Decimal::ZERO);
+
+    MintPnl {
+        mint: mint.to_string(),
+        bought_sol: position.bought_sol,
+        sold_sol: position.sold_sol,
+        bought_token: position.bought_token,
+        sold_token: position.sold_token,
+        holding_token,
+        avg_cost,
+        price,
+        realized_pnl_sol,
+        unrealized_pnl_sol,
+        total_pnl_sol: realized_pnl_sol + unrealized_pnl_sol,
+    }
+}
+
+// Estimate a wallet's realized/unrealized PnL for every mint it traded
+pub async fn get_wallet_pnl(manager: &KLineManager, wallet: &str) -> Result<WalletPnl> {
+    let mut mints = Vec::new();
+    for (mint, position) in positions::get_wallet_positions(wallet).await? {
+        let price = manager
+            .get_klines_for_mint(&mint, 60, Some(1))
+            .await?
+            .last()
+            .and_then(|k| k.close.parse().ok());
+        mints.push(compute_mint_pnl(&mint, &position, price));
+    }
+    mints.sort_by_key(|m| std::cmp::Reverse(m.total_pnl_sol));
+
+    let realized_pnl_sol: Decimal = mints.iter().map(|m| m.realized_pnl_sol).sum();
+    let unrealized_pnl_sol: Decimal = mints.iter().map(|m| m.unrealized_pnl_sol).sum();
+
+    Ok(WalletPnl {
+        wallet: wallet.to_string(),
+        realized_pnl_sol,
+        unrealized_pnl_sol,
+        total_pnl_sol: realized_pnl_sol + unrealized_pnl_sol,
+        mints,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_mint_pnl() {
+        let position = WalletPosition {
+            bought_token: Decimal::new(1000, 0),
+            sold_token: Decimal::new(400, 0),
+            bought_sol: Decimal::new(10, 0),
+            sold_sol: Decimal::new(8, 0),
+            trades: 3,
+            last_update: 0,
+        };
+        // avg cost 0.01, sold 400 for 8 SOL -> realized 4 SOL
+        // holding 600 at 0.02 -> unrealized 6 SOL
+        let pnl = compute_mint_pnl("mint", &position, Some(Decimal::new(2, 2)));
+        assert_eq!(pnl.avg_cost, Decimal::new(1, 2));
+        assert_eq!(pnl.realized_pnl_sol, Decimal::new(4, 0));
+        assert_eq!(pnl.holding_token, Decimal::new(600, 0));
+        assert_eq!(pnl.unrealized_pnl_sol, Decimal::new(6, 0));
+        assert_eq!(pnl.total_pnl_sol, Decimal::new(10, 0));
+    }
+}
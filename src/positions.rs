@@ -0,0 +1,302 @@
+use anyhow::Result;
+use redis::{AsyncCommands, aio::ConnectionManager};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::clusters::{self, ClusterAnalysis};
+use crate::kline::KLineManager;
+use crate::labels::{self, WalletLabel};
+use crate::launch::{self, LaunchInfo};
+use crate::limits;
+use crate::redis_helper;
+use crate::trades::TradeRecord;
+
+// Number of top wallets (by traded SOL volume) whose positions are tracked over time
+pub const TOP_WALLETS: usize = 10;
+
+/// 单个钱包在某个mint上的累计买卖
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WalletPosition {
+    pub bought_token: Decimal,
+    pub sold_token: Decimal,
+    pub bought_sol: Decimal,
+    pub sold_sol: Decimal,
+    pub trades: u64,
+    pub last_update: i64,
+}
+
+impl WalletPosition {
+    pub fn net_token(&self) -> Decimal {
+        self.bought_token - self.sold_token
+    }
+
+    /// 累加一笔交易
+    pub fn apply(&mut self, trade: &TradeRecord) {
+        if trade.is_buy {
+            self.bought_token += trade.token_amount;
+            self.bought_sol += trade.sol_amount;
+        } else {
+            self.sold_token += trade.token_amount;
+            self.sold_sol += trade.sol_amount;
+        }
+        self.trades += 1;
+        self.last_update = trade.timestamp;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionPoint {
+    pub timestamp: i64,
+    pub net_token: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletPositionSeries {
+    pub wallet: String,
+    pub is_creator: bool,
+    pub net_token: Decimal,
+    pub position: WalletPosition,
+    pub series: Vec<PositionPoint>,
+    /// 钱包标签（交易所、机器人等）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<WalletLabel>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MintPositions {
+    pub mint: String,
+    pub creator: Option<String>,
+    pub wallets: Vec<WalletPositionSeries>,
+    /// Top钱包的资金来源集群，只有触发过告警的mint才会分析
+    pub clusters: Option<ClusterAnalysis>,
+}
+
+// Hash of wallet -> cumulative position
+fn get_positions_key(mint: &str) -> String {
+    format!("positions:{}", mint)
+}
+
+// Sorted set of wallet -> cumulative traded SOL, used to rank top wallets
+fn get_volume_key(mint: &str) -> String {
+    format!("positions:volume:{}", mint)
+}
+
+// Sorted set of wallet -> time of its first buy, used to find early buyers
+fn get_first_buy_key(mint: &str) -> String {
+    format!("positions:first_buy:{}", mint)
+}
+
+// Set of mints a wallet has traded, used for per-wallet queries
+fn get_wallet_mints_key(wallet: &str) -> String {
+    format!("wallet:mints:{}", wallet)
+}
+
+/// 钱包交易过的mint（按名称排序），索引在钱包最后一笔交易后保留 WALLET_INDEX_TTL_SECS
+pub async fn get_wallet_mints(wallet: &str) -> Result<Vec<String>> {
+    let mut con = redis_helper::get_connection().await?;
+    let mut mints: Vec<String> = con.smembers(get_wallet_mints_key(wallet)).await?;
+    mints.sort();
+    Ok(mints)
+}
+
+// How long the wallet -> mints index is kept after the wallet's last trade
+fn wallet_index_ttl_secs() -> i64 {
+    std::env::var("WALLET_INDEX_TTL_SECS")
+        .unwrap_or_else(|_| "604800".to_string())
+        .parse()
+        .unwrap_or(604800)
+}
+
+// Hash of minute timestamp -> net token position for one tracked wallet
+fn get_series_key(mint: &str, wallet: &str) -> String {
+    format!("positions:series:{}:{}", mint, wallet)
+}
+
+/// 所有持仓相关key（用于清理）。
+/// 持仓序列只为持仓hash中的钱包写入，按钱包逐个检查，不扫描key
+pub async fn get_position_keys(con: &mut ConnectionManager, mint: &str) -> Result<Vec<String>> {
+    let wallets: Vec<String> = con.hkeys(get_positions_key(mint)).await?;
+    let mut keys = Vec::new();
+    for chunk in wallets.chunks(limits::limits().redis_pipeline_size) {
+        let series: Vec<String> = chunk
+            .iter()
+            .map(|wallet| get_series_key(mint, wallet))
+            .collect();
+        let mut pipe = redis::pipe();
+        for key in &series {
+            pipe.exists(key);
+        }
+        let exists: Vec<bool> = pipe.query_async(&mut *con).await?;
+        keys.extend(
+            series
+                .into_iter()
+                .zip(exists)
+                .filter_map(|(key, exists)| exists.then_some(key)),
+        );
+    }
+    keys.push(get_positions_key(mint));
+    keys.push(get_volume_key(mint));
+    keys.push(get_first_buy_key(mint));
+    Ok(keys)
+}
+
+// Creator of a mint, if its launch was observed
+async fn get_creator(con: &mut ConnectionManager, mint: &str) -> Result<Option<String>> {
+    let data: Option<String> = con.get(launch::get_launch_key(mint)).await?;
+    Ok(data
+        .and_then(|d| serde_json::from_str::<LaunchInfo>(&d).ok())
+        .map(|info| info.creator))
+}
+
+/// 根据一笔交易更新钱包累计持仓；创建者和Top钱包额外记录按分钟的持仓时间序列
+pub async fn record_trade(con: &mut ConnectionManager, trade: &TradeRecord) -> Result<()> {
+    if trade.user.is_empty() {
+        return Ok(());
+    }
+    let positions_key = get_positions_key(&trade.mint);
+    let existing: Option<String> = con.hget(&positions_key, &trade.user).await?;
+    let mut position: WalletPosition = existing
+        .and_then(|d| serde_json::from_str(&d).ok())
+        .unwrap_or_default();
+
+    position.apply(trade);
+    let _: () = con
+        .hset(
+            &positions_key,
+            &trade.user,
+            serde_json::to_string(&position)?,
+        )
+        .await?;
+
+    if trade.is_buy {
+        // NX keeps the time of the first buy
+        let _: () = redis::cmd("ZADD")
+            .arg(get_first_buy_key(&trade.mint))
+            .arg("NX")
+            .arg(trade.timestamp)
+            .arg(&trade.user)
+            .query_async(con)
+            .await?;
+    }
+
+    let wallet_mints_key = get_wallet_mints_key(&trade.user);
+    let _: () = con.sadd(&wallet_mints_key, &trade.mint).await?;
+    let _: () = con
+        .expire(&wallet_mints_key, wallet_index_ttl_secs())
+        .await?;
+
+    let volume_key = get_volume_key(&trade.mint);
+    let sol_volume: f64 = trade.sol_amount.try_into().unwrap_or(0.0);
+    let _: () = con.zincr(&volume_key, &trade.user, sol_volume).await?;
+
+    let rank: Option<usize> = con.zrevrank(&volume_key, &trade.user).await?;
+    let is_top = rank.is_some_and(|r| r < TOP_WALLETS);
+    let is_creator = !is_top
+        && get_creator(con, &trade.mint)
+            .await?
+            .is_some_and(|creator| creator == trade.user);
+
+    if is_top || is_creator {
+        let minute_ts = KLineManager::get_minute_timestamp(trade.timestamp);
+        let _: () = con
+            .hset(
+                get_series_key(&trade.mint, &trade.user),
+                minute_ts,
+                position.net_token().to_string(),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// 在 `until`（含）之前首次买入的钱包及其当前累计持仓，不受成交记录条数上限影响
+pub async fn get_early_positions(mint: &str, until: i64) -> Result<Vec<(String, WalletPosition)>> {
+    let mut con = redis_helper::get_connection().await?;
+    let wallets: Vec<String> = con
+        .zrangebyscore(get_first_buy_key(mint), "-inf", until)
+        .await?;
+    let mut positions = Vec::new();
+    for chunk in wallets.chunks(limits::limits().redis_pipeline_size) {
+        let data: Vec<Option<String>> = redis::cmd("HMGET")
+            .arg(get_positions_key(mint))
+            .arg(chunk)
+            .query_async(&mut *con)
+            .await?;
+        positions.extend(chunk.iter().zip(data).filter_map(|(wallet, data)| {
+            let position = serde_json::from_str(&data?).ok()?;
+            Some((wallet.clone(), position))
+        }));
+    }
+    Ok(positions)
+}
+
+/// 获取创建者和Top钱包的持仓及时间序列
+pub async fn get_mint_positions(mint: &str) -> Result<MintPositions> {
+    let mut con = redis_helper::get_connection().await?;
+    let creator = get_creator(&mut con, mint).await?;
+
+    let mut wallets: Vec<String> = con
+        .zrevrange(get_volume_key(mint), 0, TOP_WALLETS as isize - 1)
+        .await?;
+    if let Some(creator) = creator.as_ref().filter(|c| !wallets.contains(c)) {
+        wallets.insert(0, creator.clone());
+    }
+
+    let mut result = Vec::new();
+    for wallet in wallets {
+        let data: Option<String> = con.hget(get_positions_key(mint), &wallet).await?;
+        let Some(position) = data.and_then(|d| serde_json::from_str::<WalletPosition>(&d).ok())
+        else {
+            continue;
+        };
+        let points: HashMap<i64, String> = con.hgetall(get_series_key(mint, &wallet)).await?;
+        let mut series: Vec<PositionPoint> = points
+            .into_iter()
+            .filter_map(|(timestamp, net)| {
+                Some(PositionPoint {
+                    timestamp,
+                    net_token: net.parse().ok()?,
+                })
+            })
+            .collect();
+        series.sort_by_key(|p| p.timestamp);
+
+        result.push(WalletPositionSeries {
+            is_creator: creator.as_deref() == Some(wallet.as_str()),
+            net_token: position.net_token(),
+            wallet,
+            position,
+            series,
+            label: None,
+        });
+    }
+
+    drop(con);
+    let wallet_labels = labels::get_labels(result.iter().map(|w| w.wallet.as_str())).await?;
+    for wallet in &mut result {
+        wallet.label = wallet_labels.get(&wallet.wallet).cloned();
+    }
+    let clusters = clusters::get_cluster_analysis(mint).await?;
+
+    Ok(MintPositions {
+        mint: mint.to_string(),
+        creator,
+        wallets: result,
+        clusters,
+    })
+}
+
+/// 获取钱包交易过的所有mint及其累计持仓（持仓已被清理的mint会被跳过）
+pub async fn get_wallet_positions(wallet: &str) -> Result<Vec<(String, WalletPosition)>> {
+    let mints = get_wallet_mints(wallet).await?;
+    let mut con = redis_helper::get_connection().await?;
+    let mut result = Vec::new();
+    for mint in mints {
+        let data: Option<String> = con.hget(get_positions_key(&mint), wallet).await?;
+        if let Some(position) = data.and_then(|d| serde_json::from_str(&d).ok()) {
+            result.push((mint, position));
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,321 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{info, instrument, warn};
+
+use crate::alerts;
+use crate::cached_set::{CachedRedisSet, SetSource};
+use crate::localized;
+use crate::notification::NotificationManager;
+use crate::notification_format::NotificationFormat;
+use crate::redis_helper;
+use crate::severity;
+use crate::strategy::StrategyAlert;
+use crate::token_info;
+use crate::trades::TradeRecord;
+
+// Set of mints that have at least one pending price alert
+const PRICE_ALERT_MINTS_KEY: &str = "price_alerts:mints";
+
+// Pump mints have a 1B supply, used when a mint's supply can't be read
+const DEFAULT_SUPPLY: i64 = 1_000_000_000;
+
+// Removes an alert and, in the same step, drops the mint from the set once it
+// has none left, so a concurrent add can't be dropped from the set
+const REMOVE_SCRIPT: &str = r#"
+local removed = redis.call('HDEL', KEYS[1], ARGV[1])
+if redis.call('HLEN', KEYS[1]) == 0 then
+    redis.call('SREM', KEYS[2], ARGV[2])
+end
+return removed
+"#;
+
+// In-memory copy of the mints with pending price alerts, reloaded every 5 seconds
+static ALERT_MINTS: CachedRedisSet =
+    CachedRedisSet::new(PRICE_ALERT_MINTS_KEY, SetSource::Members, || {
+        Duration::from_secs(5)
+    });
+
+/// 价格提醒的目标
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PriceAlertTarget {
+    /// 价格涨到（SOL）
+    PriceAbove { value: Decimal },
+    /// 价格跌到（SOL）
+    PriceBelow { value: Decimal },
+    /// 市值涨到（SOL）
+    MarketCapAbove { value: Decimal },
+    /// 市值跌到（SOL）
+    MarketCapBelow { value: Decimal },
+}
+
+impl PriceAlertTarget {
+    /// 是否是市值目标，需要代币供应量
+    pub fn needs_supply(&self) -> bool {
+        matches!(
+            self,
+            PriceAlertTarget::MarketCapAbove { .. } | PriceAlertTarget::MarketCapBelow { .. }
+        )
+    }
+
+    /// 用成交价和代币供应量评估目标，到达时返回按通知格式生成的描述信息
+    pub fn evaluate(
+        &self,
+        price: Decimal,
+        supply: Decimal,
+        format: &NotificationFormat,
+    ) -> Option<String> {
+        let market_cap = price * supply;
+        let language = format.language;
+        match self {
+            PriceAlertTarget::PriceAbove { value } => (price >= *value).then(|| {
+                let (price, value) = (format.price(price), format.price(*value));
+                localized!(
+                    language,
+                    "价格达到目标 {1}（当前 {0}）",
+                    "price reached target {1} (now {0})",
+                    price,
+                    value
+                )
+            }),
+            PriceAlertTarget::PriceBelow { value } => (price <= *value).then(|| {
+                let (price, value) = (format.price(price), format.price(*value));
+                localized!(
+                    language,
+                    "价格跌破目标 {1}（当前 {0}）",
+                    "price fell below target {1} (now {0})",
+                    price,
+                    value
+                )
+            }),
+            PriceAlertTarget::MarketCapAbove { value } => (market_cap >= *value).then(|| {
+                let (market_cap, value) = (format.amount(market_cap), format.amount(*value));
+                localized!(
+                    language,
+                    "市值达到目标 {1}（当前 {0}）",
+                    "market cap reached target {1} (now {0})",
+                    market_cap,
+                    value
+                )
+            }),
+            PriceAlertTarget::MarketCapBelow { value } => (market_cap <= *value).then(|| {
+                let (market_cap, value) = (format.amount(market_cap), format.amount(*value));
+                localized!(
+                    language,
+                    "市值跌破目标 {1}（当前 {0}）",
+                    "market cap fell below target {1} (now {0})",
+                    market_cap,
+                    value
+                )
+            }),
+        }
+    }
+}
+
+/// 一次性价格提醒：实时交易第一次到达目标时通知并删除
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceAlert {
+    pub id: String,
+    pub mint: String,
+    pub target: PriceAlertTarget,
+    pub note: Option<String>,
+    pub created_at: i64,
+}
+
+// Generate Redis key for the pending price alerts of a mint
+fn get_price_alerts_key(mint: &str) -> String {
+    format!("price_alerts:{}", mint)
+}
+
+/// 添加价格提醒
+pub async fn add_price_alert(
+    mint: &str,
+    target: PriceAlertTarget,
+    note: Option<String>,
+) -> Result<PriceAlert> {
+    let alert = PriceAlert {
+        id: format!("{:x}", rand::random::<u64>()),
+        mint: mint.to_string(),
+        target,
+        note,
+        created_at: chrono::Utc::now().timestamp(),
+    };
+    store_price_alert(&alert).await?;
+    Ok(alert)
+}
+
+// Write an alert and register its mint in one transaction
+async fn store_price_alert(alert: &PriceAlert) -> Result<()> {
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = redis::pipe()
+        .atomic()
+        .hset(
+            get_price_alerts_key(&alert.mint),
+            &alert.id,
+            serde_json::to_string(alert)?,
+        )
+        .sadd(PRICE_ALERT_MINTS_KEY, &alert.mint)
+        .query_async(&mut *con)
+        .await?;
+    Ok(())
+}
+
+/// 获取mint的待触发价格提醒
+pub async fn get_price_alerts(mint: &str) -> Result<Vec<PriceAlert>> {
+    let mut con = redis_helper::get_connection().await?;
+    let data: Vec<String> = con.hvals(get_price_alerts_key(mint)).await?;
+    let mut price_alerts: Vec<PriceAlert> = data
+        .iter()
+        .filter_map(|d| serde_json::from_str(d).ok())
+        .collect();
+    price_alerts.sort_by_key(|a| a.created_at);
+    Ok(price_alerts)
+}
+
+/// 删除价格提醒，返回是否存在。
+/// 触发时也通过删除来认领，多个监控进程同时触发时只有一个会发送通知
+pub async fn remove_price_alert(mint: &str, id: &str) -> Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    let removed: usize = redis::Script::new(REMOVE_SCRIPT)
+        .key(get_price_alerts_key(mint))
+        .key(PRICE_ALERT_MINTS_KEY)
+        .arg(id)
+        .arg(mint)
+        .invoke_async(&mut *con)
+        .await?;
+    Ok(removed > 0)
+}
+
+// Supply of a mint in whole tokens, for market cap targets
+async fn get_supply(mint: &str) -> Decimal {
+    match token_info::get_mint_info_cached(mint).await {
+        Ok(info) => token_info::ui_amount(info.supply, info.decimals),
+        Err(e) => {
+            warn!(
+                "Failed to read supply of {}, assuming {}: {}",
+                mint, DEFAULT_SUPPLY, e
+            );
+            Decimal::new(DEFAULT_SUPPLY, 0)
+        }
+    }
+}
+
+/// 在实时交易路径上检查价格提醒，到达目标的提醒先删除认领再发送通知，
+/// 发送失败时放回，下一笔交易会再次触发
+#[instrument(name = "price_alert_check", skip_all)]
+pub async fn check_trade(trade: &TradeRecord) -> Result<()> {
+    if !ALERT_MINTS.contains(&trade.mint).await {
+        return Ok(());
+    }
+
+    let price_alerts = get_price_alerts(&trade.mint).await?;
+    let format = NotificationFormat::current().await;
+    let supply = if price_alerts.iter().any(|a| a.target.needs_supply()) {
+        get_supply(&trade.mint).await
+    } else {
+        Decimal::new(DEFAULT_SUPPLY, 0)
+    };
+    for price_alert in price_alerts {
+        let Some(message) = price_alert.target.evaluate(trade.price, supply, &format) else {
+            continue;
+        };
+        if !remove_price_alert(&trade.mint, &price_alert.id).await? {
+            continue;
+        }
+        info!(
+            "🎯 Price alert {} for {} reached: {}",
+            price_alert.id, trade.mint, message
+        );
+
+        let mut alert = StrategyAlert {
+            mint: trade.mint.clone(),
+            strategy_name: "价格提醒".to_string(),
+            message: match &price_alert.note {
+                Some(note) => format!("{} ({})", message, note),
+                None => message,
+            },
+            timestamp: trade.timestamp,
+            severity: None,
+            params: None,
+            klines: Vec::new(),
+        };
+        severity::score_alert(&mut alert).await;
+        let sent = async {
+            NotificationManager::new()?
+                .send_one_shot_notification(&alert)
+                .await
+        };
+        if let Err(e) = sent.await {
+            store_price_alert(&price_alert).await?;
+            return Err(e.context(format!("price alert {} restored", price_alert.id)));
+        }
+        alerts::record_alert(&alert).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notification_format::Language;
+
+    #[test]
+    fn test_price_alert_target() {
+        let price = Decimal::new(5, 8);
+        let supply = Decimal::new(1_000_000_000, 0); // market cap 50 SOL
+        let format = NotificationFormat::default();
+        assert!(
+            PriceAlertTarget::PriceAbove {
+                value: Decimal::new(4, 8)
+            }
+            .evaluate(price, supply, &format)
+            .is_some()
+        );
+        assert!(
+            PriceAlertTarget::PriceBelow {
+                value: Decimal::new(4, 8)
+            }
+            .evaluate(price, supply, &format)
+            .is_none()
+        );
+        assert!(
+            PriceAlertTarget::MarketCapAbove {
+                value: Decimal::new(50, 0)
+            }
+            .evaluate(price, supply, &format)
+            .is_some()
+        );
+        assert!(
+            PriceAlertTarget::MarketCapBelow {
+                value: Decimal::new(40, 0)
+            }
+            .evaluate(price, supply, &format)
+            .is_none()
+        );
+        // 供应量更小时市值按实际供应量计算
+        assert!(
+            PriceAlertTarget::MarketCapAbove {
+                value: Decimal::new(50, 0)
+            }
+            .evaluate(price, Decimal::new(800_000_000, 0), &format)
+            .is_none()
+        );
+        assert!(!PriceAlertTarget::PriceAbove { value: price }.needs_supply());
+
+        let format = NotificationFormat {
+            language: Language::En,
+            ..NotificationFormat::default()
+        };
+        assert_eq!(
+            PriceAlertTarget::MarketCapAbove {
+                value: Decimal::new(40, 0)
+            }
+            .evaluate(price, supply, &format)
+            .unwrap(),
+            "market cap reached target 40.00 SOL (now 50.00 SOL)"
+        );
+    }
+}
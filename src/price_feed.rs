@@ -0,0 +1,392 @@
+use anyhow::{Context, Result};
+use redis::AsyncCommands;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::instance_lock;
+use crate::kline::{KLineData, KLineManager};
+use crate::redis_helper;
+use crate::supervisor;
+use crate::token_info;
+
+pub const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+pub const USDT_MINT: &str = "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB";
+
+const DEFAULT_PRICE_FEED_URL: &str =
+    "https://lite-api.jup.ag/price/v3?ids=So11111111111111111111111111111111111111112";
+const DEFAULT_PRICE_FEED_POINTER: &str = "/So11111111111111111111111111111111111111112/usdPrice";
+
+// SOL/USD samples ("timestamp:price") scored by timestamp, for USD candle series
+const SOL_USD_HISTORY_KEY: &str = "sol_usd:history";
+
+// Time of the last feed request with the latest SOL/USD price it got
+static SOL_USD_CACHE: Mutex<Option<(Instant, Option<Decimal>)>> = Mutex::const_new(None);
+
+// Token a pool is quoted in, with what one whole token is worth in SOL
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoteToken {
+    pub mint: String,
+    pub decimals: u8,
+    pub price_sol: Decimal,
+}
+
+impl QuoteToken {
+    pub fn wsol() -> Self {
+        Self {
+            mint: spl_token::native_mint::id().to_string(),
+            decimals: spl_token::native_mint::DECIMALS,
+            price_sol: Decimal::ONE,
+        }
+    }
+
+    pub fn is_sol(&self) -> bool {
+        self.mint == spl_token::native_mint::id().to_string()
+    }
+
+    // Value in SOL of a raw amount of the token
+    pub fn to_sol(&self, raw: u64) -> Decimal {
+        token_info::ui_amount(raw, self.decimals) * self.price_sol
+    }
+}
+
+// USDC, USDT and the mints listed in USD_QUOTE_MINTS are valued at one dollar
+pub fn is_usd_stable(mint: &str) -> bool {
+    mint == USDC_MINT
+        || mint == USDT_MINT
+        || std::env::var("USD_QUOTE_MINTS")
+            .unwrap_or_default()
+            .split(',')
+            .any(|m| m.trim() == mint)
+}
+
+fn refresh_interval() -> Duration {
+    Duration::from_secs(
+        std::env::var("PRICE_FEED_REFRESH_SECS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()
+            .unwrap_or(60),
+    )
+}
+
+// Read a price out of a feed response; numbers and numeric strings are accepted
+fn parse_price(body: &Value, pointer: &str) -> Option<Decimal> {
+    let value = body.pointer(pointer)?;
+    let price = match value {
+        Value::String(s) => s.parse().ok()?,
+        Value::Number(n) => n.to_string().parse().ok()?,
+        _ => return None,
+    };
+    (price > Decimal::ZERO).then_some(price)
+}
+
+async fn fetch_sol_usd(url: &str, pointer: &str) -> Result<Decimal> {
+    let body: Value = reqwest::Client::new()
+        .get(url)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    parse_price(&body, pointer).with_context(|| format!("No price at {} in feed response", pointer))
+}
+
+// SOL/USD price. A fixed SOL_USD_PRICE wins; otherwise the price is read from
+// PRICE_FEED_URL at PRICE_FEED_POINTER and refreshed every PRICE_FEED_REFRESH_SECS.
+// A failed refresh keeps the last known price until the next attempt one interval
+// later. Setting PRICE_FEED_URL=none disables the feed.
+pub async fn sol_usd_price() -> Option<Decimal> {
+    if let Some(fixed) = std::env::var("SOL_USD_PRICE")
+        .ok()
+        .and_then(|p| p.parse::<Decimal>().ok())
+        .filter(|p| *p > Decimal::ZERO)
+    {
+        return Some(fixed);
+    }
+    let url =
+        std::env::var("PRICE_FEED_URL").unwrap_or_else(|_| DEFAULT_PRICE_FEED_URL.to_string());
+    if url.is_empty() || url == "none" {
+        return None;
+    }
+
+    let mut cache = SOL_USD_CACHE.lock().await;
+    let stale = cache
+        .as_ref()
+        .is_none_or(|(fetched_at, _)| fetched_at.elapsed() >= refresh_interval());
+    if stale {
+        let pointer = std::env::var("PRICE_FEED_POINTER")
+            .unwrap_or_else(|_| DEFAULT_PRICE_FEED_POINTER.to_string());
+        let last = cache.as_ref().and_then(|(_, price)| *price);
+        let price = match fetch_sol_usd(&url, &pointer).await {
+            Ok(price) => {
+                debug!("SOL/USD price from feed: {}", price);
+                Some(price)
+            }
+            Err(e) => {
+                warn!("Failed to refresh SOL/USD price: {}", e);
+                last
+            }
+        };
+        *cache = Some((Instant::now(), price));
+    }
+    cache.as_ref().and_then(|(_, price)| *price)
+}
+
+// Recording SOL/USD history is opt-in with USD_CANDLES=true
+pub fn history_enabled() -> bool {
+    std::env::var("USD_CANDLES")
+        .unwrap_or_else(|_| "false".to_string())
+        .parse()
+        .unwrap_or(false)
+}
+
+fn history_retention_secs() -> i64 {
+    std::env::var("SOL_USD_HISTORY_RETENTION_SECS")
+        .unwrap_or_else(|_| "604800".to_string())
+        .parse()
+        .unwrap_or(604800)
+}
+
+// Store a SOL/USD sample and drop the samples past the retention
+pub async fn record_sol_usd(timestamp: i64, price: Decimal) -> Result<()> {
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = redis::pipe()
+        .zadd(
+            SOL_USD_HISTORY_KEY,
+            format!("{}:{}", timestamp, price),
+            timestamp,
+        )
+        .zrembyscore(
+            SOL_USD_HISTORY_KEY,
+            "-inf",
+            timestamp - history_retention_secs(),
+        )
+        .query_async(&mut *con)
+        .await?;
+    Ok(())
+}
+
+fn parse_sample(member: &str) -> Option<(i64, Decimal)> {
+    let (timestamp, price) = member.split_once(':')?;
+    Some((timestamp.parse().ok()?, price.parse().ok()?))
+}
+
+// SOL/USD samples covering [from, to], oldest first: the ones inside the range plus
+// the last one before it. Falls back to the first sample after the range.
+pub async fn get_sol_usd_history(from: i64, to: i64) -> Result<Vec<(i64, Decimal)>> {
+    let mut con = redis_helper::get_connection().await?;
+    let mut members: Vec<String> = con
+        .zrevrangebyscore_limit(SOL_USD_HISTORY_KEY, from, "-inf", 0, 1)
+        .await?;
+    let inside: Vec<String> = con
+        .zrangebyscore(SOL_USD_HISTORY_KEY, format!("({}", from), to)
+        .await?;
+    members.extend(inside);
+    if members.is_empty() {
+        members = con
+            .zrangebyscore_limit(SOL_USD_HISTORY_KEY, format!("({}", to), "+inf", 0, 1)
+            .await?;
+    }
+    Ok(members.iter().filter_map(|m| parse_sample(m)).collect())
+}
+
+// Rate in effect at a timestamp: the last sample at or before it, or the earliest
+// sample for times before the history starts
+pub fn rate_at(history: &[(i64, Decimal)], timestamp: i64) -> Option<Decimal> {
+    let after = history.partition_point(|(t, _)| *t <= timestamp);
+    history
+        .get(after.saturating_sub(1))
+        .map(|(_, price)| *price)
+}
+
+// Candle with its SOL-denominated fields (prices, volume, net flow, liquidity)
+// converted to USD. Token volume and turnover are unit-free and kept.
+pub fn candle_to_usd(candle: &KLineData, rate: Decimal) -> KLineData {
+    let convert = |value: &str| {
+        value
+            .parse::<Decimal>()
+            .map(|v| (v * rate).normalize().to_string())
+            .unwrap_or_else(|_| value.to_string())
+    };
+    KLineData {
+        open: convert(&candle.open),
+        high: convert(&candle.high),
+        low: convert(&candle.low),
+        close: convert(&candle.close),
+        volume_sol: convert(&candle.volume_sol),
+        net_flow_sol: convert(&candle.net_flow_sol),
+        liquidity_sol: candle.liquidity_sol.as_deref().map(convert),
+        ..candle.clone()
+    }
+}
+
+// Candle with only its prices converted to USD. Volume, net flow and liquidity
+// stay in SOL, so SOL-denominated thresholds keep applying to them.
+pub fn candle_prices_to_usd(candle: &KLineData, rate: Decimal) -> KLineData {
+    let usd = candle_to_usd(candle, rate);
+    KLineData {
+        open: usd.open,
+        high: usd.high,
+        low: usd.low,
+        close: usd.close,
+        ..candle.clone()
+    }
+}
+
+// Convert candles to USD at the recorded SOL/USD rate of each candle's start time.
+// Fails when no SOL/USD history has been recorded.
+pub async fn to_usd_candles(candles: Vec<KLineData>) -> Result<Vec<KLineData>> {
+    convert_at_history(candles, candle_to_usd).await
+}
+
+// Like to_usd_candles, but converts only the prices
+pub async fn to_usd_prices(candles: Vec<KLineData>) -> Result<Vec<KLineData>> {
+    convert_at_history(candles, candle_prices_to_usd).await
+}
+
+async fn convert_at_history(
+    candles: Vec<KLineData>,
+    convert: fn(&KLineData, Decimal) -> KLineData,
+) -> Result<Vec<KLineData>> {
+    let (Some(first), Some(last)) = (candles.first(), candles.last()) else {
+        return Ok(candles);
+    };
+    let history = get_sol_usd_history(first.timestamp, last.timestamp).await?;
+    if history.is_empty() {
+        anyhow::bail!("No SOL/USD history recorded, enable USD_CANDLES on a monitor");
+    }
+    Ok(candles
+        .iter()
+        .filter_map(|k| rate_at(&history, k.timestamp).map(|rate| convert(k, rate)))
+        .collect())
+}
+
+// Sample the SOL/USD price every PRICE_FEED_REFRESH_SECS into the history,
+// when USD_CANDLES is enabled
+pub fn spawn_history_task() -> Option<JoinHandle<()>> {
+    if !history_enabled() {
+        return None;
+    }
+
+    Some(supervisor::spawn_supervised(
+        "sol_usd_history",
+        async move {
+            let mut interval = tokio::time::interval(refresh_interval());
+            loop {
+                interval.tick().await;
+                if instance_lock::is_read_only() {
+                    continue;
+                }
+                let Some(price) = sol_usd_price().await else {
+                    debug!("No SOL/USD price to record");
+                    continue;
+                };
+                let now = chrono::Utc::now().timestamp();
+                if let Err(e) = record_sol_usd(now, price).await {
+                    warn!("Failed to record SOL/USD price: {}", e);
+                }
+            }
+        },
+    ))
+}
+
+// Resolve a pool's quote token and its SOL value. USD stablecoins convert through
+// the SOL/USD price, other tokens through the latest close of their own SOL candles.
+// None when no SOL value is known yet, so the trade can't be priced.
+pub async fn resolve_quote(
+    quote_mint: &str,
+    kline_manager: &KLineManager,
+) -> Result<Option<QuoteToken>> {
+    let wsol = QuoteToken::wsol();
+    if quote_mint == wsol.mint {
+        return Ok(Some(wsol));
+    }
+
+    let price_sol = if is_usd_stable(quote_mint) {
+        sol_usd_price().await.map(|sol_usd| Decimal::ONE / sol_usd)
+    } else {
+        kline_manager
+            .get_klines_for_mint(quote_mint, 60, Some(1))
+            .await?
+            .last()
+            .and_then(|k| k.close.parse::<Decimal>().ok())
+            .filter(|p| *p > Decimal::ZERO)
+    };
+    let Some(price_sol) = price_sol else {
+        return Ok(None);
+    };
+
+    let decimals = token_info::get_mint_info_cached(quote_mint).await?.decimals;
+    Ok(Some(QuoteToken {
+        mint: quote_mint.to_string(),
+        decimals,
+        price_sol,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness;
+    use serde_json::json;
+
+    #[test]
+    fn test_quote_conversion() {
+        let wsol = QuoteToken::wsol();
+        assert!(wsol.is_sol());
+        assert_eq!(wsol.to_sol(1_500_000_000), Decimal::new(15, 1));
+
+        // 300 USDC at 150 USD/SOL
+        let usdc = QuoteToken {
+            mint: USDC_MINT.to_string(),
+            decimals: 6,
+            price_sol: Decimal::ONE / Decimal::new(150, 0),
+        };
+        assert!(!usdc.is_sol());
+        assert_eq!(usdc.to_sol(300_000_000).round_dp(9), Decimal::TWO);
+        assert!(is_usd_stable(USDT_MINT));
+
+        let body = json!({ "So11111111111111111111111111111111111111112": { "usdPrice": 151.25 } });
+        assert_eq!(
+            parse_price(&body, DEFAULT_PRICE_FEED_POINTER),
+            Some(Decimal::new(15125, 2))
+        );
+        assert_eq!(parse_price(&json!({ "price": "0" }), "/price"), None);
+    }
+
+    #[test]
+    fn test_usd_conversion() {
+        let history = [(100, Decimal::new(150, 0)), (200, Decimal::new(160, 0))];
+        assert_eq!(rate_at(&history, 50), Some(Decimal::new(150, 0)));
+        assert_eq!(rate_at(&history, 199), Some(Decimal::new(150, 0)));
+        assert_eq!(rate_at(&history, 200), Some(Decimal::new(160, 0)));
+        assert_eq!(rate_at(&[], 200), None);
+        assert_eq!(
+            parse_sample("200:160.5"),
+            Some((200, Decimal::new(1605, 1)))
+        );
+
+        let candle = KLineData {
+            high: "0.00000004".to_string(),
+            low: "0.00000002".to_string(),
+            volume_sol: "2".to_string(),
+            volume_token: "50000000".to_string(),
+            net_flow_sol: "-0.5".to_string(),
+            ..test_harness::candle(180, "0.00000003")
+        };
+        let usd = candle_to_usd(&candle, rate_at(&history, candle.timestamp).unwrap());
+        assert_eq!(usd.open, "0.0000045");
+        assert_eq!(usd.volume_sol, "300");
+        assert_eq!(usd.net_flow_sol, "-75");
+        assert_eq!(usd.volume_token, candle.volume_token);
+
+        let prices = candle_prices_to_usd(&candle, Decimal::new(150, 0));
+        assert_eq!(prices.close, "0.0000045");
+        assert_eq!(prices.volume_sol, candle.volume_sol);
+        assert_eq!(prices.net_flow_sol, candle.net_flow_sol);
+    }
+}
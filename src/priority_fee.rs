@@ -0,0 +1,200 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use solana_client::rpc_request::RpcRequest;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::constant::{PUMP_AMM_PROGRAM, PUMP_PROGRAM};
+use crate::{get_rpc_client_with_retry, redis_helper, supervisor};
+
+// Latest fee sample, expiring when sampling stops so alerts never show stale context
+const FEE_CONTEXT_KEY: &str = "network:priority_fee";
+
+/// 网络拥堵程度
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Congestion {
+    Low,
+    Medium,
+    High,
+}
+
+impl Congestion {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Congestion::Low => "低",
+            Congestion::Medium => "中",
+            Congestion::High => "高",
+        }
+    }
+}
+
+/// 最近区块的优先费统计（单位：microlamports/CU），随告警一起发送
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeContext {
+    /// 采样时间（秒）
+    pub sampled_at: i64,
+    /// 参与统计的slot数量
+    pub slots: usize,
+    pub p50: u64,
+    pub p75: u64,
+    pub p90: u64,
+    /// 建议优先费
+    pub suggested: u64,
+    pub congestion: Congestion,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PrioritizationFee {
+    prioritization_fee: u64,
+}
+
+fn sample_interval_secs() -> u64 {
+    std::env::var("PRIORITY_FEE_SAMPLE_INTERVAL_SECS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()
+        .unwrap_or(30)
+}
+
+// Suggested fee percentile and congestion thresholds (microlamports per CU)
+fn fee_config() -> (usize, u64, u64) {
+    let percentile = std::env::var("PRIORITY_FEE_SUGGESTED_PERCENTILE")
+        .unwrap_or_else(|_| "75".to_string())
+        .parse()
+        .unwrap_or(75);
+    let medium = std::env::var("PRIORITY_FEE_MEDIUM_THRESHOLD")
+        .unwrap_or_else(|_| "10000".to_string())
+        .parse()
+        .unwrap_or(10_000);
+    let high = std::env::var("PRIORITY_FEE_HIGH_THRESHOLD")
+        .unwrap_or_else(|_| "100000".to_string())
+        .parse()
+        .unwrap_or(100_000);
+    (percentile, medium, high)
+}
+
+fn percentile(sorted: &[u64], pct: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let index = (sorted.len() * pct.min(100)).div_ceil(100).max(1) - 1;
+    sorted[index.min(sorted.len() - 1)]
+}
+
+// Summarize per-slot fees; congestion follows the median so a single expensive slot doesn't raise it
+pub fn summarize_fees(
+    mut fees: Vec<u64>,
+    suggested_pct: usize,
+    medium: u64,
+    high: u64,
+    sampled_at: i64,
+) -> Option<FeeContext> {
+    if fees.is_empty() {
+        return None;
+    }
+    fees.sort_unstable();
+    let p50 = percentile(&fees, 50);
+    let congestion = if p50 >= high {
+        Congestion::High
+    } else if p50 >= medium {
+        Congestion::Medium
+    } else {
+        Congestion::Low
+    };
+    Some(FeeContext {
+        sampled_at,
+        slots: fees.len(),
+        p50,
+        p75: percentile(&fees, 75),
+        p90: percentile(&fees, 90),
+        suggested: percentile(&fees, suggested_pct),
+        congestion,
+    })
+}
+
+// Sample recent prioritization fees paid by transactions touching the pump programs
+async fn sample_fees() -> Result<Option<FeeContext>> {
+    let params = json!([[PUMP_PROGRAM, PUMP_AMM_PROGRAM]]);
+    let fees: Vec<PrioritizationFee> = get_rpc_client_with_retry(
+        |client| {
+            client
+                .send(RpcRequest::GetRecentPrioritizationFees, params.clone())
+                .map_err(anyhow::Error::from)
+        },
+        2,
+    )
+    .await?;
+
+    let (percentile, medium, high) = fee_config();
+    Ok(summarize_fees(
+        fees.into_iter().map(|f| f.prioritization_fee).collect(),
+        percentile,
+        medium,
+        high,
+        chrono::Utc::now().timestamp(),
+    ))
+}
+
+// Get the latest fee sample, if sampling is running
+pub async fn get_fee_context() -> Result<Option<FeeContext>> {
+    Ok(redis_helper::get::<_, String>(FEE_CONTEXT_KEY)
+        .await?
+        .and_then(|data| serde_json::from_str(&data).ok()))
+}
+
+// Periodically sample priority fees into Redis; disabled when the interval is 0
+pub fn spawn_sampler_task() -> Option<JoinHandle<()>> {
+    let interval_secs = sample_interval_secs();
+    if interval_secs == 0 {
+        return None;
+    }
+
+    Some(supervisor::spawn_supervised("priority_fee", async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+        loop {
+            interval.tick().await;
+            match sample_fees().await {
+                Ok(Some(context)) => {
+                    debug!(
+                        "Priority fee sample: p50={} p75={} p90={} ({:?})",
+                        context.p50, context.p75, context.p90, context.congestion
+                    );
+                    let stored = match serde_json::to_string(&context) {
+                        Ok(data) => {
+                            redis_helper::setex(FEE_CONTEXT_KEY, data, interval_secs * 3).await
+                        }
+                        Err(e) => Err(e.into()),
+                    };
+                    if let Err(e) = stored {
+                        warn!("Failed to store priority fee sample: {}", e);
+                    }
+                }
+                Ok(None) => debug!("No recent prioritization fees returned"),
+                Err(e) => warn!("Failed to sample priority fees: {}", e),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_fees() {
+        let fees: Vec<u64> = (1..=100).map(|i| i * 1_000).collect();
+        let context = summarize_fees(fees, 75, 10_000, 100_000, 1_700_000_000).unwrap();
+        assert_eq!(context.slots, 100);
+        assert_eq!(context.p50, 50_000);
+        assert_eq!(context.p75, 75_000);
+        assert_eq!(context.p90, 90_000);
+        assert_eq!(context.suggested, 75_000);
+        assert_eq!(context.congestion, Congestion::Medium);
+
+        let quiet = summarize_fees(vec![0, 0, 5_000_000], 75, 10_000, 100_000, 0).unwrap();
+        assert_eq!(quiet.congestion, Congestion::Low);
+        assert!(summarize_fees(Vec::new(), 75, 10_000, 100_000, 0).is_none());
+    }
+}
@@ -3,12 +3,33 @@ use base64::{Engine as _, engine::general_purpose};
 use rust_decimal::Decimal;
 use serde_json::Value;
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use tracing::{debug, error, info, warn};
-
-use crate::constant::PUMP_PROGRAM;
+use tracing::{debug, error, info, info_span, instrument, warn};
+
+use crate::constant::{
+    PUMP_CREATE_EVENT_DISCRIMINATOR, PUMP_PROGRAM, PUMP_VIRTUAL_SOL_OFFSET,
+    PUMP_VIRTUAL_TOKEN_OFFSET,
+};
+use crate::curve_phase;
+use crate::decode_check;
+use crate::event_layout;
+use crate::funnel;
+use crate::instance_lock;
 use crate::kline::KLineManager;
-use crate::websocket::WebSocketMonitor;
+use crate::launch;
+use crate::limits;
+use crate::metrics;
+use crate::micro_filter;
+use crate::monitor_control;
+use crate::price_alerts;
+use crate::remote_config;
+use crate::sharding;
+use crate::spam;
+use crate::supervisor;
+use crate::trades::{TradeRecord, TradeSource};
+use crate::wallets;
+use crate::watchlist;
+use crate::websocket::{self, WebSocketMonitor};
+use crate::window_alerts;
 
 #[derive(Debug)]
 pub struct TradeEvent {
@@ -27,6 +48,19 @@ pub struct TradeEvent {
     pub real_token_reserves: u64,
 }
 
+#[derive(Debug)]
+pub struct CreateEvent {
+    pub signature: String,
+    pub slot: u64,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub mint: String,
+    pub bonding_curve: String,
+    pub user: String,
+    pub creator: String,
+}
+
 #[derive(Debug)]
 pub struct TradeDetails {
     pub sol_amount_formatted: Decimal,
@@ -39,32 +73,80 @@ pub struct TradeDetails {
     pub market_cap: Decimal,
 }
 
+// Subscribe on every endpoint, merging the streams when there are several
 pub async fn connect_websocket(
-    rpc_ws_endpoint: &str,
-    kline_manager: Arc<Mutex<KLineManager>>,
+    rpc_ws_endpoints: &[String],
+    kline_manager: Arc<KLineManager>,
 ) -> Result<()> {
-    let monitor = WebSocketMonitor::new(
-        rpc_ws_endpoint.to_string(),
-        kline_manager,
-        vec![PUMP_PROGRAM.to_string()],
-        "PUMP".to_string(),
-    );
-
-    monitor
-        .start(
-            |response: &Value, kline_manager: Arc<Mutex<KLineManager>>| {
-                let response = response.clone();
-                async move { handle_pump_message(&response, kline_manager).await }
-            },
-        )
-        .await
+    let control = monitor_control::spawn_listener("PUMP").await?;
+    let monitors = rpc_ws_endpoints
+        .iter()
+        .map(|endpoint| {
+            WebSocketMonitor::new(
+                endpoint.clone(),
+                Arc::clone(&kline_manager),
+                vec![PUMP_PROGRAM.to_string()],
+                "PUMP".to_string(),
+            )
+            .with_control(control.clone())
+        })
+        .collect();
+
+    websocket::start_redundant(
+        monitors,
+        |response: &Value, kline_manager: Arc<KLineManager>| {
+            let response = response.clone();
+            async move { handle_pump_message(&response, kline_manager).await }
+        },
+    )
+    .await
 }
 
-pub async fn handle_pump_message(
-    response: &Value,
-    kline_manager: Arc<Mutex<KLineManager>>,
-) -> Result<()> {
-    if let Some(trade_events) = parse_trade_event(response) {
+pub async fn handle_pump_message(response: &Value, kline_manager: Arc<KLineManager>) -> Result<()> {
+    // A standby instance leaves all writes to the lock holder
+    if instance_lock::is_read_only() {
+        return Ok(());
+    }
+
+    // Record new launches before their trades so first buyers can be tracked
+    let (create_events, trade_events) = info_span!("parse")
+        .in_scope(|| (parse_create_event(response), parse_trade_event(response)));
+
+    if let Some(create_events) = create_events {
+        for create_event in create_events {
+            if !sharding::owns(&create_event.mint)
+                || !watchlist::is_allowed(&create_event.mint).await
+            {
+                continue;
+            }
+            if let Err(e) = launch::record_create(
+                &create_event.mint,
+                &create_event.name,
+                &create_event.symbol,
+                &create_event.uri,
+                &create_event.creator,
+                create_event.slot,
+                &create_event.signature,
+            )
+            .await
+            {
+                error!("Failed to record PUMP create event: {}", e);
+            }
+            spam::inspect_launch(
+                &create_event.mint,
+                &create_event.name,
+                &create_event.symbol,
+                &create_event.uri,
+                &create_event.creator,
+            );
+            let created_at = chrono::Utc::now().timestamp();
+            if let Err(e) = funnel::record_created(&create_event.mint, created_at).await {
+                error!("Failed to record launch funnel: {}", e);
+            }
+        }
+    }
+
+    if let Some(trade_events) = trade_events {
         debug!("Parsed PUMP trade events: {:#?}", trade_events);
 
         for trade_event in trade_events {
@@ -75,24 +157,40 @@ pub async fn handle_pump_message(
     } else {
         // Check if contains Pump instruction but parsing failed
         if contains_pump_instruction(response) {
+            metrics::record_parse_failure();
             debug!("Contains Pump instruction but parsing failed");
         }
     }
     Ok(())
 }
 
+#[instrument(name = "process_trade", skip_all, fields(mint = %trade_event.mint))]
 pub async fn process_trade_event(
     trade_event: TradeEvent,
-    kline_manager: Arc<Mutex<KLineManager>>,
+    kline_manager: Arc<KLineManager>,
 ) -> Result<()> {
+    // Drop events with implausible decoded fields, a sign of event layout drift
+    if !decode_check::accept_pump_trade(&trade_event).await {
+        return Ok(());
+    }
     if let Some(details) = calculate_trade_details(&trade_event) {
         // Skip trades with zero or invalid prices to prevent "low": "0" issues
         if details.price.is_zero() {
             warn!("Skipping trade with zero price for mint {:#?}", trade_event);
             return Ok(());
         }
+        // Skip mints handled by another shard
+        if !sharding::owns(&trade_event.mint) {
+            debug!("Skipping mint of another shard: {}", trade_event.mint);
+            return Ok(());
+        }
+        // Skip mints outside the watchlist in allowlist mode
+        if !watchlist::is_allowed(&trade_event.mint).await {
+            debug!("Skipping non-watchlist mint: {}", trade_event.mint);
+            return Ok(());
+        }
         // Skip micro transactions to keep K-lines clean
-        let min_sol_amount = std::env::var("MIN_SOL_AMOUNT_PUMP")
+        let min_sol_amount = remote_config::var("MIN_SOL_AMOUNT_PUMP")
             .unwrap_or_else(|_| "0.01".to_string())
             .parse::<Decimal>()
             .unwrap_or_else(|_| Decimal::new(1, 2)); // default 0.01 SOL
@@ -103,7 +201,13 @@ pub async fn process_trade_event(
         ONCE.call_once(|| {
             info!("📊 Pump配置 - 最小SOL金额: {}", min_sol_amount);
         });
-        if details.sol_amount_formatted < min_sol_amount {
+        if !micro_filter::accept(
+            &trade_event.mint,
+            trade_event.timestamp,
+            details.sol_amount_formatted,
+            min_sol_amount,
+            None,
+        ) {
             debug!(
                 "Skipping micro transaction: SOL={}, mint={}",
                 details.sol_amount_formatted, trade_event.mint
@@ -119,22 +223,68 @@ pub async fn process_trade_event(
         let token_amount = details.token_amount_formatted;
 
         let is_buy = trade_event.is_buy;
-        tokio::spawn(async move {
-            let manager = kline_manager.lock().await;
-            if let Err(e) = manager
-                .add_trade(
-                    &mint_clone,
-                    timestamp,
-                    price,
-                    sol_amount,
-                    token_amount,
-                    is_buy,
-                    false,
-                )
-                .await
-            {
+        let curve_progress = funnel::curve_progress(trade_event.real_token_reserves);
+
+        // Track first buyers of new launches
+        if is_buy {
+            let mint = trade_event.mint.clone();
+            let user = trade_event.user.clone();
+            let slot = trade_event.slot;
+            let signature = trade_event.signature.clone();
+            supervisor::spawn_supervised("launch_buyer", async move {
+                if let Err(e) =
+                    launch::record_buyer(&mint, &user, sol_amount, token_amount, slot, &signature)
+                        .await
+                {
+                    error!("Launch buyer tracking failed: {}", e);
+                }
+                if let Err(e) = wallets::alert_if_tracked_buy(&user, &mint, sol_amount, price).await
+                {
+                    error!("Tracked wallet alert failed: {}", e);
+                }
+            });
+        }
+
+        let trade = TradeRecord {
+            signature: trade_event.signature.clone(),
+            slot: trade_event.slot,
+            mint: mint_clone,
+            user: trade_event.user.clone(),
+            is_buy,
+            price,
+            sol_amount,
+            token_amount,
+            timestamp,
+            source: Some(TradeSource::Pump),
+            liquidity_sol: None,
+        };
+        let permit = limits::acquire_trade_permit().await;
+        supervisor::spawn_supervised("add_trade", async move {
+            let _permit = permit;
+            if let Err(e) = kline_manager.add_trade_record(&trade, false).await {
                 error!("K-line update failed: {}", e);
             }
+            if let Err(e) = price_alerts::check_trade(&trade).await {
+                error!("Price alert check failed: {}", e);
+            }
+            if let Err(e) = window_alerts::check_trade(&trade).await {
+                error!("Sliding-window alert check failed: {}", e);
+            }
+            if let Err(e) = curve_phase::check_trade(&trade, curve_progress).await {
+                error!("Curve phase check failed: {}", e);
+            }
+            if let Err(e) = funnel::record_trade(
+                &trade.mint,
+                trade.price,
+                trade.sol_amount,
+                trade.timestamp,
+                Some(curve_progress),
+                false,
+            )
+            .await
+            {
+                error!("Launch funnel update failed: {}", e);
+            }
         });
 
         info!(
@@ -262,85 +412,26 @@ fn decode_and_parse_program_data(
     // Decode base64 data
     let decoded = general_purpose::STANDARD.decode(program_data).ok()?;
 
-    if decoded.len() < 129 {
-        return None;
-    }
-
-    // Skip first 8 bytes of event identifier
-    let _event_type = &decoded[..8];
-
-    // From 8th byte is mint address (32 bytes)
-    let mint_bytes = &decoded[8..40];
-    let mint = bs58::encode(mint_bytes).into_string();
-
-    let mut pos = 40;
-
-    // Read sol_amount (8 bytes)
-    let sol_amount = {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&decoded[pos..pos + 8]);
-        u64::from_le_bytes(bytes)
-    };
-    pos += 8;
-
-    // Read token_amount (8 bytes)
-    let token_amount = {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&decoded[pos..pos + 8]);
-        u64::from_le_bytes(bytes)
-    };
-    pos += 8;
-
-    // Skip is_buy (1 byte) - we handle this in the caller
-    pos += 1;
-
-    // Read user address (32 bytes)
-    let user_bytes = &decoded[pos..pos + 32];
-    let user = bs58::encode(user_bytes).into_string();
-    pos += 32;
-
-    // Read timestamp (8 bytes)
-    let timestamp = {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&decoded[pos..pos + 8]);
-        u64::from_le_bytes(bytes) as i64
-    };
-    pos += 8;
-
-    // Read virtual_sol_reserves (8 bytes)
-    let virtual_sol_reserves = {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&decoded[pos..pos + 8]);
-        u64::from_le_bytes(bytes)
-    };
-    pos += 8;
-
-    // Read virtual_token_reserves (8 bytes)
-    let virtual_token_reserves = {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&decoded[pos..pos + 8]);
-        u64::from_le_bytes(bytes)
-    };
-    pos += 8;
-
-    // Read real_sol_reserves (8 bytes)
-    let real_sol_reserves = {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&decoded[pos..pos + 8]);
-        u64::from_le_bytes(bytes)
-    };
-    pos += 8;
-
-    // Read real_token_reserves (8 bytes)
-    let real_token_reserves = {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&decoded[pos..pos + 8]);
-        u64::from_le_bytes(bytes)
-    };
+    // Only TradeEvent is parsed here, in whichever layout version it was logged
+    let event = event_layout::decode(&decoded, &[&event_layout::PUMP_TRADE_EVENT])?;
+    let mint = event.pubkey("mint")?;
+    let sol_amount = event.u64("sol_amount")?;
+    let token_amount = event.u64("token_amount")?;
+    let user = event.pubkey("user")?;
+    let timestamp = event.i64("timestamp")?;
+    let virtual_sol_reserves = event.u64("virtual_sol_reserves")?;
+    let virtual_token_reserves = event.u64("virtual_token_reserves")?;
+    // The first layout has no real reserves, they follow from the virtual ones
+    let real_sol_reserves = event
+        .u64("real_sol_reserves")
+        .unwrap_or_else(|| virtual_sol_reserves.saturating_sub(PUMP_VIRTUAL_SOL_OFFSET));
+    let real_token_reserves = event
+        .u64("real_token_reserves")
+        .unwrap_or_else(|| virtual_token_reserves.saturating_sub(PUMP_VIRTUAL_TOKEN_OFFSET));
 
     debug!(
-        "Decoded TradeEvent: sol_amount={}, token_amount={}, user={}, timestamp={}",
-        sol_amount, token_amount, user, timestamp
+        "Decoded TradeEvent v{}: sol_amount={}, token_amount={}, user={}, timestamp={}",
+        event.layout.version, sol_amount, token_amount, user, timestamp
     );
 
     Some((
@@ -356,6 +447,88 @@ fn decode_and_parse_program_data(
     ))
 }
 
+pub fn parse_create_event(response: &Value) -> Option<Vec<CreateEvent>> {
+    let result = response.get("params")?.get("result")?;
+    let value = result.get("value")?;
+    let signature = value.get("signature")?.as_str()?.to_string();
+    let slot = result.get("context")?.get("slot")?.as_u64()?;
+    let logs = value.get("logs")?.as_array()?;
+
+    let has_create_instruction = logs.iter().any(|log| {
+        log.as_str()
+            .map(|s| s.contains("Program log: Instruction: Create"))
+            .unwrap_or(false)
+    });
+    if !has_create_instruction {
+        return None;
+    }
+
+    let events: Vec<CreateEvent> = logs
+        .iter()
+        .filter_map(|log| log.as_str()?.strip_prefix("Program data: "))
+        .filter_map(decode_create_event_data)
+        .map(|data| CreateEvent {
+            signature: signature.clone(),
+            slot,
+            name: data.0,
+            symbol: data.1,
+            uri: data.2,
+            mint: data.3,
+            bonding_curve: data.4,
+            user: data.5,
+            creator: data.6,
+        })
+        .collect();
+
+    if events.is_empty() {
+        None
+    } else {
+        Some(events)
+    }
+}
+
+// Read a borsh string (u32 length prefix followed by UTF-8 bytes)
+pub fn read_borsh_string(data: &[u8], pos: &mut usize) -> Option<String> {
+    let len_bytes: [u8; 4] = data.get(*pos..*pos + 4)?.try_into().ok()?;
+    let len = u32::from_le_bytes(len_bytes) as usize;
+    *pos += 4;
+    let bytes = data.get(*pos..*pos + len)?;
+    *pos += len;
+    Some(String::from_utf8_lossy(bytes).to_string())
+}
+
+pub fn read_pubkey(data: &[u8], pos: &mut usize) -> Option<String> {
+    let bytes = data.get(*pos..*pos + 32)?;
+    *pos += 32;
+    Some(bs58::encode(bytes).into_string())
+}
+
+fn decode_create_event_data(
+    program_data: &str,
+) -> Option<(String, String, String, String, String, String, String)> {
+    let decoded = general_purpose::STANDARD.decode(program_data).ok()?;
+    if decoded.len() < 8 || decoded[..8] != PUMP_CREATE_EVENT_DISCRIMINATOR {
+        return None;
+    }
+
+    let mut pos = 8;
+    let name = read_borsh_string(&decoded, &mut pos)?;
+    let symbol = read_borsh_string(&decoded, &mut pos)?;
+    let uri = read_borsh_string(&decoded, &mut pos)?;
+    let mint = read_pubkey(&decoded, &mut pos)?;
+    let bonding_curve = read_pubkey(&decoded, &mut pos)?;
+    let user = read_pubkey(&decoded, &mut pos)?;
+    // Older events have no separate creator field, the user is the creator
+    let creator = read_pubkey(&decoded, &mut pos).unwrap_or_else(|| user.clone());
+
+    debug!(
+        "Decoded CreateEvent: name={}, symbol={}, mint={}",
+        name, symbol, mint
+    );
+
+    Some((name, symbol, uri, mint, bonding_curve, user, creator))
+}
+
 pub fn calculate_trade_details(trade_event: &TradeEvent) -> Option<TradeDetails> {
     // Use Decimal for precise calculations
     let sol_divisor = Decimal::new(1_000_000_000, 0); // 10^9 for SOL
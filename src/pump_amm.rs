@@ -5,13 +5,28 @@ use serde_json::Value;
 use solana_sdk::pubkey::Pubkey;
 use std::str::FromStr;
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use tracing::{debug, error, info, warn};
+use tracing::{Instrument, debug, error, info, info_span, instrument, warn};
 
 use crate::constant::PUMP_AMM_PROGRAM;
+use crate::decode_check;
+use crate::event_layout;
+use crate::fees;
+use crate::funnel;
+use crate::instance_lock;
 use crate::kline::KLineManager;
-use crate::websocket::WebSocketMonitor;
-use crate::{get_rpc_client_with_retry, redis_helper};
+use crate::limits;
+use crate::metrics;
+use crate::micro_filter;
+use crate::monitor_control;
+use crate::price_alerts;
+use crate::price_feed;
+use crate::remote_config;
+use crate::sharding;
+use crate::supervisor;
+use crate::trades::{TradeRecord, TradeSource};
+use crate::websocket::{self, WebSocketMonitor};
+use crate::window_alerts;
+use crate::{get_rpc_client_with_retry, redis_helper, token_info, wallets, watchlist};
 
 #[derive(Debug, Clone)]
 pub struct AmmPoolData {
@@ -19,15 +34,33 @@ pub struct AmmPoolData {
     pub quote_token_mint: String,
 }
 
+// Traded token of a pool and the token it is quoted in
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolPair {
+    pub mint: String,
+    pub quote_mint: String,
+    // Whether the traded token is the pool's base token
+    pub mint_is_base: bool,
+}
+
 impl AmmPoolData {
-    pub fn get_mint(&self) -> Option<String> {
+    // WSOL and USD stablecoins are always the quote side. Otherwise the base token
+    // is the traded one, as in pools created on PumpSwap.
+    pub fn pair(&self) -> PoolPair {
         let wsol = spl_token::native_mint::id().to_string();
-        if self.base_token_mint == wsol {
-            Some(self.quote_token_mint.clone())
-        } else if self.quote_token_mint == wsol {
-            Some(self.base_token_mint.clone())
+        let is_quote_like = |mint: &str| mint == wsol || price_feed::is_usd_stable(mint);
+        if is_quote_like(&self.base_token_mint) && !is_quote_like(&self.quote_token_mint) {
+            PoolPair {
+                mint: self.quote_token_mint.clone(),
+                quote_mint: self.base_token_mint.clone(),
+                mint_is_base: false,
+            }
         } else {
-            None
+            PoolPair {
+                mint: self.base_token_mint.clone(),
+                quote_mint: self.quote_token_mint.clone(),
+                mint_is_base: true,
+            }
         }
     }
 }
@@ -50,6 +83,18 @@ pub struct AmmTradeEvent {
     pub coin_creator_fee: u64,
 }
 
+impl AmmTradeEvent {
+    // Raw base and quote amounts of the trade. The decoder stores them as token and
+    // SOL amounts by taking the side with the smaller reserve as SOL; undo that.
+    pub fn base_quote_amounts(&self) -> (u64, u64) {
+        if self.pool_base_token_reserves > self.pool_quote_token_reserves {
+            (self.token_amount, self.sol_amount)
+        } else {
+            (self.sol_amount, self.token_amount)
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct AmmTradeDetails {
     pub sol_amount_formatted: Decimal,
@@ -60,36 +105,49 @@ pub struct AmmTradeDetails {
     pub protocol_fee_formatted: Decimal,
     pub creator_fee_formatted: Decimal,
     pub price: Decimal,
+    // Pool liquidity in SOL, twice the SOL-side reserve
+    pub liquidity_sol: Decimal,
 }
 
+// Subscribe on every endpoint, merging the streams when there are several
 pub async fn connect_websocket(
-    rpc_ws_endpoint: &str,
-    kline_manager: Arc<Mutex<KLineManager>>,
+    rpc_ws_endpoints: &[String],
+    kline_manager: Arc<KLineManager>,
 ) -> Result<()> {
-    let monitor = WebSocketMonitor::new(
-        rpc_ws_endpoint.to_string(),
-        kline_manager,
-        vec![PUMP_AMM_PROGRAM.to_string()],
-        "AMM".to_string(),
-    );
-
-    monitor
-        .start(
-            |response: &Value, kline_manager: Arc<Mutex<KLineManager>>| {
-                let response = response.clone();
-                async move { handle_amm_message(&response, kline_manager).await }
-            },
-        )
-        .await
+    let control = monitor_control::spawn_listener("AMM").await?;
+    let monitors = rpc_ws_endpoints
+        .iter()
+        .map(|endpoint| {
+            WebSocketMonitor::new(
+                endpoint.clone(),
+                Arc::clone(&kline_manager),
+                vec![PUMP_AMM_PROGRAM.to_string()],
+                "AMM".to_string(),
+            )
+            .with_control(control.clone())
+        })
+        .collect();
+
+    websocket::start_redundant(
+        monitors,
+        |response: &Value, kline_manager: Arc<KLineManager>| {
+            let response = response.clone();
+            async move { handle_amm_message(&response, kline_manager).await }
+        },
+    )
+    .await
 }
 
-pub async fn handle_amm_message(
-    response: &Value,
-    kline_manager: Arc<Mutex<KLineManager>>,
-) -> Result<()> {
+pub async fn handle_amm_message(response: &Value, kline_manager: Arc<KLineManager>) -> Result<()> {
+    // A standby instance leaves all writes to the lock holder
+    if instance_lock::is_read_only() {
+        return Ok(());
+    }
+
     debug!("Processing AMM message: {:#?}", response);
 
-    if let Some(amm_trade_events) = parse_amm_trade_event(response) {
+    let parsed = info_span!("parse").in_scope(|| parse_amm_trade_event(response));
+    if let Some(amm_trade_events) = parsed {
         debug!("Parsed AMM trade events: {:#?}", amm_trade_events);
 
         for event in amm_trade_events {
@@ -100,6 +158,7 @@ pub async fn handle_amm_message(
     } else {
         // Check if contains AMM instruction but parsing failed
         if contains_amm_instruction(response) {
+            metrics::record_parse_failure();
             debug!("Contains AMM instruction but parsing failed");
         } else {
             debug!("No AMM instruction found in message");
@@ -132,10 +191,15 @@ pub async fn handle_amm_message(
     Ok(())
 }
 
+#[instrument(name = "process_amm_trade", skip_all, fields(pool = %amm_trade_event.pool))]
 pub async fn process_amm_trade_event(
     amm_trade_event: AmmTradeEvent,
-    kline_manager: Arc<Mutex<KLineManager>>,
+    kline_manager: Arc<KLineManager>,
 ) -> Result<()> {
+    // Drop events with implausible decoded fields, a sign of event layout drift
+    if !decode_check::accept_amm_trade(&amm_trade_event).await {
+        return Ok(());
+    }
     if let Some(details) = calculate_amm_trade_details(&amm_trade_event) {
         // Skip trades with zero or invalid prices to prevent "low": "0" issues
         if details.price.is_zero() {
@@ -146,7 +210,7 @@ pub async fn process_amm_trade_event(
             return Ok(());
         }
         // Skip micro transactions to keep K-lines clean
-        let min_sol_amount = std::env::var("MIN_SOL_AMOUNT_AMM")
+        let min_sol_amount = remote_config::var("MIN_SOL_AMOUNT_AMM")
             .unwrap_or_else(|_| "0.02".to_string())
             .parse::<Decimal>()
             .unwrap_or_else(|_| Decimal::new(2, 2)); // default 0.02 SOL
@@ -157,23 +221,25 @@ pub async fn process_amm_trade_event(
         ONCE.call_once(|| {
             info!("📊 AMM配置 - 最小SOL金额: {}", min_sol_amount);
         });
-        if details.sol_amount_formatted < min_sol_amount {
-            debug!(
-                "Skipping micro AMM transaction: SOL={}, pool={}",
-                details.sol_amount_formatted, amm_trade_event.pool
-            );
-            return Ok(());
-        }
 
         // For AMM trades, we'll use the pool address as the "mint" for K-line tracking
         let pool_clone = amm_trade_event.pool.clone();
         let timestamp = amm_trade_event.timestamp;
-        let price = details.price;
-        let sol_amount = details.sol_amount_formatted;
-        let token_amount = details.token_amount_formatted;
+        let (base_amount, quote_amount) = amm_trade_event.base_quote_amounts();
+        let base_reserves = amm_trade_event.pool_base_token_reserves;
+        let quote_reserves = amm_trade_event.pool_quote_token_reserves;
         let is_buy = amm_trade_event.is_buy;
-
-        tokio::spawn(async move {
+        let signature = amm_trade_event.signature.clone();
+        let slot = amm_trade_event.slot;
+        let user = amm_trade_event.user.clone();
+        let success = amm_trade_event.success;
+        let raw_lp_fee = amm_trade_event.lp_fee;
+        let raw_protocol_fee = amm_trade_event.protocol_fee;
+        let raw_creator_fee = amm_trade_event.coin_creator_fee;
+
+        let permit = limits::acquire_trade_permit().await;
+        supervisor::spawn_supervised("amm_add_trade", async move {
+            let _permit = permit;
             // get pool data
             let pool_pubkey = match Pubkey::from_str(&pool_clone) {
                 Ok(pubkey) => pubkey,
@@ -182,7 +248,10 @@ pub async fn process_amm_trade_event(
                     return;
                 }
             };
-            let pool_data = match get_amm_pool_cached(pool_pubkey).await {
+            let pool_data = match get_amm_pool_cached(pool_pubkey)
+                .instrument(info_span!("pool_lookup"))
+                .await
+            {
                 Ok(data) => data,
                 Err(e) => {
                     error!("Failed to get pool data for {}: {}", pool_clone, e);
@@ -190,61 +259,177 @@ pub async fn process_amm_trade_event(
                 }
             };
 
-            let mint = match pool_data.get_mint() {
-                Some(mint) => mint,
-                None => {
-                    error!("Failed to get mint from pool data for {}", pool_clone);
+            let pair = pool_data.pair();
+            let mint = pair.mint.clone();
+
+            // Skip mints handled by another shard
+            if !sharding::owns(&mint) {
+                debug!(
+                    "Skipping mint of another shard: {}, pool={}",
+                    mint, pool_clone
+                );
+                return;
+            }
+
+            // Skip mints outside the watchlist in allowlist mode
+            if !watchlist::is_allowed(&mint).await {
+                debug!("Skipping non-watchlist mint: {}, pool={}", mint, pool_clone);
+                return;
+            }
+
+            // Value the quote side in SOL; pools quoted in other tokens are converted
+            let quote = match price_feed::resolve_quote(&pair.quote_mint, &kline_manager)
+                .instrument(info_span!("quote_lookup"))
+                .await
+            {
+                Ok(Some(quote)) => quote,
+                Ok(None) => {
+                    debug!(
+                        "No SOL price for quote token {} yet, skipping trade in pool {}",
+                        pair.quote_mint, pool_clone
+                    );
                     return;
                 }
+                Err(e) => {
+                    error!(
+                        "Failed to resolve quote token {} of pool {}: {}",
+                        pair.quote_mint, pool_clone, e
+                    );
+                    return;
+                }
+            };
+            let (raw_token_amount, raw_quote_amount, raw_quote_reserves) = if pair.mint_is_base {
+                (base_amount, quote_amount, quote_reserves)
+            } else {
+                (quote_amount, base_amount, base_reserves)
             };
 
-            let manager = kline_manager.lock().await;
-            if let Err(e) = manager
-                .add_trade(
-                    &mint,
-                    timestamp,
-                    price,
-                    sol_amount,
-                    token_amount,
-                    is_buy,
-                    true,
-                )
+            let sol_amount = quote.to_sol(raw_quote_amount);
+            // Pool liquidity in SOL, twice the quote-side reserve
+            let liquidity_sol = quote.to_sol(raw_quote_reserves) * Decimal::TWO;
+            if !micro_filter::accept(
+                &mint,
+                timestamp,
+                sol_amount,
+                min_sol_amount,
+                Some(liquidity_sol),
+            ) {
+                debug!(
+                    "Skipping micro AMM transaction: SOL={}, pool={}",
+                    sol_amount, pool_clone
+                );
+                return;
+            }
+
+            // Scale with the mint's real decimals; Token-2022 mints may not use 6
+            let token_decimals = match token_info::get_mint_info_cached(&mint)
+                .instrument(info_span!("mint_info_lookup"))
                 .await
             {
+                Ok(info) => info.decimals,
+                Err(e) => {
+                    warn!(
+                        "Failed to get mint info for {}, assuming {} decimals: {}",
+                        mint,
+                        token_info::DEFAULT_TOKEN_DECIMALS,
+                        e
+                    );
+                    token_info::DEFAULT_TOKEN_DECIMALS
+                }
+            };
+            let token_amount = token_info::ui_amount(raw_token_amount, token_decimals);
+            if token_amount.is_zero() {
+                return;
+            }
+            let price = sol_amount / token_amount;
+            let lp_fee = quote.to_sol(raw_lp_fee);
+            let protocol_fee = quote.to_sol(raw_protocol_fee);
+            let creator_fee = quote.to_sol(raw_creator_fee);
+
+            info!(
+                "{} {} [AMM]: signature= {}, pool= {}, mint= {}, user= {}, SOL= {:.6}, tokens= {:.2}, price= {:.9}, lp_fee= {:.6}, protocol_fee= {:.6}, creator_fee= {:.6}{}, success= {}, time= {}",
+                if is_buy { "🟢" } else { "🔴" },
+                if is_buy { "Buy" } else { "Sell" },
+                signature,
+                pool_clone,
+                mint,
+                user,
+                sol_amount,
+                token_amount,
+                price,
+                lp_fee,
+                protocol_fee,
+                creator_fee,
+                if quote.is_sol() {
+                    String::new()
+                } else {
+                    format!(", quote= {}", quote.mint)
+                },
+                success,
+                // Convert timestamp to readable format
+                chrono::DateTime::from_timestamp(timestamp, 0)
+                    .map(|dt| dt
+                        .with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .to_string())
+                    .unwrap_or_else(|| "Invalid timestamp".to_string())
+            );
+
+            if is_buy {
+                let tracked = wallets::alert_if_tracked_buy(&user, &mint, sol_amount, price).await;
+                if let Err(e) = tracked {
+                    error!("Tracked wallet alert failed: {}", e);
+                }
+            }
+
+            if let Err(e) = fees::record_fees(
+                &pool_clone,
+                timestamp,
+                lp_fee,
+                protocol_fee,
+                creator_fee,
+                sol_amount,
+            )
+            .await
+            {
+                error!("Fee stats update failed: {}", e);
+            }
+
+            let trade = TradeRecord {
+                signature,
+                slot,
+                mint,
+                user,
+                is_buy,
+                price,
+                sol_amount,
+                token_amount,
+                timestamp,
+                source: Some(TradeSource::Amm),
+                liquidity_sol: Some(liquidity_sol),
+            };
+            if let Err(e) = kline_manager.add_trade_record(&trade, true).await {
                 error!("K-line update failed: {}", e);
             }
+            if let Err(e) = price_alerts::check_trade(&trade).await {
+                error!("Price alert check failed: {}", e);
+            }
+            if let Err(e) = window_alerts::check_trade(&trade).await {
+                error!("Sliding-window alert check failed: {}", e);
+            }
+            if let Err(e) = funnel::record_trade(
+                &trade.mint,
+                trade.price,
+                trade.sol_amount,
+                trade.timestamp,
+                None,
+                true,
+            )
+            .await
+            {
+                error!("Launch funnel update failed: {}", e);
+            }
         });
-
-        info!(
-            "{} {} [AMM]: signature= {}, pool= {}, user= {}, SOL= {:.6}, tokens= {:.2}, price= {:.9}, lp_fee= {:.6}, protocol_fee= {:.6}, creator_fee= {:.6}, success= {}, time= {}",
-            if amm_trade_event.is_buy {
-                "🟢"
-            } else {
-                "🔴"
-            },
-            if amm_trade_event.is_buy {
-                "Buy"
-            } else {
-                "Sell"
-            },
-            amm_trade_event.signature,
-            amm_trade_event.pool,
-            amm_trade_event.user,
-            details.sol_amount_formatted,
-            details.token_amount_formatted,
-            details.price,
-            details.lp_fee_formatted,
-            details.protocol_fee_formatted,
-            details.creator_fee_formatted,
-            amm_trade_event.success,
-            // Convert timestamp to readable format
-            chrono::DateTime::from_timestamp(amm_trade_event.timestamp, 0)
-                .map(|dt| dt
-                    .with_timezone(&chrono::Local)
-                    .format("%Y-%m-%d %H:%M:%S")
-                    .to_string())
-                .unwrap_or_else(|| "Invalid timestamp".to_string())
-        );
     } else {
         info!("🟡 AMM Trade detected: {:#?}", amm_trade_event);
     }
@@ -375,137 +560,38 @@ pub fn decode_and_parse_amm_program_data(
     let decoded = general_purpose::STANDARD.decode(program_data).ok()?;
     debug!("Decoded AMM program data length: {}", decoded.len());
 
-    if decoded.len() < 200 {
-        warn!("AMM program data too short: {} bytes", decoded.len());
-        return None;
-    }
-
-    // Skip first 8 bytes of event identifier
-    let _event_type = &decoded[..8];
-    let mut pos = 8;
-
-    // Read timestamp (8 bytes)
-    let timestamp = {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&decoded[pos..pos + 8]);
-        u64::from_le_bytes(bytes) as i64
-    };
-    pos += 8;
-
-    // Read second field (8 bytes) - either baseAmountOut (buy) or baseAmountIn (sell)
-    let base_amount = {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&decoded[pos..pos + 8]);
-        u64::from_le_bytes(bytes)
-    };
-    pos += 8;
-
-    // Read third field (8 bytes) - either maxQuoteAmountIn (buy) or minQuoteAmountOut (sell)
-    let quote_limit = {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&decoded[pos..pos + 8]);
-        u64::from_le_bytes(bytes)
-    };
-    pos += 8;
-
-    // Skip userBaseTokenReserves and userQuoteTokenReserves (16 bytes)
-    pos += 16;
-
-    // Read poolBaseTokenReserves (8 bytes)
-    let pool_base_token_reserves = {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&decoded[pos..pos + 8]);
-        u64::from_le_bytes(bytes)
-    };
-    pos += 8;
-
-    // Read poolQuoteTokenReserves (8 bytes)
-    let pool_quote_token_reserves = {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&decoded[pos..pos + 8]);
-        u64::from_le_bytes(bytes)
+    // Buy and sell events in whichever layout version they were logged; other
+    // PumpSwap events such as deposits are skipped
+    let event = event_layout::decode(
+        &decoded,
+        &[&event_layout::AMM_BUY_EVENT, &event_layout::AMM_SELL_EVENT],
+    )?;
+    let (base_field, quote_field) = if event.layout.event == "BuyEvent" {
+        ("base_amount_out", "quote_amount_in")
+    } else {
+        ("base_amount_in", "quote_amount_out")
     };
-    pos += 8;
 
-    // Read actual amount field (8 bytes) - either quoteAmountIn (buy) or quoteAmountOut (sell)
-    let quote_amount = {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&decoded[pos..pos + 8]);
-        u64::from_le_bytes(bytes)
-    };
+    let timestamp = event.i64("timestamp")?;
+    let base_amount = event.u64(base_field)?;
+    let quote_amount = event.u64(quote_field)?;
+    let pool_base_token_reserves = event.u64("pool_base_token_reserves")?;
+    let pool_quote_token_reserves = event.u64("pool_quote_token_reserves")?;
     let (sol_amount, token_amount) = if pool_base_token_reserves > pool_quote_token_reserves {
         (quote_amount, base_amount)
     } else {
         (base_amount, quote_amount)
     };
-
-    pos += 8;
-
-    // Skip lpFeeBasisPoints (8 bytes)
-    pos += 8;
-
-    // Read lpFee (8 bytes)
-    let lp_fee = {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&decoded[pos..pos + 8]);
-        u64::from_le_bytes(bytes)
-    };
-    pos += 8;
-
-    // Skip protocolFeeBasisPoints (8 bytes)
-    pos += 8;
-
-    // Read protocolFee (8 bytes)
-    let protocol_fee = {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&decoded[pos..pos + 8]);
-        u64::from_le_bytes(bytes)
-    };
-    pos += 8;
-
-    // Skip intermediate fields (16 bytes)
-    pos += 16;
-
-    // Read pool address (32 bytes)
-    let pool_bytes = &decoded[pos..pos + 32];
-    let pool = bs58::encode(pool_bytes).into_string();
-    pos += 32;
-
-    // Read user address (32 bytes)
-    let user_bytes = &decoded[pos..pos + 32];
-    let user = bs58::encode(user_bytes).into_string();
-    pos += 32;
-
-    // Skip userBaseTokenAccount (32 bytes)
-    pos += 32;
-
-    // Skip userQuoteTokenAccount (32 bytes)
-    pos += 32;
-
-    // Skip protocolFeeRecipient (32 bytes)
-    pos += 32;
-
-    // Skip protocolFeeRecipientTokenAccount (32 bytes)
-    pos += 32;
-
-    // Skip coinCreator (32 bytes)
-    pos += 32;
-
-    // Skip coinCreatorFeeBasisPoints (8 bytes)
-    pos += 8;
-
-    // Read coinCreatorFee (8 bytes)
-    let coin_creator_fee = if pos + 8 <= decoded.len() {
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&decoded[pos..pos + 8]);
-        u64::from_le_bytes(bytes)
-    } else {
-        0
-    };
+    let lp_fee = event.u64("lp_fee")?;
+    let protocol_fee = event.u64("protocol_fee")?;
+    let pool = event.pubkey("pool")?;
+    let user = event.pubkey("user")?;
+    // Events before creator fees have none
+    let coin_creator_fee = event.u64("coin_creator_fee").unwrap_or(0);
 
     debug!(
-        "Decoded AMM TradeEvent: pool={}, user={}, token_amount={}, sol_amount={}, timestamp={}, quote_limit={}",
-        pool, user, token_amount, sol_amount, timestamp, quote_limit
+        "Decoded AMM {} v{}: pool={}, user={}, token_amount={}, sol_amount={}, timestamp={}",
+        event.layout.event, event.layout.version, pool, user, token_amount, sol_amount, timestamp
     );
 
     Some((
@@ -544,6 +630,12 @@ pub fn calculate_amm_trade_details(amm_trade_event: &AmmTradeEvent) -> Option<Am
         Decimal::ZERO
     };
 
+    // The SOL side is the smaller raw reserve, as when decoding the amounts
+    let sol_reserve = amm_trade_event
+        .pool_base_token_reserves
+        .min(amm_trade_event.pool_quote_token_reserves);
+    let liquidity_sol = Decimal::from(sol_reserve) / sol_divisor * Decimal::TWO;
+
     Some(AmmTradeDetails {
         sol_amount_formatted,
         token_amount_formatted,
@@ -553,6 +645,7 @@ pub fn calculate_amm_trade_details(amm_trade_event: &AmmTradeEvent) -> Option<Am
         protocol_fee_formatted,
         creator_fee_formatted,
         price,
+        liquidity_sol,
     })
 }
 
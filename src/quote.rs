@@ -0,0 +1,130 @@
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::kline::{KLineData, KLineManager};
+
+// Pump tokens have a fixed supply of one billion
+pub const TOKEN_SUPPLY: i64 = 1_000_000_000;
+
+// A 1h window holds at most 60 candles, one more reaches the price before it
+const QUOTE_CANDLES: usize = 61;
+
+// Price change and SOL volume over a trailing window
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowChange {
+    pub change_pct: Option<Decimal>,
+    pub volume_sol: Decimal,
+}
+
+// Quick price snapshot of a mint built from its stored candles
+#[derive(Debug, Clone, Serialize)]
+pub struct PriceQuote {
+    pub mint: String,
+    pub price: Decimal,
+    pub market_cap_sol: Decimal,
+    pub change_5m: WindowChange,
+    pub change_1h: WindowChange,
+    pub last_trade_at: i64,
+}
+
+fn decimal(value: &str) -> Decimal {
+    value.parse::<Decimal>().unwrap_or(Decimal::ZERO)
+}
+
+// Change against the price when the window opened and the volume traded in it.
+// The window covers the current minute and the minutes before it, so 5m means the
+// current candle plus the four before. Without an earlier candle the first open in
+// the window is the reference.
+pub fn window_change(klines: &[KLineData], now: i64, window_secs: i64) -> WindowChange {
+    let window_start = KLineManager::get_minute_timestamp(now) - window_secs + 60;
+    let reference = klines
+        .iter()
+        .rev()
+        .find(|k| k.timestamp < window_start)
+        .map(|k| decimal(&k.close))
+        .or_else(|| {
+            klines
+                .iter()
+                .find(|k| k.timestamp >= window_start)
+                .map(|k| decimal(&k.open))
+        });
+    let price = klines.last().map(|k| decimal(&k.close));
+    let change_pct = match (reference, price) {
+        (Some(reference), Some(price)) if !reference.is_zero() => {
+            Some(((price - reference) / reference * Decimal::ONE_HUNDRED).round_dp(2))
+        }
+        _ => None,
+    };
+    let volume_sol = klines
+        .iter()
+        .filter(|k| k.timestamp >= window_start)
+        .map(|k| decimal(&k.volume_sol))
+        .sum();
+    WindowChange {
+        change_pct,
+        volume_sol,
+    }
+}
+
+// Snapshot of candles sorted oldest first, None when there are none
+pub fn build_quote(mint: &str, klines: &[KLineData], now: i64) -> Option<PriceQuote> {
+    let latest = klines.last()?;
+    let price = decimal(&latest.close);
+    Some(PriceQuote {
+        mint: mint.to_string(),
+        price,
+        market_cap_sol: (price * Decimal::new(TOKEN_SUPPLY, 0)).round_dp(2),
+        change_5m: window_change(klines, now, 300),
+        change_1h: window_change(klines, now, 3600),
+        last_trade_at: if latest.last_update > 0 {
+            latest.last_update as i64
+        } else {
+            latest.timestamp
+        },
+    })
+}
+
+// Read the latest candles of a mint from Redis and summarize them
+pub async fn get_quote(kline_manager: &KLineManager, mint: &str) -> Result<Option<PriceQuote>> {
+    let klines = kline_manager
+        .get_klines_for_mint(mint, 60, Some(QUOTE_CANDLES))
+        .await?;
+    Ok(build_quote(mint, &klines, chrono::Utc::now().timestamp()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::candle;
+
+    fn kline(timestamp: i64, open: &str, close: &str, volume: &str) -> KLineData {
+        KLineData {
+            open: open.to_string(),
+            low: open.to_string(),
+            volume_sol: volume.to_string(),
+            ..candle(timestamp, close)
+        }
+    }
+
+    #[test]
+    fn test_build_quote() {
+        let now = 7_230;
+        let klines = vec![
+            kline(3_600, "0.00000001", "0.00000002", "1"),
+            kline(6_900, "0.00000002", "0.00000004", "2"),
+            kline(7_200, "0.00000004", "0.00000005", "3"),
+        ];
+        let quote = build_quote("mint", &klines, now).unwrap();
+        assert_eq!(quote.market_cap_sol, Decimal::new(50, 0));
+        // 5m window starts at 6_960, the reference is the close of the 6_900 candle
+        assert_eq!(quote.change_5m.change_pct, Some(Decimal::new(25, 0)));
+        assert_eq!(quote.change_5m.volume_sol, Decimal::new(3, 0));
+        // 1h window starts at 3_660, the reference is the close of the 3_600 candle
+        assert_eq!(quote.change_1h.change_pct, Some(Decimal::new(150, 0)));
+        assert_eq!(quote.change_1h.volume_sol, Decimal::new(5, 0));
+        assert_eq!(quote.last_trade_at, 7_200);
+
+        assert!(build_quote("mint", &[], now).is_none());
+    }
+}
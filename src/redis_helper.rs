@@ -11,10 +11,14 @@ static REDIS_POOL: OnceCell<Arc<Mutex<ConnectionManager>>> = OnceCell::const_new
 pub async fn init_pool() -> Result<()> {
     let redis_url =
         std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379/".to_string());
+    init_pool_with_url(&redis_url).await
+}
 
+/// Initialize the global Redis connection pool for the given URL
+pub async fn init_pool_with_url(redis_url: &str) -> Result<()> {
     info!("Initializing Redis connection pool: {}", redis_url);
 
-    let client = Client::open(redis_url.as_str()).context("Failed to create Redis client")?;
+    let client = Client::open(redis_url).context("Failed to create Redis client")?;
     let connection_manager = client
         .get_connection_manager()
         .await
@@ -36,6 +40,12 @@ pub async fn get_connection() -> Result<tokio::sync::MutexGuard<'static, Connect
     Ok(pool.lock().await)
 }
 
+/// Clone the pooled connection for work that shouldn't hold the pool lock.
+/// The connection is multiplexed, so commands on clones run concurrently.
+pub async fn clone_connection() -> Result<ConnectionManager> {
+    Ok(get_connection().await?.clone())
+}
+
 /// Set key-value pair
 pub async fn set<K, V>(key: K, value: V) -> Result<()>
 where
@@ -92,6 +102,32 @@ where
     Ok(())
 }
 
+/// Find the keys matching a pattern with SCAN, which unlike KEYS doesn't block
+/// Redis while walking a large keyspace
+pub async fn scan_keys(con: &mut ConnectionManager, pattern: &str) -> Result<Vec<String>> {
+    let mut keys = Vec::new();
+    let mut cursor: u64 = 0;
+    loop {
+        let (next, batch): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(pattern)
+            .arg("COUNT")
+            .arg(1000)
+            .query_async(con)
+            .await?;
+        keys.extend(batch);
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+    // SCAN may return a key more than once
+    keys.sort();
+    keys.dedup();
+    Ok(keys)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
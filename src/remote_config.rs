@@ -0,0 +1,380 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env::VarError;
+use std::sync::{LazyLock, RwLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::kline;
+use crate::redis_helper;
+use crate::supervisor;
+
+// Redis hash holding the runtime overrides shared by every service
+const CONFIG_KEY: &str = "config:settings";
+
+/// 可以在运行时通过Redis覆盖的配置项（过滤条件、阈值、开关）。
+/// 连接地址和凭据等只在启动时读取的配置不在此列表中。
+pub const TUNABLE_SETTINGS: &[&str] = &[
+    "MIN_SOL_AMOUNT_PUMP",
+    "MIN_SOL_AMOUNT_AMM",
+    "MICRO_TRADE_VOLUME_PCT",
+    "MICRO_TRADE_LIQUIDITY_PCT",
+    "MICRO_TRADE_WINDOW_SECS",
+    "MICRO_TRADE_FLOOR_SOL",
+    "MICRO_TRADE_CAP_SOL",
+    "WATCHLIST_ONLY",
+    "NOTIFICATION_ENABLED",
+    "NOTIFICATION_COOLDOWN_SECONDS",
+    "NOTIFICATION_DIGEST_THRESHOLD",
+    "TASK_FAILURE_NOTIFY",
+    "RULE_COOLDOWN_SECS",
+    "STRATEGY_RISING_LOOKBACK",
+    "STRATEGY_RISING_INTERVAL",
+    "STRATEGY_RISING_MIN_TURNOVER",
+    "STRATEGY_RISING_COOLDOWN_SECS",
+    "STRATEGY_HOLDERS_MIN_GROWTH",
+    "STRATEGY_HOLDERS_WINDOW_SECS",
+    "STRATEGY_HOLDERS_MIN_NET_FLOW_SOL",
+    "STRATEGY_HOLDERS_COOLDOWN_SECS",
+    "STRATEGY_EXHAUSTION_ENABLED",
+    "STRATEGY_EXHAUSTION_RED_COUNT",
+    "STRATEGY_EXHAUSTION_MIN_DECLINE",
+    "STRATEGY_EXHAUSTION_MIN_GAIN",
+    "STRATEGY_EXHAUSTION_INTERVAL",
+    "STRATEGY_EXHAUSTION_COOLDOWN_SECS",
+    "STRATEGY_UNIVERSE_MAX_IDLE_SECS",
+    "STRATEGY_UNIVERSE_MIN_CANDLES",
+    "STRATEGY_UNIVERSE_MIN_VOLUME_SOL",
+    "STRATEGY_MAX_BETA",
+    "MARKET_BETA_WINDOW_MINUTES",
+    "MARKET_BETA_MIN_POINTS",
+    "LAUNCH_ALERT_ENABLED",
+    "LAUNCH_FIRST_BUYERS",
+    "LAUNCH_BUNDLE_SLOTS",
+    "LAUNCH_INSIDER_ALERT_PCT",
+    "SNIPER_CLUSTER_MIN_WALLETS",
+    "CLUSTER_ANALYSIS_ENABLED",
+    "DECODE_MAX_SOL_AMOUNT",
+    "DECODE_MAX_CLOCK_SKEW_SECS",
+    "DECODE_DRIFT_ALERT_RATIO",
+    "ANOMALY_ALERT_SCORE",
+    "ANOMALY_ALERT_COOLDOWN_SECS",
+    "WINDOW_ALERT_PCT",
+    "WINDOW_ALERT_SECS",
+    "WINDOW_ALERT_COOLDOWN_SECS",
+    "CURVE_PHASE_ENABLED",
+    "CURVE_PHASE_MILESTONES",
+    "CURVE_PHASE_WINDOW_SECS",
+    "CURVE_PHASE_MIN_ACCELERATION",
+    "CURVE_PHASE_MIN_BUY_SOL",
+    "FEED_GAP_DROP_RATIO",
+    "FEED_GAP_SILENCE_SECS",
+    "FEED_GAP_MIN_RATE",
+    "SMART_MONEY_MIN_MINTS",
+    "SMART_MONEY_MIN_WIN_RATE",
+    "SPAM_DETECTION_ENABLED",
+    "SPAM_REPEAT_THRESHOLD",
+];
+
+// Type and range a setting's value must have
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SettingKind {
+    Bool,
+    // Non-negative integer: counts, seconds, sizes
+    Count,
+    // Non-negative decimal: amounts, thresholds, scores
+    Amount,
+    // Decimal between 0 and 100
+    Percent,
+    // Decimal between 0 and 1
+    Ratio,
+    // Any decimal
+    Number,
+    // Candle interval: 1m or one of KLINE_ROLLUP_INTERVALS
+    Interval,
+    // Comma-separated percentages between 0 and 100
+    Percentages,
+}
+
+// Every name in TUNABLE_SETTINGS must be listed here; None means it was missed
+fn setting_kind(name: &str) -> Option<SettingKind> {
+    Some(match name {
+        "WATCHLIST_ONLY"
+        | "NOTIFICATION_ENABLED"
+        | "TASK_FAILURE_NOTIFY"
+        | "STRATEGY_EXHAUSTION_ENABLED"
+        | "LAUNCH_ALERT_ENABLED"
+        | "CLUSTER_ANALYSIS_ENABLED"
+        | "CURVE_PHASE_ENABLED"
+        | "SPAM_DETECTION_ENABLED" => SettingKind::Bool,
+        "MICRO_TRADE_WINDOW_SECS"
+        | "NOTIFICATION_COOLDOWN_SECONDS"
+        | "NOTIFICATION_DIGEST_THRESHOLD"
+        | "RULE_COOLDOWN_SECS"
+        | "STRATEGY_RISING_LOOKBACK"
+        | "STRATEGY_RISING_COOLDOWN_SECS"
+        | "STRATEGY_HOLDERS_WINDOW_SECS"
+        | "STRATEGY_HOLDERS_COOLDOWN_SECS"
+        | "STRATEGY_EXHAUSTION_RED_COUNT"
+        | "STRATEGY_EXHAUSTION_COOLDOWN_SECS"
+        | "STRATEGY_UNIVERSE_MAX_IDLE_SECS"
+        | "STRATEGY_UNIVERSE_MIN_CANDLES"
+        | "MARKET_BETA_WINDOW_MINUTES"
+        | "MARKET_BETA_MIN_POINTS"
+        | "LAUNCH_FIRST_BUYERS"
+        | "LAUNCH_BUNDLE_SLOTS"
+        | "SNIPER_CLUSTER_MIN_WALLETS"
+        | "DECODE_MAX_SOL_AMOUNT"
+        | "DECODE_MAX_CLOCK_SKEW_SECS"
+        | "ANOMALY_ALERT_COOLDOWN_SECS"
+        | "WINDOW_ALERT_SECS"
+        | "WINDOW_ALERT_COOLDOWN_SECS"
+        | "CURVE_PHASE_WINDOW_SECS"
+        | "FEED_GAP_SILENCE_SECS"
+        | "SMART_MONEY_MIN_MINTS"
+        | "SPAM_REPEAT_THRESHOLD" => SettingKind::Count,
+        "MICRO_TRADE_VOLUME_PCT"
+        | "MICRO_TRADE_LIQUIDITY_PCT"
+        | "STRATEGY_EXHAUSTION_MIN_DECLINE"
+        | "LAUNCH_INSIDER_ALERT_PCT" => SettingKind::Percent,
+        "DECODE_DRIFT_ALERT_RATIO" | "FEED_GAP_DROP_RATIO" | "SMART_MONEY_MIN_WIN_RATE" => {
+            SettingKind::Ratio
+        }
+        "STRATEGY_MAX_BETA" | "STRATEGY_HOLDERS_MIN_NET_FLOW_SOL" => SettingKind::Number,
+        "STRATEGY_RISING_INTERVAL" | "STRATEGY_EXHAUSTION_INTERVAL" => SettingKind::Interval,
+        "CURVE_PHASE_MILESTONES" => SettingKind::Percentages,
+        "MIN_SOL_AMOUNT_PUMP"
+        | "MIN_SOL_AMOUNT_AMM"
+        | "MICRO_TRADE_FLOOR_SOL"
+        | "MICRO_TRADE_CAP_SOL"
+        | "STRATEGY_RISING_MIN_TURNOVER"
+        | "STRATEGY_HOLDERS_MIN_GROWTH"
+        | "STRATEGY_EXHAUSTION_MIN_GAIN"
+        | "STRATEGY_UNIVERSE_MIN_VOLUME_SOL"
+        | "ANOMALY_ALERT_SCORE"
+        | "WINDOW_ALERT_PCT"
+        | "CURVE_PHASE_MIN_ACCELERATION"
+        | "CURVE_PHASE_MIN_BUY_SOL"
+        | "FEED_GAP_MIN_RATE" => SettingKind::Amount,
+        _ => return None,
+    })
+}
+
+// Check a value against the type and range of its setting
+fn validate_value(kind: SettingKind, value: &str) -> Result<(), String> {
+    let decimal = |value: &str| {
+        value
+            .parse::<Decimal>()
+            .map_err(|_| format!("{} is not a number", value))
+    };
+    let in_range = |value: &str, max: Decimal| {
+        let number = decimal(value)?;
+        if number < Decimal::ZERO || number > max {
+            return Err(format!("{} is not between 0 and {}", value, max));
+        }
+        Ok(())
+    };
+    match kind {
+        SettingKind::Bool => value
+            .parse::<bool>()
+            .map(|_| ())
+            .map_err(|_| format!("{} is not true or false", value)),
+        SettingKind::Count => value
+            .parse::<u64>()
+            .map(|_| ())
+            .map_err(|_| format!("{} is not a non-negative integer", value)),
+        SettingKind::Amount => match decimal(value)? {
+            number if number < Decimal::ZERO => Err(format!("{} is negative", value)),
+            _ => Ok(()),
+        },
+        SettingKind::Percent => in_range(value, Decimal::ONE_HUNDRED),
+        SettingKind::Ratio => in_range(value, Decimal::ONE),
+        SettingKind::Number => decimal(value).map(|_| ()),
+        SettingKind::Interval => match crate::parse_window(value) {
+            Some(60) => Ok(()),
+            Some(secs) if kline::rollup_intervals().contains(&secs) => Ok(()),
+            _ => Err(format!(
+                "{} is not 1m or one of KLINE_ROLLUP_INTERVALS",
+                value
+            )),
+        },
+        SettingKind::Percentages => value
+            .split(',')
+            .try_for_each(|part| in_range(part.trim(), Decimal::ONE_HUNDRED)),
+    }
+}
+
+/// 配置项及其当前生效的值
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigSetting {
+    pub name: String,
+    /// 生效的值，未覆盖且未设置环境变量时为空（使用代码默认值）
+    pub value: Option<String>,
+    /// 是否被Redis中的覆盖值设置
+    pub overridden: bool,
+}
+
+// In-memory copy of the overrides, refreshed in the background so reads stay synchronous
+static OVERRIDES: LazyLock<RwLock<HashMap<String, String>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+// How often each service reloads the overrides from Redis
+fn refresh_interval() -> Duration {
+    Duration::from_secs(
+        std::env::var("REMOTE_CONFIG_REFRESH_SECS")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse()
+            .unwrap_or(5),
+    )
+}
+
+/// 配置项的当前值：运行时覆盖值优先，否则读取环境变量。
+/// 用法与`std::env::var`相同
+pub fn var(name: &str) -> Result<String, VarError> {
+    let overrides = OVERRIDES.read().unwrap_or_else(|e| e.into_inner());
+    resolve(&overrides, name)
+}
+
+fn resolve(overrides: &HashMap<String, String>, name: &str) -> Result<String, VarError> {
+    match overrides.get(name) {
+        Some(value) => Ok(value.clone()),
+        None => std::env::var(name),
+    }
+}
+
+/// 校验配置项名称和值
+pub fn validate_setting(name: &str, value: &str) -> Result<()> {
+    if !TUNABLE_SETTINGS.contains(&name) {
+        anyhow::bail!("{} is not a runtime-tunable setting", name);
+    }
+    if value.trim().is_empty() {
+        anyhow::bail!("Value of {} must not be empty", name);
+    }
+    let Some(kind) = setting_kind(name) else {
+        anyhow::bail!("{} has no value type", name);
+    };
+    validate_value(kind, value.trim())
+        .map_err(|e| anyhow::anyhow!("Invalid value of {}: {}", name, e))
+}
+
+/// 读取Redis中的全部覆盖值
+pub async fn get_overrides() -> Result<HashMap<String, String>> {
+    let mut con = redis_helper::get_connection().await?;
+    let overrides: HashMap<String, String> = con.hgetall(CONFIG_KEY).await?;
+    Ok(overrides)
+}
+
+/// 所有可调配置项及其生效值，覆盖值直接从Redis读取
+pub async fn get_settings() -> Result<Vec<ConfigSetting>> {
+    let overrides = get_overrides().await?;
+    Ok(TUNABLE_SETTINGS
+        .iter()
+        .map(|name| ConfigSetting {
+            name: name.to_string(),
+            value: resolve(&overrides, name).ok(),
+            overridden: overrides.contains_key(*name),
+        })
+        .collect())
+}
+
+/// 设置覆盖值，立即对本进程生效，其他服务在下次刷新时生效
+pub async fn set_overrides(settings: &HashMap<String, String>) -> Result<()> {
+    for (name, value) in settings {
+        validate_setting(name, value)?;
+    }
+    if settings.is_empty() {
+        return Ok(());
+    }
+    let items: Vec<(&String, String)> = settings
+        .iter()
+        .map(|(name, value)| (name, value.trim().to_string()))
+        .collect();
+    {
+        let mut con = redis_helper::get_connection().await?;
+        let _: () = con.hset_multiple(CONFIG_KEY, &items).await?;
+    }
+    refresh().await
+}
+
+/// 删除覆盖值，恢复使用环境变量，返回是否存在
+pub async fn remove_override(name: &str) -> Result<bool> {
+    let removed: usize = {
+        let mut con = redis_helper::get_connection().await?;
+        con.hdel(CONFIG_KEY, name).await?
+    };
+    refresh().await?;
+    Ok(removed > 0)
+}
+
+// Reload the overrides from Redis into the in-memory copy
+async fn refresh() -> Result<()> {
+    let loaded = get_overrides().await?;
+    let mut overrides = OVERRIDES.write().unwrap_or_else(|e| e.into_inner());
+    if *overrides != loaded {
+        info!("⚙️ Runtime config overrides loaded: {:?}", loaded);
+        *overrides = loaded;
+    }
+    Ok(())
+}
+
+/// 加载运行时配置并在后台定期刷新
+pub async fn start() {
+    if let Err(e) = refresh().await {
+        warn!("Failed to load runtime config: {}", e);
+    }
+    supervisor::spawn_supervised("remote_config_refresh", async {
+        loop {
+            tokio::time::sleep(refresh_interval()).await;
+            if let Err(e) = refresh().await {
+                warn!("Failed to refresh runtime config: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_and_resolve() {
+        assert!(validate_setting("MIN_SOL_AMOUNT_PUMP", "0.5").is_ok());
+        assert!(validate_setting("MIN_SOL_AMOUNT_PUMP", " ").is_err());
+        assert!(validate_setting("REDIS_URL", "redis://other/").is_err());
+
+        // Values must match the setting's type and range
+        assert!(validate_setting("WATCHLIST_ONLY", "true").is_ok());
+        assert!(validate_setting("WATCHLIST_ONLY", "yes").is_err());
+        assert!(validate_setting("RULE_COOLDOWN_SECS", "300").is_ok());
+        assert!(validate_setting("RULE_COOLDOWN_SECS", "-1").is_err());
+        assert!(validate_setting("RULE_COOLDOWN_SECS", "1.5").is_err());
+        assert!(validate_setting("MIN_SOL_AMOUNT_PUMP", "abc").is_err());
+        assert!(validate_setting("MIN_SOL_AMOUNT_PUMP", "-0.1").is_err());
+        assert!(validate_setting("LAUNCH_INSIDER_ALERT_PCT", "101").is_err());
+        assert!(validate_setting("FEED_GAP_DROP_RATIO", "0.3").is_ok());
+        assert!(validate_setting("FEED_GAP_DROP_RATIO", "2").is_err());
+        assert!(validate_setting("STRATEGY_MAX_BETA", "-0.5").is_ok());
+        assert!(validate_setting("STRATEGY_RISING_INTERVAL", "1m").is_ok());
+        assert!(validate_setting("STRATEGY_RISING_INTERVAL", "7m").is_err());
+        assert!(validate_setting("CURVE_PHASE_MILESTONES", "25, 50,75").is_ok());
+        assert!(validate_setting("CURVE_PHASE_MILESTONES", "25,150").is_err());
+
+        let overrides: HashMap<String, String> =
+            [("PUMP_KMONITOR_TEST_SETTING".to_string(), "2".to_string())].into();
+        assert_eq!(
+            resolve(&overrides, "PUMP_KMONITOR_TEST_SETTING").as_deref(),
+            Ok("2")
+        );
+        assert!(resolve(&HashMap::new(), "PUMP_KMONITOR_TEST_SETTING").is_err());
+    }
+
+    #[test]
+    fn test_every_setting_has_kind() {
+        for name in TUNABLE_SETTINGS {
+            assert!(setting_kind(name).is_some(), "{} has no value type", name);
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::kline::KLineData;
+use crate::localized;
+use crate::notification_format::NotificationFormat;
+use crate::redis_helper;
+use crate::remote_config;
+
+// Set of mints that have at least one custom rule
+const RULE_MINTS_KEY: &str = "rules:mints";
+
+/// 自定义规则条件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RuleCondition {
+    /// 价格高于
+    PriceAbove { value: Decimal },
+    /// 价格低于
+    PriceBelow { value: Decimal },
+    /// 市值高于（SOL）
+    MarketCapAbove { value: Decimal },
+    /// 最近N分钟净流入高于（SOL）
+    NetFlowAbove {
+        value: Decimal,
+        window_minutes: usize,
+    },
+    /// 最近N分钟换手率高于（成交量/池子流动性，仅AMM阶段）
+    TurnoverAbove {
+        value: Decimal,
+        window_minutes: usize,
+    },
+}
+
+impl RuleCondition {
+    /// 评估规则所需的K线数量
+    pub fn required_lookback(&self) -> usize {
+        match self {
+            RuleCondition::NetFlowAbove { window_minutes, .. }
+            | RuleCondition::TurnoverAbove { window_minutes, .. } => *window_minutes,
+            _ => 1,
+        }
+    }
+
+    /// 评估条件，满足时返回按通知格式生成的描述信息
+    pub fn evaluate(&self, klines: &[KLineData], format: &NotificationFormat) -> Option<String> {
+        let last = klines.last()?;
+        let price: Decimal = last.close.parse().ok()?;
+        let language = format.language;
+        match self {
+            RuleCondition::PriceAbove { value } => (price > *value).then(|| {
+                let (price, value) = (format.price(price), format.price(*value));
+                localized!(
+                    language,
+                    "价格 {} 高于 {}",
+                    "price {} above {}",
+                    price,
+                    value
+                )
+            }),
+            RuleCondition::PriceBelow { value } => (price < *value).then(|| {
+                let (price, value) = (format.price(price), format.price(*value));
+                localized!(
+                    language,
+                    "价格 {} 低于 {}",
+                    "price {} below {}",
+                    price,
+                    value
+                )
+            }),
+            RuleCondition::MarketCapAbove { value } => {
+                let market_cap = price * Decimal::new(1_000_000_000, 0);
+                (market_cap > *value).then(|| {
+                    let (market_cap, value) = (format.amount(market_cap), format.amount(*value));
+                    localized!(
+                        language,
+                        "市值 {} 高于 {}",
+                        "market cap {} above {}",
+                        market_cap,
+                        value
+                    )
+                })
+            }
+            RuleCondition::NetFlowAbove {
+                value,
+                window_minutes,
+            } => {
+                let start = klines.len().saturating_sub(*window_minutes);
+                let net_flow: Decimal = klines[start..]
+                    .iter()
+                    .filter_map(|k| k.net_flow_sol.parse::<Decimal>().ok())
+                    .sum();
+                (net_flow > *value).then(|| {
+                    let (net_flow, value) = (format.amount(net_flow), format.amount(*value));
+                    localized!(
+                        language,
+                        "{}分钟净流入 {} 高于 {}",
+                        "{}-minute net flow {} above {}",
+                        window_minutes,
+                        net_flow,
+                        value
+                    )
+                })
+            }
+            RuleCondition::TurnoverAbove {
+                value,
+                window_minutes,
+            } => {
+                let start = klines.len().saturating_sub(*window_minutes);
+                let window = &klines[start..];
+                let liquidity: Decimal = window
+                    .iter()
+                    .rev()
+                    .find_map(|k| k.liquidity_sol.as_deref()?.parse().ok())
+                    .filter(|l: &Decimal| *l > Decimal::ZERO)?;
+                let volume: Decimal = window
+                    .iter()
+                    .filter_map(|k| k.volume_sol.parse::<Decimal>().ok())
+                    .sum();
+                let turnover = volume / liquidity;
+                (turnover > *value).then(|| {
+                    localized!(
+                        language,
+                        "{}分钟换手率 {:.4} 高于 {}（池子流动性 {}）",
+                        "{}-minute turnover {:.4} above {} (pool liquidity {})",
+                        window_minutes,
+                        turnover,
+                        value,
+                        format.amount(liquidity)
+                    )
+                })
+            }
+        }
+    }
+}
+
+/// 绑定到单个mint的自定义告警规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MintRule {
+    pub id: String,
+    pub mint: String,
+    pub condition: RuleCondition,
+    pub note: Option<String>,
+    pub created_at: i64,
+    pub last_triggered_at: Option<i64>,
+}
+
+// Generate Redis key for the rules of a mint
+fn get_rules_key(mint: &str) -> String {
+    format!("rules:{}", mint)
+}
+
+/// 规则触发冷却时间（秒）
+pub fn rule_cooldown_secs() -> i64 {
+    remote_config::var("RULE_COOLDOWN_SECS")
+        .unwrap_or_else(|_| "600".to_string())
+        .parse()
+        .unwrap_or(600)
+}
+
+/// 添加规则
+pub async fn add_rule(
+    mint: &str,
+    condition: RuleCondition,
+    note: Option<String>,
+) -> Result<MintRule> {
+    let rule = MintRule {
+        id: format!("{:x}", rand::random::<u64>()),
+        mint: mint.to_string(),
+        condition,
+        note,
+        created_at: chrono::Utc::now().timestamp(),
+        last_triggered_at: None,
+    };
+    save_rule(&rule).await?;
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = con.sadd(RULE_MINTS_KEY, mint).await?;
+    Ok(rule)
+}
+
+/// 保存规则（新增或更新）
+pub async fn save_rule(rule: &MintRule) -> Result<()> {
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = con
+        .hset(
+            get_rules_key(&rule.mint),
+            &rule.id,
+            serde_json::to_string(rule)?,
+        )
+        .await?;
+    Ok(())
+}
+
+/// 获取mint的所有规则
+pub async fn get_rules(mint: &str) -> Result<Vec<MintRule>> {
+    let mut con = redis_helper::get_connection().await?;
+    let data: Vec<String> = con.hvals(get_rules_key(mint)).await?;
+    let mut rules: Vec<MintRule> = data
+        .iter()
+        .filter_map(|d| serde_json::from_str(d).ok())
+        .collect();
+    rules.sort_by_key(|r| r.created_at);
+    Ok(rules)
+}
+
+/// 删除规则，返回是否存在
+pub async fn remove_rule(mint: &str, id: &str) -> Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    let removed: usize = con.hdel(get_rules_key(mint), id).await?;
+    let remaining: usize = con.hlen(get_rules_key(mint)).await?;
+    if remaining == 0 {
+        let _: () = con.srem(RULE_MINTS_KEY, mint).await?;
+    }
+    Ok(removed > 0)
+}
+
+/// 获取所有设置了规则的mint
+pub async fn get_rule_mints() -> Result<HashSet<String>> {
+    let mut con = redis_helper::get_connection().await?;
+    Ok(con.smembers(RULE_MINTS_KEY).await?)
+}
+
+/// 规则告警的参数，记录触发的规则ID
+pub fn rule_alert_params(id: &str) -> String {
+    format!("rule={}", id)
+}
+
+/// 从规则告警的参数中取出规则ID
+pub fn rule_id_from_params(params: &str) -> Option<&str> {
+    params.strip_prefix("rule=")
+}
+
+/// 记录规则的触发时间，只在告警实际发送后调用；规则已删除时忽略
+pub async fn record_triggered(mint: &str, id: &str, triggered_at: i64) -> Result<()> {
+    let mut con = redis_helper::get_connection().await?;
+    let data: Option<String> = con.hget(get_rules_key(mint), id).await?;
+    let Some(data) = data else {
+        return Ok(());
+    };
+    let mut rule: MintRule = serde_json::from_str(&data)?;
+    rule.last_triggered_at = Some(triggered_at);
+    save_rule(&rule).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness;
+
+    #[test]
+    fn test_rule_alert_params() {
+        let params = rule_alert_params("ab12");
+        assert_eq!(rule_id_from_params(&params), Some("ab12"));
+        assert_eq!(rule_id_from_params("count=4 min_gain=0.001"), None);
+    }
+
+    #[test]
+    fn test_rule_description_language() {
+        let kline = KLineData {
+            net_flow_sol: "1".to_string(),
+            ..test_harness::candle(0, "0.00000005")
+        };
+        let condition = RuleCondition::PriceAbove {
+            value: Decimal::new(4, 8),
+        };
+        let format = NotificationFormat {
+            price_decimals: 8,
+            ..NotificationFormat::default()
+        };
+        assert_eq!(
+            condition
+                .evaluate(std::slice::from_ref(&kline), &format)
+                .unwrap(),
+            "价格 0.00000005 SOL 高于 0.00000004 SOL"
+        );
+        let format = NotificationFormat {
+            language: crate::notification_format::Language::En,
+            ..format
+        };
+        assert_eq!(
+            condition.evaluate(&[kline], &format).unwrap(),
+            "price 0.00000005 SOL above 0.00000004 SOL"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::HashMap;
+use tracing::{debug, warn};
+
+use crate::funnel;
+use crate::holders;
+use crate::kline;
+use crate::strategy::StrategyAlert;
+use crate::suppression;
+use crate::trades::{self, TradeRecord};
+
+// Recent trades the net flow and wash score are computed from
+const SCORED_TRADES: usize = 100;
+// Holder count that scores full marks; the holder score grows with its log
+const FULL_SCORE_HOLDERS: f64 = 1000.0;
+
+// Component weights, out of 100
+const VOLUME_WEIGHT: f64 = 30.0;
+const NET_FLOW_WEIGHT: f64 = 25.0;
+const HOLDERS_WEIGHT: f64 = 20.0;
+const WASH_WEIGHT: f64 = 15.0;
+const CURVE_WEIGHT: f64 = 10.0;
+
+/// 告警严重度的各分项（0-1），数据不存在的分项为None
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SeverityInputs {
+    /// 累计成交额在所有mint中的百分位
+    pub volume_percentile: Option<f64>,
+    /// 最近交易的净流入占成交额比例，-1（全部卖出）到1（全部买入）
+    pub net_flow_ratio: Option<f64>,
+    /// 最近一次持有人快照的数量
+    pub holders: Option<u64>,
+    /// 刷量评分，见 `suppression::wash_score`
+    pub wash_score: Option<f64>,
+    /// 联合曲线进度，已毕业为1
+    pub curve_progress: Option<f64>,
+}
+
+/// 按权重合成0-100的严重度，只计入有数据的分项，全部缺失时返回None
+pub fn severity_score(inputs: &SeverityInputs) -> Option<u8> {
+    let components = [
+        (inputs.volume_percentile, VOLUME_WEIGHT),
+        (
+            inputs.net_flow_ratio.map(|r| (r + 1.0) / 2.0),
+            NET_FLOW_WEIGHT,
+        ),
+        (
+            inputs
+                .holders
+                .map(|h| (h as f64 + 1.0).log10() / FULL_SCORE_HOLDERS.log10()),
+            HOLDERS_WEIGHT,
+        ),
+        (inputs.wash_score.map(|w| 1.0 - w), WASH_WEIGHT),
+        (inputs.curve_progress, CURVE_WEIGHT),
+    ];
+    let (sum, weights) = components
+        .iter()
+        .filter_map(|(value, weight)| Some((value.filter(|v| v.is_finite())?, *weight)))
+        .fold((0.0, 0.0), |(sum, weights), (value, weight)| {
+            (sum + value.clamp(0.0, 1.0) * weight, weights + weight)
+        });
+    if weights <= 0.0 {
+        return None;
+    }
+    Some((sum / weights * 100.0).round() as u8)
+}
+
+/// 最近交易的净流入占成交额比例，没有成交额时返回None
+pub fn net_flow_ratio(trades: &[TradeRecord]) -> Option<f64> {
+    let (net, total) = trades
+        .iter()
+        .fold((Decimal::ZERO, Decimal::ZERO), |(net, total), trade| {
+            let signed = if trade.is_buy {
+                trade.sol_amount
+            } else {
+                -trade.sol_amount
+            };
+            (net + signed, total + trade.sol_amount)
+        });
+    if total <= Decimal::ZERO {
+        return None;
+    }
+    (net / total).to_f64()
+}
+
+/// 收集mint的评分数据，单项查询失败时记录警告并视为缺失
+pub async fn collect_inputs(mint: &str) -> SeverityInputs {
+    fn ok<T>(mint: &str, what: &str, result: anyhow::Result<Option<T>>) -> Option<T> {
+        result.unwrap_or_else(|e| {
+            warn!("⚠️ 获取 {} 的{}失败: {}", mint, what, e);
+            None
+        })
+    }
+
+    let recent = ok(
+        mint,
+        "最近交易",
+        trades::get_trades_for_mint(mint, Some(SCORED_TRADES))
+            .await
+            .map(Some),
+    )
+    .unwrap_or_default();
+    let complete = ok(
+        mint,
+        "毕业状态",
+        kline::is_mint_complete(mint).await.map(Some),
+    )
+    .unwrap_or(false);
+    let curve_progress = if complete {
+        Some(1.0)
+    } else {
+        ok(mint, "曲线进度", funnel::get_curve_progress(mint).await)
+    };
+
+    SeverityInputs {
+        volume_percentile: ok(mint, "成交额排名", kline::get_volume_percentile(mint).await),
+        net_flow_ratio: net_flow_ratio(&recent),
+        holders: ok(
+            mint,
+            "持有人快照",
+            holders::get_snapshots(mint, 0)
+                .await
+                .map(|mut snapshots| snapshots.pop()),
+        )
+        .map(|snapshot| snapshot.count),
+        wash_score: suppression::wash_score(&recent),
+        curve_progress,
+    }
+}
+
+/// 为告警评分，同一mint的告警共用一次数据查询
+pub async fn score_alerts(alerts: &mut [StrategyAlert]) {
+    if !severity_enabled() {
+        return;
+    }
+
+    let mut scored: HashMap<String, Option<u8>> = HashMap::new();
+    for alert in alerts.iter_mut() {
+        if !scored.contains_key(&alert.mint) {
+            let inputs = collect_inputs(&alert.mint).await;
+            let score = severity_score(&inputs);
+            debug!("{} 严重度 {:?}: {:?}", alert.mint, score, inputs);
+            scored.insert(alert.mint.clone(), score);
+        }
+        alert.severity = scored.get(&alert.mint).copied().flatten();
+    }
+}
+
+/// 为单条告警评分
+pub async fn score_alert(alert: &mut StrategyAlert) {
+    score_alerts(std::slice::from_mut(alert)).await;
+}
+
+/// 是否计算告警严重度（SEVERITY_ENABLED，默认开启）
+pub fn severity_enabled() -> bool {
+    std::env::var("SEVERITY_ENABLED")
+        .unwrap_or_else(|_| "true".to_string())
+        .parse()
+        .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_severity_score() {
+        assert_eq!(severity_score(&SeverityInputs::default()), None);
+
+        let strong = SeverityInputs {
+            volume_percentile: Some(1.0),
+            net_flow_ratio: Some(1.0),
+            holders: Some(1000),
+            wash_score: Some(0.0),
+            curve_progress: Some(1.0),
+        };
+        assert_eq!(severity_score(&strong), Some(100));
+
+        // Missing components are left out rather than counted as zero
+        let partial = SeverityInputs {
+            volume_percentile: Some(0.5),
+            net_flow_ratio: Some(0.0),
+            ..Default::default()
+        };
+        assert_eq!(severity_score(&partial), Some(50));
+
+        let weak = SeverityInputs {
+            wash_score: Some(0.9),
+            net_flow_ratio: Some(-1.0),
+            ..strong
+        };
+        assert!(severity_score(&weak).unwrap() < 70);
+    }
+}
@@ -0,0 +1,223 @@
+use anyhow::{Context, Result};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::{redis_helper, supervisor};
+
+// How often each instance refreshes its shard registration
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+// Registrations older than this belong to instances that are gone
+const HEARTBEAT_STALE_SECS: i64 = 60;
+
+// Shard of this process, set once when a monitor starts
+static SHARD: OnceLock<ShardConfig> = OnceLock::new();
+
+// Slice of the mints one monitor instance is responsible for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShardConfig {
+    pub index: u64,
+    pub count: u64,
+}
+
+// Registration of a running instance, stored per monitor and shard index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShardRegistration {
+    pub count: u64,
+    pub instance: String,
+    pub heartbeat: i64,
+}
+
+// Shard of a mint. SHA-256 keeps the split identical across processes and builds.
+pub fn shard_of(mint: &str, count: u64) -> u64 {
+    let digest = Sha256::digest(mint.as_bytes());
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(prefix) % count.max(1)
+}
+
+// Trailing number of a host name, e.g. 2 for "kmonitor-amm-2"
+fn hostname_ordinal(hostname: &str) -> Option<u64> {
+    let digits: String = hostname
+        .chars()
+        .rev()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    digits.parse().ok()
+}
+
+impl ShardConfig {
+    pub fn new(index: u64, count: u64) -> Result<Self> {
+        if count == 0 {
+            anyhow::bail!("MONITOR_SHARD_COUNT must be at least 1");
+        }
+        if index >= count {
+            anyhow::bail!(
+                "MONITOR_SHARD_INDEX {} is out of range for {} shards",
+                index,
+                count
+            );
+        }
+        Ok(Self { index, count })
+    }
+
+    // MONITOR_SHARD_COUNT instances split the mints, this one handles MONITOR_SHARD_INDEX.
+    // "auto" takes the index from the trailing number of HOSTNAME (StatefulSet pods).
+    pub fn from_env() -> Result<Self> {
+        let count = std::env::var("MONITOR_SHARD_COUNT")
+            .unwrap_or_else(|_| "1".to_string())
+            .parse()
+            .context("MONITOR_SHARD_COUNT must be a number")?;
+        let index = match std::env::var("MONITOR_SHARD_INDEX")
+            .unwrap_or_else(|_| "0".to_string())
+            .as_str()
+        {
+            "auto" => {
+                let hostname = std::env::var("HOSTNAME").unwrap_or_default();
+                hostname_ordinal(&hostname).with_context(|| {
+                    format!(
+                        "MONITOR_SHARD_INDEX=auto but HOSTNAME {:?} has no ordinal",
+                        hostname
+                    )
+                })?
+            }
+            index => index
+                .parse()
+                .context("MONITOR_SHARD_INDEX must be a number or \"auto\"")?,
+        };
+        Self::new(index, count)
+    }
+
+    pub fn is_sharded(&self) -> bool {
+        self.count > 1
+    }
+
+    pub fn owns(&self, mint: &str) -> bool {
+        !self.is_sharded() || shard_of(mint, self.count) == self.index
+    }
+}
+
+// Whether this process handles a mint's trades. Always true when unsharded.
+pub fn owns(mint: &str) -> bool {
+    SHARD.get().is_none_or(|shard| shard.owns(mint))
+}
+
+// Generate Redis key for the shard registrations of a monitor
+fn get_registrations_key(monitor_name: &str) -> String {
+    format!("monitor:shards:{}", monitor_name)
+}
+
+fn instance_name() -> String {
+    format!(
+        "{}:{}",
+        std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string()),
+        std::process::id()
+    )
+}
+
+async fn register(monitor_name: &str, shard: ShardConfig, instance: &str) -> Result<()> {
+    let registration = ShardRegistration {
+        count: shard.count,
+        instance: instance.to_string(),
+        heartbeat: chrono::Utc::now().timestamp(),
+    };
+    let mut con = redis_helper::get_connection().await?;
+    let _: () = con
+        .hset(
+            get_registrations_key(monitor_name),
+            shard.index,
+            serde_json::to_string(&registration)?,
+        )
+        .await?;
+    Ok(())
+}
+
+// Live registrations of a monitor by shard index
+pub async fn get_registrations(monitor_name: &str) -> Result<HashMap<u64, ShardRegistration>> {
+    let mut con = redis_helper::get_connection().await?;
+    let data: HashMap<u64, String> = con.hgetall(get_registrations_key(monitor_name)).await?;
+    let now = chrono::Utc::now().timestamp();
+    Ok(data
+        .into_iter()
+        .filter_map(|(index, d)| Some((index, serde_json::from_str::<ShardRegistration>(&d).ok()?)))
+        .filter(|(_, registration)| now - registration.heartbeat <= HEARTBEAT_STALE_SECS)
+        .collect())
+}
+
+// Set this process's shard from the environment. When sharded, refuse to start
+// next to instances configured with another shard count, since the two splits
+// would overlap, then keep a heartbeat registration so peers can check.
+pub async fn init(monitor_name: &str) -> Result<ShardConfig> {
+    let shard = ShardConfig::from_env()?;
+    let _ = SHARD.set(shard);
+    if !shard.is_sharded() {
+        return Ok(shard);
+    }
+
+    let instance = instance_name();
+    let peers = get_registrations(monitor_name).await?;
+    if let Some((index, peer)) = peers.iter().find(|(_, peer)| peer.count != shard.count) {
+        anyhow::bail!(
+            "{} shard {} ({}) runs with {} shards, this instance is configured for {}",
+            monitor_name,
+            index,
+            peer.instance,
+            peer.count,
+            shard.count
+        );
+    }
+    if let Some(peer) = peers.get(&shard.index) {
+        warn!(
+            "{} shard {} is already registered by {}, trades of this shard will be written twice",
+            monitor_name, shard.index, peer.instance
+        );
+    }
+
+    info!(
+        "🧩 {} handling shard {} of {} ({} other shards live)",
+        monitor_name,
+        shard.index,
+        shard.count,
+        peers.len()
+    );
+    register(monitor_name, shard, &instance).await?;
+
+    let monitor_name = monitor_name.to_string();
+    supervisor::spawn_supervised("shard_heartbeat", async move {
+        loop {
+            tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+            if let Err(e) = register(&monitor_name, shard, &instance).await {
+                warn!("Failed to refresh shard registration: {}", e);
+            }
+        }
+    });
+    Ok(shard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_split() {
+        let shards: Vec<ShardConfig> = (0..4).map(|i| ShardConfig::new(i, 4).unwrap()).collect();
+        for i in 0..200 {
+            let mint = format!("Mint{}pump", i);
+            assert_eq!(shards.iter().filter(|s| s.owns(&mint)).count(), 1);
+        }
+        assert!(ShardConfig::new(0, 1).unwrap().owns("any"));
+        assert!(ShardConfig::new(4, 4).is_err());
+        assert!(ShardConfig::new(0, 0).is_err());
+
+        assert_eq!(hostname_ordinal("kmonitor-amm-2"), Some(2));
+        assert_eq!(hostname_ordinal("kmonitor"), None);
+    }
+}
@@ -0,0 +1,227 @@
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use crate::labels::{self, WalletLabel};
+use crate::launch;
+use crate::pnl;
+use crate::positions::{self, WalletPosition};
+use crate::redis_helper;
+use crate::remote_config;
+use crate::supervisor;
+use crate::trades;
+use crate::wallets;
+
+// Ranked smart-money list (JSON array), replaced on every discovery pass
+const SMART_MONEY_KEY: &str = "smartmoney:ranked";
+
+/// 聪明钱包统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartWallet {
+    pub wallet: String,
+    /// 早期买入的mint数量
+    pub early_mints: usize,
+    /// 其中盈利的mint数量
+    pub profitable_mints: usize,
+    pub win_rate: Decimal,
+    pub total_pnl_sol: Decimal,
+    pub updated_at: i64,
+    /// 钱包标签，查询时附上，不随排名存储
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<WalletLabel>,
+}
+
+pub struct SmartMoneyConfig {
+    pub interval_secs: u64,
+    pub early_secs: i64,
+    pub min_mints: usize,
+    pub min_win_rate: Decimal,
+    pub auto_track: usize,
+}
+
+impl SmartMoneyConfig {
+    pub fn from_env() -> Self {
+        Self {
+            interval_secs: std::env::var("SMART_MONEY_INTERVAL_SECS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .unwrap_or(3600),
+            early_secs: std::env::var("SMART_MONEY_EARLY_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+            min_mints: remote_config::var("SMART_MONEY_MIN_MINTS")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .unwrap_or(3),
+            min_win_rate: remote_config::var("SMART_MONEY_MIN_WIN_RATE")
+                .unwrap_or_else(|_| "0.6".to_string())
+                .parse()
+                .unwrap_or_else(|_| Decimal::new(6, 1)),
+            auto_track: std::env::var("SMART_MONEY_AUTO_TRACK")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+        }
+    }
+}
+
+#[derive(Default)]
+struct WalletStats {
+    early_mints: usize,
+    profitable_mints: usize,
+    total_pnl_sol: Decimal,
+}
+
+// PnL of the early buyers' whole positions at the last price
+fn early_entry_pnl(
+    positions: &[(String, WalletPosition)],
+    last_price: Decimal,
+) -> HashMap<String, Decimal> {
+    positions
+        .iter()
+        .map(|(wallet, position)| {
+            let mint_pnl = pnl::compute_mint_pnl("", position, Some(last_price));
+            (wallet.clone(), mint_pnl.total_pnl_sol)
+        })
+        .collect()
+}
+
+// Rank consistently profitable early buyers of observed launches. Launches come
+// from the launch index and early buyers from the first-buy index, so neither
+// depends on the capped trade list.
+pub async fn discover(config: &SmartMoneyConfig) -> Result<Vec<SmartWallet>> {
+    let mut stats: HashMap<String, WalletStats> = HashMap::new();
+    for mint in launch::get_recorded_launches().await? {
+        let Some(info) = launch::get_launch(&mint).await? else {
+            continue;
+        };
+        let Some(last_price) = trades::get_trades_for_mint(&mint, Some(1))
+            .await?
+            .last()
+            .map(|t| t.price)
+        else {
+            continue;
+        };
+        let early =
+            positions::get_early_positions(&mint, info.created_at + config.early_secs).await?;
+
+        for (wallet, pnl) in early_entry_pnl(&early, last_price) {
+            let entry = stats.entry(wallet).or_default();
+            entry.early_mints += 1;
+            if pnl > Decimal::ZERO {
+                entry.profitable_mints += 1;
+            }
+            entry.total_pnl_sol += pnl;
+        }
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let mut ranked: Vec<SmartWallet> = stats
+        .into_iter()
+        .filter(|(_, s)| s.early_mints >= config.min_mints)
+        .map(|(wallet, s)| SmartWallet {
+            wallet,
+            early_mints: s.early_mints,
+            profitable_mints: s.profitable_mints,
+            win_rate: Decimal::from(s.profitable_mints) / Decimal::from(s.early_mints),
+            total_pnl_sol: s.total_pnl_sol,
+            updated_at: now,
+            label: None,
+        })
+        .filter(|w| w.win_rate >= config.min_win_rate && w.total_pnl_sol > Decimal::ZERO)
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.profitable_mints
+            .cmp(&a.profitable_mints)
+            .then(b.total_pnl_sol.cmp(&a.total_pnl_sol))
+    });
+
+    redis_helper::set(SMART_MONEY_KEY, serde_json::to_string(&ranked)?).await?;
+    Ok(ranked)
+}
+
+// Get the ranked smart-money list from the last discovery pass, with wallet labels
+pub async fn get_smart_money() -> Result<Vec<SmartWallet>> {
+    let data: Option<String> = redis_helper::get(SMART_MONEY_KEY).await?;
+    let mut ranked: Vec<SmartWallet> = data
+        .and_then(|d| serde_json::from_str(&d).ok())
+        .unwrap_or_default();
+    let wallet_labels = labels::get_labels(ranked.iter().map(|w| w.wallet.as_str())).await?;
+    for wallet in &mut ranked {
+        wallet.label = wallet_labels.get(&wallet.wallet).cloned();
+    }
+    Ok(ranked)
+}
+
+// Periodically rediscover smart money and track the top wallets
+pub fn spawn_discovery_task() -> Option<tokio::task::JoinHandle<()>> {
+    let config = SmartMoneyConfig::from_env();
+    if config.interval_secs == 0 {
+        return None;
+    }
+
+    info!(
+        "🧠 Smart-money discovery enabled: every {}s, auto-tracking top {}",
+        config.interval_secs, config.auto_track
+    );
+
+    Some(supervisor::spawn_supervised("smart_money", async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(config.interval_secs));
+        loop {
+            interval.tick().await;
+            // Thresholds are re-read every round so runtime overrides apply
+            let config = SmartMoneyConfig::from_env();
+            match discover(&config).await {
+                Ok(ranked) => {
+                    info!("🧠 Smart-money discovery found {} wallets", ranked.len());
+                    for wallet in ranked.iter().take(config.auto_track) {
+                        match wallets::track_wallet(&wallet.wallet).await {
+                            Ok(true) => info!("👀 Auto-tracking smart wallet {}", wallet.wallet),
+                            Ok(false) => {}
+                            Err(e) => warn!("Failed to track wallet {}: {}", wallet.wallet, e),
+                        }
+                    }
+                }
+                Err(e) => warn!("Smart-money discovery failed: {}", e),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trades::TradeRecord;
+
+    fn trade(user: &str, is_buy: bool, price: i64, tokens: i64, timestamp: i64) -> TradeRecord {
+        TradeRecord {
+            signature: String::new(),
+            slot: 0,
+            mint: "mint".to_string(),
+            user: user.to_string(),
+            is_buy,
+            price: Decimal::new(price, 3),
+            sol_amount: Decimal::new(price * tokens, 3),
+            token_amount: Decimal::from(tokens),
+            timestamp,
+            source: None,
+            liquidity_sol: None,
+        }
+    }
+
+    #[test]
+    fn test_early_entry_pnl() {
+        let mut position = WalletPosition::default();
+        position.apply(&trade("early", true, 1, 100, 10));
+        position.apply(&trade("early", false, 4, 50, 1010));
+        let result = early_entry_pnl(&[("early".to_string(), position)], Decimal::new(4, 3));
+
+        // Realized 0.15 + unrealized 0.15 at last price 0.004
+        assert_eq!(result.len(), 1);
+        assert_eq!(result["early"], Decimal::new(3, 1));
+    }
+}
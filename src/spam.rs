@@ -0,0 +1,291 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::cached_set::{CachedRedisSet, SetSource};
+use crate::clusters;
+use crate::redis_helper;
+use crate::remote_config;
+use crate::supervisor;
+
+// Redis hash of blacklisted mints, mint -> BlacklistEntry
+const BLACKLIST_KEY: &str = "spam:blacklist";
+
+// Redis set of mints a reviewer took off the blacklist, never auto-blacklisted again
+const ALLOWED_KEY: &str = "spam:allowed";
+
+// In-memory copy of the blacklisted mints used by the monitors, reloaded every 10 seconds.
+// A failed reload keeps the previous copy, spam should stay out during a Redis hiccup.
+static BLACKLIST: CachedRedisSet =
+    CachedRedisSet::new(BLACKLIST_KEY, SetSource::HashFields, || {
+        Duration::from_secs(10)
+    });
+
+/// 垃圾代币识别配置
+#[derive(Debug, Clone)]
+pub struct SpamConfig {
+    pub enabled: bool,
+    /// 同一创建者集群在窗口内发行相同指纹的代币达到该数量时拉黑
+    pub repeat_threshold: usize,
+    /// 统计重复发行的时间窗口（秒）
+    pub window_secs: i64,
+    /// 按创建者的资金来源归并集群（需要RPC查询），关闭时按创建者地址
+    pub cluster_by_funder: bool,
+}
+
+/// 是否开启垃圾代币识别（SPAM_DETECTION_ENABLED），关闭时不识别也不过滤
+pub fn detection_enabled() -> bool {
+    remote_config::var("SPAM_DETECTION_ENABLED")
+        .unwrap_or_else(|_| "false".to_string())
+        .parse()
+        .unwrap_or(false)
+}
+
+impl SpamConfig {
+    pub fn from_env() -> Self {
+        Self {
+            enabled: detection_enabled(),
+            repeat_threshold: remote_config::var("SPAM_REPEAT_THRESHOLD")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .unwrap_or(3),
+            window_secs: std::env::var("SPAM_WINDOW_SECS")
+                .unwrap_or_else(|_| "86400".to_string())
+                .parse()
+                .unwrap_or(86400),
+            cluster_by_funder: std::env::var("SPAM_CLUSTER_BY_FUNDER")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// 自动拉黑记录，供人工复核
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlacklistEntry {
+    pub mint: String,
+    /// 匹配的指纹类型：name（名称和符号）或 uri（元数据）
+    pub reason: String,
+    pub fingerprint: String,
+    /// 创建者集群（资金来源或创建者地址）
+    pub cluster: String,
+    /// 同一集群中指纹相同的代币
+    pub related: Vec<String>,
+    pub created_at: i64,
+}
+
+// Lowercase letters and digits only, so "PEPE 2.0" and "pepe20" collide
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// First 8 bytes of SHA-256 as hex, short enough for Redis keys
+fn short_hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes())[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 代币的指纹：(类型, 哈希)，名称和符号归一化后计算，元数据URI原样计算
+pub fn fingerprints(name: &str, symbol: &str, uri: &str) -> Vec<(&'static str, String)> {
+    let mut fingerprints = Vec::new();
+    let name = normalize(name);
+    if !name.is_empty() {
+        fingerprints.push((
+            "name",
+            short_hash(&format!("{}|{}", name, normalize(symbol))),
+        ));
+    }
+    let uri = uri.trim();
+    if !uri.is_empty() {
+        fingerprints.push(("uri", short_hash(uri)));
+    }
+    fingerprints
+}
+
+// Generate Redis key for the launches of a cluster sharing a fingerprint
+fn get_launches_key(cluster: &str, fingerprint: &str) -> String {
+    format!("spam:launches:{}:{}", cluster, fingerprint)
+}
+
+// Creator cluster: the creator's funder when enabled and found, else the creator
+async fn creator_cluster(creator: &str, config: &SpamConfig) -> String {
+    if config.cluster_by_funder {
+        match clusters::get_funder_cached(creator).await {
+            Ok(Some(funder)) => return funder,
+            Ok(None) => {}
+            Err(e) => warn!("Failed to resolve funder of {}: {}", creator, e),
+        }
+    }
+    creator.to_string()
+}
+
+/// 检查新发行的代币，同一集群重复发行相同指纹的代币时全部拉黑，返回新拉黑的mint
+pub async fn check_launch(
+    mint: &str,
+    name: &str,
+    symbol: &str,
+    uri: &str,
+    creator: &str,
+) -> Result<Vec<String>> {
+    let config = SpamConfig::from_env();
+    if !config.enabled {
+        return Ok(Vec::new());
+    }
+    let cluster = creator_cluster(creator, &config).await;
+    let now = chrono::Utc::now().timestamp();
+
+    let mut blacklisted = Vec::new();
+    for (reason, fingerprint) in fingerprints(name, symbol, uri) {
+        let key = get_launches_key(&cluster, &fingerprint);
+        let (launches,): (Vec<String>,) = {
+            let mut con = redis_helper::get_connection().await?;
+            redis::pipe()
+                .zadd(&key, mint, now)
+                .ignore()
+                .zrembyscore(&key, "-inf", now - config.window_secs)
+                .ignore()
+                .expire(&key, config.window_secs)
+                .ignore()
+                .zrange(&key, 0, -1)
+                .query_async(&mut *con)
+                .await?
+        };
+        if launches.len() < config.repeat_threshold.max(2) {
+            continue;
+        }
+        let entry = BlacklistEntry {
+            mint: String::new(),
+            reason: reason.to_string(),
+            fingerprint,
+            cluster: cluster.clone(),
+            related: launches.clone(),
+            created_at: now,
+        };
+        for mint in add_to_blacklist(&launches, &entry).await? {
+            if !blacklisted.contains(&mint) {
+                blacklisted.push(mint);
+            }
+        }
+    }
+
+    if !blacklisted.is_empty() {
+        info!(
+            "🚫 Blacklisted {} spam launches by {} ({})",
+            blacklisted.len(),
+            cluster,
+            blacklisted.join(", ")
+        );
+        BLACKLIST.invalidate();
+    }
+    Ok(blacklisted)
+}
+
+/// 在后台检查新发行的代币，未开启识别时不做任何事
+pub fn inspect_launch(mint: &str, name: &str, symbol: &str, uri: &str, creator: &str) {
+    if !SpamConfig::from_env().enabled {
+        return;
+    }
+    let (mint, name, symbol, uri, creator) = (
+        mint.to_string(),
+        name.to_string(),
+        symbol.to_string(),
+        uri.to_string(),
+        creator.to_string(),
+    );
+    supervisor::spawn_supervised("spam_check", async move {
+        if let Err(e) = check_launch(&mint, &name, &symbol, &uri, &creator).await {
+            warn!("Failed to check launch {} for spam: {}", mint, e);
+        }
+    });
+}
+
+// Blacklist mints that are neither blacklisted already nor allowed by a
+// reviewer, returning the ones added
+async fn add_to_blacklist(mints: &[String], entry: &BlacklistEntry) -> Result<Vec<String>> {
+    let mut con = redis_helper::get_connection().await?;
+    let mut added = Vec::new();
+    for mint in mints {
+        let allowed: bool = con.sismember(ALLOWED_KEY, mint).await?;
+        if allowed {
+            continue;
+        }
+        let entry = BlacklistEntry {
+            mint: mint.clone(),
+            ..entry.clone()
+        };
+        let new: bool = con
+            .hset_nx(BLACKLIST_KEY, mint, serde_json::to_string(&entry)?)
+            .await?;
+        if new {
+            added.push(mint.clone());
+        }
+    }
+    Ok(added)
+}
+
+/// 获取拉黑记录，最新的在前
+pub async fn get_blacklist() -> Result<Vec<BlacklistEntry>> {
+    let mut con = redis_helper::get_connection().await?;
+    let data: HashMap<String, String> = con.hgetall(BLACKLIST_KEY).await?;
+    let mut entries: Vec<BlacklistEntry> = data
+        .values()
+        .filter_map(|d| serde_json::from_str(d).ok())
+        .collect();
+    entries.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+    Ok(entries)
+}
+
+/// 撤销拉黑：移出黑名单，并且之后不再自动拉黑该mint。返回是否存在
+pub async fn remove_from_blacklist(mint: &str) -> Result<bool> {
+    let removed: usize = {
+        let mut con = redis_helper::get_connection().await?;
+        let removed: usize = con.hdel(BLACKLIST_KEY, mint).await?;
+        let _: () = con.sadd(ALLOWED_KEY, mint).await?;
+        removed
+    };
+    BLACKLIST.invalidate();
+    Ok(removed > 0)
+}
+
+/// mint是否被拉黑。使用定期刷新的内存缓存，避免每笔交易都访问Redis；
+/// 未开启垃圾代币识别时直接返回false，不读取缓存
+pub async fn is_blacklisted(mint: &str) -> bool {
+    detection_enabled() && BLACKLIST.contains(mint).await
+}
+
+/// 被拉黑mint的快照，批量检查时只读取一次缓存；未开启垃圾代币识别时为空
+pub async fn blacklist_snapshot() -> Arc<HashSet<String>> {
+    if !detection_enabled() {
+        return Arc::default();
+    }
+    BLACKLIST.snapshot().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprints() {
+        let a = fingerprints("PEPE 2.0", "$PEPE", "https://ipfs.io/ipfs/Qm1");
+        let b = fingerprints("pepe20", "pepe", " https://ipfs.io/ipfs/Qm1 ");
+        assert_eq!(a, b);
+        assert_eq!(a[0].0, "name");
+        assert_eq!(a[1].0, "uri");
+        assert_eq!(a[0].1.len(), 16);
+
+        assert_ne!(fingerprints("PEPE", "PEPE2", "")[0], a[0]);
+        assert!(fingerprints("🚀🚀", "X", "").is_empty());
+    }
+}
@@ -1,21 +1,34 @@
-use crate::kline::{KLineData, KLineManager};
+use crate::alerts;
+use crate::annotations::{self, Annotation};
+pub use crate::api::StrategyAlert;
+use crate::candle_cache::{CandleCache, WarmupConfig};
+use crate::candle_hub;
+use crate::candle_stream::{self, CandleStreamConsumer};
+use crate::clock::{self, SharedClock};
+use crate::clusters;
+use crate::holders::{self, HolderSnapshot};
+use crate::kline::{self, KLineAggregator, KLineData, KLineManager};
+use crate::localized;
+use crate::market_beta;
+use crate::metrics;
 use crate::notification::NotificationManager;
+use crate::notification_format::NotificationFormat;
+use crate::price_feed;
+use crate::remote_config;
+use crate::rules::{self, MintRule};
+use crate::severity;
+use crate::spam;
 use anyhow::Result;
+use futures_util::future::BoxFuture;
 use rust_decimal::Decimal;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::collections::{HashMap, HashSet, hash_map::Entry};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
 use tracing::{debug, info, warn};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StrategyAlert {
-    pub mint: String,
-    pub strategy_name: String,
-    pub message: String,
-    pub timestamp: i64,
-    pub klines: Vec<KLineData>,
-}
+const RISING_STRATEGY_NAME: &str = "连续递增上涨模式";
+const HOLDER_GROWTH_STRATEGY_NAME: &str = "持有人增长";
+const SELL_EXHAUSTION_STRATEGY_NAME: &str = "卖压衰竭反转";
 
 #[derive(Debug, Clone)]
 pub struct ConsecutiveRisingPattern {
@@ -25,6 +38,12 @@ pub struct ConsecutiveRisingPattern {
     pub require_increasing_gains: bool,
     /// 最小涨幅阈值 (百分比)
     pub min_gain_threshold: Decimal,
+    /// 回溯K线数量（包含最后一根形成中的K线）
+    pub lookback: usize,
+    /// 最小平均换手率（成交量/池子流动性），只对有流动性快照的AMM阶段K线生效
+    pub min_turnover: Option<Decimal>,
+    /// K线周期（秒）
+    pub interval_secs: i64,
 }
 
 impl Default for ConsecutiveRisingPattern {
@@ -33,84 +52,64 @@ impl Default for ConsecutiveRisingPattern {
             consecutive_count: 4,
             require_increasing_gains: false,
             min_gain_threshold: Decimal::new(1, 3), // 0.1%
+            lookback: 5,
+            min_turnover: None,
+            interval_secs: 60,
         }
     }
 }
 
-pub struct StrategyEngine {
-    kline_manager: Arc<Mutex<KLineManager>>,
-    notification_manager: NotificationManager,
-    /// 存储每个mint最近检查的K线数据，避免重复检查
-    last_checked: HashMap<String, u64>,
-}
-
-impl StrategyEngine {
-    pub fn new(
-        kline_manager: Arc<Mutex<KLineManager>>,
-        notification_manager: NotificationManager,
-    ) -> Self {
-        Self {
-            kline_manager,
-            notification_manager,
-            last_checked: HashMap::new(),
+impl ConsecutiveRisingPattern {
+    /// 从环境变量加载配置，未配置时使用默认值
+    pub fn from_env() -> Self {
+        let mut pattern = Self::default();
+        if let Some(lookback) = remote_config::var("STRATEGY_RISING_LOOKBACK")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+        {
+            pattern.lookback = lookback;
         }
+        pattern.min_turnover = remote_config::var("STRATEGY_RISING_MIN_TURNOVER")
+            .ok()
+            .and_then(|v| v.parse::<Decimal>().ok())
+            .filter(|v| *v > Decimal::ZERO);
+        pattern.interval_secs = interval_from_env("STRATEGY_RISING_INTERVAL");
+        pattern
     }
 
-    /// 运行策略检测
-    pub async fn run_strategy_check(&mut self) -> Result<()> {
-        info!("🔍 开始运行策略检测...");
-
-        // 获取所有活跃的mint
-        let active_mints = {
-            let manager = self.kline_manager.lock().await;
-            manager.get_active_mints().await?
-        };
-
-        info!("📊 发现 {} 个活跃 mint", active_mints.len());
-
-        for (mint, last_activity, _complete) in active_mints {
-            // 检查是否需要检测这个mint（避免重复检测相同的数据）
-            if let Some(&last_check) = self.last_checked.get(&mint) {
-                if last_activity <= last_check {
-                    continue;
-                }
-            }
-
-            // 获取该mint的K线数据
-            let klines = {
-                let manager = self.kline_manager.lock().await;
-                manager.get_klines_for_mint(&mint, Some(10)).await?
-            };
-
-            // 检测连续上涨模式
-            if let Some(alert) = self.check_consecutive_rising_pattern(&mint, &klines) {
-                info!("🚨 策略触发: {} - {}", alert.strategy_name, alert.message);
-
-                // 发送通知
-                if let Err(e) = self.notification_manager.send_notification(&alert).await {
-                    warn!("❌ 通知发送失败: {}", e);
-                } else {
-                    // info!("✅ 通知发送成功");
-                }
-            }
-
-            // 更新最后检查时间
-            self.last_checked.insert(mint, last_activity);
+    /// 参数组合的标签，记录在告警中用于按参数组合统计
+    pub fn params_label(&self) -> String {
+        let mut label = format!(
+            "count={} min_gain={} lookback={}",
+            self.consecutive_count,
+            self.min_gain_threshold,
+            self.required_lookback()
+        );
+        if self.require_increasing_gains {
+            label.push_str(" increasing");
         }
-
-        Ok(())
+        if let Some(min_turnover) = self.min_turnover {
+            label.push_str(&format!(" min_turnover={}", min_turnover));
+        }
+        if self.interval_secs != 60 {
+            label.push_str(&format!(
+                " interval={}",
+                kline::interval_label(self.interval_secs)
+            ));
+        }
+        label
     }
 
-    /// 检测连续上涨模式
-    fn check_consecutive_rising_pattern(
-        &self,
-        mint: &str,
-        klines: &[KLineData],
-    ) -> Option<StrategyAlert> {
-        let pattern = ConsecutiveRisingPattern::default();
+    /// 策略实际需要的K线数量，至少为 consecutive_count + 1
+    pub fn required_lookback(&self) -> usize {
+        self.lookback.max(self.consecutive_count + 1)
+    }
 
+    /// 检测连续上涨，最后一根K线视为形成中的K线不参与检测。
+    /// 满足条件时返回每根K线的涨幅和参与检测的K线
+    pub fn detect(&self, klines: &[KLineData]) -> Option<(Vec<Decimal>, Vec<KLineData>)> {
         // 需要足够的K线数据，至少要有 consecutive_count + 1 根（排除最后一根）
-        if klines.len() < pattern.consecutive_count + 1 {
+        if klines.len() < self.consecutive_count + 1 {
             return None;
         }
 
@@ -120,25 +119,13 @@ impl StrategyEngine {
 
         // 排除最后一根还在形成中的K线，取倒数第2根到倒数第(consecutive_count+1)根
         let end_index = sorted_klines.len() - 1; // 排除最后一根
-        let start_index = end_index - pattern.consecutive_count;
+        let start_index = end_index - self.consecutive_count;
         let recent_klines = &sorted_klines[start_index..end_index];
 
-        debug!(
-            "🔍 策略检测 {} - 总K线数: {}, 检测范围: {} 到 {} (排除最后一根), 检测K线数: {}",
-            mint,
-            sorted_klines.len(),
-            start_index,
-            end_index - 1,
-            recent_klines.len()
-        );
-
         // 检查是否连续上涨
         let mut gains = Vec::new();
-        let mut is_consecutive_rising = true;
-
-        for i in 0..recent_klines.len() {
-            let kline = &recent_klines[i];
 
+        for kline in recent_klines {
             // 解析开盘价和收盘价
             let open_price = match kline.open.parse::<Decimal>() {
                 Ok(price) => price,
@@ -152,8 +139,7 @@ impl StrategyEngine {
 
             // 检查是否为阳线（收盘价 > 开盘价）
             if close_price <= open_price {
-                is_consecutive_rising = false;
-                break;
+                return None;
             }
 
             // 计算实体涨幅（只考虑实体部分）
@@ -164,27 +150,346 @@ impl StrategyEngine {
             };
 
             // 检查是否满足最小涨幅要求
-            if gain < pattern.min_gain_threshold {
-                is_consecutive_rising = false;
-                break;
+            if gain < self.min_gain_threshold {
+                return None;
             }
 
             gains.push(gain);
         }
 
-        if !is_consecutive_rising {
+        // 如果要求递增涨幅，检查每根K线的涨幅是否递增
+        if self.require_increasing_gains && gains.windows(2).any(|w| w[1] <= w[0]) {
             return None;
         }
 
-        // 如果要求递增涨幅，检查每根K线的涨幅是否递增
-        if pattern.require_increasing_gains {
-            for i in 1..gains.len() {
-                if gains[i] <= gains[i - 1] {
+        // 换手率按池子大小归一化成交量，过滤大池子中相对成交不活跃的上涨
+        if let Some(min_turnover) = self.min_turnover {
+            let turnovers: Vec<Decimal> = recent_klines
+                .iter()
+                .filter_map(|k| k.turnover.as_deref()?.parse().ok())
+                .collect();
+            if !turnovers.is_empty() {
+                let average = turnovers.iter().sum::<Decimal>() / Decimal::from(turnovers.len());
+                if average < min_turnover {
                     return None;
                 }
             }
         }
 
+        Some((gains, recent_klines.to_vec()))
+    }
+}
+
+/// 持有人增长策略：持有人数量增长速度超过阈值且资金净流入为正
+#[derive(Debug, Clone)]
+pub struct HolderGrowthPattern {
+    /// 每分钟最少新增持有人数量，0表示不启用
+    pub min_growth_per_minute: f64,
+    /// 计算增长速度和净流入的时间窗口（秒）
+    pub window_secs: i64,
+    /// 时间窗口内的净流入（SOL）必须大于该值
+    pub min_net_flow_sol: Decimal,
+}
+
+impl HolderGrowthPattern {
+    pub fn from_env() -> Self {
+        Self {
+            min_growth_per_minute: remote_config::var("STRATEGY_HOLDERS_MIN_GROWTH")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0.0),
+            window_secs: remote_config::var("STRATEGY_HOLDERS_WINDOW_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+            min_net_flow_sol: remote_config::var("STRATEGY_HOLDERS_MIN_NET_FLOW_SOL")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(Decimal::ZERO),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.min_growth_per_minute > 0.0
+    }
+
+    /// 参数组合的标签，记录在告警中用于按参数组合统计
+    pub fn params_label(&self) -> String {
+        format!(
+            "min_growth={} window={}s min_net_flow={}",
+            self.min_growth_per_minute, self.window_secs, self.min_net_flow_sol
+        )
+    }
+
+    /// 覆盖时间窗口需要的K线数量
+    pub fn required_lookback(&self) -> usize {
+        (self.window_secs.max(0) / 60) as usize + 1
+    }
+
+    /// 用时间窗口内的持有人快照和K线检测，满足条件时返回每分钟增长数量和净流入
+    pub fn detect(
+        &self,
+        snapshots: &[HolderSnapshot],
+        klines: &[KLineData],
+        now: i64,
+    ) -> Option<(f64, Decimal)> {
+        let since = now - self.window_secs;
+        let growth = holders::growth_per_minute(snapshots)?;
+        if growth < self.min_growth_per_minute {
+            return None;
+        }
+        let net_flow: Decimal = klines
+            .iter()
+            .filter(|k| k.timestamp >= KLineManager::get_minute_timestamp(since))
+            .filter_map(|k| k.net_flow_sol.parse::<Decimal>().ok())
+            .sum();
+        (net_flow > self.min_net_flow_sol).then_some((growth, net_flow))
+    }
+}
+
+/// 卖压衰竭策略：连续阴线的卖出量逐根递减，随后出现净流入转正的阳线，提示可能的反转点
+#[derive(Debug, Clone)]
+pub struct SellExhaustionPattern {
+    /// 是否启用
+    pub enabled: bool,
+    /// 反转阳线之前的连续阴线数量，至少2根
+    pub red_count: usize,
+    /// 最后一根阴线的卖出量相对第一根至少下降的百分比
+    pub min_sell_decline: Decimal,
+    /// 反转阳线的最小实体涨幅 (百分比)
+    pub min_gain_threshold: Decimal,
+    /// K线周期（秒）
+    pub interval_secs: i64,
+}
+
+impl Default for SellExhaustionPattern {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            red_count: 3,
+            min_sell_decline: Decimal::new(30, 0),
+            min_gain_threshold: Decimal::new(1, 3), // 0.1%
+            interval_secs: 60,
+        }
+    }
+}
+
+/// 卖压衰竭检测结果
+#[derive(Debug, Clone)]
+pub struct SellExhaustion {
+    /// 每根阴线的卖出量（SOL），按时间顺序
+    pub sell_volumes: Vec<Decimal>,
+    /// 反转阳线的实体涨幅 (百分比)
+    pub reversal_gain: Decimal,
+    /// 反转阳线的净流入（SOL）
+    pub reversal_net_flow: Decimal,
+    /// 连续阴线和反转阳线
+    pub klines: Vec<KLineData>,
+}
+
+impl SellExhaustionPattern {
+    /// 从环境变量加载配置，未配置时使用默认值
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            enabled: remote_config::var("STRATEGY_EXHAUSTION_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            red_count: remote_config::var("STRATEGY_EXHAUSTION_RED_COUNT")
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(default.red_count)
+                .max(2),
+            min_sell_decline: remote_config::var("STRATEGY_EXHAUSTION_MIN_DECLINE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.min_sell_decline),
+            min_gain_threshold: remote_config::var("STRATEGY_EXHAUSTION_MIN_GAIN")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.min_gain_threshold),
+            interval_secs: interval_from_env("STRATEGY_EXHAUSTION_INTERVAL"),
+        }
+    }
+
+    /// 参数组合的标签，记录在告警中用于按参数组合统计
+    pub fn params_label(&self) -> String {
+        let mut label = format!(
+            "red_count={} min_decline={} min_gain={}",
+            self.red_count, self.min_sell_decline, self.min_gain_threshold
+        );
+        if self.interval_secs != 60 {
+            label.push_str(&format!(
+                " interval={}",
+                kline::interval_label(self.interval_secs)
+            ));
+        }
+        label
+    }
+
+    /// 连续阴线、反转阳线和最后一根形成中的K线
+    pub fn required_lookback(&self) -> usize {
+        self.red_count + 2
+    }
+
+    /// K线的卖出量：成交量 = 买入 + 卖出，净流入 = 买入 - 卖出
+    fn sell_volume(kline: &KLineData) -> Option<Decimal> {
+        let volume = kline.volume_sol.parse::<Decimal>().ok()?;
+        let net_flow = kline.net_flow_sol.parse::<Decimal>().ok()?;
+        Some(((volume - net_flow) / Decimal::TWO).max(Decimal::ZERO))
+    }
+
+    /// 检测卖压衰竭，最后一根K线视为形成中的K线不参与检测。
+    /// 阴线需要全部为净流出且卖出量严格递减，反转阳线需要净流入为正
+    pub fn detect(&self, klines: &[KLineData]) -> Option<SellExhaustion> {
+        if self.red_count < 2 || klines.len() < self.required_lookback() {
+            return None;
+        }
+
+        let mut sorted_klines = klines.to_vec();
+        sorted_klines.sort_by_key(|k| k.timestamp);
+
+        // 排除最后一根形成中的K线，倒数第2根为反转阳线，之前为连续阴线
+        let end_index = sorted_klines.len() - 1;
+        let pattern_klines = &sorted_klines[end_index - self.red_count - 1..end_index];
+        let (reds, reversal) = pattern_klines.split_at(self.red_count);
+        let reversal = &reversal[0];
+
+        let mut sell_volumes = Vec::with_capacity(self.red_count);
+        for kline in reds {
+            let open = kline.open.parse::<Decimal>().ok()?;
+            let close = kline.close.parse::<Decimal>().ok()?;
+            let net_flow = kline.net_flow_sol.parse::<Decimal>().ok()?;
+            if close >= open || net_flow >= Decimal::ZERO {
+                return None;
+            }
+            sell_volumes.push(Self::sell_volume(kline)?);
+        }
+        if sell_volumes.windows(2).any(|w| w[1] >= w[0]) {
+            return None;
+        }
+        let (first, last) = (sell_volumes[0], sell_volumes[sell_volumes.len() - 1]);
+        let decline = (first - last) / first * Decimal::ONE_HUNDRED;
+        if decline < self.min_sell_decline {
+            return None;
+        }
+
+        let open = reversal.open.parse::<Decimal>().ok()?;
+        let close = reversal.close.parse::<Decimal>().ok()?;
+        let reversal_net_flow = reversal.net_flow_sol.parse::<Decimal>().ok()?;
+        if open <= Decimal::ZERO || close <= open || reversal_net_flow <= Decimal::ZERO {
+            return None;
+        }
+        let reversal_gain = (close - open) / open * Decimal::ONE_HUNDRED;
+        if reversal_gain < self.min_gain_threshold {
+            return None;
+        }
+
+        Some(SellExhaustion {
+            sell_volumes,
+            reversal_gain,
+            reversal_net_flow,
+            klines: pattern_klines.to_vec(),
+        })
+    }
+}
+
+/// 从环境变量读取策略的K线周期（如 "5m"），未配置时为1分钟。
+/// 只能使用1分钟或 KLINE_ROLLUP_INTERVALS 中的周期，其他值回退为1分钟
+fn interval_from_env(name: &str) -> i64 {
+    let Ok(value) = remote_config::var(name) else {
+        return 60;
+    };
+    match crate::parse_window(&value) {
+        Some(60) => 60,
+        Some(secs) if kline::rollup_intervals().contains(&secs) => secs,
+        _ => {
+            warn!(
+                "⚠️ {}={} 不是1分钟或 KLINE_ROLLUP_INTERVALS 中的周期，使用1分钟K线",
+                name, value
+            );
+            60
+        }
+    }
+}
+
+/// 策略插件：检测一个mint最近的K线，满足条件时返回告警。
+/// 新策略实现该trait并注册到 `StrategyRegistry`，无需修改引擎的检测循环
+pub trait Strategy: Send + Sync {
+    /// 策略名称，用作告警的 strategy_name，在注册表中唯一
+    fn name(&self) -> &str;
+
+    /// 检测需要的K线数量（包含最后一根形成中的K线），引擎按同一周期所有策略的最大值获取一次
+    fn required_lookback(&self) -> usize;
+
+    /// 检测使用的K线周期（秒），默认1分钟。其他周期需在 KLINE_ROLLUP_INTERVALS 中配置
+    fn interval_secs(&self) -> i64 {
+        60
+    }
+
+    /// 是否启用，未启用的策略不参与检测
+    fn is_enabled(&self) -> bool {
+        true
+    }
+
+    /// K线修正后是否重新评估：不再成立的告警会收到失效通知
+    fn reevaluate_on_correction(&self) -> bool {
+        true
+    }
+
+    /// 检测mint的K线（按时间升序，最后一根为形成中的K线），`now` 为告警时间。
+    /// 需要读取其他数据（如持有人快照）的策略在返回的future中异步读取
+    fn evaluate<'a>(
+        &'a self,
+        mint: &'a str,
+        klines: &'a [KLineData],
+        now: i64,
+    ) -> BoxFuture<'a, Option<StrategyAlert>>;
+}
+
+impl Strategy for ConsecutiveRisingPattern {
+    fn name(&self) -> &str {
+        RISING_STRATEGY_NAME
+    }
+
+    fn required_lookback(&self) -> usize {
+        ConsecutiveRisingPattern::required_lookback(self)
+    }
+
+    fn interval_secs(&self) -> i64 {
+        self.interval_secs
+    }
+
+    fn evaluate<'a>(
+        &'a self,
+        mint: &'a str,
+        klines: &'a [KLineData],
+        now: i64,
+    ) -> BoxFuture<'a, Option<StrategyAlert>> {
+        Box::pin(async move {
+            debug!(
+                "🔍 策略检测 {} - 总K线数: {}, 检测最近{}根已收盘K线",
+                mint,
+                klines.len(),
+                self.consecutive_count
+            );
+            let rising = self.detect(klines)?;
+            let format = NotificationFormat::current().await;
+            Some(self.alert(mint, rising, now, &format))
+        })
+    }
+}
+
+impl ConsecutiveRisingPattern {
+    /// 用检测到的连续上涨（涨幅序列和对应K线）构造告警
+    fn alert(
+        &self,
+        mint: &str,
+        (gains, recent_klines): (Vec<Decimal>, Vec<KLineData>),
+        now: i64,
+        format: &NotificationFormat,
+    ) -> StrategyAlert {
         // 构造告警消息
         let total_gain: Decimal = gains.iter().sum();
         let gain_sequence: Vec<String> = gains.iter().map(|g| format!("{:.2}%", g)).collect();
@@ -192,35 +497,1199 @@ impl StrategyEngine {
         info!(
             "✅ 连续上涨模式检测成功 {} - 连续{}根阳线, 涨幅序列: [{}], 总涨幅: {:.2}%",
             mint,
-            pattern.consecutive_count,
+            self.consecutive_count,
             gain_sequence.join(", "),
             total_gain
         );
 
-        let message = format!(
+        let message = localized!(
+            format.language,
             "发现连续{}根递增上涨K线！总涨幅: {:.2}%, 涨幅序列: [{}]",
-            pattern.consecutive_count,
+            "{} consecutive rising candles with growing gains! Total gain: {:.2}%, gains: [{}]",
+            self.consecutive_count,
             total_gain,
             gain_sequence.join(", ")
         );
 
-        Some(StrategyAlert {
+        StrategyAlert {
             mint: mint.to_string(),
-            strategy_name: "连续递增上涨模式".to_string(),
+            strategy_name: RISING_STRATEGY_NAME.to_string(),
             message,
-            timestamp: chrono::Local::now().timestamp(),
-            klines: recent_klines.to_vec(),
+            timestamp: now,
+            severity: None,
+            params: Some(self.params_label()),
+            klines: recent_klines,
+        }
+    }
+}
+
+impl Strategy for HolderGrowthPattern {
+    fn name(&self) -> &str {
+        HOLDER_GROWTH_STRATEGY_NAME
+    }
+
+    fn required_lookback(&self) -> usize {
+        HolderGrowthPattern::required_lookback(self)
+    }
+
+    fn is_enabled(&self) -> bool {
+        HolderGrowthPattern::is_enabled(self)
+    }
+
+    /// 没有持有人快照的mint不会触发
+    fn evaluate<'a>(
+        &'a self,
+        mint: &'a str,
+        klines: &'a [KLineData],
+        now: i64,
+    ) -> BoxFuture<'a, Option<StrategyAlert>> {
+        Box::pin(async move {
+            let snapshots = match holders::get_snapshots(mint, now - self.window_secs).await {
+                Ok(snapshots) => snapshots,
+                Err(e) => {
+                    warn!("⚠️ 获取持有人快照失败 {}: {}", mint, e);
+                    return None;
+                }
+            };
+            let (growth, net_flow) = self.detect(&snapshots, klines, now)?;
+            let (first, last) = (snapshots.first()?, snapshots.last()?);
+            let format = NotificationFormat::current().await;
+
+            info!(
+                "✅ 持有人增长检测成功 {} - {} -> {}, {:.1}/分钟, 净流入: {} SOL",
+                mint, first.count, last.count, growth, net_flow
+            );
+
+            Some(StrategyAlert {
+                mint: mint.to_string(),
+                strategy_name: HOLDER_GROWTH_STRATEGY_NAME.to_string(),
+                message: localized!(
+                    format.language,
+                    "持有人{}分钟内从 {} 增至 {}（{:.1}/分钟），净流入: {}",
+                    "Holders grew from {1} to {2} in {0} minutes ({3:.1}/min), net flow: {4}",
+                    (last.timestamp - first.timestamp) / 60,
+                    first.count,
+                    last.count,
+                    growth,
+                    format.amount(net_flow)
+                ),
+                timestamp: now,
+                severity: None,
+                params: Some(self.params_label()),
+                klines: klines.to_vec(),
+            })
         })
     }
+}
 
-    /// 持续运行策略检测
-    pub async fn run_continuous_check(&mut self, interval_secs: u64) -> Result<()> {
-        info!("🔄 开始持续策略检测，检测间隔: {}秒", interval_secs);
+impl Strategy for SellExhaustionPattern {
+    fn name(&self) -> &str {
+        SELL_EXHAUSTION_STRATEGY_NAME
+    }
+
+    fn required_lookback(&self) -> usize {
+        SellExhaustionPattern::required_lookback(self)
+    }
+
+    fn interval_secs(&self) -> i64 {
+        self.interval_secs
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn evaluate<'a>(
+        &'a self,
+        mint: &'a str,
+        klines: &'a [KLineData],
+        now: i64,
+    ) -> BoxFuture<'a, Option<StrategyAlert>> {
+        Box::pin(async move {
+            let exhaustion = self.detect(klines)?;
+            let format = NotificationFormat::current().await;
+            Some(self.alert(mint, exhaustion, now, &format))
+        })
+    }
+}
+
+impl SellExhaustionPattern {
+    /// 用检测到的卖压衰竭构造告警
+    fn alert(
+        &self,
+        mint: &str,
+        exhaustion: SellExhaustion,
+        now: i64,
+        format: &NotificationFormat,
+    ) -> StrategyAlert {
+        let volume_sequence: Vec<String> = exhaustion
+            .sell_volumes
+            .iter()
+            .map(|v| format.amount(*v))
+            .collect();
+
+        info!(
+            "✅ 卖压衰竭检测成功 {} - 连续{}根阴线卖出量: [{}] SOL, 反转阳线涨幅: {:.2}%, 净流入: {:.4} SOL",
+            mint,
+            self.red_count,
+            volume_sequence.join(", "),
+            exhaustion.reversal_gain,
+            exhaustion.reversal_net_flow
+        );
+
+        StrategyAlert {
+            mint: mint.to_string(),
+            strategy_name: SELL_EXHAUSTION_STRATEGY_NAME.to_string(),
+            message: localized!(
+                format.language,
+                "连续{}根阴线卖出量递减 [{}]，随后阳线上涨 {:.2}% 且净流入转正 {}，可能出现反转",
+                "Selling fell over {} red candles [{}], then a green candle gained {:.2}% with net flow turning positive at {}, a possible reversal",
+                self.red_count,
+                volume_sequence.join(" → "),
+                exhaustion.reversal_gain,
+                format.amount(exhaustion.reversal_net_flow)
+            ),
+            timestamp: now,
+            severity: None,
+            params: Some(self.params_label()),
+            klines: exhaustion.klines,
+        }
+    }
+}
+
+/// 注册的策略及其冷却时间
+pub struct RegisteredStrategy {
+    pub strategy: Box<dyn Strategy>,
+    /// 同一mint上两次告警的最小间隔（秒），0表示不限制
+    pub cooldown_secs: i64,
+    /// 是否为内置策略，内置策略的参数和冷却时间在每轮检测前重新读取
+    pub builtin: bool,
+}
+
+/// 策略注册表：引擎对每个mint依次运行其中所有启用的策略
+#[derive(Default)]
+pub struct StrategyRegistry {
+    strategies: Vec<RegisteredStrategy>,
+}
+
+impl StrategyRegistry {
+    /// 内置策略（连续上涨、持有人增长、卖压衰竭），参数和冷却时间从环境变量读取
+    pub fn builtin() -> Self {
+        let cooldown = |name: &str| {
+            remote_config::var(name)
+                .unwrap_or_else(|_| "0".to_string())
+                .parse::<i64>()
+                .unwrap_or(0)
+        };
+        let mut registry = Self::default();
+        registry.register(
+            Box::new(ConsecutiveRisingPattern::from_env()),
+            cooldown("STRATEGY_RISING_COOLDOWN_SECS"),
+        );
+        registry.register(
+            Box::new(HolderGrowthPattern::from_env()),
+            cooldown("STRATEGY_HOLDERS_COOLDOWN_SECS"),
+        );
+        registry.register(
+            Box::new(SellExhaustionPattern::from_env()),
+            cooldown("STRATEGY_EXHAUSTION_COOLDOWN_SECS"),
+        );
+        for registered in &mut registry.strategies {
+            registered.builtin = true;
+        }
+        registry
+    }
+
+    /// 重新读取内置策略的参数和冷却时间，使运行时覆盖值生效。
+    /// 已被同名自定义策略替换的内置策略保持替换
+    pub fn reload_builtin(&mut self) {
+        for fresh in Self::builtin().strategies {
+            match self
+                .strategies
+                .iter_mut()
+                .find(|s| s.strategy.name() == fresh.strategy.name())
+            {
+                Some(existing) if existing.builtin => *existing = fresh,
+                Some(_) => {}
+                None => self.strategies.push(fresh),
+            }
+        }
+    }
+
+    /// 注册策略，已有同名策略时替换它
+    pub fn register(&mut self, strategy: Box<dyn Strategy>, cooldown_secs: i64) {
+        let registered = RegisteredStrategy {
+            strategy,
+            cooldown_secs: cooldown_secs.max(0),
+            builtin: false,
+        };
+        match self
+            .strategies
+            .iter_mut()
+            .find(|s| s.strategy.name() == registered.strategy.name())
+        {
+            Some(existing) => *existing = registered,
+            None => self.strategies.push(registered),
+        }
+    }
+
+    /// 按名称查找策略
+    pub fn get(&self, name: &str) -> Option<&RegisteredStrategy> {
+        self.strategies.iter().find(|s| s.strategy.name() == name)
+    }
+
+    /// 启用的策略，按注册顺序
+    pub fn enabled(&self) -> impl Iterator<Item = &RegisteredStrategy> {
+        self.strategies.iter().filter(|s| s.strategy.is_enabled())
+    }
+
+    /// 启用的策略使用的K线周期，升序
+    pub fn intervals(&self) -> Vec<i64> {
+        let mut intervals: Vec<i64> = self.enabled().map(|s| s.strategy.interval_secs()).collect();
+        intervals.sort();
+        intervals.dedup();
+        intervals
+    }
+
+    /// 使用该周期的启用策略中最大的回溯K线数量
+    pub fn max_lookback(&self, interval_secs: i64) -> usize {
+        self.enabled()
+            .filter(|s| s.strategy.interval_secs() == interval_secs)
+            .map(|s| s.strategy.required_lookback())
+            .max()
+            .unwrap_or(10)
+    }
+}
+
+/// 每个策略在每个mint上的冷却结束时间。
+/// 只记录在当前进程内，多个worker各自计算冷却
+#[derive(Debug, Default)]
+pub struct StrategyCooldowns {
+    cooling_until: Mutex<HashMap<(String, String), i64>>,
+}
+
+impl StrategyCooldowns {
+    /// 冷却已结束时记录本次告警并返回true，冷却中返回false
+    pub fn try_start(&self, strategy: &str, mint: &str, cooldown_secs: i64, now: i64) -> bool {
+        if cooldown_secs <= 0 {
+            return true;
+        }
+        let mut cooling_until = self.cooling_until.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (strategy.to_string(), mint.to_string());
+        if cooling_until.get(&key).is_some_and(|until| now < *until) {
+            return false;
+        }
+        // 清理冷却已结束的记录，避免长期运行时无限增长
+        if cooling_until.len() >= 10_000 {
+            cooling_until.retain(|_, until| now < *until);
+        }
+        cooling_until.insert(key, now + cooldown_secs);
+        true
+    }
+}
+
+/// 策略检测的mint范围过滤，在获取K线之前执行，0表示不限制
+#[derive(Debug, Clone, Default)]
+pub struct UniverseFilter {
+    /// 最近活跃时间距今不超过该秒数
+    pub max_idle_secs: u64,
+    /// 最少K线数量
+    pub min_candles: usize,
+    /// 最少累计成交量（SOL）
+    pub min_volume_sol: Decimal,
+}
+
+impl UniverseFilter {
+    pub fn from_env() -> Self {
+        Self {
+            max_idle_secs: remote_config::var("STRATEGY_UNIVERSE_MAX_IDLE_SECS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0),
+            min_candles: remote_config::var("STRATEGY_UNIVERSE_MIN_CANDLES")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0),
+            min_volume_sol: remote_config::var("STRATEGY_UNIVERSE_MIN_VOLUME_SOL")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(Decimal::ZERO),
+        }
+    }
+
+    /// 是否需要按K线数量或成交量过滤
+    fn filters_size(&self) -> bool {
+        self.min_candles > 0 || self.min_volume_sol > Decimal::ZERO
+    }
+
+    fn accepts_size(&self, candles: usize, volume_sol: Decimal) -> bool {
+        candles >= self.min_candles && volume_sol >= self.min_volume_sol
+    }
+}
+
+/// 内置策略告警要求的最大市场beta，未配置时不过滤
+fn max_beta_from_env() -> Option<f64> {
+    remote_config::var("STRATEGY_MAX_BETA")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+}
+
+/// 按时间戳找回换算前的SOL K线
+fn sol_klines_at(sol_klines: &[KLineData], klines: &[KLineData]) -> Vec<KLineData> {
+    klines
+        .iter()
+        .filter_map(|k| {
+            sol_klines
+                .iter()
+                .find(|sol| sol.timestamp == k.timestamp)
+                .cloned()
+        })
+        .collect()
+}
+
+pub struct StrategyEngine {
+    kline_manager: Arc<KLineManager>,
+    notification_manager: NotificationManager,
+    /// 对每个mint运行的策略
+    strategies: StrategyRegistry,
+    /// 各策略在各mint上的冷却状态
+    cooldowns: StrategyCooldowns,
+    /// 存储每个mint最近检查的K线数据，避免重复检查
+    last_checked: HashMap<String, u64>,
+    /// 时钟，回放时使用回放时钟保证告警时间和冷却判断可复现
+    clock: SharedClock,
+    /// mint范围过滤
+    universe: UniverseFilter,
+    /// 开启预热时的K线内存缓存
+    candle_cache: Option<Mutex<CandleCache>>,
+    /// K线缓存由K线中心推送的更新保持最新，检测时不再从Redis读取
+    hub_updates: bool,
+    /// 内置策略使用按SOL/USD汇率换算为美元价格的K线检测，成交量和净流入仍为SOL，
+    /// 需要监控服务记录汇率历史（USD_CANDLES=true）。自定义规则始终使用SOL K线
+    usd_candles: bool,
+    /// 内置策略告警要求的最大市场beta，过滤随大盘整体上涨的mint（STRATEGY_MAX_BETA）
+    max_beta: Option<f64>,
+}
+
+impl StrategyEngine {
+    pub fn new(
+        kline_manager: Arc<KLineManager>,
+        notification_manager: NotificationManager,
+    ) -> Self {
+        Self {
+            kline_manager,
+            notification_manager,
+            strategies: StrategyRegistry::builtin(),
+            cooldowns: StrategyCooldowns::default(),
+            last_checked: HashMap::new(),
+            clock: clock::system_clock(),
+            universe: UniverseFilter::from_env(),
+            candle_cache: None,
+            hub_updates: false,
+            usd_candles: std::env::var("STRATEGY_USD_CANDLES")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            max_beta: max_beta_from_env(),
+        }
+        .with_warmup_config(&WarmupConfig::from_env())
+    }
+
+    /// 重新读取运行时可调的配置：内置策略的参数和冷却时间、mint范围过滤和最大beta。
+    /// 每轮检测前调用，覆盖值无需重启即可生效
+    fn reload_settings(&mut self) {
+        self.strategies.reload_builtin();
+        self.universe = UniverseFilter::from_env();
+        self.max_beta = max_beta_from_env();
+    }
+
+    /// 按预热配置启用或关闭K线内存缓存
+    pub fn with_warmup_config(mut self, config: &WarmupConfig) -> Self {
+        self.candle_cache = config
+            .enabled
+            .then(|| Mutex::new(CandleCache::new(config.max_mints)));
+        self
+    }
+
+    /// 使用指定时钟
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 注册额外的策略，已有同名策略时替换它
+    pub fn with_strategy(mut self, strategy: Box<dyn Strategy>, cooldown_secs: i64) -> Self {
+        self.strategies.register(strategy, cooldown_secs);
+        self
+    }
+
+    /// 1分钟K线策略中最大的回溯K线数量，每个mint只需获取一次
+    fn max_lookback(&self) -> usize {
+        self.strategies.max_lookback(60)
+    }
+
+    /// 预热：把最近活跃mint的K线预加载到内存缓存，重启后的第一轮检测不必逐个从Redis读取完整历史。
+    /// 未开启预热时不做任何事，返回预加载的mint数量
+    pub async fn warm_up(&self) -> Result<usize> {
+        let Some(cache) = &self.candle_cache else {
+            return Ok(0);
+        };
+        let capacity = cache.lock().unwrap_or_else(|e| e.into_inner()).capacity();
+        let started = std::time::Instant::now();
+
+        // get_active_mints按最近活跃排序，只预加载缓存容量内的mint
+        let now = self.clock.now().max(0) as u64;
+        let max_idle = self.universe.max_idle_secs;
+        let mints: Vec<String> = self
+            .kline_manager
+            .get_active_mints()
+            .await?
+            .into_iter()
+            .filter(|(_, last_activity, _)| {
+                max_idle == 0 || now.saturating_sub(*last_activity) <= max_idle
+            })
+            .take(capacity)
+            .map(|(mint, _, _)| mint)
+            .collect();
+        let accepted = self.filter_universe(&mints).await?;
+
+        let lookback = self.max_lookback();
+        let mut loaded = 0;
+        for mint in mints.iter().filter(|mint| accepted.contains(*mint)) {
+            let klines = self
+                .kline_manager
+                .get_klines_for_mint(mint, 60, Some(lookback))
+                .await?;
+            cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(mint, klines, lookback);
+            loaded += 1;
+        }
+
+        info!(
+            "🔥 预热完成：预加载 {} 个mint的K线，耗时 {:.1}秒",
+            loaded,
+            started.elapsed().as_secs_f64()
+        );
+        Ok(loaded)
+    }
+
+    /// 获取mint最近的K线。开启预热缓存时只读取缓存中最新K线之后的部分
+    async fn load_klines(&self, mint: &str, lookback: usize) -> Result<Vec<KLineData>> {
+        let Some(cache) = &self.candle_cache else {
+            return self
+                .kline_manager
+                .get_klines_for_mint(mint, 60, Some(lookback))
+                .await;
+        };
+
+        if self.hub_updates
+            && let Some(klines) = cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .synced(mint, lookback)
+        {
+            return Ok(klines);
+        }
+
+        let since = cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .last_timestamp(mint, lookback);
+        if let Some(since) = since {
+            let newer = self
+                .kline_manager
+                .get_klines_in_range(mint, 60, Some(since), None, None)
+                .await?;
+            if let Some(klines) = cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .merge(mint, newer, lookback)
+            {
+                return Ok(klines);
+            }
+        }
+
+        let klines = self
+            .kline_manager
+            .get_klines_for_mint(mint, 60, Some(lookback))
+            .await?;
+        cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(mint, klines.clone(), lookback);
+        Ok(klines)
+    }
+
+    /// 运行策略检测
+    pub async fn run_strategy_check(&mut self) -> Result<()> {
+        info!("🔍 开始运行策略检测...");
+        self.reload_settings();
+
+        // 获取所有活跃的mint
+        let active_mints = self.kline_manager.get_active_mints().await?;
+
+        info!("📊 发现 {} 个活跃 mint", active_mints.len());
+
+        // 按活跃时间过滤mint范围
+        let now = self.clock.now().max(0) as u64;
+        let max_idle = self.universe.max_idle_secs;
+        let active_mints: Vec<(String, u64, bool)> = active_mints
+            .into_iter()
+            .filter(|(_, last_activity, _)| {
+                max_idle == 0 || now.saturating_sub(*last_activity) <= max_idle
+            })
+            .collect();
+
+        // 设置了自定义规则的mint
+        let rule_mints = rules::get_rule_mints().await.unwrap_or_else(|e| {
+            warn!("⚠️ 获取自定义规则失败: {}", e);
+            HashSet::new()
+        });
+
+        // 检查是否需要检测这个mint（避免重复检测相同的数据）
+        let pending: Vec<(String, u64, bool)> = active_mints
+            .into_iter()
+            .filter(|(mint, last_activity, _)| {
+                self.last_checked
+                    .get(mint)
+                    .is_none_or(|last_check| *last_activity > *last_check)
+            })
+            .collect();
+
+        // 按K线数量和成交量过滤mint范围
+        let mints: Vec<String> = pending.iter().map(|(mint, _, _)| mint.clone()).collect();
+        let accepted = self.filter_universe(&mints).await?;
+        let skipped = mints.len() - accepted.len();
+        if skipped > 0 {
+            debug!("🔍 mint范围过滤跳过 {} 个mint", skipped);
+        }
+
+        let mut triggered = Vec::new();
+        for (mint, last_activity, _complete) in pending {
+            if !accepted.contains(&mint) {
+                continue;
+            }
+
+            // 单个mint检测失败不影响其他mint，未更新检查时间的mint下一轮重试
+            match self.check_mint(&mint, &rule_mints).await {
+                Ok(alerts) => triggered.extend(alerts),
+                Err(e) => {
+                    warn!("❌ 策略检测出错 {}: {}", mint, e);
+                    continue;
+                }
+            }
+
+            // 更新最后检查时间
+            self.last_checked.insert(mint, last_activity);
+        }
+
+        // 本轮所有告警一起发送，数量较多时合并为汇总通知
+        self.dispatch_alerts(&triggered).await;
+
+        Ok(())
+    }
+
+    /// 返回满足K线数量和成交量要求的mint，被识别为垃圾代币的mint不参与检测
+    async fn filter_universe(&self, mints: &[String]) -> Result<HashSet<String>> {
+        let blacklist = spam::blacklist_snapshot().await;
+        let allowed: Vec<String> = mints
+            .iter()
+            .filter(|mint| !blacklist.contains(*mint))
+            .cloned()
+            .collect();
+        let mints = allowed.as_slice();
+        if !self.universe.filters_size() {
+            return Ok(mints.iter().cloned().collect());
+        }
+        let sizes = self.kline_manager.get_mint_sizes(mints).await?;
+        Ok(mints
+            .iter()
+            .zip(sizes)
+            .filter(|(_, (candles, volume))| self.universe.accepts_size(*candles, *volume))
+            .map(|(mint, _)| mint.clone())
+            .collect())
+    }
 
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(interval_secs));
+    /// 对单个mint运行所有策略和自定义规则，返回冷却已结束的告警
+    async fn check_mint(
+        &self,
+        mint: &str,
+        rule_mints: &HashSet<String>,
+    ) -> Result<Vec<StrategyAlert>> {
+        let alerts = self.evaluate_mint(mint, rule_mints, None).await?;
+        Ok(self.apply_cooldowns(alerts))
+    }
+
+    /// 去掉冷却中的策略告警，并为其余告警开始冷却。自定义规则有自己的冷却时间，不在此处理
+    fn apply_cooldowns(&self, alerts: Vec<StrategyAlert>) -> Vec<StrategyAlert> {
+        let now = self.clock.now();
+        alerts
+            .into_iter()
+            .filter(|alert| {
+                let Some(registered) = self.strategies.get(&alert.strategy_name) else {
+                    return true;
+                };
+                let started = self.cooldowns.try_start(
+                    &alert.strategy_name,
+                    &alert.mint,
+                    registered.cooldown_secs,
+                    now,
+                );
+                if !started {
+                    debug!("⏳ {} 策略冷却中，跳过 {}", alert.strategy_name, alert.mint);
+                }
+                started
+            })
+            .collect()
+    }
+
+    /// 对单个mint运行所有策略和自定义规则，返回触发的告警（不考虑策略冷却）。
+    /// `until` 指定时只使用该时间戳及之前的K线，用于重新检测历史窗口
+    async fn evaluate_mint(
+        &self,
+        mint: &str,
+        rule_mints: &HashSet<String>,
+        until: Option<i64>,
+    ) -> Result<Vec<StrategyAlert>> {
+        let mint_rules = if rule_mints.contains(mint) {
+            rules::get_rules(mint).await?
+        } else {
+            Vec::new()
+        };
+        let mint_lookback = mint_rules
+            .iter()
+            .map(|r| r.condition.required_lookback())
+            .fold(self.max_lookback(), usize::max);
+
+        // 每个周期获取一次K线（按该周期策略的最大回溯数量，各策略共享），
+        // 1分钟K线同时用于自定义规则
+        let mut intervals = self.strategies.intervals();
+        if !mint_rules.is_empty() && !intervals.contains(&60) {
+            intervals.push(60);
+        }
+        let mut series: HashMap<i64, Vec<KLineData>> = HashMap::new();
+        for interval in intervals {
+            let klines = match (interval, until) {
+                (60, Some(until)) => {
+                    self.kline_manager
+                        .get_klines_in_range(mint, 60, None, Some(until), Some(mint_lookback))
+                        .await?
+                }
+                (60, None) => self.load_klines(mint, mint_lookback).await?,
+                (interval, until) => self.load_rollup_klines(mint, interval, until).await?,
+            };
+            series.insert(interval, klines);
+        }
+        // 策略使用美元价格K线时另外换算一份，自定义规则的阈值以SOL计，始终使用SOL K线
+        let mut usd_series: HashMap<i64, Vec<KLineData>> = HashMap::new();
+        if self.usd_candles {
+            for (interval, klines) in &series {
+                match price_feed::to_usd_prices(klines.clone()).await {
+                    Ok(klines) => usd_series.insert(*interval, klines),
+                    Err(e) => {
+                        debug!("⚠️ {} 换算美元K线失败，跳过检测: {}", mint, e);
+                        return Ok(Vec::new());
+                    }
+                };
+            }
+        }
+        let klines = series.get(&60).map(Vec::as_slice).unwrap_or_default();
+
+        // 依次运行所有启用的策略，各自使用其周期的K线
+        let now = self.clock.now();
+        let mut triggered = Vec::new();
+        for registered in self.strategies.enabled() {
+            let interval = registered.strategy.interval_secs();
+            let sol_klines = series.get(&interval).map(Vec::as_slice).unwrap_or_default();
+            let strategy_klines = match usd_series.get(&interval) {
+                Some(klines) => klines.as_slice(),
+                None => sol_klines,
+            };
+            if let Some(mut alert) = registered
+                .strategy
+                .evaluate(mint, strategy_klines, now)
+                .await
+            {
+                // 告警附带的K线换回SOL，通知按NOTIFICATION_CURRENCY统一换算
+                if self.usd_candles {
+                    alert.klines = sol_klines_at(sol_klines, &alert.klines);
+                }
+                triggered.push(alert);
+            }
+        }
+
+        // 只保留独立于大盘的走势
+        if !triggered.is_empty()
+            && let Some(max_beta) = self.max_beta
+            && !self.is_idiosyncratic(mint, max_beta).await
+        {
+            triggered.clear();
+        }
+
+        // 检测自定义规则
+        triggered.extend(self.check_mint_rules(mint, mint_rules, klines).await);
+
+        Ok(triggered)
+    }
+
+    /// 获取mint最近的高周期K线。`until` 指定时从1分钟K线重新聚合到该时间戳为止，
+    /// 得到当时的K线，而不是之后的K线更新过的汇总K线
+    async fn load_rollup_klines(
+        &self,
+        mint: &str,
+        interval_secs: i64,
+        until: Option<i64>,
+    ) -> Result<Vec<KLineData>> {
+        let lookback = self.strategies.max_lookback(interval_secs);
+        let Some(until) = until else {
+            return self
+                .kline_manager
+                .get_klines_for_mint(mint, interval_secs, Some(lookback))
+                .await;
+        };
+        let from = until - until.rem_euclid(interval_secs) - (lookback as i64 - 1) * interval_secs;
+        let minutes = self
+            .kline_manager
+            .get_klines_in_range(mint, 60, Some(from), Some(until), None)
+            .await?;
+        let mut aggregator = KLineAggregator::new(interval_secs);
+        let mut klines: Vec<KLineData> = minutes
+            .into_iter()
+            .filter_map(|minute| aggregator.push(minute))
+            .collect();
+        klines.extend(aggregator.finish());
+        Ok(klines)
+    }
+
+    /// 处理K线修正事件：重新检测mint，只返回此前未在该K线上触发过的告警，
+    /// 以及不再成立的告警对应的失效通知
+    async fn check_correction(
+        &self,
+        mint: &str,
+        candle_ts: i64,
+        rule_mints: &HashSet<String>,
+    ) -> Result<Vec<StrategyAlert>> {
+        // 缓存中的旧K线已过时，重新完整加载
+        if let Some(cache) = &self.candle_cache {
+            cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .invalidate(mint);
+        }
+        // 该K线收盘时触发的告警标记在它或紧随其后的K线上
+        let previous: Vec<Annotation> =
+            annotations::get_annotations(mint, candle_ts, candle_ts + 60)
+                .await?
+                .into_iter()
+                .filter(|a| !a.invalidated)
+                .collect();
+
+        // 按告警触发时的K线窗口重新检测，而不是当前最新窗口，之后的K线不影响结果。
+        // 收盘事件检测的窗口以紧随其后的K线结尾；自定义规则只在该窗口检测
+        let fired_until = candle_ts + 60;
+        let mut windows: HashMap<i64, Vec<StrategyAlert>> = HashMap::new();
+        windows.insert(
+            fired_until,
+            self.evaluate_mint(mint, rule_mints, Some(fired_until))
+                .await?,
+        );
+        for annotation in &previous {
+            if let Entry::Vacant(window) = windows.entry(annotation.timestamp) {
+                window.insert(
+                    self.evaluate_mint(mint, &HashSet::new(), Some(annotation.timestamp))
+                        .await?,
+                );
+            }
+        }
+
+        let mut triggered: Vec<StrategyAlert> = windows[&fired_until]
+            .iter()
+            .filter(|alert| {
+                !previous
+                    .iter()
+                    .any(|a| a.strategy_name == alert.strategy_name)
+            })
+            .cloned()
+            .collect();
+
+        for annotation in previous {
+            let name = annotation.strategy_name.as_str();
+            // 自定义规则有冷却时间，重新检测不会再次触发，因此不重新评估
+            let reevaluated = self
+                .strategies
+                .get(name)
+                .is_some_and(|s| s.strategy.reevaluate_on_correction());
+            let still_fires = windows[&annotation.timestamp]
+                .iter()
+                .any(|alert| alert.strategy_name == name);
+            if !reevaluated || still_fires {
+                continue;
+            }
+            annotations::invalidate_annotation(mint, &annotation).await?;
+            info!("♻️ K线修正后告警失效: {} - {}", mint, name);
+            let format = NotificationFormat::current().await;
+            triggered.push(StrategyAlert {
+                mint: mint.to_string(),
+                strategy_name: "告警失效".to_string(),
+                message: localized!(
+                    format.language,
+                    "延迟到达的交易修正了 {} 的K线，此前的「{}」告警不再成立: {}",
+                    "Late trades corrected the {} candle, the earlier \"{}\" alert no longer holds: {}",
+                    chrono::DateTime::from_timestamp(candle_ts, 0)
+                        .map(|dt| dt.with_timezone(&chrono::Local).format("%H:%M").to_string())
+                        .unwrap_or_default(),
+                    format.language.strategy_label(name),
+                    annotation.message
+                ),
+                timestamp: self.clock.now(),
+                severity: None,
+                params: None,
+                klines: Vec::new(),
+            });
+        }
+        Ok(self.apply_cooldowns(triggered))
+    }
+
+    /// 记录并发送一轮检测产生的告警
+    async fn dispatch_alerts(&self, triggered: &[StrategyAlert]) {
+        // 可疑mint附加Top钱包集群分析
+        let mut triggered = triggered.to_vec();
+        clusters::annotate_alerts(&mut triggered).await;
+        severity::score_alerts(&mut triggered).await;
+
+        for alert in &triggered {
+            info!("🚨 策略触发: {} - {}", alert.strategy_name, alert.message);
+
+            // 记录告警历史
+            if let Err(e) = alerts::record_alert(alert).await {
+                warn!("⚠️ 记录告警历史失败: {}", e);
+            }
+        }
+
+        metrics::record_alerts(triggered.len());
+
+        // 发送通知
+        let delivered = match self
+            .notification_manager
+            .send_notifications(&triggered)
+            .await
+        {
+            Ok(delivered) => delivered,
+            Err(e) => {
+                warn!("❌ 通知发送失败: {}", e);
+                return;
+            }
+        };
+
+        // 只有实际发送的规则告警才记录规则的触发时间
+        for alert in delivered {
+            let Some(id) = alert.params.as_deref().and_then(rules::rule_id_from_params) else {
+                continue;
+            };
+            if let Err(e) = rules::record_triggered(&alert.mint, id, alert.timestamp).await {
+                warn!("⚠️ 更新规则触发时间失败: {}", e);
+            }
+        }
+    }
+
+    /// 检测mint的自定义规则，冷却时间内已触发的规则会被跳过。
+    /// 触发时间在告警发送后才保存，未发送的告警由内存中的冷却避免每轮重复触发
+    async fn check_mint_rules(
+        &self,
+        mint: &str,
+        mint_rules: Vec<MintRule>,
+        klines: &[KLineData],
+    ) -> Vec<StrategyAlert> {
+        if mint_rules.is_empty() {
+            return Vec::new();
+        }
+        let now = self.clock.now();
+        let cooldown = rules::rule_cooldown_secs();
+        let format = NotificationFormat::current().await;
+        let mut triggered = Vec::new();
+
+        for rule in mint_rules {
+            if rule.last_triggered_at.is_some_and(|t| now - t < cooldown) {
+                continue;
+            }
+            let Some(description) = rule.condition.evaluate(klines, &format) else {
+                continue;
+            };
+            let params = rules::rule_alert_params(&rule.id);
+            if !self.cooldowns.try_start(&params, mint, cooldown, now) {
+                continue;
+            }
+
+            let message = match &rule.note {
+                Some(note) => format!("{} ({})", description, note),
+                None => description,
+            };
+            triggered.push(StrategyAlert {
+                mint: mint.to_string(),
+                strategy_name: "自定义规则".to_string(),
+                message,
+                timestamp: now,
+                severity: None,
+                params: Some(params),
+                klines: klines.to_vec(),
+            });
+        }
+
+        triggered
+    }
+
+    /// mint的市场beta是否不超过上限；数据不足或计算失败时不过滤
+    async fn is_idiosyncratic(&self, mint: &str, max_beta: f64) -> bool {
+        match market_beta::get_mint_indicators(&self.kline_manager, mint).await {
+            Ok(indicators) => match indicators.market {
+                Some(market) if market.beta > max_beta => {
+                    info!(
+                        "🌊 {} 走势跟随大盘 (beta {:.2} > {:.2}, 相关系数 {:.2})，忽略策略告警",
+                        mint, market.beta, max_beta, market.correlation
+                    );
+                    false
+                }
+                _ => true,
+            },
+            Err(e) => {
+                warn!("⚠️ 计算 {} 市场beta失败: {}", mint, e);
+                true
+            }
+        }
+    }
+
+    /// 消费K线收盘事件流运行策略检测（at-least-once）。
+    /// 启动时先重放本consumer未ack的事件，之后阻塞读取新事件；
+    /// 多个worker使用同一group、不同consumer名称即可水平扩展。
+    pub async fn run_stream_consumer(&mut self, group: &str, consumer: &str) -> Result<()> {
+        info!(
+            "📥 开始消费K线收盘事件流 - group: {}, consumer: {}",
+            group, consumer
+        );
+
+        let mut stream = CandleStreamConsumer::new(group, consumer).await?;
+        // "0" 起始表示先重放pending事件，读完后切换为 ">" 读取新事件
+        let mut cursor = "0".to_string();
+        let claim_idle_ms = candle_stream::claim_idle_ms();
+        let mut last_claim = std::time::Instant::now();
 
         loop {
-            interval.tick().await;
+            // 定期接手已退出consumer遗留的pending事件
+            let mut claimed = Vec::new();
+            if cursor == ">" && last_claim.elapsed().as_millis() as u64 >= claim_idle_ms / 2 {
+                last_claim = std::time::Instant::now();
+                match stream.claim_stale(claim_idle_ms, 100).await {
+                    Ok(events) if !events.is_empty() => {
+                        info!("📥 接手 {} 条超时未ack的K线事件", events.len());
+                        claimed = events;
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("⚠️ 认领pending K线事件失败: {}", e),
+                }
+            }
+
+            let events = if !claimed.is_empty() {
+                claimed
+            } else {
+                match stream.read(&cursor, 100, 5000).await {
+                    Ok(events) => events,
+                    Err(e) => {
+                        warn!("❌ 读取K线事件流失败: {}", e);
+                        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                        continue;
+                    }
+                }
+            };
+
+            if cursor != ">" {
+                match events.last() {
+                    Some(last) => cursor = last.id.clone(),
+                    None => {
+                        info!("✅ pending事件重放完成");
+                        cursor = ">".to_string();
+                        continue;
+                    }
+                }
+            }
+
+            if events.is_empty() {
+                continue;
+            }
+
+            // 等待最新收盘的K线定稿，留出时间让延迟到达的交易写入
+            let settle_until =
+                events.iter().map(|e| e.closed_before).max().unwrap_or(0) + settle_secs();
+            let wait = settle_until - self.clock.now();
+            if wait > 0 {
+                tokio::time::sleep(tokio::time::Duration::from_secs(wait as u64)).await;
+            }
+
+            self.reload_settings();
+            let rule_mints = rules::get_rule_mints().await.unwrap_or_else(|e| {
+                warn!("⚠️ 获取自定义规则失败: {}", e);
+                HashSet::new()
+            });
+
+            // 收盘事件本身说明mint刚刚活跃，只按K线数量和成交量过滤
+            let mut batch_mints: Vec<String> = events.iter().map(|e| e.mint.clone()).collect();
+            batch_mints.sort();
+            batch_mints.dedup();
+            let accepted = match self.filter_universe(&batch_mints).await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("⚠️ mint范围过滤失败: {}", e);
+                    batch_mints.into_iter().collect()
+                }
+            };
+
+            // 同一批次中同一mint的收盘事件只检测一次，修正事件按K线分别检测；
+            // 检测失败的事件不ack，留待重放
+            let mut results: HashMap<(String, Option<i64>), bool> = HashMap::new();
+            let mut acked = Vec::new();
+            let mut triggered = Vec::new();
+            for event in events {
+                let key = (event.mint.clone(), event.corrected);
+                let ok = match results.get(&key) {
+                    Some(&ok) => ok,
+                    None if event.mint.is_empty() || !accepted.contains(&event.mint) => true,
+                    None => {
+                        let checked = match event.corrected {
+                            Some(candle_ts) => {
+                                self.check_correction(&event.mint, candle_ts, &rule_mints)
+                                    .await
+                            }
+                            None => self.check_mint(&event.mint, &rule_mints).await,
+                        };
+                        let ok = match checked {
+                            Ok(alerts) => {
+                                triggered.extend(alerts);
+                                true
+                            }
+                            Err(e) => {
+                                warn!("❌ 策略检测出错 {}: {}", event.mint, e);
+                                false
+                            }
+                        };
+                        results.insert(key, ok);
+                        ok
+                    }
+                };
+                if ok {
+                    acked.push(event.id);
+                }
+            }
+
+            self.dispatch_alerts(&triggered).await;
+
+            if let Err(e) = stream.ack(&acked).await {
+                warn!("⚠️ ack K线事件失败: {}", e);
+            }
+        }
+    }
+
+    /// 订阅K线中心运行策略检测：监控服务推送的K线更新写入内存缓存，
+    /// mint开启新K线说明上一根已收盘，等待 `STRATEGY_SETTLE_SECS` 秒后检测该mint。
+    /// 检测使用缓存中的K线，只有不在缓存中的mint才从Redis加载；订阅落后丢失更新时，
+    /// 缓存的mint在下次检测前从Redis增量同步
+    pub async fn run_hub_subscriber(&mut self) -> Result<()> {
+        info!("📡 订阅K线中心运行策略检测");
+        // 先订阅再预热，预热期间的更新缓冲在订阅中，之后覆盖预加载的K线
+        let mut updates = candle_hub::hub().subscribe_all();
+        if self.candle_cache.is_none() {
+            self.candle_cache = Some(Mutex::new(CandleCache::new(
+                WarmupConfig::from_env().max_mints,
+            )));
+        }
+        self.hub_updates = true;
+        if let Err(e) = self.warm_up().await {
+            warn!("⚠️ 策略预热失败: {}", e);
+        }
+
+        // 等待检测的mint及其检测时间
+        let mut pending: HashMap<String, i64> = HashMap::new();
+        let mut tick = tokio::time::interval(tokio::time::Duration::from_secs(1));
+        loop {
+            tokio::select! {
+                update = updates.recv() => match update {
+                    Ok(update) => {
+                        if update.opened {
+                            pending
+                                .entry(update.mint.clone())
+                                .or_insert(update.kline.timestamp + settle_secs());
+                        }
+                        if let Some(cache) = &self.candle_cache {
+                            cache.lock().unwrap_or_else(|e| e.into_inner()).apply(&update.mint, update.kline);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!("⚠️ K线订阅落后，丢失 {} 条更新，缓存的K线将从Redis同步", missed);
+                        if let Some(cache) = &self.candle_cache {
+                            cache.lock().unwrap_or_else(|e| e.into_inner()).mark_unsynced();
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        anyhow::bail!("K线中心已关闭");
+                    }
+                },
+                _ = tick.tick() => {
+                    let now = self.clock.now();
+                    let due: Vec<String> = pending
+                        .iter()
+                        .filter(|(_, check_at)| **check_at <= now)
+                        .map(|(mint, _)| mint.clone())
+                        .collect();
+                    if due.is_empty() {
+                        continue;
+                    }
+                    for mint in &due {
+                        pending.remove(mint);
+                    }
+                    self.check_closed_mints(due).await;
+                }
+            }
+        }
+    }
+
+    /// 检测一批K线刚收盘的mint并发送告警，收盘本身说明mint刚刚活跃，只按K线数量和成交量过滤
+    async fn check_closed_mints(&mut self, mints: Vec<String>) {
+        self.reload_settings();
+        let rule_mints = rules::get_rule_mints().await.unwrap_or_else(|e| {
+            warn!("⚠️ 获取自定义规则失败: {}", e);
+            HashSet::new()
+        });
+        let accepted = match self.filter_universe(&mints).await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!("⚠️ mint范围过滤失败: {}", e);
+                mints.iter().cloned().collect()
+            }
+        };
+
+        let mut triggered = Vec::new();
+        for mint in mints.iter().filter(|mint| accepted.contains(*mint)) {
+            match self.check_mint(mint, &rule_mints).await {
+                Ok(alerts) => triggered.extend(alerts),
+                Err(e) => warn!("❌ 策略检测出错 {}: {}", mint, e),
+            }
+        }
+        self.dispatch_alerts(&triggered).await;
+    }
+
+    /// 持续运行策略检测，对齐K线边界：每个周期在K线收盘后 `STRATEGY_ALIGN_OFFSET_SECS` 秒执行，
+    /// 保证检测的是刚收盘的K线。周期向上取整为整分钟
+    pub async fn run_continuous_check(&mut self, interval_secs: u64) -> Result<()> {
+        let period_secs = interval_secs.div_ceil(60).max(1) * 60;
+        let offset_secs = align_offset_secs();
+        info!(
+            "🔄 开始持续策略检测，检测周期: {}秒，K线收盘后 {}秒 执行",
+            period_secs, offset_secs
+        );
+
+        loop {
+            let now_ms = chrono::Utc::now().timestamp_millis();
+            tokio::time::sleep(next_aligned_delay(now_ms, period_secs, offset_secs)).await;
 
             if let Err(e) = self.run_strategy_check().await {
                 warn!("❌ 策略检测出错: {}", e);
@@ -228,3 +1697,229 @@ impl StrategyEngine {
         }
     }
 }
+
+/// 流模式下K线收盘后等待延迟交易的秒数，之后才认为K线已定稿
+fn settle_secs() -> i64 {
+    std::env::var("STRATEGY_SETTLE_SECS")
+        .unwrap_or_else(|_| "5".to_string())
+        .parse()
+        .unwrap_or(5)
+}
+
+/// K线收盘后延迟执行的秒数，留出时间让边界上的交易写入
+fn align_offset_secs() -> u64 {
+    std::env::var("STRATEGY_ALIGN_OFFSET_SECS")
+        .unwrap_or_else(|_| "3".to_string())
+        .parse()
+        .unwrap_or(3)
+}
+
+/// 计算距离下一个对齐执行时间的等待时长：下一个 `period_secs` 整数倍时刻再加 `offset_secs`
+fn next_aligned_delay(now_ms: i64, period_secs: u64, offset_secs: u64) -> std::time::Duration {
+    let period_ms = period_secs as i64 * 1000;
+    let offset_ms = (offset_secs as i64 * 1000) % period_ms;
+    let elapsed_ms = (now_ms - offset_ms).rem_euclid(period_ms);
+    std::time::Duration::from_millis((period_ms - elapsed_ms) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness;
+
+    #[test]
+    fn test_holder_growth_pattern() {
+        let pattern = HolderGrowthPattern {
+            min_growth_per_minute: 5.0,
+            window_secs: 300,
+            min_net_flow_sol: Decimal::ZERO,
+        };
+        let kline = |timestamp: i64, net_flow: &str| KLineData {
+            net_flow_sol: net_flow.to_string(),
+            ..test_harness::candle(timestamp, "1")
+        };
+        let snapshots = [
+            HolderSnapshot {
+                timestamp: 600,
+                count: 100,
+            },
+            HolderSnapshot {
+                timestamp: 840,
+                count: 140,
+            },
+        ];
+        let klines = [kline(540, "-10"), kline(660, "2"), kline(840, "1")];
+        let (growth, net_flow) = pattern.detect(&snapshots, &klines, 900).unwrap();
+        assert_eq!(growth, 10.0);
+        assert_eq!(net_flow, Decimal::new(3, 0));
+
+        // Holders grow but money flows out
+        let klines = [kline(660, "2"), kline(840, "-3")];
+        assert!(pattern.detect(&snapshots, &klines, 900).is_none());
+    }
+
+    #[test]
+    fn test_sell_exhaustion_pattern() {
+        let pattern = SellExhaustionPattern {
+            enabled: true,
+            ..SellExhaustionPattern::default()
+        };
+        // (open, close, volume, net_flow), the last candle is still forming
+        let klines = |candles: &[(&str, &str, &str, &str)]| -> Vec<KLineData> {
+            candles
+                .iter()
+                .enumerate()
+                .map(|(i, (open, close, volume, net_flow))| KLineData {
+                    open: open.to_string(),
+                    high: open.max(close).to_string(),
+                    low: open.min(close).to_string(),
+                    volume_sol: volume.to_string(),
+                    net_flow_sol: net_flow.to_string(),
+                    ..test_harness::candle(i as i64 * 60, close)
+                })
+                .collect()
+        };
+
+        // Sells 10 -> 6 -> 4 SOL, then a green candle with net inflow
+        let reversal = klines(&[
+            ("1.0", "0.9", "12", "-8"),
+            ("0.9", "0.85", "8", "-4"),
+            ("0.85", "0.83", "5", "-3"),
+            ("0.83", "0.9", "6", "2"),
+            ("0.9", "0.9", "1", "0"),
+        ]);
+        let exhaustion = pattern.detect(&reversal).unwrap();
+        assert_eq!(
+            exhaustion.sell_volumes,
+            vec![Decimal::from(10), Decimal::from(6), Decimal::from(4)]
+        );
+        assert_eq!(exhaustion.reversal_net_flow, Decimal::from(2));
+        let format = NotificationFormat::default();
+        let alert = pattern.alert("A", exhaustion, 300, &format);
+        assert_eq!(alert.klines.len(), 4);
+        assert!(alert.message.contains("[10.00 SOL → 6.00 SOL → 4.00 SOL]"));
+
+        // Selling picks up again on the last red candle
+        let mut growing = reversal.clone();
+        growing[2].net_flow_sol = "-7".to_string();
+        growing[2].volume_sol = "15".to_string();
+        assert!(pattern.detect(&growing).is_none());
+
+        // Green candle without a net flow flip
+        let mut no_flip = reversal.clone();
+        no_flip[3].net_flow_sol = "-1".to_string();
+        assert!(pattern.detect(&no_flip).is_none());
+
+        // Decline below the threshold
+        let strict = SellExhaustionPattern {
+            min_sell_decline: Decimal::from(70),
+            ..pattern.clone()
+        };
+        assert!(strict.detect(&reversal).is_none());
+    }
+
+    #[test]
+    fn test_strategy_registry() {
+        let mut registry = StrategyRegistry::default();
+        registry.register(Box::new(ConsecutiveRisingPattern::default()), 0);
+        registry.register(
+            Box::new(HolderGrowthPattern {
+                min_growth_per_minute: 0.0,
+                window_secs: 600,
+                min_net_flow_sol: Decimal::ZERO,
+            }),
+            0,
+        );
+        // The disabled holder strategy neither runs nor widens the lookback
+        assert_eq!(registry.enabled().count(), 1);
+        assert_eq!(registry.max_lookback(60), 5);
+
+        let stricter = ConsecutiveRisingPattern {
+            consecutive_count: 6,
+            ..ConsecutiveRisingPattern::default()
+        };
+        registry.register(Box::new(stricter), 300);
+        assert_eq!(registry.enabled().count(), 1);
+        assert_eq!(registry.max_lookback(60), 7);
+
+        // A strategy on 5-minute candles gets its own lookback
+        registry.register(
+            Box::new(SellExhaustionPattern {
+                enabled: true,
+                interval_secs: 300,
+                ..SellExhaustionPattern::default()
+            }),
+            0,
+        );
+        assert_eq!(registry.intervals(), vec![60, 300]);
+        assert_eq!(registry.max_lookback(60), 7);
+        assert_eq!(registry.max_lookback(300), 5);
+        assert_eq!(
+            registry.get(RISING_STRATEGY_NAME).unwrap().cooldown_secs,
+            300
+        );
+
+        // Reloading the built-ins keeps strategies that replaced one of them
+        let mut reloaded = StrategyRegistry::builtin();
+        reloaded.register(
+            Box::new(ConsecutiveRisingPattern {
+                consecutive_count: 6,
+                ..ConsecutiveRisingPattern::default()
+            }),
+            300,
+        );
+        reloaded.reload_builtin();
+        let rising = reloaded.get(RISING_STRATEGY_NAME).unwrap();
+        assert!(!rising.builtin);
+        assert_eq!(rising.cooldown_secs, 300);
+        assert!(reloaded.get(SELL_EXHAUSTION_STRATEGY_NAME).unwrap().builtin);
+
+        let cooldowns = StrategyCooldowns::default();
+        assert!(cooldowns.try_start(RISING_STRATEGY_NAME, "A", 300, 1_000));
+        assert!(!cooldowns.try_start(RISING_STRATEGY_NAME, "A", 300, 1_299));
+        assert!(cooldowns.try_start(RISING_STRATEGY_NAME, "B", 300, 1_100));
+        assert!(cooldowns.try_start(HOLDER_GROWTH_STRATEGY_NAME, "A", 300, 1_100));
+        assert!(cooldowns.try_start(RISING_STRATEGY_NAME, "A", 300, 1_300));
+        assert!(cooldowns.try_start(RISING_STRATEGY_NAME, "A", 0, 1_301));
+    }
+
+    #[tokio::test]
+    async fn test_engine_cooldowns_follow_clock() {
+        // 手动时钟让冷却判断不依赖系统时间
+        let clock = Arc::new(crate::clock::ManualClock::new(1_000));
+        let engine = StrategyEngine::new(
+            Arc::new(KLineManager::new().await.unwrap()),
+            NotificationManager::new().unwrap(),
+        )
+        .with_strategy(Box::new(ConsecutiveRisingPattern::default()), 300)
+        .with_clock(clock.clone());
+        let alert = StrategyAlert {
+            mint: "A".to_string(),
+            strategy_name: RISING_STRATEGY_NAME.to_string(),
+            message: String::new(),
+            timestamp: 1_000,
+            severity: None,
+            params: None,
+            klines: Vec::new(),
+        };
+
+        assert_eq!(engine.apply_cooldowns(vec![alert.clone()]).len(), 1);
+        clock.advance(299);
+        assert!(engine.apply_cooldowns(vec![alert.clone()]).is_empty());
+        clock.advance(1);
+        assert_eq!(engine.apply_cooldowns(vec![alert]).len(), 1);
+    }
+
+    #[test]
+    fn test_next_aligned_delay() {
+        // 12:00:30 -> next run at 12:01:03
+        let now_ms = 1_700_000_000_000 - 1_700_000_000_000 % 60_000 + 30_000;
+        assert_eq!(next_aligned_delay(now_ms, 60, 3).as_secs(), 33);
+        // Exactly at the run time waits a full period instead of running twice
+        let at_run = now_ms - 30_000 + 3_000;
+        assert_eq!(next_aligned_delay(at_run, 60, 3).as_secs(), 60);
+        // Between the boundary and the offset, run in the same minute
+        let before_offset = now_ms - 30_000 + 1_000;
+        assert_eq!(next_aligned_delay(before_offset, 60, 3).as_secs(), 2);
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::Result;
+use futures_util::FutureExt;
+use redis::AsyncCommands;
+use std::any::Any;
+use std::collections::HashMap;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::task::JoinHandle;
+use tracing::{Instrument, error, warn};
+
+use crate::localized;
+use crate::notification::NotificationManager;
+use crate::notification_format::Language;
+use crate::redis_helper;
+use crate::remote_config;
+use crate::strategy::StrategyAlert;
+
+// Redis hash of panic counts by task name
+const TASK_STATS_KEY: &str = "stats:tasks";
+
+// Panics seen by this process, including ones outside supervised tasks
+static PANIC_COUNT: AtomicU64 = AtomicU64::new(0);
+
+// Whether task panics are also sent through the notification channels
+fn notify_enabled() -> bool {
+    remote_config::var("TASK_FAILURE_NOTIFY")
+        .unwrap_or_else(|_| "false".to_string())
+        .parse()
+        .unwrap_or(false)
+}
+
+// Log every panic with its thread and location before the default hook runs
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        PANIC_COUNT.fetch_add(1, Ordering::Relaxed);
+        let thread = std::thread::current();
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}", l.file(), l.line()))
+            .unwrap_or_else(|| "unknown".to_string());
+        error!(
+            "💥 Panic in thread {} at {}: {}",
+            thread.name().unwrap_or("unnamed"),
+            location,
+            panic_message(info.payload())
+        );
+        default_hook(info);
+    }));
+}
+
+// Number of panics since the process started
+pub fn panic_count() -> u64 {
+    PANIC_COUNT.load(Ordering::Relaxed)
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+// Spawn a background task whose panic is reported instead of silently ending the task.
+// The task runs in the caller's span, so traces follow work across the spawn.
+pub fn spawn_supervised<F>(name: &'static str, future: F) -> JoinHandle<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(
+        async move {
+            if let Err(payload) = AssertUnwindSafe(future).catch_unwind().await {
+                report_task_panic(name, &panic_message(payload.as_ref())).await;
+            }
+        }
+        .in_current_span(),
+    )
+}
+
+async fn report_task_panic(name: &str, message: &str) {
+    error!("💥 Background task {} panicked: {}", name, message);
+
+    let recorded = async {
+        let mut con = redis_helper::get_connection().await?;
+        let _: () = con
+            .hincr(TASK_STATS_KEY, format!("panic:{}", name), 1)
+            .await?;
+        Ok::<_, anyhow::Error>(())
+    };
+    if let Err(e) = recorded.await {
+        warn!("Failed to record panic of task {}: {}", name, e);
+    }
+
+    if notify_enabled() {
+        let alert = StrategyAlert {
+            mint: format!("task:{}", name),
+            strategy_name: "后台任务崩溃".to_string(),
+            message: localized!(
+                Language::from_env(),
+                "后台任务 {} panic: {}",
+                "Background task {} panicked: {}",
+                name,
+                message
+            ),
+            timestamp: chrono::Local::now().timestamp(),
+            severity: None,
+            params: None,
+            klines: Vec::new(),
+        };
+        let sent = match NotificationManager::new() {
+            Ok(manager) => manager.send_notification(&alert).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = sent {
+            warn!("Failed to notify panic of task {}: {}", name, e);
+        }
+    }
+}
+
+// Get panic counts by task name
+pub async fn get_task_stats() -> Result<HashMap<String, usize>> {
+    let mut con = redis_helper::get_connection().await?;
+    Ok(con.hgetall(TASK_STATS_KEY).await?)
+}
@@ -0,0 +1,189 @@
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::holders;
+use crate::launch;
+use crate::strategy::StrategyAlert;
+use crate::trades::{self, TradeRecord};
+
+/// 屏蔽条件，所需数据不存在时不屏蔽
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SuppressCondition {
+    /// 持有人数量低于，使用最近一次持有人快照
+    HoldersBelow { value: u64 },
+    /// 刷量评分高于，评分为最近N笔交易中既买又卖的钱包成交额占比（0-1）
+    WashScoreAbove {
+        value: f64,
+        #[serde(default = "default_wash_trades")]
+        trades: usize,
+    },
+    /// 发行后N分钟内，需要发行记录
+    LaunchedWithin { minutes: i64 },
+}
+
+fn default_wash_trades() -> usize {
+    100
+}
+
+/// 屏蔽规则：告警满足条件时不发送通知
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuppressionRule {
+    /// 规则名称，唯一
+    pub name: String,
+    /// 只作用于这些策略的告警，为空时作用于全部
+    #[serde(default)]
+    pub strategies: Vec<String>,
+    #[serde(flatten)]
+    pub condition: SuppressCondition,
+}
+
+/// 刷量评分：既买又卖的钱包成交额占总成交额的比例，没有成交额时返回None
+pub fn wash_score(trades: &[TradeRecord]) -> Option<f64> {
+    let mut wallets: HashMap<&str, (bool, bool, Decimal)> = HashMap::new();
+    for trade in trades {
+        let entry = wallets.entry(trade.user.as_str()).or_default();
+        if trade.is_buy {
+            entry.0 = true;
+        } else {
+            entry.1 = true;
+        }
+        entry.2 += trade.sol_amount;
+    }
+    let total: Decimal = wallets.values().map(|(_, _, volume)| volume).sum();
+    if total <= Decimal::ZERO {
+        return None;
+    }
+    let round_trips: Decimal = wallets
+        .values()
+        .filter(|(bought, sold, _)| *bought && *sold)
+        .map(|(_, _, volume)| volume)
+        .sum();
+    (round_trips / total).to_f64()
+}
+
+impl SuppressCondition {
+    /// 评估条件，满足时返回屏蔽原因
+    pub async fn evaluate(&self, mint: &str, now: i64) -> Result<Option<String>> {
+        match self {
+            SuppressCondition::HoldersBelow { value } => {
+                let latest = holders::get_snapshots(mint, 0).await?.pop();
+                Ok(latest
+                    .filter(|snapshot| snapshot.count < *value)
+                    .map(|snapshot| format!("持有人 {} 少于 {}", snapshot.count, value)))
+            }
+            SuppressCondition::WashScoreAbove { value, trades } => {
+                let recent = trades::get_trades_for_mint(mint, Some(*trades)).await?;
+                Ok(wash_score(&recent)
+                    .filter(|score| score > value)
+                    .map(|score| format!("刷量评分 {:.2} 高于 {}", score, value)))
+            }
+            SuppressCondition::LaunchedWithin { minutes } => {
+                let launch = launch::get_launch(mint).await?;
+                Ok(launch
+                    .map(|launch| now - launch.created_at)
+                    .filter(|age| *age < minutes * 60)
+                    .map(|age| format!("发行仅 {} 分钟", age / 60)))
+            }
+        }
+    }
+}
+
+/// 告警命中的第一条屏蔽规则及原因。条件评估失败时记录警告并视为未命中
+pub async fn check_alert(rules: &[SuppressionRule], alert: &StrategyAlert) -> Option<String> {
+    for rule in rules {
+        if !rule.strategies.is_empty() && !rule.strategies.contains(&alert.strategy_name) {
+            continue;
+        }
+        match rule.condition.evaluate(&alert.mint, alert.timestamp).await {
+            Ok(Some(reason)) => return Some(format!("{}: {}", rule.name, reason)),
+            Ok(None) => {}
+            Err(e) => warn!("⚠️ 屏蔽规则 {} 评估失败 {}: {}", rule.name, alert.mint, e),
+        }
+    }
+    None
+}
+
+/// 从 `SUPPRESSION_RULES_PATH` 指向的JSON文件读取屏蔽规则，未设置时没有规则。
+/// 规则名称重复或刷量评分阈值不在0-1之间时返回错误
+pub fn load_rules() -> Result<Vec<SuppressionRule>> {
+    let Some(path) = std::env::var("SUPPRESSION_RULES_PATH")
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+    else {
+        return Ok(Vec::new());
+    };
+    let data = std::fs::read_to_string(&path)
+        .with_context(|| format!("读取屏蔽规则文件 {} 失败", path))?;
+    let rules: Vec<SuppressionRule> =
+        serde_json::from_str(&data).with_context(|| format!("解析屏蔽规则文件 {} 失败", path))?;
+    validate_rules(&rules)?;
+    Ok(rules)
+}
+
+fn validate_rules(rules: &[SuppressionRule]) -> Result<()> {
+    for (i, rule) in rules.iter().enumerate() {
+        if rules[..i].iter().any(|r| r.name == rule.name) {
+            anyhow::bail!("屏蔽规则名称重复: {}", rule.name);
+        }
+        if let SuppressCondition::WashScoreAbove { value, .. } = rule.condition
+            && !(0.0..=1.0).contains(&value)
+        {
+            anyhow::bail!("屏蔽规则 {} 的刷量评分阈值应在0到1之间", rule.name);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(user: &str, is_buy: bool, sol: i64) -> TradeRecord {
+        TradeRecord {
+            signature: String::new(),
+            slot: 0,
+            mint: "M".to_string(),
+            user: user.to_string(),
+            is_buy,
+            price: Decimal::ONE,
+            sol_amount: Decimal::from(sol),
+            token_amount: Decimal::from(sol),
+            timestamp: 0,
+            source: None,
+            liquidity_sol: None,
+        }
+    }
+
+    #[test]
+    fn test_wash_score_and_rules() {
+        let trades = [
+            trade("A", true, 3),
+            trade("A", false, 3),
+            trade("B", true, 4),
+        ];
+        assert_eq!(wash_score(&trades), Some(0.6));
+        assert_eq!(wash_score(&[]), None);
+
+        let rules: Vec<SuppressionRule> = serde_json::from_str(
+            r#"[{"name": "wash", "kind": "wash_score_above", "value": 0.5},
+                {"name": "young", "strategies": ["持有人增长"], "kind": "launched_within", "minutes": 10}]"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            rules[0].condition,
+            SuppressCondition::WashScoreAbove { trades: 100, .. }
+        ));
+        assert!(validate_rules(&rules).is_ok());
+        assert!(validate_rules(&[rules[0].clone(), rules[0].clone()]).is_err());
+
+        let invalid: Vec<SuppressionRule> =
+            serde_json::from_str(r#"[{"name": "x", "kind": "wash_score_above", "value": 60}]"#)
+                .unwrap();
+        assert!(validate_rules(&invalid).is_err());
+    }
+}
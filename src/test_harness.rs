@@ -0,0 +1,398 @@
+// End-to-end test harness: an in-process websocket server that plays scripted
+// logsNotification payloads to a real WebSocketMonitor, plus helpers to build
+// Pump trade logs. A script can be replayed on a ReplayClock, which sends each
+// message once replay time reaches its recorded timestamp. Storage is a real Redis named by TEST_REDIS_URL; tests skip
+// when it is unset. Use a dedicated database, since the monitor's idle cleanup
+// runs against everything stored in it.
+
+use anyhow::Result;
+use base64::{Engine as _, engine::general_purpose};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use solana_sdk::pubkey::Pubkey;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+use crate::clock::ReplayClock;
+use crate::constant::{PUMP_PROGRAM, PUMP_TRADE_EVENT_DISCRIMINATOR};
+use crate::kline::KLineData;
+use crate::redis_helper;
+
+// Websocket server answering every subscription with the same scripted notifications
+pub struct MockWsServer {
+    pub endpoint: String,
+    // Subscription requests received, in order
+    pub requests: Arc<Mutex<Vec<Value>>>,
+    task: JoinHandle<()>,
+}
+
+impl MockWsServer {
+    pub async fn start(script: Vec<Value>) -> Result<Self> {
+        Self::start_paced(
+            script.into_iter().map(|message| (0, message)).collect(),
+            None,
+        )
+        .await
+    }
+
+    // Play (recorded timestamp, message) pairs at the pace of a replay clock
+    pub async fn start_replay(script: Vec<(i64, Value)>, clock: ReplayClock) -> Result<Self> {
+        Self::start_paced(script, Some(clock)).await
+    }
+
+    async fn start_paced(script: Vec<(i64, Value)>, clock: Option<ReplayClock>) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("ws://{}", listener.local_addr()?);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let received = Arc::clone(&requests);
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let Ok(mut ws) = accept_async(stream).await else {
+                    continue;
+                };
+                let Some(Ok(Message::Text(text))) = ws.next().await else {
+                    continue;
+                };
+                let request: Value = serde_json::from_str(&text).unwrap_or_default();
+                let id = request.get("id").cloned().unwrap_or(json!(1));
+                received.lock().unwrap().push(request);
+
+                let confirmation = json!({ "jsonrpc": "2.0", "result": 1, "id": id });
+                if ws
+                    .send(Message::text(confirmation.to_string()))
+                    .await
+                    .is_err()
+                {
+                    continue;
+                }
+                for (ts, message) in &script {
+                    if let Some(clock) = &clock {
+                        clock.sleep_until(*ts).await;
+                    }
+                    if ws.send(Message::text(message.to_string())).await.is_err() {
+                        break;
+                    }
+                }
+                // Keep the connection open until the client leaves
+                while let Some(Ok(_)) = ws.next().await {}
+            }
+        });
+
+        Ok(Self {
+            endpoint,
+            requests,
+            task,
+        })
+    }
+}
+
+impl Drop for MockWsServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// A Pump trade to encode as a TradeEvent log
+#[derive(Debug, Clone)]
+pub struct ScriptedTrade {
+    pub mint: Pubkey,
+    pub user: Pubkey,
+    pub sol_lamports: u64,
+    pub token_amount: u64,
+    pub is_buy: bool,
+    pub timestamp: i64,
+}
+
+// Flat candle with every price at `close`, 1 SOL and 1 token of volume and no
+// net flow. Tests set the fields they exercise with struct update syntax.
+pub fn candle(timestamp: i64, close: &str) -> KLineData {
+    KLineData {
+        timestamp,
+        open: close.to_string(),
+        high: close.to_string(),
+        low: close.to_string(),
+        close: close.to_string(),
+        volume_sol: "1".to_string(),
+        volume_token: "1".to_string(),
+        net_flow_sol: "0".to_string(),
+        last_update: 0,
+        source: None,
+        liquidity_sol: None,
+        turnover: None,
+        anomaly_score: None,
+        close_time: None,
+    }
+}
+
+// Random address on the ed25519 curve, as real mints are
+pub fn on_curve_pubkey() -> Pubkey {
+    loop {
+        let key = Pubkey::new_from_array(rand::random());
+        if key.is_on_curve() {
+            return key;
+        }
+    }
+}
+
+// "Program data: " payload of a Pump TradeEvent
+pub fn pump_trade_data(trade: &ScriptedTrade) -> String {
+    let mut data = Vec::with_capacity(129);
+    data.extend_from_slice(&PUMP_TRADE_EVENT_DISCRIMINATOR);
+    data.extend_from_slice(trade.mint.as_ref());
+    data.extend_from_slice(&trade.sol_lamports.to_le_bytes());
+    data.extend_from_slice(&trade.token_amount.to_le_bytes());
+    data.push(trade.is_buy as u8);
+    data.extend_from_slice(trade.user.as_ref());
+    data.extend_from_slice(&(trade.timestamp as u64).to_le_bytes());
+    // Virtual and real SOL and token reserves
+    for reserve in [
+        30_000_000_000u64,
+        800_000_000_000_000,
+        1_000_000_000,
+        700_000_000_000_000,
+    ] {
+        data.extend_from_slice(&reserve.to_le_bytes());
+    }
+    general_purpose::STANDARD.encode(data)
+}
+
+// Transaction logs of a successful Pump buy or sell
+pub fn pump_trade_logs(trade: &ScriptedTrade) -> Vec<String> {
+    vec![
+        format!("Program {} invoke [1]", PUMP_PROGRAM),
+        format!(
+            "Program log: Instruction: {}",
+            if trade.is_buy { "Buy" } else { "Sell" }
+        ),
+        format!("Program data: {}", pump_trade_data(trade)),
+        format!("Program {} success", PUMP_PROGRAM),
+    ]
+}
+
+// logsNotification message as sent by the RPC node
+pub fn logs_notification(signature: &str, slot: u64, logs: Vec<String>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "logsNotification",
+        "params": {
+            "result": {
+                "context": { "slot": slot },
+                "value": { "signature": signature, "err": null, "logs": logs }
+            },
+            "subscription": 1
+        }
+    })
+}
+
+// Connect the global Redis pool to TEST_REDIS_URL, false when it is unset
+pub async fn init_test_redis() -> Result<bool> {
+    let Ok(url) = std::env::var("TEST_REDIS_URL") else {
+        return Ok(false);
+    };
+    // Another test may have connected the pool already
+    if redis_helper::get_connection().await.is_err() {
+        redis_helper::init_pool_with_url(&url).await?;
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kline::KLineManager;
+    use crate::pump;
+    use crate::websocket::WebSocketMonitor;
+    use rust_decimal::Decimal;
+    use std::time::Duration;
+
+    #[test]
+    fn test_scripted_trade_parses() {
+        let trade = ScriptedTrade {
+            mint: on_curve_pubkey(),
+            user: Pubkey::new_unique(),
+            sol_lamports: 1_000_000_000,
+            token_amount: 30_000_000_000,
+            is_buy: false,
+            timestamp: 1_700_000_000,
+        };
+        let message = logs_notification("sig", 7, pump_trade_logs(&trade));
+        let events = pump::parse_trade_event(&message).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].mint, trade.mint.to_string());
+        assert_eq!(events[0].user, trade.user.to_string());
+        assert_eq!(events[0].sol_amount, trade.sol_lamports);
+        assert_eq!(events[0].slot, 7);
+        assert!(!events[0].is_buy);
+        assert!(events[0].success);
+    }
+
+    #[tokio::test]
+    async fn test_pump_trades_end_to_end() {
+        if !init_test_redis().await.unwrap() {
+            println!("Skipping test: TEST_REDIS_URL not set");
+            return;
+        }
+
+        let mint = on_curve_pubkey();
+        let now = chrono::Utc::now().timestamp();
+        let trade = |sol_lamports, token_amount, is_buy| ScriptedTrade {
+            mint,
+            user: Pubkey::new_unique(),
+            sol_lamports,
+            token_amount,
+            is_buy,
+            timestamp: now,
+        };
+        let script = vec![
+            logs_notification(
+                "e2e-buy",
+                1,
+                pump_trade_logs(&trade(1_000_000_000, 30_000_000_000, true)),
+            ),
+            logs_notification(
+                "e2e-sell",
+                2,
+                pump_trade_logs(&trade(500_000_000, 20_000_000_000, false)),
+            ),
+        ];
+        let server = MockWsServer::start(script).await.unwrap();
+
+        let kline_manager = Arc::new(KLineManager::new().await.unwrap());
+        let monitor = WebSocketMonitor::new(
+            server.endpoint.clone(),
+            Arc::clone(&kline_manager),
+            vec![PUMP_PROGRAM.to_string()],
+            "PUMP".to_string(),
+        );
+        let monitor_task = tokio::spawn(async move {
+            monitor
+                .start(|response: &Value, kline_manager: Arc<KLineManager>| {
+                    let response = response.clone();
+                    async move { pump::handle_pump_message(&response, kline_manager).await }
+                })
+                .await
+        });
+
+        // Trades are written by background tasks, wait until both are in the candle
+        let mint = mint.to_string();
+        let mut candles = Vec::new();
+        for _ in 0..100 {
+            candles = kline_manager
+                .get_klines_for_mint(&mint, 60, None)
+                .await
+                .unwrap();
+            if candles
+                .first()
+                .is_some_and(|k| k.volume_sol.parse::<Decimal>().ok() == Some(Decimal::new(15, 1)))
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        monitor_task.abort();
+
+        let requests = server.requests.lock().unwrap().clone();
+        assert_eq!(requests[0]["method"], "logsSubscribe");
+        assert_eq!(candles.len(), 1);
+        let candle = &candles[0];
+        let decimal = |value: &str| value.parse::<Decimal>().unwrap();
+        assert_eq!(decimal(&candle.open), Decimal::ONE / Decimal::from(30_000));
+        assert_eq!(
+            decimal(&candle.close),
+            Decimal::new(5, 1) / Decimal::from(20_000)
+        );
+        assert_eq!(decimal(&candle.volume_sol), Decimal::new(15, 1));
+        assert_eq!(decimal(&candle.net_flow_sol), Decimal::new(5, 1));
+
+        let to = candle.timestamp;
+        kline_manager
+            .delete_klines_in_range(&mint, to, to)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replay_buckets_by_recorded_time() {
+        if !init_test_redis().await.unwrap() {
+            println!("Skipping test: TEST_REDIS_URL not set");
+            return;
+        }
+
+        // Two minutes of trades recorded an hour ago, replayed at 100x in under a second
+        let mint = on_curve_pubkey();
+        let start = KLineManager::get_minute_timestamp(chrono::Utc::now().timestamp() - 3600);
+        let script: Vec<(i64, Value)> =
+            [(start + 10, true), (start + 70, true), (start + 80, false)]
+                .into_iter()
+                .enumerate()
+                .map(|(i, (timestamp, is_buy))| {
+                    let trade = ScriptedTrade {
+                        mint,
+                        user: Pubkey::new_unique(),
+                        sol_lamports: 1_000_000_000,
+                        token_amount: 30_000_000_000,
+                        is_buy,
+                        timestamp,
+                    };
+                    let message = logs_notification(
+                        &format!("replay-{}", i),
+                        i as u64,
+                        pump_trade_logs(&trade),
+                    );
+                    (timestamp, message)
+                })
+                .collect();
+
+        let clock = ReplayClock::new(start, 100.0);
+        let kline_manager = Arc::new(
+            KLineManager::new()
+                .await
+                .unwrap()
+                .with_clock(Arc::new(clock.clone())),
+        );
+        let server = MockWsServer::start_replay(script, clock).await.unwrap();
+        let monitor = WebSocketMonitor::new(
+            server.endpoint.clone(),
+            Arc::clone(&kline_manager),
+            vec![PUMP_PROGRAM.to_string()],
+            "PUMP".to_string(),
+        );
+        let monitor_task = tokio::spawn(async move {
+            monitor
+                .start(|response: &Value, kline_manager: Arc<KLineManager>| {
+                    let response = response.clone();
+                    async move { pump::handle_pump_message(&response, kline_manager).await }
+                })
+                .await
+        });
+
+        let mint = mint.to_string();
+        let mut candles = Vec::new();
+        for _ in 0..100 {
+            candles = kline_manager
+                .get_klines_for_mint(&mint, 60, None)
+                .await
+                .unwrap();
+            if candles.len() == 2
+                && candles[1].volume_sol.parse::<Decimal>().ok() == Some(Decimal::TWO)
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        monitor_task.abort();
+
+        // Candles follow the recorded timestamps, and their updates the replay time
+        let timestamps: Vec<i64> = candles.iter().map(|k| k.timestamp).collect();
+        assert_eq!(timestamps, vec![start, start + 60]);
+        assert!(candles[1].last_update < (start + 600) as u64);
+
+        kline_manager
+            .delete_klines_in_range(&mint, start, start + 60)
+            .await
+            .unwrap();
+    }
+}
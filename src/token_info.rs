@@ -0,0 +1,147 @@
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use tracing::debug;
+
+use crate::constant::TOKEN_2022_PROGRAM;
+use crate::{get_rpc_client_with_retry, redis_helper};
+
+// Size of the base SPL mint layout, shared by Token-2022 mints (extensions follow it)
+const MINT_LAYOUT_LEN: usize = 82;
+
+// Decimals assumed by the trade decoders when the mint cannot be fetched
+pub const DEFAULT_TOKEN_DECIMALS: u8 = 6;
+
+// Largest scale a Decimal supports
+const MAX_DECIMALS: u32 = 28;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TokenProgram {
+    Spl,
+    Token2022,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MintInfo {
+    pub program: TokenProgram,
+    pub decimals: u8,
+    pub supply: u64,
+}
+
+fn get_mint_info_key(mint: &str) -> String {
+    format!("mintinfo:{}", mint)
+}
+
+// Cache lifetime by owner program. Token-2022 mints can change supply-affecting
+// extensions after creation, so they are refetched more often.
+fn cache_ttl_secs(program: TokenProgram) -> u64 {
+    let (var, default) = match program {
+        TokenProgram::Spl => ("MINT_INFO_CACHE_TTL_SECS", 3600),
+        TokenProgram::Token2022 => ("MINT_INFO_TOKEN2022_TTL_SECS", 300),
+    };
+    std::env::var(var)
+        .unwrap_or_else(|_| default.to_string())
+        .parse()
+        .unwrap_or(default)
+}
+
+// Parse a mint account owned by the SPL Token or the Token-2022 program; both
+// share the base mint layout. Accounts of any other owner are not mints.
+pub fn parse_mint_account(owner: &Pubkey, data: &[u8]) -> Result<MintInfo> {
+    let program = if *owner == spl_token::id() {
+        TokenProgram::Spl
+    } else if *owner == Pubkey::from_str(TOKEN_2022_PROGRAM)? {
+        TokenProgram::Token2022
+    } else {
+        return Err(anyhow::anyhow!("Account owned by {} is not a mint", owner));
+    };
+
+    if data.len() < MINT_LAYOUT_LEN {
+        return Err(anyhow::anyhow!("Mint data too short: {} bytes", data.len()));
+    }
+    // Layout: mint_authority (36) | supply (8) | decimals (1) | is_initialized (1) | freeze_authority (36)
+    let supply = u64::from_le_bytes(data[36..44].try_into()?);
+    let decimals = data[44];
+    if data[45] != 1 {
+        return Err(anyhow::anyhow!("Mint is not initialized"));
+    }
+    if u32::from(decimals) > MAX_DECIMALS {
+        return Err(anyhow::anyhow!("Unsupported mint decimals: {}", decimals));
+    }
+
+    Ok(MintInfo {
+        program,
+        decimals,
+        supply,
+    })
+}
+
+async fn fetch_mint_info(mint: &str) -> Result<MintInfo> {
+    let pubkey = Pubkey::from_str(mint)?;
+    let account = get_rpc_client_with_retry(
+        |client| client.get_account(&pubkey).map_err(anyhow::Error::from),
+        3,
+    )
+    .await?;
+    parse_mint_account(&account.owner, &account.data)
+}
+
+// Get a mint's program, decimals and supply, cached in Redis
+pub async fn get_mint_info_cached(mint: &str) -> Result<MintInfo> {
+    let key = get_mint_info_key(mint);
+    if let Some(info) = redis_helper::get::<_, String>(&key)
+        .await?
+        .and_then(|data| serde_json::from_str::<MintInfo>(&data).ok())
+    {
+        return Ok(info);
+    }
+
+    debug!("Cache miss for mint info {}", mint);
+    let info = fetch_mint_info(mint).await?;
+    redis_helper::setex(
+        &key,
+        serde_json::to_string(&info)?,
+        cache_ttl_secs(info.program),
+    )
+    .await?;
+    Ok(info)
+}
+
+// Convert a raw token amount to UI units, zero for decimals Decimal can't represent
+pub fn ui_amount(raw: u64, decimals: u8) -> Decimal {
+    Decimal::try_from_i128_with_scale(raw as i128, decimals as u32).unwrap_or(Decimal::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mint_account() {
+        let mut data = vec![0u8; 170];
+        data[36..44].copy_from_slice(&1_000_000_000_000_000u64.to_le_bytes());
+        data[44] = 9;
+        data[45] = 1;
+
+        let token_2022 = Pubkey::from_str(TOKEN_2022_PROGRAM).unwrap();
+        let info = parse_mint_account(&token_2022, &data).unwrap();
+        assert_eq!(info.program, TokenProgram::Token2022);
+        assert_eq!(info.decimals, 9);
+        assert_eq!(info.supply, 1_000_000_000_000_000);
+
+        let info = parse_mint_account(&spl_token::id(), &data[..82]).unwrap();
+        assert_eq!(info.program, TokenProgram::Spl);
+
+        assert!(parse_mint_account(&spl_token::id(), &data[..44]).is_err());
+        // System-owned or other accounts are not mints
+        assert!(parse_mint_account(&Pubkey::default(), &data).is_err());
+        assert!(parse_mint_account(&Pubkey::new_unique(), &data).is_err());
+
+        data[44] = 29;
+        assert!(parse_mint_account(&token_2022, &data).is_err());
+        assert_eq!(ui_amount(1_500_000, 6), Decimal::new(15, 1));
+        assert_eq!(ui_amount(1, 255), Decimal::ZERO);
+    }
+}
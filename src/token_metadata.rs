@@ -0,0 +1,633 @@
+use anyhow::{Context, Result};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::alerts;
+use crate::constant::{METADATA_PROGRAM, TOKEN_2022_PROGRAM};
+use crate::kline::KLineManager;
+use crate::localized;
+use crate::notification::NotificationManager;
+use crate::notification_format::Language;
+use crate::pump::{read_borsh_string, read_pubkey};
+use crate::severity;
+use crate::strategy::StrategyAlert;
+use crate::{get_rpc_client_with_retry, redis_helper, supervisor};
+
+pub const METADATA_STRATEGY_NAME: &str = "元数据变更";
+
+// Token-2022 extension type of the embedded token metadata
+const TOKEN_METADATA_EXTENSION: u16 = 19;
+// Token-2022 extensions start after the base account, padded to 165 bytes, and its account type
+const TOKEN_2022_EXTENSIONS_OFFSET: usize = 166;
+// Changes kept per mint
+const MAX_CHANGES: isize = 100;
+// Snapshots and change logs of mints that stop being refreshed expire after a week
+const METADATA_TTL_SECS: i64 = 7 * 24 * 3600;
+// Fields of the off-chain JSON tracked as socials
+const SOCIAL_FIELDS: [&str; 3] = ["twitter", "telegram", "website"];
+// Largest off-chain metadata JSON read, larger bodies are rejected
+const MAX_METADATA_JSON_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct MetadataRefreshConfig {
+    // Seconds between refreshes, 0 disables them
+    pub interval_secs: u64,
+    // Most recently active mints refreshed each round
+    pub max_mints: usize,
+    // Alert on suspicious changes
+    pub alert_enabled: bool,
+    // Timeout of the off-chain JSON request
+    pub uri_timeout: Duration,
+}
+
+impl MetadataRefreshConfig {
+    pub fn from_env() -> Self {
+        Self {
+            interval_secs: std::env::var("METADATA_REFRESH_INTERVAL_SECS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0),
+            max_mints: std::env::var("METADATA_REFRESH_MAX_MINTS")
+                .unwrap_or_else(|_| "100".to_string())
+                .parse()
+                .unwrap_or(100),
+            alert_enabled: std::env::var("METADATA_ALERT_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            uri_timeout: Duration::from_secs(
+                std::env::var("METADATA_URI_TIMEOUT_SECS")
+                    .unwrap_or_else(|_| "5".to_string())
+                    .parse()
+                    .unwrap_or(5),
+            ),
+        }
+    }
+}
+
+/// 代币元数据快照
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    /// 更新权限，已放弃时为None
+    pub update_authority: Option<String>,
+    /// 元数据是否仍可修改
+    pub is_mutable: bool,
+    /// URI指向的JSON中的社交链接（twitter、telegram、website），获取失败时为None
+    pub socials: Option<BTreeMap<String, String>>,
+    pub fetched_at: i64,
+}
+
+/// 元数据的一次字段变更
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetadataChange {
+    /// 字段名，社交链接为 "socials.<名称>"
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+    pub timestamp: i64,
+}
+
+impl MetadataChange {
+    /// 是否可疑：名称、符号、URI、社交链接被修改，或更新权限转给了其他地址。
+    /// 放弃更新权限和变为不可修改不算可疑
+    pub fn is_suspicious(&self) -> bool {
+        match self.field.as_str() {
+            "update_authority" => self.new.is_some(),
+            "is_mutable" => false,
+            _ => true,
+        }
+    }
+}
+
+/// mint的最新元数据和变更记录（最新的在前）
+#[derive(Debug, Clone, Serialize)]
+pub struct MintMetadata {
+    pub metadata: Option<TokenMetadata>,
+    pub changes: Vec<MetadataChange>,
+}
+
+fn get_metadata_key(mint: &str) -> String {
+    format!("metadata:{}", mint)
+}
+
+fn get_changes_key(mint: &str) -> String {
+    format!("metadata:changes:{}", mint)
+}
+
+// Borsh strings of fixed-size Metaplex fields are padded with NULs
+fn trimmed(value: String) -> String {
+    value.trim_end_matches('\0').to_string()
+}
+
+// Parse a Metaplex token metadata account:
+// key (1) | update_authority (32) | mint (32) | name | symbol | uri |
+// seller_fee_basis_points (2) | creators (option of vec of 34-byte entries) |
+// primary_sale_happened (1) | is_mutable (1)
+pub fn parse_metaplex_metadata(data: &[u8]) -> Option<TokenMetadata> {
+    let mut pos = 1;
+    let update_authority = read_pubkey(data, &mut pos)?;
+    pos += 32;
+    let name = trimmed(read_borsh_string(data, &mut pos)?);
+    let symbol = trimmed(read_borsh_string(data, &mut pos)?);
+    let uri = trimmed(read_borsh_string(data, &mut pos)?);
+    pos += 2;
+    if *data.get(pos)? == 1 {
+        let count = u32::from_le_bytes(data.get(pos + 1..pos + 5)?.try_into().ok()?) as usize;
+        pos += 5 + count * 34;
+    } else {
+        pos += 1;
+    }
+    pos += 1;
+    let is_mutable = *data.get(pos)? == 1;
+    Some(TokenMetadata {
+        name,
+        symbol,
+        uri,
+        update_authority: Some(update_authority),
+        is_mutable,
+        socials: None,
+        fetched_at: 0,
+    })
+}
+
+// Parse the token metadata extension of a Token-2022 mint:
+// update_authority (32, zeroes when renounced) | mint (32) | name | symbol | uri | ...
+pub fn parse_token2022_metadata(data: &[u8]) -> Option<TokenMetadata> {
+    let mut pos = TOKEN_2022_EXTENSIONS_OFFSET;
+    while let Some(header) = data.get(pos..pos + 4) {
+        let kind = u16::from_le_bytes([header[0], header[1]]);
+        let len = u16::from_le_bytes([header[2], header[3]]) as usize;
+        pos += 4;
+        if kind == TOKEN_METADATA_EXTENSION {
+            let value = data.get(pos..pos + len)?;
+            let renounced = value.get(..32)?.iter().all(|b| *b == 0);
+            let mut value_pos = 0;
+            let update_authority = read_pubkey(value, &mut value_pos)?;
+            value_pos += 32;
+            let name = read_borsh_string(value, &mut value_pos)?;
+            let symbol = read_borsh_string(value, &mut value_pos)?;
+            let uri = read_borsh_string(value, &mut value_pos)?;
+            return Some(TokenMetadata {
+                name,
+                symbol,
+                uri,
+                update_authority: (!renounced).then_some(update_authority),
+                is_mutable: !renounced,
+                socials: None,
+                fetched_at: 0,
+            });
+        }
+        pos += len;
+    }
+    None
+}
+
+// Social links of the off-chain JSON, top-level or under "extensions"
+pub fn parse_socials(json: &Value) -> BTreeMap<String, String> {
+    SOCIAL_FIELDS
+        .iter()
+        .filter_map(|field| {
+            let value = json
+                .get(field)
+                .or_else(|| json.get("extensions").and_then(|e| e.get(field)))?
+                .as_str()?
+                .trim();
+            (!value.is_empty()).then(|| (field.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// 比较两次快照，返回变更的字段。任一快照的社交链接未知时不比较社交链接
+pub fn diff_metadata(old: &TokenMetadata, new: &TokenMetadata, now: i64) -> Vec<MetadataChange> {
+    let mut changes = Vec::new();
+    let mut compare = |field: &str, old: Option<&str>, new: Option<&str>| {
+        if old != new {
+            changes.push(MetadataChange {
+                field: field.to_string(),
+                old: old.map(str::to_string),
+                new: new.map(str::to_string),
+                timestamp: now,
+            });
+        }
+    };
+    compare("name", Some(&old.name), Some(&new.name));
+    compare("symbol", Some(&old.symbol), Some(&new.symbol));
+    compare("uri", Some(&old.uri), Some(&new.uri));
+    compare(
+        "update_authority",
+        old.update_authority.as_deref(),
+        new.update_authority.as_deref(),
+    );
+    compare(
+        "is_mutable",
+        Some(if old.is_mutable { "true" } else { "false" }),
+        Some(if new.is_mutable { "true" } else { "false" }),
+    );
+    if let (Some(old_socials), Some(new_socials)) = (&old.socials, &new.socials) {
+        for field in SOCIAL_FIELDS {
+            compare(
+                &format!("socials.{}", field),
+                old_socials.get(field).map(String::as_str),
+                new_socials.get(field).map(String::as_str),
+            );
+        }
+    }
+    changes
+}
+
+// Owner and data of an account
+async fn get_account_data(pubkey: Pubkey) -> Result<(Pubkey, Vec<u8>)> {
+    get_rpc_client_with_retry(
+        |client| {
+            let account = client.get_account(&pubkey)?;
+            Ok((account.owner, account.data))
+        },
+        3,
+    )
+    .await
+}
+
+// Whether an address is reachable from the public internet. Metadata URIs are
+// set by token creators, so loopback, private, link-local and other special
+// ranges are refused to keep them from reaching internal services.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                // Shared address space (100.64.0.0/10)
+                || (a == 100 && (64..128).contains(&b))
+                // Reserved (240.0.0.0/4)
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local (fc00::/7) and link-local (fe80::/10)
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+// Fetch the off-chain metadata JSON over https only. The host is resolved
+// once, refused unless every address is public, and the request is pinned to
+// those addresses. Redirects are not followed and the body is capped.
+async fn fetch_socials(uri: &str, timeout: Duration) -> Result<BTreeMap<String, String>> {
+    let url = reqwest::Url::parse(uri).with_context(|| format!("Invalid metadata URI: {}", uri))?;
+    if url.scheme() != "https" {
+        anyhow::bail!("Unsupported metadata URI: {}", uri);
+    }
+    let host = url
+        .host_str()
+        .context("Metadata URI has no host")?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs: Vec<SocketAddr> =
+        tokio::time::timeout(timeout, tokio::net::lookup_host((host.as_str(), port)))
+            .await
+            .context("Resolving metadata host timed out")??
+            .collect();
+    if addrs.is_empty() {
+        anyhow::bail!("Metadata host {} has no address", host);
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        anyhow::bail!(
+            "Metadata host {} resolves to non-public {}",
+            host,
+            addr.ip()
+        );
+    }
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve_to_addrs(&host, &addrs)
+        .timeout(timeout)
+        .build()?;
+    let mut response = client.get(url).send().await?.error_for_status()?;
+    if response.status().is_redirection() {
+        anyhow::bail!("Metadata URI redirects, not followed: {}", uri);
+    }
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_METADATA_JSON_BYTES as u64)
+    {
+        anyhow::bail!(
+            "Metadata JSON larger than {} bytes",
+            MAX_METADATA_JSON_BYTES
+        );
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > MAX_METADATA_JSON_BYTES {
+            anyhow::bail!(
+                "Metadata JSON larger than {} bytes",
+                MAX_METADATA_JSON_BYTES
+            );
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let json: Value = serde_json::from_slice(&body)?;
+    Ok(parse_socials(&json))
+}
+
+/// 从链上读取mint的元数据，Token-2022 mint优先使用内嵌的元数据扩展，
+/// 否则读取Metaplex元数据账户。社交链接来自URI指向的JSON
+pub async fn fetch_metadata(mint: &str, config: &MetadataRefreshConfig) -> Result<TokenMetadata> {
+    let mint_pubkey = Pubkey::from_str(mint)?;
+    let (owner, data) = get_account_data(mint_pubkey).await?;
+    let embedded = (owner == Pubkey::from_str(TOKEN_2022_PROGRAM)?)
+        .then(|| parse_token2022_metadata(&data))
+        .flatten();
+    let mut metadata = match embedded {
+        Some(metadata) => metadata,
+        None => {
+            let program = Pubkey::from_str(METADATA_PROGRAM)?;
+            let (address, _) = Pubkey::find_program_address(
+                &[b"metadata", program.as_ref(), mint_pubkey.as_ref()],
+                &program,
+            );
+            let (_, data) = get_account_data(address).await?;
+            parse_metaplex_metadata(&data).context("Invalid metadata account")?
+        }
+    };
+
+    metadata.socials = match fetch_socials(&metadata.uri, config.uri_timeout).await {
+        Ok(socials) => Some(socials),
+        Err(e) => {
+            debug!("Failed to fetch metadata JSON of {}: {}", mint, e);
+            None
+        }
+    };
+    metadata.fetched_at = chrono::Utc::now().timestamp();
+    Ok(metadata)
+}
+
+/// 读取mint的最新元数据和变更记录
+pub async fn get_mint_metadata(mint: &str) -> Result<MintMetadata> {
+    let mut con = redis_helper::get_connection().await?;
+    let metadata: Option<String> = con.get(get_metadata_key(mint)).await?;
+    let changes: Vec<String> = con.lrange(get_changes_key(mint), 0, -1).await?;
+    Ok(MintMetadata {
+        metadata: metadata.and_then(|d| serde_json::from_str(&d).ok()),
+        changes: changes
+            .iter()
+            .filter_map(|d| serde_json::from_str(d).ok())
+            .collect(),
+    })
+}
+
+/// 重新读取mint的元数据，记录与上次快照相比的变更并返回。
+/// 首次读取只保存快照
+pub async fn refresh_mint(
+    mint: &str,
+    config: &MetadataRefreshConfig,
+) -> Result<Vec<MetadataChange>> {
+    let mut metadata = fetch_metadata(mint, config).await?;
+    let previous = get_mint_metadata(mint).await?.metadata;
+    // An unreachable JSON does not mean the socials were removed
+    if metadata.socials.is_none() {
+        metadata.socials = previous.as_ref().and_then(|p| p.socials.clone());
+    }
+    let changes = previous
+        .map(|previous| diff_metadata(&previous, &metadata, metadata.fetched_at))
+        .unwrap_or_default();
+
+    let mut con = redis_helper::get_connection().await?;
+    let mut pipe = redis::pipe();
+    pipe.set_ex(
+        get_metadata_key(mint),
+        serde_json::to_string(&metadata)?,
+        METADATA_TTL_SECS as u64,
+    )
+    .ignore();
+    if !changes.is_empty() {
+        let key = get_changes_key(mint);
+        for change in &changes {
+            pipe.lpush(&key, serde_json::to_string(change)?).ignore();
+        }
+        pipe.ltrim(&key, 0, MAX_CHANGES - 1)
+            .ignore()
+            .expire(&key, METADATA_TTL_SECS)
+            .ignore();
+    }
+    let _: () = pipe.query_async(&mut *con).await?;
+    Ok(changes)
+}
+
+fn describe_change(change: &MetadataChange, language: Language) -> String {
+    let none = match language {
+        Language::Zh => "无",
+        Language::En => "none",
+    };
+    format!(
+        "{}: {} -> {}",
+        change.field,
+        change.old.as_deref().unwrap_or(none),
+        change.new.as_deref().unwrap_or(none)
+    )
+}
+
+async fn alert_changes(mint: &str, changes: &[MetadataChange]) -> Result<()> {
+    let language = Language::from_env();
+    let suspicious: Vec<String> = changes
+        .iter()
+        .filter(|change| change.is_suspicious())
+        .map(|change| describe_change(change, language))
+        .collect();
+    if suspicious.is_empty() {
+        return Ok(());
+    }
+    let mut alert = StrategyAlert {
+        mint: mint.to_string(),
+        strategy_name: METADATA_STRATEGY_NAME.to_string(),
+        message: localized!(
+            language,
+            "发行后元数据被修改: {}",
+            "Metadata changed after launch: {}",
+            suspicious.join("; ")
+        ),
+        timestamp: chrono::Local::now().timestamp(),
+        severity: None,
+        params: None,
+        klines: Vec::new(),
+    };
+    severity::score_alert(&mut alert).await;
+    alerts::record_alert(&alert).await?;
+    NotificationManager::new()?
+        .send_one_shot_notification(&alert)
+        .await
+}
+
+/// 定期重新读取最近活跃mint的元数据，记录变更，并按配置对可疑变更告警
+pub fn spawn_refresh_task(kline_manager: Arc<KLineManager>) -> Option<tokio::task::JoinHandle<()>> {
+    let config = MetadataRefreshConfig::from_env();
+    if config.interval_secs == 0 {
+        return None;
+    }
+
+    info!(
+        "🏷️ Metadata refresh enabled: every {}s for the {} most active mints",
+        config.interval_secs, config.max_mints
+    );
+
+    Some(supervisor::spawn_supervised(
+        "metadata_refresh",
+        async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+            loop {
+                interval.tick().await;
+                let mut active = match kline_manager.get_active_mints().await {
+                    Ok(active) => active,
+                    Err(e) => {
+                        warn!("Failed to list active mints for metadata refresh: {}", e);
+                        continue;
+                    }
+                };
+                active.sort_by_key(|(_, last_activity, _)| std::cmp::Reverse(*last_activity));
+
+                for (mint, _, _) in active.into_iter().take(config.max_mints) {
+                    let changes = match refresh_mint(&mint, &config).await {
+                        Ok(changes) => changes,
+                        Err(e) => {
+                            warn!("Failed to refresh metadata of {}: {}", mint, e);
+                            continue;
+                        }
+                    };
+                    for change in &changes {
+                        info!(
+                            "🏷️ {} metadata changed: {}",
+                            mint,
+                            describe_change(change, Language::En)
+                        );
+                    }
+                    if config.alert_enabled
+                        && let Err(e) = alert_changes(&mint, &changes).await
+                    {
+                        warn!("Failed to alert metadata changes of {}: {}", mint, e);
+                    }
+                }
+            }
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn borsh_string(out: &mut Vec<u8>, value: &str) {
+        out.extend((value.len() as u32).to_le_bytes());
+        out.extend(value.as_bytes());
+    }
+
+    #[test]
+    fn test_parse_and_diff_metadata() {
+        let authority = Pubkey::new_unique();
+        let mut data = vec![4];
+        data.extend(authority.to_bytes());
+        data.extend([0u8; 32]);
+        borsh_string(&mut data, "Dog\0\0");
+        borsh_string(&mut data, "DOG");
+        borsh_string(&mut data, "https://x/1.json");
+        data.extend([0, 0, 1, 1, 0, 0, 0]);
+        data.extend([0u8; 34]);
+        data.extend([0, 1]);
+        let metaplex = parse_metaplex_metadata(&data).unwrap();
+        assert_eq!(metaplex.name, "Dog");
+        assert_eq!(metaplex.update_authority, Some(authority.to_string()));
+        assert!(metaplex.is_mutable);
+
+        let mut value = vec![0u8; 64];
+        borsh_string(&mut value, "Cat");
+        borsh_string(&mut value, "CAT");
+        borsh_string(&mut value, "https://x/2.json");
+        let mut mint = vec![0u8; TOKEN_2022_EXTENSIONS_OFFSET];
+        mint.extend(18u16.to_le_bytes());
+        mint.extend(64u16.to_le_bytes());
+        mint.extend([0u8; 64]);
+        mint.extend(TOKEN_METADATA_EXTENSION.to_le_bytes());
+        mint.extend((value.len() as u16).to_le_bytes());
+        mint.extend(value);
+        let embedded = parse_token2022_metadata(&mint).unwrap();
+        assert_eq!(embedded.symbol, "CAT");
+        assert_eq!(embedded.update_authority, None);
+
+        let socials = parse_socials(
+            &serde_json::json!({"twitter": "https://x.com/a", "extensions": {"website": "https://a.io"}}),
+        );
+        assert_eq!(socials.len(), 2);
+
+        let old = TokenMetadata {
+            socials: Some(socials.clone()),
+            ..metaplex.clone()
+        };
+        let renounced = TokenMetadata {
+            update_authority: None,
+            is_mutable: false,
+            ..old.clone()
+        };
+        let changes = diff_metadata(&old, &renounced, 1);
+        assert_eq!(changes.len(), 2);
+        assert!(changes.iter().all(|c| !c.is_suspicious()));
+
+        let mut new_socials = socials;
+        new_socials.insert("twitter".to_string(), "https://x.com/b".to_string());
+        let rebranded = TokenMetadata {
+            uri: "https://x/3.json".to_string(),
+            socials: Some(new_socials),
+            ..old.clone()
+        };
+        let changes = diff_metadata(&old, &rebranded, 1);
+        assert_eq!(changes.len(), 2);
+        assert!(changes.iter().all(MetadataChange::is_suspicious));
+        assert_eq!(changes[1].field, "socials.twitter");
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert!(is_public_ip(ip("104.18.1.1")));
+        assert!(is_public_ip(ip("2606:4700::1")));
+        for blocked in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip(blocked)), "{} should be blocked", blocked);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use anyhow::Result;
+use redis::{AsyncCommands, aio::ConnectionManager};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+pub use crate::api::TradeSource;
+use crate::positions;
+use crate::redis_helper;
+
+// A single trade (tick) as applied to the K-lines
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeRecord {
+    pub signature: String,
+    pub slot: u64,
+    pub mint: String,
+    pub user: String,
+    pub is_buy: bool,
+    pub price: Decimal,
+    pub sol_amount: Decimal,
+    pub token_amount: Decimal,
+    pub timestamp: i64,
+    // None for trades stored before source tagging
+    #[serde(default)]
+    pub source: Option<TradeSource>,
+    // Pool liquidity in SOL (both sides) after the trade, AMM trades only
+    #[serde(default)]
+    pub liquidity_sol: Option<Decimal>,
+}
+
+// Generate Redis key for the tick trades of a mint
+pub fn get_trades_key(mint: &str) -> String {
+    format!("trades:{}", mint)
+}
+
+// Maximum number of tick trades kept per mint
+fn trade_history_limit() -> isize {
+    std::env::var("TRADE_HISTORY_LIMIT")
+        .unwrap_or_else(|_| "2000".to_string())
+        .parse()
+        .unwrap_or(2000)
+}
+
+// Store a tick trade, newest first, trimming the list to the history limit
+pub async fn push_trade(con: &mut ConnectionManager, trade: &TradeRecord) -> Result<()> {
+    let key = get_trades_key(&trade.mint);
+    let _: () = con.lpush(&key, serde_json::to_string(trade)?).await?;
+    let _: () = con.ltrim(&key, 0, trade_history_limit() - 1).await?;
+    Ok(())
+}
+
+// Trade side selected by a filter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+impl std::str::FromStr for TradeSide {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "buy" => Ok(TradeSide::Buy),
+            "sell" => Ok(TradeSide::Sell),
+            _ => Err(anyhow::anyhow!("Invalid trade side: {}", s)),
+        }
+    }
+}
+
+// Server-side filter for a mint's trade feed; unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct TradeFilter {
+    pub side: Option<TradeSide>,
+    pub min_sol: Option<Decimal>,
+    pub max_sol: Option<Decimal>,
+    pub wallet: Option<String>,
+    pub source: Option<TradeSource>,
+}
+
+impl TradeFilter {
+    pub fn matches(&self, trade: &TradeRecord) -> bool {
+        self.side
+            .is_none_or(|side| (side == TradeSide::Buy) == trade.is_buy)
+            && self.min_sol.is_none_or(|min| trade.sol_amount >= min)
+            && self.max_sol.is_none_or(|max| trade.sol_amount <= max)
+            && self
+                .wallet
+                .as_deref()
+                .is_none_or(|wallet| trade.user == wallet)
+            && self
+                .source
+                .is_none_or(|source| trade.source == Some(source))
+    }
+}
+
+// Get the newest `limit` trades matching a filter, oldest first
+pub async fn get_filtered_trades(
+    mint: &str,
+    filter: &TradeFilter,
+    limit: Option<usize>,
+) -> Result<Vec<TradeRecord>> {
+    let mut trades: Vec<TradeRecord> = get_trades_for_mint(mint, None)
+        .await?
+        .into_iter()
+        .filter(|trade| filter.matches(trade))
+        .collect();
+    if let Some(limit) = limit {
+        trades.drain(..trades.len().saturating_sub(limit));
+    }
+    Ok(trades)
+}
+
+// Keep one wallet's trades at or after `since` from per-mint lists (newest
+// first, as stored), merged oldest first and cut to the newest `limit`
+pub fn merge_wallet_trades(
+    lists: Vec<Vec<String>>,
+    wallet: &str,
+    since: Option<i64>,
+    limit: usize,
+) -> Vec<TradeRecord> {
+    let mut trades: Vec<TradeRecord> = lists
+        .iter()
+        .flatten()
+        .filter_map(|d| serde_json::from_str::<TradeRecord>(d).ok())
+        .filter(|trade| trade.user == wallet && since.is_none_or(|since| trade.timestamp >= since))
+        .collect();
+    trades.sort_by_key(|trade| (trade.timestamp, trade.slot));
+    trades.drain(..trades.len().saturating_sub(limit));
+    trades
+}
+
+// Get a wallet's stored tick trades across every mint it traded, oldest first.
+// Mints come from the wallet index kept by position tracking, so only trades
+// still within each mint's TRADE_HISTORY_LIMIT are found.
+pub async fn get_wallet_trades(
+    wallet: &str,
+    since: Option<i64>,
+    limit: usize,
+) -> Result<Vec<TradeRecord>> {
+    let mints = positions::get_wallet_mints(wallet).await?;
+    if mints.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut con = redis_helper::get_connection().await?;
+    let mut pipe = redis::pipe();
+    for mint in &mints {
+        pipe.lrange(get_trades_key(mint), 0, -1);
+    }
+    let lists: Vec<Vec<String>> = pipe.query_async(&mut *con).await?;
+    Ok(merge_wallet_trades(lists, wallet, since, limit))
+}
+
+// Get stored tick trades for a mint in chronological order (oldest first)
+pub async fn get_trades_for_mint(mint: &str, limit: Option<usize>) -> Result<Vec<TradeRecord>> {
+    let mut con = redis_helper::get_connection().await?;
+    let end = limit.map(|l| l as isize - 1).unwrap_or(-1);
+    let data: Vec<String> = con.lrange(get_trades_key(mint), 0, end).await?;
+
+    let mut trades: Vec<TradeRecord> = data
+        .iter()
+        .filter_map(|d| serde_json::from_str(d).ok())
+        .collect();
+    trades.reverse();
+    Ok(trades)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(user: &str, is_buy: bool, sol_amount: Decimal) -> TradeRecord {
+        TradeRecord {
+            signature: "sig".to_string(),
+            slot: 1,
+            mint: "Mint111".to_string(),
+            user: user.to_string(),
+            is_buy,
+            price: Decimal::ONE,
+            sol_amount,
+            token_amount: Decimal::ONE,
+            timestamp: 1_700_000_000,
+            source: Some(TradeSource::Pump),
+            liquidity_sol: None,
+        }
+    }
+
+    #[test]
+    fn test_trade_filter() {
+        let filter = TradeFilter {
+            side: Some("BUY".parse().unwrap()),
+            min_sol: Some(Decimal::new(5, 1)),
+            ..Default::default()
+        };
+        assert!(filter.matches(&trade("a", true, Decimal::ONE)));
+        assert!(!filter.matches(&trade("a", false, Decimal::ONE)));
+        assert!(!filter.matches(&trade("a", true, Decimal::new(1, 1))));
+
+        let filter = TradeFilter {
+            wallet: Some("a".to_string()),
+            ..Default::default()
+        };
+        assert!(filter.matches(&trade("a", false, Decimal::ZERO)));
+        assert!(!filter.matches(&trade("b", false, Decimal::ZERO)));
+
+        let filter = TradeFilter {
+            source: Some("AMM".parse().unwrap()),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&trade("a", true, Decimal::ONE)));
+        assert!("hold".parse::<TradeSide>().is_err());
+    }
+
+    #[test]
+    fn test_merge_wallet_trades() {
+        let at = |user: &str, mint: &str, timestamp: i64| {
+            serde_json::to_string(&TradeRecord {
+                mint: mint.to_string(),
+                timestamp,
+                ..trade(user, true, Decimal::ONE)
+            })
+            .unwrap()
+        };
+        let lists = vec![
+            vec![at("a", "M1", 30), at("b", "M1", 20), at("a", "M1", 10)],
+            vec![at("a", "M2", 25), "not json".to_string(), at("a", "M2", 5)],
+        ];
+
+        let trades = merge_wallet_trades(lists.clone(), "a", Some(10), 10);
+        let order: Vec<(i64, &str)> = trades
+            .iter()
+            .map(|trade| (trade.timestamp, trade.mint.as_str()))
+            .collect();
+        assert_eq!(order, vec![(10, "M1"), (25, "M2"), (30, "M1")]);
+
+        // The limit keeps the newest trades
+        let trades = merge_wallet_trades(lists, "a", None, 2);
+        assert_eq!(trades[0].timestamp, 25);
+        assert_eq!(trades.len(), 2);
+    }
+}
@@ -0,0 +1,347 @@
+use anyhow::Result;
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Cell, List, ListItem, Paragraph, Row, Sparkline, Table, TableState},
+};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::alerts;
+use crate::kline::{KLineData, KLineManager};
+use crate::strategy::StrategyAlert;
+
+// Number of recent candles used for the table and sparkline
+const KLINES_PER_MINT: usize = 30;
+// Maximum number of mints listed in the table
+const MAX_ROWS: usize = 200;
+
+struct MintRow {
+    mint: String,
+    last_activity: u64,
+    complete: bool,
+    price: Decimal,
+    change_pct: Decimal,
+    volume_sol: Decimal,
+    net_flow_sol: Decimal,
+    closes: Vec<Decimal>,
+}
+
+struct App {
+    rows: Vec<MintRow>,
+    alerts: Vec<StrategyAlert>,
+    total_klines: usize,
+    table_state: TableState,
+    last_refresh: Option<Instant>,
+    error: Option<String>,
+}
+
+impl App {
+    fn new() -> Self {
+        Self {
+            rows: Vec::new(),
+            alerts: Vec::new(),
+            total_klines: 0,
+            table_state: TableState::default().with_selected(0),
+            last_refresh: None,
+            error: None,
+        }
+    }
+
+    async fn refresh(&mut self, manager: &KLineManager) {
+        match load_rows(manager).await {
+            Ok((rows, total_klines)) => {
+                self.rows = rows;
+                self.total_klines = total_klines;
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("Failed to load mints: {}", e)),
+        }
+        match alerts::get_recent_alerts(20).await {
+            Ok(alerts) => self.alerts = alerts,
+            Err(e) => self.error = Some(format!("Failed to load alerts: {}", e)),
+        }
+        if self.rows.is_empty() {
+            self.table_state.select(None);
+        } else {
+            let selected = self.table_state.selected().unwrap_or(0);
+            self.table_state
+                .select(Some(selected.min(self.rows.len() - 1)));
+        }
+        self.last_refresh = Some(Instant::now());
+    }
+
+    fn select_next(&mut self) {
+        if !self.rows.is_empty() {
+            let next = self.table_state.selected().map_or(0, |i| i + 1);
+            self.table_state.select(Some(next.min(self.rows.len() - 1)));
+        }
+    }
+
+    fn select_previous(&mut self) {
+        let previous = self
+            .table_state
+            .selected()
+            .map_or(0, |i| i.saturating_sub(1));
+        self.table_state.select(Some(previous));
+    }
+}
+
+async fn load_rows(manager: &KLineManager) -> Result<(Vec<MintRow>, usize)> {
+    let active_mints = manager.get_active_mints().await?;
+    let latest = manager.get_latest_klines(KLINES_PER_MINT).await?;
+    let total_klines = latest.len();
+
+    let mut mint_klines: HashMap<String, Vec<KLineData>> = HashMap::new();
+    for (mint, kline) in latest {
+        mint_klines.entry(mint).or_default().push(kline);
+    }
+
+    let rows = active_mints
+        .into_iter()
+        .take(MAX_ROWS)
+        .map(|(mint, last_activity, complete)| {
+            let mut klines = mint_klines.remove(&mint).unwrap_or_default();
+            klines.sort_by_key(|k| k.timestamp);
+            let closes: Vec<Decimal> = klines.iter().filter_map(|k| k.close.parse().ok()).collect();
+            let open: Decimal = klines
+                .first()
+                .and_then(|k| k.open.parse().ok())
+                .unwrap_or(Decimal::ZERO);
+            let price = closes.last().copied().unwrap_or(Decimal::ZERO);
+            let change_pct = if open > Decimal::ZERO {
+                (price - open) / open * Decimal::new(100, 0)
+            } else {
+                Decimal::ZERO
+            };
+            MintRow {
+                mint,
+                last_activity,
+                complete,
+                price,
+                change_pct,
+                volume_sol: klines
+                    .iter()
+                    .filter_map(|k| k.volume_sol.parse::<Decimal>().ok())
+                    .sum(),
+                net_flow_sol: klines
+                    .iter()
+                    .filter_map(|k| k.net_flow_sol.parse::<Decimal>().ok())
+                    .sum(),
+                closes,
+            }
+        })
+        .collect();
+
+    Ok((rows, total_klines))
+}
+
+// Shorten a mint address for table display
+fn short_mint(mint: &str) -> String {
+    if mint.len() > 12 {
+        format!("{}…{}", &mint[..6], &mint[mint.len() - 4..])
+    } else {
+        mint.to_string()
+    }
+}
+
+// Scale close prices to 0..=100 for the sparkline
+fn sparkline_data(closes: &[Decimal]) -> Vec<u64> {
+    let (Some(min), Some(max)) = (closes.iter().min(), closes.iter().max()) else {
+        return Vec::new();
+    };
+    let range = *max - *min;
+    closes
+        .iter()
+        .map(|c| {
+            if range.is_zero() {
+                50
+            } else {
+                ((*c - *min) / range * Decimal::new(100, 0))
+                    .round()
+                    .try_into()
+                    .unwrap_or(0)
+            }
+        })
+        .collect()
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let [status_area, main_area, alerts_area] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(10),
+        Constraint::Length(10),
+    ])
+    .areas(frame.area());
+    let [table_area, detail_area] =
+        Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)])
+            .areas(main_area);
+
+    // Monitor status: how long since the most recent trade was recorded
+    let now = chrono::Utc::now().timestamp() as u64;
+    let last_trade_age = app
+        .rows
+        .iter()
+        .map(|r| now.saturating_sub(r.last_activity))
+        .min();
+    let monitor_status = match last_trade_age {
+        Some(age) if age < 60 => format!("🟢 live (last trade {}s ago)", age),
+        Some(age) => format!("🟡 stale (last trade {}s ago)", age),
+        None => "🔴 no activity".to_string(),
+    };
+    let status = format!(
+        " Mints: {} | K-lines: {} | Monitor: {} | Refreshed: {}s ago | q: quit, ↑/↓: select, r: refresh",
+        app.rows.len(),
+        app.total_klines,
+        monitor_status,
+        app.last_refresh.map_or(0, |t| t.elapsed().as_secs())
+    );
+    let mut status_lines = vec![Line::from(status)];
+    if let Some(error) = &app.error {
+        status_lines.push(Line::styled(error.clone(), Style::default().fg(Color::Red)));
+    }
+    frame.render_widget(
+        Paragraph::new(status_lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" pump-kmonitor "),
+        ),
+        status_area,
+    );
+
+    // Active mints table
+    let header = Row::new([
+        "Mint", "Price", "Chg %", "Vol SOL", "Flow SOL", "Phase", "Age",
+    ])
+    .style(Style::default().add_modifier(Modifier::BOLD));
+    let rows = app.rows.iter().map(|r| {
+        let color = if r.change_pct >= Decimal::ZERO {
+            Color::Green
+        } else {
+            Color::Red
+        };
+        Row::new(vec![
+            Cell::from(short_mint(&r.mint)),
+            Cell::from(format!("{:.9}", r.price)),
+            Cell::from(format!("{:+.2}", r.change_pct)).style(Style::default().fg(color)),
+            Cell::from(format!("{:.2}", r.volume_sol)),
+            Cell::from(format!("{:+.2}", r.net_flow_sol)),
+            Cell::from(if r.complete { "AMM" } else { "Curve" }),
+            Cell::from(format!("{}s", now.saturating_sub(r.last_activity))),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(13),
+            Constraint::Length(14),
+            Constraint::Length(9),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(6),
+            Constraint::Length(6),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Active mints "),
+    )
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(table, table_area, &mut app.table_state);
+
+    // Sparkline of the selected mint
+    let selected = app.table_state.selected().and_then(|i| app.rows.get(i));
+    let (title, data) = match selected {
+        Some(row) => (
+            format!(" {} ({} candles) ", short_mint(&row.mint), row.closes.len()),
+            sparkline_data(&row.closes),
+        ),
+        None => (" No mint selected ".to_string(), Vec::new()),
+    };
+    frame.render_widget(
+        Sparkline::default()
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .data(&data)
+            .style(Style::default().fg(Color::Cyan)),
+        detail_area,
+    );
+
+    // Recent alerts
+    let items: Vec<ListItem> = app
+        .alerts
+        .iter()
+        .map(|a| {
+            let time = chrono::DateTime::from_timestamp(a.timestamp, 0)
+                .map(|dt| {
+                    dt.with_timezone(&chrono::Local)
+                        .format("%H:%M:%S")
+                        .to_string()
+                })
+                .unwrap_or_default();
+            ListItem::new(format!(
+                "{} {} [{}] {}",
+                time,
+                short_mint(&a.mint),
+                a.strategy_name,
+                a.message
+            ))
+        })
+        .collect();
+    frame.render_widget(
+        List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Recent alerts "),
+        ),
+        alerts_area,
+    );
+}
+
+async fn run_app(terminal: &mut DefaultTerminal, refresh_secs: u64) -> Result<()> {
+    let manager = KLineManager::new().await?;
+    let refresh_interval = Duration::from_secs(refresh_secs.max(1));
+    let mut app = App::new();
+
+    loop {
+        if app
+            .last_refresh
+            .is_none_or(|t| t.elapsed() >= refresh_interval)
+        {
+            app.refresh(&manager).await;
+        }
+
+        terminal.draw(|frame| draw(frame, &mut app))?;
+
+        // Short poll so data refreshes keep flowing without key presses
+        if !event::poll(Duration::from_millis(200))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Down | KeyCode::Char('j') => app.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => app.select_previous(),
+            KeyCode::Char('r') => app.last_refresh = None,
+            _ => {}
+        }
+    }
+}
+
+// Run the terminal dashboard until the user quits
+pub async fn run(refresh_secs: u64) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run_app(&mut terminal, refresh_secs).await;
+    ratatui::restore();
+    result
+}
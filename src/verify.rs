@@ -0,0 +1,330 @@
+use anyhow::Result;
+use rand::seq::IndexedRandom;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::kline::{KLineData, KLineManager};
+use crate::redis_helper;
+use crate::supervisor;
+use crate::trades::{self, TradeRecord};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CandleMismatch {
+    pub timestamp: i64,
+    pub field: String,
+    pub stored: String,
+    pub recomputed: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyReport {
+    pub mint: String,
+    pub trades: usize,
+    pub candles_checked: usize,
+    pub missing_candles: Vec<i64>,
+    pub mismatches: Vec<CandleMismatch>,
+}
+
+impl VerifyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missing_candles.is_empty() && self.mismatches.is_empty()
+    }
+}
+
+// Candle recomputed from tick trades
+struct RecomputedCandle {
+    open: Decimal,
+    high: Decimal,
+    low: Decimal,
+    close: Decimal,
+    volume_sol: Decimal,
+    volume_token: Decimal,
+    net_flow_sol: Decimal,
+}
+
+// Rebuild minute candles from tick trades (trades must be in arrival order)
+fn recompute_candles(trades: &[TradeRecord]) -> BTreeMap<i64, RecomputedCandle> {
+    let mut candles: BTreeMap<i64, RecomputedCandle> = BTreeMap::new();
+    for trade in trades {
+        let minute_ts = KLineManager::get_minute_timestamp(trade.timestamp);
+        let flow = if trade.is_buy {
+            trade.sol_amount
+        } else {
+            -trade.sol_amount
+        };
+        candles
+            .entry(minute_ts)
+            .and_modify(|c| {
+                c.high = c.high.max(trade.price);
+                c.low = c.low.min(trade.price);
+                c.close = trade.price;
+                c.volume_sol += trade.sol_amount;
+                c.volume_token += trade.token_amount;
+                c.net_flow_sol += flow;
+            })
+            .or_insert(RecomputedCandle {
+                open: trade.price,
+                high: trade.price,
+                low: trade.price,
+                close: trade.price,
+                volume_sol: trade.sol_amount,
+                volume_token: trade.token_amount,
+                net_flow_sol: flow,
+            });
+    }
+    candles
+}
+
+fn compare_candle(stored: &KLineData, recomputed: &RecomputedCandle) -> Vec<CandleMismatch> {
+    let fields = [
+        ("open", &stored.open, recomputed.open),
+        ("high", &stored.high, recomputed.high),
+        ("low", &stored.low, recomputed.low),
+        ("close", &stored.close, recomputed.close),
+        ("volume_sol", &stored.volume_sol, recomputed.volume_sol),
+        (
+            "volume_token",
+            &stored.volume_token,
+            recomputed.volume_token,
+        ),
+        (
+            "net_flow_sol",
+            &stored.net_flow_sol,
+            recomputed.net_flow_sol,
+        ),
+    ];
+
+    fields
+        .into_iter()
+        .filter(|(_, stored_value, expected)| {
+            stored_value.parse::<Decimal>().ok() != Some(*expected)
+        })
+        .map(|(field, stored_value, expected)| CandleMismatch {
+            timestamp: stored.timestamp,
+            field: field.to_string(),
+            stored: stored_value.clone(),
+            recomputed: expected.to_string(),
+        })
+        .collect()
+}
+
+// Verify the stored candles of one mint against its tick trades
+pub async fn verify_mint(manager: &KLineManager, mint: &str) -> Result<VerifyReport> {
+    let trades = trades::get_trades_for_mint(mint, None).await?;
+    let klines = manager.get_klines_for_mint(mint, 60, None).await?;
+    Ok(build_report(
+        mint,
+        &trades,
+        klines,
+        chrono::Utc::now().timestamp(),
+    ))
+}
+
+// Compare stored candles with the ones recomputed from the trades, as of `now`
+fn build_report(
+    mint: &str,
+    trades: &[TradeRecord],
+    klines: Vec<KLineData>,
+    now: i64,
+) -> VerifyReport {
+    let stored: BTreeMap<i64, KLineData> = klines.into_iter().map(|k| (k.timestamp, k)).collect();
+
+    let mut report = VerifyReport {
+        mint: mint.to_string(),
+        trades: trades.len(),
+        candles_checked: 0,
+        missing_candles: Vec::new(),
+        mismatches: Vec::new(),
+    };
+
+    let Some(oldest) = trades.first() else {
+        return report;
+    };
+    // The oldest minute may be truncated by the history limit, and the
+    // current minute may still be receiving trades, so skip both
+    let oldest_minute = KLineManager::get_minute_timestamp(oldest.timestamp);
+    let current_minute = KLineManager::get_minute_timestamp(now);
+
+    for (minute_ts, candle) in recompute_candles(trades) {
+        if minute_ts <= oldest_minute || minute_ts >= current_minute {
+            continue;
+        }
+        report.candles_checked += 1;
+        match stored.get(&minute_ts) {
+            Some(kline) => report.mismatches.extend(compare_candle(kline, &candle)),
+            None => report.missing_candles.push(minute_ts),
+        }
+    }
+
+    report
+}
+
+// Verify a random sample of active mints
+pub async fn verify_sample(
+    manager: &KLineManager,
+    sample_size: usize,
+) -> Result<Vec<VerifyReport>> {
+    let active_mints = manager.get_active_mints().await?;
+    let sample: Vec<String> = active_mints
+        .choose_multiple(&mut rand::rng(), sample_size)
+        .map(|(mint, _, _)| mint.clone())
+        .collect();
+
+    let mut reports = Vec::new();
+    for mint in sample {
+        reports.push(verify_mint(manager, &mint).await?);
+    }
+    Ok(reports)
+}
+
+// Periodically audit a sample of mints and flag inconsistent candles
+pub fn spawn_audit_task(kline_manager: Arc<KLineManager>) -> Option<tokio::task::JoinHandle<()>> {
+    let interval_secs: u64 = std::env::var("AUDIT_INTERVAL_SECS")
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .unwrap_or(0);
+    if interval_secs == 0 {
+        return None;
+    }
+    let sample_size: usize = std::env::var("AUDIT_SAMPLE_SIZE")
+        .unwrap_or_else(|_| "10".to_string())
+        .parse()
+        .unwrap_or(10);
+
+    info!(
+        "🔎 Candle audit enabled: every {}s, {} mints per pass",
+        interval_secs, sample_size
+    );
+
+    Some(supervisor::spawn_supervised("candle_audit", async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+        loop {
+            interval.tick().await;
+            match verify_sample(&kline_manager, sample_size).await {
+                Ok(reports) => {
+                    for report in reports.iter().filter(|r| !r.is_consistent()) {
+                        warn!(
+                            "⚠️ Candle audit mismatch for {}: {} mismatched fields, {} missing candles",
+                            report.mint,
+                            report.mismatches.len(),
+                            report.missing_candles.len()
+                        );
+                        if let Err(e) = record_audit_failure(report).await {
+                            warn!("Failed to record audit failure: {}", e);
+                        }
+                    }
+                }
+                Err(e) => warn!("Candle audit failed: {}", e),
+            }
+        }
+    }))
+}
+
+// Keep the latest failing report per mint for inspection
+async fn record_audit_failure(report: &VerifyReport) -> Result<()> {
+    redis_helper::setex(
+        format!("audit:mismatch:{}", report.mint),
+        serde_json::to_string(report)?,
+        86400,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness;
+
+    fn trade(timestamp: i64, is_buy: bool, price: i64, sol_amount: i64) -> TradeRecord {
+        TradeRecord {
+            signature: "sig".to_string(),
+            slot: 1,
+            mint: "Mint111".to_string(),
+            user: "user".to_string(),
+            is_buy,
+            price: Decimal::from(price),
+            sol_amount: Decimal::from(sol_amount),
+            token_amount: Decimal::from(sol_amount * 10),
+            timestamp,
+            source: None,
+            liquidity_sol: None,
+        }
+    }
+
+    fn stored(timestamp: i64, ohlc: [&str; 4], volume_sol: &str, net_flow_sol: &str) -> KLineData {
+        KLineData {
+            open: ohlc[0].to_string(),
+            high: ohlc[1].to_string(),
+            low: ohlc[2].to_string(),
+            volume_sol: volume_sol.to_string(),
+            volume_token: (volume_sol.parse::<i64>().unwrap() * 10).to_string(),
+            net_flow_sol: net_flow_sol.to_string(),
+            ..test_harness::candle(timestamp, ohlc[3])
+        }
+    }
+
+    #[test]
+    fn test_recompute_candles() {
+        let trades = [
+            trade(60, true, 5, 2),
+            trade(70, false, 3, 1),
+            trade(119, true, 4, 3),
+            trade(120, true, 6, 1),
+        ];
+        let candles = recompute_candles(&trades);
+        assert_eq!(candles.len(), 2);
+
+        // Trades keep arrival order within a minute; sells count against net flow
+        let first = &candles[&60];
+        assert_eq!(
+            [first.open, first.high, first.low, first.close],
+            [5, 5, 3, 4].map(Decimal::from)
+        );
+        assert_eq!(first.volume_sol, Decimal::from(6));
+        assert_eq!(first.volume_token, Decimal::from(60));
+        assert_eq!(first.net_flow_sol, Decimal::from(4));
+        assert_eq!(candles[&120].open, Decimal::from(6));
+    }
+
+    #[test]
+    fn test_compare_candle() {
+        let candles = recompute_candles(&[trade(60, true, 5, 2), trade(70, false, 3, 1)]);
+        let candle = &candles[&60];
+
+        // Values are compared as numbers, not as strings
+        let matching = stored(60, ["5.0", "5", "3", "3"], "3", "1.00");
+        assert!(compare_candle(&matching, candle).is_empty());
+
+        let drifted = stored(60, ["5", "5", "3", "4"], "3", "3");
+        let mismatches = compare_candle(&drifted, candle);
+        let fields: Vec<&str> = mismatches.iter().map(|m| m.field.as_str()).collect();
+        assert_eq!(fields, ["close", "net_flow_sol"]);
+        assert_eq!(mismatches[0].stored, "4");
+        assert_eq!(mismatches[0].recomputed, "3");
+    }
+
+    #[test]
+    fn test_build_report() {
+        let trades = [
+            trade(30, true, 1, 1),
+            trade(60, true, 5, 2),
+            trade(130, true, 2, 1),
+            trade(190, true, 2, 1),
+        ];
+        let klines = vec![stored(60, ["5", "5", "5", "5"], "2", "2")];
+        let report = build_report("Mint111", &trades, klines, 200);
+
+        // The oldest minute may be cut off by the history limit and the
+        // current one is still open, so neither is checked
+        assert_eq!(report.trades, 4);
+        assert_eq!(report.candles_checked, 2);
+        assert_eq!(report.missing_candles, vec![120]);
+        assert!(report.mismatches.is_empty());
+        assert!(!report.is_consistent());
+
+        assert!(build_report("Mint111", &[], Vec::new(), 200).is_consistent());
+    }
+}
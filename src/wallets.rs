@@ -0,0 +1,85 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use rust_decimal::Decimal;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+use crate::alerts;
+use crate::cached_set::{CachedRedisSet, SetSource};
+use crate::localized;
+use crate::notification::NotificationManager;
+use crate::notification_format::NotificationFormat;
+use crate::redis_helper;
+use crate::severity;
+use crate::strategy::StrategyAlert;
+
+// Redis set of wallets whose buys trigger alerts
+const TRACKED_WALLETS_KEY: &str = "wallets:tracked";
+
+// In-memory copy of the tracked wallets used by the monitors, reloaded every 30 seconds
+static TRACKED: CachedRedisSet =
+    CachedRedisSet::new(TRACKED_WALLETS_KEY, SetSource::Members, || {
+        Duration::from_secs(30)
+    });
+
+// Notification manager shared by the tracked-wallet alerts of the process
+static NOTIFICATION_MANAGER: OnceCell<NotificationManager> = OnceCell::const_new();
+
+/// 添加跟踪钱包，返回是否新增
+pub async fn track_wallet(wallet: &str) -> Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    let added: usize = con.sadd(TRACKED_WALLETS_KEY, wallet).await?;
+    Ok(added > 0)
+}
+
+/// 取消跟踪钱包，返回是否存在
+pub async fn untrack_wallet(wallet: &str) -> Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    let removed: usize = con.srem(TRACKED_WALLETS_KEY, wallet).await?;
+    Ok(removed > 0)
+}
+
+/// 获取所有跟踪钱包
+pub async fn get_tracked_wallets() -> Result<Vec<String>> {
+    let mut con = redis_helper::get_connection().await?;
+    let mut wallets: Vec<String> = con.smembers(TRACKED_WALLETS_KEY).await?;
+    wallets.sort();
+    Ok(wallets)
+}
+
+/// 跟踪钱包买入时发送告警
+pub async fn alert_if_tracked_buy(
+    wallet: &str,
+    mint: &str,
+    sol_amount: Decimal,
+    price: Decimal,
+) -> Result<()> {
+    if !TRACKED.contains(wallet).await {
+        return Ok(());
+    }
+
+    let format = NotificationFormat::current().await;
+    let mut alert = StrategyAlert {
+        mint: mint.to_string(),
+        strategy_name: "跟踪钱包买入".to_string(),
+        message: localized!(
+            format.language,
+            "跟踪钱包 {} 买入 {}, 价格: {}",
+            "Tracked wallet {} bought {}, price: {}",
+            wallet,
+            format.amount(sol_amount),
+            format.price(price)
+        ),
+        timestamp: chrono::Local::now().timestamp(),
+        severity: None,
+        params: None,
+        klines: Vec::new(),
+    };
+    severity::score_alert(&mut alert).await;
+    alerts::record_alert(&alert).await?;
+    NOTIFICATION_MANAGER
+        .get_or_try_init(|| async { NotificationManager::new() })
+        .await?
+        .send_notification(&alert)
+        .await
+}
@@ -0,0 +1,73 @@
+use anyhow::Result;
+use redis::AsyncCommands;
+use std::time::Duration;
+
+use crate::cached_set::{CachedRedisSet, SetSource};
+use crate::redis_helper;
+use crate::remote_config;
+use crate::spam;
+
+// Redis set holding the watched mints
+const WATCHLIST_KEY: &str = "watchlist";
+
+// In-memory copy of the watchlist used by the monitors. A failed reload keeps
+// the previous copy so a Redis hiccup does not open the floodgates.
+static WATCHLIST: CachedRedisSet =
+    CachedRedisSet::new(WATCHLIST_KEY, SetSource::Members, refresh_interval);
+
+/// 是否开启白名单模式（只处理观察列表中的mint）
+pub fn allowlist_enabled() -> bool {
+    remote_config::var("WATCHLIST_ONLY")
+        .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+        .unwrap_or(false)
+}
+
+// How often the monitors reload the watchlist from Redis
+fn refresh_interval() -> Duration {
+    Duration::from_secs(
+        std::env::var("WATCHLIST_REFRESH_SECS")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse()
+            .unwrap_or(10),
+    )
+}
+
+/// 添加mint到观察列表，返回是否新增
+pub async fn add_mint(mint: &str) -> Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    let added: usize = con.sadd(WATCHLIST_KEY, mint).await?;
+    Ok(added > 0)
+}
+
+/// 从观察列表移除mint，返回是否存在
+pub async fn remove_mint(mint: &str) -> Result<bool> {
+    let mut con = redis_helper::get_connection().await?;
+    let removed: usize = con.srem(WATCHLIST_KEY, mint).await?;
+    Ok(removed > 0)
+}
+
+/// 获取观察列表
+pub async fn get_watchlist() -> Result<Vec<String>> {
+    let mut con = redis_helper::get_connection().await?;
+    let mut mints: Vec<String> = con.smembers(WATCHLIST_KEY).await?;
+    mints.sort();
+    Ok(mints)
+}
+
+/// 判断monitor是否应处理该mint的交易。
+/// 被识别为垃圾代币的mint总是返回false；白名单模式关闭时其余mint返回true；
+/// 开启时使用定期刷新的内存缓存，避免每笔交易都访问Redis。
+pub async fn is_allowed(mint: &str) -> bool {
+    if spam::is_blacklisted(mint).await {
+        return false;
+    }
+    if !allowlist_enabled() {
+        return true;
+    }
+    is_watched(mint).await
+}
+
+/// 判断mint是否在观察列表中，使用定期刷新的内存缓存
+pub async fn is_watched(mint: &str) -> bool {
+    WATCHLIST.contains(mint).await
+}
@@ -1,96 +1,472 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::{Html, Json},
-    routing::{Router, get},
+    body::Body,
+    extract::{Path, Query, Request, State},
+    http::{
+        Method, StatusCode,
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+    },
+    middleware::{self, Next},
+    response::{
+        Html, IntoResponse, Json, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{Router, delete, get, post},
 };
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, sync::Arc};
-use tokio::sync::Mutex;
-use tower_http::{cors::CorsLayer, services::ServeDir};
-use tracing::info;
+use futures_util::{Stream, StreamExt, stream};
+use moka::future::Cache;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::{Mutex, broadcast};
+use tower_http::{
+    compression::CompressionLayer,
+    cors::CorsLayer,
+    services::ServeDir,
+    trace::{DefaultMakeSpan, TraceLayer},
+};
+use tracing::{Level, info, warn};
 
-use crate::kline::{KLineData, KLineManager};
+use crate::admin::{self, AdminConfig};
+use crate::aggregate_cache;
+use crate::alerts::{self, AlertPoll, Leaderboard, LeaderboardPeriod, StrategyStats};
+use crate::annotations;
+pub use crate::api::{ApiResponse, KLinesResponse, KlineQuery, MintInfo};
+use crate::api_tokens::{self, ApiToken, CreatedToken, TokenScope};
+use crate::candle_hub::{self, CandleUpdate};
+use crate::decode_check;
+use crate::feed_health;
+use crate::fees::{self, PoolFees};
+use crate::funnel::{self, FunnelStats};
+use crate::heartbeat::{self, HealthReport};
+use crate::heatmap::{self, HeatmapData, HeatmapEntry};
+use crate::kline::{self, GapMode, KLineAggregator, KLineData, KLineManager};
+use crate::labels::{self, LabeledTrade};
+use crate::launch::{self, LaunchInfo};
+use crate::market_beta::{self, MintIndicators};
+use crate::metrics;
+use crate::monitor_control::{self, MonitorState};
+use crate::notification::{ChannelTestResult, FailedNotifications, NotificationManager};
+use crate::parse_window;
+use crate::pnl::{self, WalletPnl};
+use crate::positions::{self, MintPositions};
+use crate::price_alerts::{self, PriceAlert, PriceAlertTarget};
+use crate::price_feed;
+use crate::remote_config::{self, ConfigSetting};
+use crate::rules::{self, MintRule, RuleCondition};
+use crate::smart_money::{self, SmartWallet};
+use crate::spam::{self, BlacklistEntry};
+use crate::supervisor;
+use crate::token_metadata::{self, MintMetadata};
+use crate::trades::{self, TradeFilter, TradeSide, TradeSource};
 
 #[derive(Clone)]
 pub struct AppState {
-    pub kline_manager: Arc<Mutex<KLineManager>>,
+    pub kline_manager: Arc<KLineManager>,
+    // Cached heatmap responses keyed by window in seconds
+    pub heatmap_cache: Arc<Mutex<HashMap<i64, (Instant, HeatmapData)>>>,
+    pub cache: WebCache,
+}
+
+// Candle queries are cached per mint and query parameters
+type KlineCacheKey = (String, i64, Option<i64>, Option<i64>, Option<usize>);
+
+// Short-TTL in-process cache for dashboard reads. Concurrent viewers asking for
+// the same entry share one load instead of each locking the KLineManager.
+#[derive(Clone)]
+pub struct WebCache {
+    enabled: bool,
+    mints: Cache<(), Vec<MintInfo>>,
+    stats: Cache<(), HashMap<String, usize>>,
+    klines: Cache<KlineCacheKey, KLinesResponse>,
+}
+
+impl WebCache {
+    pub fn from_env() -> Self {
+        let ttl_secs: u64 = std::env::var("WEB_CACHE_TTL_SECS")
+            .unwrap_or_else(|_| "2".to_string())
+            .parse()
+            .unwrap_or(2);
+        let kline_entries: u64 = std::env::var("WEB_CACHE_KLINE_ENTRIES")
+            .unwrap_or_else(|_| "256".to_string())
+            .parse()
+            .unwrap_or(256);
+        let ttl = Duration::from_secs(ttl_secs.max(1));
+
+        Self {
+            enabled: ttl_secs > 0,
+            mints: Cache::builder().max_capacity(1).time_to_live(ttl).build(),
+            stats: Cache::builder().max_capacity(1).time_to_live(ttl).build(),
+            klines: Cache::builder()
+                .max_capacity(kline_entries)
+                .time_to_live(ttl)
+                .build(),
+        }
+    }
+
+    // Drop all cached entries, e.g. after candles were deleted
+    pub fn invalidate(&self) {
+        self.mints.invalidate_all();
+        self.stats.invalidate_all();
+        self.klines.invalidate_all();
+    }
+
+    // Return the cached value or run `load`; failed loads are not cached
+    async fn get_or_load<K, V, F>(&self, cache: &Cache<K, V>, key: K, load: F) -> anyhow::Result<V>
+    where
+        K: Hash + Eq + Send + Sync + 'static,
+        V: Clone + Send + Sync + 'static,
+        F: Future<Output = anyhow::Result<V>>,
+    {
+        if !self.enabled {
+            return load.await;
+        }
+        cache
+            .try_get_with(key, load)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))
+    }
 }
 
-#[derive(Serialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub message: Option<String>,
+#[derive(Deserialize)]
+pub struct KlineCsvQuery {
+    pub interval: Option<String>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct MintInfo {
-    pub mint: String,
-    pub last_activity: u64,
-    pub kline_count: usize,
-    pub complete: bool,
+#[derive(Deserialize)]
+pub struct TradesQuery {
+    pub side: Option<String>,
+    pub min_sol: Option<Decimal>,
+    pub max_sol: Option<Decimal>,
+    pub wallet: Option<String>,
+    pub source: Option<String>,
+    pub limit: Option<usize>,
 }
 
 #[derive(Deserialize)]
-pub struct KlineQuery {
+pub struct WalletTradesQuery {
+    // Unix timestamp of the oldest trade to return
+    pub since: Option<i64>,
     pub limit: Option<usize>,
 }
 
-pub async fn create_web_server(kline_manager: Arc<Mutex<KLineManager>>) -> Router {
-    let state = AppState { kline_manager };
+#[derive(Deserialize)]
+pub struct DeleteRangeQuery {
+    pub from: i64,
+    pub to: i64,
+}
 
-    Router::new()
-        .route("/", get(serve_index))
+#[derive(Deserialize)]
+pub struct FeesQuery {
+    pub hours: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateRuleRequest {
+    pub condition: RuleCondition,
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CreatePriceAlertRequest {
+    pub target: PriceAlertTarget,
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct HeatmapQuery {
+    pub window: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TopGainersQuery {
+    pub window: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct FunnelQuery {
+    pub window: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateTokenRequest {
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+}
+
+#[derive(Deserialize)]
+pub struct TokenPageQuery {
+    // Candle interval the chart opens with, as in the klines API (default 1m)
+    pub interval: Option<String>,
+    // Number of candles shown (default 200)
+    pub lookback: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct LeaderboardQuery {
+    pub period: Option<LeaderboardPeriod>,
+    // Parameter sets with fewer evaluated alerts are left out (default 3)
+    pub min_alerts: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct AlertPollQuery {
+    pub since_id: Option<String>,
+    pub timeout: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+// Longest a long-poll request may wait for new alerts
+fn max_alert_poll_secs() -> u64 {
+    std::env::var("ALERT_LONG_POLL_MAX_SECS")
+        .unwrap_or_else(|_| "30".to_string())
+        .parse()
+        .unwrap_or(30)
+}
+
+// Candles read from storage per chunk of a CSV download
+const CSV_CHUNK_SIZE: usize = 500;
+
+const CSV_HEADER: &str = "timestamp,time,open,high,low,close,volume_sol,volume_token,net_flow_sol,source,liquidity_sol,turnover,anomaly_score\n";
+
+// Largest heatmap window allowed (1 day)
+const MAX_HEATMAP_WINDOW_SECS: i64 = 86400;
+
+pub async fn create_web_server(kline_manager: Arc<KLineManager>) -> Router {
+    let state = AppState {
+        kline_manager,
+        heatmap_cache: Arc::new(Mutex::new(HashMap::new())),
+        cache: WebCache::from_env(),
+    };
+
+    // Destructive and configuration endpoints, guarded by token and IP allowlist
+    let admin_config = Arc::new(AdminConfig::from_env());
+    if admin_config.token.is_none() {
+        info!("Admin API disabled, set ADMIN_API_TOKEN to enable /admin routes");
+    }
+
+    // Large candle payloads that change at most once per minute get ETags
+    let cached = Router::new()
         .route("/api/mints", get(get_mints))
         .route("/api/mint/:mint/klines", get(get_klines))
+        .route("/api/v2/mint/:mint/klines", get(get_klines_v2))
+        .route("/api/mint/:mint/klines/usd", get(get_usd_klines))
+        .route_layer(middleware::from_fn(etag_middleware));
+
+    // Read-only endpoints, open unless API_REQUIRE_TOKEN is set
+    let read_routes = Router::new()
+        .merge(cached)
+        .route("/api/mint/:mint/klines.csv", get(get_klines_csv))
+        .route("/api/mint/:mint/klines/stream", get(stream_mint_klines))
+        .route("/api/klines/stream", get(stream_all_klines))
+        .route("/api/mint/:mint/trades", get(get_trades))
+        .route("/api/mint/:mint/launch", get(get_launch))
+        .route("/api/mint/:mint/metadata", get(get_metadata))
+        .route("/api/mint/:mint/indicators", get(get_indicators))
+        .route("/api/mint/:mint/positions", get(get_positions))
+        .route("/api/mint/:mint/rules", get(get_mint_rules))
+        .route("/api/mint/:mint/price-alerts", get(get_price_alerts))
+        .route("/api/pool/:pool/fees", get(get_pool_fees))
+        .route("/api/wallet/:address/pnl", get(get_wallet_pnl))
+        .route("/api/wallet/:address/trades", get(get_wallet_trades))
+        .route("/api/smart-money", get(get_smart_money))
         .route("/api/stats", get(get_stats))
+        .route("/api/strategies/stats", get(get_strategy_stats))
+        .route("/api/strategies/leaderboard", get(get_leaderboard))
+        .route("/api/alerts/poll", get(poll_alerts))
+        .route("/api/heatmap", get(get_heatmap))
+        .route("/api/top-gainers", get(get_top_gainers))
+        .route("/api/analytics/funnel", get(get_funnel))
+        .route("/metrics", get(get_metrics))
+        .route_layer(middleware::from_fn_with_state(
+            admin_config.clone(),
+            admin::require_read,
+        ));
+    let config_routes = Router::new()
+        .route("/api/config", get(get_config).put(update_config))
+        .route("/api/config/:name", delete(delete_config_override))
+        .route_layer(middleware::from_fn_with_state(
+            admin_config.clone(),
+            admin::require_admin,
+        ));
+    let notification_routes = Router::new()
+        .route("/api/notifications/failed", get(get_failed_notifications))
+        .route(
+            "/api/notifications/failed/:id",
+            delete(discard_failed_notification),
+        )
+        .route(
+            "/api/notifications/failed/:id/resend",
+            post(resend_failed_notification),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            admin_config.clone(),
+            admin::require_admin,
+        ));
+    let token_routes = Router::new()
+        .route(
+            "/api/admin/tokens",
+            get(list_api_tokens).post(create_api_token),
+        )
+        .route("/api/admin/tokens/:id", delete(revoke_api_token))
+        .route_layer(middleware::from_fn_with_state(
+            admin_config.clone(),
+            admin::require_admin,
+        ));
+    let spam_routes = Router::new()
+        .route("/api/spam/blacklist", get(get_spam_blacklist))
+        .route("/api/spam/blacklist/:mint", delete(remove_spam_blacklist))
+        .route_layer(middleware::from_fn_with_state(
+            admin_config.clone(),
+            admin::require_admin,
+        ));
+    let admin_routes = Router::new()
+        .route("/mint/:mint/klines", delete(delete_klines_range))
+        .route("/mint/:mint/rules", post(create_mint_rule))
+        .route("/mint/:mint/rules/:id", delete(delete_mint_rule))
+        .route("/mint/:mint/price-alerts", post(create_price_alert))
+        .route("/mint/:mint/price-alerts/:id", delete(delete_price_alert))
+        .route("/notifications/test", post(test_notifications))
+        .route("/monitors", get(get_monitor_states))
+        .route("/monitors/:monitor/pause", post(pause_monitor))
+        .route("/monitors/:monitor/resume", post(resume_monitor))
+        .route_layer(middleware::from_fn_with_state(
+            admin_config,
+            admin::require_admin,
+        ));
+
+    Router::new()
+        .route("/", get(serve_index))
+        .route("/leaderboard", get(serve_leaderboard))
+        .route("/token/:mint", get(serve_token_page))
+        .route("/api/health", get(get_health))
+        .merge(read_routes)
+        .merge(config_routes)
+        .merge(notification_routes)
+        .merge(token_routes)
+        .merge(spam_routes)
+        .nest("/admin", admin_routes)
         .nest_service("/static", ServeDir::new("static"))
+        // One span per request, at info level so it passes the default log filter
+        .layer(TraceLayer::new_for_http().make_span_with(DefaultMakeSpan::new().level(Level::INFO)))
+        .layer(CompressionLayer::new())
         .layer(CorsLayer::permissive())
         .with_state(state)
 }
 
+// Tag successful GET responses with a weak ETag of the body and answer
+// If-None-Match hits with 304, so polling clients skip unchanged candles
+async fn etag_middleware(req: Request, next: Next) -> Response {
+    if req.method() != Method::GET {
+        return next.run(req).await;
+    }
+    let if_none_match = req.headers().get(IF_NONE_MATCH).cloned();
+
+    let response = next.run(req).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to buffer response body for ETag: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let etag = format!("W/\"{:x}\"", Sha256::digest(&bytes));
+
+    let matched = if_none_match
+        .as_ref()
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .any(|tag| tag.trim() == "*" || tag.trim() == etag)
+        });
+    if matched {
+        return (StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response();
+    }
+
+    if let Ok(value) = etag.parse() {
+        parts.headers.insert(ETAG, value);
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
+
 async fn serve_index() -> Html<&'static str> {
     Html(include_str!("../static/index.html"))
 }
 
+async fn serve_leaderboard() -> Html<&'static str> {
+    Html(include_str!("../static/leaderboard.html"))
+}
+
+// The index page with the chart of one mint opened, for links from notifications.
+// The chart settings are validated here and handed to the page's script.
+async fn serve_token_page(
+    Path(mint): Path<String>,
+    Query(params): Query<TokenPageQuery>,
+) -> Response {
+    if !bs58::decode(&mint)
+        .into_vec()
+        .is_ok_and(|key| key.len() == 32)
+    {
+        return (StatusCode::NOT_FOUND, "Unknown mint address").into_response();
+    }
+    let interval = params
+        .interval
+        .filter(|interval| !interval.is_empty())
+        .unwrap_or_else(|| "1m".to_string());
+    if parse_window(&interval).is_none_or(|secs| secs % 60 != 0) {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid interval: {}", interval),
+        )
+            .into_response();
+    }
+    let initial = serde_json::json!({
+        "mint": mint,
+        "interval": interval,
+        "lookback": params.lookback.unwrap_or(200).clamp(1, 5000),
+    });
+    Html(include_str!("../static/index.html").replacen(
+        "const initialChart = null;",
+        &format!("const initialChart = {};", initial),
+        1,
+    ))
+    .into_response()
+}
+
 async fn get_mints(
     State(state): State<AppState>,
 ) -> Result<Json<ApiResponse<Vec<MintInfo>>>, StatusCode> {
-    let manager = state.kline_manager.lock().await;
-
-    match manager.get_active_mints().await {
-        Ok(active_mints) => {
-            let mut mint_infos = Vec::new();
-
-            for (mint, last_activity, complete) in active_mints {
-                // Get K-line count for this mint
-                match manager.get_klines_for_mint(&mint, None).await {
-                    Ok(klines) => {
-                        mint_infos.push(MintInfo {
-                            mint,
-                            last_activity,
-                            kline_count: klines.len(),
-                            complete,
-                        });
-                    }
-                    Err(_) => {
-                        mint_infos.push(MintInfo {
-                            mint,
-                            last_activity,
-                            kline_count: 0,
-                            complete,
-                        });
-                    }
-                }
-            }
+    let loaded = state
+        .cache
+        .get_or_load(
+            &state.cache.mints,
+            (),
+            aggregate_cache::get_or_compute("mints", load_mints(&state)),
+        )
+        .await;
 
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(mint_infos),
-                message: None,
-            }))
-        }
+    match loaded {
+        Ok(mint_infos) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(mint_infos),
+            message: None,
+        })),
         Err(e) => Ok(Json(ApiResponse {
             success: false,
             data: None,
@@ -99,62 +475,1350 @@ async fn get_mints(
     }
 }
 
+async fn load_mints(state: &AppState) -> anyhow::Result<Vec<MintInfo>> {
+    let manager = &state.kline_manager;
+    let active_mints = manager.get_active_mints().await?;
+
+    let mut mint_infos = Vec::new();
+    for (mint, last_activity, complete) in active_mints {
+        // Get K-line count for this mint
+        let kline_count = manager
+            .get_klines_for_mint(&mint, 60, None)
+            .await
+            .map(|klines| klines.len())
+            .unwrap_or(0);
+        mint_infos.push(MintInfo {
+            mint,
+            last_activity,
+            kline_count,
+            complete,
+        });
+    }
+    Ok(mint_infos)
+}
+
+// v1 shape: the candle array only, as returned before annotations and gaps
+// were added. Gap filling still applies, gap markers need v2.
 async fn get_klines(
     Path(mint): Path<String>,
     Query(params): Query<KlineQuery>,
     State(state): State<AppState>,
 ) -> Result<Json<ApiResponse<Vec<KLineData>>>, StatusCode> {
-    let manager = state.kline_manager.lock().await;
+    Ok(Json(klines_v1(fetch_klines(&state, &mint, &params).await)))
+}
+
+// v2 shape: candles with the alert annotations and gaps on them
+async fn get_klines_v2(
+    Path(mint): Path<String>,
+    Query(params): Query<KlineQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<KLinesResponse>>, StatusCode> {
+    Ok(Json(fetch_klines(&state, &mint, &params).await))
+}
+
+fn klines_v1(response: ApiResponse<KLinesResponse>) -> ApiResponse<Vec<KLineData>> {
+    ApiResponse {
+        success: response.success,
+        data: response.data.map(|data| data.klines),
+        message: response.message,
+    }
+}
+
+async fn fetch_klines(
+    state: &AppState,
+    mint: &str,
+    params: &KlineQuery,
+) -> ApiResponse<KLinesResponse> {
+    let (interval_secs, gap_mode) = match parse_kline_params(params) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return ApiResponse {
+                success: false,
+                data: None,
+                message: Some(e.to_string()),
+            };
+        }
+    };
+    let key = (
+        mint.to_string(),
+        interval_secs,
+        params.from,
+        params.to,
+        params.limit,
+    );
+    let loaded = state
+        .cache
+        .get_or_load(
+            &state.cache.klines,
+            key,
+            load_klines(state, mint, interval_secs, params),
+        )
+        .await
+        .map(|response| apply_gap_mode(response, interval_secs, gap_mode));
+
+    match loaded {
+        Ok(response) => ApiResponse {
+            success: true,
+            data: Some(response),
+            message: None,
+        },
+        Err(e) => ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get K-lines: {}", e)),
+        },
+    }
+}
+
+async fn load_klines(
+    state: &AppState,
+    mint: &str,
+    interval_secs: i64,
+    params: &KlineQuery,
+) -> anyhow::Result<KLinesResponse> {
+    let klines = state
+        .kline_manager
+        .get_klines_in_range(mint, interval_secs, params.from, params.to, params.limit)
+        .await?;
+
+    // Alert markers on the returned candles, up to the last minute of the last one
+    let annotations = match (klines.first(), klines.last()) {
+        (Some(first), Some(last)) => {
+            annotations::get_annotations(mint, first.timestamp, last.timestamp + interval_secs - 60)
+                .await
+                .unwrap_or_else(|e| {
+                    warn!("Failed to get annotations for {}: {}", mint, e);
+                    Vec::new()
+                })
+        }
+        _ => Vec::new(),
+    };
+    Ok(KLinesResponse {
+        klines,
+        annotations,
+        gaps: Vec::new(),
+    })
+}
+
+// Candle interval in seconds (1m unless `interval` is set) and gap mode of a request
+fn parse_kline_params(params: &KlineQuery) -> anyhow::Result<(i64, GapMode)> {
+    let interval_secs = match params.interval.as_deref().filter(|i| !i.is_empty()) {
+        Some(interval) => parse_window(interval)
+            .filter(|secs| secs % 60 == 0)
+            .ok_or_else(|| anyhow::anyhow!("Invalid interval: {}", interval))?,
+        None => 60,
+    };
+    let gap_mode = match params.gaps.as_deref().filter(|gaps| !gaps.is_empty()) {
+        Some(gaps) => gaps.parse()?,
+        None => GapMode::None,
+    };
+    Ok((interval_secs, gap_mode))
+}
+
+// List or fill the intervals without trades between the returned candles. The
+// candle cache keeps stored candles only, so this runs on every request.
+fn apply_gap_mode(response: KLinesResponse, interval_secs: i64, mode: GapMode) -> KLinesResponse {
+    match mode {
+        GapMode::None => response,
+        GapMode::Markers => KLinesResponse {
+            gaps: kline::find_gaps(&response.klines, interval_secs),
+            ..response
+        },
+        GapMode::Fill => {
+            let (klines, gaps) = kline::fill_gaps(response.klines, interval_secs);
+            KLinesResponse {
+                klines,
+                gaps,
+                ..response
+            }
+        }
+    }
+}
+
+// Candles converted to USD at the recorded SOL/USD rate of each candle's start;
+// shares the SOL candle cache of get_klines
+async fn get_usd_klines(
+    Path(mint): Path<String>,
+    Query(params): Query<KlineQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<KLinesResponse>>, StatusCode> {
+    let (interval_secs, gap_mode) = match parse_kline_params(&params) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return Ok(Json(ApiResponse {
+                success: false,
+                data: None,
+                message: Some(e.to_string()),
+            }));
+        }
+    };
+    let key = (
+        mint.clone(),
+        interval_secs,
+        params.from,
+        params.to,
+        params.limit,
+    );
+    let loaded = state
+        .cache
+        .get_or_load(
+            &state.cache.klines,
+            key,
+            load_klines(&state, &mint, interval_secs, &params),
+        )
+        .await
+        .map(|response| apply_gap_mode(response, interval_secs, gap_mode));
+    let converted = match loaded {
+        Ok(response) => price_feed::to_usd_candles(response.klines)
+            .await
+            .map(|klines| KLinesResponse {
+                klines,
+                annotations: response.annotations,
+                gaps: response.gaps,
+            }),
+        Err(e) => Err(e),
+    };
 
-    match manager.get_klines_for_mint(&mint, params.limit).await {
-        Ok(klines) => Ok(Json(ApiResponse {
+    match converted {
+        Ok(response) => Ok(Json(ApiResponse {
             success: true,
-            data: Some(klines),
+            data: Some(response),
             message: None,
         })),
         Err(e) => Ok(Json(ApiResponse {
             success: false,
             data: None,
-            message: Some(format!("Failed to get K-lines: {}", e)),
+            message: Some(format!("Failed to get USD K-lines: {}", e)),
         })),
     }
 }
 
-async fn get_stats(
+// Server-sent events of live candle updates for one mint, fed by the candle hub
+async fn stream_mint_klines(
+    Path(mint): Path<String>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    candle_sse(candle_hub::hub().subscribe(&mint))
+}
+
+// Remaining work of a CSV download: candle start times still to read and the
+// aggregator of the interval being written. None aggregator means finished.
+struct CsvDownload {
+    kline_manager: Arc<KLineManager>,
+    mint: String,
+    timestamps: Vec<i64>,
+    aggregator: Option<KLineAggregator>,
+}
+
+fn csv_row(kline: &KLineData) -> String {
+    let time = chrono::DateTime::from_timestamp(kline.timestamp, 0)
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_default();
+    format!(
+        "{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+        kline.timestamp,
+        time,
+        kline.open,
+        kline.high,
+        kline.low,
+        kline.close,
+        kline.volume_sol,
+        kline.volume_token,
+        kline.net_flow_sol,
+        kline.source.map(|s| s.as_str()).unwrap_or_default(),
+        kline.liquidity_sol.as_deref().unwrap_or_default(),
+        kline.turnover.as_deref().unwrap_or_default(),
+        kline
+            .anomaly_score
+            .map(|s| s.to_string())
+            .unwrap_or_default()
+    )
+}
+
+// Read the next chunk of candles and render the intervals it completes
+async fn next_csv_chunk(
+    mut download: CsvDownload,
+) -> anyhow::Result<Option<(String, CsvDownload)>> {
+    let Some(mut aggregator) = download.aggregator.take() else {
+        return Ok(None);
+    };
+    let klines = if !download.timestamps.is_empty() {
+        let chunk: Vec<i64> = download
+            .timestamps
+            .drain(..CSV_CHUNK_SIZE.min(download.timestamps.len()))
+            .collect();
+        download
+            .kline_manager
+            .get_klines_at(&download.mint, &chunk)
+            .await?
+    } else {
+        let rows = aggregator.finish().map(|k| csv_row(&k)).unwrap_or_default();
+        return Ok(Some((rows, download)));
+    };
+
+    let mut rows = String::new();
+    for kline in klines {
+        if let Some(completed) = aggregator.push(kline) {
+            rows.push_str(&csv_row(&completed));
+        }
+    }
+    download.aggregator = Some(aggregator);
+    Ok(Some((rows, download)))
+}
+
+// Candles of a mint as CSV, streamed chunk by chunk from storage so large
+// ranges never have to fit in one response buffer
+async fn get_klines_csv(
+    Path(mint): Path<String>,
+    Query(params): Query<KlineCsvQuery>,
     State(state): State<AppState>,
-) -> Result<Json<ApiResponse<HashMap<String, usize>>>, StatusCode> {
-    let manager = state.kline_manager.lock().await;
+) -> Result<Response, StatusCode> {
+    let interval = params.interval.as_deref().unwrap_or("1m");
+    let interval_secs = parse_window(interval)
+        .filter(|secs| secs % 60 == 0)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    if let (Some(from), Some(to)) = (params.from, params.to)
+        && from > to
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
 
-    match manager.get_stats().await {
-        Ok((mint_count, kline_count)) => {
-            let mut stats = HashMap::new();
-            stats.insert("total_mints".to_string(), mint_count);
-            stats.insert("total_klines".to_string(), kline_count);
+    let kline_manager = Arc::clone(&state.kline_manager);
+    let timestamps = kline_manager
+        .get_kline_timestamps(&mint, params.from, params.to)
+        .await
+        .map_err(|e| {
+            warn!("Failed to read K-line index for {}: {}", mint, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let filename = format!("{}-{}.csv", mint, interval);
+    let download = CsvDownload {
+        kline_manager,
+        mint,
+        timestamps,
+        aggregator: Some(KLineAggregator::new(interval_secs)),
+    };
+    let rows = stream::once(async { Ok::<_, anyhow::Error>(CSV_HEADER.to_string()) })
+        .chain(stream::try_unfold(download, next_csv_chunk));
+
+    Ok((
+        [
+            (CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        Body::from_stream(rows),
+    )
+        .into_response())
+}
+
+// Server-sent events of live candle updates for every mint
+async fn stream_all_klines() -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    candle_sse(candle_hub::hub().subscribe_all())
+}
+
+fn candle_sse(
+    receiver: broadcast::Receiver<CandleUpdate>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(update) => {
+                    let event = Event::default().event("candle").json_data(&update);
+                    return Some((event, receiver));
+                }
+                // A slow client skips the updates it missed; the next one carries the full candle
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Candle stream client lagged, skipped {} updates", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
 
+async fn delete_klines_range(
+    Path(mint): Path<String>,
+    Query(params): Query<DeleteRangeQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<usize>>, StatusCode> {
+    if params.from > params.to {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    match state
+        .kline_manager
+        .delete_klines_in_range(&mint, params.from, params.to)
+        .await
+    {
+        Ok(deleted) => {
+            state.cache.invalidate();
+            if let Err(e) = aggregate_cache::invalidate_all().await {
+                warn!("Failed to invalidate aggregate cache: {}", e);
+            }
             Ok(Json(ApiResponse {
                 success: true,
-                data: Some(stats),
+                data: Some(deleted),
                 message: None,
             }))
         }
         Err(e) => Ok(Json(ApiResponse {
             success: false,
             data: None,
-            message: Some(format!("Failed to get stats: {}", e)),
+            message: Some(format!("Failed to delete K-lines: {}", e)),
         })),
     }
 }
 
-pub async fn start_web_server(
-    kline_manager: Arc<Mutex<KLineManager>>,
-    port: u16,
-) -> anyhow::Result<()> {
-    let app = create_web_server(kline_manager).await;
+async fn get_trades(
+    Path(mint): Path<String>,
+    Query(params): Query<TradesQuery>,
+) -> Result<Json<ApiResponse<Vec<LabeledTrade>>>, StatusCode> {
+    let side = match params.side.as_deref().filter(|side| !side.is_empty()) {
+        Some(side) => match side.parse::<TradeSide>() {
+            Ok(side) => Some(side),
+            Err(e) => {
+                return Ok(Json(ApiResponse {
+                    success: false,
+                    data: None,
+                    message: Some(e.to_string()),
+                }));
+            }
+        },
+        None => None,
+    };
+    let source = match params.source.as_deref().filter(|source| !source.is_empty()) {
+        Some(source) => match source.parse::<TradeSource>() {
+            Ok(source) => Some(source),
+            Err(e) => {
+                return Ok(Json(ApiResponse {
+                    success: false,
+                    data: None,
+                    message: Some(e.to_string()),
+                }));
+            }
+        },
+        None => None,
+    };
+    let filter = TradeFilter {
+        side,
+        min_sol: params.min_sol,
+        max_sol: params.max_sol,
+        wallet: params.wallet.filter(|wallet| !wallet.is_empty()),
+        source,
+    };
 
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
-    info!("Web server starting on http://0.0.0.0:{}", port);
+    let trades = match trades::get_filtered_trades(&mint, &filter, params.limit).await {
+        Ok(trades) => labels::label_trades(trades).await,
+        Err(e) => Err(e),
+    };
+    match trades {
+        Ok(trades) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(trades),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get trades: {}", e)),
+        })),
+    }
+}
+
+async fn get_launch(Path(mint): Path<String>) -> Result<Json<ApiResponse<LaunchInfo>>, StatusCode> {
+    match launch::get_launch(&mint).await {
+        Ok(Some(info)) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(info),
+            message: None,
+        })),
+        Ok(None) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("No launch record for mint {}", mint)),
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get launch info: {}", e)),
+        })),
+    }
+}
 
-    axum::serve(listener, app).await?;
+async fn get_metadata(
+    Path(mint): Path<String>,
+) -> Result<Json<ApiResponse<MintMetadata>>, StatusCode> {
+    match token_metadata::get_mint_metadata(&mint).await {
+        Ok(metadata) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(metadata),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get metadata: {}", e)),
+        })),
+    }
+}
 
-    Ok(())
+async fn get_indicators(
+    Path(mint): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<MintIndicators>>, StatusCode> {
+    match market_beta::get_mint_indicators(&state.kline_manager, &mint).await {
+        Ok(indicators) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(indicators),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get indicators: {}", e)),
+        })),
+    }
+}
+
+async fn get_positions(
+    Path(mint): Path<String>,
+) -> Result<Json<ApiResponse<MintPositions>>, StatusCode> {
+    match positions::get_mint_positions(&mint).await {
+        Ok(mint_positions) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(mint_positions),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get positions: {}", e)),
+        })),
+    }
+}
+
+async fn get_pool_fees(
+    Path(pool): Path<String>,
+    Query(params): Query<FeesQuery>,
+) -> Result<Json<ApiResponse<PoolFees>>, StatusCode> {
+    match fees::get_pool_fees(&pool, params.hours).await {
+        Ok(pool_fees) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(pool_fees),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get pool fees: {}", e)),
+        })),
+    }
+}
+
+async fn get_wallet_pnl(
+    Path(address): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<WalletPnl>>, StatusCode> {
+    match pnl::get_wallet_pnl(&state.kline_manager, &address).await {
+        Ok(wallet_pnl) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(wallet_pnl),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to compute wallet PnL: {}", e)),
+        })),
+    }
+}
+
+async fn get_wallet_trades(
+    Path(address): Path<String>,
+    Query(params): Query<WalletTradesQuery>,
+) -> Result<Json<ApiResponse<Vec<LabeledTrade>>>, StatusCode> {
+    let limit = params.limit.unwrap_or(500).min(5000);
+    let trades = match trades::get_wallet_trades(&address, params.since, limit).await {
+        Ok(trades) => labels::label_trades(trades).await,
+        Err(e) => Err(e),
+    };
+    match trades {
+        Ok(trades) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(trades),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get wallet trades: {}", e)),
+        })),
+    }
+}
+
+async fn get_smart_money() -> Result<Json<ApiResponse<Vec<SmartWallet>>>, StatusCode> {
+    match smart_money::get_smart_money().await {
+        Ok(ranked) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(ranked),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get smart money: {}", e)),
+        })),
+    }
+}
+
+async fn get_mint_rules(
+    Path(mint): Path<String>,
+) -> Result<Json<ApiResponse<Vec<MintRule>>>, StatusCode> {
+    match rules::get_rules(&mint).await {
+        Ok(mint_rules) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(mint_rules),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get rules: {}", e)),
+        })),
+    }
+}
+
+async fn create_mint_rule(
+    Path(mint): Path<String>,
+    Json(request): Json<CreateRuleRequest>,
+) -> Result<Json<ApiResponse<MintRule>>, StatusCode> {
+    match rules::add_rule(&mint, request.condition, request.note).await {
+        Ok(rule) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(rule),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to create rule: {}", e)),
+        })),
+    }
+}
+
+async fn delete_mint_rule(
+    Path((mint, id)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<bool>>, StatusCode> {
+    match rules::remove_rule(&mint, &id).await {
+        Ok(removed) => Ok(Json(ApiResponse {
+            success: removed,
+            data: Some(removed),
+            message: (!removed).then(|| format!("Rule {} not found", id)),
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to delete rule: {}", e)),
+        })),
+    }
+}
+
+async fn get_config() -> Result<Json<ApiResponse<Vec<ConfigSetting>>>, StatusCode> {
+    match remote_config::get_settings().await {
+        Ok(settings) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(settings),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get config: {}", e)),
+        })),
+    }
+}
+
+async fn update_config(
+    Json(settings): Json<HashMap<String, String>>,
+) -> Result<Json<ApiResponse<Vec<ConfigSetting>>>, StatusCode> {
+    if let Err(e) = remote_config::set_overrides(&settings).await {
+        return Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to update config: {}", e)),
+        }));
+    }
+    get_config().await
+}
+
+async fn delete_config_override(
+    Path(name): Path<String>,
+) -> Result<Json<ApiResponse<bool>>, StatusCode> {
+    match remote_config::remove_override(&name).await {
+        Ok(removed) => Ok(Json(ApiResponse {
+            success: removed,
+            data: Some(removed),
+            message: (!removed).then(|| format!("No override set for {}", name)),
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to delete config override: {}", e)),
+        })),
+    }
+}
+
+async fn get_failed_notifications() -> Result<Json<ApiResponse<FailedNotifications>>, StatusCode> {
+    let failed = async { NotificationManager::new()?.get_failed_notifications().await };
+    match failed.await {
+        Ok(failed) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(failed),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get failed notifications: {}", e)),
+        })),
+    }
+}
+
+async fn resend_failed_notification(
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<bool>>, StatusCode> {
+    let resent = async { NotificationManager::new()?.resend_failed(&id).await };
+    match resent.await {
+        Ok(found) => Ok(Json(ApiResponse {
+            success: found,
+            data: Some(found),
+            message: (!found).then(|| format!("Failed notification {} not found", id)),
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to resend notification: {}", e)),
+        })),
+    }
+}
+
+async fn discard_failed_notification(
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<bool>>, StatusCode> {
+    let discarded = async { NotificationManager::new()?.discard_failed(&id).await };
+    match discarded.await {
+        Ok(found) => Ok(Json(ApiResponse {
+            success: found,
+            data: Some(found),
+            message: (!found).then(|| format!("Failed notification {} not found", id)),
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to discard notification: {}", e)),
+        })),
+    }
+}
+
+async fn get_spam_blacklist() -> Result<Json<ApiResponse<Vec<BlacklistEntry>>>, StatusCode> {
+    match spam::get_blacklist().await {
+        Ok(entries) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(entries),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get spam blacklist: {}", e)),
+        })),
+    }
+}
+
+async fn remove_spam_blacklist(
+    Path(mint): Path<String>,
+) -> Result<Json<ApiResponse<bool>>, StatusCode> {
+    match spam::remove_from_blacklist(&mint).await {
+        Ok(found) => Ok(Json(ApiResponse {
+            success: found,
+            data: Some(found),
+            message: (!found).then(|| format!("Mint {} is not blacklisted", mint)),
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to remove mint from blacklist: {}", e)),
+        })),
+    }
+}
+
+async fn list_api_tokens() -> Result<Json<ApiResponse<Vec<ApiToken>>>, StatusCode> {
+    match api_tokens::list_tokens().await {
+        Ok(tokens) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(tokens),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to list API tokens: {}", e)),
+        })),
+    }
+}
+
+// The secret is only returned here; only its hash is stored
+async fn create_api_token(
+    Json(request): Json<CreateTokenRequest>,
+) -> Result<Json<ApiResponse<CreatedToken>>, StatusCode> {
+    match api_tokens::create_token(&request.name, &request.scopes).await {
+        Ok(created) => {
+            info!(
+                "Created API token {} ({}) with scopes {:?}",
+                created.token.id, created.token.name, created.token.scopes
+            );
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(created),
+                message: None,
+            }))
+        }
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to create API token: {}", e)),
+        })),
+    }
+}
+
+async fn revoke_api_token(Path(id): Path<String>) -> Result<Json<ApiResponse<bool>>, StatusCode> {
+    match api_tokens::revoke_token(&id).await {
+        Ok(found) => {
+            if found {
+                info!("Revoked API token {}", id);
+            }
+            Ok(Json(ApiResponse {
+                success: found,
+                data: Some(found),
+                message: (!found).then(|| format!("API token {} not found", id)),
+            }))
+        }
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to revoke API token: {}", e)),
+        })),
+    }
+}
+
+async fn get_price_alerts(
+    Path(mint): Path<String>,
+) -> Result<Json<ApiResponse<Vec<PriceAlert>>>, StatusCode> {
+    match price_alerts::get_price_alerts(&mint).await {
+        Ok(pending) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(pending),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get price alerts: {}", e)),
+        })),
+    }
+}
+
+async fn create_price_alert(
+    Path(mint): Path<String>,
+    Json(request): Json<CreatePriceAlertRequest>,
+) -> Result<Json<ApiResponse<PriceAlert>>, StatusCode> {
+    match price_alerts::add_price_alert(&mint, request.target, request.note).await {
+        Ok(price_alert) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(price_alert),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to create price alert: {}", e)),
+        })),
+    }
+}
+
+async fn delete_price_alert(
+    Path((mint, id)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<bool>>, StatusCode> {
+    match price_alerts::remove_price_alert(&mint, &id).await {
+        Ok(removed) => Ok(Json(ApiResponse {
+            success: removed,
+            data: Some(removed),
+            message: (!removed).then(|| format!("Price alert {} not found", id)),
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to delete price alert: {}", e)),
+        })),
+    }
+}
+
+async fn get_health() -> Result<Json<ApiResponse<HealthReport>>, StatusCode> {
+    match heartbeat::get_health().await {
+        Ok(report) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(report),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get service health: {}", e)),
+        })),
+    }
+}
+
+// Counters of every service in the Prometheus text format, plus store gauges
+async fn get_metrics(State(state): State<AppState>) -> Response {
+    let mut snapshots = match metrics::load_snapshots().await {
+        Ok(snapshots) => snapshots,
+        Err(e) => {
+            warn!("Failed to load published metrics: {}", e);
+            Vec::new()
+        }
+    };
+    snapshots.push(metrics::snapshot("web"));
+
+    let mut gauges = Vec::new();
+    match state.kline_manager.get_stats().await {
+        Ok((mint_count, kline_count)) => {
+            gauges.push((
+                "kmonitor_active_mints",
+                "Mints with stored candles.",
+                mint_count as f64,
+            ));
+            gauges.push((
+                "kmonitor_klines",
+                "Minute candles stored.",
+                kline_count as f64,
+            ));
+        }
+        Err(e) => warn!("Failed to get store stats for metrics: {}", e),
+    }
+
+    (
+        [(CONTENT_TYPE, metrics::CONTENT_TYPE)],
+        metrics::render(&snapshots, &gauges),
+    )
+        .into_response()
+}
+
+async fn get_stats(
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<HashMap<String, usize>>>, StatusCode> {
+    let loaded = state
+        .cache
+        .get_or_load(&state.cache.stats, (), load_stats(&state))
+        .await;
+
+    match loaded {
+        Ok(mut stats) => {
+            // Read live so panics show up without waiting for the cache
+            stats.insert(
+                "process.panics".to_string(),
+                supervisor::panic_count() as usize,
+            );
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(stats),
+                message: None,
+            }))
+        }
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get stats: {}", e)),
+        })),
+    }
+}
+
+async fn load_stats(state: &AppState) -> anyhow::Result<HashMap<String, usize>> {
+    let (mint_count, kline_count) = state.kline_manager.get_stats().await?;
+
+    let mut stats = HashMap::new();
+    stats.insert("total_mints".to_string(), mint_count);
+    stats.insert("total_klines".to_string(), kline_count);
+    match state.kline_manager.get_integrity_stats().await {
+        Ok(integrity) => stats.extend(
+            integrity
+                .into_iter()
+                .map(|(field, count)| (format!("integrity.{}", field), count)),
+        ),
+        Err(e) => warn!("Failed to get integrity stats: {}", e),
+    }
+    match decode_check::get_decode_stats().await {
+        Ok(decode) => stats.extend(
+            decode
+                .into_iter()
+                .map(|(field, count)| (format!("decode.{}", field), count)),
+        ),
+        Err(e) => warn!("Failed to get decode stats: {}", e),
+    }
+    match feed_health::get_feed_stats().await {
+        Ok(feed) => stats.extend(
+            feed.into_iter()
+                .map(|(field, count)| (format!("feed.{}", field), count)),
+        ),
+        Err(e) => warn!("Failed to get feed stats: {}", e),
+    }
+    match supervisor::get_task_stats().await {
+        Ok(tasks) => stats.extend(
+            tasks
+                .into_iter()
+                .map(|(field, count)| (format!("tasks.{}", field), count)),
+        ),
+        Err(e) => warn!("Failed to get task stats: {}", e),
+    }
+    Ok(stats)
+}
+
+async fn get_strategy_stats(
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<Vec<StrategyStats>>>, StatusCode> {
+    match alerts::get_strategy_stats(&state.kline_manager).await {
+        Ok(stats) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(stats),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get strategy stats: {}", e)),
+        })),
+    }
+}
+
+async fn get_leaderboard(
+    State(state): State<AppState>,
+    Query(params): Query<LeaderboardQuery>,
+) -> Result<Json<ApiResponse<Leaderboard>>, StatusCode> {
+    match alerts::get_leaderboard(
+        &state.kline_manager,
+        params.period.unwrap_or_default(),
+        params.min_alerts.unwrap_or(3),
+    )
+    .await
+    {
+        Ok(leaderboard) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(leaderboard),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get strategy leaderboard: {}", e)),
+        })),
+    }
+}
+
+// Long-poll for alerts after a cursor, for clients that can't use WebSocket or SSE
+async fn poll_alerts(
+    Query(params): Query<AlertPollQuery>,
+) -> Result<Json<ApiResponse<AlertPoll>>, StatusCode> {
+    let since_id = params.since_id.filter(|id| !id.is_empty());
+    if let Some(id) = since_id.as_deref()
+        && !alerts::is_valid_cursor(id)
+    {
+        return Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Invalid since_id: {}", id)),
+        }));
+    }
+    let timeout = Duration::from_secs(params.timeout.unwrap_or(25).min(max_alert_poll_secs()));
+    let limit = params.limit.unwrap_or(100).clamp(1, 500);
+
+    match alerts::poll_alerts(since_id.as_deref(), limit, timeout).await {
+        Ok(poll) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(poll),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to poll alerts: {}", e)),
+        })),
+    }
+}
+
+async fn get_heatmap(
+    Query(params): Query<HeatmapQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<HeatmapData>>, StatusCode> {
+    let window = params.window.unwrap_or_else(|| "15m".to_string());
+    let window_secs = match parse_window(&window) {
+        Some(secs) if secs <= MAX_HEATMAP_WINDOW_SECS => secs,
+        _ => {
+            return Ok(Json(ApiResponse {
+                success: false,
+                data: None,
+                message: Some(format!("Invalid window: {}", window)),
+            }));
+        }
+    };
+
+    let cache_ttl = Duration::from_secs(
+        std::env::var("HEATMAP_CACHE_TTL_SECS")
+            .unwrap_or_else(|_| "15".to_string())
+            .parse()
+            .unwrap_or(15),
+    );
+
+    // Hold the cache lock while computing so concurrent viewers share one computation
+    let mut cache = state.heatmap_cache.lock().await;
+    if let Some((_, data)) = cache
+        .get(&window_secs)
+        .filter(|(cached_at, _)| cached_at.elapsed() < cache_ttl)
+    {
+        return Ok(Json(ApiResponse {
+            success: true,
+            data: Some(data.clone()),
+            message: None,
+        }));
+    }
+
+    let computed = aggregate_cache::get_or_compute(
+        &format!("heatmap:{}", window_secs),
+        heatmap::compute_heatmap(&state.kline_manager, &window, window_secs),
+    )
+    .await;
+    match computed {
+        Ok(data) => {
+            cache.insert(window_secs, (Instant::now(), data.clone()));
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(data),
+                message: None,
+            }))
+        }
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to compute heatmap: {}", e)),
+        })),
+    }
+}
+
+// Mints with the largest price change over the window, ranked from the shared
+// heatmap aggregate so it costs no extra scan
+async fn get_top_gainers(
+    Query(params): Query<TopGainersQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<Vec<HeatmapEntry>>>, StatusCode> {
+    let window = params.window.unwrap_or_else(|| "1h".to_string());
+    let window_secs = match parse_window(&window) {
+        Some(secs) if secs <= MAX_HEATMAP_WINDOW_SECS => secs,
+        _ => {
+            return Ok(Json(ApiResponse {
+                success: false,
+                data: None,
+                message: Some(format!("Invalid window: {}", window)),
+            }));
+        }
+    };
+    let limit = params.limit.unwrap_or(20).clamp(1, 200);
+
+    let computed = aggregate_cache::get_or_compute(
+        &format!("heatmap:{}", window_secs),
+        heatmap::compute_heatmap(&state.kline_manager, &window, window_secs),
+    )
+    .await;
+    match computed {
+        Ok(data) => {
+            let mut entries = data.entries;
+            entries.sort_by_key(|entry| std::cmp::Reverse(entry.change_pct));
+            entries.truncate(limit);
+            Ok(Json(ApiResponse {
+                success: true,
+                data: Some(entries),
+                message: None,
+            }))
+        }
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to compute top gainers: {}", e)),
+        })),
+    }
+}
+
+async fn get_funnel(
+    Query(params): Query<FunnelQuery>,
+) -> Result<Json<ApiResponse<FunnelStats>>, StatusCode> {
+    let window = params.window.unwrap_or_else(|| "24h".to_string());
+    let Some(window_secs) = parse_window(&window) else {
+        return Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Invalid window: {}", window)),
+        }));
+    };
+
+    match funnel::get_funnel(window_secs).await {
+        Ok(stats) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(stats),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to compute funnel: {}", e)),
+        })),
+    }
+}
+
+async fn test_notifications() -> Result<Json<ApiResponse<Vec<ChannelTestResult>>>, StatusCode> {
+    match NotificationManager::new() {
+        Ok(notification_manager) => {
+            let results = notification_manager.test_channels().await;
+            let all_ok = results.iter().all(|r| r.success);
+            Ok(Json(ApiResponse {
+                success: all_ok,
+                data: Some(results),
+                message: (!all_ok).then(|| "Some notification channels failed".to_string()),
+            }))
+        }
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to create notification manager: {}", e)),
+        })),
+    }
+}
+
+async fn get_monitor_states() -> Result<Json<ApiResponse<Vec<MonitorState>>>, StatusCode> {
+    match monitor_control::get_states().await {
+        Ok(states) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(states),
+            message: None,
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to get monitor states: {}", e)),
+        })),
+    }
+}
+
+async fn pause_monitor(
+    Path(monitor): Path<String>,
+) -> Result<Json<ApiResponse<MonitorState>>, StatusCode> {
+    set_monitor_paused(&monitor, true).await
+}
+
+async fn resume_monitor(
+    Path(monitor): Path<String>,
+) -> Result<Json<ApiResponse<MonitorState>>, StatusCode> {
+    set_monitor_paused(&monitor, false).await
+}
+
+// Record the monitor's state and signal the running monitor over the control channel
+async fn set_monitor_paused(
+    monitor: &str,
+    paused: bool,
+) -> Result<Json<ApiResponse<MonitorState>>, StatusCode> {
+    let monitor = match monitor_control::parse_monitor(monitor) {
+        Ok(monitor) => monitor,
+        Err(e) => {
+            return Ok(Json(ApiResponse {
+                success: false,
+                data: None,
+                message: Some(e.to_string()),
+            }));
+        }
+    };
+    match monitor_control::set_paused(&monitor, paused).await {
+        Ok(changed) => Ok(Json(ApiResponse {
+            success: true,
+            data: Some(MonitorState { monitor, paused }),
+            message: (!changed).then(|| {
+                format!(
+                    "Monitor was already {}",
+                    if paused { "paused" } else { "running" }
+                )
+            }),
+        })),
+        Err(e) => Ok(Json(ApiResponse {
+            success: false,
+            data: None,
+            message: Some(format!("Failed to update monitor: {}", e)),
+        })),
+    }
+}
+
+pub async fn start_web_server(kline_manager: Arc<KLineManager>, port: u16) -> anyhow::Result<()> {
+    let app = create_web_server(kline_manager).await;
+
+    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
+    info!("Web server starting on http://0.0.0.0:{}", port);
+
+    // Connection info gives the admin guard the client IP
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness;
+
+    fn candle(timestamp: i64) -> KLineData {
+        KLineData {
+            last_update: timestamp as u64,
+            ..test_harness::candle(timestamp, "1")
+        }
+    }
+
+    #[test]
+    fn test_klines_versions() {
+        let response = KLinesResponse {
+            klines: vec![candle(1_700_000_040), candle(1_700_000_160)],
+            annotations: Vec::new(),
+            gaps: Vec::new(),
+        };
+        let response = apply_gap_mode(response, 60, GapMode::Fill);
+        let v2 = ApiResponse {
+            success: true,
+            data: Some(response),
+            message: None,
+        };
+        let value = serde_json::to_value(&v2).unwrap();
+        assert_eq!(value["data"]["klines"].as_array().unwrap().len(), 3);
+        assert_eq!(value["data"]["gaps"].as_array().unwrap().len(), 1);
+
+        // v1 keeps the candle array as data
+        let value = serde_json::to_value(klines_v1(v2)).unwrap();
+        assert_eq!(value["data"].as_array().unwrap().len(), 3);
+        assert_eq!(value["data"][2]["timestamp"], 1_700_000_160);
+
+        let failed = klines_v1(ApiResponse {
+            success: false,
+            data: None,
+            message: Some("error".to_string()),
+        });
+        assert!(failed.data.is_none());
+    }
 }
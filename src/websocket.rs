@@ -1,56 +1,179 @@
 use anyhow::{Context, Result};
 use futures_util::{SinkExt, StreamExt};
-use serde_json::{Value, json};
+use serde_json::Value;
+use std::collections::{HashSet, VecDeque};
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use tokio_tungstenite::{connect_async, tungstenite::Message};
-use tracing::{debug, error, info, warn};
+use tokio::net::TcpStream;
+use tokio::sync::{Mutex, watch};
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream, client_async_tls, connect_async,
+    tungstenite::{Message, client::IntoClientRequest},
+};
+use tracing::{Instrument, debug, error, info, info_span, warn};
 
+use crate::block_feed::{self, IngestionMode};
+use crate::feed_health::FeedMonitor;
+use crate::instance_lock;
 use crate::kline::KLineManager;
+use crate::metrics;
+use crate::monitor_control;
+use crate::proxy::ProxyConfig;
+use crate::supervisor;
+
+// Connect to a websocket endpoint, tunneled through the proxy when one is given.
+// IPv6 endpoints are written with the address in brackets, e.g. wss://[2001:db8::1]:443
+pub async fn connect_websocket(
+    endpoint: &str,
+    proxy: Option<&ProxyConfig>,
+) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+    let Some(proxy) = proxy else {
+        let (ws_stream, _) = connect_async(endpoint)
+            .await
+            .context("Failed to connect to WebSocket server")?;
+        return Ok(ws_stream);
+    };
+
+    let request = endpoint.into_client_request()?;
+    let uri = request.uri();
+    let host = uri
+        .host()
+        .context("WebSocket endpoint has no host")?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+        Some("wss") => 443,
+        _ => 80,
+    });
+    let stream = proxy.connect(&host, port).await?;
+    let (ws_stream, _) = client_async_tls(request, stream)
+        .await
+        .context("Failed to connect to WebSocket server through proxy")?;
+    Ok(ws_stream)
+}
+
+// Signatures already handled by the connections of a redundant monitor. The
+// oldest ones are forgotten once the capacity is reached.
+pub struct SignatureDedup {
+    capacity: usize,
+    seen: std::sync::Mutex<(HashSet<String>, VecDeque<String>)>,
+}
+
+impl SignatureDedup {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            seen: std::sync::Mutex::new((HashSet::new(), VecDeque::new())),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var("WS_DEDUP_CAPACITY")
+                .unwrap_or_else(|_| "100000".to_string())
+                .parse()
+                .unwrap_or(100000),
+        )
+    }
+
+    // True the first time a signature is seen; messages without one always pass
+    pub fn first_seen(&self, signature: &str) -> bool {
+        if signature.is_empty() {
+            return true;
+        }
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        let (set, order) = &mut *seen;
+        if !set.insert(signature.to_string()) {
+            return false;
+        }
+        order.push_back(signature.to_string());
+        while order.len() > self.capacity {
+            if let Some(oldest) = order.pop_front() {
+                set.remove(&oldest);
+            }
+        }
+        true
+    }
+}
 
 pub struct WebSocketMonitor {
     pub endpoint: String,
-    pub kline_manager: Arc<Mutex<KLineManager>>,
+    pub kline_manager: Arc<KLineManager>,
     pub program_addresses: Vec<String>,
     pub monitor_name: String,
+    // Paused state from the control channel, None when the monitor can't be paused
+    pub control: Option<watch::Receiver<bool>>,
+    // Shared with the other connections of a redundant monitor. A monitor of
+    // several programs always has one, as a transaction mentioning more than
+    // one of them arrives once per subscription.
+    pub dedup: Option<Arc<SignatureDedup>>,
+    // Whether this connection runs the idle K-line cleanup; a redundant
+    // monitor runs it on its first connection only
+    pub run_cleanup: bool,
 }
 
 impl WebSocketMonitor {
     pub fn new(
         endpoint: String,
-        kline_manager: Arc<Mutex<KLineManager>>,
+        kline_manager: Arc<KLineManager>,
         program_addresses: Vec<String>,
         monitor_name: String,
     ) -> Self {
+        let dedup = (program_addresses.len() > 1).then(|| Arc::new(SignatureDedup::from_env()));
         Self {
             endpoint,
             kline_manager,
             program_addresses,
             monitor_name,
+            control: None,
+            dedup,
+            run_cleanup: true,
         }
     }
 
+    pub fn with_control(mut self, control: watch::Receiver<bool>) -> Self {
+        self.control = Some(control);
+        self
+    }
+
+    pub fn with_dedup(mut self, dedup: Arc<SignatureDedup>) -> Self {
+        self.dedup = Some(dedup);
+        self
+    }
+
     pub async fn start<F, Fut>(&self, message_handler: F) -> Result<()>
     where
-        F: Fn(&Value, Arc<Mutex<KLineManager>>) -> Fut + Send + Sync + 'static,
+        F: Fn(&Value, Arc<KLineManager>) -> Fut + Send + Sync + 'static,
         Fut: std::future::Future<Output = Result<()>> + Send + 'static,
     {
         let mut reconnect_attempts = 0;
         const MAX_RECONNECT_ATTEMPTS: u32 = 10;
         const INITIAL_RECONNECT_DELAY: u64 = 5; // seconds
+        let mut control = self.control.clone();
 
         loop {
-            match self.connect_internal(&message_handler).await {
+            // Stay disconnected while ingestion is paused
+            if let Some(control) = &mut control
+                && *control.borrow_and_update()
+            {
+                info!("{} ingestion paused, waiting for resume", self.monitor_name);
+                if control.wait_for(|paused| !*paused).await.is_err() {
+                    return Err(anyhow::anyhow!(
+                        "{} control channel closed",
+                        self.monitor_name
+                    ));
+                }
+                info!("{} ingestion resumed", self.monitor_name);
+            }
+
+            match self.connect_internal(&message_handler, &mut control).await {
                 Ok(_) => {
-                    // Reset reconnection counter on successful connection
+                    // The connection was closed for a pause; reset reconnection counter
                     reconnect_attempts = 0;
-                    info!(
-                        "{} WebSocket connection restored successfully",
-                        self.monitor_name
-                    );
                 }
                 Err(e) => {
                     reconnect_attempts += 1;
+                    metrics::record_reconnect();
                     error!(
                         "{} WebSocket connection failed (attempt {}/{}): {}",
                         self.monitor_name, reconnect_attempts, MAX_RECONNECT_ATTEMPTS, e
@@ -74,9 +197,14 @@ impl WebSocketMonitor {
         }
     }
 
-    async fn connect_internal<F, Fut>(&self, message_handler: &F) -> Result<()>
+    // Ok when the connection was closed because ingestion was paused
+    async fn connect_internal<F, Fut>(
+        &self,
+        message_handler: &F,
+        control: &mut Option<watch::Receiver<bool>>,
+    ) -> Result<()>
     where
-        F: Fn(&Value, Arc<Mutex<KLineManager>>) -> Fut + Send + Sync + 'static,
+        F: Fn(&Value, Arc<KLineManager>) -> Fut + Send + Sync + 'static,
         Fut: std::future::Future<Output = Result<()>> + Send + 'static,
     {
         info!(
@@ -84,51 +212,74 @@ impl WebSocketMonitor {
             self.monitor_name, self.endpoint
         );
 
-        let (ws_stream, _) = connect_async(&self.endpoint)
-            .await
-            .context("Failed to connect to WebSocket server")?;
+        let proxy = ProxyConfig::for_monitor(&self.monitor_name)?;
+        if let Some(proxy) = &proxy {
+            info!(
+                "Tunneling {} WebSocket through {:?} proxy {}",
+                self.monitor_name,
+                proxy.kind,
+                proxy.address()
+            );
+        }
+        let ws_stream = connect_websocket(&self.endpoint, proxy.as_ref()).await?;
 
         info!("Connected to {} WebSocket server", self.monitor_name);
+        let mode = IngestionMode::for_monitor(&self.monitor_name);
+        let programs: Vec<&str> = self.program_addresses.iter().map(String::as_str).collect();
+        // Feed gaps are measured against the first program's on-chain activity
+        let program = programs.first().copied().unwrap_or_default();
 
         let (write, mut read) = ws_stream.split();
 
         // Start periodic cleanup task for K-line data
         let kline_manager_clone = Arc::clone(&self.kline_manager);
         let monitor_name = self.monitor_name.clone();
-        let kline_check_task = tokio::spawn(async move {
-            let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
-            loop {
-                interval.tick().await;
-                let manager = kline_manager_clone.lock().await;
-                if let Err(e) = manager.cleanup_idle_klines().await {
-                    error!("{} K-line cleanup failed: {}", monitor_name, e);
+        let kline_check_task = self.run_cleanup.then(|| {
+            supervisor::spawn_supervised("kline_cleanup", async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+                loop {
+                    interval.tick().await;
+                    // The lock holder cleans up for the standby as well
+                    if instance_lock::is_read_only() {
+                        continue;
+                    }
+                    if let Err(e) = kline_manager_clone.cleanup_idle_klines().await {
+                        error!("{} K-line cleanup failed: {}", monitor_name, e);
+                    }
                 }
-            }
+            })
+        });
+
+        // Watch message continuity to tell feed failures from quiet markets
+        let feed = Arc::new(FeedMonitor::new(&self.monitor_name, program));
+        let feed_clone = Arc::clone(&feed);
+        let feed_check_task = supervisor::spawn_supervised("feed_gap_check", async move {
+            feed_clone.run_checker().await;
         });
 
         // Wrap write in Arc<Mutex<>> for sharing between tasks
         let write_arc = Arc::new(Mutex::new(write));
 
-        // Send subscription request
-        let logs_subscribe = json!({
-              "jsonrpc": "2.0",
-              "id": 1,
-              "method": "logsSubscribe",
-              "params": [
-                {
-                  "mentions": self.program_addresses
-                },
-                {
-                  "commitment": "confirmed"
-                }
-              ]
-        });
-
-        let msg = Message::text(logs_subscribe.to_string());
+        // Send one subscription request per program
+        info!(
+            "{} subscribing in {:?} ingestion mode",
+            self.monitor_name, mode
+        );
         {
             let mut writer = write_arc.lock().await;
-            if let Err(e) = writer.send(msg).await {
-                kline_check_task.abort();
+            let mut sent = Ok(());
+            for (id, program) in (1..).zip(&programs) {
+                let subscribe = mode.subscribe_request(program, id);
+                sent = writer.send(Message::text(subscribe.to_string())).await;
+                if sent.is_err() {
+                    break;
+                }
+            }
+            if let Err(e) = sent {
+                if let Some(task) = &kline_check_task {
+                    task.abort();
+                }
+                feed_check_task.abort();
                 return Err(anyhow::anyhow!(
                     "Failed to send {} subscription message: {}",
                     self.monitor_name,
@@ -145,7 +296,7 @@ impl WebSocketMonitor {
         // Start ping task to keep connection alive
         let write_clone = Arc::clone(&write_arc);
         let monitor_name_clone = self.monitor_name.clone();
-        let ping_task = tokio::spawn(async move {
+        let ping_task = supervisor::spawn_supervised("websocket_ping", async move {
             let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
             loop {
                 interval.tick().await;
@@ -158,24 +309,68 @@ impl WebSocketMonitor {
             }
         });
 
-        // Main message processing loop
-        while let Some(message) = read.next().await {
+        // Main message processing loop, left when paused
+        let mut paused = false;
+        loop {
+            let message = tokio::select! {
+                message = read.next() => message,
+                _ = monitor_control::wait_paused(control) => {
+                    paused = true;
+                    break;
+                }
+            };
+            let Some(message) = message else {
+                break;
+            };
             match message {
                 Ok(Message::Text(text)) => {
                     let response: serde_json::Value = serde_json::from_str(&text).unwrap();
 
                     // check if response contains "method" field
                     if let Some(method) = response.get("method") {
-                        if method == "logsNotification" {
-                            debug!("Received {} logsNotification", self.monitor_name);
-                            if let Err(e) =
-                                message_handler(&response, Arc::clone(&self.kline_manager)).await
-                            {
-                                debug!("{} message handling failed: {}", self.monitor_name, e);
+                        if method == mode.notification_method() {
+                            debug!("Received {} {}", self.monitor_name, method);
+                            // A block carries many transactions, each is handled like a logs notification
+                            let messages = match mode {
+                                IngestionMode::Logs => vec![response],
+                                IngestionMode::Block => {
+                                    block_feed::transactions_from_block(&response, &programs)
+                                }
+                            };
+                            for message in messages {
+                                feed.record_message();
+                                let signature = message
+                                    .pointer("/params/result/value/signature")
+                                    .and_then(|s| s.as_str())
+                                    .unwrap_or_default();
+                                // Already handled from another endpoint or subscription
+                                if let Some(dedup) = &self.dedup
+                                    && !dedup.first_seen(signature)
+                                {
+                                    continue;
+                                }
+                                let span = info_span!(
+                                    "ws_message",
+                                    monitor = %self.monitor_name,
+                                    signature = signature
+                                );
+                                if let Err(e) =
+                                    message_handler(&message, Arc::clone(&self.kline_manager))
+                                        .instrument(span)
+                                        .await
+                                {
+                                    debug!("{} message handling failed: {}", self.monitor_name, e);
+                                }
                             }
                         } else {
                             debug!("Received {} method: {}", self.monitor_name, method);
                         }
+                    } else if let Some(error) = response.get("error") {
+                        // e.g. blockSubscribe on a node without --rpc-pubsub-enable-block-subscription
+                        error!(
+                            "{} subscription rejected by the server: {}",
+                            self.monitor_name, error
+                        );
                     } else {
                         debug!(
                             "Received {} subscription response: {:#?}",
@@ -218,12 +413,76 @@ impl WebSocketMonitor {
         }
 
         // Clean up tasks when connection closes
-        kline_check_task.abort();
+        if let Some(task) = &kline_check_task {
+            task.abort();
+        }
+        feed_check_task.abort();
         ping_task.abort();
 
+        if paused {
+            let _ = write_arc.lock().await.close().await;
+            info!(
+                "{} WebSocket connection closed for pause",
+                self.monitor_name
+            );
+            return Ok(());
+        }
         Err(anyhow::anyhow!(
             "{} WebSocket connection closed",
             self.monitor_name
         ))
     }
 }
+
+// Run the same subscription against every endpoint and merge the streams: each
+// transaction is handled once, by whichever connection delivers it first. A
+// provider hiccup then leaves no gap as long as another connection is up.
+// Returns when every connection has given up.
+pub async fn start_redundant<F, Fut>(
+    monitors: Vec<WebSocketMonitor>,
+    message_handler: F,
+) -> Result<()>
+where
+    F: Fn(&Value, Arc<KLineManager>) -> Fut + Clone + Send + Sync + 'static,
+    Fut: std::future::Future<Output = Result<()>> + Send + 'static,
+{
+    let monitors: Vec<WebSocketMonitor> = if monitors.len() > 1 {
+        let dedup = Arc::new(SignatureDedup::from_env());
+        monitors
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut monitor)| {
+                monitor.run_cleanup = i == 0;
+                monitor.with_dedup(Arc::clone(&dedup))
+            })
+            .collect()
+    } else {
+        monitors
+    };
+    let results = futures_util::future::join_all(
+        monitors
+            .iter()
+            .map(|monitor| monitor.start(message_handler.clone())),
+    )
+    .await;
+    results.into_iter().find(Result::is_err).unwrap_or(Ok(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_dedup() {
+        let dedup = SignatureDedup::new(2);
+        assert!(dedup.first_seen("a"));
+        assert!(!dedup.first_seen("a"));
+        assert!(dedup.first_seen("b"));
+        assert!(dedup.first_seen(""));
+        assert!(dedup.first_seen(""));
+        // "a" is forgotten once the capacity is exceeded
+        assert!(dedup.first_seen("c"));
+        assert!(dedup.first_seen("a"));
+        assert!(!dedup.first_seen("c"));
+    }
+}
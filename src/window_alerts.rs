@@ -0,0 +1,227 @@
+use anyhow::Result;
+use rust_decimal::Decimal;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use tracing::info;
+
+use crate::alerts;
+use crate::localized;
+use crate::notification::NotificationManager;
+use crate::notification_format::{Language, NotificationFormat};
+use crate::remote_config;
+use crate::severity;
+use crate::strategy::StrategyAlert;
+use crate::trades::TradeRecord;
+use crate::watchlist;
+
+#[derive(Debug, Clone)]
+pub struct WindowAlertConfig {
+    // Price change (%) within the window that raises an alert, 0 disables the rule
+    pub change_pct: Decimal,
+    // Length of the sliding window in seconds
+    pub window_secs: i64,
+    // Seconds between alerts for the same mint
+    pub cooldown_secs: i64,
+}
+
+impl WindowAlertConfig {
+    pub fn from_env() -> Self {
+        Self {
+            change_pct: remote_config::var("WINDOW_ALERT_PCT")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(Decimal::ZERO),
+            window_secs: remote_config::var("WINDOW_ALERT_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            cooldown_secs: remote_config::var("WINDOW_ALERT_COOLDOWN_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.change_pct > Decimal::ZERO && self.window_secs > 0
+    }
+}
+
+// Price move that crossed the threshold: from the window's low (rise) or high (drop)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowMove {
+    pub from_price: Decimal,
+    pub to_price: Decimal,
+    pub change_pct: Decimal,
+    pub elapsed_secs: i64,
+}
+
+// Trade prices of one mint within the window, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct PriceWindow {
+    prices: VecDeque<(i64, Decimal)>,
+    last_alert: Option<i64>,
+}
+
+impl PriceWindow {
+    // Add a trade and check the move from the lowest and highest price of the
+    // last `window_secs`. After an alert the window restarts at this trade, so
+    // one move alerts once.
+    pub fn observe(
+        &mut self,
+        price: Decimal,
+        timestamp: i64,
+        config: &WindowAlertConfig,
+    ) -> Option<WindowMove> {
+        if price <= Decimal::ZERO {
+            return None;
+        }
+        while self
+            .prices
+            .front()
+            .is_some_and(|(t, _)| *t < timestamp - config.window_secs)
+        {
+            self.prices.pop_front();
+        }
+        self.prices.push_back((timestamp, price));
+
+        let cooling_down = self
+            .last_alert
+            .is_some_and(|last| timestamp - last < config.cooldown_secs);
+        if cooling_down {
+            return None;
+        }
+
+        let hundred = Decimal::ONE_HUNDRED;
+        let (low_ts, low) = *self.prices.iter().min_by_key(|(_, p)| *p)?;
+        let (high_ts, high) = *self.prices.iter().max_by_key(|(_, p)| *p)?;
+        let rise = (price - low) / low * hundred;
+        let drop = (high - price) / high * hundred;
+        let window_move = if rise >= drop {
+            WindowMove {
+                from_price: low,
+                to_price: price,
+                change_pct: rise,
+                elapsed_secs: timestamp - low_ts,
+            }
+        } else {
+            WindowMove {
+                from_price: high,
+                to_price: price,
+                change_pct: -drop,
+                elapsed_secs: timestamp - high_ts,
+            }
+        };
+        if window_move.change_pct.abs() < config.change_pct {
+            return None;
+        }
+
+        self.last_alert = Some(timestamp);
+        self.prices.clear();
+        self.prices.push_back((timestamp, price));
+        Some(window_move)
+    }
+}
+
+static WINDOWS: LazyLock<Mutex<HashMap<String, PriceWindow>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Check a trade of a watchlisted mint against the sliding-window rule and
+// alert on a large move. Windows live in memory, so each monitor process
+// checks the trades it sees.
+pub async fn check_trade(trade: &TradeRecord) -> Result<()> {
+    let config = WindowAlertConfig::from_env();
+    if !config.is_enabled() || !watchlist::is_watched(&trade.mint).await {
+        return Ok(());
+    }
+
+    let window_move = {
+        let mut windows = WINDOWS.lock().unwrap_or_else(|e| e.into_inner());
+        // Mints dropped from the watchlist leave their window behind; keep the map small
+        if windows.len() > 1000 && !windows.contains_key(&trade.mint) {
+            let cutoff = trade.timestamp - config.window_secs.max(config.cooldown_secs);
+            windows.retain(|_, w| {
+                w.prices.back().is_some_and(|(t, _)| *t >= cutoff)
+                    || w.last_alert.is_some_and(|t| t >= cutoff)
+            });
+        }
+        windows.entry(trade.mint.clone()).or_default().observe(
+            trade.price,
+            trade.timestamp,
+            &config,
+        )
+    };
+    let Some(window_move) = window_move else {
+        return Ok(());
+    };
+
+    info!(
+        "⚡ {} moved {:.2}% in {}s ({} -> {})",
+        trade.mint,
+        window_move.change_pct,
+        window_move.elapsed_secs,
+        window_move.from_price,
+        window_move.to_price
+    );
+    let format = NotificationFormat::current().await;
+    let rising = window_move.change_pct > Decimal::ZERO;
+    let mut alert = StrategyAlert {
+        mint: trade.mint.clone(),
+        strategy_name: "价格急变".to_string(),
+        message: localized!(
+            format.language,
+            "{}秒内价格{} {:.2}%: {} -> {}",
+            "Price {1} {2:.2}% in {0}s: {3} -> {4}",
+            window_move.elapsed_secs,
+            match (format.language, rising) {
+                (Language::Zh, true) => "上涨",
+                (Language::Zh, false) => "下跌",
+                (Language::En, true) => "rose",
+                (Language::En, false) => "fell",
+            },
+            window_move.change_pct.abs(),
+            format.price(window_move.from_price),
+            format.price(window_move.to_price)
+        ),
+        timestamp: trade.timestamp,
+        severity: None,
+        params: None,
+        klines: Vec::new(),
+    };
+    severity::score_alert(&mut alert).await;
+    alerts::record_alert(&alert).await?;
+    NotificationManager::new()?
+        .send_one_shot_notification(&alert)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_window() {
+        let config = WindowAlertConfig {
+            change_pct: Decimal::from(10),
+            window_secs: 30,
+            cooldown_secs: 60,
+        };
+        let mut window = PriceWindow::default();
+        assert!(window.observe(Decimal::from(100), 0, &config).is_none());
+        assert!(window.observe(Decimal::from(105), 10, &config).is_none());
+        // 100 -> 110 within 25 seconds
+        let rise = window.observe(Decimal::from(110), 25, &config).unwrap();
+        assert_eq!(rise.change_pct, Decimal::from(10));
+        assert_eq!(rise.elapsed_secs, 25);
+        // Cooling down
+        assert!(window.observe(Decimal::from(80), 40, &config).is_none());
+
+        // The high of 110 left the window, so 100 -> 89 is measured from 100
+        let mut window = PriceWindow::default();
+        window.observe(Decimal::from(110), 0, &config);
+        window.observe(Decimal::from(100), 20, &config);
+        let drop = window.observe(Decimal::from(89), 40, &config).unwrap();
+        assert_eq!(drop.change_pct, Decimal::from(-11));
+        assert_eq!(drop.from_price, Decimal::from(100));
+    }
+}